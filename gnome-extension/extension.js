@@ -24,8 +24,21 @@ const DBUS_IFACE = `
       <arg type="b"/>
       <arg type="b"/>
     </signal>
+    <method name="PauseSync">
+      <arg type="b" direction="in"/>
+    </method>
+    <method name="SendClipboard"/>
+    <method name="ApplyPending">
+      <arg type="b" direction="out"/>
+    </method>
     <method name="ShowWindow"/>
     <method name="Quit"/>
+    <property name="AutoSend" type="b" access="read"/>
+    <property name="AutoReceive" type="b" access="read"/>
+    <property name="Paused" type="b" access="read"/>
+    <property name="PeerCount" type="u" access="read"/>
+    <property name="UnreadCount" type="u" access="read"/>
+    <property name="PendingCount" type="u" access="read"/>
   </interface>
 </node>`;
 
@@ -65,6 +78,10 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
                     this._proxySignalId = this._proxy.connectSignal('StateChanged', (proxy, senderName, [autoSend, autoReceive]) => {
                          this._updateInternalState(autoSend, autoReceive);
                     });
+                    // Peer/unread/pending counts (apps that publish them)
+                    this._propsSignalId = this._proxy.connect('g-properties-changed', () => {
+                         this._updateState();
+                    });
                 }
             }
         );
@@ -100,31 +117,10 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
              }
 
             if (this._proxy) {
-                 const newState = this._toggle.checked;
-                 
-                 if (newState) {
-                     // Enable Both
-                     this._proxy.ToggleAutoSendRemote((res, err) => {
-                          if (!err) {
-                               this._proxy.ToggleAutoReceiveRemote((r, e) => {
-                                   this._updateState();
-                               });
-                          } else {
-                              // console.error('ClusterCut: ToggleAutoSend failed', err);
-                          }
-                     });
-                 } else {
-                     // Disable Both
-                     this._proxy.ToggleAutoSendRemote((res, err) => {
-                          if (!err) {
-                               this._proxy.ToggleAutoReceiveRemote((r, e) => {
-                                   this._updateState();
-                               });
-                          } else {
-                              // console.error('ClusterCut: ToggleAutoSend failed', err);
-                          }
-                     });
-                 }
+                 // Checked = syncing; sets both directions
+                 this._proxy.PauseSyncRemote(!this._toggle.checked, (res, err) => {
+                      this._updateState();
+                 });
             }
         });
 
@@ -135,6 +131,20 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
             Main.panel.closeQuickSettings();
         });
         
+        this._sendItem = this._toggle.menu.addAction('Send Clipboard', () => {
+             if (this._appRunning && this._proxy) this._proxy.SendClipboardRemote();
+             Main.panel.closeQuickSettings();
+        });
+
+        this._pendingItem = this._toggle.menu.addAction('Apply Pending', () => {
+             if (this._appRunning && this._proxy) {
+                 this._proxy.ApplyPendingRemote((result, error) => {
+                      this._updateState();
+                 });
+             }
+        });
+        this._pendingItem.visible = false;
+
         this._autoSendItem = this._toggle.menu.addAction('Enable Auto-Send', () => {
              if (this._appRunning && this._proxy) {
                  this._proxy.ToggleAutoSendRemote((result, error) => {
@@ -191,7 +201,13 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
     _updateInternalState(autoSend, autoReceive) {
         if (!this._toggle) return;
 
-        this._toggle.set({ checked: autoSend && autoReceive });
+        // Null when the app predates the properties
+        const paused = this._proxy ? this._proxy.Paused : null;
+        const peerCount = this._proxy ? this._proxy.PeerCount : null;
+        const unreadCount = this._proxy ? this._proxy.UnreadCount : null;
+        const pendingCount = this._proxy ? this._proxy.PendingCount : null;
+
+        this._toggle.set({ checked: paused !== null ? !paused : autoSend && autoReceive });
         
         // Update Menu Labels
         if (this._autoSendItem && this._autoSendItem.label) {
@@ -200,7 +216,21 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
         if (this._autoReceiveItem && this._autoReceiveItem.label) {
             this._autoReceiveItem.label.text = autoReceive ? 'Disable Auto-Receive' : 'Enable Auto-Receive';
         }
-        
+        if (this._sendItem) {
+            this._sendItem.visible = peerCount !== null;
+        }
+        if (this._pendingItem && this._pendingItem.label) {
+            this._pendingItem.visible = pendingCount > 0;
+            this._pendingItem.label.text = pendingCount > 1 ? `Apply Pending (${pendingCount})` : 'Apply Pending';
+        }
+
+        if (peerCount !== null) {
+            let text = paused ? 'Paused' : peerCount === 1 ? '1 device' : `${peerCount} devices`;
+            if (unreadCount > 0) text += ` · ${unreadCount} new`;
+            this._toggle.subtitle = text;
+            return;
+        }
+
         let text = '';
         if (autoSend && autoReceive) {
             text = 'Auto';
@@ -253,6 +283,10 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
             this._proxy.disconnectSignal(this._proxySignalId);
             this._proxySignalId = null;
         }
+        if (this._propsSignalId && this._proxy) {
+            this._proxy.disconnect(this._propsSignalId);
+            this._propsSignalId = null;
+        }
 
         // Clean up toggle signal if we stored it (we didn't before, but now we should)
         if (this._toggleSignalId && this._toggle) {
@@ -268,6 +302,8 @@ class ClusterCutIndicator extends QuickSettings.SystemIndicator {
         // Disconnect items
         if (this._autoSendItem) this._autoSendItem = null;
         if (this._autoReceiveItem) this._autoReceiveItem = null;
+        if (this._sendItem) this._sendItem = null;
+        if (this._pendingItem) this._pendingItem = null;
 
         this.emit('destroy');
     }
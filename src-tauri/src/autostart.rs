@@ -0,0 +1,140 @@
+//! Start at login inside the Flatpak sandbox.
+//!
+//! Sandboxed apps ask the Background portal (org.freedesktop.portal.Background) to run at
+//! login; the portal writes the host's autostart entry itself, and newer portals ignore one
+//! the app drops there. If no portal answers, the entry is written by hand as before (the
+//! manifest exposes xdg-config/autostart). Either way the entry ends up as
+//! `<host config>/autostart/<app id>.desktop`, which is what the state is read back from.
+//!
+//! Outside Flatpak the autostart plugin handles it (`None` from both functions).
+
+use std::path::PathBuf;
+
+// The portal may show a dialog; stop waiting for the user after this
+#[cfg(target_os = "linux")]
+const RESPONSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+fn flatpak_id() -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    std::env::var("FLATPAK_ID").ok()
+}
+
+// The host's config dir: inside the sandbox XDG_CONFIG_HOME points at ~/.var/app/<id>/config
+fn host_config_dir() -> Option<PathBuf> {
+    std::env::var("HOST_XDG_CONFIG_HOME")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config")))
+}
+
+fn entry_path(id: &str) -> Option<PathBuf> {
+    Some(host_config_dir()?.join("autostart").join(format!("{}.desktop", id)))
+}
+
+/// Whether the app starts at login, or `None` outside Flatpak.
+pub fn state() -> Option<bool> {
+    let id = flatpak_id()?;
+    Some(entry_path(&id).is_some_and(|p| p.exists()))
+}
+
+/// Turn starting at login on or off. Returns whether it is now on (the user or the portal
+/// may refuse), or `None` outside Flatpak.
+pub async fn configure(enable: bool) -> Result<Option<bool>, String> {
+    let Some(id) = flatpak_id() else {
+        return Ok(None);
+    };
+    remove_legacy_entry(&id);
+
+    #[cfg(target_os = "linux")]
+    match portal::request_background(enable).await {
+        Ok(granted) => {
+            tracing::info!("[Autostart] Background portal answered: autostart {}", granted);
+            return Ok(Some(granted));
+        }
+        Err(e) => tracing::warn!("[Autostart] Background portal unavailable ({}), writing the entry directly", e),
+    }
+
+    write_entry(&id, enable)?;
+    Ok(Some(enable))
+}
+
+fn write_entry(id: &str, enable: bool) -> Result<(), String> {
+    let file_path = entry_path(id).ok_or("Could not determine config directory")?;
+    if enable {
+        if let Some(dir) = file_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        // Started minimized: at login the window would only get in the way
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName=ClusterCut\nComment=ClusterCut Clipboard Sync\nExec=flatpak run {} --minimized\nX-Flatpak={}\nTerminal=false\nCategories=Utility;\n",
+            id, id
+        );
+        std::fs::write(&file_path, content).map_err(|e| e.to_string())?;
+    } else if file_path.exists() {
+        std::fs::remove_file(&file_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Earlier versions wrote into the sandbox's own config dir, which nothing reads
+fn remove_legacy_entry(id: &str) {
+    let Some(config) = std::env::var("XDG_CONFIG_HOME").ok().map(PathBuf::from) else {
+        return;
+    };
+    let legacy = config.join("autostart").join(format!("{}.desktop", id));
+    if Some(&legacy) != entry_path(id).as_ref() && legacy.exists() {
+        let _ = std::fs::remove_file(&legacy);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod portal {
+    use super::RESPONSE_TIMEOUT;
+    use futures::StreamExt;
+    use std::collections::HashMap;
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+    const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+    const PATH: &str = "/org/freedesktop/portal/desktop";
+
+    /// Ask to (not) run at login. `Ok(false)` if the user or the portal refused; `Err` if
+    /// there's no portal to ask.
+    pub async fn request_background(autostart: bool) -> zbus::Result<bool> {
+        let conn = zbus::Connection::session().await?;
+        // The portal answers on a Request object derived from our name and this token; listen
+        // there before asking so the answer can't be missed
+        let token = format!("clustercut_{}", rand::random::<u32>());
+        let sender = conn
+            .unique_name()
+            .ok_or_else(|| zbus::Error::Failure("No unique bus name".to_string()))?
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let request_path = format!("{}/request/{}/{}", PATH, sender, token);
+        let request = zbus::Proxy::new(&conn, DESTINATION, request_path.as_str(), "org.freedesktop.portal.Request").await?;
+        let mut responses = request.receive_signal("Response").await?;
+
+        let mut options: HashMap<&str, Value> = HashMap::new();
+        options.insert("handle_token", Value::from(token.as_str()));
+        options.insert("reason", Value::from(crate::i18n::t("autostart.reason")));
+        options.insert("autostart", Value::from(autostart));
+        options.insert("commandline", Value::from(vec!["clustercut", "--minimized"]));
+        options.insert("dbus-activatable", Value::from(false));
+        let background = zbus::Proxy::new(&conn, DESTINATION, PATH, "org.freedesktop.portal.Background").await?;
+        // No parent window: the dialog, if any, isn't attached to ours
+        let _: OwnedObjectPath = background.call("RequestBackground", &("", options)).await?;
+
+        let Ok(Some(message)) = tokio::time::timeout(RESPONSE_TIMEOUT, responses.next()).await else {
+            tracing::warn!("[Autostart] No answer from the Background portal");
+            return Ok(false);
+        };
+        // 0 = granted, 1 = cancelled by the user, 2 = refused
+        let (response, results): (u32, HashMap<String, OwnedValue>) = message.body().deserialize()?;
+        if response != 0 {
+            return Ok(false);
+        }
+        Ok(results.get("autostart").and_then(|v| bool::try_from(v).ok()).unwrap_or(false))
+    }
+}
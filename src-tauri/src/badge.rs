@@ -0,0 +1,137 @@
+//! Drawing the unread badge onto the tray icon (`tray_badge` settings).
+//!
+//! Shapes are rasterized directly into the icon; the count style uses a built-in 3x5 pixel
+//! font so no font files are needed. "mono" paints the badge in the icon's own color, and
+//! high contrast adds an outline in whichever of black/white stands out against the fill.
+
+use crate::storage::{TrayBadgePosition, TrayBadgeSettings, TrayBadgeStyle};
+use image::{Rgba, RgbaImage};
+
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+// 3x5 glyphs, one row per byte, leftmost pixel in bit 2
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const PLUS: [u8; 5] = [0b000, 0b010, 0b111, 0b010, 0b000];
+
+/// "#rrggbb" as a color, `mono` for "mono", red for anything unreadable.
+fn parse_color(color: &str, mono: Rgba<u8>) -> Rgba<u8> {
+    if color.eq_ignore_ascii_case("mono") {
+        return mono;
+    }
+    let hex = color.trim().trim_start_matches('#');
+    match (hex.len(), u32::from_str_radix(hex, 16)) {
+        (6, Ok(rgb)) => Rgba([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255]),
+        _ => RED,
+    }
+}
+
+fn contrast(color: Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, _] = color.0;
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luminance > 140.0 {
+        BLACK
+    } else {
+        WHITE
+    }
+}
+
+fn put(img: &mut RgbaImage, x: i64, y: i64, color: Rgba<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+        img.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+/// Draw the badge for `count` unread items. `mono` is the icon's own foreground color.
+pub fn draw(img: &mut RgbaImage, count: u32, settings: &TrayBadgeSettings, mono: Rgba<u8>) {
+    let w = img.width().min(img.height()) as f32;
+    // The count needs room for its digits
+    let size = match settings.style {
+        TrayBadgeStyle::Square => w * 0.25,
+        TrayBadgeStyle::Dot | TrayBadgeStyle::Ring => w * 0.3,
+        TrayBadgeStyle::Count => w * 0.5,
+    }
+    .max(3.0);
+    let padding = if settings.style == TrayBadgeStyle::Count { 0.0 } else { w * 0.05 };
+    let outline = if settings.high_contrast { (size / 10.0).max(1.0) } else { 0.0 };
+
+    let (left, top) = match settings.position {
+        TrayBadgePosition::TopRight => (img.width() as f32 - size - padding, padding),
+        TrayBadgePosition::TopLeft => (padding, padding),
+        TrayBadgePosition::BottomRight => (img.width() as f32 - size - padding, img.height() as f32 - size - padding),
+        TrayBadgePosition::BottomLeft => (padding, img.height() as f32 - size - padding),
+    };
+    let fill = parse_color(&settings.color, mono);
+    let edge = contrast(fill);
+
+    if settings.style == TrayBadgeStyle::Square {
+        for y in top as i64..(top + size) as i64 {
+            for x in left as i64..(left + size) as i64 {
+                let border = (x as f32) < left + outline
+                    || (y as f32) < top + outline
+                    || (x as f32) >= left + size - outline
+                    || (y as f32) >= top + size - outline;
+                put(img, x, y, if border { edge } else { fill });
+            }
+        }
+        return;
+    }
+
+    let radius = size / 2.0;
+    let (cx, cy) = (left + radius, top + radius);
+    let inner = radius - outline;
+    let ring = (inner / 3.0).max(1.0);
+    for y in top as i64..(top + size).ceil() as i64 {
+        for x in left as i64..(left + size).ceil() as i64 {
+            let d = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+            if d > radius {
+                continue;
+            }
+            if d > inner {
+                put(img, x, y, edge);
+            } else if settings.style != TrayBadgeStyle::Ring || d > inner - ring {
+                put(img, x, y, fill);
+            }
+        }
+    }
+
+    if settings.style == TrayBadgeStyle::Count {
+        draw_count(img, count, cx, cy, inner * 2.0, contrast(fill));
+    }
+}
+
+fn draw_count(img: &mut RgbaImage, count: u32, cx: f32, cy: f32, diameter: f32, color: Rgba<u8>) {
+    let glyphs: Vec<[u8; 5]> = if count > 9 { vec![DIGITS[9], PLUS] } else { vec![DIGITS[count as usize]] };
+    let cols = glyphs.len() as f32 * 4.0 - 1.0;
+    // Fit inside the circle, never below one pixel per font pixel
+    let scale = ((diameter * 0.7 / cols).min(diameter * 0.6 / 5.0)).floor().max(1.0) as i64;
+    let x0 = (cx - cols * scale as f32 / 2.0).round() as i64;
+    let y0 = (cy - 5.0 * scale as f32 / 2.0).round() as i64;
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let gx = x0 + i as i64 * 4 * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        put(img, gx + col * scale + dx, y0 + row as i64 * scale + dy, color);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+// Headless test peer; build with `--features emulator`
+fn main() {
+    clustercut_lib::emulator::main()
+}
@@ -0,0 +1,148 @@
+//! Retention of received files in the download cache (`temp_downloads`).
+//!
+//! `received_files_retention` decides what happens at start and exit: `session` empties the
+//! cache every time (the original behaviour), `days` removes entries older than
+//! `received_files_retention_days`, `size` removes the oldest entries once the cache is over
+//! `received_files_retention_gb`, and `never` leaves it to `clear_received_files`. Resumable
+//! partial transfers (`.part` plus sidecar) are always kept. Large texts we offered last session
+//! are removed regardless, since peers can't fetch them any more.
+
+use crate::storage::AppSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::AppHandle;
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceivedRetention {
+    Session,
+    #[default]
+    Days,
+    Size,
+    Never,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct CleanupReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    // Partial transfers left in place for resume
+    pub kept_partial: usize,
+}
+
+struct Entry {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+fn size_of(path: &Path) -> u64 {
+    if path.is_dir() {
+        std::fs::read_dir(path).into_iter().flatten().flatten().map(|e| size_of(&e.path())).sum()
+    } else {
+        path.metadata().map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+// Top-level cache entries, minus resumable partial transfers (counted in the report)
+fn entries(app: &AppHandle, report: &mut CleanupReport) -> Vec<Entry> {
+    let Some(dir) = crate::storage::temp_downloads_dir(app).filter(|d| d.exists()) else {
+        return Vec::new();
+    };
+    let resumable: HashSet<String> = crate::storage::load_partial_transfers(app).into_iter().map(|t| t.part_path).collect();
+    report.kept_partial = resumable.len();
+
+    let read = match std::fs::read_dir(&dir) {
+        Ok(read) => read,
+        Err(e) => {
+            tracing::error!("Failed to read temp downloads {:?}: {}", dir, e);
+            return Vec::new();
+        }
+    };
+    read.flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let path_str = path.to_string_lossy();
+            let keep = resumable.contains(path_str.as_ref()) || path_str.strip_suffix(".json").is_some_and(|p| resumable.contains(p));
+            if keep {
+                return None;
+            }
+            let modified = entry.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+            Some(Entry { size: size_of(&path), path, modified })
+        })
+        .collect()
+}
+
+fn remove(entry: &Entry, report: &mut CleanupReport) {
+    let result = if entry.path.is_dir() { std::fs::remove_dir_all(&entry.path) } else { std::fs::remove_file(&entry.path) };
+    match result {
+        Ok(()) => {
+            report.removed += 1;
+            report.freed_bytes += entry.size;
+        }
+        Err(e) => tracing::error!("Failed to clear {:?}: {}", entry.path, e),
+    }
+}
+
+/// Remove every received file (manual cleanup).
+pub fn clear_received(app: &AppHandle) -> CleanupReport {
+    let mut report = CleanupReport::default();
+    for entry in entries(app, &mut report) {
+        remove(&entry, &mut report);
+    }
+    tracing::info!("Cleared {} received files ({} bytes)", report.removed, report.freed_bytes);
+    report
+}
+
+/// Apply the retention policy at start or exit.
+pub fn clean(app: &AppHandle, settings: &AppSettings) {
+    let mut report = CleanupReport::default();
+    match settings.received_files_retention {
+        ReceivedRetention::Session => {
+            for entry in entries(app, &mut report) {
+                remove(&entry, &mut report);
+            }
+        }
+        ReceivedRetention::Days => {
+            let max_age = Duration::from_secs(u64::from(settings.received_files_retention_days) * 24 * 3600);
+            let cutoff = SystemTime::now().checked_sub(max_age).unwrap_or(SystemTime::UNIX_EPOCH);
+            for entry in entries(app, &mut report).iter().filter(|e| e.modified < cutoff) {
+                remove(entry, &mut report);
+            }
+        }
+        ReceivedRetention::Size => {
+            let limit = u64::from(settings.received_files_retention_gb) * GB;
+            let mut all = entries(app, &mut report);
+            let mut total: u64 = all.iter().map(|e| e.size).sum();
+            all.sort_by_key(|e| e.modified);
+            for entry in all {
+                if total <= limit {
+                    break;
+                }
+                total = total.saturating_sub(entry.size);
+                remove(&entry, &mut report);
+            }
+        }
+        ReceivedRetention::Never => {}
+    }
+    if report.removed > 0 {
+        tracing::info!("Cleared {} received files ({} bytes, retention: {:?})", report.removed, report.freed_bytes, settings.received_files_retention);
+    }
+    if report.kept_partial > 0 {
+        tracing::info!("Kept {} partial transfers for resume", report.kept_partial);
+    }
+
+    for dir in [crate::storage::outgoing_text_dir(app), crate::storage::outgoing_images_dir(app)]
+        .into_iter()
+        .flatten()
+        .filter(|d| d.exists())
+    {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            tracing::error!("Failed to clear {:?}: {}", dir, e);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Content-type detection for history entries: URL, email address, color, JSON and code
+//! (with a language guess), plus a `sensitive` tag for things that look like credentials.
+//!
+//! Tags are computed locally when an item is committed to history (see `history::commit`)
+//! and never travel over the wire; each device classifies what it stores. Detection is
+//! heuristic and only looks at the first `MAX_CLASSIFY_BYTES` of text.
+
+use serde::{Deserialize, Serialize};
+
+const MAX_CLASSIFY_BYTES: usize = 64 * 1024;
+// Keyword hits needed before a language is guessed
+const MIN_LANGUAGE_SCORE: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    Url,
+    Email,
+    Color,
+    Json,
+    Code,
+    // Private keys and access tokens; tagged alongside the kinds above
+    Sensitive,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ContentTags {
+    pub kinds: Vec<ContentKind>,
+    // Only for code, e.g. "rust"; None when no language stood out
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+// Prefixes of well-known access tokens (GitHub, GitLab, Slack, AWS, Google, OpenAI-style keys)
+const TOKEN_PREFIXES: &[&str] = &["ghp_", "gho_", "ghs_", "github_pat_", "glpat-", "xoxb-", "xoxp-", "AKIA", "AIza", "sk-"];
+// Shortest word that counts as a token (prefix included)
+const MIN_TOKEN_LEN: usize = 20;
+
+// Markers per language; each one found counts once. On a tie the earlier entry wins
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "pub fn ", "-> ", "::", "#[derive", "match ", "&self", "use std"]),
+    ("python", &["def ", "import ", "elif ", "self.", "print(", "from ", "__init__", "None", "lambda "]),
+    ("javascript", &["const ", "function ", "=> ", "console.log", "let ", "require(", "===", "document."]),
+    ("typescript", &["interface ", ": string", ": number", "export ", "=> ", "const ", "readonly ", "<T>"]),
+    ("go", &["func ", "package ", ":= ", "fmt.", "go func", "chan ", "defer "]),
+    ("java", &["public class ", "System.out", "private ", "void ", "@Override", "new ", "import java"]),
+    ("c", &["#include", "int main", "printf(", "malloc(", "->", "NULL", "sizeof("]),
+    ("cpp", &["#include", "std::", "cout", "template<", "namespace ", "nullptr", "::"]),
+    ("shell", &["#!/bin/", "echo ", "$(", "fi\n", "then\n", "export ", "sudo ", "| grep"]),
+    ("sql", &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "UPDATE ", "JOIN ", "CREATE TABLE"]),
+    ("html", &["<!DOCTYPE", "<div", "</", "<html", "<span", "class=\"", "<a href"]),
+    ("css", &["{\n", "color:", "margin:", "padding:", "display:", "px;", "@media"]),
+];
+
+/// Tags for `text`, or None when nothing was detected.
+pub fn classify(text: &str) -> Option<ContentTags> {
+    let text = truncate(text.trim(), MAX_CLASSIFY_BYTES);
+    if text.is_empty() {
+        return None;
+    }
+    let mut tags = ContentTags::default();
+    if is_url(text) {
+        tags.kinds.push(ContentKind::Url);
+    } else if is_email(text) {
+        tags.kinds.push(ContentKind::Email);
+    } else if is_color(text) {
+        tags.kinds.push(ContentKind::Color);
+    } else if is_json(text) {
+        tags.kinds.push(ContentKind::Json);
+    } else if let Some(language) = code_language(text) {
+        tags.kinds.push(ContentKind::Code);
+        tags.language = language;
+    }
+    if is_sensitive(text) {
+        tags.kinds.push(ContentKind::Sensitive);
+    }
+    (!tags.kinds.is_empty()).then_some(tags)
+}
+
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn is_url(text: &str) -> bool {
+    if text.chars().any(char::is_whitespace) {
+        return false;
+    }
+    url::Url::parse(text).is_ok_and(|u| matches!(u.scheme(), "http" | "https" | "ftp" | "ws" | "wss") && u.host_str().is_some())
+}
+
+fn is_email(text: &str) -> bool {
+    let text = text.strip_prefix("mailto:").unwrap_or(text);
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty() && local.chars().all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+    local_ok && domain_ok
+}
+
+// #rgb, #rgba, #rrggbb, #rrggbbaa, or rgb()/rgba()/hsl()/hsla()
+fn is_color(text: &str) -> bool {
+    if let Some(hex) = text.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    let lower = text.to_ascii_lowercase();
+    ["rgb(", "rgba(", "hsl(", "hsla("].iter().any(|prefix| {
+        lower.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(')')).is_some_and(|args| {
+            let parts: Vec<&str> = args.split([',', ' ', '/']).filter(|p| !p.is_empty()).collect();
+            (3..=4).contains(&parts.len())
+                && parts.iter().all(|p| p.trim_end_matches(['%']).trim_end_matches("deg").parse::<f32>().is_ok())
+        })
+    })
+}
+
+fn is_json(text: &str) -> bool {
+    (text.starts_with('{') || text.starts_with('[')) && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+// A PEM private key, a JWT, or a word that starts like a known access token
+fn is_sensitive(text: &str) -> bool {
+    if text.contains("-----BEGIN") && text.contains("PRIVATE KEY-----") {
+        return true;
+    }
+    text.split_whitespace().map(|w| w.trim_matches(|c: char| "\"'`,;=".contains(c))).any(|word| {
+        let token_chars = word.chars().all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+        let is_jwt = word.starts_with("eyJ") && word.split('.').filter(|s| !s.is_empty()).count() == 3;
+        token_chars && word.len() >= MIN_TOKEN_LEN && (is_jwt || TOKEN_PREFIXES.iter().any(|p| word.starts_with(p)))
+    })
+}
+
+/// Some(language guess) if `text` looks like code, None if it reads as prose.
+fn code_language(text: &str) -> Option<Option<String>> {
+    let mut best: Option<(&str, usize)> = None;
+    for (language, markers) in LANGUAGES {
+        let score = markers.iter().filter(|m| text.contains(*m)).count();
+        if score >= MIN_LANGUAGE_SCORE && best.is_none_or(|(_, s)| score > s) {
+            best = Some((language, score));
+        }
+    }
+
+    // Structural signals: lines ending like statements or blocks, indentation
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let structured = lines
+        .iter()
+        .filter(|l| {
+            let t = l.trim_end();
+            t.ends_with(';') || t.ends_with('{') || t.ends_with('}') || t.ends_with(':') || l.starts_with("    ") || l.starts_with('\t')
+        })
+        .count();
+    let looks_like_code = lines.len() >= 2 && structured * 2 >= lines.len();
+
+    match best {
+        Some((language, score)) if looks_like_code || (lines.len() == 1 && score > MIN_LANGUAGE_SCORE) => Some(Some(language.to_string())),
+        None if looks_like_code => Some(None),
+        _ => None,
+    }
+}
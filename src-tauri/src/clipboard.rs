@@ -3,7 +3,7 @@ use crate::protocol::{ClipboardPayload, FileMetadata, Message};
 use crate::state::AppState;
 use crate::transport::Transport;
 use std::{thread, time::Duration};
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Manager};
 use tauri_plugin_clipboard::Clipboard;
 use std::sync::mpsc;
 
@@ -15,6 +15,8 @@ use std::sync::{Arc, Mutex};
 enum ClipboardContent {
     Text(String),
     Files(Vec<String>),
+    // A bitmap, by clipboard sequence number (Windows only, see winclip.rs)
+    Image(u32),
     None,
 }
 
@@ -27,28 +29,28 @@ fn read_clipboard(app: &AppHandle) -> ClipboardContent {
 
     // Priority: Files > Text
     // Note: Check API availability. Assuming `read_files()` exists in CrossCopy plugin.
-    match clip.read_files() {
-        Ok(files) => {
-            if !files.is_empty() {
-                // Sanitize?
-                // CrossCopy on Linux might return file:// URIs.
-                // We should probably normalize to paths if needed, or keep as URIs?
-                // For 'std::fs', we need paths.
-                // Let's assume they are paths or URIs we can parse.
-                // For now, store as is.
-                return ClipboardContent::Files(files);
-            }
+    // An error just means no files; check text
+    if let Ok(files) = clip.read_files() {
+        if !files.is_empty() {
+            // Sanitize?
+            // CrossCopy on Linux might return file:// URIs.
+            // We should probably normalize to paths if needed, or keep as URIs?
+            // For 'std::fs', we need paths.
+            // Let's assume they are paths or URIs we can parse.
+            // For now, store as is.
+            return ClipboardContent::Files(files);
         }
-        Err(_) => {} // Ignore error, check text
     }
 
-    match clip.read_text() {
-        Ok(text) => {
-            if !text.is_empty() {
-                return ClipboardContent::Text(text);
-            }
+    if let Ok(text) = clip.read_text() {
+        if !text.is_empty() {
+            return ClipboardContent::Text(text);
         }
-        Err(_) => {}
+    }
+
+    // After text: Office and spreadsheets put a bitmap next to copied text
+    if let Some(sequence) = crate::winclip::image_sequence() {
+        return ClipboardContent::Image(sequence);
     }
 
     ClipboardContent::None
@@ -84,9 +86,13 @@ pub fn set_clipboard_files(app: &AppHandle, files: Vec<String>) -> Result<(), St
             return Err("No valid paths".to_string());
         }
 
-        app.state::<Clipboard>()
-            .write_files_uris(paths)
-            .map_err(|e| e.to_string())
+        // Written directly as CF_HDROP, like Explorer does (see winclip.rs)
+        crate::winclip::write_files(&paths).or_else(|e| {
+            tracing::warn!("[Clipboard] Native file list failed ({}), using the plugin", e);
+            app.state::<Clipboard>()
+                .write_files_uris(paths)
+                .map_err(|e| e.to_string())
+        })
     }
 
     #[cfg(not(target_os = "windows"))]
@@ -135,11 +141,8 @@ pub fn set_clipboard(app: &AppHandle, text: String) {
             *ignored = ClipboardContent::Text(text_clone.clone());
         }
 
-        if let Err(e) = set_system_clipboard(&app_handle, text_clone) {
-            tracing::error!("Failed to set clipboard text: {}", e);
-        } else {
-            tracing::debug!("Successfully set local clipboard text.");
-        }
+        // Bounded, retried, with a command-line fallback (see clipwrite.rs)
+        crate::clipwrite::write_text(&app_handle, text_clone);
     });
 }
 
@@ -154,14 +157,27 @@ pub fn set_clipboard_paths(app: &AppHandle, paths: Vec<String>) {
             *ignored = ClipboardContent::Files(paths_clone.clone());
         }
 
-        if let Err(e) = set_clipboard_files(&app_handle, paths_clone) {
-            tracing::error!("Failed to set clipboard files: {}", e);
-        } else {
-            tracing::debug!("Successfully set local clipboard files.");
-        }
+        crate::clipwrite::write_files(&app_handle, paths_clone);
     });
 }
 
+/// Put a received bitmap back on the clipboard as an image. Only Windows receives these; the
+/// file is applied like any other elsewhere.
+pub fn set_clipboard_image(app: &AppHandle, path: String) {
+    #[cfg(target_os = "windows")]
+    {
+        let app_handle = app.clone();
+        thread::spawn(move || {
+            // It also goes up as a file (CF_HDROP), which is what the monitor reads back
+            *IGNORED_CONTENT.lock().unwrap() = ClipboardContent::Files(vec![path.clone()]);
+            crate::clipwrite::write_image(&app_handle, path);
+        });
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    set_clipboard_paths(app, vec![path]);
+}
+
 pub fn start_monitor(app_handle: AppHandle, state: AppState, transport: Transport) {
     let app_handle_worker = app_handle.clone();
     
@@ -200,9 +216,13 @@ pub fn start_monitor(app_handle: AppHandle, state: AppState, transport: Transpor
             // If the OS clipboard is locked, the worker will be stuck in 'read_clipboard'
             // and won't send the result in time.
             let current_content = match res_rx.recv_timeout(Duration::from_millis(500)) {
-                Ok(c) => c,
+                Ok(c) => {
+                    crate::clipwrite::record_read(&app_handle, false);
+                    c
+                }
                 Err(mpsc::RecvTimeoutError::Timeout) => {
                     tracing::warn!("Clipboard read timed out (possible deadlock/lock). Skipping cycle.");
+                    crate::clipwrite::record_read(&app_handle, true);
                     // We skip the rest of the loop and try again later.
                     // The worker is still stuck. 
                     // If it unblocks, it will send the result to the channel (buffered).
@@ -237,7 +257,7 @@ pub fn start_monitor(app_handle: AppHandle, state: AppState, transport: Transpor
             {
                 let mut ignored = IGNORED_CONTENT.lock().unwrap();
                 match &*ignored {
-                    ClipboardContent::None => {
+                    ClipboardContent::None | ClipboardContent::Image(_) => {
                         if current_content != last_content
                             && current_content != ClipboardContent::None
                         {
@@ -299,40 +319,23 @@ pub fn start_monitor(app_handle: AppHandle, state: AppState, transport: Transpor
 
             if should_process {
                 last_content = current_content.clone();
+                state.power.touch();
+
+                // Look up the source app right away, while it still has focus
+                let (allowed, source) = check_source(&state);
+                let current_content = if allowed {
+                    current_content
+                } else {
+                    tracing::info!("[Clipboard] Copied outside the allow-listed apps; keeping it local");
+                    ClipboardContent::None
+                };
 
                 // Process Change
                 match current_content {
                     ClipboardContent::Text(text) => {
                         tracing::debug!("Clipboard Text Change Detected (len={})", text.len());
 
-                        // Dedupe Global
-                        {
-                            let mut last_global = state.last_clipboard_content.lock().unwrap();
-                            if *last_global == text {
-                                // Double check?
-                            } else {
-                                *last_global = text.clone();
-                            }
-                        }
-
-                        let hostname = crate::get_hostname_internal();
-                        let msg_id = uuid::Uuid::new_v4().to_string();
-                        let ts = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs();
-
-                        let local_id = state.local_device_id.lock().unwrap().clone();
-                        let payload_obj = ClipboardPayload {
-                            id: msg_id.clone(),
-                            text: text.clone(),
-                            files: None,
-                            timestamp: ts,
-                            sender: hostname,
-                            sender_id: local_id,
-                        };
-
-                        broadcast_clipboard(&app_handle, &state, &transport, payload_obj);
+                        broadcast_text(&app_handle, &state, &transport, text, source.clone());
                     }
                     ClipboardContent::Files(raw_paths) => {
                         tracing::debug!(
@@ -350,59 +353,18 @@ pub fn start_monitor(app_handle: AppHandle, state: AppState, transport: Transpor
                             .as_secs();
 
                         // Process Metadata & Validate Paths
+                        let previews = { state.settings.lock().unwrap().generate_file_previews };
                         let mut file_metas = Vec::new();
                         let mut valid_paths = Vec::new();
 
                         for path_str in &raw_paths {
-                            // Try to parse as URL first (e.g. file://...)
-                            let path_buf = if let Ok(u) = url::Url::parse(path_str) {
-                                if u.scheme() == "file" {
-                                    if let Ok(p) = u.to_file_path() {
-                                        p
-                                    } else {
-                                        // Metadata decoding failed or not a local file
-                                        std::path::PathBuf::from(path_str) // Fallback
-                                    }
-                                } else {
-                                    std::path::PathBuf::from(path_str)
-                                }
-                            } else {
-                                // Not a URI. Check if it's a percent-encoded path string (e.g. Linux path with %20)
-                                let decoded = percent_encoding::percent_decode_str(path_str)
-                                    .decode_utf8_lossy();
-                                std::path::PathBuf::from(decoded.as_ref())
-                            };
-
-                            let path = path_buf.as_path();
-                            if path.exists() {
-                                let name = path
-                                    .file_name()
-                                    .unwrap_or_default()
-                                    .to_string_lossy()
-                                    .to_string();
-                                let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-                                file_metas.push(FileMetadata { name, size });
-                                valid_paths.push(path.to_string_lossy().to_string());
-                            } else {
-                                // tracing::warn!("Path does not exist: {:?}", path);
-                                if path_buf.to_string_lossy() != *path_str {
-                                    let raw_p = std::path::Path::new(path_str);
-                                    if raw_p.exists() {
-                                        let name = raw_p
-                                            .file_name()
-                                            .unwrap_or_default()
-                                            .to_string_lossy()
-                                            .to_string();
-                                        let size =
-                                            std::fs::metadata(raw_p).map(|m| m.len()).unwrap_or(0);
-                                        file_metas.push(FileMetadata { name, size });
-                                        valid_paths.push(path_str.clone());
-                                    } else {
-                                        tracing::warn!("Path does not exist: {:?}", path);
-                                    }
-                                } else {
-                                    tracing::warn!("Path does not exist: {:?}", path);
+                            match local_path(path_str) {
+                                Some(path) => {
+                                    // Hashed below, off this thread
+                                    file_metas.push(describe_file(&path, previews));
+                                    valid_paths.push(path.to_string_lossy().to_string());
                                 }
+                                None => tracing::warn!("Path does not exist: {}", crate::redact::text(path_str)),
                             }
                         }
 
@@ -414,63 +376,629 @@ pub fn start_monitor(app_handle: AppHandle, state: AppState, transport: Transpor
                                 let _ = write!(sig, "{}:{};", f.name, f.size);
                             }
 
-                            // Dedupe Global Check
+                            // Dedupe Global Check (whether we copied or received it last)
                             {
                                 let mut last_global = state.last_clipboard_content.lock().unwrap();
-                                if *last_global == sig {
+                                if last_global.content == sig {
                                     tracing::debug!(
                                         "Ignoring broadcast - files match last_clipboard_content"
                                     );
                                     return; // Abort broadcast
                                 }
-                                *last_global = sig;
+                                *last_global = crate::state::LastClipboard::local(sig);
                             }
 
-                            // Store files mapping for serving requests (Use VALID paths)
-                            {
-                                let mut files_lock = state.local_files.lock().unwrap();
-                                files_lock.insert(msg_id.clone(), valid_paths.clone());
-                            }
+                            let (app_handle, state, transport, source) =
+                                (app_handle.clone(), state.clone(), transport.clone(), source.clone());
+                            tauri::async_runtime::spawn(async move {
+                                hash_in_background(&state, &mut file_metas, &valid_paths).await;
+
+                                // Embed tiny files directly in the payload
+                                let inline_threshold =
+                                    { state.settings.lock().unwrap().inline_file_threshold_kb } * 1024;
+                                embed_inline_files(&mut file_metas, &valid_paths, inline_threshold);
+
+                                // Store files mapping for serving requests (Use VALID paths)
+                                crate::offers::register(&app_handle, &state, msg_id.clone(), valid_paths.clone());
 
+                                let local_id = state.local_device_id.lock().unwrap().clone();
+                                let payload_obj = ClipboardPayload {
+                                    id: msg_id.clone(),
+                                    text: String::new(), // Empty text for files
+                                    files: Some(file_metas),
+                                    timestamp: ts,
+                                    sender: hostname,
+                                    sender_id: local_id,
+                                    source,
+                                    trace: crate::latency::start_trace(&state),
+                                    channel: state.settings.lock().unwrap().send_channel.clone(),
+                                    link: None,
+                                    content: None,
+                                    auth: None,
+                                };
+                                broadcast_clipboard(&app_handle, &state, &transport, payload_obj);
+                            });
+                        } else {
+                            tracing::warn!("No valid files found in clipboard content.");
+                        }
+                    }
+                    ClipboardContent::Image(_) => {
+                        let msg_id = uuid::Uuid::new_v4().to_string();
+                        if let Some(meta) = image_to_file(&app_handle, &state, &msg_id) {
                             let local_id = state.local_device_id.lock().unwrap().clone();
                             let payload_obj = ClipboardPayload {
-                                id: msg_id.clone(),
-                                text: String::new(), // Empty text for files
-                                files: Some(file_metas),
-                                timestamp: ts,
-                                sender: hostname,
+                                id: msg_id,
+                                text: String::new(),
+                                files: Some(vec![meta]),
+                                timestamp: std::time::SystemTime::now()
+                                    .duration_since(std::time::UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs(),
+                                sender: crate::get_hostname_internal(),
                                 sender_id: local_id,
+                                source: source.clone(),
+                                trace: crate::latency::start_trace(&state),
+                                channel: state.settings.lock().unwrap().send_channel.clone(),
+                                link: None,
+                                content: None,
+                                auth: None,
                             };
                             broadcast_clipboard(&app_handle, &state, &transport, payload_obj);
-                        } else {
-                            tracing::warn!("No valid files found in clipboard content.");
                         }
                     }
                     ClipboardContent::None => {}
                 }
             }
 
-            thread::sleep(Duration::from_millis(500));
+            // The interval is re-read while waiting, so activity (or leaving low-power mode)
+            // cuts a slow wait short
+            let started = std::time::Instant::now();
+            loop {
+                let interval = state.power.monitor_interval(&state.settings.lock().unwrap());
+                let elapsed = started.elapsed();
+                if elapsed >= interval || state.is_shutdown() {
+                    break;
+                }
+                thread::sleep((interval - elapsed).min(Duration::from_millis(100)));
+            }
         }
     }); // end spawn
 }
 
+/// New local text, from the monitor or handed over explicitly (`clustercut share`, the macOS
+/// Services menu): build the payload and send it like any copy.
+pub fn broadcast_text(app_handle: &AppHandle, state: &AppState, transport: &Transport, text: String, source: Option<crate::protocol::SourceContext>) {
+    // Dedupe Global
+    *state.last_clipboard_content.lock().unwrap() = crate::state::LastClipboard::local(text.clone());
+
+    let hostname = crate::get_hostname_internal();
+    let msg_id = uuid::Uuid::new_v4().to_string();
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    // Too large to send inline: hand it over as a text file instead
+    let as_file = text_to_file(app_handle, state, &text, &msg_id);
+
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let payload_obj = ClipboardPayload {
+        id: msg_id.clone(),
+        text: if as_file.is_some() { String::new() } else { text.clone() },
+        files: as_file.map(|meta| vec![meta]),
+        timestamp: ts,
+        sender: hostname,
+        sender_id: local_id,
+        source,
+        trace: crate::latency::start_trace(state),
+        channel: state.settings.lock().unwrap().send_channel.clone(),
+        link: None,
+        content: None,
+        auth: None,
+    };
+
+    broadcast_clipboard(app_handle, state, transport, payload_obj);
+    crate::remote::apply_link_rules(state, transport, &text);
+}
+
+const MAX_HASH_SIZE: u64 = 512 * 1024 * 1024;
+// Upper bound on inline bytes per payload (messages are capped at 10MB after JSON/encryption overhead)
+const MAX_INLINE_TOTAL: u64 = 1024 * 1024;
+// Upper bound on the text-to-file threshold (larger inline text would hit the message cap)
+const MAX_TEXT_TO_FILE_THRESHOLD_KB: u64 = 2048;
+// Characters of a converted text file shown to receivers
+const TEXT_PREVIEW_CHARS: usize = 200;
+
+/// Resolve a path as it comes from a clipboard or file picker: a file:// URL, a percent-encoded
+/// path (e.g. Linux paths with %20) or a plain path. None if nothing exists there.
+pub fn local_path(raw: &str) -> Option<std::path::PathBuf> {
+    let decoded = match url::Url::parse(raw) {
+        // Not a local file URL: fall back to the raw string
+        Ok(u) if u.scheme() == "file" => u.to_file_path().unwrap_or_else(|_| std::path::PathBuf::from(raw)),
+        Ok(_) => std::path::PathBuf::from(raw),
+        Err(_) => std::path::PathBuf::from(percent_encoding::percent_decode_str(raw).decode_utf8_lossy().as_ref()),
+    };
+    if decoded.exists() {
+        return Some(decoded);
+    }
+    // A literal '%' in a plain path decodes to something else
+    let raw_path = std::path::PathBuf::from(raw);
+    (raw_path != decoded && raw_path.exists()).then_some(raw_path)
+}
+
+/// Resolve and check paths for sending as files. Returns them as local paths.
+pub fn validate_files(state: &AppState, paths: &[String]) -> Result<Vec<String>, String> {
+    if !state.settings.lock().unwrap().enable_file_transfer {
+        return Err("File transfer is disabled in settings".to_string());
+    }
+    let mut valid_paths = Vec::new();
+    for raw in paths {
+        match local_path(raw) {
+            Some(path) if path.is_file() => valid_paths.push(path.to_string_lossy().to_string()),
+            Some(_) => return Err(format!("{} is not a file", raw)),
+            None => return Err(format!("{} does not exist", raw)),
+        }
+    }
+    if valid_paths.is_empty() {
+        return Err("No files to send".to_string());
+    }
+    Ok(valid_paths)
+}
+
+/// Send files picked in the UI (or passed from the tray/CLI) without going through the
+/// clipboard. Returns the id of the sent item.
+pub fn send_files(
+    app: &AppHandle,
+    state: &AppState,
+    transport: &Transport,
+    paths: &[String],
+    peer_id: Option<&str>,
+) -> Result<String, String> {
+    let valid_paths = validate_files(state, paths)?;
+    let previews = state.settings.lock().unwrap().generate_file_previews;
+    let metas = valid_paths.iter().map(|p| file_metadata(state, std::path::Path::new(p), previews)).collect();
+    let targets = peer_id.map(|p| vec![p.to_string()]);
+    send_file_batch(app, state, transport, valid_paths, metas, targets.as_deref())
+}
+
+/// Broadcast files whose metadata is already built (`metas` in the same order as `paths`) to
+/// `targets`, or every peer when None. Returns the id of the sent item.
+pub fn send_file_batch(
+    app: &AppHandle,
+    state: &AppState,
+    transport: &Transport,
+    paths: Vec<String>,
+    mut metas: Vec<FileMetadata>,
+    targets: Option<&[String]>,
+) -> Result<String, String> {
+    if let Some(targets) = targets {
+        let peers = state.get_peers();
+        if let Some(missing) = targets.iter().find(|t| !peers.contains_key(*t)) {
+            return Err(format!("Peer {} not found or offline", missing));
+        }
+    }
+    if state.is_reset_pending() {
+        return Err("This device was removed from the cluster".to_string());
+    }
+
+    let (inline_threshold, channel) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.inline_file_threshold_kb * 1024, settings.send_channel.clone())
+    };
+    embed_inline_files(&mut metas, &paths, inline_threshold);
+
+    let msg_id = uuid::Uuid::new_v4().to_string();
+    let count = paths.len();
+    crate::offers::register(app, state, msg_id.clone(), paths);
+    let payload = ClipboardPayload {
+        id: msg_id.clone(),
+        text: String::new(),
+        files: Some(metas),
+        timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+        sender: crate::get_hostname_internal(),
+        sender_id: state.local_device_id.lock().unwrap().clone(),
+        source: None,
+        trace: crate::latency::start_trace(state),
+        channel,
+        link: None,
+        content: None,
+        auth: None,
+    };
+    let to = targets.map_or_else(|| "all peers".to_string(), |t| t.join(", "));
+    tracing::info!("[Clipboard] Sending {} file(s) as {} to {}", count, msg_id, to);
+    send_payload_to(app, state, transport, payload, targets);
+    Ok(msg_id)
+}
+
+/// Attach file contents for files at or below `threshold` bytes, within the per-payload budget
+fn embed_inline_files(metas: &mut [FileMetadata], paths: &[String], threshold: u64) {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+    if threshold == 0 {
+        return;
+    }
+    let mut budget = MAX_INLINE_TOTAL;
+    for (meta, path) in metas.iter_mut().zip(paths) {
+        if meta.size > threshold || meta.size > budget {
+            continue;
+        }
+        match std::fs::read(path) {
+            Ok(bytes) if bytes.len() as u64 == meta.size => {
+                budget -= meta.size;
+                meta.inline_data = Some(BASE64.encode(bytes));
+            }
+            Ok(_) => tracing::debug!("File {} changed size while reading, not inlining", crate::redact::text(path)),
+            Err(e) => tracing::warn!("Failed to read {} for inlining: {}", crate::redact::text(path), e),
+        }
+    }
+}
+
+/// Large text: write it to a .txt file and register it for transfer like a copied file.
+/// Returns the file's metadata (with a short text preview), or None to send the text inline.
+fn text_to_file(app: &AppHandle, state: &AppState, text: &str, msg_id: &str) -> Option<FileMetadata> {
+    let (threshold_kb, file_transfer) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.text_to_file_threshold_kb, settings.enable_file_transfer)
+    };
+    if threshold_kb == 0 || !file_transfer || (text.len() as u64) <= threshold_kb.min(MAX_TEXT_TO_FILE_THRESHOLD_KB) * 1024 {
+        return None;
+    }
+    let dir = crate::storage::outgoing_text_dir(app)?;
+    let path = dir.join(format!("clipboard-{}.txt", &msg_id[..8.min(msg_id.len())]));
+    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, text)) {
+        tracing::warn!("Failed to write large text to {:?}, sending inline: {}", path, e);
+        return None;
+    }
+    tracing::info!("[Clipboard] Text is {} KB, sending it as {:?}", text.len() / 1024, path);
+    crate::offers::register(app, state, msg_id.to_string(), vec![path.to_string_lossy().to_string()]);
+
+    let mut meta = file_metadata(state, &path, false);
+    meta.mime = Some("text/plain".to_string());
+    meta.text_preview = Some(text.chars().take(TEXT_PREVIEW_CHARS).collect());
+    Some(meta)
+}
+
+/// A copied bitmap: write it out as PNG and register it for transfer like a copied file.
+/// None when file transfer is off, no peer takes clipboard images, or it was just received.
+fn image_to_file(app: &AppHandle, state: &AppState, msg_id: &str) -> Option<FileMetadata> {
+    let (file_transfer, previews, inline_threshold) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.enable_file_transfer, settings.generate_file_previews, settings.inline_file_threshold_kb * 1024)
+    };
+    if !file_transfer {
+        return None;
+    }
+    if !state.get_peers().values().any(|p| p.capabilities.is_some_and(|c| c.windows_formats)) {
+        tracing::debug!("[Clipboard] Image copied, but no peer takes clipboard images");
+        return None;
+    }
+    let png = crate::winclip::read_image_png()?;
+    let dir = crate::storage::outgoing_images_dir(app)?;
+    let path = dir.join(format!("image-{}.png", &msg_id[..8.min(msg_id.len())]));
+    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, &png)) {
+        tracing::warn!("Failed to write copied image to {:?}: {}", path, e);
+        return None;
+    }
+
+    let mut meta = file_metadata(state, &path, previews);
+    meta.clipboard_image = true;
+    // Same dedupe as copied files
+    {
+        let sig = format!("IMAGE:{}", meta.hash.as_deref().unwrap_or_default());
+        let mut last_global = state.last_clipboard_content.lock().unwrap();
+        if last_global.content == sig {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        *last_global = crate::state::LastClipboard::local(sig);
+    }
+    tracing::info!("[Clipboard] Image copied ({} KB PNG), sending it as {:?}", png.len() / 1024, path);
+    let path = path.to_string_lossy().to_string();
+    embed_inline_files(std::slice::from_mut(&mut meta), std::slice::from_ref(&path), inline_threshold);
+    crate::offers::register(app, state, msg_id.to_string(), vec![path]);
+    Some(meta)
+}
+
+/// Build metadata for a local file, including MIME, content hash and (optionally) a preview
+/// thumbnail. Hashing reads the whole file; on the monitor thread use `describe_file` and
+/// `hash_in_background` instead.
+pub fn file_metadata(state: &AppState, path: &std::path::Path, with_preview: bool) -> FileMetadata {
+    let mut meta = describe_file(path, with_preview);
+    meta.hash = content_hash(state, path);
+    meta
+}
+
+/// SHA-256 of a local file we're offering, reused while its size and mtime are unchanged.
+/// None for files too large to hash (they just skip dedupe).
+pub fn content_hash(state: &AppState, path: &std::path::Path) -> Option<String> {
+    const MAX_CACHED: usize = 256;
+    let stamp = crate::state::FileStamp::of(path).filter(|s| s.size <= MAX_HASH_SIZE)?;
+    let key = path.to_string_lossy().to_string();
+    if let Some((_, hash)) = state.file_hashes.lock().unwrap().get(&key).filter(|(cached, _)| *cached == stamp) {
+        return Some(hash.clone());
+    }
+    let hash = crypto::hash_file(path).ok()?;
+    let mut cache = state.file_hashes.lock().unwrap();
+    if cache.len() >= MAX_CACHED {
+        cache.clear();
+    }
+    cache.insert(key, (stamp, hash.clone()));
+    Some(hash)
+}
+
+/// Fill in the content hashes of `metas` (one per path) on the blocking pool.
+pub async fn hash_in_background(state: &AppState, metas: &mut [FileMetadata], paths: &[String]) {
+    let state = state.clone();
+    let paths = paths.to_vec();
+    let hashes = tauri::async_runtime::spawn_blocking(move || {
+        paths.iter().map(|p| content_hash(&state, std::path::Path::new(p))).collect::<Vec<_>>()
+    })
+    .await;
+    match hashes {
+        Ok(hashes) => {
+            for (meta, hash) in metas.iter_mut().zip(hashes) {
+                meta.hash = hash;
+            }
+        }
+        Err(e) => tracing::warn!("[Clipboard] Hashing files failed: {}", e),
+    }
+}
+
+/// Metadata for a local file without its content hash.
+pub fn describe_file(path: &std::path::Path, with_preview: bool) -> FileMetadata {
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let thumbnail = if with_preview {
+        crate::preview::generate_thumbnail(path, size)
+    } else {
+        None
+    };
+    FileMetadata {
+        name,
+        size,
+        mime: crate::preview::guess_mime(path),
+        thumbnail,
+        hash: None,
+        inline_data: None,
+        text_preview: None,
+        clipboard_image: false,
+    }
+}
+
+/// Whether a new local copy may be broadcast (allow-list mode), and the source context to
+/// attach to it (only when sharing the source app is on).
+pub fn check_source(state: &AppState) -> (bool, Option<crate::protocol::SourceContext>) {
+    let (share, allowlist_mode, allowed_apps) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.share_source_app, settings.app_allowlist_mode, settings.allowed_apps.clone())
+    };
+    if !share && !allowlist_mode {
+        return (true, None);
+    }
+    let source = crate::source::active_window();
+    let allowed = !allowlist_mode || crate::source::is_allowed(&allowed_apps, source.as_ref());
+    (allowed, source.filter(|_| share))
+}
+
+/// Clipboard message bytes for one peer. Peers that accept everything share `member_data`
+/// (sealed once with the cluster key and ChaCha20-Poly1305); guests, peers advertising
+/// narrower capabilities and peers that negotiated AES-256-GCM get their own copy. None =
+/// nothing this peer should receive.
+pub fn data_for_peer(
+    state: &AppState,
+    cluster_key: &[u8; 32],
+    peer: &crate::peer::Peer,
+    payload: &[u8],
+    member_data: &[u8],
+) -> Option<Vec<u8>> {
+    let windows_formats = peer.capabilities.is_some_and(|c| c.windows_formats);
+    let restricted = peer
+        .capabilities
+        .is_some_and(|c| !c.files || !c.images || c.max_payload.is_some())
+        || (!windows_formats && carries_clipboard_image(payload));
+    let preference = state.settings.lock().unwrap().cipher_suite;
+    let suite = crypto::negotiate(preference, peer.capabilities.is_some_and(|c| c.aes_gcm));
+    if peer.guest_expires_at.is_none() && !restricted && suite == crypto::CipherSuite::ChaCha20Poly1305 {
+        return Some(member_data.to_vec());
+    }
+    // Guests can't derive each other's keys
+    if peer.guest_expires_at.is_some() && state.guest_expires_at.lock().unwrap().is_some() {
+        return None;
+    }
+
+    let mut adapted: ClipboardPayload = serde_json::from_slice(payload).ok()?;
+    // File transfers are authenticated with the cluster key, so guests only get text
+    let accepts_files = peer.guest_expires_at.is_none() && peer.capabilities.is_none_or(|c| c.files);
+    let accepts_images = peer.capabilities.is_none_or(|c| c.images);
+    adapted.files = adapted
+        .files
+        .take()
+        .filter(|_| accepts_files)
+        .map(|files| {
+            files
+                .into_iter()
+                .filter(|f| accepts_images || !f.mime.as_deref().is_some_and(|m| m.starts_with("image/")))
+                .filter(|f| windows_formats || !f.clipboard_image)
+                .collect::<Vec<_>>()
+        })
+        .filter(|files| !files.is_empty());
+
+    if let Some(max) = peer.capabilities.and_then(|c| c.max_payload) {
+        if adapted.text.len() as u64 > max {
+            tracing::debug!("Skipping {}: text exceeds its max payload ({} > {})", peer.id, adapted.text.len(), max);
+            return None;
+        }
+    }
+    if adapted.text.is_empty() && adapted.files.is_none() {
+        return None;
+    }
+    // Our own items are re-signed for the files this peer actually gets
+    if adapted.sender_id == *state.local_device_id.lock().unwrap() {
+        crate::senderauth::sign(state, &mut adapted);
+    }
+
+    let key = match peer.guest_expires_at {
+        Some(exp) => crate::guest::derive_key(cluster_key, &peer.id, exp),
+        None => *cluster_key,
+    };
+    let cipher = crypto::encrypt_with(suite, &key, &serde_json::to_vec(&adapted).ok()?).ok()?;
+    serde_json::to_vec(&Message::Clipboard(cipher)).ok()
+}
+
+// Whether a serialized payload includes a copied bitmap, without decoding the rest of it
+fn carries_clipboard_image(payload: &[u8]) -> bool {
+    #[derive(serde::Deserialize)]
+    struct File {
+        #[serde(default)]
+        clipboard_image: bool,
+    }
+    #[derive(serde::Deserialize)]
+    struct Files {
+        #[serde(default)]
+        files: Option<Vec<File>>,
+    }
+    serde_json::from_slice::<Files>(payload).is_ok_and(|p| p.files.is_some_and(|f| f.iter().any(|f| f.clipboard_image)))
+}
+
 fn broadcast_clipboard(
     app_handle: &AppHandle,
     state: &AppState,
     transport: &Transport,
     payload_obj: ClipboardPayload,
 ) {
+    // Removed from the cluster (reset pending): don't send anything
+    if state.is_reset_pending() {
+        tracing::debug!("Reset pending. Not broadcasting clipboard.");
+        return;
+    }
+
     // Check Auto-Send
-    let auto_send = { state.settings.lock().unwrap().auto_send };
-    if !auto_send {
-        tracing::debug!("Auto-send disabled. Emitting monitor update only.");
-        let _ = app_handle.emit("clipboard-monitor-update", &payload_obj);
+    let (auto_send, review) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.auto_send, settings.review_before_send)
+    };
+    if !auto_send || crate::screenlock::sync_paused(state) {
+        tracing::debug!("Auto-send disabled or screen locked. Emitting monitor update only.");
+        crate::events::emit(app_handle, crate::events::ClipboardMonitorUpdate(&payload_obj));
         return;
     }
+    if review {
+        stage_outgoing(app_handle, state, payload_obj);
+        return;
+    }
+
+    send_payload(app_handle, state, transport, payload_obj, None);
+}
+
+/// Review before send: hold new local content until the user confirms (or discards) it.
+/// Newer copies replace whatever was waiting.
+fn stage_outgoing(app_handle: &AppHandle, state: &AppState, payload_obj: ClipboardPayload) {
+    tracing::info!("[Clipboard] Holding {} for review before sending", payload_obj.id);
+    *state.pending_outgoing.lock().unwrap() = Some(payload_obj.clone());
+    crate::events::emit(app_handle, crate::events::ClipboardOutgoingPending(&payload_obj));
+    crate::tray::update_tray_menu(app_handle);
+    crate::send_notification(
+        app_handle,
+        &crate::i18n::t("notify.review.title"),
+        &crate::i18n::t("notify.review.body"),
+        false,
+        Some(2),
+        "history",
+        crate::NotificationPayload::None,
+    );
+}
+
+// Incoming items held at once; the oldest is dropped beyond this
+const MAX_PENDING_INCOMING: usize = 20;
+
+/// Hold incoming content until the user applies it ("clipboard-pending"). Items from several
+/// senders wait side by side instead of replacing each other.
+pub fn hold_incoming(app_handle: &AppHandle, state: &AppState, payload: ClipboardPayload) {
+    let dropped = {
+        let mut pending = state.pending_clipboard.lock().unwrap();
+        pending.shift_remove(&payload.id);
+        pending.insert(payload.id.clone(), payload.clone());
+        if pending.len() > MAX_PENDING_INCOMING {
+            pending.shift_remove_index(0).map(|(id, _)| id)
+        } else {
+            None
+        }
+    };
+    if let Some(id) = dropped {
+        crate::events::emit(app_handle, crate::events::ClipboardPendingRemoved { id });
+    }
+    crate::events::emit(app_handle, crate::events::ClipboardPending(&payload));
+}
+
+/// Apply the pending item `id` (the most recent one if None) to the system clipboard.
+pub fn apply_pending(app_handle: &AppHandle, state: &AppState, id: Option<&str>) -> Option<ClipboardPayload> {
+    let payload = {
+        let mut pending = state.pending_clipboard.lock().unwrap();
+        match id {
+            Some(id) => pending.shift_remove(id),
+            None => pending.pop().map(|(_, p)| p),
+        }
+    }?;
+    tracing::info!("Applying pending clipboard from {}", payload.sender);
+    set_clipboard(app_handle, payload.text.clone());
+    crate::events::emit(app_handle, crate::events::ClipboardPendingRemoved { id: payload.id.clone() });
+    // So history updates
+    crate::history::commit(app_handle, &payload);
+    Some(payload)
+}
+
+pub fn discard_pending(app_handle: &AppHandle, state: &AppState, id: &str) -> bool {
+    if state.pending_clipboard.lock().unwrap().shift_remove(id).is_none() {
+        return false;
+    }
+    crate::events::emit(app_handle, crate::events::ClipboardPendingRemoved { id: id.to_string() });
+    true
+}
 
+/// Send the content held for review. Returns false if nothing was waiting.
+pub fn confirm_outgoing(app_handle: &AppHandle, state: &AppState, transport: &Transport) -> bool {
+    let Some(payload_obj) = state.pending_outgoing.lock().unwrap().take() else {
+        return false;
+    };
+    crate::events::emit(app_handle, crate::events::ClipboardOutgoingCleared { id: payload_obj.id.clone() });
+    crate::tray::update_tray_menu(app_handle);
+    if state.is_reset_pending() {
+        tracing::debug!("Reset pending. Dropping reviewed clipboard.");
+        return true;
+    }
+    tracing::info!("[Clipboard] Sending reviewed content {}", payload_obj.id);
+    send_payload(app_handle, state, transport, payload_obj, None);
+    true
+}
+
+/// Drop the content held for review without sending it. Returns false if nothing was waiting.
+pub fn discard_outgoing(app_handle: &AppHandle, state: &AppState) -> bool {
+    let Some(payload_obj) = state.pending_outgoing.lock().unwrap().take() else {
+        return false;
+    };
+    tracing::info!("[Clipboard] Discarded {} without sending", payload_obj.id);
+    state.local_files.lock().unwrap().remove(&payload_obj.id);
+    crate::events::emit(app_handle, crate::events::ClipboardOutgoingCleared { id: payload_obj.id.clone() });
+    crate::tray::update_tray_menu(app_handle);
+    true
+}
+
+/// Commit a payload to history and send it to every peer (or only to `target`).
+pub fn send_payload(app_handle: &AppHandle, state: &AppState, transport: &Transport, payload_obj: ClipboardPayload, target: Option<&str>) {
+    let targets = target.map(|t| vec![t.to_string()]);
+    send_payload_to(app_handle, state, transport, payload_obj, targets.as_deref());
+}
+
+/// Like `send_payload`, to several peers (every peer when None).
+pub fn send_payload_to(app_handle: &AppHandle, state: &AppState, transport: &Transport, mut payload_obj: ClipboardPayload, targets: Option<&[String]>) {
+    crate::senderauth::sign(state, &mut payload_obj);
     // Emit Local Event (Committed to History)
-    let _ = app_handle.emit("clipboard-change", &payload_obj);
+    crate::history::commit(app_handle, &payload_obj);
+    crate::unfurl::after_send(app_handle, &payload_obj);
 
     // Encrypt
     let payload_bytes = match serde_json::to_vec(&payload_obj) {
@@ -502,14 +1030,14 @@ fn broadcast_clipboard(
                         let notifications = state.settings.lock().unwrap().notifications.clone();
                         if notifications.data_sent {
                             let body = if payload_obj.files.is_some() {
-                                "File info broadcasted to cluster."
+                                crate::i18n::t("notify.clipboard_sent.files_body")
                             } else {
-                                "Clipboard content broadcasted to cluster."
+                                crate::i18n::t("notify.clipboard_sent.text_body")
                             };
                             crate::send_notification(
                                 app_handle,
-                                "Clipboard Sent",
-                                body,
+                                &crate::i18n::t("notify.clipboard_sent.title"),
+                                &body,
                                 false,
                                 Some(2),
                                 "history",
@@ -518,17 +1046,20 @@ fn broadcast_clipboard(
                         }
                     }
 
-                    for peer in peers.values() {
-                        let addr = std::net::SocketAddr::new(peer.ip, peer.port);
-                        let transport_clone = transport.clone();
-                        let data_vec = data.clone();
-                        tauri::async_runtime::spawn(async move {
-                            if let Err(e) = transport_clone.send_message(addr, &data_vec).await {
-                                tracing::error!("Failed to send to {}: {}", addr, e);
-                            } else {
-                                tracing::info!("Sent clipboard to {}", addr);
-                            }
-                        });
+                    for peer in peers.values().filter(|p| targets.is_none_or(|t| t.contains(&p.id))) {
+                        let Some(data_vec) =
+                            data_for_peer(state, &key_arr, peer, &payload_bytes, &data)
+                        else {
+                            continue;
+                        };
+                        tauri::async_runtime::spawn(crate::retry::send(
+                            app_handle.clone(),
+                            state.clone(),
+                            transport.clone(),
+                            payload_obj.id.clone(),
+                            peer.id.clone(),
+                            data_vec,
+                        ));
                     }
                 }
                 Err(e) => tracing::error!("Encryption failed: {}", e),
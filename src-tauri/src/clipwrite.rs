@@ -0,0 +1,237 @@
+//! Bounded clipboard writes and a watchdog for the clipboard backend.
+//!
+//! The clipboard plugin can hang indefinitely (most often on Wayland without a focused
+//! window). Each write runs on its own thread and is abandoned after `WRITE_TIMEOUT`;
+//! failed or timed-out text writes are retried, then handed to the platform's command-line
+//! tool (wl-copy/xclip/xsel, pbcopy, PowerShell). While an abandoned write is still stuck,
+//! new writes skip the plugin entirely. Health is exposed via `get_clipboard_health` and
+//! "clipboard-health" events; writes that fail for good are reported on "app-error".
+
+use crate::error::{report, AppError};
+use once_cell::sync::Lazy;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+const WRITE_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+// Consecutive monitor read timeouts (500ms each) before the backend counts as unhealthy
+const READ_TIMEOUT_THRESHOLD: u32 = 10;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ClipboardHealth {
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub read_timeouts: u32,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<u64>,
+    // Last successful write went through the command-line fallback
+    pub using_fallback: bool,
+}
+
+static HEALTH: Lazy<Mutex<ClipboardHealth>> = Lazy::new(|| {
+    Mutex::new(ClipboardHealth {
+        healthy: true,
+        consecutive_failures: 0,
+        read_timeouts: 0,
+        last_error: None,
+        last_success_at: None,
+        using_fallback: false,
+    })
+});
+// A plugin write that timed out and hasn't returned yet
+static PLUGIN_STUCK: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
+
+pub fn health() -> ClipboardHealth {
+    HEALTH.lock().unwrap().clone()
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn update(app: &AppHandle, f: impl FnOnce(&mut ClipboardHealth)) {
+    let (changed, snapshot) = {
+        let mut health = HEALTH.lock().unwrap();
+        let was_healthy = health.healthy;
+        f(&mut health);
+        health.healthy = health.consecutive_failures == 0 && health.read_timeouts < READ_TIMEOUT_THRESHOLD;
+        (was_healthy != health.healthy, health.clone())
+    };
+    if changed {
+        tracing::info!("[Clipboard] Backend {}", if snapshot.healthy { "recovered" } else { "unhealthy" });
+        crate::events::emit(app, &snapshot);
+    }
+}
+
+/// Called by the monitor for every read: `timed_out` when the plugin didn't answer in time.
+pub fn record_read(app: &AppHandle, timed_out: bool) {
+    let crossed = {
+        let health = HEALTH.lock().unwrap();
+        timed_out && health.read_timeouts + 1 == READ_TIMEOUT_THRESHOLD
+    };
+    update(app, |h| h.read_timeouts = if timed_out { h.read_timeouts + 1 } else { 0 });
+    if crossed {
+        let error = AppError::Clipboard("The system clipboard is not responding to reads".to_string());
+        report(app, "clipboard-read", &error);
+    }
+}
+
+/// Run `write` on its own thread, giving up after WRITE_TIMEOUT.
+fn bounded(write: impl FnOnce() -> Result<(), String> + Send + 'static) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let stuck = PLUGIN_STUCK.clone();
+    thread::spawn(move || {
+        let result = write();
+        // Unblocks later writes if this one had been given up on
+        stuck.store(false, Ordering::Relaxed);
+        let _ = tx.send(result);
+    });
+    match rx.recv_timeout(WRITE_TIMEOUT) {
+        Ok(result) => result,
+        Err(_) => {
+            PLUGIN_STUCK.store(true, Ordering::Relaxed);
+            Err(format!("Timed out after {}s", WRITE_TIMEOUT.as_secs()))
+        }
+    }
+}
+
+fn with_retries(label: &str, write: impl Fn() -> Result<(), String> + Send + Sync + Clone + 'static) -> Result<(), String> {
+    let mut last_error = String::new();
+    for attempt in 1..=WRITE_ATTEMPTS {
+        if PLUGIN_STUCK.load(Ordering::Relaxed) {
+            return Err("Clipboard plugin is stuck on an earlier write".to_string());
+        }
+        match bounded(write.clone()) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                tracing::warn!("[Clipboard] Writing {} failed (attempt {}/{}): {}", label, attempt, WRITE_ATTEMPTS, e);
+                last_error = e;
+            }
+        }
+        thread::sleep(RETRY_DELAY);
+    }
+    Err(last_error)
+}
+
+fn succeeded(app: &AppHandle, fallback: bool) {
+    update(app, |h| {
+        h.consecutive_failures = 0;
+        h.last_error = None;
+        h.last_success_at = Some(now());
+        h.using_fallback = fallback;
+    });
+}
+
+fn failed(app: &AppHandle, message: String) {
+    update(app, |h| {
+        h.consecutive_failures += 1;
+        h.last_error = Some(message.clone());
+    });
+    report(app, "clipboard-write", &AppError::Clipboard(message));
+}
+
+/// Write text, with retries and the command-line fallback. Blocks; call off the main thread.
+pub fn write_text(app: &AppHandle, text: String) {
+    let handle = app.clone();
+    let plugin_text = text.clone();
+    let result = with_retries("text", move || crate::clipboard::set_system_clipboard(&handle, plugin_text.clone()));
+    match result {
+        Ok(()) => succeeded(app, false),
+        Err(e) => match fallback_text(&text) {
+            Ok(tool) => {
+                tracing::info!("[Clipboard] Plugin failed ({}); wrote text with {}", e, tool);
+                succeeded(app, true);
+            }
+            Err(fallback_error) => failed(app, format!("Failed to set clipboard text: {} (fallback: {})", e, fallback_error)),
+        },
+    }
+}
+
+/// Write file paths, with retries (no command-line fallback for file lists).
+pub fn write_files(app: &AppHandle, paths: Vec<String>) {
+    let handle = app.clone();
+    match with_retries("files", move || crate::clipboard::set_clipboard_files(&handle, paths.clone())) {
+        Ok(()) => succeeded(app, false),
+        Err(e) => failed(app, format!("Failed to set clipboard files: {}", e)),
+    }
+}
+
+/// Write a received bitmap back as an image (Windows), with retries.
+#[cfg(target_os = "windows")]
+pub fn write_image(app: &AppHandle, path: String) {
+    match with_retries("image", move || crate::winclip::write_image(&path)) {
+        Ok(()) => succeeded(app, false),
+        Err(e) => failed(app, format!("Failed to set clipboard image: {}", e)),
+    }
+}
+
+// Candidate tools, in order of preference
+fn fallback_tools() -> Vec<(&'static str, Vec<&'static str>)> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut tools = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.push(("wl-copy", vec![]));
+        }
+        tools.push(("xclip", vec!["-selection", "clipboard"]));
+        tools.push(("xsel", vec!["--clipboard", "--input"]));
+        tools
+    }
+    #[cfg(target_os = "macos")]
+    {
+        vec![("pbcopy", vec![])]
+    }
+    #[cfg(target_os = "windows")]
+    {
+        vec![("powershell", vec!["-NoProfile", "-Command", "[Console]::In.ReadToEnd() | Set-Clipboard"])]
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        Vec::new()
+    }
+}
+
+fn fallback_text(text: &str) -> Result<&'static str, String> {
+    let mut errors = Vec::new();
+    for (tool, args) in fallback_tools() {
+        match run_tool(tool, &args, text) {
+            Ok(()) => return Ok(tool),
+            Err(e) => errors.push(format!("{}: {}", tool, e)),
+        }
+    }
+    if errors.is_empty() {
+        return Err("no fallback available on this platform".to_string());
+    }
+    Err(errors.join("; "))
+}
+
+fn run_tool(tool: &str, args: &[&str], text: &str) -> Result<(), String> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let deadline = Instant::now() + WRITE_TIMEOUT;
+    loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => return Err(format!("exited with {}", status)),
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                return Err("timed out".to_string());
+            }
+            None => thread::sleep(Duration::from_millis(50)),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Applying a received item to the clipboard in one go.
+//!
+//! An item can carry text and several files, and auto-downloaded files land one at a time
+//! (inline, already on disk, or streamed). Writing each part as it lands would leave the
+//! clipboard flipping between them mid-download. Instead the parts are collected here and the
+//! clipboard is written once every file has arrived or failed: all received files together, or
+//! the item's text when none of them made it. Parts still missing after `COMPOSE_TIMEOUT` don't
+//! hold the rest back; one that shows up later is applied on its own.
+//!
+//! A copied bitmap (`FileMetadata::clipboard_image`) goes back on the clipboard as an image
+//! rather than a file where that's supported (see winclip.rs).
+
+use crate::state::AppState;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const COMPOSE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+enum Part {
+    Pending,
+    Ready(String),
+    // Failed, blocked, or saved somewhere else instead
+    Missing,
+}
+
+pub struct Composition {
+    // Applied only if no file makes it
+    text: Option<String>,
+    parts: Vec<Part>,
+    // The item is a single copied bitmap
+    image: bool,
+}
+
+/// Start collecting the `file_count` files of item `id`. `text` is the item's text, when it
+/// should go on the clipboard (auto-receive on).
+pub fn begin(app: &AppHandle, state: &AppState, id: &str, text: Option<String>, file_count: usize) {
+    let parts = (0..file_count).map(|_| Part::Pending).collect();
+    let image = state.remote_files.lock().unwrap().get(id).is_some_and(|f| f.len() == 1 && f[0].clipboard_image);
+    state.compositions.lock().unwrap().insert(id.to_string(), Composition { text, parts, image });
+
+    let (app, id) = (app.clone(), id.to_string());
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(COMPOSE_TIMEOUT).await;
+        let state = app.state::<AppState>();
+        let stalled = state.compositions.lock().unwrap().remove(&id);
+        if let Some(composition) = stalled {
+            tracing::warn!("[Compose] Not all files of {} arrived in time; applying what did", id);
+            apply(&app, composition);
+        }
+    });
+}
+
+/// A file of item `id` is on disk: add it to the item's clipboard content, or put it on the
+/// clipboard directly when the item isn't being composed.
+pub fn file_ready(app: &AppHandle, state: &AppState, id: &str, index: usize, path: String) {
+    if !resolve(app, state, id, index, Part::Ready(path.clone())) {
+        crate::clipboard::set_clipboard_paths(app, vec![path]);
+    }
+}
+
+/// A file of item `id` won't reach the clipboard.
+pub fn file_missing(app: &AppHandle, state: &AppState, id: &str, index: usize) {
+    resolve(app, state, id, index, Part::Missing);
+}
+
+// Fill in one part, applying the item once none are pending. False if it isn't being composed.
+fn resolve(app: &AppHandle, state: &AppState, id: &str, index: usize, part: Part) -> bool {
+    let complete = {
+        let mut compositions = state.compositions.lock().unwrap();
+        let Some(slot) = compositions.get_mut(id).and_then(|c| c.parts.get_mut(index)) else {
+            return false;
+        };
+        *slot = part;
+        let pending = compositions.get(id).is_some_and(|c| c.parts.iter().any(|p| matches!(p, Part::Pending)));
+        if pending {
+            return true;
+        }
+        compositions.remove(id)
+    };
+    if let Some(composition) = complete {
+        apply(app, composition);
+    }
+    true
+}
+
+fn apply(app: &AppHandle, composition: Composition) {
+    let mut paths: Vec<String> = composition
+        .parts
+        .into_iter()
+        .filter_map(|p| match p {
+            Part::Ready(path) => Some(path),
+            _ => None,
+        })
+        .collect();
+    if composition.image && paths.len() == 1 {
+        tracing::info!("[Compose] Applying received image to the clipboard");
+        crate::clipboard::set_clipboard_image(app, paths.remove(0));
+    } else if !paths.is_empty() {
+        tracing::info!("[Compose] Applying {} received file(s) to the clipboard", paths.len());
+        crate::clipboard::set_clipboard_paths(app, paths);
+    } else if let Some(text) = composition.text {
+        crate::clipboard::set_clipboard(app, text);
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::Read;
+use std::path::Path;
+
+// Extensions that are already compressed (archives, media, office docs).
+// Running zstd over these only burns CPU.
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "apk", "avi", "avif", "br", "bz2", "cab", "deb", "dmg", "docx", "epub", "flac", "gif",
+    "gz", "heic", "jar", "jpeg", "jpg", "lz", "lz4", "lzma", "m4a", "m4v", "mkv", "mov", "mp3",
+    "mp4", "odp", "ods", "odt", "ogg", "opus", "pptx", "rar", "rpm", "tgz", "txz", "webm", "webp",
+    "whl", "xlsx", "xz", "zip", "zst",
+];
+
+// Magic numbers for common compressed formats (for files with missing/misleading extensions)
+const COMPRESSED_MAGIC: &[&[u8]] = &[
+    b"PK\x03\x04",             // zip (and docx/xlsx/jar/apk)
+    b"\x1f\x8b",               // gzip
+    b"\x28\xb5\x2f\xfd",       // zstd
+    b"BZh",                    // bzip2
+    b"\xfd7zXZ\x00",           // xz
+    b"7z\xbc\xaf\x27\x1c",     // 7z
+    b"Rar!\x1a\x07",           // rar
+    b"\x89PNG\r\n\x1a\n",      // png
+    b"\xff\xd8\xff",           // jpeg
+    b"GIF8",                   // gif
+    b"OggS",                   // ogg/opus
+    b"fLaC",                   // flac
+    b"ID3",                    // mp3
+    b"\x1a\x45\xdf\xa3",       // mkv/webm
+];
+
+/// Files smaller than this aren't worth compressing.
+const MIN_COMPRESS_SIZE: u64 = 4 * 1024;
+
+/// zstd level for file streams. Low levels keep up with Wi-Fi throughput on modest CPUs.
+pub const ZSTD_LEVEL: i32 = 3;
+
+/// Decide whether a file is worth compressing before streaming it.
+/// Checks the extension first, then sniffs the leading bytes.
+pub fn should_compress(path: &Path, size: u64) -> bool {
+    if size < MIN_COMPRESS_SIZE {
+        return false;
+    }
+
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        let ext = ext.to_lowercase();
+        if COMPRESSED_EXTENSIONS.contains(&ext.as_str()) {
+            return false;
+        }
+    }
+
+    let mut head = [0u8; 16];
+    let n = match std::fs::File::open(path).and_then(|mut f| f.read(&mut head)) {
+        Ok(n) => n,
+        Err(_) => return false,
+    };
+    let head = &head[..n];
+
+    // ftyp box at offset 4 (mp4/mov/heic)
+    if head.len() >= 8 && &head[4..8] == b"ftyp" {
+        return false;
+    }
+    // RIFF container with WEBP/AVI payload
+    if head.len() >= 12 && &head[0..4] == b"RIFF" && (&head[8..12] == b"WEBP" || &head[8..12] == b"AVI ") {
+        return false;
+    }
+
+    !COMPRESSED_MAGIC.iter().any(|magic| head.starts_with(magic))
+}
@@ -0,0 +1,190 @@
+use crate::state::AppState;
+use crate::storage::{AutoDownloadPolicy, AppSettings};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// How often the watcher checks the config file for changes.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Power-user config file (clustercut.toml). Everything is optional; values here
+/// override settings.json and the stored per-peer policies at runtime, and are never
+/// written back to them.
+///
+/// ```toml
+/// port = 4654
+///
+/// [settings]
+/// auto_receive = true
+/// max_auto_download_size = 104857600
+///
+/// [peer_policies]
+/// clustercut-12345 = "always"
+/// clustercut-67890 = { up_to = 10485760 }
+/// ```
+#[derive(serde::Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FileConfig {
+    pub port: Option<u16>,
+    pub settings: Option<toml::Table>,
+    pub peer_policies: HashMap<String, AutoDownloadPolicy>,
+}
+
+/// `--config PATH` if given, otherwise clustercut.toml in the app config dir.
+pub fn resolve_path(app: &AppHandle, cli_path: Option<&str>) -> Option<PathBuf> {
+    if let Some(p) = cli_path {
+        return Some(PathBuf::from(p));
+    }
+    app.path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join("clustercut.toml"))
+}
+
+/// Missing file = empty config. Parse errors are returned so the caller can keep the previous config.
+pub fn load(path: &Path) -> Result<FileConfig, String> {
+    if !path.exists() {
+        return Ok(FileConfig::default());
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    toml::from_str(&content).map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+}
+
+/// Overlay the [settings] table onto settings loaded from settings.json.
+pub fn overlay_settings(base: AppSettings, config: &FileConfig) -> AppSettings {
+    let Some(table) = &config.settings else {
+        return base;
+    };
+    let mut merged = match serde_json::to_value(&base) {
+        Ok(v) => v,
+        Err(_) => return base,
+    };
+    let overrides = match serde_json::to_value(table) {
+        Ok(v) => v,
+        Err(_) => return base,
+    };
+    if let (Some(merged_obj), Some(overrides_obj)) = (merged.as_object_mut(), overrides.as_object()) {
+        for (k, v) in overrides_obj {
+            merged_obj.insert(k.clone(), v.clone());
+        }
+    }
+    match serde_json::from_value(merged) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::error!("Invalid [settings] in config file: {}", e);
+            base
+        }
+    }
+}
+
+/// Apply settings and peer policy overrides to the running state.
+pub fn apply(app: &AppHandle, state: &AppState, config: &FileConfig) {
+    let settings = overlay_settings(crate::storage::load_settings(app), config);
+    *state.settings.lock().unwrap() = settings;
+
+    let mut policies = crate::storage::load_peer_download_policies(app);
+    policies.extend(config.peer_policies.iter().map(|(k, v)| (k.clone(), *v)));
+    *state.peer_download_policies.lock().unwrap() = policies;
+    *state.config_overrides.lock().unwrap() = config.clone();
+}
+
+fn current_overrides(app: &AppHandle) -> FileConfig {
+    app.try_state::<AppState>().map(|s| s.config_overrides.lock().unwrap().clone()).unwrap_or_default()
+}
+
+/// What to write to settings.json: keys set in [settings] keep their stored value, so the
+/// config file's overrides never end up persisted (and outlive the file).
+pub fn persisted_settings(app: &AppHandle, settings: &AppSettings) -> AppSettings {
+    let Some(table) = current_overrides(app).settings else {
+        return settings.clone();
+    };
+    let (Ok(mut merged), Ok(stored)) = (serde_json::to_value(settings), serde_json::to_value(crate::storage::load_settings(app))) else {
+        return settings.clone();
+    };
+    if let (Some(merged_obj), Some(stored_obj)) = (merged.as_object_mut(), stored.as_object()) {
+        for key in table.keys() {
+            match stored_obj.get(key) {
+                Some(value) => merged_obj.insert(key.clone(), value.clone()),
+                None => merged_obj.remove(key),
+            };
+        }
+    }
+    serde_json::from_value(merged).unwrap_or_else(|_| settings.clone())
+}
+
+/// Same for peer_policies.json: peers listed in [peer_policies] keep their stored policy.
+pub fn persisted_policies(app: &AppHandle, policies: &HashMap<String, AutoDownloadPolicy>) -> HashMap<String, AutoDownloadPolicy> {
+    let overridden = current_overrides(app).peer_policies;
+    if overridden.is_empty() {
+        return policies.clone();
+    }
+    let mut out: HashMap<_, _> = policies.iter().filter(|(id, _)| !overridden.contains_key(*id)).map(|(k, v)| (k.clone(), *v)).collect();
+    let mut stored = crate::storage::load_peer_download_policies(app);
+    out.extend(overridden.keys().filter_map(|id| stored.remove_entry(id)));
+    out
+}
+
+/// Poll the config file's mtime and re-apply it when it changes.
+pub fn spawn_watcher(app: AppHandle, path: PathBuf, initial: FileConfig) {
+    tauri::async_runtime::spawn(async move {
+        let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        let mut last_mtime = mtime(&path);
+        let mut current = initial;
+        tracing::info!("Watching config file {:?}", path);
+
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let state = app.state::<AppState>();
+            if state.is_shutdown() {
+                break;
+            }
+
+            let now = mtime(&path);
+            if now == last_mtime {
+                continue;
+            }
+            last_mtime = now;
+
+            let new_config = match load(&path) {
+                Ok(c) => c,
+                Err(e) => {
+                    tracing::error!("{}. Keeping previous config.", e);
+                    crate::events::emit(&app, crate::events::ConfigError { message: e });
+                    continue;
+                }
+            };
+            if new_config == current {
+                continue;
+            }
+            tracing::info!("Config file changed. Reloading.");
+
+            apply(&app, &state, &new_config);
+            crate::register_shortcuts(&app);
+
+            if new_config.port != current.port {
+                rebind_port(&state, new_config.port.unwrap_or(crate::DEFAULT_PORT));
+            }
+
+            current = new_config;
+            crate::events::emit(&app, crate::events::ConfigReloaded { path: path.to_string_lossy().into_owned(), port: current.port });
+        }
+    });
+}
+
+fn rebind_port(state: &AppState, port: u16) {
+    let Some(transport) = state.transport.lock().unwrap().clone() else {
+        return;
+    };
+    match transport.rebind(port) {
+        Ok(()) => {
+            tracing::info!("Transport rebound to port {}", port);
+            state.set_bound_port(port);
+            let device_id = state.local_device_id.lock().unwrap().clone();
+            let name = state.network_name.lock().unwrap().clone();
+            if let Some(discovery) = state.discovery.lock().unwrap().as_mut() {
+                let _ = discovery.register(&device_id, &name, port);
+            }
+        }
+        Err(e) => tracing::error!("Failed to rebind transport to port {}: {}", port, e),
+    }
+}
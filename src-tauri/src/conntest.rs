@@ -0,0 +1,142 @@
+//! On-demand connection test against a single peer (`test_peer_connection`).
+//!
+//! Runs four steps and reports each one: a bare QUIC handshake, a round-trip ping, an
+//! encrypted echo (proves both sides hold the same cluster key) and a small throughput
+//! sample. Probes ride the normal message path, so a passing test means real clipboard
+//! traffic would get through too.
+
+use crate::protocol::{Message, Probe, ProbeKind, ProbeReply};
+use crate::state::AppState;
+use crate::transport::Transport;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+// Random payload for the throughput step (hex, so twice this on the wire before encryption)
+const THROUGHPUT_SAMPLE_BYTES: usize = 64 * 1024;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TestStep {
+    pub name: &'static str,
+    pub ok: bool,
+    pub duration_ms: Option<u64>,
+    pub detail: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ConnectionReport {
+    pub peer_id: String,
+    pub addr: String,
+    pub ok: bool,
+    pub steps: Vec<TestStep>,
+    // Wire throughput of the sample, kilobytes per second
+    pub throughput_kbps: Option<u64>,
+}
+
+fn step(name: &'static str, started: Instant, result: Result<String, String>) -> TestStep {
+    let duration_ms = Some(started.elapsed().as_millis() as u64);
+    match result {
+        Ok(detail) => TestStep { name, ok: true, duration_ms, detail },
+        Err(detail) => TestStep { name, ok: false, duration_ms: None, detail },
+    }
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill(&mut bytes[..]);
+    hex::encode(bytes)
+}
+
+/// Send a probe and wait for the matching reply. Returns the reply and the wire size sent.
+async fn probe(state: &AppState, transport: &Transport, addr: std::net::SocketAddr, kind: ProbeKind, data: String) -> Result<(ProbeReply, usize), String> {
+    let request = Probe { id: uuid::Uuid::new_v4().to_string(), kind, data };
+    let cipher = crate::encrypt_cluster_message(state, &request).ok_or("No Cluster Key set")?;
+    let bytes = serde_json::to_vec(&Message::Probe(cipher)).map_err(|e| e.to_string())?;
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state.pending_probes.lock().unwrap().insert(request.id.clone(), tx);
+    // send_message lingers after writing; the reply may arrive before it returns
+    let sender = transport.clone();
+    let wire = bytes.clone();
+    let send = tauri::async_runtime::spawn(async move { sender.send_message(addr, &wire).await.map_err(|e| e.to_string()) });
+
+    let reply = tokio::time::timeout(REPLY_TIMEOUT, rx).await;
+    state.pending_probes.lock().unwrap().remove(&request.id);
+    match reply {
+        Ok(Ok(reply)) => Ok((reply, bytes.len())),
+        _ => match send.await {
+            Ok(Err(e)) => Err(format!("Send failed: {}", e)),
+            _ => Err(format!("No reply within {}s", REPLY_TIMEOUT.as_secs())),
+        },
+    }
+}
+
+pub async fn run(state: &AppState, transport: &Transport, peer_id: &str) -> Result<ConnectionReport, String> {
+    let peer = state.get_peers().get(peer_id).cloned().ok_or_else(|| format!("Peer {} not found or offline", peer_id))?;
+    if peer.guest_expires_at.is_some() {
+        return Err("Connection tests aren't available for guest devices".to_string());
+    }
+    let addr = std::net::SocketAddr::new(peer.ip, peer.port);
+    let mut steps = Vec::new();
+    let mut throughput_kbps = None;
+
+    let started = Instant::now();
+    let handshake = transport.handshake(addr).await.map(|_| "QUIC connection established".to_string()).map_err(|e| e.to_string());
+    let reachable = handshake.is_ok();
+    steps.push(step("handshake", started, handshake));
+
+    if reachable {
+        let started = Instant::now();
+        let ping = probe(state, transport, addr, ProbeKind::Ping, String::new()).await.map(|_| "Reply received".to_string());
+        steps.push(step("ping", started, ping));
+
+        let started = Instant::now();
+        let nonce = random_hex(32);
+        let echo = probe(state, transport, addr, ProbeKind::Echo, nonce.clone()).await.and_then(|(reply, _)| {
+            if reply.data == nonce {
+                Ok("Peer decrypted and returned our payload".to_string())
+            } else {
+                Err("Echo did not match (cluster keys differ?)".to_string())
+            }
+        });
+        steps.push(step("encrypted_echo", started, echo));
+
+        let started = Instant::now();
+        let sample = probe(state, transport, addr, ProbeKind::Sink, random_hex(THROUGHPUT_SAMPLE_BYTES)).await;
+        let elapsed = started.elapsed();
+        let throughput = sample.map(|(reply, sent)| {
+            let kbps = (sent as f64 / 1024.0 / elapsed.as_secs_f64().max(0.001)) as u64;
+            throughput_kbps = Some(kbps);
+            format!("{} KB in {} ms (peer read {} bytes)", sent / 1024, elapsed.as_millis(), reply.received_bytes)
+        });
+        steps.push(step("throughput", started, throughput));
+    }
+
+    let ok = steps.iter().all(|s| s.ok);
+    tracing::info!("[ConnTest] {} ({}): {}", peer_id, addr, if ok { "all steps passed" } else { "failed" });
+    Ok(ConnectionReport { peer_id: peer_id.to_string(), addr: addr.to_string(), ok, steps, throughput_kbps })
+}
+
+/// Answer a probe from another member.
+pub async fn answer(state: &AppState, transport: &Transport, addr: std::net::SocketAddr, probe: Probe) {
+    let reply = ProbeReply {
+        id: probe.id,
+        received_bytes: probe.data.len() as u64,
+        data: if probe.kind == ProbeKind::Echo { probe.data } else { String::new() },
+    };
+    let Some(cipher) = crate::encrypt_cluster_message(state, &reply) else {
+        return;
+    };
+    if let Ok(data) = serde_json::to_vec(&Message::ProbeReply(cipher)) {
+        if let Err(e) = transport.send_message(addr, &data).await {
+            tracing::debug!("[ConnTest] Failed to answer probe from {}: {}", addr, e);
+        }
+    }
+}
+
+/// Hand a reply to the test waiting for it.
+pub fn deliver(state: &AppState, reply: ProbeReply) {
+    if let Some(tx) = state.pending_probes.lock().unwrap().remove(&reply.id) {
+        let _ = tx.send(reply);
+    }
+}
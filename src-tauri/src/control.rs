@@ -0,0 +1,420 @@
+//! Local control socket for scripting: `clustercut copy|share|paste|peers`.
+//!
+//! The running instance listens on a per-user Unix socket (named pipe on Windows), one per
+//! profile. The socket lives in a directory only the user can enter, and connections from
+//! any other uid are refused. The pipe is named after the user's SID and its DACL admits only
+//! that user. The CLI subcommands connect, send a single JSON line and print the JSON reply.
+//! `share` is also what the macOS Services menu entry runs (see services.rs).
+
+use crate::protocol::ClipboardPayload;
+use crate::state::AppState;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Listener, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+// Requests are one line; this bounds `copy` payloads (clipboard messages are capped at ~10MB)
+const MAX_REQUEST_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum CliCommand {
+    /// Broadcast stdin to the cluster
+    Copy,
+    /// Send stdin to the cluster like a new copy, leaving the local clipboard alone
+    Share,
+    /// Print the latest clipboard content synced through the cluster
+    Paste,
+    /// List peers
+    Peers {
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Copy { text: String },
+    Share { text: String },
+    Paste,
+    Peers,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct Response {
+    ok: bool,
+    #[serde(default)]
+    result: serde_json::Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl Response {
+    fn ok(result: serde_json::Value) -> Self {
+        Self { ok: true, result, error: None }
+    }
+
+    fn err(e: impl Into<String>) -> Self {
+        Self { ok: false, result: serde_json::Value::Null, error: Some(e.into()) }
+    }
+}
+
+fn endpoint_suffix(profile: Option<&str>) -> String {
+    let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+    match profile {
+        Some(p) => format!("clustercut-{}-{}", user, p),
+        None => format!("clustercut-{}", user),
+    }
+}
+
+#[cfg(unix)]
+fn socket_dir() -> std::path::PathBuf {
+    // XDG_RUNTIME_DIR is private to the user, but the temp dir fallback may be shared (/tmp)
+    let base = std::env::var_os("XDG_RUNTIME_DIR").map(std::path::PathBuf::from).unwrap_or_else(std::env::temp_dir);
+    base.join(endpoint_suffix(None))
+}
+
+#[cfg(unix)]
+fn socket_path(profile: Option<&str>) -> std::path::PathBuf {
+    socket_dir().join(format!("{}.sock", endpoint_suffix(profile)))
+}
+
+#[cfg(unix)]
+fn own_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and can't fail
+    unsafe { libc::getuid() }
+}
+
+// Create the socket's directory 0700, or check that an existing one is ours and private, so
+// nobody else can reach the socket even before its own permissions are set
+#[cfg(unix)]
+fn private_socket_dir() -> io::Result<std::path::PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    let dir = socket_dir();
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+    let meta = std::fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != own_uid() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{:?} isn't a directory owned by this user", dir)));
+    }
+    if meta.mode() & 0o077 != 0 {
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(dir)
+}
+
+// Pipe names are machine-wide, so the name carries the user's SID and the pipe only admits
+// that user (see PipeSecurity)
+#[cfg(windows)]
+fn pipe_name(profile: Option<&str>) -> io::Result<String> {
+    Ok(format!(r"\\.\pipe\{}-{}", endpoint_suffix(profile), user_sid()?))
+}
+
+// The current user's SID (S-1-5-21-...)
+#[cfg(windows)]
+fn user_sid() -> io::Result<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, LocalFree, HANDLE, HLOCAL};
+    use windows::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows::Win32::Security::{GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER};
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+        let mut len = 0u32;
+        let _ = GetTokenInformation(token, TokenUser, None, 0, &mut len);
+        // u64s keep TOKEN_USER's pointer aligned
+        let mut buf = vec![0u64; (len as usize).div_ceil(8)];
+        let result = GetTokenInformation(token, TokenUser, Some(buf.as_mut_ptr().cast()), len, &mut len);
+        let _ = CloseHandle(token);
+        result?;
+        let user = &*(buf.as_ptr() as *const TOKEN_USER);
+
+        let mut sid = PWSTR::null();
+        ConvertSidToStringSidW(user.User.Sid, &mut sid)?;
+        let text = sid.to_string().map_err(io::Error::other);
+        let _ = LocalFree(HLOCAL(sid.0.cast()));
+        text
+    }
+}
+
+// Security descriptor whose DACL grants the current user, and nobody else, access to the pipe
+#[cfg(windows)]
+struct PipeSecurity(windows::Win32::Security::PSECURITY_DESCRIPTOR);
+
+// SAFETY: the descriptor is immutable once built and only freed on drop
+#[cfg(windows)]
+unsafe impl Send for PipeSecurity {}
+#[cfg(windows)]
+unsafe impl Sync for PipeSecurity {}
+
+#[cfg(windows)]
+impl PipeSecurity {
+    fn for_current_user() -> io::Result<Self> {
+        use windows::core::HSTRING;
+        use windows::Win32::Security::Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+        use windows::Win32::Security::PSECURITY_DESCRIPTOR;
+
+        // Protected DACL, generic-all for this SID only
+        let sddl = HSTRING::from(format!("D:P(A;;GA;;;{})", user_sid()?));
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        unsafe { ConvertStringSecurityDescriptorToSecurityDescriptorW(&sddl, SDDL_REVISION_1, &mut descriptor, None)? };
+        Ok(Self(descriptor))
+    }
+
+    fn create(&self, options: &tokio::net::windows::named_pipe::ServerOptions, name: &str) -> io::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+        use windows::Win32::Security::SECURITY_ATTRIBUTES;
+        let mut attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: self.0 .0,
+            bInheritHandle: false.into(),
+        };
+        // SAFETY: `attributes` and the descriptor it points to outlive the call
+        unsafe { options.create_with_security_attributes_raw(name, (&mut attributes as *mut SECURITY_ATTRIBUTES).cast()) }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for PipeSecurity {
+    fn drop(&mut self) {
+        use windows::Win32::Foundation::{LocalFree, HLOCAL};
+        // SAFETY: allocated by ConvertStringSecurityDescriptorToSecurityDescriptorW
+        let _ = unsafe { LocalFree(HLOCAL(self.0 .0)) };
+    }
+}
+
+// --- Server (inside the running app) ---
+
+/// Serve control requests for the active profile until the app exits.
+pub async fn serve(app: AppHandle) -> io::Result<()> {
+    // Latest clipboard item seen by this instance (sent, received or pending)
+    let latest: Arc<Mutex<Option<ClipboardPayload>>> = Arc::new(Mutex::new(None));
+    for event in ["clipboard-change", "clipboard-pending"] {
+        let latest = latest.clone();
+        app.listen(event, move |e| {
+            if let Ok(payload) = serde_json::from_str::<ClipboardPayload>(e.payload()) {
+                *latest.lock().unwrap() = Some(payload);
+            }
+        });
+    }
+
+    let profile = crate::storage::active_profile();
+    accept_loop(app, latest, profile.as_deref()).await
+}
+
+#[cfg(unix)]
+async fn accept_loop(app: AppHandle, latest: Arc<Mutex<Option<ClipboardPayload>>>, profile: Option<&str>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    private_socket_dir()?;
+    let path = socket_path(profile);
+    // Left behind by a previous run (single-instance guarantees no live owner)
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    tracing::info!("Control socket listening at {:?}", path);
+
+    let uid = own_uid();
+    loop {
+        let (stream, _) = listener.accept().await?;
+        match stream.peer_cred() {
+            Ok(cred) if cred.uid() == uid => {}
+            Ok(cred) => {
+                tracing::warn!("Refusing control connection from uid {}", cred.uid());
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Refusing control connection: can't read its credentials ({})", e);
+                continue;
+            }
+        }
+        let (app, latest) = (app.clone(), latest.clone());
+        tauri::async_runtime::spawn(async move { handle_connection(&app, &latest, stream).await });
+    }
+}
+
+#[cfg(windows)]
+async fn accept_loop(app: AppHandle, latest: Arc<Mutex<Option<ClipboardPayload>>>, profile: Option<&str>) -> io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+    let name = pipe_name(profile)?;
+    let security = PipeSecurity::for_current_user()?;
+    // first_pipe_instance: fail rather than join a pipe someone else created under our name
+    let mut server = security.create(ServerOptions::new().first_pipe_instance(true).reject_remote_clients(true), &name)?;
+    tracing::info!("Control pipe listening at {}", name);
+
+    loop {
+        server.connect().await?;
+        let connected = server;
+        server = security.create(ServerOptions::new().reject_remote_clients(true), &name)?;
+        let (app, latest) = (app.clone(), latest.clone());
+        tauri::async_runtime::spawn(async move { handle_connection(&app, &latest, connected).await });
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn accept_loop(_app: AppHandle, _latest: Arc<Mutex<Option<ClipboardPayload>>>, _profile: Option<&str>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "control socket not supported on this platform"))
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(app: &AppHandle, latest: &Mutex<Option<ClipboardPayload>>, stream: S) {
+    let (read, mut write) = tokio::io::split(stream);
+    let mut line = String::new();
+    let response = match BufReader::new(read.take(MAX_REQUEST_BYTES)).read_line(&mut line).await {
+        Ok(_) => match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle_request(app, latest, request).await,
+            Err(e) => Response::err(format!("Invalid request: {}", e)),
+        },
+        Err(e) => Response::err(format!("Read failed: {}", e)),
+    };
+    let mut out = serde_json::to_vec(&response).unwrap_or_default();
+    out.push(b'\n');
+    let _ = write.write_all(&out).await;
+    let _ = write.shutdown().await;
+}
+
+async fn handle_request(app: &AppHandle, latest: &Mutex<Option<ClipboardPayload>>, request: Request) -> Response {
+    match request {
+        Request::Copy { text } => match crate::send_clipboard(text, None, app.state(), app.state(), app.clone()).await {
+            Ok(()) => Response::ok(serde_json::Value::Null),
+            Err(e) => Response::err(e.to_string()),
+        },
+        Request::Share { text } => share(app, text).await,
+        Request::Paste => match latest.lock().unwrap().clone() {
+            Some(p) if p.files.as_ref().is_some_and(|f| !f.is_empty()) => {
+                Response::err("The latest clipboard item is a file transfer, not text")
+            }
+            Some(p) => Response::ok(serde_json::Value::String(p.text)),
+            None => Response::err("Nothing has been synced since ClusterCut started"),
+        },
+        Request::Peers => {
+            let mut peers: Vec<_> = app.state::<AppState>().get_peers().into_values().collect();
+            peers.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+            Response::ok(serde_json::to_value(peers).unwrap_or_default())
+        }
+    }
+}
+
+// Text handed over by another app: goes out the way a local copy would (auto-send, review,
+// screen lock), attributed to the app in front, which is where it was selected
+async fn share(app: &AppHandle, text: String) -> Response {
+    if text.is_empty() {
+        return Response::err("Nothing to share");
+    }
+    let state = app.state::<AppState>().inner().clone();
+    if state.is_reset_pending() {
+        return Response::err("Syncing is paused while a reset is pending");
+    }
+    let Some(transport) = app.try_state::<crate::transport::Transport>().map(|t| t.inner().clone()) else {
+        return Response::err("ClusterCut is still starting");
+    };
+    let app = app.clone();
+    // Link previews are fetched with block_on
+    let sent = tauri::async_runtime::spawn_blocking(move || {
+        // Same gate as the clipboard monitor: outside the allow-listed apps, nothing is sent
+        let (allowed, source) = crate::clipboard::check_source(&state);
+        if !allowed {
+            tracing::info!("[Control] Share from outside the allow-listed apps; not sending it");
+            return false;
+        }
+        crate::clipboard::broadcast_text(&app, &state, &transport, text, source);
+        true
+    })
+    .await;
+    match sent {
+        Ok(true) => Response::ok(serde_json::Value::Null),
+        Ok(false) => Response::err("The app in front isn't allowed to share (see the app allow-list)"),
+        Err(e) => Response::err(e.to_string()),
+    }
+}
+
+// --- Client (CLI subcommands) ---
+
+#[cfg(unix)]
+fn connect(profile: Option<&str>) -> io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(socket_path(profile))
+}
+
+#[cfg(windows)]
+fn connect(profile: Option<&str>) -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new().read(true).write(true).open(pipe_name(profile)?)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn connect(_profile: Option<&str>) -> io::Result<std::fs::File> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "control socket not supported on this platform"))
+}
+
+fn call(profile: Option<&str>, request: &Request) -> Result<serde_json::Value, String> {
+    let mut stream = connect(profile).map_err(|e| format!("ClusterCut is not running ({})", e))?;
+    let mut line = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+    stream.write_all(&line).map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    io::BufReader::new(stream).read_line(&mut reply).map_err(|e| e.to_string())?;
+    let response: Response = serde_json::from_str(&reply).map_err(|e| format!("Invalid reply: {}", e))?;
+    if response.ok {
+        Ok(response.result)
+    } else {
+        Err(response.error.unwrap_or_else(|| "Unknown error".to_string()))
+    }
+}
+
+/// Run a CLI subcommand against the running instance. Returns the process exit code.
+pub fn run_client(command: &CliCommand, profile: Option<&str>) -> i32 {
+    // Release builds on Windows have no console of their own
+    #[cfg(windows)]
+    unsafe {
+        use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+
+    let result = match command {
+        CliCommand::Copy => {
+            let mut text = String::new();
+            match io::stdin().read_to_string(&mut text) {
+                Ok(_) if text.is_empty() => Err("Nothing to copy (stdin was empty)".to_string()),
+                Ok(_) => call(profile, &Request::Copy { text }).map(|_| ()),
+                Err(e) => Err(format!("Failed to read stdin: {}", e)),
+            }
+        }
+        CliCommand::Share => {
+            let mut text = String::new();
+            match io::stdin().read_to_string(&mut text) {
+                Ok(_) if text.is_empty() => Err("Nothing to share (stdin was empty)".to_string()),
+                Ok(_) => call(profile, &Request::Share { text }).map(|_| ()),
+                Err(e) => Err(format!("Failed to read stdin: {}", e)),
+            }
+        }
+        CliCommand::Paste => call(profile, &Request::Paste).map(|v| {
+            print!("{}", v.as_str().unwrap_or_default());
+            let _ = io::stdout().flush();
+        }),
+        CliCommand::Peers { json } => call(profile, &Request::Peers).map(|v| {
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&v).unwrap_or_default());
+                return;
+            }
+            for p in v.as_array().into_iter().flatten() {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    p["id"].as_str().unwrap_or_default(),
+                    p["hostname"].as_str().unwrap_or_default(),
+                    p["ip"].as_str().unwrap_or_default(),
+                    if p["is_trusted"].as_bool().unwrap_or(false) { "trusted" } else { "untrusted" },
+                );
+            }
+        }),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("clustercut: {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Opt-in local crash reports (`crash_reports` setting). Nothing is ever uploaded.
+//!
+//! Panics are written as JSON reports (message, location, backtrace) from a panic hook. Native
+//! crashes can't safely do much work: on Unix a fatal-signal handler only drops a marker file
+//! with async-signal-safe calls, which is turned into a report on the next launch; on Windows
+//! an unhandled-exception filter writes a minidump next to the report. Hooks are installed
+//! once and stay in place; turning the setting off just makes them do nothing.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use tauri::{AppHandle, Manager};
+
+// Oldest reports beyond this are deleted
+const MAX_REPORTS: usize = 20;
+#[cfg(unix)]
+const PENDING_MARKER: &str = "native-pending";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DIR: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
+static INSTALL: Once = Once::new();
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrashReport {
+    pub id: String,
+    // "panic" or "native"
+    pub kind: String,
+    pub timestamp: u64,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub message: String,
+    #[serde(default)]
+    pub thread: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub backtrace: Option<String>,
+    // Path of the minidump, when one was written
+    #[serde(default)]
+    pub minidump: Option<String>,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+pub fn reports_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|p| p.join("crash_reports"))
+}
+
+fn new_report(kind: &str, timestamp: u64, message: String) -> CrashReport {
+    CrashReport {
+        id: format!("{}-{}-{}", kind, timestamp, &uuid::Uuid::new_v4().simple().to_string()[..8]),
+        kind: kind.to_string(),
+        timestamp,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        message,
+        thread: None,
+        location: None,
+        backtrace: None,
+        minidump: None,
+    }
+}
+
+fn write_report(dir: &Path, report: &CrashReport) {
+    let _ = std::fs::create_dir_all(dir);
+    if let Ok(json) = serde_json::to_string_pretty(report) {
+        let _ = std::fs::write(dir.join(format!("{}.json", report.id)), json);
+    }
+}
+
+/// Apply the setting: install the hooks the first time it's on, and pick up a native crash
+/// recorded by the previous run.
+pub fn configure(app: &AppHandle, enabled: bool) {
+    let Some(dir) = reports_dir(app) else {
+        return;
+    };
+    let dir = DIR.get_or_init(|| dir).clone();
+    ENABLED.store(enabled, Ordering::Relaxed);
+    #[cfg(unix)]
+    collect_pending(&dir);
+    if enabled {
+        INSTALL.call_once(|| {
+            let _ = std::fs::create_dir_all(&dir);
+            install_panic_hook();
+            native::install(&dir);
+            tracing::info!("[Crash] Crash reporting enabled ({})", dir.display());
+        });
+        prune(&dir);
+    }
+}
+
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if ENABLED.load(Ordering::Relaxed) {
+            if let Some(dir) = DIR.get() {
+                let message = info
+                    .payload()
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "Box<dyn Any>".to_string());
+                let mut report = new_report("panic", now(), message);
+                report.thread = std::thread::current().name().map(str::to_string);
+                report.location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+                report.backtrace = Some(std::backtrace::Backtrace::force_capture().to_string());
+                write_report(dir, &report);
+            }
+        }
+        previous(info);
+    }));
+}
+
+/// Turn the marker left by the signal handler into a report.
+#[cfg(unix)]
+fn collect_pending(dir: &Path) {
+    let marker = dir.join(PENDING_MARKER);
+    let Ok(signal) = std::fs::read_to_string(&marker) else {
+        return;
+    };
+    let timestamp = std::fs::metadata(&marker)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or_else(now, |d| d.as_secs());
+    let _ = std::fs::remove_file(&marker);
+    tracing::warn!("[Crash] The previous run crashed ({})", signal.trim());
+    write_report(dir, &new_report("native", timestamp, format!("Terminated by {}", signal.trim())));
+}
+
+fn prune(dir: &Path) {
+    let mut reports = list(dir);
+    if reports.len() <= MAX_REPORTS {
+        return;
+    }
+    for report in reports.drain(MAX_REPORTS..) {
+        remove(dir, &report);
+    }
+}
+
+fn remove(dir: &Path, report: &CrashReport) {
+    let _ = std::fs::remove_file(dir.join(format!("{}.json", report.id)));
+    if let Some(dump) = &report.minidump {
+        let _ = std::fs::remove_file(dump);
+    }
+}
+
+/// Reports in `dir`, newest first.
+fn list(dir: &Path) -> Vec<CrashReport> {
+    let mut reports: Vec<CrashReport> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|s| serde_json::from_str(&s).ok())
+        .collect();
+    reports.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+    reports
+}
+
+pub fn reports(app: &AppHandle) -> Vec<CrashReport> {
+    reports_dir(app).map(|dir| list(&dir)).unwrap_or_default()
+}
+
+pub fn clear(app: &AppHandle) {
+    if let Some(dir) = reports_dir(app) {
+        for report in list(&dir) {
+            remove(&dir, &report);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod native {
+    use std::ffi::CString;
+    use std::path::Path;
+    use std::sync::atomic::Ordering;
+
+    const SIGNALS: [libc::c_int; 5] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGFPE, libc::SIGABRT];
+
+    static MARKER: once_cell::sync::OnceCell<CString> = once_cell::sync::OnceCell::new();
+    // Handlers that were in place before ours (Rust's own stack-overflow handler among them)
+    static mut PREVIOUS: [Option<libc::sigaction>; 5] = [None; 5];
+
+    fn signal_name(signal: libc::c_int) -> &'static [u8] {
+        match signal {
+            libc::SIGSEGV => b"SIGSEGV",
+            libc::SIGBUS => b"SIGBUS",
+            libc::SIGILL => b"SIGILL",
+            libc::SIGFPE => b"SIGFPE",
+            libc::SIGABRT => b"SIGABRT",
+            _ => b"a fatal signal",
+        }
+    }
+
+    // Only async-signal-safe calls from here on
+    extern "C" fn on_fatal_signal(signal: libc::c_int) {
+        unsafe {
+            if super::ENABLED.load(Ordering::Relaxed) {
+                if let Some(path) = MARKER.get() {
+                    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o600);
+                    if fd >= 0 {
+                        let name = signal_name(signal);
+                        libc::write(fd, name.as_ptr().cast(), name.len());
+                        libc::close(fd);
+                    }
+                }
+            }
+            // Hand the signal to whoever had it before us (or the default action)
+            let index = SIGNALS.iter().position(|s| *s == signal).unwrap_or(0);
+            match (*std::ptr::addr_of!(PREVIOUS))[index] {
+                Some(previous) => libc::sigaction(signal, &previous, std::ptr::null_mut()),
+                None => {
+                    libc::signal(signal, libc::SIG_DFL);
+                    0
+                }
+            };
+            libc::raise(signal);
+        }
+    }
+
+    pub fn install(dir: &Path) {
+        let Ok(path) = CString::new(dir.join(super::PENDING_MARKER).to_string_lossy().into_owned()) else {
+            return;
+        };
+        let _ = MARKER.set(path);
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_fatal_signal as *const () as usize;
+            // On the alternate stack, so a stack overflow is still caught
+            action.sa_flags = libc::SA_ONSTACK;
+            libc::sigemptyset(&mut action.sa_mask);
+            for (i, signal) in SIGNALS.iter().enumerate() {
+                let mut previous: libc::sigaction = std::mem::zeroed();
+                if libc::sigaction(*signal, &action, &mut previous) == 0 {
+                    (*std::ptr::addr_of_mut!(PREVIOUS))[i] = Some(previous);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod native {
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use std::sync::atomic::Ordering;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Diagnostics::Debug::{
+        MiniDumpNormal, MiniDumpWriteDump, SetUnhandledExceptionFilter, EXCEPTION_POINTERS, MINIDUMP_EXCEPTION_INFORMATION,
+    };
+    use windows::Win32::System::Threading::{GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId};
+
+    // EXCEPTION_CONTINUE_SEARCH: let Windows carry on terminating the process
+    const CONTINUE_SEARCH: i32 = 0;
+
+    unsafe extern "system" fn on_unhandled_exception(info: *const EXCEPTION_POINTERS) -> i32 {
+        if !super::ENABLED.load(Ordering::Relaxed) {
+            return CONTINUE_SEARCH;
+        }
+        let Some(dir) = super::DIR.get() else {
+            return CONTINUE_SEARCH;
+        };
+        let code = info.as_ref().and_then(|i| i.ExceptionRecord.as_ref()).map_or(0, |r| r.ExceptionCode.0 as u32);
+        let mut report = super::new_report("native", super::now(), format!("Unhandled exception 0x{:08X}", code));
+        let dump_path = dir.join(format!("{}.dmp", report.id));
+        if let Ok(file) = std::fs::File::create(&dump_path) {
+            let exception = MINIDUMP_EXCEPTION_INFORMATION { ThreadId: GetCurrentThreadId(), ExceptionPointers: info as *mut _, ClientPointers: false.into() };
+            let written = MiniDumpWriteDump(
+                GetCurrentProcess(),
+                GetCurrentProcessId(),
+                HANDLE(file.as_raw_handle()),
+                MiniDumpNormal,
+                Some(&exception),
+                None,
+                None,
+            );
+            if written.is_ok() {
+                report.minidump = Some(dump_path.to_string_lossy().into_owned());
+            }
+        }
+        super::write_report(dir, &report);
+        CONTINUE_SEARCH
+    }
+
+    pub fn install(_dir: &Path) {
+        unsafe {
+            SetUnhandledExceptionFilter(Some(on_unhandled_exception));
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod native {
+    pub fn install(_dir: &std::path::Path) {}
+}
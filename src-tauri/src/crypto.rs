@@ -1,37 +1,231 @@
+use aes_gcm::Aes256Gcm;
+use argon2::{Algorithm, Argon2, Params, Version};
 use chacha20poly1305::aead::{Aead, AeadCore, OsRng};
 use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
 use spake2::{Ed25519Group, Identity, Password, Spake2};
 use std::error::Error;
 
+// AES-GCM messages are tagged: MAGIC, suite id, then nonce || ciphertext. ChaCha20-Poly1305
+// keeps the original untagged layout (nonce || ciphertext) so older peers can still read it.
+const ENVELOPE_MAGIC: [u8; 2] = [0xC5, 0x55];
+const NONCE_LEN: usize = 12;
+
+/// Version of the PIN stretching, key schedule and confirmation exchange below. Bumped
+/// whenever any of them changes; a PairRequest with another version can't complete, so it's
+/// refused.
+pub const PAIRING_KDF_VERSION: u8 = 2;
+// Argon2id cost for the PIN pre-hash (OWASP minimum: 19 MiB, 2 passes, 1 lane)
+const PIN_HASH_MEMORY_KIB: u32 = 19 * 1024;
+const PIN_HASH_PASSES: u32 = 2;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CipherSuite {
+    #[default]
+    #[serde(rename = "chacha20_poly1305")]
+    ChaCha20Poly1305,
+    #[serde(rename = "aes_256_gcm")]
+    Aes256Gcm,
+}
+
+impl CipherSuite {
+    fn id(self) -> u8 {
+        match self {
+            CipherSuite::ChaCha20Poly1305 => 1,
+            CipherSuite::Aes256Gcm => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CipherSuite::ChaCha20Poly1305),
+            2 => Some(CipherSuite::Aes256Gcm),
+            _ => None,
+        }
+    }
+}
+
+/// `cipher_suite` setting: which AEAD we'd like peers to use for what they send us.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CipherPreference {
+    // AES-256-GCM when this CPU accelerates it, ChaCha20-Poly1305 otherwise
+    #[default]
+    Auto,
+    #[serde(rename = "chacha20_poly1305")]
+    ChaCha20Poly1305,
+    #[serde(rename = "aes_256_gcm")]
+    Aes256Gcm,
+}
+
+/// Whether the CPU has AES instructions (where AES-GCM beats ChaCha20-Poly1305).
+pub fn aes_accelerated() -> bool {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        std::arch::is_x86_feature_detected!("aes") && std::arch::is_x86_feature_detected!("pclmulqdq")
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        std::arch::is_aarch64_feature_detected!("aes")
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        false
+    }
+}
+
+/// Whether we advertise AES-256-GCM (peers then use it for what they send us).
+pub fn prefers_aes(preference: CipherPreference) -> bool {
+    match preference {
+        CipherPreference::Auto => aes_accelerated(),
+        CipherPreference::ChaCha20Poly1305 => false,
+        CipherPreference::Aes256Gcm => true,
+    }
+}
+
+/// Suite for a message to a peer: AES-GCM only if it asked for it and our own preference
+/// allows it. Peers that advertise nothing (older versions) get ChaCha20-Poly1305.
+pub fn negotiate(preference: CipherPreference, peer_wants_aes: bool) -> CipherSuite {
+    if peer_wants_aes && prefers_aes(preference) {
+        CipherSuite::Aes256Gcm
+    } else {
+        CipherSuite::ChaCha20Poly1305
+    }
+}
+
 pub struct SpakeState {
     spake: Spake2<Ed25519Group>,
+    // Our own SPAKE2 message, for the transcript
+    outbound: Vec<u8>,
 }
 
-pub fn start_spake2(
-    password: &str,
-    _id_a: &str,
-    _id_b: &str,
-) -> Result<(SpakeState, Vec<u8>), Box<dyn Error + Send + Sync>> {
-    let (spake, msg) = Spake2::<Ed25519Group>::start_symmetric(
-        &Password::new(password.as_bytes()),
-        &Identity::new(b"clustercut-connect"),
-    );
+/// Keys derived from a completed SPAKE2 exchange. The raw SPAKE2 output is never used directly.
+#[derive(Clone)]
+pub struct SessionKeys {
+    // Wraps the cluster key in Welcome
+    pub encryption: [u8; 32],
+    // MACs proving to the other side that we derived the same keys
+    pub confirmation: [u8; 32],
+    // Hash of both SPAKE2 messages, covered by every confirmation tag
+    pub transcript: [u8; 32],
+}
 
-    Ok((SpakeState { spake }, msg))
+// Keep key material out of logs
+impl std::fmt::Debug for SessionKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SessionKeys { .. }")
+    }
 }
 
-pub fn finish_spake2(
-    state: SpakeState,
-    inbound_msg: &[u8],
-) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
-    let key = state
+// Argon2id over the PIN, salted with the network name, so each offline guess against a
+// captured handshake costs a full Argon2 run and can't be shared across networks.
+fn stretch_pin(pin: &str, network_name: &str) -> Result<[u8; 32], Box<dyn Error + Send + Sync>> {
+    if pin.is_empty() {
+        return Err("Refusing to pair with an empty PIN".into());
+    }
+    let salt = Sha256::digest(format!("clustercut-pin:{}", network_name).as_bytes());
+    let params = Params::new(PIN_HASH_MEMORY_KIB, PIN_HASH_PASSES, 1, Some(32)).map_err(|e| format!("Argon2 params: {}", e))?;
+    let mut out = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(pin.as_bytes(), &salt, &mut out)
+        .map_err(|e| format!("Argon2 error: {}", e))?;
+    Ok(out)
+}
+
+/// Start a SPAKE2 exchange for `pin` on the network called `network_name`. Both sides must
+/// use the same name, since it salts the PIN hash.
+pub fn start_spake2(pin: &str, network_name: &str) -> Result<(SpakeState, Vec<u8>), Box<dyn Error + Send + Sync>> {
+    let password = stretch_pin(pin, network_name)?;
+    let (spake, msg) = Spake2::<Ed25519Group>::start_symmetric(&Password::new(password), &Identity::new(b"clustercut-connect"));
+
+    Ok((SpakeState { spake, outbound: msg.clone() }, msg))
+}
+
+pub fn finish_spake2(state: SpakeState, inbound_msg: &[u8]) -> Result<SessionKeys, Box<dyn Error + Send + Sync>> {
+    let shared = state
         .spake
         .finish(inbound_msg)
         .map_err(|e| format!("Spake error: {}", e))?;
-    Ok(key)
+    // Symmetric SPAKE2 has no fixed message order, so both sides hash the messages sorted
+    let (first, second) = if state.outbound.as_slice() <= inbound_msg {
+        (state.outbound.as_slice(), inbound_msg)
+    } else {
+        (inbound_msg, state.outbound.as_slice())
+    };
+    let mut transcript = Sha256::new();
+    transcript.update((first.len() as u32).to_be_bytes());
+    transcript.update(first);
+    transcript.update(second);
+
+    let hkdf = Hkdf::<Sha256>::new(Some(b"clustercut-pairing-v1"), &shared);
+    let mut keys = SessionKeys { encryption: [0u8; 32], confirmation: [0u8; 32], transcript: transcript.finalize().into() };
+    hkdf.expand(b"encryption", &mut keys.encryption).map_err(|e| format!("HKDF error: {}", e))?;
+    hkdf.expand(b"confirmation", &mut keys.confirmation).map_err(|e| format!("HKDF error: {}", e))?;
+    Ok(keys)
+}
+
+/// What a key confirmation tag vouches for. Each step uses its own context (and the sender's
+/// device id), so a tag can't be replayed in the other direction or at a later step.
+pub enum Confirm<'a> {
+    // PairResponse, from the responder
+    Responder(&'a str),
+    // PairConfirm, from the initiator
+    Initiator(&'a str),
+    // Welcome, naming the joiner it was meant for
+    Welcome(&'a str),
+}
+
+fn confirmation_mac(keys: &SessionKeys, context: Confirm) -> Hmac<Sha256> {
+    let (label, device_id): (&[u8], &str) = match context {
+        Confirm::Responder(id) => (b"clustercut-confirm-responder:", id),
+        Confirm::Initiator(id) => (b"clustercut-confirm-initiator:", id),
+        Confirm::Welcome(id) => (b"clustercut-welcome:", id),
+    };
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&keys.confirmation).expect("HMAC takes any key length");
+    mac.update(label);
+    mac.update(device_id.as_bytes());
+    mac.update(&keys.transcript);
+    mac
+}
+
+/// MAC over the handshake transcript proving we hold the same session keys.
+pub fn confirmation_tag(keys: &SessionKeys, context: Confirm) -> Vec<u8> {
+    confirmation_mac(keys, context).finalize().into_bytes().to_vec()
+}
+
+/// Constant-time check of the other side's confirmation tag.
+pub fn verify_confirmation(keys: &SessionKeys, context: Confirm, tag: &[u8]) -> bool {
+    confirmation_mac(keys, context).verify_slice(tag).is_ok()
 }
 
+/// Encrypt with ChaCha20-Poly1305, which every version understands.
 pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    encrypt_with(CipherSuite::ChaCha20Poly1305, key, plaintext)
+}
+
+pub fn encrypt_with(suite: CipherSuite, key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    match suite {
+        CipherSuite::ChaCha20Poly1305 => encrypt_chacha(key, plaintext),
+        CipherSuite::Aes256Gcm => {
+            let cipher = Aes256Gcm::new(aes_gcm::Key::<Aes256Gcm>::from_slice(key));
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(&nonce, plaintext)
+                .map_err(|e| format!("Encryption failure: {}", e))?;
+            let mut result = Vec::with_capacity(ENVELOPE_MAGIC.len() + 1 + NONCE_LEN + ciphertext.len());
+            result.extend_from_slice(&ENVELOPE_MAGIC);
+            result.push(suite.id());
+            result.extend_from_slice(&nonce);
+            result.extend_from_slice(&ciphertext);
+            Ok(result)
+        }
+    }
+}
+
+fn encrypt_chacha(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
     let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
     let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng); // 96-bits; unique per message
     let ciphertext = cipher
@@ -43,16 +237,32 @@ pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Erro
     Ok(result)
 }
 
-pub fn decrypt(
+/// Decrypt either layout. An untagged (ChaCha) message can start with the magic bytes by
+/// chance, so a tagged attempt that fails authentication falls back to the untagged one.
+pub fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let header = ENVELOPE_MAGIC.len() + 1;
+    if data.len() >= header + NONCE_LEN && data.starts_with(&ENVELOPE_MAGIC) {
+        if let Some(CipherSuite::Aes256Gcm) = CipherSuite::from_id(data[ENVELOPE_MAGIC.len()]) {
+            let (nonce, ciphertext) = data[header..].split_at(NONCE_LEN);
+            let cipher = Aes256Gcm::new(aes_gcm::Key::<Aes256Gcm>::from_slice(key));
+            if let Ok(plaintext) = cipher.decrypt(nonce.into(), ciphertext) {
+                return Ok(plaintext);
+            }
+        }
+    }
+    decrypt_chacha(key, data)
+}
+
+fn decrypt_chacha(
     key: &[u8; 32],
     ciphertext_with_nonce: &[u8],
 ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
-    if ciphertext_with_nonce.len() < 12 {
+    if ciphertext_with_nonce.len() < NONCE_LEN {
         return Err("Ciphertext too short".into());
     }
 
-    let nonce = &ciphertext_with_nonce[..12];
-    let ciphertext = &ciphertext_with_nonce[12..];
+    let nonce = &ciphertext_with_nonce[..NONCE_LEN];
+    let ciphertext = &ciphertext_with_nonce[NONCE_LEN..];
 
     let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
     let plaintext = cipher
@@ -61,3 +271,20 @@ pub fn decrypt(
 
     Ok(plaintext)
 }
+
+/// SHA-256 of a file's contents, hex encoded.
+pub fn hash_file(path: &std::path::Path) -> std::io::Result<String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -1,8 +1,247 @@
+//! Session bus services (Linux).
+//!
+//! `org.clustercut.Control1` at /org/clustercut/Control1 is the desktop-neutral control
+//! interface for panel widgets (Plasma applets, bars, scripts). Its contract is
+//! assets/dbus/org.clustercut.Control1.xml: members are only ever added to it; anything
+//! incompatible goes into a Control2 served alongside.
+//!
+//! `com.keithvassallo.clustercut` at /org/gnome/Shell/Extensions/ClusterCut is the GNOME
+//! Shell extension's (see gnome-extension/), which ships with the app and may change with it.
+//!
+//! Both run on one connection and share the state below; a single watch loop sends
+//! PropertiesChanged (and Control1's PeersChanged/LatestItemChanged) when it changes.
+
+use crate::presence::Presence;
 use crate::state::AppState;
-use tauri::{Emitter, Listener, Manager};
+use crate::storage::AppSettings;
+use std::sync::Arc;
+use tauri::{Listener, Manager};
 use zbus::interface;
 use zbus::object_server::SignalContext;
 
+const GNOME_PATH: &str = "/org/gnome/Shell/Extensions/ClusterCut";
+const GNOME_NAME: &str = "com.keithvassallo.clustercut";
+const CONTROL_PATH: &str = "/org/clustercut/Control1";
+const CONTROL_NAME: &str = "org.clustercut.Control1";
+
+// Events after which something published may have changed
+const WATCHED_EVENTS: &[&str] = &[
+    "settings-changed",
+    "screen-lock-changed",
+    "peer-update",
+    "peer-remove",
+    "peer-presence",
+    "unread-changed",
+    "clipboard-change",
+    "clipboard-pending",
+    "clipboard-pending-removed",
+];
+
+// Change settings the way the tray does: save, tell the UI, refresh the tray
+fn update_settings(app: &tauri::AppHandle, change: impl FnOnce(&mut AppSettings)) -> AppSettings {
+    change_settings(&app.state::<AppState>(), change, |settings| {
+        crate::storage::save_settings(app, settings);
+        crate::events::emit(app, crate::events::SettingsChanged(settings));
+
+        #[cfg(desktop)]
+        crate::tray::update_tray_menu(app);
+    })
+}
+
+// Apply `change` under the settings lock, then `publish` the result with the lock released
+// (the tray menu and settings.json both lock the settings again)
+fn change_settings(state: &AppState, change: impl FnOnce(&mut AppSettings), publish: impl FnOnce(&AppSettings)) -> AppSettings {
+    let settings = {
+        let mut settings = state.settings.lock().unwrap();
+        change(&mut settings);
+        settings.clone()
+    };
+    publish(&settings);
+    settings
+}
+
+// Pausing turns both directions off and remembers how they were; resuming puts them back
+// (both on if nothing was on before, since otherwise resuming would change nothing)
+fn pause_sync(app: &tauri::AppHandle, paused: bool) {
+    tracing::info!("[D-Bus] {} sync", if paused { "Pausing" } else { "Resuming" });
+    update_settings(app, |s| {
+        if paused {
+            if s.paused_toggles.is_none() {
+                s.paused_toggles = Some((s.auto_send, s.auto_receive));
+            }
+            s.auto_send = false;
+            s.auto_receive = false;
+        } else {
+            let (send, receive) = s.paused_toggles.take().filter(|&(send, receive)| send || receive).unwrap_or((true, true));
+            s.auto_send = send;
+            s.auto_receive = receive;
+        }
+    });
+}
+
+async fn send_clipboard(app: &tauri::AppHandle) {
+    let app = app.clone();
+    let settings = app.state::<AppState>().settings.lock().unwrap().clone();
+    // Reads the clipboard; keep it off the bus executor
+    let _ = tauri::async_runtime::spawn_blocking(move || crate::send_clipboard_now(&app, &settings)).await;
+}
+
+fn apply_pending(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<AppState>();
+    crate::clipboard::apply_pending(app, &state, None).is_some()
+}
+
+/// The published state, compared to decide what changed.
+#[derive(PartialEq, Clone)]
+struct Snapshot {
+    auto_send: bool,
+    auto_receive: bool,
+    paused: bool,
+    unread_count: u32,
+    pending_count: u32,
+    // Trusted devices as ListPeers returns them
+    peers: Vec<(String, String, String, u64)>,
+    latest_item: Option<String>,
+}
+
+impl Snapshot {
+    fn of(state: &AppState) -> Self {
+        let (auto_send, auto_receive) = {
+            let settings = state.settings.lock().unwrap();
+            (settings.auto_send, settings.auto_receive)
+        };
+        let mut peers: Vec<_> = crate::presence::snapshot(state)
+            .into_iter()
+            .filter(|p| p.peer.is_trusted)
+            .map(|p| (p.peer.id, p.peer.hostname, presence_name(p.presence).to_string(), p.since))
+            .collect();
+        peers.sort();
+        Self {
+            auto_send,
+            auto_receive,
+            // Paused from here (both directions off) or by the screen lock
+            paused: (!auto_send && !auto_receive) || crate::screenlock::sync_paused(state),
+            unread_count: crate::unread::count(state),
+            pending_count: state.pending_clipboard.lock().unwrap().len() as u32,
+            peers,
+            latest_item: state.recent_items.lock().unwrap().front().map(|p| p.id.clone()),
+        }
+    }
+
+    /// Trusted devices that aren't offline.
+    fn peer_count(&self) -> u32 {
+        self.peers.iter().filter(|p| p.2 != presence_name(Presence::Offline)).count() as u32
+    }
+}
+
+fn presence_name(presence: Presence) -> &'static str {
+    match presence {
+        Presence::Online => "online",
+        Presence::Away => "away",
+        Presence::Offline => "offline",
+    }
+}
+
+fn snapshot(app: &tauri::AppHandle) -> Snapshot {
+    Snapshot::of(&app.state::<AppState>())
+}
+
+/// org.clustercut.Control1, see assets/dbus/org.clustercut.Control1.xml.
+pub struct Control {
+    app_handle: tauri::AppHandle,
+}
+
+#[interface(name = "org.clustercut.Control1")]
+impl Control {
+    /// Trusted devices: (id, name, "online" | "away" | "offline", unix time it entered that state or 0).
+    async fn list_peers(&self) -> Vec<(String, String, String, u64)> {
+        snapshot(&self.app_handle).peers
+    }
+
+    /// Newest item sent or received: (id, sender name, preview, file count, unix time).
+    /// The id is empty when there's none.
+    async fn get_latest_item(&self) -> (String, String, String, u32, u64) {
+        let state = self.app_handle.state::<AppState>();
+        let items = state.recent_items.lock().unwrap();
+        let Some(item) = items.front() else {
+            return Default::default();
+        };
+        let last = crate::peerstats::LastItem::from_payload(item);
+        // Tokens and keys stay out of panels and other session processes
+        let sensitive = item
+            .content
+            .as_ref()
+            .is_some_and(|c| c.kinds.contains(&crate::classify::ContentKind::Sensitive));
+        let preview = if sensitive { String::new() } else { last.preview };
+        (last.id, item.sender.clone(), preview, last.file_count as u32, last.timestamp)
+    }
+
+    async fn pause_sync(&self, paused: bool) {
+        pause_sync(&self.app_handle, paused);
+    }
+
+    async fn send_clipboard(&self) {
+        send_clipboard(&self.app_handle).await;
+    }
+
+    async fn apply_pending(&self) -> bool {
+        apply_pending(&self.app_handle)
+    }
+
+    async fn show_window(&self) {
+        crate::show_main_window(&self.app_handle);
+    }
+
+    #[zbus(property)]
+    async fn auto_send(&self) -> bool {
+        snapshot(&self.app_handle).auto_send
+    }
+
+    #[zbus(property)]
+    async fn set_auto_send(&mut self, enabled: bool) {
+        update_settings(&self.app_handle, |s| s.auto_send = enabled);
+    }
+
+    #[zbus(property)]
+    async fn auto_receive(&self) -> bool {
+        snapshot(&self.app_handle).auto_receive
+    }
+
+    #[zbus(property)]
+    async fn set_auto_receive(&mut self, enabled: bool) {
+        update_settings(&self.app_handle, |s| s.auto_receive = enabled);
+    }
+
+    #[zbus(property)]
+    async fn paused(&self) -> bool {
+        snapshot(&self.app_handle).paused
+    }
+
+    #[zbus(property)]
+    async fn peer_count(&self) -> u32 {
+        snapshot(&self.app_handle).peer_count()
+    }
+
+    #[zbus(property)]
+    async fn unread_count(&self) -> u32 {
+        snapshot(&self.app_handle).unread_count
+    }
+
+    #[zbus(property)]
+    async fn pending_count(&self) -> u32 {
+        snapshot(&self.app_handle).pending_count
+    }
+
+    /// ListPeers would return something different.
+    #[zbus(signal)]
+    async fn peers_changed(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    /// A new item was sent or received.
+    #[zbus(signal)]
+    async fn latest_item_changed(ctxt: &SignalContext<'_>, id: &str) -> zbus::Result<()>;
+}
+
+/// The GNOME Shell extension's interface.
 pub struct ClusterCutDBus {
     app_handle: tauri::AppHandle,
 }
@@ -16,30 +255,11 @@ impl ClusterCutDBus {
 #[interface(name = "com.keithvassallo.clustercut")]
 impl ClusterCutDBus {
     async fn toggle_auto_send(&mut self) -> bool {
-        let state = self.app_handle.state::<AppState>();
-        let mut settings = state.settings.lock().unwrap();
-        settings.auto_send = !settings.auto_send;
-        crate::storage::save_settings(&self.app_handle, &settings);
-        let _ = self.app_handle.emit("settings-changed", settings.clone());
-
-        // Notify Tray if applicable
-        #[cfg(desktop)]
-        crate::tray::update_tray_menu(&self.app_handle);
-
-        settings.auto_send
+        update_settings(&self.app_handle, |s| s.auto_send = !s.auto_send).auto_send
     }
 
     async fn toggle_auto_receive(&mut self) -> bool {
-        let state = self.app_handle.state::<AppState>();
-        let mut settings = state.settings.lock().unwrap();
-        settings.auto_receive = !settings.auto_receive;
-        crate::storage::save_settings(&self.app_handle, &settings);
-        let _ = self.app_handle.emit("settings-changed", settings.clone());
-
-        #[cfg(desktop)]
-        crate::tray::update_tray_menu(&self.app_handle);
-
-        settings.auto_receive
+        update_settings(&self.app_handle, |s| s.auto_receive = !s.auto_receive).auto_receive
     }
 
     async fn get_state(&self) -> (bool, bool) {
@@ -48,18 +268,61 @@ impl ClusterCutDBus {
         (settings.auto_send, settings.auto_receive)
     }
 
+    /// Stop (or resume) sending and receiving altogether.
+    async fn pause_sync(&mut self, paused: bool) {
+        pause_sync(&self.app_handle, paused);
+    }
+
+    /// Send the current clipboard now, like the send shortcut.
+    async fn send_clipboard(&self) {
+        send_clipboard(&self.app_handle).await;
+    }
+
+    /// Apply the most recent pending item. False if nothing was waiting.
+    async fn apply_pending(&self) -> bool {
+        apply_pending(&self.app_handle)
+    }
+
     async fn show_window(&self) {
-        if let Some(window) = self.app_handle.get_webview_window("main") {
-            let _ = window.show();
-            let _ = window.set_focus();
-            crate::tray::set_badge(&self.app_handle, false);
-        }
+        crate::show_main_window(&self.app_handle);
     }
 
     async fn quit(&self) {
         self.app_handle.exit(0);
     }
 
+    #[zbus(property)]
+    async fn auto_send(&self) -> bool {
+        snapshot(&self.app_handle).auto_send
+    }
+
+    #[zbus(property)]
+    async fn auto_receive(&self) -> bool {
+        snapshot(&self.app_handle).auto_receive
+    }
+
+    #[zbus(property)]
+    async fn paused(&self) -> bool {
+        snapshot(&self.app_handle).paused
+    }
+
+    /// Trusted devices that aren't offline.
+    #[zbus(property)]
+    async fn peer_count(&self) -> u32 {
+        snapshot(&self.app_handle).peer_count()
+    }
+
+    #[zbus(property)]
+    async fn unread_count(&self) -> u32 {
+        snapshot(&self.app_handle).unread_count
+    }
+
+    /// Received items waiting to be applied (auto-receive off or screen locked).
+    #[zbus(property)]
+    async fn pending_count(&self) -> u32 {
+        snapshot(&self.app_handle).pending_count
+    }
+
     #[zbus(signal)]
     pub async fn state_changed(
         ctxt: &SignalContext<'_>,
@@ -69,17 +332,19 @@ impl ClusterCutDBus {
 }
 
 pub async fn start_dbus_server(app_handle: tauri::AppHandle) -> zbus::Result<()> {
-    let service = ClusterCutDBus::new(app_handle.clone());
     let conn = zbus::connection::Builder::session()?
-        .name("com.keithvassallo.clustercut")?
-        .serve_at("/org/gnome/Shell/Extensions/ClusterCut", service)?
+        .name(GNOME_NAME)?
+        .name(CONTROL_NAME)?
+        .serve_at(GNOME_PATH, ClusterCutDBus::new(app_handle.clone()))?
+        .serve_at(CONTROL_PATH, Control { app_handle: app_handle.clone() })?
         .build()
         .await?;
 
     // Clone for the closure
     let dbus_conn = conn.clone();
 
-    // Listen for internal settings changes
+    // Listen for internal settings changes (StateChanged predates the properties; kept for
+    // older extensions)
     app_handle.listen("settings-changed", move |event: tauri::Event| {
         if let Ok(payload) = serde_json::from_str::<crate::storage::AppSettings>(event.payload()) {
             let conn = dbus_conn.clone();
@@ -88,8 +353,8 @@ pub async fn start_dbus_server(app_handle: tauri::AppHandle) -> zbus::Result<()>
                 let _ = conn
                     .emit_signal(
                         Option::<&str>::None, // destination (broadcast)
-                        "/org/gnome/Shell/Extensions/ClusterCut",
-                        "com.keithvassallo.clustercut",
+                        GNOME_PATH,
+                        GNOME_NAME,
                         "StateChanged",
                         &(payload.auto_send, payload.auto_receive),
                     )
@@ -98,7 +363,79 @@ pub async fn start_dbus_server(app_handle: tauri::AppHandle) -> zbus::Result<()>
         }
     });
 
-    // Keep connection alive
-    std::future::pending::<()>().await;
-    Ok(())
+    // Bursts of events collapse into one check
+    let changed = Arc::new(tokio::sync::Notify::new());
+    for event in WATCHED_EVENTS {
+        let changed = changed.clone();
+        app_handle.listen(*event, move |_| changed.notify_one());
+    }
+
+    let gnome_ref = conn.object_server().interface::<_, ClusterCutDBus>(GNOME_PATH).await?;
+    let control_ref = conn.object_server().interface::<_, Control>(CONTROL_PATH).await?;
+    let mut last = snapshot(&app_handle);
+    // Keeps the connection alive
+    loop {
+        changed.notified().await;
+        let now = snapshot(&app_handle);
+        if now == last {
+            continue;
+        }
+        let gnome_ctxt = gnome_ref.signal_context();
+        let gnome = gnome_ref.get().await;
+        let control_ctxt = control_ref.signal_context();
+        let control = control_ref.get().await;
+        if now.auto_send != last.auto_send {
+            let _ = gnome.auto_send_changed(gnome_ctxt).await;
+            let _ = control.auto_send_changed(control_ctxt).await;
+        }
+        if now.auto_receive != last.auto_receive {
+            let _ = gnome.auto_receive_changed(gnome_ctxt).await;
+            let _ = control.auto_receive_changed(control_ctxt).await;
+        }
+        if now.paused != last.paused {
+            let _ = gnome.paused_changed(gnome_ctxt).await;
+            let _ = control.paused_changed(control_ctxt).await;
+        }
+        if now.peer_count() != last.peer_count() {
+            let _ = gnome.peer_count_changed(gnome_ctxt).await;
+            let _ = control.peer_count_changed(control_ctxt).await;
+        }
+        if now.unread_count != last.unread_count {
+            let _ = gnome.unread_count_changed(gnome_ctxt).await;
+            let _ = control.unread_count_changed(control_ctxt).await;
+        }
+        if now.pending_count != last.pending_count {
+            let _ = gnome.pending_count_changed(gnome_ctxt).await;
+            let _ = control.pending_count_changed(control_ctxt).await;
+        }
+        if now.peers != last.peers {
+            let _ = Control::peers_changed(control_ctxt).await;
+        }
+        if now.latest_item != last.latest_item {
+            if let Some(id) = &now.latest_item {
+                let _ = Control::latest_item_changed(control_ctxt, id).await;
+            }
+        }
+        last = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_unlocked_while_publishing() {
+        let state = AppState::new();
+        for expected in [false, true] {
+            let settings = change_settings(
+                &state,
+                |s| s.auto_send = !s.auto_send,
+                // What update_tray_menu does; would deadlock if the guard were still held
+                |_| assert!(state.settings.try_lock().is_ok()),
+            );
+            assert_eq!(settings.auto_send, expected);
+            assert_eq!(state.settings.lock().unwrap().auto_send, expected);
+        }
+    }
 }
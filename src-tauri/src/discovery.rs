@@ -1,12 +1,37 @@
 use local_ip_address::local_ip;
-use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
 use std::error::Error;
 
 pub const SERVICE_TYPE: &str = "_clustercut._tcp.local.";
+const MAX_NAMESPACE_LEN: usize = 32;
 
 pub struct Discovery {
     daemon: ServiceDaemon,
     registered_service: Option<String>, // Stores fullname of registered service
+    instance_tag: Option<String>,       // Dev instance number (--instance)
+    advertise_ip: Option<std::net::IpAddr>, // Set when restricted to certain interfaces
+    preferred_ip: Option<std::net::IpAddr>, // Where peers actually reach us (see ObservedAddress)
+    namespace: Option<String>,              // Discovery namespace (`discovery_namespace` setting)
+}
+
+/// Clean up a `discovery_namespace` setting: lowercase letters, digits and '-' only.
+/// None is the shared default namespace (what versions without namespaces use).
+pub fn normalize_namespace(value: &str) -> Option<String> {
+    let ns: String = value
+        .trim()
+        .to_ascii_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .take(MAX_NAMESPACE_LEN)
+        .collect();
+    let ns = ns.trim_matches('-').to_string();
+    (!ns.is_empty()).then_some(ns)
+}
+
+/// Whether a resolved service's `ns` property puts it in our namespace. Services without one
+/// are in the default namespace.
+pub fn in_namespace(advertised: Option<&str>, ours: Option<&str>) -> bool {
+    advertised.filter(|ns| !ns.is_empty()) == ours
 }
 
 impl Discovery {
@@ -15,9 +40,31 @@ impl Discovery {
         Ok(Self {
             daemon,
             registered_service: None,
+            instance_tag: None,
+            advertise_ip: None,
+            preferred_ip: None,
+            namespace: None,
         })
     }
 
+    /// Only see (and be seen by) devices in the same namespace, so separate groups sharing a
+    /// LAN don't fill each other's device lists. Returns whether it changed; re-register for
+    /// it to take effect.
+    pub fn set_namespace(&mut self, namespace: Option<String>) -> bool {
+        let changed = self.namespace != namespace;
+        self.namespace = namespace;
+        changed
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Tag registrations with a dev instance number so local instances are distinguishable.
+    pub fn set_instance_tag(&mut self, instance: Option<u16>) {
+        self.instance_tag = instance.map(|n| n.to_string());
+    }
+
     pub fn register(
         &mut self,
         device_id: &str,
@@ -33,7 +80,10 @@ impl Discovery {
         }
 
         // Get the local IP address
-        let ip = local_ip()?;
+        let ip = match self.advertise_ip.or(self.preferred_ip) {
+            Some(ip) => ip,
+            None => local_ip()?,
+        };
 
         // Hostname usually needs to be unique on the network, but we'll base it on device ID for now.
         // Format: device_id.local.
@@ -44,19 +94,33 @@ impl Discovery {
             .map(|h| h.to_string_lossy().to_string())
             .unwrap_or_else(|_| "Unknown Device".to_string());
 
+        // Dev instances share a hostname; suffix it so the UI can tell them apart
+        let system_hostname = match &self.instance_tag {
+            Some(n) => format!("{} #{}", system_hostname, n),
+            None => system_hostname,
+        };
+
         // Properties can be used to send public key fingerprint or other metadata
-        let properties = [
-            ("version", "0.1.0"),
+        let platform = crate::platform::txt_properties();
+        let mut properties = vec![
             ("id", device_id),
             ("n", network_name),     // n = network name
             ("h", &system_hostname), // h = visible hostname
         ];
+        if let Some(n) = &self.instance_tag {
+            properties.push(("i", n.as_str())); // i = dev instance number
+        }
+        if let Some(ns) = &self.namespace {
+            properties.push(("ns", ns.as_str())); // ns = discovery namespace
+        }
+        // os, osv, arch, version, proto (see platform.rs)
+        properties.extend(platform.iter().map(|(k, v)| (*k, v.as_str())));
 
         let service_info = ServiceInfo::new(
             SERVICE_TYPE,
             device_id,
             &m_hostname,
-            &ip.to_string(),
+            ip.to_string(),
             port,
             &properties[..],
         )?;
@@ -78,6 +142,62 @@ impl Discovery {
         Ok(())
     }
 
+    /// Shut the daemon down and start a fresh one (after a profile switch), keeping the instance
+    /// tag and interface restriction. The old browse ends; browse again on the new daemon.
+    pub fn restart(&mut self) -> Result<(), Box<dyn Error>> {
+        self.unregister();
+        let daemon = ServiceDaemon::new()?;
+        let old = std::mem::replace(&mut self.daemon, daemon);
+        if let Err(e) = old.shutdown() {
+            tracing::warn!("Failed to shut down the old mDNS daemon: {}", e);
+        }
+        self.preferred_ip = None;
+        Ok(())
+    }
+
+    /// Withdraw the registered service (the daemon and any browse keep running).
+    pub fn unregister(&mut self) {
+        if let Some(fullname) = self.registered_service.take() {
+            tracing::info!("Unregistering service: {}", fullname);
+            if let Err(e) = self.daemon.unregister(&fullname) {
+                tracing::error!("Failed to unregister service: {}", e);
+            }
+        }
+    }
+
+    /// How often the daemon re-checks network interfaces (backed off in low-power mode).
+    pub fn set_ip_check_interval(&self, secs: u32) {
+        if let Err(e) = self.daemon.set_ip_check_interval(secs) {
+            tracing::warn!("Failed to set mDNS interface check interval: {}", e);
+        }
+    }
+
+    /// Advertise `ip` instead of the default-route address (an interface restriction still wins).
+    /// Returns whether it changed; re-register for it to take effect.
+    pub fn set_preferred_ip(&mut self, ip: Option<std::net::IpAddr>) -> bool {
+        let changed = self.preferred_ip != ip;
+        self.preferred_ip = ip;
+        changed
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        self.advertise_ip.is_some()
+    }
+
+    /// Limit mDNS to the given interfaces and advertise `advertise_ip` (None = all interfaces / default route address).
+    pub fn restrict_interfaces(&mut self, interfaces: Option<Vec<String>>, advertise_ip: Option<std::net::IpAddr>) {
+        self.advertise_ip = advertise_ip;
+        let result = match interfaces {
+            Some(names) => self.daemon.disable_interface(IfKind::All).and_then(|_| {
+                self.daemon.enable_interface(names.into_iter().map(IfKind::Name).collect::<Vec<_>>())
+            }),
+            None => self.daemon.enable_interface(IfKind::All),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to update mDNS interfaces: {}", e);
+        }
+    }
+
     pub fn browse(&self) -> Result<mdns_sd::Receiver<ServiceEvent>, Box<dyn Error>> {
         let receiver = self.daemon.browse(SERVICE_TYPE)?;
         Ok(receiver)
@@ -0,0 +1,233 @@
+//! Clipboard sync dry run (`dry_run_sync`), for checking a new cluster setup safely.
+//!
+//! A test payload is built, sealed per peer and sent exactly like a real clipboard item
+//! (`clipboard::data_for_peer`, same transport), but inside `Message::DryRun` rather than
+//! `Message::Clipboard`. Receivers decrypt it and run it past the same checks a real item goes
+//! through (blocked sender, sender authentication, pending reset, timestamp, channel, auto-receive, screen lock, file
+//! limits, relaying), log what they would have done and answer with a `DryRunReceipt`. Nothing
+//! is applied, added to history or relayed. Versions without dry runs can't parse the message
+//! and drop it, so they show up as "no receipt" rather than applying the test text.
+
+use crate::protocol::{ClipboardPayload, DryRunReceipt, Message};
+use crate::state::AppState;
+use crate::transport::Transport;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_TEXT: &str = "ClusterCut dry run";
+// Same freshness window as real clipboard messages
+const MAX_AGE_SECS: u64 = 120;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PeerResult {
+    pub peer_id: String,
+    pub hostname: String,
+    pub addr: String,
+    pub ok: bool,
+    pub round_trip_ms: Option<u64>,
+    pub error: Option<String>,
+    pub receipt: Option<DryRunReceipt>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DryRunReport {
+    pub id: String,
+    pub peers: Vec<PeerResult>,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn receipt_key(payload_id: &str, device_id: &str) -> String {
+    format!("{}:{}", payload_id, device_id)
+}
+
+// Re-wrap a peer's sealed Message::Clipboard as a dry run
+fn as_dry_run(data: &[u8]) -> Option<Vec<u8>> {
+    match serde_json::from_slice::<Message>(data).ok()? {
+        Message::Clipboard(cipher) => serde_json::to_vec(&Message::DryRun(cipher)).ok(),
+        _ => None,
+    }
+}
+
+async fn send_to(state: &AppState, transport: &Transport, peer: crate::peer::Peer, payload_id: String, data: Option<Vec<u8>>) -> PeerResult {
+    let addr = std::net::SocketAddr::new(peer.ip, peer.port);
+    let mut result = PeerResult {
+        peer_id: peer.id.clone(),
+        hostname: peer.hostname.clone(),
+        addr: addr.to_string(),
+        ok: false,
+        round_trip_ms: None,
+        error: None,
+        receipt: None,
+    };
+    let Some(data) = data else {
+        result.error = Some("Nothing to send to this device (guest or unsupported cipher)".to_string());
+        return result;
+    };
+
+    let key = receipt_key(&payload_id, &peer.id);
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state.pending_dry_runs.lock().unwrap().insert(key.clone(), tx);
+    let started = Instant::now();
+    // send_message lingers after writing; the receipt may arrive before it returns
+    let sender = transport.clone();
+    let send = tauri::async_runtime::spawn(async move { sender.send_message(addr, &data).await.map_err(|e| e.to_string()) });
+
+    let receipt = tokio::time::timeout(RECEIPT_TIMEOUT, rx).await;
+    state.pending_dry_runs.lock().unwrap().remove(&key);
+    match receipt {
+        Ok(Ok(receipt)) => {
+            result.round_trip_ms = Some(started.elapsed().as_millis() as u64);
+            result.ok = true;
+            result.receipt = Some(receipt);
+        }
+        _ => {
+            result.error = Some(match send.await {
+                Ok(Err(e)) => format!("Send failed: {}", e),
+                _ => format!("No receipt within {}s (device may run an older version)", RECEIPT_TIMEOUT.as_secs()),
+            });
+        }
+    }
+    result
+}
+
+/// Send a dry-run payload to every member and collect their receipts.
+pub async fn run(state: &AppState, transport: &Transport, text: Option<String>) -> Result<DryRunReport, String> {
+    if state.guest_expires_at.lock().unwrap().is_some() {
+        return Err("Dry runs aren't available on guest devices".to_string());
+    }
+    let key = state.cluster_key.lock().unwrap().clone().filter(|k| k.len() == 32).ok_or("No Cluster Key set")?;
+    let mut key_arr = [0u8; 32];
+    key_arr.copy_from_slice(&key);
+
+    let channel = state.settings.lock().unwrap().send_channel.clone();
+    let mut payload = ClipboardPayload {
+        id: uuid::Uuid::new_v4().to_string(),
+        text: text.filter(|t| !t.is_empty()).unwrap_or_else(|| DEFAULT_TEXT.to_string()),
+        files: None,
+        timestamp: now(),
+        sender: crate::get_hostname_internal(),
+        sender_id: state.local_device_id.lock().unwrap().clone(),
+        source: None,
+        trace: None,
+        channel,
+        link: None,
+        content: None,
+        auth: None,
+    };
+    crate::senderauth::sign(state, &mut payload);
+    let payload_bytes = serde_json::to_vec(&payload).map_err(|e| e.to_string())?;
+    let cipher = crate::crypto::encrypt(&key_arr, &payload_bytes).map_err(|e| e.to_string())?;
+    let member_data = serde_json::to_vec(&Message::Clipboard(cipher)).map_err(|e| e.to_string())?;
+
+    let peers: Vec<_> = state.get_peers().into_values().filter(|p| p.guest_expires_at.is_none()).collect();
+    tracing::info!("[DryRun] {} to {} devices on channel '{}'", payload.id, peers.len(), payload.channel);
+    let sends = peers.into_iter().map(|peer| {
+        let data = crate::clipboard::data_for_peer(state, &key_arr, &peer, &payload_bytes, &member_data).and_then(|d| as_dry_run(&d));
+        send_to(state, transport, peer, payload.id.clone(), data)
+    });
+    let results = futures::future::join_all(sends).await;
+    for r in &results {
+        match &r.receipt {
+            Some(receipt) => tracing::info!("[DryRun] {} ({}): receipt in {:?} ms, would apply: {}", r.hostname, r.addr, r.round_trip_ms, receipt.would_apply),
+            None => tracing::warn!("[DryRun] {} ({}): {}", r.hostname, r.addr, r.error.as_deref().unwrap_or("failed")),
+        }
+    }
+    Ok(DryRunReport { id: payload.id, peers: results })
+}
+
+// What a real clipboard item with this payload, from `ip`, would go through here
+fn evaluate(state: &AppState, ip: std::net::IpAddr, payload: &ClipboardPayload) -> (Vec<String>, bool) {
+    let mut steps = Vec::new();
+    if state.is_blocked(&payload.sender_id) {
+        steps.push("Sender is blocked: would drop".to_string());
+        return (steps, false);
+    }
+    if let Err(reason) = crate::senderauth::check_clipboard(state, ip, payload) {
+        steps.push(format!("Sender authentication failed ({}): would drop", reason));
+        return (steps, false);
+    }
+    if state.is_reset_pending() {
+        steps.push("Reset pending: would ignore".to_string());
+        return (steps, false);
+    }
+    let age = now().abs_diff(payload.timestamp);
+    if age > MAX_AGE_SECS {
+        steps.push(format!("Timestamp is {}s off (limit {}s): would drop as stale, check the clocks", age, MAX_AGE_SECS));
+        return (steps, false);
+    }
+    steps.push(format!("Timestamp within {}s", age));
+
+    let settings = state.settings.lock().unwrap().clone();
+    if !settings.subscribed_channels.contains(&payload.channel) {
+        steps.push(format!("Not subscribed to channel '{}': would only relay it, not apply it", payload.channel));
+        return (steps, false);
+    }
+    steps.push(format!("Subscribed to channel '{}'", payload.channel));
+
+    let locked = crate::screenlock::sync_paused(state);
+    let mut would_apply = false;
+    if let Some(files) = payload.files.as_ref().filter(|f| !f.is_empty()) {
+        let total: u64 = files.iter().map(|f| f.size).sum();
+        if !settings.enable_file_transfer {
+            steps.push(format!("{} files: file transfer is disabled", files.len()));
+        } else if settings.auto_receive && !locked && total <= settings.max_auto_download_size {
+            steps.push(format!("{} files ({} bytes): would download automatically", files.len(), total));
+            would_apply = true;
+        } else {
+            steps.push(format!("{} files ({} bytes): would offer for manual download", files.len(), total));
+        }
+    }
+    if !payload.text.is_empty() {
+        if locked {
+            steps.push("Screen locked: would hold the text for review".to_string());
+        } else if !settings.auto_receive {
+            steps.push("Auto-receive off: would hold the text for review".to_string());
+        } else {
+            steps.push(format!("Would apply {} characters to the clipboard", payload.text.chars().count()));
+            would_apply = true;
+        }
+    }
+
+    if settings.auto_send && !locked {
+        let others = state.get_peers().len().saturating_sub(1);
+        steps.push(format!("Would relay to {} other devices", others));
+    } else {
+        steps.push("Would not relay (auto-send off or screen locked)".to_string());
+    }
+    (steps, would_apply)
+}
+
+/// Check a dry-run payload from another member and send back a receipt. Never applies it.
+pub async fn answer(state: &AppState, transport: &Transport, addr: std::net::SocketAddr, payload: ClipboardPayload) {
+    let (steps, would_apply) = evaluate(state, addr.ip(), &payload);
+    tracing::info!("[DryRun] {} from {} ({}) via {}:", payload.id, payload.sender, payload.sender_id, addr);
+    for step in &steps {
+        tracing::info!("[DryRun]   {}", step);
+    }
+    let receipt = DryRunReceipt {
+        id: payload.id,
+        device_id: state.local_device_id.lock().unwrap().clone(),
+        hostname: crate::get_hostname_internal(),
+        steps,
+        would_apply,
+    };
+    let Some(cipher) = crate::encrypt_cluster_message(state, &receipt) else {
+        return;
+    };
+    if let Ok(data) = serde_json::to_vec(&Message::DryRunReceipt(cipher)) {
+        if let Err(e) = transport.send_message(addr, &data).await {
+            tracing::debug!("[DryRun] Failed to send receipt to {}: {}", addr, e);
+        }
+    }
+}
+
+/// Hand a receipt to the dry run waiting for it.
+pub fn deliver(state: &AppState, receipt: DryRunReceipt) {
+    if let Some(tx) = state.pending_dry_runs.lock().unwrap().remove(&receipt_key(&receipt.id, &receipt.device_id)) {
+        let _ = tx.send(receipt);
+    }
+}
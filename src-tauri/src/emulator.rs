@@ -0,0 +1,453 @@
+//! Headless test peer for QA (`clustercut-peer`, built with `--features emulator`).
+//!
+//! Speaks the real wire protocol with no window, tray or clipboard: it announces itself over
+//! mDNS, owns a cluster of its own that devices join with its PIN, heartbeats like any member,
+//! echoes every clipboard item back to whoever sent it and can offer a test file that it then
+//! serves over a file stream. Point a normal install at it to check pairing, sync and transfers
+//! without a second machine:
+//!
+//!     cargo run --features emulator --bin clustercut-peer -- --pin EMU123 --offer-file
+//!
+//! It keeps nothing on disk; every run is a fresh cluster.
+
+use crate::crypto::{self, Confirm};
+use crate::peer::Peer;
+use crate::protocol::{ClipboardPayload, FileMetadata, FileRequestPayload, FileStreamHeader, Message};
+use crate::state::{AppState, PendingConfirmation};
+use crate::transport::{Delivery, Transport};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use clap::Parser;
+use rand::RngCore;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+// Generated when --offer-file is given without a path
+const TEST_FILE_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Parser, Debug, Clone)]
+#[command(name = "clustercut-peer", about = "Headless ClusterCut peer for testing")]
+pub struct EmulatorArgs {
+    /// QUIC port to listen on (pick a free one when running next to the app)
+    #[arg(long, default_value_t = 4655)]
+    pub port: u16,
+    /// Network name to advertise
+    #[arg(long, default_value = "Emulator")]
+    pub network_name: String,
+    /// PIN devices join with
+    #[arg(long, default_value = "EMU123")]
+    pub pin: String,
+    /// Discovery namespace (see `discovery_namespace`)
+    #[arg(long)]
+    pub namespace: Option<String>,
+    /// Don't echo received clipboard items back
+    #[arg(long)]
+    pub no_echo: bool,
+    /// Offer a file to each device that joins; without a value a random 4 MB file is generated
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    pub offer_file: Option<String>,
+    /// Seconds to wait after a device joins before offering the file
+    #[arg(long, default_value_t = 3)]
+    pub offer_delay: u64,
+}
+
+struct Emulator {
+    state: AppState,
+    transport: Transport,
+    args: EmulatorArgs,
+    // Payload id and path of the file on offer
+    offered: Mutex<Option<(String, PathBuf)>>,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn cluster_key(state: &AppState) -> Option<[u8; 32]> {
+    let key = state.cluster_key.lock().unwrap().clone().filter(|k| k.len() == 32)?;
+    let mut key_arr = [0u8; 32];
+    key_arr.copy_from_slice(&key);
+    Some(key_arr)
+}
+
+/// Entry point of the `clustercut-peer` binary.
+pub fn main() {
+    let args = EmulatorArgs::parse();
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+
+    if let Err(e) = tauri::async_runtime::block_on(run(args)) {
+        eprintln!("clustercut-peer: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: EmulatorArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let state = AppState::new();
+    let device_id = format!("emulator-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    *state.local_device_id.lock().unwrap() = device_id.clone();
+    *state.cluster_key.lock().unwrap() = Some(key.to_vec());
+    // A throwaway signing key, like the cluster key: each run is a new device
+    *state.device_key.lock().unwrap() = Some(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng));
+    *state.cluster_owner.lock().unwrap() = Some(device_id.clone());
+    *state.network_name.lock().unwrap() = args.network_name.clone();
+    *state.network_pin.lock().unwrap() = args.pin.clone();
+    // No owner approval: whoever knows the PIN is welcomed straight away
+    state.settings.lock().unwrap().require_owner_approval = false;
+
+    let transport = Transport::new(args.port)?;
+    state.set_bound_port(args.port);
+
+    let mut discovery = crate::discovery::Discovery::new()?;
+    discovery.set_instance_tag(Some(args.port));
+    discovery.set_namespace(args.namespace.as_deref().and_then(crate::discovery::normalize_namespace));
+    discovery.register(&device_id, &args.network_name, args.port)?;
+    *state.discovery.lock().unwrap() = Some(discovery);
+
+    let emulator = Arc::new(Emulator { state, transport: transport.clone(), args, offered: Mutex::new(None) });
+    if let Some(path) = emulator.args.offer_file.as_deref() {
+        let path = if path.is_empty() { generate_test_file()? } else { PathBuf::from(path) };
+        if !path.is_file() {
+            return Err(format!("{} is not a file", path.display()).into());
+        }
+        *emulator.offered.lock().unwrap() = Some((uuid::Uuid::new_v4().to_string(), path));
+    }
+
+    let on_message = {
+        let emulator = emulator.clone();
+        move |data: crate::spool::Inbound, addr: SocketAddr| {
+            let emulator = emulator.clone();
+            tauri::async_runtime::spawn(async move { emulator.handle(data, addr).await });
+        }
+    };
+    // Nothing is ever requested from peers, so incoming file streams are just dropped
+    transport.start_listening(on_message, |_recv, addr| tracing::warn!("Ignoring file stream from {}", addr));
+
+    let heartbeat = emulator.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            heartbeat.heartbeat().await;
+        }
+    });
+
+    println!(
+        "clustercut-peer {} listening on port {}: network '{}', PIN {}",
+        device_id, emulator.args.port, emulator.args.network_name, emulator.args.pin
+    );
+    tokio::signal::ctrl_c().await?;
+    if let Some(discovery) = emulator.state.discovery.lock().unwrap().as_mut() {
+        discovery.unregister();
+    }
+    Ok(())
+}
+
+fn generate_test_file() -> std::io::Result<PathBuf> {
+    let mut data = vec![0u8; TEST_FILE_SIZE];
+    rand::thread_rng().fill_bytes(&mut data);
+    let path = std::env::temp_dir().join("clustercut-peer-test.bin");
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+impl Emulator {
+    fn local_id(&self) -> String {
+        self.state.local_device_id.lock().unwrap().clone()
+    }
+
+    async fn send(&self, delivery: Delivery, addr: SocketAddr, msg: &Message) {
+        let Ok(data) = serde_json::to_vec(msg) else { return };
+        if let Err(e) = self.transport.send_with(delivery, addr, &data).await {
+            tracing::warn!("Send to {} failed: {}", addr, e);
+        }
+    }
+
+    async fn heartbeat(&self) {
+        let me = Message::PeerDiscovery(crate::local_peer_info(&self.state, &self.transport));
+        let targets: Vec<SocketAddr> =
+            self.state.peers.lock().unwrap().values().map(|p| SocketAddr::new(p.ip, p.port)).collect();
+        for addr in targets {
+            self.send(Delivery::BestEffort, addr, &me).await;
+        }
+    }
+
+    async fn handle(&self, data: crate::spool::Inbound, addr: SocketAddr) {
+        let msg = match data.parse::<Message>() {
+            Ok(msg) => msg,
+            Err(e) => {
+                tracing::warn!("Unparseable message from {}: {}", addr, e);
+                return;
+            }
+        };
+        match msg {
+            Message::PairRequest { msg, device_id, kdf, .. } => self.on_pair_request(addr, msg, device_id, kdf).await,
+            Message::PairConfirm { device_id, confirmation, device_key } => {
+                self.on_pair_confirm(addr, device_id, confirmation, device_key).await
+            }
+            Message::PeerDiscovery(peer) => self.on_discovery(addr, peer).await,
+            Message::Clipboard(ciphertext) => {
+                if let Some(payload) = crate::decrypt_cluster_message::<ClipboardPayload>(&self.state, &ciphertext) {
+                    self.on_clipboard(addr, payload).await;
+                }
+            }
+            Message::FileRequest(ciphertext) => {
+                if let Some(req) = crate::decrypt_cluster_message::<FileRequestPayload>(&self.state, &ciphertext) {
+                    self.on_file_request(addr, req).await;
+                }
+            }
+            Message::Probe(ciphertext) => {
+                if let Some(probe) = crate::decrypt_cluster_message(&self.state, &ciphertext) {
+                    crate::conntest::answer(&self.state, &self.transport, addr, probe).await;
+                }
+            }
+            Message::DryRun(ciphertext) => {
+                if let Some(payload) = crate::decrypt_cluster_message(&self.state, &ciphertext) {
+                    crate::dryrun::answer(&self.state, &self.transport, addr, payload).await;
+                }
+            }
+            Message::PeerRemoval(id) => {
+                tracing::info!("{} left", id);
+                self.state.peers.lock().unwrap().remove(&id);
+            }
+            other => tracing::debug!("Ignoring {:?} from {}", std::mem::discriminant(&other), addr),
+        }
+    }
+
+    async fn on_pair_request(&self, addr: SocketAddr, msg: Vec<u8>, device_id: String, kdf: u8) {
+        if kdf != crypto::PAIRING_KDF_VERSION {
+            tracing::warn!("Rejecting PairRequest from {}: key derivation v{}", device_id, kdf);
+            return;
+        }
+        let local_id = self.local_id();
+        let keys = crypto::start_spake2(&self.args.pin, &self.args.network_name)
+            .and_then(|(spake, response)| Ok((crypto::finish_spake2(spake, &msg)?, response)));
+        let (session_keys, response) = match keys {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!("Pairing with {} failed: {}", device_id, e);
+                return;
+            }
+        };
+        tracing::info!("PairRequest from {} ({})", device_id, addr);
+        let reply = Message::PairResponse {
+            msg: response,
+            device_id: local_id.clone(),
+            confirmation: crypto::confirmation_tag(&session_keys, Confirm::Responder(&local_id)),
+        };
+        crate::handshake::insert(
+            &self.state.pending_confirmations,
+            &addr.to_string(),
+            PendingConfirmation { device_id, session_keys },
+        );
+        self.send(Delivery::Reliable, addr, &reply).await;
+    }
+
+    async fn on_pair_confirm(&self, addr: SocketAddr, device_id: String, confirmation: Vec<u8>, device_key: Vec<u8>) {
+        let pending = crate::handshake::take(&self.state.pending_confirmations, &addr.to_string());
+        let Some(pending) = pending.filter(|p| p.device_id == device_id) else {
+            tracing::warn!("PairConfirm from {} with no handshake in progress", addr);
+            return;
+        };
+        if !crypto::verify_confirmation(&pending.session_keys, Confirm::Initiator(&device_id), &confirmation) {
+            tracing::warn!("{} used a different PIN", device_id);
+            return;
+        }
+        let Some(key) = cluster_key(&self.state) else { return };
+        let Ok(encrypted_cluster_key) = crypto::encrypt(&pending.session_keys.encryption, &key) else { return };
+        let welcome = Message::Welcome {
+            encrypted_cluster_key,
+            known_peers: vec![],
+            network_name: self.args.network_name.clone(),
+            network_pin: self.args.pin.clone(),
+            owner_id: Some(self.local_id()),
+            guest_expires_at: None,
+            key_confirmation: crypto::confirmation_tag(&pending.session_keys, Confirm::Welcome(&device_id)),
+            device_key: crate::identity::seal(&self.state, &pending.session_keys),
+        };
+        self.send(Delivery::Reliable, addr, &welcome).await;
+        tracing::info!("{} joined from {}", device_id, addr);
+
+        self.state.add_peer(Peer {
+            id: device_id.clone(),
+            ip: addr.ip(),
+            port: addr.port(),
+            hostname: device_id,
+            last_seen: now(),
+            is_trusted: true,
+            is_manual: false,
+            network_name: Some(self.args.network_name.clone()),
+            signature: None,
+            guest_expires_at: None,
+            capabilities: None,
+            platform: None,
+            addresses: vec![],
+            observed_addr: None,
+            public_key: crate::identity::open(&pending.session_keys, &device_key),
+        });
+        self.send(Delivery::Reliable, addr, &Message::PeerDiscovery(crate::local_peer_info(&self.state, &self.transport)))
+            .await;
+
+        if self.offered.lock().unwrap().is_some() {
+            tokio::time::sleep(Duration::from_secs(self.args.offer_delay)).await;
+            self.offer_file(addr).await;
+        }
+    }
+
+    async fn on_discovery(&self, addr: SocketAddr, mut peer: Peer) {
+        let Some(key) = cluster_key(&self.state) else { return };
+        let signed = peer.signature.as_deref().is_some_and(|sig| crate::verify_signature(&key, &peer.id, sig));
+        if !signed {
+            tracing::debug!("Unsigned PeerDiscovery from {} ({})", peer.id, addr);
+            return;
+        }
+        let known = self.state.peers.lock().unwrap().contains_key(&peer.id);
+        peer.ip = addr.ip();
+        peer.port = addr.port();
+        peer.reset_address_history();
+        peer.is_trusted = true;
+        peer.last_seen = now();
+        peer.observed_addr = None;
+        self.state.add_peer(peer);
+        if !known {
+            let me = Message::PeerDiscovery(crate::local_peer_info(&self.state, &self.transport));
+            self.send(Delivery::BestEffort, addr, &me).await;
+        }
+    }
+
+    async fn on_clipboard(&self, addr: SocketAddr, payload: ClipboardPayload) {
+        let local_id = self.local_id();
+        if payload.sender_id == local_id {
+            return;
+        }
+        let files = payload.files.as_ref().map_or(0, |f| f.len());
+        tracing::info!(
+            "Clipboard from {} ({}): {} chars, {} file(s), channel '{}'",
+            payload.sender,
+            addr,
+            payload.text.chars().count(),
+            files,
+            payload.channel
+        );
+        // Only text is echoed; the files were never here to serve
+        if self.args.no_echo || payload.text.is_empty() {
+            return;
+        }
+        let mut echo = ClipboardPayload {
+            id: uuid::Uuid::new_v4().to_string(),
+            text: payload.text,
+            files: None,
+            timestamp: now(),
+            sender: format!("{} (echo)", local_id),
+            sender_id: local_id,
+            source: None,
+            trace: None,
+            channel: payload.channel,
+            link: None,
+            content: None,
+            auth: None,
+        };
+        crate::senderauth::sign(&self.state, &mut echo);
+        if let Some(ciphertext) = crate::encrypt_cluster_message(&self.state, &echo) {
+            self.send(Delivery::Reliable, addr, &Message::Clipboard(ciphertext)).await;
+        }
+    }
+
+    async fn offer_file(&self, addr: SocketAddr) {
+        let Some((id, path)) = self.offered.lock().unwrap().clone() else { return };
+        let size = match std::fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(e) => {
+                tracing::warn!("Can't offer {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "test.bin".to_string());
+        let local_id = self.local_id();
+        let mut payload = ClipboardPayload {
+            id,
+            text: String::new(),
+            files: Some(vec![FileMetadata {
+                name,
+                size,
+                mime: None,
+                thumbnail: None,
+                hash: crypto::hash_file(&path).ok(),
+                inline_data: None,
+                text_preview: None,
+                clipboard_image: false,
+            }]),
+            timestamp: now(),
+            sender: local_id.clone(),
+            sender_id: local_id,
+            source: None,
+            trace: None,
+            channel: crate::protocol::DEFAULT_CHANNEL.to_string(),
+            link: None,
+            content: None,
+            auth: None,
+        };
+        crate::senderauth::sign(&self.state, &mut payload);
+        if let Some(ciphertext) = crate::encrypt_cluster_message(&self.state, &payload) {
+            tracing::info!("Offering {} ({} bytes) to {}", path.display(), size, addr);
+            self.send(Delivery::Reliable, addr, &Message::Clipboard(ciphertext)).await;
+        }
+    }
+
+    async fn on_file_request(&self, addr: SocketAddr, req: FileRequestPayload) {
+        let offered = self.offered.lock().unwrap().clone();
+        let Some((_, path)) = offered.filter(|(id, _)| *id == req.id && req.file_index == 0) else {
+            tracing::warn!("FileRequest from {} for something we never offered ({})", addr, req.id);
+            return;
+        };
+        if let Err(e) = self.serve_file(addr, req, &path).await {
+            tracing::warn!("Serving {} to {} failed: {}", path.display(), addr, e);
+        }
+    }
+
+    async fn serve_file(
+        &self,
+        addr: SocketAddr,
+        req: FileRequestPayload,
+        path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let key = cluster_key(&self.state).ok_or("no cluster key")?;
+        let mut file = tokio::fs::File::open(path).await?;
+        let file_size = file.metadata().await?.len();
+        let offset = req.offset.min(file_size);
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+
+        let (connection, mut stream) = self.transport.send_file_stream(addr).await?;
+        let header = FileStreamHeader {
+            id: req.id,
+            file_index: req.file_index,
+            file_name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            file_size,
+            auth_token: BASE64.encode(crypto::encrypt(&key, &now().to_le_bytes())?),
+            compressed: false,
+            offset,
+        };
+        stream.write_all(serde_json::to_string(&header)?.as_bytes()).await?;
+        stream.write_all(b"\n").await?;
+
+        let mut buf = vec![0u8; 1024 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            stream.write_all(&buf[..n]).await?;
+        }
+        stream.shutdown().await?;
+        // Let the receiver acknowledge the tail before the connection goes away
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        connection.close(0u32.into(), b"done");
+        tracing::info!("Served {} ({} bytes from offset {}) to {}", path.display(), file_size - offset, offset, addr);
+        Ok(())
+    }
+}
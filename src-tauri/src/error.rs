@@ -0,0 +1,87 @@
+//! Crate-wide error type and the "app-error" event stream.
+//!
+//! Commands reject with the plain message (so existing `String(e)` handling in the UI keeps
+//! working). Failures in background work, which have no caller to return to, are reported
+//! through `report`, which logs them and emits a structured "app-error" event for toasts.
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Network,
+    Crypto,
+    Storage,
+    Clipboard,
+    Pairing,
+    Invalid,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AppError {
+    // Peer unreachable, send failed, transport not ready
+    #[error("{0}")]
+    Network(String),
+    // Missing/invalid cluster key, encryption or verification failure
+    #[error("{0}")]
+    Crypto(String),
+    #[error("{0}")]
+    Storage(String),
+    // Reading or writing the system clipboard
+    #[error("{0}")]
+    Clipboard(String),
+    #[error("{0}")]
+    Pairing(String),
+    // Bad input or an operation that doesn't apply in the current state
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl AppError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            AppError::Network(_) => ErrorCategory::Network,
+            AppError::Crypto(_) => ErrorCategory::Crypto,
+            AppError::Storage(_) => ErrorCategory::Storage,
+            AppError::Clipboard(_) => ErrorCategory::Clipboard,
+            AppError::Pairing(_) => ErrorCategory::Pairing,
+            AppError::Invalid(_) => ErrorCategory::Invalid,
+        }
+    }
+
+    /// Whether trying again may succeed (the UI offers a Retry button that reconnects to peers).
+    pub fn retryable(&self) -> bool {
+        matches!(self, AppError::Network(_))
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Payload of the "app-error" event.
+#[derive(Serialize, Clone, Debug)]
+pub struct ErrorEvent {
+    pub category: ErrorCategory,
+    pub message: String,
+    pub retryable: bool,
+    // What was being done, e.g. "file-download" (lets the UI pick a retry action)
+    pub context: String,
+    pub timestamp: u64,
+}
+
+/// Log a background failure and surface it to the UI.
+pub fn report(app: &AppHandle, context: &str, error: &AppError) {
+    tracing::error!("[{}] {:?} error: {}", context, error.category(), error);
+    let event = ErrorEvent {
+        category: error.category(),
+        message: error.to_string(),
+        retryable: error.retryable(),
+        context: context.to_string(),
+        timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+    };
+    crate::events::emit(app, &event);
+}
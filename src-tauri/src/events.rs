@@ -0,0 +1,403 @@
+//! Events sent to the frontend, and their payloads.
+//!
+//! Every event the backend emits is declared here, with its name and payload type, and goes
+//! out through `emit`. Payloads are always JSON objects carrying `v` (`VERSION`) next to their
+//! fields, so other frontends (the TUI, a future web UI) can rely on the shapes below and
+//! detect when they change. Bump `VERSION` when a field is removed, renamed or changes type;
+//! adding fields doesn't need a bump.
+
+use crate::peer::Peer;
+use crate::protocol::ClipboardPayload;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Runtime};
+
+pub const VERSION: u32 = 1;
+
+/// A payload with a fixed event name.
+pub trait Event: Serialize {
+    const NAME: &'static str;
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, E> {
+    v: u32,
+    #[serde(flatten)]
+    payload: &'a E,
+}
+
+/// Emit `event` to every window.
+pub fn emit<R: Runtime, E: Event>(emitter: &impl Emitter<R>, event: E) {
+    match serde_json::to_value(Envelope { v: VERSION, payload: &event }) {
+        Ok(payload) => {
+            let _ = emitter.emit(E::NAME, payload);
+        }
+        Err(e) => tracing::error!("Failed to serialize {} event: {}", E::NAME, e),
+    }
+}
+
+impl<E: Event> Event for &E {
+    const NAME: &'static str = E::NAME;
+}
+
+macro_rules! events {
+    ($($ty:ty => $name:literal),* $(,)?) => {
+        $(impl Event for $ty {
+            const NAME: &'static str = $name;
+        })*
+    };
+}
+
+events! {
+    ClipboardChange<'_> => "clipboard-change",
+    ClipboardMonitorUpdate<'_> => "clipboard-monitor-update",
+    ClipboardOutgoingPending<'_> => "clipboard-outgoing-pending",
+    ClipboardOutgoingCleared => "clipboard-outgoing-cleared",
+    ClipboardPending<'_> => "clipboard-pending",
+    ClipboardPendingRemoved => "clipboard-pending-removed",
+    crate::clipwrite::ClipboardHealth => "clipboard-health",
+    HistoryDeleted => "history-delete",
+    HistoryAnnotated<'_> => "history-annotate",
+    HistoryImported => "history-imported",
+    HistoryLinkPreview => "history-link-preview",
+    PeerUpdate<'_> => "peer-update",
+    PeerRemoved => "peer-remove",
+    crate::presence::PeerPresence => "peer-presence",
+    PairingFailed => "pairing-failed",
+    PairingResumeNeeded => "pairing-resume-needed",
+    PairingAwaitingApproval => "pairing-awaiting-approval",
+    AdmissionRequested<'_> => "admission-request",
+    NetworkUpdate => "network-update",
+    NetworkReset => "network-reset",
+    NetworkPinChanged => "network-pin-changed",
+    ProfileSwitched => "profile-switched",
+    RecoveryPhraseCreated => "recovery-phrase-created",
+    ResetPending => "reset-pending",
+    ResetCancelled => "reset-cancelled",
+    SettingsChanged<'_> => "settings-changed",
+    ConfigReloaded => "config-reloaded",
+    ConfigError => "config-error",
+    FileProgress => "file-progress",
+    FileReceived => "file-received",
+    FileTransferSkipped => "file-transfer-skipped",
+    DownloadDeferred => "download-deferred",
+    FileOffersExpired => "file-offers-expired",
+    crate::storage::PartialTransfer => "file-transfer-incomplete",
+    FileDownloadRequested => "file-download-requested",
+    FileDownloadFailed => "file-download-failed",
+    FileError => "file-error",
+    FileVerifyFailed => "file-verify-failed",
+    FileSaveRequested => "file-save-requested",
+    FileSaved => "file-saved",
+    crate::staging::StagingProgress => "staging-progress",
+    crate::retry::SendFailure => "send-failed",
+    crate::error::ErrorEvent => "app-error",
+    crate::remote::PendingTypeText => "type-text-request",
+    TypeTextCleared => "type-text-cleared",
+    UrlOpened => "url-opened",
+    NotificationClicked => "notification-clicked",
+    DeepLink => "deep-link",
+    UnreadChanged => "unread-changed",
+    ScreenLockChanged => "screen-lock-changed",
+    crate::power::PowerStatus => "power-state-changed",
+    crate::onboarding::OnboardingProgress => "onboarding-progress",
+    crate::migration::MigrationReport => "storage-migration",
+    crate::update::UpdateInfo => "update-available",
+}
+
+// Clipboard
+
+/// An item was added to history (local copy or received).
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ClipboardChange<'a>(pub &'a ClipboardPayload);
+
+/// Local clipboard changed but wasn't sent (auto-send off or screen locked).
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ClipboardMonitorUpdate<'a>(pub &'a ClipboardPayload);
+
+/// Local content is held for review before sending.
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ClipboardOutgoingPending<'a>(pub &'a ClipboardPayload);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClipboardOutgoingCleared {
+    pub id: String,
+}
+
+/// Incoming content is waiting to be applied.
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ClipboardPending<'a>(pub &'a ClipboardPayload);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClipboardPendingRemoved {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryDeleted {
+    pub id: String,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct HistoryAnnotated<'a>(pub &'a crate::protocol::HistoryAnnotation);
+
+/// A link preview arrived for a history item (after it was sent, see unfurl.rs).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryLinkPreview {
+    pub id: String,
+    pub link: crate::protocol::LinkPreview,
+}
+
+/// An archive was merged into history (`import_history`); reload it and its notes.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryImported {
+    pub imported: usize,
+}
+
+// Peers and pairing
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct PeerUpdate<'a>(pub &'a Peer);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PeerRemoved {
+    pub id: String,
+}
+
+/// Joining failed; `reason` is the localized message shown in the join dialog.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PairingFailed {
+    pub error: crate::protocol::PairingError,
+    pub reason: String,
+}
+
+impl PairingFailed {
+    pub fn new(error: crate::protocol::PairingError) -> Self {
+        use crate::protocol::PairingError;
+        let key = match error {
+            PairingError::WrongPin => "pairing.error.wrong_pin",
+            PairingError::Timeout => "pairing.error.timeout",
+            PairingError::Unreachable => "pairing.error.unreachable",
+            PairingError::Rejected => "pairing.error.rejected",
+            PairingError::VersionMismatch => "pairing.error.version_mismatch",
+            PairingError::KeyMismatch => "pairing.error.key_mismatch",
+            PairingError::OwnerOffline => "pairing.error.owner_offline",
+        };
+        Self { error, reason: crate::i18n::t(key) }
+    }
+}
+
+/// The PIN was accepted and the cluster owner has to approve us; the Welcome may take a while.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PairingAwaitingApproval {
+    pub device_id: String,
+}
+
+/// A pairing started before a restart got its answer; the user has to enter the PIN again.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PairingResumeNeeded {
+    pub peer_id: String,
+    pub network_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct AdmissionRequested<'a>(pub &'a crate::protocol::AdmissionRequest);
+
+// Network and settings
+
+/// Network name or PIN changed; fetch them again.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NetworkUpdate {}
+
+/// Network state was wiped; the UI reloads.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NetworkReset {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NetworkPinChanged {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProfileSwitched {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RecoveryPhraseCreated {}
+
+/// We were removed from the cluster; local state resets at `deadline` unless cancelled.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResetPending {
+    pub deadline: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ResetCancelled {}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct SettingsChanged<'a>(pub &'a crate::storage::AppSettings);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigReloaded {
+    pub path: String,
+    pub port: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigError {
+    pub message: String,
+}
+
+// Files
+
+/// Throttled progress of an incoming file stream.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProgress {
+    pub id: String,
+    pub file_name: String,
+    pub total: u64,
+    pub transferred: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FileReceived {
+    pub id: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub file_index: usize,
+    pub path: String,
+    // Came inline with the clipboard message rather than as a stream
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inline: bool,
+    // Already on disk from an earlier transfer; nothing was downloaded
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deduplicated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan: Option<crate::scan::ScanReport>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileTransferSkipped {
+    pub id: String,
+    pub file_index: usize,
+    pub reason: String,
+}
+
+/// Auto-download of an item's files was held back (metered connection or battery saver).
+#[derive(Serialize, Clone, Debug)]
+pub struct DownloadDeferred {
+    pub id: String,
+    pub peer_id: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    pub reason: crate::power::Constraint,
+}
+
+/// Files this device offered are no longer served (past the offer TTL or count limit).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileOffersExpired {
+    pub ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileDownloadRequested {
+    pub id: String,
+    pub file_index: usize,
+    pub file_count: usize,
+    pub peer_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileDownloadFailed {
+    pub id: String,
+    pub file_index: usize,
+    pub error: String,
+}
+
+/// A peer couldn't serve a file we requested.
+#[derive(Serialize, Clone, Debug)]
+pub struct FileError {
+    pub id: String,
+    pub file_index: usize,
+    // Sender, when its address maps to a known peer (needed to request again)
+    pub peer_id: Option<String>,
+    pub reason: crate::protocol::FileErrorReason,
+    pub message: String,
+    // Requesting again may work (the file was there but couldn't be read)
+    pub retryable: bool,
+}
+
+/// A downloaded file didn't match the hash its sender announced and was deleted.
+#[derive(Serialize, Clone, Debug)]
+pub struct FileVerifyFailed {
+    pub id: String,
+    pub file_index: usize,
+    pub file_name: String,
+    // Failed attempts so far (1 for the first download)
+    pub attempt: u32,
+    // The file is being requested again
+    pub will_retry: bool,
+}
+
+/// A large file finished downloading and was not put on the clipboard; ask where to save it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileSaveRequested {
+    pub id: String,
+    pub file_index: usize,
+    pub file_name: String,
+    pub file_size: u64,
+    pub path: String,
+}
+
+/// A received file was moved out of the cache by `save_received_file`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileSaved {
+    pub id: String,
+    pub file_index: usize,
+    pub from: String,
+    pub path: String,
+}
+
+// Remote control
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TypeTextCleared {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UrlOpened {
+    pub url: String,
+    pub sender: String,
+}
+
+// App
+
+/// A notification was clicked; switch to `view`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationClicked {
+    pub view: String,
+}
+
+/// Arguments of a second launch (deep links from notifications).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeepLink {
+    pub args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnreadChanged {
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScreenLockChanged {
+    pub locked: bool,
+}
@@ -0,0 +1,35 @@
+//! Time-limited guest membership.
+//!
+//! A guest never sees the cluster key. It gets a key derived from the cluster key, its device
+//! ID and its expiry, so any member can re-derive it from the guest's membership record, and
+//! a forged/extended expiry simply yields a key that doesn't decrypt anything.
+
+use crate::peer::Peer;
+use crate::state::AppState;
+use sha2::{Digest, Sha256};
+
+pub const DEFAULT_GUEST_DURATION_SECS: u64 = 2 * 60 * 60;
+pub const MAX_GUEST_DURATION_SECS: u64 = 7 * 24 * 60 * 60;
+/// How long an armed invite waits for the guest to pair.
+pub const GUEST_INVITE_WINDOW_SECS: u64 = 5 * 60;
+
+pub fn derive_key(cluster_key: &[u8; 32], guest_id: &str, expires_at: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"clustercut-guest-v1");
+    hasher.update(cluster_key);
+    hasher.update(guest_id.as_bytes());
+    hasher.update(expires_at.to_be_bytes());
+    hasher.finalize().into()
+}
+
+pub fn is_expired(peer: &Peer, now: u64) -> bool {
+    peer.guest_expires_at.is_some_and(|exp| exp <= now)
+}
+
+/// Derived key of the (unexpired) guest currently at `ip`, if any.
+pub fn key_for_addr(state: &AppState, cluster_key: &[u8; 32], ip: std::net::IpAddr) -> Option<[u8; 32]> {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let peers = state.peers.lock().unwrap();
+    let guest = peers.values().find(|p| p.ip == ip && p.guest_expires_at.is_some() && !is_expired(p, now))?;
+    Some(derive_key(cluster_key, &guest.id, guest.guest_expires_at?))
+}
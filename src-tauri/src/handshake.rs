@@ -0,0 +1,165 @@
+//! Expiry for in-flight pairing handshakes.
+//!
+//! Three maps in `AppState` hold handshake material, keyed by the other side's address: the
+//! initiator's SPAKE2 state waiting for PairResponse, its derived keys waiting for Welcome,
+//! and the responder's keys waiting for PairConfirm. Entries live for at most `TTL`, except
+//! that the keys waiting for Welcome are kept for `ADMISSION_TTL` once the responder reports
+//! that the cluster owner has to approve us (AdmissionPending). Lookups
+//! skip expired entries, so a late Welcome can't be decrypted with a stale session, and
+//! `run` sweeps them out in the background. Completed or failed handshakes are removed
+//! straight away.
+//!
+//! Answering a PairRequest costs an Argon2 run (see `crypto::stretch_pin`) before anything is
+//! authenticated, so each address gets at most one answer per `PAIR_REQUEST_INTERVAL`.
+//!
+//! Those maps are in memory only, so the initiator also writes a small `PairingIntent` (peer and
+//! network, never the PIN) to disk for the same `TTL`. If it restarts before the PairResponse
+//! arrives, `resume` hands the intent back and the user is asked for the PIN again instead of
+//! the pairing just failing. The intent is dropped once the pairing is welcomed or rejected.
+//! One-time PINs are used up by the first attempt, so those pairings leave no intent.
+
+use crate::state::AppState;
+use crate::storage::PairingIntent;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const TTL: Duration = Duration::from_secs(120);
+// How long a paired-but-unapproved device waits for the owner's decision
+pub const ADMISSION_TTL: Duration = Duration::from_secs(10 * 60);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+pub const PAIR_REQUEST_INTERVAL: Duration = Duration::from_secs(3);
+
+pub struct Expiring<T> {
+    value: T,
+    expires_at: Instant,
+}
+
+impl<T> Expiring<T> {
+    fn live(&self, now: Instant) -> bool {
+        now < self.expires_at
+    }
+}
+
+pub type HandshakeMap<T> = Mutex<HashMap<String, Expiring<T>>>;
+
+pub fn insert<T>(map: &HandshakeMap<T>, addr: &str, value: T) {
+    map.lock().unwrap().insert(addr.to_string(), Expiring { value, expires_at: Instant::now() + TTL });
+}
+
+/// Keep a live entry for `addr` around for `ttl` from now. False if there's none.
+pub fn extend<T>(map: &HandshakeMap<T>, addr: &str, ttl: Duration) -> bool {
+    let now = Instant::now();
+    match map.lock().unwrap().get_mut(addr) {
+        Some(entry) if entry.live(now) => {
+            entry.expires_at = entry.expires_at.max(now + ttl);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Remove and return the entry for `addr`, unless it has expired.
+pub fn take<T>(map: &HandshakeMap<T>, addr: &str) -> Option<T> {
+    let entry = map.lock().unwrap().remove(addr)?;
+    if entry.live(Instant::now()) {
+        Some(entry.value)
+    } else {
+        tracing::info!("[Handshake] Session with {} expired", addr);
+        None
+    }
+}
+
+/// Whether a PairRequest from `ip` may be answered now (and note that it was).
+pub fn allow_pair_request(state: &AppState, ip: std::net::IpAddr) -> bool {
+    let now = Instant::now();
+    let mut times = state.pair_request_times.lock().unwrap();
+    times.retain(|_, at| now.duration_since(*at) < PAIR_REQUEST_INTERVAL);
+    if times.contains_key(&ip) {
+        return false;
+    }
+    times.insert(ip, now);
+    true
+}
+
+/// Drop everything held for `addr` (pairing finished, failed or the peer was blocked).
+pub fn forget(state: &AppState, addr: &str) {
+    state.pending_handshakes.lock().unwrap().remove(addr);
+    state.handshake_sessions.lock().unwrap().remove(addr);
+    state.pending_confirmations.lock().unwrap().remove(addr);
+}
+
+pub fn clear(state: &AppState) {
+    state.pending_handshakes.lock().unwrap().clear();
+    state.handshake_sessions.lock().unwrap().clear();
+    state.pending_confirmations.lock().unwrap().clear();
+}
+
+fn sweep_map<T>(map: &HandshakeMap<T>, now: Instant) -> usize {
+    let mut map = map.lock().unwrap();
+    let before = map.len();
+    map.retain(|_, entry| entry.live(now));
+    before - map.len()
+}
+
+/// Remove expired entries. Returns how many were dropped, and how many of those were our own
+/// pairings still waiting on the other side (PairResponse or Welcome).
+pub fn sweep(state: &AppState) -> (usize, usize) {
+    let now = Instant::now();
+    let unanswered = sweep_map(&state.pending_handshakes, now) + sweep_map(&state.handshake_sessions, now);
+    (unanswered + sweep_map(&state.pending_confirmations, now), unanswered)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn live_intents(app: &tauri::AppHandle) -> HashMap<String, PairingIntent> {
+    let now = unix_now();
+    let mut intents = crate::storage::load_pairing_intents(app);
+    intents.retain(|_, intent| intent.expires_at > now);
+    intents
+}
+
+/// Persist a pairing we just started with `peer_id`.
+pub fn remember(app: &tauri::AppHandle, peer_id: &str, network_name: &str) {
+    let mut intents = live_intents(app);
+    let intent = PairingIntent {
+        peer_id: peer_id.to_string(),
+        network_name: network_name.to_string(),
+        expires_at: unix_now() + TTL.as_secs(),
+    };
+    intents.insert(peer_id.to_string(), intent);
+    crate::storage::save_pairing_intents(app, &intents);
+}
+
+/// The live pairing intent for `peer_id`, removed so a pairing is restarted at most once.
+pub fn resume(app: &tauri::AppHandle, peer_id: &str) -> Option<PairingIntent> {
+    let mut intents = live_intents(app);
+    let intent = intents.remove(peer_id);
+    crate::storage::save_pairing_intents(app, &intents);
+    intent
+}
+
+/// Drop the intent for `peer_id`: the pairing was welcomed or rejected.
+pub fn settle(app: &tauri::AppHandle, peer_id: &str) {
+    let mut intents = live_intents(app);
+    if intents.remove(peer_id).is_some() {
+        crate::storage::save_pairing_intents(app, &intents);
+    }
+}
+
+pub async fn run(app: tauri::AppHandle) {
+    use tauri::Manager;
+    let state = app.state::<AppState>().inner().clone();
+    while !state.is_shutdown() {
+        tokio::time::sleep(SWEEP_INTERVAL).await;
+        let (expired, unanswered) = sweep(&state);
+        if expired > 0 {
+            tracing::info!("[Handshake] Dropped {} expired pairing sessions", expired);
+        }
+        if unanswered > 0 {
+            crate::events::emit(&app, crate::events::PairingFailed::new(crate::protocol::PairingError::Timeout));
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! Backend copy of the recent clipboard history, for commands that work on items by id
+//! (diffing two entries, QR export, sending again) and for archiving it to a file (JSON or
+//! CSV) and back. Mirrors the frontend list: in memory only, newest first.
+
+use crate::classify::ContentKind;
+use crate::protocol::{ClipboardPayload, HistoryAnnotation};
+use crate::state::AppState;
+use crate::transport::Transport;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use similar::{ChangeTag, TextDiff};
+use tauri::{AppHandle, Listener, Manager};
+
+// Same cap as the history list in the UI
+pub const HISTORY_LIMIT: usize = 50;
+// Unchanged lines kept around each change
+const DIFF_CONTEXT_LINES: usize = 3;
+// Past this, the diff settles for a coarser (still correct) result instead of the minimal one
+const DIFF_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+// Rendered QR edge in pixels (large enough for phone cameras at arm's length)
+const QR_MIN_EDGE: u32 = 320;
+// Largest archive import_history reads
+const MAX_ARCHIVE_BYTES: u64 = 32 * 1024 * 1024;
+const ARCHIVE_VERSION: u32 = 1;
+const CSV_COLUMNS: [&str; 9] = ["id", "timestamp", "sender", "sender_id", "channel", "kinds", "text", "files", "note"];
+
+/// Record every committed clipboard item (sent or received).
+pub fn track(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen("clipboard-change", move |event| {
+        let Ok(payload) = serde_json::from_str::<ClipboardPayload>(event.payload()) else {
+            return;
+        };
+        let state = handle.state::<AppState>();
+        let mut items = state.recent_items.lock().unwrap();
+        // Same id re-emitted (e.g. file metadata then text): keep the latest copy
+        items.retain(|p| p.id != payload.id);
+        items.push_front(payload);
+        items.truncate(HISTORY_LIMIT);
+    });
+}
+
+/// Commit `payload` to history: tag its content kinds and emit "clipboard-change".
+pub fn commit(app: &AppHandle, payload: &ClipboardPayload) {
+    let mut entry = payload.clone();
+    if entry.files.as_ref().is_none_or(|f| f.is_empty()) {
+        entry.content = crate::classify::classify(&entry.text);
+    }
+    crate::events::emit(app, crate::events::ClipboardChange(&entry));
+}
+
+/// History items matching `query` (case-insensitive, in text, file names, sender and note),
+/// narrowed to any of `kinds` and to `language` when given. Newest first.
+pub fn search(state: &AppState, query: &str, kinds: &[ContentKind], language: Option<&str>) -> Vec<ClipboardPayload> {
+    let query = query.trim().to_lowercase();
+    let annotations = state.history_annotations.lock().unwrap();
+    state
+        .recent_items
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|item| {
+            let tags = item.content.as_ref();
+            let kind_ok = kinds.is_empty() || tags.is_some_and(|t| t.kinds.iter().any(|k| kinds.contains(k)));
+            let language_ok = language
+                .is_none_or(|l| tags.and_then(|t| t.language.as_deref()).is_some_and(|have| have.eq_ignore_ascii_case(l)));
+            let text_ok = query.is_empty()
+                || item.text.to_lowercase().contains(&query)
+                || item.sender.to_lowercase().contains(&query)
+                || item.files.iter().flatten().any(|f| f.name.to_lowercase().contains(&query))
+                || annotations.get(&item.id).and_then(|a| a.note.as_ref()).is_some_and(|n| n.to_lowercase().contains(&query));
+            kind_ok && language_ok && text_ok
+        })
+        .cloned()
+        .collect()
+}
+
+fn text_item(state: &AppState, id: &str) -> Result<String, String> {
+    let items = state.recent_items.lock().unwrap();
+    let item = items.iter().find(|p| p.id == id).ok_or_else(|| format!("History item {} not found", id))?;
+    if item.files.as_ref().is_some_and(|f| !f.is_empty()) {
+        return Err(format!("History item {} is a file transfer, not text", id));
+    }
+    Ok(item.text.clone())
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffTag {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl From<ChangeTag> for DiffTag {
+    fn from(tag: ChangeTag) -> Self {
+        match tag {
+            ChangeTag::Equal => DiffTag::Equal,
+            ChangeTag::Delete => DiffTag::Delete,
+            ChangeTag::Insert => DiffTag::Insert,
+        }
+    }
+}
+
+/// A run of text within a line; `emphasized` marks the words that actually changed.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DiffSpan {
+    pub text: String,
+    pub emphasized: bool,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    // 1-based line numbers in the old (a) / new (b) text
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub spans: Vec<DiffSpan>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct HistoryDiff {
+    pub id_a: String,
+    pub id_b: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    // Empty when the texts are identical
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Line diff of two text items, with word-level highlights inside changed lines.
+pub fn diff(state: &AppState, id_a: &str, id_b: &str) -> Result<HistoryDiff, String> {
+    let old = text_item(state, id_a)?;
+    let new = text_item(state, id_b)?;
+    let diff = TextDiff::configure().timeout(DIFF_TIMEOUT).diff_lines(&old, &new);
+
+    let (mut insertions, mut deletions) = (0, 0);
+    let mut hunks = Vec::new();
+    for group in diff.grouped_ops(DIFF_CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let (old_range, new_range) = (first.old_range().start..last.old_range().end, first.new_range().start..last.new_range().end);
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_inline_changes(op) {
+                match change.tag() {
+                    ChangeTag::Insert => insertions += 1,
+                    ChangeTag::Delete => deletions += 1,
+                    ChangeTag::Equal => {}
+                }
+                let spans = change
+                    .iter_strings_lossy()
+                    .map(|(emphasized, text)| DiffSpan { text: text.trim_end_matches(['\r', '\n']).to_string(), emphasized })
+                    .filter(|s| !s.text.is_empty())
+                    .collect();
+                lines.push(DiffLine {
+                    tag: change.tag().into(),
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                    spans,
+                });
+            }
+        }
+
+        hunks.push(DiffHunk {
+            old_start: old_range.start + 1,
+            old_lines: old_range.len(),
+            new_start: new_range.start + 1,
+            new_lines: new_range.len(),
+            lines,
+        });
+    }
+
+    Ok(HistoryDiff { id_a: id_a.to_string(), id_b: id_b.to_string(), insertions, deletions, hunks })
+}
+
+/// Send a history item again, to every peer or just `peer_id`, as a new item from this device.
+/// Files are only offered if their original paths (from when we sent them) still exist.
+pub fn resend(app: &AppHandle, state: &AppState, transport: &Transport, id: &str, peer_id: Option<&str>) -> Result<String, String> {
+    let item = state
+        .recent_items
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+        .ok_or_else(|| format!("History item {} not found", id))?;
+    if let Some(peer_id) = peer_id {
+        if !state.get_peers().contains_key(peer_id) {
+            return Err(format!("Peer {} not found or offline", peer_id));
+        }
+    }
+    if state.is_reset_pending() {
+        return Err("This device was removed from the cluster".to_string());
+    }
+
+    let new_id = uuid::Uuid::new_v4().to_string();
+    let files = match item.files.filter(|f| !f.is_empty()) {
+        None => None,
+        Some(metas) => {
+            let paths = state.local_files.lock().unwrap().paths(id).cloned().unwrap_or_default();
+            // Metadata and paths were recorded in the same order; keep the pairs still on disk
+            let (metas, paths): (Vec<_>, Vec<_>) = metas
+                .into_iter()
+                .zip(paths)
+                .filter(|(_, path)| std::path::Path::new(path).exists())
+                .unzip();
+            if metas.is_empty() {
+                return Err("The original files are no longer available on this device".to_string());
+            }
+            crate::offers::register(app, state, new_id.clone(), paths);
+            Some(metas)
+        }
+    };
+    if files.is_none() && item.text.is_empty() {
+        return Err(format!("History item {} has nothing to send", id));
+    }
+
+    let payload = ClipboardPayload {
+        id: new_id.clone(),
+        text: item.text,
+        files,
+        timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+        sender: crate::get_hostname_internal(),
+        sender_id: state.local_device_id.lock().unwrap().clone(),
+        source: None,
+        trace: crate::latency::start_trace(state),
+        channel: item.channel,
+        link: item.link,
+        content: None,
+        auth: None,
+    };
+    tracing::info!("[History] Re-sending {} as {} to {}", id, new_id, peer_id.unwrap_or("all peers"));
+    crate::clipboard::send_payload(app, state, transport, payload, peer_id);
+    Ok(new_id)
+}
+
+/// Render a text item as a QR code PNG (base64), for phones that aren't in the cluster.
+pub fn qr_png(state: &AppState, id: &str) -> Result<String, String> {
+    let text = text_item(state, id)?;
+    if text.is_empty() {
+        return Err(format!("History item {} has no text", id));
+    }
+    // Medium error correction holds ~2.3KB of bytes; fall back to Low for longer text
+    let code = qrcode::QrCode::with_error_correction_level(&text, qrcode::EcLevel::M)
+        .or_else(|_| qrcode::QrCode::with_error_correction_level(&text, qrcode::EcLevel::L))
+        .map_err(|_| format!("Item is too long for a QR code ({} bytes)", text.len()))?;
+
+    let image = code.render::<image::Luma<u8>>().min_dimensions(QR_MIN_EDGE, QR_MIN_EDGE).build();
+    let mut buf = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    Ok(BASE64.encode(buf))
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Inclusive bounds on the item timestamp (unix seconds); either side may be open.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default)]
+pub struct DateRange {
+    #[serde(default)]
+    pub from: Option<u64>,
+    #[serde(default)]
+    pub to: Option<u64>,
+}
+
+impl DateRange {
+    fn contains(&self, ts: u64) -> bool {
+        self.from.is_none_or(|from| ts >= from) && self.to.is_none_or(|to| ts <= to)
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub include_files: bool,
+    // Items tagged `sensitive` (see classify.rs)
+    pub include_sensitive: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self { include_files: true, include_sensitive: false }
+    }
+}
+
+/// A history item as written to an archive, with its note.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+struct ArchivedItem {
+    #[serde(flatten)]
+    item: ClipboardPayload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct Archive {
+    version: u32,
+    exported_at: u64,
+    items: Vec<ArchivedItem>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ImportReport {
+    pub imported: usize,
+    // Already in history, or malformed rows
+    pub skipped: usize,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn is_file_entry(item: &ClipboardPayload) -> bool {
+    item.files.as_ref().is_some_and(|f| !f.is_empty())
+}
+
+fn is_sensitive(item: &ClipboardPayload) -> bool {
+    item.content.as_ref().is_some_and(|t| t.kinds.contains(&ContentKind::Sensitive))
+}
+
+/// Write the history items within `range` to `path`, oldest first. Returns how many were written.
+/// File entries keep their metadata only (names, sizes, hashes), never contents.
+pub fn export(state: &AppState, path: &std::path::Path, format: ExportFormat, range: DateRange, options: ExportOptions) -> Result<usize, String> {
+    let annotations = state.history_annotations.lock().unwrap().clone();
+    let items: Vec<ArchivedItem> = state
+        .recent_items
+        .lock()
+        .unwrap()
+        .iter()
+        .rev()
+        .filter(|item| range.contains(item.timestamp))
+        .filter(|item| options.include_files || !is_file_entry(item))
+        .filter(|item| options.include_sensitive || !is_sensitive(item))
+        .map(|item| {
+            let mut item = item.clone();
+            // Signatures are only meaningful in flight; inline contents stay on this device
+            item.auth = None;
+            item.trace = None;
+            for file in item.files.iter_mut().flatten() {
+                file.inline_data = None;
+            }
+            let note = annotations.get(&item.id).and_then(|a| a.note.clone());
+            ArchivedItem { item, note }
+        })
+        .collect();
+
+    let count = items.len();
+    let data = match format {
+        ExportFormat::Json => {
+            let archive = Archive { version: ARCHIVE_VERSION, exported_at: now(), items };
+            serde_json::to_vec_pretty(&archive).map_err(|e| format!("Failed to serialize history: {}", e))?
+        }
+        ExportFormat::Csv => to_csv(&items)?.into_bytes(),
+    };
+    std::fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    tracing::info!("[History] Exported {} item(s) to {}", count, crate::redact::path(path));
+    Ok(count)
+}
+
+/// Read an archive written by `export` (JSON or CSV, detected from the contents) and merge it
+/// into history. Items already present are skipped; the list is then capped at `HISTORY_LIMIT`,
+/// so only the newest entries of a large archive stay. Notes never overwrite existing ones.
+pub fn import(state: &AppState, path: &std::path::Path) -> Result<(ImportReport, Vec<HistoryAnnotation>), String> {
+    let size = std::fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+    if size > MAX_ARCHIVE_BYTES {
+        return Err(format!("{} is too large to import ({} bytes)", path.display(), size));
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let (archived, mut skipped) = if content.trim_start().starts_with('{') {
+        let archive: Archive = serde_json::from_str(&content).map_err(|e| format!("Not a history archive: {}", e))?;
+        if archive.version > ARCHIVE_VERSION {
+            return Err(format!("History archive version {} is newer than this app supports", archive.version));
+        }
+        (archive.items, 0)
+    } else {
+        from_csv(&content)?
+    };
+
+    let mut notes = Vec::new();
+    let mut imported = 0;
+    {
+        let mut items = state.recent_items.lock().unwrap();
+        for ArchivedItem { mut item, note } in archived {
+            if item.id.is_empty() || items.iter().any(|p| p.id == item.id) {
+                skipped += 1;
+                continue;
+            }
+            // Tags are computed locally, like for items committed here
+            item.auth = None;
+            item.content = if is_file_entry(&item) { None } else { crate::classify::classify(&item.text) };
+            if let Some(note) = note.filter(|n| !n.trim().is_empty()) {
+                // updated_at 0: any note already synced for this id wins
+                notes.push(HistoryAnnotation { id: item.id.clone(), note: Some(note), updated_at: 0 });
+            }
+            items.push_back(item);
+            imported += 1;
+        }
+        items.make_contiguous().sort_by_key(|i| std::cmp::Reverse(i.timestamp));
+        items.truncate(HISTORY_LIMIT);
+        notes.retain(|n| items.iter().any(|p| p.id == n.id));
+    }
+    let notes = notes.into_iter().filter(|n| state.apply_history_annotation(n.clone())).collect();
+    Ok((ImportReport { imported, skipped }, notes))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// File metadata goes into the `files` column as JSON, so CSV archives round-trip too
+fn to_csv(items: &[ArchivedItem]) -> Result<String, String> {
+    let mut out = CSV_COLUMNS.join(",");
+    out.push_str("\r\n");
+    for ArchivedItem { item, note } in items {
+        let kinds = item
+            .content
+            .iter()
+            .flat_map(|t| &t.kinds)
+            .filter_map(|k| serde_json::to_value(k).ok().and_then(|v| v.as_str().map(str::to_string)))
+            .collect::<Vec<_>>()
+            .join(";");
+        let files = match item.files.as_ref().filter(|f| !f.is_empty()) {
+            Some(files) => serde_json::to_string(files).map_err(|e| format!("Failed to serialize file list: {}", e))?,
+            None => String::new(),
+        };
+        let row = [
+            item.id.clone(),
+            item.timestamp.to_string(),
+            item.sender.clone(),
+            item.sender_id.clone(),
+            item.channel.clone(),
+            kinds,
+            item.text.clone(),
+            files,
+            note.clone().unwrap_or_default(),
+        ];
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push_str("\r\n");
+    }
+    Ok(out)
+}
+
+// RFC 4180 records: quoted fields may hold commas, quotes ("") and line breaks
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') | (false, '\r') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn from_csv(content: &str) -> Result<(Vec<ArchivedItem>, usize), String> {
+    let mut records = parse_csv(content).into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let (Some(id), Some(timestamp), Some(text)) = (column("id"), column("timestamp"), column("text")) else {
+        return Err("Not a history archive: the CSV needs id, timestamp and text columns".to_string());
+    };
+    let optional = ["sender", "sender_id", "channel", "files", "note"].map(column);
+
+    let mut items = Vec::new();
+    let mut skipped = 0;
+    for record in records.filter(|r| r.iter().any(|f| !f.is_empty())) {
+        let get = |i: Option<usize>| i.and_then(|i| record.get(i)).cloned().unwrap_or_default();
+        let Ok(ts) = get(Some(timestamp)).trim().parse::<u64>() else {
+            skipped += 1;
+            continue;
+        };
+        let [sender, sender_id, channel, files, note] = optional.map(&get);
+        let files = match files.trim() {
+            "" => None,
+            json => match serde_json::from_str(json) {
+                Ok(files) => Some(files),
+                Err(_) => {
+                    skipped += 1;
+                    continue;
+                }
+            },
+        };
+        let item = ClipboardPayload {
+            id: get(Some(id)),
+            text: get(Some(text)),
+            files,
+            timestamp: ts,
+            sender,
+            sender_id,
+            source: None,
+            trace: None,
+            channel: if channel.is_empty() { crate::protocol::DEFAULT_CHANNEL.to_string() } else { channel },
+            link: None,
+            content: None,
+            auth: None,
+        };
+        items.push(ArchivedItem { item, note: (!note.is_empty()).then_some(note) });
+    }
+    Ok((items, skipped))
+}
@@ -0,0 +1,65 @@
+//! Translations for the strings the backend shows itself: notifications and the tray menu.
+//!
+//! Locales are flat JSON maps embedded from `locales/<code>.json`, keyed by message id, with
+//! `{name}` placeholders. The language follows the system locale unless the `locale` setting
+//! pins one. A key missing from the active locale falls back to English, then to the key.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+const FALLBACK: &str = "en";
+
+static LOCALES: Lazy<HashMap<&'static str, HashMap<String, String>>> = Lazy::new(|| {
+    [
+        ("en", include_str!("../locales/en.json")),
+        ("de", include_str!("../locales/de.json")),
+        ("es", include_str!("../locales/es.json")),
+        ("fr", include_str!("../locales/fr.json")),
+        ("it", include_str!("../locales/it.json")),
+    ]
+    .into_iter()
+    .map(|(code, json)| (code, serde_json::from_str(json).unwrap_or_default()))
+    .collect()
+});
+
+static CURRENT: Lazy<RwLock<&'static str>> = Lazy::new(|| RwLock::new(detect()));
+
+/// Locale codes with a translation, sorted.
+pub fn available() -> Vec<&'static str> {
+    let mut codes: Vec<&'static str> = LOCALES.keys().copied().collect();
+    codes.sort();
+    codes
+}
+
+// "de-AT", "de_AT.UTF-8" and "de" all resolve to "de"
+fn resolve(requested: &str) -> Option<&'static str> {
+    let language = requested.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+    LOCALES.keys().copied().find(|code| *code == language)
+}
+
+fn detect() -> &'static str {
+    sys_locale::get_locale().as_deref().and_then(resolve).unwrap_or(FALLBACK)
+}
+
+/// Use `locale` (None or unknown = follow the system). Returns the locale now in effect.
+pub fn set_locale(locale: Option<&str>) -> &'static str {
+    let code = locale.and_then(resolve).unwrap_or_else(detect);
+    *CURRENT.write().unwrap() = code;
+    code
+}
+
+pub fn current() -> &'static str {
+    *CURRENT.read().unwrap()
+}
+
+/// The message for `key` in the current locale.
+pub fn t(key: &str) -> String {
+    let lookup = |code: &str| LOCALES.get(code).and_then(|m| m.get(key)).cloned();
+    lookup(current()).or_else(|| lookup(FALLBACK)).unwrap_or_else(|| key.to_string())
+}
+
+/// `t` with `{name}` placeholders filled in.
+pub fn tf(key: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(t(key), |message, (name, value)| message.replace(&format!("{{{}}}", name), value))
+}
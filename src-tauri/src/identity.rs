@@ -0,0 +1,89 @@
+//! This device's own signing key (Ed25519), and checking other devices' signatures.
+//!
+//! The cluster key only proves membership: every member holds it, so anything sealed or MACed
+//! with it could have come from any of them. Each device therefore also has a key pair of its
+//! own, created on first run (`device_key.bin`). The public half is handed over inside the PIN
+//! handshake, sealed with the session key (PairConfirm from the joiner, Welcome from the member
+//! that let it in), and passed on in Welcome peer lists, membership announcements and peer
+//! sync. A membership announcement is signed by the member that admitted the device (see
+//! senderauth.rs), and only pins a key for a device we had none for. A device's own
+//! PeerDiscovery only fills it in when we have none yet (trust on first use). Once known, a
+//! peer's key is pinned: a different one is ignored until the device pairs with us again.
+
+use crate::state::AppState;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use tauri::AppHandle;
+
+/// Load this profile's key, creating it on first run.
+pub fn load(app: &AppHandle, state: &AppState) {
+    let key = match crate::storage::load_device_key(app) {
+        Some(seed) => SigningKey::from_bytes(&seed),
+        None => {
+            let key = SigningKey::generate(&mut rand::rngs::OsRng);
+            crate::storage::save_device_key(app, &key.to_bytes());
+            tracing::info!("Generated a new device signing key");
+            key
+        }
+    };
+    *state.device_key.lock().unwrap() = Some(key);
+}
+
+/// Our public key (Base64), as advertised to peers.
+pub fn public_key(state: &AppState) -> Option<String> {
+    let key = state.device_key.lock().unwrap();
+    key.as_ref().map(|k| BASE64.encode(k.verifying_key().as_bytes()))
+}
+
+/// Sign `message` with our key (Base64 signature). None before the key is loaded.
+pub fn sign(state: &AppState, message: &[u8]) -> Option<String> {
+    let key = state.device_key.lock().unwrap();
+    key.as_ref().map(|k| BASE64.encode(k.sign(message).to_bytes()))
+}
+
+fn verifying_key(public_key: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = BASE64.decode(public_key).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+/// Whether `signature` (Base64) is `public_key`'s signature over `message`.
+pub fn verify(public_key: &str, message: &[u8], signature: &str) -> bool {
+    let Some(key) = verifying_key(public_key) else {
+        return false;
+    };
+    let Some(signature) = BASE64.decode(signature).ok().and_then(|s| Signature::from_slice(&s).ok()) else {
+        return false;
+    };
+    key.verify(message, &signature).is_ok()
+}
+
+/// Whether `public_key` parses as an Ed25519 public key.
+pub fn is_valid_public_key(public_key: &str) -> bool {
+    verifying_key(public_key).is_some()
+}
+
+/// Our public key, sealed with a handshake's session key for PairConfirm/Welcome. Empty when
+/// there's no key to send.
+pub fn seal(state: &AppState, keys: &crate::crypto::SessionKeys) -> Vec<u8> {
+    public_key(state)
+        .and_then(|k| crate::crypto::encrypt(&keys.encryption, k.as_bytes()).ok())
+        .unwrap_or_default()
+}
+
+/// The other side's public key from PairConfirm/Welcome. None for older versions (empty) or
+/// anything that doesn't open with the session key.
+pub fn open(keys: &crate::crypto::SessionKeys, sealed: &[u8]) -> Option<String> {
+    if sealed.is_empty() {
+        return None;
+    }
+    let plain = crate::crypto::decrypt(&keys.encryption, sealed).ok()?;
+    String::from_utf8(plain).ok().filter(|k| is_valid_public_key(k))
+}
+
+/// The key to keep for a peer: a pinned key stays; otherwise whatever was offered.
+pub fn pinned(existing: Option<&str>, offered: Option<String>) -> Option<String> {
+    match existing {
+        Some(key) => Some(key.to_string()),
+        None => offered.filter(|k| is_valid_public_key(k)),
+    }
+}
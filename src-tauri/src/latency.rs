@@ -0,0 +1,117 @@
+//! Opt-in clipboard latency tracing.
+//!
+//! With `latency_tracing` on, a sender stamps its payloads with the send time, every relay
+//! appends its own hop, and the receiver records the end-to-end time when it applies the
+//! payload. Timestamps come from different machines, so clock skew shows up in the numbers
+//! (it can even make a leg negative); compare peers against each other rather than trusting
+//! absolute values.
+
+use crate::protocol::{ClipboardPayload, TraceHop};
+use crate::state::AppState;
+
+/// Samples kept per origin device.
+const MAX_SAMPLES: usize = 50;
+/// Relays stop adding hops past this (bounds payload growth on relay loops).
+const MAX_HOPS: usize = 16;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct LatencySample {
+    pub payload_id: String,
+    pub received_at_ms: u64,
+    pub total_ms: i64,
+    // Time spent on each leg: origin -> relay ... -> us
+    pub legs_ms: Vec<i64>,
+    pub via: Vec<String>, // Relay device IDs
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct PeerLatency {
+    pub peer_id: String,
+    pub hostname: Option<String>,
+    pub samples: usize,
+    pub last_ms: i64,
+    pub min_ms: i64,
+    pub avg_ms: i64,
+    pub p95_ms: i64,
+    pub max_ms: i64,
+    pub relayed: usize, // Samples that went through at least one relay
+    pub last: LatencySample,
+}
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Origin hop for a payload we're about to send (None when tracing is off).
+pub fn start_trace(state: &AppState) -> Option<Vec<TraceHop>> {
+    if !state.settings.lock().unwrap().latency_tracing {
+        return None;
+    }
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    Some(vec![TraceHop { device_id, at_ms: now_ms() }])
+}
+
+/// Stamp a relay hop onto an already-traced payload.
+pub fn add_hop(trace: &mut Option<Vec<TraceHop>>, device_id: &str) {
+    if let Some(hops) = trace.as_mut().filter(|h| h.len() < MAX_HOPS) {
+        hops.push(TraceHop { device_id: device_id.to_string(), at_ms: now_ms() });
+    }
+}
+
+/// Record a traced payload we just applied.
+pub fn record(state: &AppState, payload: &ClipboardPayload) {
+    let Some(hops) = payload.trace.as_ref().filter(|h| !h.is_empty()) else {
+        return;
+    };
+    let received_at_ms = now_ms();
+    let mut stamps: Vec<u64> = hops.iter().map(|h| h.at_ms).collect();
+    stamps.push(received_at_ms);
+    let legs_ms: Vec<i64> = stamps.windows(2).map(|w| w[1] as i64 - w[0] as i64).collect();
+    let sample = LatencySample {
+        payload_id: payload.id.clone(),
+        received_at_ms,
+        total_ms: received_at_ms as i64 - hops[0].at_ms as i64,
+        legs_ms,
+        via: hops[1..].iter().map(|h| h.device_id.clone()).collect(),
+    };
+    tracing::debug!("Clipboard {} from {} took {}ms ({:?})", payload.id, hops[0].device_id, sample.total_ms, sample.legs_ms);
+
+    let mut samples = state.latency_samples.lock().unwrap();
+    let entry = samples.entry(hops[0].device_id.clone()).or_default();
+    if entry.len() >= MAX_SAMPLES {
+        entry.pop_front();
+    }
+    entry.push_back(sample);
+}
+
+/// Per-origin summary of the recorded samples.
+pub fn report(state: &AppState) -> Vec<PeerLatency> {
+    let samples = state.latency_samples.lock().unwrap().clone();
+    let peers = state.get_peers();
+    let mut out: Vec<PeerLatency> = samples
+        .into_iter()
+        .filter_map(|(peer_id, entries)| {
+            let last = entries.back()?.clone();
+            let mut totals: Vec<i64> = entries.iter().map(|s| s.total_ms).collect();
+            totals.sort_unstable();
+            let p95_idx = ((totals.len() as f64 * 0.95).ceil() as usize).saturating_sub(1);
+            Some(PeerLatency {
+                hostname: peers.get(&peer_id).map(|p| p.hostname.clone()),
+                peer_id,
+                samples: totals.len(),
+                last_ms: last.total_ms,
+                min_ms: totals[0],
+                avg_ms: totals.iter().sum::<i64>() / totals.len() as i64,
+                p95_ms: totals[p95_idx],
+                max_ms: totals[totals.len() - 1],
+                relayed: entries.iter().filter(|s| !s.via.is_empty()).count(),
+                last,
+            })
+        })
+        .collect();
+    out.sort_by_key(|r| std::cmp::Reverse(r.avg_ms));
+    out
+}
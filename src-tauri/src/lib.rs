@@ -1,14 +1,71 @@
+mod autostart;
+mod badge;
+mod cache;
+mod classify;
 mod clipboard;
+mod clipwrite;
+mod compression;
+mod compose;
+mod config;
+mod conntest;
+mod control;
+mod crash;
 #[cfg(target_os = "linux")]
 mod dbus;
 mod crypto;
 mod discovery;
+mod dryrun;
+#[cfg(feature = "emulator")]
+pub mod emulator;
+mod error;
+mod events;
+mod guest;
+mod handshake;
+mod history;
+mod i18n;
+mod identity;
+mod latency;
+mod logs;
+mod migration;
+mod netscope;
+mod offers;
+mod onboarding;
+mod power;
+mod presence;
 mod peer;
+mod peerstats;
+mod peersync;
+mod pin;
+mod platform;
+mod preview;
 mod protocol;
+mod proxy;
+mod queue;
+mod recovery;
+mod redact;
+mod remote;
+mod resume;
+mod retry;
+mod scan;
+mod screenlock;
+mod senderauth;
+mod services;
+#[cfg(test)]
+mod sim;
+mod source;
+mod spool;
+mod staging;
 mod state;
 mod storage;
 mod transport;
 mod tray;
+mod tui;
+mod unfurl;
+mod unread;
+mod update;
+mod validation;
+mod winclip;
+mod wipe;
 
 use clap::Parser;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -17,11 +74,12 @@ use tauri::Listener;
 use local_ip_address::list_afinet_netifas;
 
 use tauri_plugin_clipboard::Clipboard;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncBufReadExt, AsyncSeekExt, BufReader};
 use std::str::FromStr;
 use std::path::PathBuf;
 use tokio::fs::File;
 use crate::protocol::Message;
+use crate::error::AppError;
 
 
 #[derive(Parser, Debug)]
@@ -33,13 +91,38 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     debug: bool,
 
-    #[arg(long, default_value_t = false)]
+    // Start in the tray without showing the window (autostart passes this)
+    #[arg(long, visible_alias = "hidden", default_value_t = false)]
     minimized: bool,
 
     #[arg(long)]
     theme: Option<String>,
+
+    // Alternate config file (default: clustercut.toml in the app config dir)
+    #[arg(long)]
+    config: Option<String>,
+
+    // Profile name: keys, peers and settings are stored per profile
+    #[arg(long)]
+    profile: Option<String>,
+
+    // Dev mode: run several instances on one host (port offset, isolated storage, no single-instance lock)
+    #[arg(long)]
+    instance: Option<u16>,
+
+    // Terminal UI instead of the window (for servers / SSH sessions). Still needs the GUI
+    // libraries and, on Linux, a display server (Xvfb will do); see tui.rs
+    #[arg(long, default_value_t = false)]
+    tui: bool,
+
+    // Scripting: talk to the running instance instead of starting one
+    #[command(subcommand)]
+    command: Option<crate::control::CliCommand>,
 }
 
+// Fixed port for discovery (overridable via the config file)
+pub(crate) const DEFAULT_PORT: u16 = 4654;
+
 #[tauri::command]
 async fn get_theme_override() -> Option<String> {
     std::env::var("CLUSTERCUT_THEME").ok()
@@ -50,63 +133,15 @@ async fn get_current_theme(state: tauri::State<'_, AppState>) -> Result<Option<S
     Ok(state.current_theme.lock().unwrap().clone())
 }
 
+// Flatpak only (see autostart.rs); None = not handled, use the autostart plugin
 #[tauri::command]
-async fn configure_autostart(app_handle: tauri::AppHandle, enable: bool) -> Result<bool, String> {
-    // Check if running in Flatpak
-    if cfg!(target_os = "linux") && std::env::var("FLATPAK_ID").is_ok() {
-        // Explicitly ignore app_handle to silence warnings
-        let _ = app_handle;
-
-        let id = std::env::var("FLATPAK_ID").unwrap();
-        
-        let base_config = std::env::var("XDG_CONFIG_HOME").ok()
-            .map(PathBuf::from)
-            .or_else(|| std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config")))
-            .ok_or("Could not determine config directory")?;
-            
-        let autostart_dir = base_config.join("autostart");
-        let file_path = autostart_dir.join(format!("{}.desktop", id));
-
-        if enable {
-            if !autostart_dir.exists() {
-                std::fs::create_dir_all(&autostart_dir).map_err(|e| e.to_string())?;
-            }
-            
-            // X-Flatpak tag and Exec command are key
-            // Appending --minimized is tricky here because we don't know if the user WANTS it minimized always?
-            // The requirement says: "Make this command line argument default when creating the launcher/service/whatever for auto-startup."
-            let content = format!(
-                "[Desktop Entry]\nType=Application\nName=ClusterCut\nComment=ClusterCut Clipboard Sync\nExec=flatpak run {} --minimized\nX-Flatpak={}\nTerminal=false\nCategories=Utility;\n",
-                id, id
-            );
-            std::fs::write(&file_path, content).map_err(|e| e.to_string())?;
-        } else {
-            if file_path.exists() {
-                std::fs::remove_file(&file_path).map_err(|e| e.to_string())?;
-            }
-        }
-        Ok(true) // Handled
-    } else {
-        Ok(false) // Not handled
-    }
+async fn configure_autostart(enable: bool) -> Result<Option<bool>, String> {
+    crate::autostart::configure(enable).await
 }
 
 #[tauri::command]
-async fn get_autostart_state(app_handle: tauri::AppHandle) -> Result<Option<bool>, String> {
-    if cfg!(target_os = "linux") && std::env::var("FLATPAK_ID").is_ok() {
-         let _ = app_handle;
-         let id = std::env::var("FLATPAK_ID").unwrap();
-         
-         let base_config = std::env::var("XDG_CONFIG_HOME").ok()
-            .map(PathBuf::from)
-            .or_else(|| std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config")))
-            .ok_or("Could not determine config directory")?;
-
-        let file_path = base_config.join("autostart").join(format!("{}.desktop", id));
-        Ok(Some(file_path.exists()))
-    } else {
-        Ok(None)
-    }
+async fn get_autostart_state() -> Result<Option<bool>, String> {
+    Ok(crate::autostart::state())
 }
 
 #[tauri::command]
@@ -170,7 +205,7 @@ fn init_logging() -> Args {
         Ok(a) => a,
         Err(_) => {
             // Keep default if parsing fails (e.g. extra args)
-            Args { log_level: "info".to_string(), debug: false, minimized: false, theme: None }
+            Args { log_level: "info".to_string(), debug: false, minimized: false, theme: None, config: None, profile: None, instance: None, tui: false, command: None }
         }
     };
 
@@ -198,18 +233,9 @@ fn init_logging() -> Args {
         .compact(); // Compact format
 
     // 3. Setup File Layer (Rolling Daily)
-    // We need a path. Since we are before AppHandle, we can't easily get AppDataDir.
-    // We'll trust XDG or standard paths or just current dir for now?
-    // User requested "sinks".
-    // Better to use `tauri::api::path::app_log_dir`? No, we don't have app handle yet.
-    // Let's us `directories` crate? Or just `.logs` in CWD for development as requested?
-    // "We need each log line to be timestamped, and include hostname."
-    
-    // Use temp_dir for logs to ensure we can write even if CWD is / (macOS Bundle)
-    let log_dir = std::env::temp_dir().join("ClusterCutLogs");
-    let file_appender = tracing_appender::rolling::daily(&log_dir, "clustercut.log");
+    // No AppHandle yet: start in the temp dir, setup moves it to app_log_dir (see logs.rs)
     let file_layer = tracing_subscriber::fmt::layer()
-        .with_writer(file_appender)
+        .with_writer(crate::logs::init())
         .with_ansi(false)
         .with_target(true);
 
@@ -231,7 +257,8 @@ fn init_logging() -> Args {
 
     tracing_subscriber::registry()
         .with(filter)
-        .with(stdout_layer)
+        // The TUI owns the terminal; logs still go to the file
+        .with((!args.tui && args.command.is_none()).then_some(stdout_layer))
         .with(file_layer)
         .init();
         
@@ -299,7 +326,7 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
         // We'll mimic the simpler notification but use 'activationType="protocol"' to wake app.
         
         // Dynamic Actions
-        let mut actions_xml = format!(r#"<action content="Open" arguments="clustercut://action/show?view={}" activationType="protocol"/>"#, target_view);
+        let mut actions_xml = format!(r#"<action content="{}" arguments="clustercut://action/show?view={}" activationType="protocol"/>"#, i18n::t("notify.action.open"), target_view);
 
         if let NotificationPayload::DownloadAvailable { msg_id, file_count, peer_id } = &payload {
              // Encode params if needed, but for now simple format
@@ -309,10 +336,18 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
              // We need to escape '&' to '&amp;' in the URL when putting it into XML attribute.
              let download_args_escaped = download_args.replace("&", "&amp;");
              
-             let download_action = format!(r#"<action content="Download" arguments="{}" activationType="protocol"/>"#, download_args_escaped);
+             let download_action = format!(r#"<action content="{}" arguments="{}" activationType="protocol"/>"#, i18n::t("notify.action.download"), download_args_escaped);
              actions_xml.push_str(&download_action);
         }
 
+        if let NotificationPayload::FileReceived { path } = &payload {
+             // Percent-encoded, so there's nothing left to escape for XML
+             let encoded: String = url::form_urlencoded::byte_serialize(path.as_bytes()).collect();
+             for (action, label) in [("open-file", "notify.action.open_file"), ("reveal-file", "notify.action.show_in_folder")] {
+                 actions_xml.push_str(&format!(r#"<action content="{}" arguments="clustercut://action/{}?path={}" activationType="protocol"/>"#, i18n::t(label), action, encoded));
+             }
+        }
+
         let xml = format!(r#"
 <toast activationType="protocol" launch="clustercut://action/show?view={}">
     <visual>
@@ -379,6 +414,10 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
         let title = title.to_string();
         let body = body.to_string();
         let view = target_view.to_string();
+        let file_path = match &payload {
+            NotificationPayload::FileReceived { path } => Some(path.clone()),
+            _ => None,
+        };
         let app = app_handle.clone();
         
         static NOTIFICATION_MANAGER: std::sync::OnceLock<std::sync::Arc<dyn user_notify::NotificationManager>> = std::sync::OnceLock::new();
@@ -408,21 +447,31 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
                                         view = v.clone();
                                     }
                                     
-                                    #[derive(serde::Serialize, Clone)]
-                                    struct Payload {
-                                        view: String,
-                                    }
-
-                                    let _ = w.emit("notification-clicked", Payload { view });
-                                    let _ = w.unminimize();
-                                    let _ = w.show();
-                                    let _ = w.set_focus();
+                                    crate::events::emit(&w, crate::events::NotificationClicked { view });
                                 });
+                                show_main_window(&app_handle_callback);
+                            }
+                            user_notify::NotificationResponseAction::Other(action) => {
+                                if let Some(path) = response.user_info.get("path") {
+                                    crate::open_received_file(&app_handle_callback, path, action == "reveal-file");
+                                }
                             }
                             _ => {}
                         }
                     }),
-                    vec![] 
+                    vec![user_notify::NotificationCategory {
+                        identifier: "file-received".to_string(),
+                        actions: vec![
+                            user_notify::NotificationCategoryAction::Action {
+                                identifier: "open-file".to_string(),
+                                title: i18n::t("notify.action.open_file"),
+                            },
+                            user_notify::NotificationCategoryAction::Action {
+                                identifier: "reveal-file".to_string(),
+                                title: i18n::t("notify.action.show_in_folder"),
+                            },
+                        ],
+                    }]
                 ) {
                     Ok(_) => tracing::info!("[Notification] Callback registered successfully."),
                     Err(e) => tracing::error!("[Notification] Callback registration failed: {:?}" , e),
@@ -521,6 +570,10 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
                 // Add Context
                 let mut map = std::collections::HashMap::new();
                 map.insert("view".to_string(), view);
+                if let Some(path) = file_path {
+                    map.insert("path".to_string(), path);
+                    notification = notification.set_category_id("file-received");
+                }
                 notification = notification.set_user_info(map);
 
                 match manager.send_notification(notification).await {
@@ -576,11 +629,15 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
             }
             
             // Actions
-            notification.action("default", "Open");
-            notification.action("open_btn", "Open");
+            notification.action("default", &i18n::t("notify.action.open"));
+            notification.action("open_btn", &i18n::t("notify.action.open"));
             
             if let NotificationPayload::DownloadAvailable { .. } = &payload {
-                 notification.action("download", "Download");
+                 notification.action("download", &i18n::t("notify.action.download"));
+            }
+            if let NotificationPayload::FileReceived { .. } = &payload {
+                 notification.action("open_file", &i18n::t("notify.action.open_file"));
+                 notification.action("reveal_file", &i18n::t("notify.action.show_in_folder"));
             }
 
             if let Ok(id) = std::env::var("FLATPAK_ID") {
@@ -601,18 +658,8 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
                 if action == "default" || action == "Open" || action == "open_btn" {
                     tracing::info!("Emitting 'notification-clicked' event");
                     
-                    #[derive(serde::Serialize, Clone)]
-                    struct Payload {
-                        view: String,
-                    }
-                    
-                    let _ = app.emit("notification-clicked", Payload { view: view.clone() });
-                    
-                    let _ = app.get_webview_window("main").map(|w| {
-                        let _ = w.unminimize();
-                        let _ = w.show();
-                        let _ = w.set_focus();
-                    });
+                    crate::events::emit(&app, crate::events::NotificationClicked { view: view.clone() });
+                    show_main_window(&app);
                 } else if action == "download" || action == "Download" {
                      if let NotificationPayload::DownloadAvailable { msg_id: _, file_count, peer_id } = &payload {
                          tracing::info!("User clicked Download. Triggering download for {} files...", file_count);
@@ -631,23 +678,23 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
                          let count = *file_count;
                          
                          tauri::async_runtime::spawn(async move {
-                             let _ = app.emit("notification-clicked", serde_json::json!({ "view": "history" }));
-                             let _ = app.get_webview_window("main").map(|w| {
-                                 let _ = w.unminimize();
-                                 let _ = w.show();
-                                 let _ = w.set_focus();
-                             });
+                             crate::events::emit(&app, crate::events::NotificationClicked { view: "history".to_string() });
+                             crate::show_main_window(&app);
 
                              tracing::info!("Starting background download sequence override...");
                              for i in 0..count {
-                                  if let Err(e) = crate::request_file_internal(&state_clone, msg_id.clone(), i, peer_id_clone.clone()).await {
-                                      tracing::error!("Failed to auto-download file {}/{}: {}", i, count, e);
+                                  if let Err(e) = crate::request_file_internal(&app, &state_clone, msg_id.clone(), i, peer_id_clone.clone()).await {
+                                      crate::error::report(&app, "file-download", &e);
                                   } else {
                                       tracing::info!("Successfully requested file {}/{}", i, count);
                                   }
                              }
                          });
                      }
+                } else if action == "open_file" || action == "reveal_file" {
+                     if let NotificationPayload::FileReceived { path } = &payload {
+                         crate::open_received_file(&app, path, action == "reveal_file");
+                     }
                 }
             });
         });
@@ -656,6 +703,299 @@ pub(crate) fn send_notification(app_handle: &tauri::AppHandle, title: &str, body
 
 }
 
+// Open a received file, or show it in its folder, for a notification action. The Windows
+// actions arrive as deep links, so only paths we actually received are accepted.
+pub(crate) fn open_received_file(app: &tauri::AppHandle, path: &str, reveal: bool) {
+    use tauri_plugin_opener::OpenerExt;
+    let state = app.state::<AppState>();
+    let received = state.received_files.lock().unwrap().values().any(|p| p == path);
+    if !received || !std::path::Path::new(path).exists() {
+        tracing::warn!("Not opening {}: it isn't a received file or it's gone", crate::redact::text(path));
+        return;
+    }
+    let result = if reveal {
+        app.opener().reveal_item_in_dir(path)
+    } else {
+        app.opener().open_path(path, None::<&str>)
+    };
+    if let Err(e) = result {
+        crate::error::report(app, "open-file", &AppError::Storage(e.to_string()));
+    }
+}
+
+// clustercut://action/open-file?path=.. and clustercut://action/reveal-file?path=.. (Windows toast actions)
+fn handle_file_deep_link(app: &tauri::AppHandle, url: &str) {
+    let parsed = match url::Url::parse(url) {
+        Ok(u) => u,
+        Err(e) => {
+            tracing::warn!("Invalid file deep link {}: {}", url, e);
+            return;
+        }
+    };
+    let reveal = match (parsed.host_str(), parsed.path()) {
+        (Some("action"), "/open-file") => false,
+        (Some("action"), "/reveal-file") => true,
+        _ => return,
+    };
+    match parsed.query_pairs().find(|(k, _)| k == "path") {
+        Some((_, path)) => open_received_file(app, &path, reveal),
+        None => tracing::warn!("File deep link missing path: {}", url),
+    }
+}
+
+// clustercut://action/download?msg_id=..&peer_id=..&file_count=.. (Windows toast "Download" action)
+fn handle_download_deep_link(app: &tauri::AppHandle, url: &str) {
+    let parsed = match url::Url::parse(url) {
+        Ok(u) => u,
+        Err(e) => {
+            tracing::warn!("Invalid download deep link {}: {}", url, e);
+            return;
+        }
+    };
+    if parsed.host_str() != Some("action") || parsed.path() != "/download" {
+        return;
+    }
+
+    let param = |name: &str| parsed.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.into_owned());
+    let (Some(msg_id), Some(peer_id)) = (param("msg_id"), param("peer_id")) else {
+        tracing::warn!("Download deep link missing msg_id/peer_id: {}", url);
+        return;
+    };
+
+    let state = app.state::<AppState>().inner().clone();
+
+    // Only download what the peer actually offered us
+    let file_count = match state.remote_files.lock().unwrap().get(&msg_id) {
+        Some(files) => files.len(),
+        None => {
+            tracing::warn!("Download deep link for unknown message {}. Ignoring.", msg_id);
+            return;
+        }
+    };
+    let peer_known = state.get_peers().contains_key(&peer_id) || state.known_peers.lock().unwrap().contains_key(&peer_id);
+    if !peer_known {
+        tracing::warn!("Download deep link for unknown peer {}. Ignoring.", peer_id);
+        return;
+    }
+
+    tracing::info!("Toast Download action: requesting {} files of {} from {}", file_count, msg_id, peer_id);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for i in 0..file_count {
+            match request_file_internal(&app, &state, msg_id.clone(), i, peer_id.clone()).await {
+                Ok(()) => {
+                    crate::events::emit(&app, crate::events::FileDownloadRequested { id: msg_id.clone(), file_index: i, file_count, peer_id: peer_id.clone() });
+                }
+                Err(e) => {
+                    crate::error::report(&app, "file-download", &e);
+                    crate::events::emit(&app, crate::events::FileDownloadFailed { id: msg_id.clone(), file_index: i, error: e.to_string() });
+                }
+            }
+        }
+    });
+}
+
+// Follow mDNS events until the daemon behind `receiver` shuts down (at exit, or when
+// switch_profile restarts discovery)
+fn spawn_discovery_loop(d_handle: tauri::AppHandle, d_state: AppState, receiver: mdns_sd::Receiver<mdns_sd::ServiceEvent>) {
+    tauri::async_runtime::spawn(async move {
+        while let Ok(event) = receiver.recv_async().await {
+            match event {
+                mdns_sd::ServiceEvent::ServiceResolved(info) => {
+                    if let Some(ip) = info.get_addresses().iter().next() {
+                        let id = info
+                            .get_property_val_str("id")
+                            .unwrap_or("unknown")
+                            .to_string();
+
+                        let local_id =
+                            { d_state.local_device_id.lock().unwrap().clone() };
+                        if id == local_id || d_state.is_blocked(&id) {
+                            continue;
+                        }
+                        let namespace = d_state.discovery.lock().unwrap().as_ref().and_then(|d| d.namespace().map(str::to_string));
+                        if !crate::discovery::in_namespace(info.get_property_val_str("ns"), namespace.as_deref()) {
+                            tracing::debug!("[Discovery] Ignoring peer {} (namespace {:?}, ours {:?})", id, info.get_property_val_str("ns"), namespace);
+                            continue;
+                        }
+                        let in_scope = ip.to_string().parse::<std::net::IpAddr>().map_or(true, |a| crate::netscope::allows_peer(&d_state, a));
+                        if !in_scope {
+                            tracing::debug!("[Discovery] Ignoring peer {} at {} (outside allowed networks)", id, ip);
+                            continue;
+                        }
+
+                        // DEBOUNCE: Cancel any pending removal for this peer
+                        {
+                            let mut pending = d_state.pending_removals.lock().unwrap();
+                            if pending.remove(&id).is_some() {
+                                tracing::debug!("[Discovery] Debounce: Cancelled pending removal for reappearing peer {}", id);
+                            }
+                        }
+
+                        let network_name_prop = info
+                            .get_property_val_str("n")
+                            .map(|s| s.to_string());
+                        
+                        if let Some(n) = &network_name_prop {
+                            tracing::debug!("Discovered peer {} with network name: {}", id, n);
+                        } else {
+                            tracing::warn!("Discovered peer {} WITHOUT network name (properties: {:?})", id, info.get_properties());
+                        }
+
+                        // Lock known_peers to prevent race with PairRequest
+                        let kp = d_state.known_peers.lock().unwrap();
+                        let is_known = kp.contains_key(&id);
+
+                        // Extract hostname from property or fallback to mDNS hostname
+                        let h_prop = info.get_property_val_str("h");
+                        let hostname_prop = h_prop
+                            .or_else(|| info.get_property_val_str("hostname"))
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| info.get_hostname().to_string());
+
+                        tracing::info!("[Discovery] Peer {} resolved. 'h' prop: {:?}, Final hostname: {}", id, h_prop, hostname_prop);
+
+                        // Every address the record lists (Wi-Fi and Ethernet, IPv4 and IPv6)
+                        let now = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs();
+                        let addresses = info
+                            .get_addresses()
+                            .iter()
+                            .filter_map(|a| a.to_string().parse::<std::net::IpAddr>().ok())
+                            .map(|a| crate::peer::KnownAddress {
+                                last_seen: now,
+                                ..crate::peer::KnownAddress::new(std::net::SocketAddr::new(a, info.get_port()))
+                            })
+                            .collect();
+                        let mut peer = Peer {
+                            id: id.clone(),
+                            ip: ip.to_string().parse().unwrap_or(std::net::IpAddr::V4(
+                                std::net::Ipv4Addr::new(127, 0, 0, 1),
+                            )),
+                            port: info.get_port(),
+                            hostname: hostname_prop,
+                            last_seen: now,
+                            is_trusted: is_known,
+                            is_manual: false, // Discovered via mDNS
+                            network_name: network_name_prop,
+                            signature: None,
+                            // Guest status only comes from the (signed) membership record
+                            guest_expires_at: kp.get(&id).and_then(|p| p.guest_expires_at),
+                            capabilities: kp.get(&id).and_then(|p| p.capabilities),
+                            platform: crate::platform::from_txt(|k| info.get_property_val_str(k))
+                                .or_else(|| kp.get(&id).and_then(|p| p.platform.clone())),
+                            addresses,
+                            observed_addr: None,
+                            public_key: None,
+                        };
+                        // Keep the reachability history from earlier runs
+                        if let Some(known) = kp.get(&id) {
+                            peer.merge_addresses(known);
+                        }
+                        crate::platform::check_skew(&peer);
+
+                        let peer = d_state.add_peer(peer);
+                        crate::events::emit(&d_handle, crate::events::PeerUpdate(&peer));
+                        crate::presence::set(&d_handle, &d_state, &peer, crate::presence::Presence::Online);
+
+                        // Trigger Notification
+                        {
+                            let should_notify = {
+                                let local_net = d_state.network_name.lock().unwrap();
+                                if let Some(remote_net) = &peer.network_name {
+                                    *remote_net == *local_net
+                                } else {
+                                    false
+                                }
+                            };
+
+                            if should_notify {
+                                if d_state.settings.lock().unwrap().notifications.device_join {
+                                    // Suppress notifications during startup
+                                    if d_state.should_notify() {
+                                        tracing::info!("[Notification] Triggering 'Device Joined' for discovered peer: {}", peer.hostname);
+                                        send_notification(&d_handle, &i18n::t("notify.device_joined.title"), &i18n::tf("notify.device_joined.body", &[("name", &peer.hostname)]), false, Some(1), "devices", NotificationPayload::None);
+                                    } else {
+                                        tracing::debug!("[Notification] Device join notification suppressed by startup timer for peer: {}", peer.hostname);
+                                    }
+                                } else {
+                                    tracing::debug!("[Notification] Device join notification suppressed by settings for discovered peer: {}", peer.hostname);
+                                }                                      } else {
+                                // tracing::debug!("[Notification] suppressed - different cluster name.");
+                            }
+                        }
+                        // Lock drops here
+                    }
+
+                }
+                mdns_sd::ServiceEvent::ServiceRemoved(_ty, fullname) => {
+                    let id =
+                        fullname.split('.').next().unwrap_or("unknown").to_string();
+                    tracing::info!("[Discovery] Service Removed: {} -> ID: {}", fullname, id);
+                    
+                    // Safety Check: If we effectively just saw this peer (in the last 2 seconds),
+                    // ignore this removal as a "phantom" or out-of-order packet.
+                    // This happens often when devices re-announce themselves.
+                    {
+                        let peers = d_state.peers.lock().unwrap();
+                        if let Some(peer) = peers.get(&id) {
+                            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+                            if now.saturating_sub(peer.last_seen) < 2 {
+                                 tracing::warn!("[Discovery] Ignoring ServiceRemoved for {} (seen {}s ago) - likely phantom.", id, now.saturating_sub(peer.last_seen));
+                                 return;
+                            }
+                        }
+                    }
+
+                    // DEBOUNCE: Don't remove immediately; give the peer a chance to reappear.
+                    let nonce = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+                    {
+                        let mut pending = d_state.pending_removals.lock().unwrap();
+                        pending.insert(id.clone(), nonce);
+                    }
+                    
+                    let r_state = d_state.clone();
+                    let r_handle = d_handle.clone();
+                    let r_id = id.clone();
+                    let debounce = crate::presence::tuning(&d_state).removal_debounce;
+                    
+                    tauri::async_runtime::spawn(async move {
+                        tokio::time::sleep(debounce).await;
+                        
+                        let mut pending = r_state.pending_removals.lock().unwrap();
+                        if let Some(n) = pending.get(&r_id) {
+                            if *n == nonce {
+                                // Confirmed! Still pending and nonce matches (not overwritten by newer removal?)
+                                pending.remove(&r_id);
+                                drop(pending); // Drop lock
+                                
+                                // Proceed with removal
+                                tracing::info!("[Discovery] Debounce expired. Removing peer {}", r_id);
+                                let removed = r_state.peers.lock().unwrap().remove(&r_id);
+                                if let Some(peer) = &removed {
+                                    check_and_notify_leave(&r_handle, &r_state, peer);
+                                }
+                                crate::events::emit(&r_handle, crate::events::PeerRemoved { id: r_id.clone() });
+                                // Known members stay listed as offline
+                                if let Some(peer) = &removed {
+                                    crate::presence::departed(&r_handle, &r_state, peer);
+                                }
+                            } else {
+                                tracing::debug!("[Discovery] Removal Debounce cancelled (Nonce mismatch) for {}", r_id);
+                            }
+                        } else {
+                            tracing::debug!("[Discovery] Removal Debounce cancelled (Entry gone) for {}", r_id);
+                        }
+                    });
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
 fn check_and_notify_leave(app_handle: &tauri::AppHandle, state: &AppState, peer: &Peer) {
     // Suppress leave notifications on startup too (though less likely to happen immediately)
     if !state.should_notify() {
@@ -669,25 +1009,45 @@ fn check_and_notify_leave(app_handle: &tauri::AppHandle, state: &AppState, peer:
         if let Some(remote_net) = &peer.network_name {
             if *remote_net == local_net {
                 tracing::info!("[Notification] Device Left: {}", peer.hostname);
-                send_notification(app_handle, "Device Left", &format!("{} has left the cluster", peer.hostname), false, Some(1), "devices", NotificationPayload::None);
+                send_notification(app_handle, &i18n::t("notify.device_left.title"), &i18n::tf("notify.device_left.body", &[("name", &peer.hostname)]), false, Some(1), "devices", NotificationPayload::None);
             }
         }
     }
 }
 
-fn gossip_peer(
+// How old a MembershipAnnounce may be before other members stop honouring it
+const MEMBERSHIP_ANNOUNCE_MAX_AGE_SECS: u64 = 120;
+
+// Vouch for a device we just welcomed, so the rest of the cluster trusts it without
+// waiting for its own signed PeerDiscovery
+fn announce_membership(
     new_peer: &Peer,
     state: &AppState,
     transport: &Transport,
     exclude_addr: Option<std::net::SocketAddr>,
 ) {
+    let mut announcement = crate::protocol::MembershipAnnouncement {
+        member: new_peer.clone(),
+        admitted_by: state.local_device_id.lock().unwrap().clone(),
+        admitted_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+        auth: None,
+    };
+    crate::senderauth::sign_membership(state, &mut announcement);
+    if announcement.auth.is_none() {
+        tracing::error!("Cannot announce {}: No device key loaded.", new_peer.id);
+        return;
+    }
+    let Some(cipher) = encrypt_cluster_message(state, &announcement) else {
+        tracing::error!("Cannot announce {}: No Cluster Key loaded.", new_peer.id);
+        return;
+    };
     let peers = state.get_peers();
-    let msg = Message::PeerDiscovery(new_peer.clone());
+    let msg = Message::MembershipAnnounce(cipher);
     let data = serde_json::to_vec(&msg).unwrap_or_default();
 
     for p in peers.values() {
-        // Don't gossip to the new peer itself
-        if p.id == new_peer.id {
+        // Not to the new peer itself, and not to guests (they can't decrypt cluster messages)
+        if p.id == new_peer.id || p.guest_expires_at.is_some() || !p.is_trusted {
             continue;
         }
         let addr = std::net::SocketAddr::new(p.ip, p.port);
@@ -700,7 +1060,7 @@ fn gossip_peer(
         
         tauri::async_runtime::spawn(async move {
             if let Err(e) = transport_clone.send_message(addr, &data_vec).await {
-                tracing::error!("Failed to gossip peer to {}: {}", addr, e);
+                tracing::error!("Failed to announce new member to {}: {}", addr, e);
             }
         });
     }
@@ -723,6 +1083,13 @@ fn get_network_pin(state: tauri::State<'_, AppState>) -> String {
     state.network_pin.lock().unwrap().clone()
 }
 
+#[tauri::command]
+fn generate_one_time_pin(ttl_secs: Option<u64>, state: tauri::State<'_, AppState>) -> crate::pin::OneTimePin {
+    let otp = crate::pin::generate_one_time(&state, ttl_secs.unwrap_or(crate::pin::DEFAULT_ONE_TIME_TTL_SECS));
+    tracing::info!("Generated one-time PIN (expires at {})", otp.expires_at);
+    otp
+}
+
 #[tauri::command]
 fn get_hostname(state: tauri::State<'_, AppState>) -> String {
     let settings = state.settings.lock().unwrap();
@@ -748,13 +1115,38 @@ fn save_settings(
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) {
-    *state.settings.lock().unwrap() = settings.clone();
+    let (scope_changed, namespace_changed) = {
+        let mut current = state.settings.lock().unwrap();
+        let changed = current.allowed_interfaces != settings.allowed_interfaces || current.allowed_subnets != settings.allowed_subnets;
+        let namespace_changed = current.discovery_namespace != settings.discovery_namespace;
+        *current = settings.clone();
+        (changed, namespace_changed)
+    };
     tracing::info!("Saving Settings: auto_send={}, auto_receive={}", settings.auto_send, settings.auto_receive);
+    if scope_changed {
+        crate::netscope::apply(&app_handle, &state);
+    }
+    if namespace_changed {
+        apply_discovery_namespace(&app_handle, &state);
+    }
     crate::storage::save_settings(&app_handle, &settings);
-    let _ = app_handle.emit("settings-changed", settings.clone());
+    crate::crash::configure(&app_handle, settings.crash_reports);
+    crate::redact::set_mode(settings.log_redaction);
+    crate::logs::configure(&app_handle, settings.log_retention_days);
+    if settings.proxy.enabled {
+        if let Err(e) = crate::proxy::parse(&settings.proxy.url) {
+            tracing::warn!("[Proxy] {}; outbound requests will fail until it's fixed", e);
+        }
+    }
+    i18n::set_locale(settings.locale.as_deref());
+    crate::services::configure(settings.macos_service);
+    crate::events::emit(&app_handle, crate::events::SettingsChanged(&settings));
     
     #[cfg(desktop)]
-    crate::tray::update_tray_menu(&app_handle);
+    {
+        crate::tray::update_tray_menu(&app_handle);
+        crate::tray::refresh_badge(&app_handle);
+    }
     
     // Update Shortcuts
     register_shortcuts(&app_handle);
@@ -808,22 +1200,140 @@ fn set_network_identity(
     // If I change Name, they see "My Home" instead of "Fuzzy-Badger".
     // I'll stick to just updating Name/PIN.
     
-    // Re-register mDNS with new name
-    let device_id = state.local_device_id.lock().unwrap().clone();
+    // Re-register mDNS with new name and tell peers
+    announce_identity(&state);
     
-    // Get actual port from transport
-    let port = if let Some(transport) = state.transport.lock().unwrap().as_ref() {
-        transport.local_addr().map(|a| a.port()).unwrap_or(4654)
-    } else {
-        4654
+    crate::events::emit(&app_handle, crate::events::NetworkUpdate {});
+}
+
+// Re-register under the `discovery_namespace` setting and drop untrusted devices we only knew
+// from the old namespace (cluster members stay; they're reached directly, not via mDNS)
+fn apply_discovery_namespace(app_handle: &tauri::AppHandle, state: &AppState) {
+    let namespace = crate::discovery::normalize_namespace(&state.settings.lock().unwrap().discovery_namespace);
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    let network_name = state.network_name.lock().unwrap().clone();
+    {
+        let mut discovery = state.discovery.lock().unwrap();
+        let Some(discovery) = discovery.as_mut() else {
+            return;
+        };
+        if !discovery.set_namespace(namespace.clone()) {
+            return;
+        }
+        tracing::info!("[Discovery] Namespace is now {}", namespace.as_deref().unwrap_or("(default)"));
+        if let Err(e) = discovery.register(&device_id, &network_name, state.bound_port()) {
+            tracing::error!("[Discovery] Failed to re-register: {}", e);
+        }
+    }
+    let removed: Vec<String> = {
+        let mut peers = state.peers.lock().unwrap();
+        let removed: Vec<String> = peers.values().filter(|p| !p.is_trusted && !p.is_manual).map(|p| p.id.clone()).collect();
+        peers.retain(|id, _| !removed.contains(id));
+        removed
+    };
+    for id in removed {
+        crate::presence::forget(state, &id);
+        crate::events::emit(app_handle, crate::events::PeerRemoved { id });
+    }
+}
+
+// Our own Peer record as sent in PeerDiscovery (signed with the cluster key when we have one)
+fn local_peer_info(state: &AppState, transport: &Transport) -> Peer {
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let hostname = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or("Unknown".to_string());
+    let network_name = state.network_name.lock().unwrap().clone();
+    let signature = state.cluster_key.lock().unwrap().as_ref().filter(|k| k.len() == 32).and_then(|key_vec| {
+        let mut key_arr = [0u8; 32];
+        key_arr.copy_from_slice(key_vec);
+        generate_signature(&key_arr, &local_id)
+    });
+    // The endpoint is usually bound to 0.0.0.0; report the address peers actually see us at
+    let bound_ip = transport.local_addr().ok().map(|a| a.ip()).filter(|ip| !ip.is_unspecified());
+    let ip = bound_ip
+        .or_else(|| state.preferred_local_ip())
+        .or_else(|| local_ip_address::local_ip().ok())
+        .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED));
+    // Every interface we listen on, so peers have somewhere else to try when `ip` stops working
+    let port = state.bound_port();
+    let interface_ips: Vec<std::net::IpAddr> = match bound_ip {
+        Some(bound) => vec![bound],
+        None => list_afinet_netifas()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, ip)| ip)
+            .filter(|ip| ip.is_ipv4() && !ip.is_loopback())
+            .collect(),
     };
+    let addresses = interface_ips
+        .into_iter()
+        .map(|ip| crate::peer::KnownAddress::new(std::net::SocketAddr::new(ip, port)))
+        .take(crate::peer::MAX_ADDRESSES)
+        .collect();
+    Peer {
+        id: local_id,
+        ip,
+        port,
+        hostname,
+        last_seen: 0,
+        is_trusted: false,
+        is_manual: true,
+        network_name: Some(network_name),
+        signature,
+        guest_expires_at: *state.guest_expires_at.lock().unwrap(),
+        capabilities: Some(crate::peer::Capabilities::from_settings(&state.settings.lock().unwrap())),
+        platform: Some(crate::platform::local()),
+        addresses,
+        observed_addr: None,
+        public_key: crate::identity::public_key(state),
+    }
+}
 
-    // Discovery usually stores port.
+// A peer told us which address it saw us at. Count it (if it really is ours) and, when the
+// most-reported address changes, advertise that one over mDNS instead of the default route.
+fn note_observed_address(state: &AppState, observed: std::net::SocketAddr) {
+    let ip = observed.ip();
+    if ip.is_loopback() || ip.is_unspecified() || !is_local_ip(ip) {
+        return;
+    }
+    let before = state.preferred_local_ip();
+    *state.observed_ips.lock().unwrap().entry(ip).or_insert(0) += 1;
+    let preferred = state.preferred_local_ip();
+    if preferred == before {
+        return;
+    }
+    tracing::info!("Peers see this device at {:?}; advertising it", preferred);
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    let name = state.network_name.lock().unwrap().clone();
     if let Some(discovery) = state.discovery.lock().unwrap().as_mut() {
-          let _ = discovery.register(&device_id, &name, port);
+        if discovery.set_preferred_ip(preferred) && !discovery.is_restricted() {
+            let _ = discovery.register(&device_id, &name, state.bound_port());
+        }
+    }
+}
+
+// Re-register mDNS on the real bound port and push our updated record (new network name)
+// to every current peer, so they don't have to wait for the next heartbeat
+fn announce_identity(state: &AppState) {
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    let name = state.network_name.lock().unwrap().clone();
+    if let Some(discovery) = state.discovery.lock().unwrap().as_mut() {
+        let _ = discovery.register(&device_id, &name, state.bound_port());
+    }
+
+    let Some(transport) = state.transport.lock().unwrap().clone() else {
+        return;
+    };
+    let data = serde_json::to_vec(&Message::PeerDiscovery(local_peer_info(state, &transport))).unwrap_or_default();
+    for p in state.get_peers().into_values() {
+        let addr = std::net::SocketAddr::new(p.ip, p.port);
+        let transport = transport.clone();
+        let data = data.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = transport.send_with(crate::transport::Delivery::BestEffort, addr, &data).await {
+                tracing::debug!("Failed to re-announce to {}: {}", addr, e);
+            }
+        });
     }
-    
-    let _ = app_handle.emit("network-update", ());
 }
 
 #[tauri::command]
@@ -836,89 +1346,661 @@ fn regenerate_network_identity(
     *state.network_name.lock().unwrap() = name.clone();
     *state.network_pin.lock().unwrap() = pin.clone();
     
-    let device_id = state.local_device_id.lock().unwrap().clone();
-    
-    // Get actual port from transport
-    let port = if let Some(transport) = state.transport.lock().unwrap().as_ref() {
-        transport.local_addr().map(|a| a.port()).unwrap_or(4654)
-    } else {
-        4654
-    };
-    
-    if let Some(discovery) = state.discovery.lock().unwrap().as_mut() {
-          let _ = discovery.register(&device_id, &name, port);
-    }
+    announce_identity(&state);
     
-    let _ = app_handle.emit("network-update", ());
+    crate::events::emit(&app_handle, crate::events::NetworkUpdate {});
 }
 
 #[tauri::command]
-fn get_listening_port(state: tauri::State<'_, AppState>) -> u16 {
-    if let Some(transport) = state.transport.lock().unwrap().as_ref() {
-        transport.local_addr().map(|a| a.port()).unwrap_or(4654)
-    } else {
-        4654
+async fn show_recovery_phrase(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    if !crate::recovery::verify_user("Show the ClusterCut recovery phrase").await.map_err(AppError::Crypto)? {
+        return Err(AppError::Crypto("Verification failed".to_string()));
     }
+    let key = state.cluster_key.lock().unwrap().clone().ok_or_else(|| AppError::Crypto("No Cluster Key set".to_string()))?;
+    crate::recovery::phrase_from_key(&key).map_err(AppError::Crypto)
 }
 
+// Restore the cluster key from a recovery phrase. Nothing here is signed: members trust a
+// device whose PeerDiscovery carries an id token sealed with the cluster key (see
+// verify_signature), so holding the key again is enough and no online peer needs to approve.
 #[tauri::command]
-fn get_peers(state: tauri::State<AppState>) -> std::collections::HashMap<String, Peer> {
-    state.get_peers()
+fn recover_from_phrase(
+    phrase: String,
+    network_name: Option<String>,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let key = crate::recovery::key_from_phrase(&phrase).map_err(AppError::Invalid)?;
+    save_cluster_key(&app_handle, &key);
+    *state.cluster_key.lock().unwrap() = Some(key);
+    // A recovered device is no longer "removed"
+    *state.pending_reset.lock().unwrap() = None;
+    crate::storage::save_pending_reset(&app_handle, None);
+
+    let name = match network_name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+        Some(n) => {
+            save_network_name(&app_handle, &n);
+            *state.network_name.lock().unwrap() = n.clone();
+            n
+        }
+        None => state.network_name.lock().unwrap().clone(),
+    };
+    tracing::info!("Recovered Cluster Key from phrase. Network: {}", name);
+
+    announce_identity(&state);
+
+    crate::events::emit(&app_handle, crate::events::NetworkUpdate {});
+    Ok(())
 }
 
 #[tauri::command]
-fn get_known_peers(state: tauri::State<AppState>) -> std::collections::HashMap<String, Peer> {
-    state.known_peers.lock().unwrap().clone()
+fn get_active_profile() -> String {
+    crate::storage::active_profile().unwrap_or_else(|| "default".to_string())
 }
 
 #[tauri::command]
-fn log_frontend(message: String, level: Option<String>) {
-    match level.as_deref() {
-        Some("error") => tracing::error!("[Frontend] {}", message),
-        Some("warn") => tracing::warn!("[Frontend] {}", message),
-        Some("debug") => tracing::debug!("[Frontend] {}", message),
-        Some("trace") => tracing::trace!("[Frontend] {}", message),
-        _ => tracing::info!("[Frontend] {}", message),
-    }
+fn list_profiles(app_handle: tauri::AppHandle) -> Vec<String> {
+    crate::storage::list_profiles(&app_handle)
 }
 
+// Switch to another profile at runtime: drop everything tied to the current identity,
+// reload keys/peers/settings from the new profile, then restart discovery (a fresh mDNS daemon
+// in the new profile's namespace) and drop the transport's cached connections before
+// re-announcing under the new device ID.
 #[tauri::command]
-fn get_local_ip() -> String {
-    local_ip_address::local_ip()
-        .map(|ip| ip.to_string())
-        .unwrap_or_else(|_| "127.0.0.1".to_string())
-}
+fn switch_profile(name: String, state: tauri::State<'_, AppState>, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    if !crate::storage::is_valid_profile_name(&name) {
+        return Err(AppError::Invalid("Profile names may only contain letters, digits, '-' and '_'".to_string()));
+    }
+    if crate::storage::active_profile().unwrap_or_else(|| "default".to_string()) == name {
+        return Ok(());
+    }
+    tracing::info!("Switching to profile: {}", name);
 
-use ipnetwork::IpNetwork;
+    // 1. Stop announcing the old identity, and drop connections made for it
+    if let Some(discovery) = state.discovery.lock().unwrap().as_mut() {
+        discovery.unregister();
+    }
+    if let Some(transport) = app_handle.try_state::<Transport>() {
+        transport.reset_connections();
+    }
 
-// Signature Helpers
-fn generate_signature(key: &[u8; 32], id: &str) -> Option<String> {
-    let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
-    let payload = format!("{}:{}", id, ts);
-    if let Ok(encrypted) = crypto::encrypt(key, payload.as_bytes()) {
-        return Some(BASE64.encode(encrypted));
+    // 2. Drop runtime state belonging to the old profile
+    let old_peers: Vec<String> = state.peers.lock().unwrap().drain().map(|(id, _)| id).collect();
+    for id in old_peers {
+        crate::events::emit(&app_handle, crate::events::PeerRemoved { id });
     }
-    None
-}
+    crate::handshake::clear(&state);
+    state.pending_admissions.lock().unwrap().clear();
+    state.admission_requests.lock().unwrap().clear();
+    *state.guest_invite.lock().unwrap() = None;
+    state.pending_removals.lock().unwrap().clear();
+    state.presence.lock().unwrap().clear();
+    state.pending_clipboard.lock().unwrap().clear();
+    *state.pending_outgoing.lock().unwrap() = None;
+    *state.pending_type_text.lock().unwrap() = None;
+    crate::retry::clear(&state);
+    *state.pending_reset.lock().unwrap() = None;
+    state.local_files.lock().unwrap().clear();
+    state.staged_files.lock().unwrap().clear();
+    state.remote_files.lock().unwrap().clear();
+    state.received_hashes.lock().unwrap().clear();
+    state.file_hashes.lock().unwrap().clear();
+    state.partial_transfers.lock().unwrap().clear();
+    state.verify_failures.lock().unwrap().clear();
+    state.compositions.lock().unwrap().clear();
+
+    // 3. Load the new profile
+    crate::storage::set_active_profile(Some(name.clone()));
+    load_profile_state(&app_handle, &state);
+    register_shortcuts(&app_handle);
 
-fn verify_signature(key: &[u8; 32], id: &str, signature: &str) -> bool {
-    if let Ok(encrypted) = BASE64.decode(signature) {
-         if let Ok(decrypted) = crypto::decrypt(key, &encrypted) {
-             if let Ok(payload) = String::from_utf8(decrypted) {
-                 // Payload: "ID:TIMESTAMP"
-                 let parts: Vec<&str> = payload.split(':').collect();
-                 if parts.len() == 2 {
-                     if parts[0] == id {
-                         if let Ok(ts) = parts[1].parse::<u64>() {
-                             let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
-                             // Allow 60s skew/replay window
-                             if now >= ts && (now - ts) < 60 {
-                                 return true;
-                             }
-                             // Also allow minor clock drift (future timestamp)? 
-                             if ts > now && (ts - now) < 10 {
-                                 return true;
-                             }
+    // 4. Restart discovery and re-announce under the new identity
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    let network_name = state.network_name.lock().unwrap().clone();
+    let namespace = crate::discovery::normalize_namespace(&state.settings.lock().unwrap().discovery_namespace);
+    let port = state.bound_port();
+    let receiver = {
+        let mut discovery = state.discovery.lock().unwrap();
+        match discovery.as_mut() {
+            Some(discovery) => {
+                discovery.restart().map_err(|e| AppError::Network(e.to_string()))?;
+                discovery.set_namespace(namespace);
+                discovery.register(&device_id, &network_name, port).map_err(|e| AppError::Network(e.to_string()))?;
+                Some(discovery.browse().map_err(|e| AppError::Network(e.to_string()))?)
+            }
+            None => None,
+        }
+    };
+    if let Some(receiver) = receiver {
+        spawn_discovery_loop(app_handle.clone(), state.inner().clone(), receiver);
+    }
+    // Re-applies the interface/subnet restriction to the new daemon
+    crate::netscope::apply(&app_handle, &state);
+
+    crate::events::emit(&app_handle, crate::events::ProfileSwitched { name: name.clone() });
+    crate::events::emit(&app_handle, crate::events::NetworkReset {});
+    Ok(())
+}
+
+// (Re)load everything stored per profile into the runtime state
+fn load_profile_state(app_handle: &tauri::AppHandle, state: &AppState) {
+    let migration = crate::migration::run(app_handle);
+    let key_generated = match load_cluster_key(app_handle) {
+        Some(key) => {
+            *state.cluster_key.lock().unwrap() = Some(key);
+            false
+        }
+        None => {
+            tracing::info!("No Cluster Key found for profile. Generating new one...");
+            let mut new_key = [0u8; 32];
+            rand::thread_rng().fill(&mut new_key);
+            save_cluster_key(app_handle, &new_key);
+            *state.cluster_key.lock().unwrap() = Some(new_key.to_vec());
+            true
+        }
+    };
+
+    *state.known_peers.lock().unwrap() = load_known_peers(app_handle);
+    *state.peer_download_policies.lock().unwrap() = crate::storage::load_peer_download_policies(app_handle);
+    *state.peer_permissions.lock().unwrap() = crate::storage::load_peer_permissions(app_handle);
+    *state.peer_stats.lock().unwrap() = crate::peerstats::StatsStore::new(crate::storage::load_peer_stats(app_handle));
+    *state.blocked_peers.lock().unwrap() = crate::storage::load_blocked_peers(app_handle);
+    crate::peersync::load_removals(app_handle, &state);
+    *state.history_annotations.lock().unwrap() = crate::storage::load_history_annotations(app_handle);
+    state.recent_items.lock().unwrap().clear();
+    *state.settings.lock().unwrap() = load_settings(app_handle);
+
+    let mut device_id = load_device_id(app_handle);
+    if device_id.is_empty() {
+        let run_id: u32 = rand::thread_rng().gen();
+        device_id = format!("clustercut-{}", run_id);
+        save_device_id(app_handle, &device_id);
+        tracing::info!("Generated new Device ID: {}", device_id);
+    }
+    load_cluster_owner_state(app_handle, state, &device_id, key_generated);
+    crate::identity::load(app_handle, state);
+    *state.guest_expires_at.lock().unwrap() = crate::storage::load_guest_expiry(app_handle);
+    *state.local_device_id.lock().unwrap() = device_id;
+    let pending_reset = crate::storage::load_pending_reset(app_handle);
+    *state.pending_reset.lock().unwrap() = pending_reset;
+    if let Some(deadline) = pending_reset {
+        tracing::warn!("A reset was pending when we last ran. Syncing stays paused until it's confirmed or cancelled.");
+        let wait = arm_reset_timer(app_handle, state, deadline);
+        crate::events::emit(app_handle, crate::events::ResetPending { deadline });
+        let body = i18n::tf("notify.removed.body", &[("minutes", &wait.div_ceil(60).to_string())]);
+        send_notification(app_handle, &i18n::t("notify.removed.title"), &body, false, None, "settings", NotificationPayload::None);
+    }
+    *state.network_name.lock().unwrap() = load_network_name(app_handle);
+    *state.network_pin.lock().unwrap() = load_network_pin(app_handle);
+
+    for t in crate::storage::load_partial_transfers(app_handle) {
+        state.partial_transfers.lock().unwrap().insert(t.key(), t);
+    }
+    crate::migration::finish(app_handle, state, migration);
+}
+
+// Whoever generated the cluster key owns the cluster
+fn load_cluster_owner_state(app_handle: &tauri::AppHandle, state: &AppState, device_id: &str, key_generated: bool) {
+    let owner = if key_generated {
+        crate::storage::save_cluster_owner(app_handle, Some(device_id));
+        Some(device_id.to_string())
+    } else {
+        crate::storage::load_cluster_owner(app_handle)
+    };
+    *state.cluster_owner.lock().unwrap() = owner;
+}
+
+#[tauri::command]
+fn get_listening_port(state: tauri::State<'_, AppState>) -> u16 {
+    state.bound_port()
+}
+
+#[tauri::command]
+fn get_message_metrics(state: tauri::State<'_, AppState>) -> crate::state::MessageMetrics {
+    state.message_metrics.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn get_migration_report(state: tauri::State<'_, AppState>) -> Option<crate::migration::MigrationReport> {
+    state.migration_report.lock().unwrap().clone()
+}
+
+// Large texts take a while to diff; keep that off the main thread
+#[tauri::command]
+async fn diff_history_items(id_a: String, id_b: String, state: tauri::State<'_, AppState>) -> Result<crate::history::HistoryDiff, AppError> {
+    let state = state.inner().clone();
+    tokio::task::spawn_blocking(move || crate::history::diff(&state, &id_a, &id_b))
+        .await
+        .map_err(|e| AppError::Invalid(e.to_string()))?
+        .map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+fn search_history(
+    query: Option<String>,
+    kinds: Option<Vec<crate::classify::ContentKind>>,
+    language: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Vec<crate::protocol::ClipboardPayload> {
+    crate::history::search(&state, query.as_deref().unwrap_or(""), &kinds.unwrap_or_default(), language.as_deref())
+}
+
+#[tauri::command]
+fn get_item_as_qr(id: String, state: tauri::State<'_, AppState>) -> Result<String, AppError> {
+    crate::history::qr_png(&state, &id).map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+fn resend_history_item(
+    id: String,
+    peer_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    crate::history::resend(&app_handle, &state, &transport, &id, peer_id.as_deref()).map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+async fn send_files(
+    paths: Vec<String>,
+    peer_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    // Hashing large files takes a while; keep it off the async runtime
+    let (state, transport) = (state.inner().clone(), transport.inner().clone());
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::clipboard::send_files(&app_handle, &state, &transport, &paths, peer_id.as_deref())
+    })
+    .await
+    .map_err(|e| AppError::Invalid(e.to_string()))?
+    .map_err(AppError::Invalid)
+}
+
+/// Ids of history items whose files this device still serves.
+#[tauri::command]
+fn list_file_offers(state: tauri::State<'_, AppState>) -> Vec<String> {
+    state.local_files.lock().unwrap().active()
+}
+
+#[tauri::command]
+fn stage_dropped_files(
+    paths: Vec<String>,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    crate::staging::stage(&app_handle, &state, &paths).map_err(AppError::Invalid)
+}
+
+/// `peers`: device IDs to send to; None or empty sends to every peer.
+#[tauri::command]
+fn send_staged(
+    batch_id: String,
+    peers: Option<Vec<String>>,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let peers = peers.filter(|p| !p.is_empty());
+    crate::staging::send(&app_handle, &state, &transport, &batch_id, peers.as_deref()).map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+fn discard_staged(batch_id: String, state: tauri::State<'_, AppState>) -> bool {
+    crate::staging::discard(&state, &batch_id)
+}
+
+#[tauri::command]
+fn export_history(
+    path: String,
+    format: crate::history::ExportFormat,
+    date_range: Option<crate::history::DateRange>,
+    options: Option<crate::history::ExportOptions>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, AppError> {
+    crate::history::export(&state, std::path::Path::new(&path), format, date_range.unwrap_or_default(), options.unwrap_or_default())
+        .map_err(AppError::Storage)
+}
+
+#[tauri::command]
+fn import_history(path: String, state: tauri::State<'_, AppState>, app_handle: tauri::AppHandle) -> Result<crate::history::ImportReport, AppError> {
+    let (report, notes) = crate::history::import(&state, std::path::Path::new(&path)).map_err(AppError::Storage)?;
+    if !notes.is_empty() {
+        crate::storage::save_history_annotations(&app_handle, &state.history_annotations.lock().unwrap());
+    }
+    tracing::info!("[History] Imported {} item(s) from {} ({} skipped)", report.imported, crate::redact::path(std::path::Path::new(&path)), report.skipped);
+    crate::events::emit(&app_handle, crate::events::HistoryImported { imported: report.imported });
+    Ok(report)
+}
+
+#[tauri::command]
+fn get_clipboard_health() -> crate::clipwrite::ClipboardHealth {
+    crate::clipwrite::health()
+}
+
+#[tauri::command]
+async fn test_peer_connection(
+    peer_id: String,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+) -> Result<crate::conntest::ConnectionReport, AppError> {
+    crate::conntest::run(&state, &transport, &peer_id).await.map_err(AppError::Network)
+}
+
+#[tauri::command]
+async fn dry_run_sync(
+    text: Option<String>,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+) -> Result<crate::dryrun::DryRunReport, AppError> {
+    crate::dryrun::run(&state, &transport, text).await.map_err(AppError::Network)
+}
+
+#[tauri::command]
+fn get_power_status(state: tauri::State<'_, AppState>) -> crate::power::PowerStatus {
+    state.power.status()
+}
+
+#[tauri::command]
+fn get_latency_report(state: tauri::State<'_, AppState>) -> Vec<crate::latency::PeerLatency> {
+    crate::latency::report(&state)
+}
+
+#[tauri::command]
+fn get_peer_presence(state: tauri::State<AppState>) -> Vec<crate::presence::PeerPresence> {
+    crate::presence::snapshot(&state)
+}
+
+#[tauri::command]
+fn get_peers(state: tauri::State<AppState>) -> std::collections::HashMap<String, Peer> {
+    state.get_peers()
+}
+
+#[tauri::command]
+fn get_peer_details(peer_id: String, state: tauri::State<AppState>) -> Result<crate::peerstats::PeerDetails, AppError> {
+    crate::peerstats::details(&state, &peer_id).map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+fn get_known_peers(state: tauri::State<AppState>) -> std::collections::HashMap<String, Peer> {
+    state.known_peers.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn get_peer_download_policies(state: tauri::State<AppState>) -> std::collections::HashMap<String, crate::storage::AutoDownloadPolicy> {
+    state.peer_download_policies.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn set_peer_download_policy(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    peer_id: String,
+    policy: Option<crate::storage::AutoDownloadPolicy>,
+) {
+    let mut policies = state.peer_download_policies.lock().unwrap();
+    match policy {
+        Some(p) => {
+            tracing::info!("Auto-download policy for {}: {:?}", peer_id, p);
+            policies.insert(peer_id, p);
+        }
+        None => {
+            tracing::info!("Auto-download policy for {} reset to default", peer_id);
+            policies.remove(&peer_id);
+        }
+    }
+    crate::storage::save_peer_download_policies(&app_handle, &policies);
+}
+
+#[tauri::command]
+fn get_peer_permissions(state: tauri::State<AppState>) -> std::collections::HashMap<String, crate::storage::PeerPermissions> {
+    state.peer_permissions.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn set_peer_permissions(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    peer_id: String,
+    permissions: crate::storage::PeerPermissions,
+) {
+    tracing::info!("Remote action permissions for {}: {:?}", peer_id, permissions);
+    let mut all = state.peer_permissions.lock().unwrap();
+    if permissions == crate::storage::PeerPermissions::default() {
+        all.remove(&peer_id);
+    } else {
+        all.insert(peer_id, permissions);
+    }
+    crate::storage::save_peer_permissions(&app_handle, &all);
+}
+
+#[tauri::command]
+async fn open_on_peer(
+    peer_id: String,
+    url: String,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+) -> Result<(), AppError> {
+    crate::remote::open_on_peer(&state, &transport, &peer_id, &url).await.map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+async fn type_on_peer(
+    peer_id: String,
+    text: String,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+) -> Result<(), AppError> {
+    crate::remote::type_on_peer(&state, &transport, &peer_id, &text).await.map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+fn get_pending_type_text(state: tauri::State<AppState>) -> Option<crate::remote::PendingTypeText> {
+    crate::remote::pending_type_text(&state)
+}
+
+#[tauri::command]
+fn confirm_type_text(app_handle: tauri::AppHandle, state: tauri::State<AppState>, id: String) -> Result<(), AppError> {
+    crate::remote::confirm_type_text(&app_handle, &state, &id).map_err(AppError::Invalid)
+}
+
+#[tauri::command]
+fn reject_type_text(app_handle: tauri::AppHandle, state: tauri::State<AppState>, id: String) -> bool {
+    crate::remote::reject_type_text(&app_handle, &state, &id)
+}
+
+#[derive(serde::Serialize)]
+struct LocaleInfo {
+    locale: &'static str,
+    available: Vec<&'static str>,
+}
+
+#[tauri::command]
+fn get_locale() -> LocaleInfo {
+    LocaleInfo { locale: i18n::current(), available: i18n::available() }
+}
+
+/// Pin the language of notifications and the tray (None = follow the system).
+#[tauri::command]
+fn set_locale(app_handle: tauri::AppHandle, state: tauri::State<AppState>, locale: Option<String>) -> &'static str {
+    let effective = i18n::set_locale(locale.as_deref());
+    let settings = {
+        let mut settings = state.settings.lock().unwrap();
+        settings.locale = locale;
+        settings.clone()
+    };
+    crate::storage::save_settings(&app_handle, &settings);
+    crate::events::emit(&app_handle, crate::events::SettingsChanged(&settings));
+    #[cfg(desktop)]
+    crate::tray::update_tray_menu(&app_handle);
+    effective
+}
+
+#[tauri::command]
+fn get_unread_count(state: tauri::State<AppState>) -> u32 {
+    crate::unread::count(&state)
+}
+
+#[tauri::command]
+fn clear_unread(app_handle: tauri::AppHandle) {
+    crate::unread::clear(&app_handle);
+}
+
+#[tauri::command]
+fn get_send_failures(state: tauri::State<'_, AppState>) -> Vec<crate::retry::SendFailure> {
+    crate::retry::failures(&state)
+}
+
+#[tauri::command]
+fn clear_send_failures(state: tauri::State<'_, AppState>) {
+    crate::retry::clear(&state);
+}
+
+#[tauri::command]
+fn open_log_folder(app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    use tauri_plugin_opener::OpenerExt;
+    let dir = crate::logs::dir();
+    std::fs::create_dir_all(&dir).map_err(|e| AppError::Storage(e.to_string()))?;
+    app_handle.opener().open_path(dir.to_string_lossy(), None::<&str>).map_err(|e| AppError::Storage(e.to_string()))
+}
+
+#[tauri::command]
+fn clear_logs(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    let retention = state.settings.lock().unwrap().log_retention_days;
+    crate::logs::clear(retention).map_err(AppError::Storage)
+}
+
+#[tauri::command]
+fn clear_received_files(app_handle: tauri::AppHandle) -> crate::cache::CleanupReport {
+    crate::cache::clear_received(&app_handle)
+}
+
+#[tauri::command]
+async fn check_for_updates(state: tauri::State<'_, AppState>) -> Result<Option<crate::update::UpdateInfo>, AppError> {
+    let settings = state.settings.lock().unwrap().clone();
+    crate::update::check(&settings).await.map_err(AppError::Network)
+}
+
+#[tauri::command]
+fn get_platform_info() -> crate::platform::PlatformInfo {
+    crate::platform::local()
+}
+
+#[tauri::command]
+fn get_crash_reports(app_handle: tauri::AppHandle) -> Vec<crate::crash::CrashReport> {
+    crate::crash::reports(&app_handle)
+}
+
+#[tauri::command]
+fn clear_crash_reports(app_handle: tauri::AppHandle) {
+    crate::crash::clear(&app_handle);
+}
+
+#[tauri::command]
+fn get_blocked_peers(state: tauri::State<AppState>) -> std::collections::HashMap<String, crate::storage::BlockedPeer> {
+    state.blocked_peers.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn block_peer(app_handle: tauri::AppHandle, state: tauri::State<AppState>, peer_id: String) -> Result<(), String> {
+    if peer_id == *state.local_device_id.lock().unwrap() {
+        return Err("Cannot block this device".to_string());
+    }
+
+    // Remember hostname/address for display and for messages that don't carry an id
+    let known = state
+        .peers
+        .lock()
+        .unwrap()
+        .get(&peer_id)
+        .cloned()
+        .or_else(|| state.known_peers.lock().unwrap().get(&peer_id).cloned());
+    let entry = crate::storage::BlockedPeer {
+        id: peer_id.clone(),
+        hostname: known.as_ref().map(|p| p.hostname.clone()).unwrap_or_default(),
+        ip: known.as_ref().map(|p| p.ip),
+        blocked_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(),
+    };
+    tracing::info!("Blocking device {} ({})", peer_id, entry.hostname);
+    {
+        let mut blocked = state.blocked_peers.lock().unwrap();
+        blocked.insert(peer_id.clone(), entry);
+        crate::storage::save_blocked_peers(&app_handle, &blocked);
+    }
+
+    // Forget it locally (no PeerRemoval broadcast: blocking is a local decision)
+    {
+        let mut kp = state.known_peers.lock().unwrap();
+        if kp.remove(&peer_id).is_some() {
+            save_known_peers(&app_handle, &kp);
+        }
+    }
+    if let Some(peer) = state.peers.lock().unwrap().remove(&peer_id) {
+        crate::handshake::forget(&state, &std::net::SocketAddr::new(peer.ip, peer.port).to_string());
+    }
+    crate::presence::forget(&state, &peer_id);
+    crate::events::emit(&app_handle, crate::events::PeerRemoved { id: peer_id.clone() });
+    Ok(())
+}
+
+#[tauri::command]
+fn unblock_peer(app_handle: tauri::AppHandle, state: tauri::State<AppState>, peer_id: String) {
+    let mut blocked = state.blocked_peers.lock().unwrap();
+    if blocked.remove(&peer_id).is_some() {
+        tracing::info!("Unblocked device {}", peer_id);
+        crate::storage::save_blocked_peers(&app_handle, &blocked);
+    }
+}
+
+#[tauri::command]
+fn log_frontend(message: String, level: Option<String>) {
+    match level.as_deref() {
+        Some("error") => tracing::error!("[Frontend] {}", message),
+        Some("warn") => tracing::warn!("[Frontend] {}", message),
+        Some("debug") => tracing::debug!("[Frontend] {}", message),
+        Some("trace") => tracing::trace!("[Frontend] {}", message),
+        _ => tracing::info!("[Frontend] {}", message),
+    }
+}
+
+#[tauri::command]
+fn get_local_ip() -> String {
+    local_ip_address::local_ip()
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|_| "127.0.0.1".to_string())
+}
+
+use ipnetwork::IpNetwork;
+
+// Signature Helpers
+fn generate_signature(key: &[u8; 32], id: &str) -> Option<String> {
+    let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let payload = format!("{}:{}", id, ts);
+    if let Ok(encrypted) = crypto::encrypt(key, payload.as_bytes()) {
+        return Some(BASE64.encode(encrypted));
+    }
+    None
+}
+
+fn verify_signature(key: &[u8; 32], id: &str, signature: &str) -> bool {
+    if let Ok(encrypted) = BASE64.decode(signature) {
+         if let Ok(decrypted) = crypto::decrypt(key, &encrypted) {
+             if let Ok(payload) = String::from_utf8(decrypted) {
+                 // Payload: "ID:TIMESTAMP"
+                 let parts: Vec<&str> = payload.split(':').collect();
+                 if parts.len() == 2 && parts[0] == id {
+                     if let Ok(ts) = parts[1].parse::<u64>() {
+                         let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+                         // Allow 60s skew/replay window
+                         if now >= ts && (now - ts) < 60 {
+                             return true;
+                         }
+                         // Also allow minor clock drift (future timestamp)? 
+                         if ts > now && (ts - now) < 10 {
+                             return true;
                          }
                      }
                  }
@@ -936,6 +2018,10 @@ async fn probe_ip(
     transport: Transport,
     app_handle: tauri::AppHandle,
 ) {
+    if !crate::netscope::allows_peer(&state, ip) {
+        tracing::debug!("Not probing {} (outside allowed networks)", ip);
+        return;
+    }
     let addr = std::net::SocketAddr::new(ip, port);
     
     // Attempt connection loop (simple probe)
@@ -950,31 +2036,9 @@ async fn probe_ip(
     // Or we rely on them reacting to our PeerDiscovery by connecting back? 
     // Let's implement a 'Hello' ping. 
 
-    let local_id = state.local_device_id.lock().unwrap().clone();
-    let hostname = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or("Unknown".to_string());
-    let network_name = state.network_name.lock().unwrap().clone();
-
-    let mut signature = None;
-    if let Some(key_vec) = state.cluster_key.lock().unwrap().as_ref() {
-        if key_vec.len() == 32 {
-            let mut key_arr = [0u8; 32];
-            key_arr.copy_from_slice(key_vec);
-            signature = generate_signature(&key_arr, &local_id);
-        }
-    }
-    
-    // Send OUR info so they can add us.
-    let my_peer = Peer {
-        id: local_id.clone(),
-        ip: transport.local_addr().unwrap().ip(),
-        port: transport.local_addr().unwrap().port(),
-        hostname,
-        last_seen: 0,
-        is_trusted: false, // We don't know if we are trusted yet
-        is_manual: true,
-        network_name: Some(network_name),
-        signature,
-    };
+    // Send OUR info so they can add us (not trusted until they verify the signature)
+    let mut my_peer = local_peer_info(&state, &transport);
+    my_peer.observed_addr = Some(addr);
 
     let msg = Message::PeerDiscovery(my_peer);
     let _data = serde_json::to_vec(&msg).unwrap_or_default();
@@ -996,7 +2060,7 @@ async fn probe_ip(
                    
                    // NOTIFY SUCCESS (Only if not startup)
                    if state.should_notify() {
-                       send_notification(&app_handle, "Connection Established", &format!("Successfully contacted {}.", ip), false, None, "devices", NotificationPayload::None);
+                       send_notification(&app_handle, &i18n::t("notify.connection_established.title"), &i18n::tf("notify.connection_established.body", &[("ip", &ip.to_string())]), false, None, "devices", NotificationPayload::None);
                    }
 
                    // We successfully sent the packet.
@@ -1018,9 +2082,15 @@ async fn probe_ip(
                              is_manual: true,
                              network_name: None,
                              signature: None, 
+                             guest_expires_at: None,
+                             capabilities: None,
+                             platform: None,
+                             addresses: Vec::new(),
+                             observed_addr: None,
+                             public_key: None,
                          };
                          peers.insert(id.clone(), peer.clone());
-                         let _ = app_handle.emit("peer-update", &peer);
+                         crate::events::emit(&app_handle, crate::events::PeerUpdate(&peer));
                          save_known_peers(&app_handle, &peers); // PERSIST manual placeholder
                          
                           let notifications = state.settings.lock().unwrap().notifications.clone();
@@ -1028,7 +2098,7 @@ async fn probe_ip(
                              // Check startup timer
                              if state.should_notify() {
                                  tracing::info!("[Notification] Triggering 'Device Joined' for manual peer: {}", peer.hostname);
-                                 send_notification(&app_handle, "Device Joined", &format!("Found manual peer: {}", peer.hostname), false, Some(1), "devices", NotificationPayload::None);
+                                 send_notification(&app_handle, &i18n::t("notify.device_joined.title"), &i18n::tf("notify.device_joined.manual_body", &[("name", &peer.hostname)]), false, Some(1), "devices", NotificationPayload::None);
                              } else {
                                  tracing::debug!("[Notification] Device join (manual) notification suppressed by startup timer for peer: {}", peer.hostname);
                              }
@@ -1038,20 +2108,20 @@ async fn probe_ip(
                          tracing::debug!("Manual peer {} already exists.", id);
                          // Still notify success to confirm connectivity (if not startup)
                          if state.should_notify() {
-                             send_notification(&app_handle, "Connection Verified", &format!("Connection to {} is active.", ip), false, None, "devices", NotificationPayload::None);
+                             send_notification(&app_handle, &i18n::t("notify.connection_verified.title"), &i18n::tf("notify.connection_verified.body", &[("ip", &ip.to_string())]), false, None, "devices", NotificationPayload::None);
                          }
                      }
                 },
                 Ok(Err(e)) => {
                     tracing::warn!("Probe to {} FAILED (Send Error): {}", addr, e);
                     if state.should_notify() {
-                        send_notification(&app_handle, "Connection Failed", &format!("Failed to send packet to {}: {}", ip, e), true, None, "devices", NotificationPayload::None);
+                        send_notification(&app_handle, &i18n::t("notify.connection_failed.title"), &i18n::tf("notify.connection_failed.send_body", &[("ip", &ip.to_string()), ("error", &e.to_string())]), true, None, "devices", NotificationPayload::None);
                     }
                 },
                 Err(_) => {
                     tracing::warn!("Probe to {} FAILED (Timeout)", addr);
                     if state.should_notify() {
-                        send_notification(&app_handle, "Connection Failed", &format!("Connection to {} timed out. Check firewall/VPN.", ip), true, None, "devices", NotificationPayload::None);
+                        send_notification(&app_handle, &i18n::t("notify.connection_failed.title"), &i18n::tf("notify.connection_failed.timeout_body", &[("ip", &ip.to_string())]), true, None, "devices", NotificationPayload::None);
                     }
                 }
             }
@@ -1096,7 +2166,7 @@ async fn add_manual_peer(
     state: tauri::State<'_, AppState>,
     transport: tauri::State<'_, Transport>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     
     // 1. Try parsing as CIDR
     if let Ok(net) = ip.parse::<IpNetwork>() {
@@ -1133,8 +2203,11 @@ async fn add_manual_peer(
         } else if let Ok(ip_addr) = ip.parse::<std::net::IpAddr>() {
             (ip_addr, 4654)
         } else {
-             return Err("Invalid Format. Use IP, IP:PORT, or CIDR (e.g. 192.168.1.0/24)".to_string());
+             return Err(AppError::Invalid("Invalid Format. Use IP, IP:PORT, or CIDR (e.g. 192.168.1.0/24)".to_string()));
         };
+        if !crate::netscope::allows_peer(&state, addr) {
+            return Err(AppError::Invalid(format!("{} is outside the allowed networks (see Network settings)", addr)));
+        }
 
         // For single IP, PROBE IT.
         probe_ip(addr, port, (*state).clone(), (*transport).clone(), app_handle).await;
@@ -1148,9 +2221,34 @@ async fn leave_network(
     transport: tauri::State<'_, Transport>,
     app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
-    let local_id = state.local_device_id.lock().unwrap().clone();
-    
     // 1. Broadcast "Self-Removal" to Network
+    announce_departure(&state, &transport);
+
+    // 2. Perform Factory Reset Locally
+    let port = transport.local_addr().map(|a| a.port()).unwrap_or(0);
+    perform_factory_reset(&app_handle, &state, port);
+    
+    Ok(())
+}
+
+/// Leave the network like `leave_network`, but shred history, keys, cached files and logs
+/// before the factory reset (see wipe.rs).
+#[tauri::command]
+async fn secure_wipe(
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::wipe::WipeReport, String> {
+    announce_departure(&state, &transport);
+    let report = crate::wipe::secure_wipe(&app_handle, &state);
+    let port = transport.local_addr().map(|a| a.port()).unwrap_or(0);
+    perform_factory_reset(&app_handle, &state, port);
+    Ok(report)
+}
+
+// Tell every peer we're leaving ("Self-Removal")
+fn announce_departure(state: &AppState, transport: &Transport) {
+    let local_id = state.local_device_id.lock().unwrap().clone();
     let removal_msg = Message::PeerRemoval(local_id.clone());
     let data = serde_json::to_vec(&removal_msg).unwrap_or_default();
     
@@ -1166,12 +2264,6 @@ async fn leave_network(
              let _ = transport_clone.send_message(addr, &data_vec).await;
          });
     }
-    
-    // 2. Perform Factory Reset Locally
-    let port = transport.local_addr().map(|a| a.port()).unwrap_or(0);
-    perform_factory_reset(&app_handle, &state, port);
-    
-    Ok(())
 }
 
 #[tauri::command]
@@ -1203,68 +2295,637 @@ async fn delete_peer(
          });
     }
 
-    // 1. Remove from Known Peers
+    // 1. Remove from Known Peers
+    {
+        let mut kp = state.known_peers.lock().unwrap();
+        if kp.remove(&peer_id).is_some() {
+            save_known_peers(&app_handle, &kp);
+        }
+    }
+    crate::peersync::record_removal(&app_handle, &state, &peer_id);
+
+    // 2. Remove from Runtime Peers
+    {
+        let mut peers = state.peers.lock().unwrap();
+        peers.remove(&peer_id);
+    }
+    crate::presence::forget(&state, &peer_id);
+
+    // 3. Emit Removal
+    crate::events::emit(&app_handle, crate::events::PeerRemoved { id: peer_id.clone() });
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_pairing(
+    peer_id: String,
+    pin: String,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    // 1. Find peer to get IP, and the network name that salts the PIN
+    let (peer_addr, network_name) = {
+        let peers = state.get_peers();
+        let Some(peer) = peers.get(&peer_id) else {
+            return Err(AppError::Pairing("Peer not found".to_string()));
+        };
+        let Some(network_name) = peer.network_name.clone().filter(|n| !n.is_empty()) else {
+            return Err(AppError::Pairing("This device hasn't announced its network yet. Try again in a moment.".to_string()));
+        };
+        (std::net::SocketAddr::new(peer.ip, peer.port), network_name)
+    };
+
+    send_pair_request(&state, &transport, peer_addr, &network_name, &pin).await?;
+    // So a PairResponse arriving after a restart can ask for the PIN again (a one-time PIN is spent)
+    if !crate::pin::is_one_time_format(&pin) {
+        crate::handshake::remember(&app_handle, &peer_id, &network_name);
+    }
+    Ok(())
+}
+
+// Send a PairRequest and keep its SPAKE2 state for the PairResponse
+async fn send_pair_request<T: crate::transport::MessageTransport>(
+    state: &AppState,
+    transport: &T,
+    peer_addr: std::net::SocketAddr,
+    network_name: &str,
+    pin: &str,
+) -> Result<(), AppError> {
+    // 2. Start SPAKE2 (Argon2 runs off the async runtime)
+    let (pin_owned, name_owned) = (pin.to_string(), network_name.to_string());
+    let (spake_state, msg) = tokio::task::spawn_blocking(move || crypto::start_spake2(&pin_owned, &name_owned).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| AppError::Crypto(e.to_string()))?
+        .map_err(AppError::Crypto)?;
+
+    // 3. Store state (by address)
+    crate::handshake::insert(&state.pending_handshakes, &peer_addr.to_string(), spake_state);
+
+    // 4. Send Message
+    let local_id = { state.local_device_id.lock().unwrap().clone() };
+
+    let msg_struct = Message::PairRequest {
+        msg,
+        device_id: local_id,
+        one_time: crate::pin::is_one_time_format(pin),
+        kdf: crypto::PAIRING_KDF_VERSION,
+    };
+    let data = serde_json::to_vec(&msg_struct).map_err(|e| AppError::Pairing(e.to_string()))?;
+
+    if let Err(e) = transport.send_message(peer_addr, &data).await {
+        crate::handshake::forget(state, &peer_addr.to_string());
+        return Err(AppError::Network(e.to_string()));
+    }
+
+    Ok(())
+}
+
+// Responder: tell the initiator its Welcome waits on the owner, so it keeps the session that long
+async fn send_admission_pending(state: &AppState, transport: &Transport, addr: std::net::SocketAddr) {
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    if let Ok(data) = serde_json::to_vec(&Message::AdmissionPending { device_id }) {
+        if let Err(e) = transport.send_message(addr, &data).await {
+            tracing::debug!("Failed to send AdmissionPending to {}: {}", addr, e);
+        }
+    }
+}
+
+// Responder: tell the initiator why its pairing attempt stops here
+async fn send_pair_rejected<T: crate::transport::MessageTransport>(state: &AppState, transport: &T, addr: std::net::SocketAddr, reason: crate::protocol::PairingError) {
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    if let Ok(data) = serde_json::to_vec(&Message::PairRejected { device_id, reason }) {
+        if let Err(e) = transport.send_message(addr, &data).await {
+            tracing::debug!("Failed to send PairRejected to {}: {}", addr, e);
+        }
+    }
+}
+
+// Pairing steps that don't need the app handle, so the sim tests drive the same code (see sim.rs)
+
+// Responder: answer a PairRequest with our SPAKE2 message and key confirmation
+async fn answer_pair_request<T: crate::transport::MessageTransport>(
+    state: &AppState,
+    transport: &T,
+    addr: std::net::SocketAddr,
+    msg: Vec<u8>,
+    device_id: String,
+    one_time: bool,
+    kdf: u8,
+) {
+    if !crate::handshake::allow_pair_request(state, addr.ip()) {
+        tracing::warn!("Dropping PairRequest from {} ({}): too many pairing attempts", addr, device_id);
+        return;
+    }
+    tracing::info!("Received PairRequest from {} ({}, one-time PIN: {}). Authenticating...", addr, device_id, one_time);
+    if kdf != crypto::PAIRING_KDF_VERSION {
+        tracing::warn!("Rejecting PairRequest from {}: key derivation v{} (need v{}); it must update first", device_id, kdf, crypto::PAIRING_KDF_VERSION);
+        send_pair_rejected(state, transport, addr, crate::protocol::PairingError::VersionMismatch).await;
+        return;
+    }
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let network_name = state.network_name.lock().unwrap().clone();
+    let pin = match crate::pin::responder_password(state, one_time) {
+        Ok(pin) => pin,
+        Err((reason, e)) => {
+            tracing::warn!("Rejecting PairRequest from {}: {}", device_id, e);
+            send_pair_rejected(state, transport, addr, reason).await;
+            return;
+        }
+    };
+
+    // Argon2 takes a while and a lot of memory; keep it off the listener's thread
+    let spake = tokio::task::spawn_blocking(move || {
+        let (spake_state, response_msg) = crypto::start_spake2(&pin, &network_name).map_err(|e| format!("SPAKE2 Error: {}", e))?;
+        let session_keys = crypto::finish_spake2(spake_state, &msg).map_err(|e| format!("Auth Failed: {}", e))?;
+        Ok::<_, String>((response_msg, session_keys))
+    })
+    .await;
+    let (response_msg, session_keys) = match spake {
+        Ok(Ok(done)) => done,
+        Ok(Err(e)) => {
+            tracing::error!("{}", e);
+            return;
+        }
+        Err(e) => {
+            tracing::error!("SPAKE2 task failed: {}", e);
+            return;
+        }
+    };
+    // The initiator checks our tag, then proves its own keys with PairConfirm
+    let resp_struct = Message::PairResponse {
+        msg: response_msg,
+        device_id: local_id.clone(),
+        confirmation: crypto::confirmation_tag(&session_keys, crypto::Confirm::Responder(&local_id)),
+    };
+    crate::handshake::insert(&state.pending_confirmations, &addr.to_string(), crate::state::PendingConfirmation { device_id, session_keys });
+    if let Ok(resp_data) = serde_json::to_vec(&resp_struct) {
+        if let Err(e) = transport.send_message(addr, &resp_data).await {
+            tracing::warn!("Failed to send PairResponse to {}: {}", addr, e);
+            crate::handshake::forget(state, &addr.to_string());
+        }
+    }
+}
+
+// Initiator: check the responder's keys and prove ours with PairConfirm.
+// None if we have no handshake with `addr`; Err is the reason to show the user.
+async fn answer_pair_response<T: crate::transport::MessageTransport>(
+    state: &AppState,
+    transport: &T,
+    addr: std::net::SocketAddr,
+    msg: &[u8],
+    device_id: &str,
+    confirmation: &[u8],
+) -> Option<Result<(), crate::protocol::PairingError>> {
+    use crate::protocol::PairingError;
+    let spake_state = crate::handshake::take(&state.pending_handshakes, &addr.to_string())?;
+    let session_keys = match crypto::finish_spake2(spake_state, msg) {
+        Ok(keys) => keys,
+        Err(e) => {
+            tracing::error!("Auth Failed: {}", e);
+            return Some(Err(PairingError::KeyMismatch));
+        }
+    };
+    if confirmation.is_empty() {
+        tracing::warn!("PairResponse from {} has no key confirmation (older version)", device_id);
+        return Some(Err(PairingError::VersionMismatch));
+    }
+    if !crypto::verify_confirmation(&session_keys, crypto::Confirm::Responder(device_id), confirmation) {
+        tracing::warn!("Key confirmation from {} failed: PIN mismatch", device_id);
+        return Some(Err(PairingError::WrongPin));
+    }
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let confirm = Message::PairConfirm {
+        device_id: local_id.clone(),
+        confirmation: crypto::confirmation_tag(&session_keys, crypto::Confirm::Initiator(&local_id)),
+        device_key: crate::identity::seal(state, &session_keys),
+    };
+    crate::handshake::insert(&state.handshake_sessions, &addr.to_string(), session_keys);
+    let sent = match serde_json::to_vec(&confirm) {
+        Ok(data) => transport.send_message(addr, &data).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match sent {
+        Ok(_) => {
+            tracing::info!("Auth Success (Initiator)! Waiting for Welcome...");
+            Some(Ok(()))
+        }
+        Err(e) => {
+            tracing::error!("Failed to send PairConfirm to {}: {}", addr, e);
+            crate::handshake::forget(state, &addr.to_string());
+            Some(Err(PairingError::Unreachable))
+        }
+    }
+}
+
+// Responder: check the initiator's PairConfirm. Returns the device to welcome (or put to the
+// owner), None if there's nothing to admit (a wrong PIN is rejected here).
+async fn confirm_pairing<T: crate::transport::MessageTransport>(
+    state: &AppState,
+    transport: &T,
+    addr: std::net::SocketAddr,
+    device_id: String,
+    confirmation: Vec<u8>,
+    device_key: Vec<u8>,
+) -> Option<crate::state::PendingAdmission> {
+    let pending = crate::handshake::take(&state.pending_confirmations, &addr.to_string());
+    let Some(pending) = pending.filter(|p| p.device_id == device_id) else {
+        tracing::warn!("Received PairConfirm from {} ({}) with no handshake in progress", addr, device_id);
+        return None;
+    };
+    if !crypto::verify_confirmation(&pending.session_keys, crypto::Confirm::Initiator(&device_id), &confirmation) {
+        tracing::warn!("Key confirmation from {} failed: it used a different PIN", device_id);
+        send_pair_rejected(state, transport, addr, crate::protocol::PairingError::WrongPin).await;
+        return None;
+    }
+    tracing::info!("Authentication Success for {}!", device_id);
+    Some(crate::state::PendingAdmission {
+        public_key: crate::identity::open(&pending.session_keys, &device_key),
+        device_id,
+        addr,
+        session_keys: pending.session_keys,
+        created_at: 0,
+        guest_duration_secs: take_guest_invite(state),
+    })
+}
+
+// --- Onboarding (first-run wizard) ---
+// Progress is reported through "onboarding-progress" events as well as the return values.
+
+#[tauri::command]
+async fn detect_existing_clusters(
+    wait_secs: Option<u64>,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<crate::onboarding::DiscoveredCluster>, String> {
+    use crate::onboarding::{progress, OnboardingStage};
+    progress(&app_handle, OnboardingStage::Scanning, "Looking for networks on the LAN...");
+    // mDNS browsing runs continuously; give freshly started discovery a moment to resolve
+    let wait = wait_secs.unwrap_or(2).min(10);
+    tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+
+    let clusters = crate::onboarding::clusters(&state);
+    progress(&app_handle, OnboardingStage::Done, format!("Found {} network(s)", clusters.len()));
+    Ok(clusters)
+}
+
+#[tauri::command]
+fn create_cluster(
+    name: Option<String>,
+    pin: Option<String>,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(String, String), String> {
+    use crate::onboarding::{progress, OnboardingStage};
+    let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let pin = pin.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if name.as_ref().is_some_and(|n| n.len() > crate::validation::MAX_NETWORK_NAME_LEN) {
+        return Err("Network name is too long".to_string());
+    }
+    if pin.as_ref().is_some_and(|p| p.len() > crate::validation::MAX_PIN_LEN) {
+        return Err("PIN is too long".to_string());
+    }
+    // Don't silently abandon an existing cluster
+    let members = state.known_peers.lock().unwrap().values().filter(|p| p.is_trusted).count();
+    if members > 0 {
+        return Err(format!("This device is already in a cluster with {} device(s). Leave the network first.", members));
+    }
+
+    progress(&app_handle, OnboardingStage::Creating, "Creating a new cluster...");
+    perform_factory_reset(&app_handle, &state, state.bound_port());
+
+    if name.is_some() || pin.is_some() {
+        let name = name.unwrap_or_else(|| state.network_name.lock().unwrap().clone());
+        let pin = pin.unwrap_or_else(|| state.network_pin.lock().unwrap().clone());
+        set_network_identity(name, pin, state.clone(), app_handle.clone());
+    }
+
+    let name = state.network_name.lock().unwrap().clone();
+    let pin = state.network_pin.lock().unwrap().clone();
+    progress(&app_handle, OnboardingStage::Done, format!("Created network {}", name));
+    Ok((name, pin))
+}
+
+#[tauri::command]
+async fn join_cluster(
+    network: String,
+    pin: String,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    use crate::onboarding::{progress, OnboardingStage};
+    let fail = |msg: String| {
+        progress(&app_handle, OnboardingStage::Failed, msg.clone());
+        Err(msg)
+    };
+
+    progress(&app_handle, OnboardingStage::Scanning, format!("Looking for devices in {}...", network));
+    let candidates = crate::onboarding::join_candidates(&state, &network);
+    if candidates.is_empty() {
+        return fail(format!("No reachable devices in network {}", network));
+    }
+
+    // Joined = the Welcome handler replaced our key and adopted the network name
+    let old_key = state.cluster_key.lock().unwrap().clone();
+    let joined = |state: &AppState| {
+        *state.cluster_key.lock().unwrap() != old_key && *state.network_name.lock().unwrap() == network
+    };
+
+    // Wrong PIN etc. is reported by the PairResponse/Welcome handlers
+    let (fail_tx, mut fail_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let listener = app_handle.listen("pairing-failed", move |event| {
+        let reason = serde_json::from_str::<crate::events::PairingFailed>(event.payload()).map(|f| f.reason);
+        let _ = fail_tx.send(reason.unwrap_or_default());
+    });
+    // The responder parked us for the owner's approval, which can take the whole admission window
+    let (pending_tx, mut pending_rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+    let pending_listener = app_handle.listen("pairing-awaiting-approval", move |_| {
+        let _ = pending_tx.send(());
+    });
+
+    let mut result = fail(format!("No device in {} answered", network));
+    'candidates: for peer in candidates {
+        progress(&app_handle, OnboardingStage::Pairing, format!("Pairing with {}...", peer.hostname));
+        if let Err(e) = start_pairing(peer.id.clone(), pin.clone(), state.clone(), transport.clone(), app_handle.clone()).await {
+            tracing::warn!("[Onboarding] Could not reach {}: {}", peer.hostname, e);
+            continue;
+        }
+        progress(&app_handle, OnboardingStage::Waiting, format!("Waiting for {} (the cluster owner may need to approve)...", peer.hostname));
+
+        let mut deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(crate::onboarding::JOIN_ATTEMPT_TIMEOUT_SECS);
+        while tokio::time::Instant::now() < deadline {
+            if joined(&state) {
+                progress(&app_handle, OnboardingStage::Done, format!("Joined {}", network));
+                result = Ok(());
+                break 'candidates;
+            }
+            tokio::select! {
+                Some(reason) = fail_rx.recv() => {
+                    // The PIN is the same for every device, so don't retry elsewhere
+                    result = fail(reason);
+                    break 'candidates;
+                }
+                Some(()) = pending_rx.recv() => {
+                    progress(&app_handle, OnboardingStage::Waiting, format!("{} accepted the PIN. Waiting for the cluster owner to approve...", peer.hostname));
+                    deadline = tokio::time::Instant::now() + crate::handshake::ADMISSION_TTL;
+                }
+                _ = tokio::time::sleep(std::time::Duration::from_millis(250)) => {}
+            }
+        }
+        tracing::info!("[Onboarding] No answer from {}. Trying next device.", peer.hostname);
+    }
+
+    app_handle.unlisten(listener);
+    app_handle.unlisten(pending_listener);
+    result
+}
+
+// How long a paired-but-unapproved device waits for the owner's decision
+const ADMISSION_TIMEOUT_SECS: u64 = crate::handshake::ADMISSION_TTL.as_secs();
+
+pub(crate) fn decrypt_cluster_message<T: serde::de::DeserializeOwned>(state: &AppState, ciphertext: &[u8]) -> Option<T> {
+    let key = state.cluster_key.lock().unwrap().clone().filter(|k| k.len() == 32)?;
+    let mut key_arr = [0u8; 32];
+    key_arr.copy_from_slice(&key);
+    let plaintext = crypto::decrypt(&key_arr, ciphertext).ok()?;
+    serde_json::from_slice(&plaintext).ok()
+}
+
+pub(crate) fn encrypt_cluster_message<T: serde::Serialize>(state: &AppState, value: &T) -> Option<Vec<u8>> {
+    let key = state.cluster_key.lock().unwrap().clone().filter(|k| k.len() == 32)?;
+    let mut key_arr = [0u8; 32];
+    key_arr.copy_from_slice(&key);
+    let plaintext = serde_json::to_vec(value).ok()?;
+    crypto::encrypt(&key_arr, &plaintext).ok()
+}
+
+// Hand the cluster key to a device that completed the PIN handshake, and announce it
+async fn send_welcome(state: &AppState, app_handle: &tauri::AppHandle, transport: &Transport, joiner: crate::state::PendingAdmission) {
+    let crate::state::PendingAdmission { device_id, addr, session_keys, guest_duration_secs, public_key, .. } = joiner;
+    let session_keys = &session_keys;
+    // Guests hold a derived key they mustn't hand out (responder_password already refuses them)
+    if state.guest_expires_at.lock().unwrap().is_some() {
+        tracing::warn!("Not welcoming {}: this device is a guest", device_id);
+        send_pair_rejected(state, transport, addr, crate::protocol::PairingError::Rejected).await;
+        return;
+    }
+    let Some(cluster_key) = state.cluster_key.lock().unwrap().clone().filter(|k| k.len() == 32) else {
+        tracing::error!("Cannot welcome {}: No Cluster Key loaded.", device_id);
+        return;
+    };
+
+    // Guests get a derived key (and no PIN) instead of the cluster key
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let guest_expires_at = guest_duration_secs.map(|d| now + d);
+    let granted_key = match guest_expires_at {
+        Some(exp) => {
+            let mut key_arr = [0u8; 32];
+            key_arr.copy_from_slice(&cluster_key);
+            tracing::info!("Admitting {} as a guest until {}", device_id, exp);
+            crate::guest::derive_key(&key_arr, &device_id, exp).to_vec()
+        }
+        None => cluster_key,
+    };
+    let encrypted_ck = match crypto::encrypt(&session_keys.encryption, &granted_key) {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Failed to encrypt Cluster Key for {}: {}", device_id, e);
+            return;
+        }
+    };
+
+    let known_peers = state.known_peers.lock().unwrap().values().cloned().collect();
+    let network_name = state.network_name.lock().unwrap().clone();
+    let network_pin = if guest_expires_at.is_some() { String::new() } else { state.network_pin.lock().unwrap().clone() };
+    let welcome = Message::Welcome {
+        encrypted_cluster_key: encrypted_ck,
+        known_peers,
+        network_name: network_name.clone(),
+        network_pin,
+        owner_id: state.cluster_owner.lock().unwrap().clone(),
+        guest_expires_at,
+        key_confirmation: crypto::confirmation_tag(session_keys, crypto::Confirm::Welcome(&device_id)),
+        device_key: crate::identity::seal(state, session_keys),
+    };
+    let Ok(welcome_data) = serde_json::to_vec(&welcome) else {
+        return;
+    };
+    let _ = transport.send_message(addr, &welcome_data).await;
+
+    let p = crate::peer::Peer {
+        id: device_id.clone(),
+        ip: addr.ip(),
+        port: addr.port(),
+        hostname: format!("Peer ({})", addr.ip()),
+        last_seen: now,
+        is_trusted: true,
+        is_manual: false,
+        network_name: Some(network_name),
+        signature: None,
+        guest_expires_at,
+        capabilities: None,
+        platform: None,
+        addresses: Vec::new(),
+        observed_addr: None,
+        public_key,
+    };
     {
-        let mut kp = state.known_peers.lock().unwrap();
-        if kp.remove(&peer_id).is_some() {
-            save_known_peers(&app_handle, &kp);
-        }
+        let mut kp_lock = state.known_peers.lock().unwrap();
+        kp_lock.insert(device_id, p.clone());
+        save_known_peers(app_handle, &kp_lock);
     }
+    state.add_peer(p.clone());
+    crate::events::emit(app_handle, crate::events::PeerUpdate(&p));
+    announce_membership(&p, state, transport, Some(addr));
+}
 
-    // 2. Remove from Runtime Peers
+fn notify_admission_request(app_handle: &tauri::AppHandle, request: &crate::protocol::AdmissionRequest) {
+    crate::events::emit(app_handle, crate::events::AdmissionRequested(request));
+    let body = i18n::tf("notify.admission.body", &[("device", &request.device_id), ("addr", &request.addr.to_string())]);
+    send_notification(app_handle, &i18n::t("notify.admission.title"), &body, false, None, "devices", NotificationPayload::None);
+}
+
+// Park a freshly paired device and ask the cluster owner whether to let it in
+async fn request_admission(state: &AppState, app_handle: &tauri::AppHandle, transport: &Transport, joiner: crate::state::PendingAdmission) {
+    let (device_id, addr, guest_duration_secs) = (joiner.device_id.clone(), joiner.addr, joiner.guest_duration_secs);
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let request = crate::protocol::AdmissionRequest {
+        request_id: uuid::Uuid::new_v4().to_string(),
+        device_id: device_id.clone(),
+        addr: addr.to_string(),
+        via: local_id.clone(),
+        requested_at: now,
+        guest_duration_secs,
+    };
     {
-        let mut peers = state.peers.lock().unwrap();
-        peers.remove(&peer_id);
+        let mut pending = state.pending_admissions.lock().unwrap();
+        pending.retain(|_, p| now.saturating_sub(p.created_at) < ADMISSION_TIMEOUT_SECS);
+        pending.insert(request.request_id.clone(), crate::state::PendingAdmission { created_at: now, ..joiner });
     }
 
-    // 3. Emit Removal
-    let _ = app_handle.emit("peer-remove", &peer_id);
+    // We are the owner, or the cluster predates owner approval: decide locally
+    let owner = state.cluster_owner.lock().unwrap().clone();
+    let Some(owner_id) = owner.filter(|o| *o != local_id) else {
+        tracing::info!("Admission for {} awaiting local approval", device_id);
+        notify_admission_request(app_handle, &request);
+        state.admission_requests.lock().unwrap().insert(request.request_id.clone(), (request, None));
+        send_admission_pending(state, transport, addr).await;
+        return;
+    };
 
-    Ok(())
+    // Nobody else can approve, so an unreachable owner ends the attempt instead of leaving it waiting
+    let owner_addr = state.peers.lock().unwrap().get(&owner_id).map(|p| std::net::SocketAddr::new(p.ip, p.port));
+    let forwarded = match (owner_addr, encrypt_cluster_message(state, &request)) {
+        (None, _) => Err("it is offline".to_string()),
+        (Some(_), None) => Err("no cluster key to forward the request with".to_string()),
+        (Some(owner_addr), Some(cipher)) => {
+            let data = serde_json::to_vec(&Message::AdmissionRequest(cipher)).unwrap_or_default();
+            tracing::info!("Forwarding admission of {} to cluster owner {}", device_id, owner_id);
+            transport.send_message(owner_addr, &data).await.map_err(|e| e.to_string())
+        }
+    };
+    match forwarded {
+        Ok(()) => send_admission_pending(state, transport, addr).await,
+        Err(e) => {
+            tracing::warn!("Can't reach cluster owner {} to admit {} ({}). Turning the request down.", owner_id, device_id, e);
+            state.pending_admissions.lock().unwrap().remove(&request.request_id);
+            send_pair_rejected(state, transport, addr, crate::protocol::PairingError::OwnerOffline).await;
+        }
+    }
+}
+
+// Apply the owner's decision to a device parked by request_admission
+async fn complete_admission(state: &AppState, app_handle: &tauri::AppHandle, transport: &Transport, request_id: &str, approved: bool) {
+    let Some(pending) = state.pending_admissions.lock().unwrap().remove(request_id) else {
+        tracing::warn!("No pending admission for request {}", request_id);
+        return;
+    };
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    if now.saturating_sub(pending.created_at) >= ADMISSION_TIMEOUT_SECS {
+        tracing::warn!("Admission for {} expired before the owner decided", pending.device_id);
+        send_pair_rejected(state, transport, pending.addr, crate::protocol::PairingError::Timeout).await;
+        return;
+    }
+    if approved {
+        tracing::info!("Cluster owner approved {}. Sending Welcome.", pending.device_id);
+        send_welcome(state, app_handle, transport, pending).await;
+    } else {
+        tracing::info!("Cluster owner denied {}", pending.device_id);
+        send_pair_rejected(state, transport, pending.addr, crate::protocol::PairingError::Rejected).await;
+    }
 }
 
 #[tauri::command]
-async fn start_pairing(
-    peer_id: String,
-    pin: String,
+fn get_admission_requests(state: tauri::State<'_, AppState>) -> Vec<crate::protocol::AdmissionRequest> {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut requests = state.admission_requests.lock().unwrap();
+    requests.retain(|_, (r, _)| now.saturating_sub(r.requested_at) < ADMISSION_TIMEOUT_SECS);
+    requests.values().map(|(r, _)| r.clone()).collect()
+}
+
+#[tauri::command]
+async fn respond_to_admission(
+    request_id: String,
+    approve: bool,
     state: tauri::State<'_, AppState>,
     transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
-    // 1. Find peer to get IP
-    let peer_addr = {
-        let peers = state.get_peers();
-        if let Some(peer) = peers.get(&peer_id) {
-            std::net::SocketAddr::new(peer.ip, peer.port)
-        } else {
-            return Err("Peer not found".to_string());
+    let (request, relay) = state
+        .admission_requests
+        .lock()
+        .unwrap()
+        .remove(&request_id)
+        .ok_or("Admission request not found or expired")?;
+    tracing::info!("Admission of {}: {}", request.device_id, if approve { "approved" } else { "denied" });
+
+    match relay {
+        None => complete_admission(&state, &app_handle, &transport, &request_id, approve).await,
+        Some(relay_addr) => {
+            let decision = crate::protocol::AdmissionDecision { request_id, approved: approve };
+            let cipher = encrypt_cluster_message(&state, &decision).ok_or("No Cluster Key set")?;
+            let data = serde_json::to_vec(&Message::AdmissionResponse(cipher)).map_err(|e| e.to_string())?;
+            transport.send_message(relay_addr, &data).await.map_err(|e| e.to_string())?;
         }
-    };
-
-    // 2. Start SPAKE2
-    let (spake_state, msg) =
-        crypto::start_spake2(&pin, "clustercut-connect", "clustercut-connect").map_err(|e| e.to_string())?;
-
-    // 3. Store state
-    {
-        let mut pending = state.pending_handshakes.lock().unwrap();
-        pending.insert(peer_addr.to_string(), spake_state); // Store by address
     }
+    Ok(())
+}
 
-    // 4. Send Message
-    let local_id = { state.local_device_id.lock().unwrap().clone() };
+// Consume an armed guest invite (if still within its window): Some(guest duration)
+fn take_guest_invite(state: &AppState) -> Option<u64> {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (deadline, duration) = state.guest_invite.lock().unwrap().take()?;
+    (now <= deadline).then_some(duration)
+}
 
-    let msg_struct = Message::PairRequest {
-        msg,
-        device_id: local_id,
-    };
-    let data = serde_json::to_vec(&msg_struct).map_err(|e| e.to_string())?;
+// Arm guest mode: the next device to pair (within the invite window) joins as a guest
+#[tauri::command]
+fn invite_guest(duration_secs: Option<u64>, state: tauri::State<'_, AppState>) -> Result<u64, String> {
+    if state.guest_expires_at.lock().unwrap().is_some() {
+        return Err("Guests cannot invite other devices".to_string());
+    }
+    let duration = duration_secs
+        .unwrap_or(crate::guest::DEFAULT_GUEST_DURATION_SECS)
+        .clamp(60, crate::guest::MAX_GUEST_DURATION_SECS);
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let deadline = now + crate::guest::GUEST_INVITE_WINDOW_SECS;
+    *state.guest_invite.lock().unwrap() = Some((deadline, duration));
+    tracing::info!("Guest invite armed for {}s (pair before {})", duration, deadline);
+    Ok(deadline)
+}
 
-    transport
-        .send_message(peer_addr, &data)
-        .await
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+fn cancel_guest_invite(state: tauri::State<'_, AppState>) {
+    *state.guest_invite.lock().unwrap() = None;
+}
 
-    Ok(())
+// Our own guest expiry (None = full member)
+#[tauri::command]
+fn get_guest_expiry(state: tauri::State<'_, AppState>) -> Option<u64> {
+    *state.guest_expires_at.lock().unwrap()
 }
 
 // Helper to wipe state and restart network identity
@@ -1277,8 +2938,6 @@ fn perform_factory_reset(app_handle: &tauri::AppHandle, state: &AppState, port:
         let mut kp = state.known_peers.lock().unwrap();
         let mut peers = state.peers.lock().unwrap();
         let mut ck = state.cluster_key.lock().unwrap();
-        let mut ph = state.pending_handshakes.lock().unwrap();
-        let mut hs = state.handshake_sessions.lock().unwrap();
         let mut nn = state.network_name.lock().unwrap();
         let mut np = state.network_pin.lock().unwrap();
 
@@ -1293,9 +2952,19 @@ fn perform_factory_reset(app_handle: &tauri::AppHandle, state: &AppState, port:
         rand::thread_rng().fill(&mut new_key);
         *ck = Some(new_key.to_vec());
         save_cluster_key(app_handle, &new_key);
+
+        // New cluster, owned by us
+        let local_id = state.local_device_id.lock().unwrap().clone();
+        crate::storage::save_cluster_owner(app_handle, Some(&local_id));
+        *state.cluster_owner.lock().unwrap() = Some(local_id);
+        state.pending_admissions.lock().unwrap().clear();
+        state.admission_requests.lock().unwrap().clear();
+        state.presence.lock().unwrap().clear();
+        state.removed_members.lock().unwrap().clear();
+        *state.guest_invite.lock().unwrap() = None;
+        *state.guest_expires_at.lock().unwrap() = None;
         
-        ph.clear();
-        hs.clear();
+        crate::handshake::clear(state);
 
         // Load new identity (generated by accessors if missing)
         let new_name_val = load_network_name(app_handle);
@@ -1317,17 +2986,111 @@ fn perform_factory_reset(app_handle: &tauri::AppHandle, state: &AppState, port:
     }
     
     // 4. Notify Frontend
-    let _ = app_handle.emit("network-reset", ());
+    crate::events::emit(app_handle, crate::events::NetworkReset {});
+    // New key, new phrase: prompt the user to write it down
+    crate::events::emit(app_handle, crate::events::RecoveryPhraseCreated {});
+}
+
+// How long a removed device waits for confirmation before wiping itself
+const RESET_GRACE_PERIOD_SECS: u64 = 5 * 60;
+// After a restart, the user gets at least this long to answer a reset whose deadline passed
+const RESET_RESUME_GRACE_SECS: u64 = 60;
+
+// PeerRemoval for ourselves could be spoofed, so don't wipe immediately:
+// pause syncing, ask the user, and only reset after confirmation or the grace period.
+// The deadline is stored, so quitting the app neither loses nor skips the reset.
+fn stage_pending_reset(app_handle: &tauri::AppHandle, state: &AppState) {
+    let deadline = {
+        let mut pending = state.pending_reset.lock().unwrap();
+        if pending.is_some() {
+            tracing::info!("Reset already pending. Ignoring duplicate PeerRemoval.");
+            return;
+        }
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+        let deadline = now + RESET_GRACE_PERIOD_SECS;
+        *pending = Some(deadline);
+        deadline
+    };
+    crate::storage::save_pending_reset(app_handle, Some(deadline));
+
+    crate::events::emit(app_handle, crate::events::ResetPending { deadline });
+    let body = i18n::tf("notify.removed.body", &[("minutes", &(RESET_GRACE_PERIOD_SECS / 60).to_string())]);
+    send_notification(app_handle, &i18n::t("notify.removed.title"), &body, false, None, "settings", NotificationPayload::None);
+    arm_reset_timer(app_handle, state, deadline);
+}
+
+// Wipe once `deadline` passes, unless the reset was confirmed or cancelled by then.
+// Returns how long that is from now, in seconds.
+fn arm_reset_timer(app_handle: &tauri::AppHandle, state: &AppState, deadline: u64) -> u64 {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+    let wait = deadline.saturating_sub(now).max(RESET_RESUME_GRACE_SECS);
+    let app = app_handle.clone();
+    let state = state.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+        // Only fire if this exact reset is still pending (not cancelled/confirmed/restaged)
+        let still_pending = {
+            let mut pending = state.pending_reset.lock().unwrap();
+            if *pending == Some(deadline) {
+                *pending = None;
+                true
+            } else {
+                false
+            }
+        };
+        if still_pending {
+            tracing::warn!("Reset grace period expired. Resetting state...");
+            crate::storage::save_pending_reset(&app, None);
+            perform_factory_reset(&app, &state, state.bound_port());
+        }
+    });
+    wait
+}
+
+#[tauri::command]
+fn get_pending_reset(state: tauri::State<'_, AppState>) -> Option<u64> {
+    *state.pending_reset.lock().unwrap()
+}
+
+#[tauri::command]
+fn confirm_pending_reset(state: tauri::State<'_, AppState>, app_handle: tauri::AppHandle) -> Result<(), String> {
+    if state.pending_reset.lock().unwrap().take().is_none() {
+        return Err("No reset pending".to_string());
+    }
+    crate::storage::save_pending_reset(&app_handle, None);
+    tracing::warn!("Pending reset confirmed by user. Resetting state...");
+    perform_factory_reset(&app_handle, &state, state.bound_port());
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_pending_reset(state: tauri::State<'_, AppState>, app_handle: tauri::AppHandle) -> Result<(), String> {
+    if state.pending_reset.lock().unwrap().take().is_none() {
+        return Err("No reset pending".to_string());
+    }
+    crate::storage::save_pending_reset(&app_handle, None);
+    tracing::info!("Pending reset cancelled by user. Resuming sync.");
+    crate::events::emit(&app_handle, crate::events::ResetCancelled {});
+    Ok(())
 }
 
 #[tauri::command]
 async fn send_clipboard(
     text: String,
+    channel: Option<String>,
     state: tauri::State<'_, AppState>,
     transport: tauri::State<'_, Transport>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     
+    if state.is_reset_pending() {
+        return Err(AppError::Invalid("Syncing is paused while a reset is pending".to_string()));
+    }
+    let channel = channel.unwrap_or_else(|| state.settings.lock().unwrap().send_channel.clone());
+    if !crate::protocol::is_valid_channel(&channel) {
+        return Err(AppError::Invalid(format!("Invalid channel name '{}'", channel)));
+    }
+
     // Manual Send Command
     clipboard::set_clipboard(&app_handle, text.clone()); // Update local clipboard too? Yes, usually.
     
@@ -1337,17 +3100,25 @@ async fn send_clipboard(
     let msg_id = uuid::Uuid::new_v4().to_string();
     let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
 
-    let payload_obj = crate::protocol::ClipboardPayload {
+    let mut payload_obj = crate::protocol::ClipboardPayload {
         id: msg_id.clone(),
         text: text.clone(),
         timestamp: ts,
         sender: hostname,
         sender_id: local_id,
         files: None,
+        source: None,
+        trace: crate::latency::start_trace(&state),
+        channel,
+        link: None,
+        content: None,
+        auth: None,
     };
+    crate::senderauth::sign(&state, &mut payload_obj);
 
     // Emit local event so history updates
-    let _ = app_handle.emit("clipboard-change", &payload_obj);
+    crate::history::commit(&app_handle, &payload_obj);
+    crate::unfurl::after_send(&app_handle, &payload_obj);
 
     // Encrypt & Send
     let ck_lock = state.cluster_key.lock().unwrap();
@@ -1355,23 +3126,27 @@ async fn send_clipboard(
         if key.len() == 32 {
              let mut key_arr = [0u8; 32];
              key_arr.copy_from_slice(key);
-             let json_payload = serde_json::to_vec(&payload_obj).map_err(|e| e.to_string())?;
+             let json_payload = serde_json::to_vec(&payload_obj).map_err(|e| AppError::Invalid(e.to_string()))?;
              
              match crypto::encrypt(&key_arr, &json_payload) {
                  Ok(cipher) => {
                      let msg = Message::Clipboard(cipher);
-                     let data = serde_json::to_vec(&msg).map_err(|e| e.to_string())?;
+                     let data = serde_json::to_vec(&msg).map_err(|e| AppError::Invalid(e.to_string()))?;
                      
                      let peers = state.get_peers();
                      for p in peers.values() {
                          let addr = std::net::SocketAddr::new(p.ip, p.port);
                          let transport_clone = (*transport).clone();
-                         let data_vec = data.clone();
+                         let Some(data_vec) = clipboard::data_for_peer(&state, &key_arr, p, &json_payload, &data) else { continue; };
+                         let app = app_handle.clone();
+                         let stats_state = (*state).clone();
+                         let peer_id = p.id.clone();
                          tauri::async_runtime::spawn(async move {
                              if let Err(e) = transport_clone.send_message(addr, &data_vec).await {
-                                 tracing::error!("[Clipboard] Failed to send to {}: {}", addr, e);
+                                 crate::error::report(&app, "send", &AppError::Network(format!("Failed to send to {}: {}", addr, e)));
                              } else {
                                  tracing::debug!("[Clipboard] Sent to {}", addr);
+                                 crate::peerstats::record(&stats_state, &peer_id, crate::peerstats::Direction::Sent, crate::peerstats::Kind::Item, data_vec.len() as u64);
                              }
                          });
                      }
@@ -1379,18 +3154,25 @@ async fn send_clipboard(
                      // Notify locally
                      let notifications = state.settings.lock().unwrap().notifications.clone();
                      if notifications.data_sent {
-                         send_notification(&app_handle, "Clipboard Sent", "Manual broadcast successful.", false, Some(2), "history", NotificationPayload::None);
+                         send_notification(&app_handle, &i18n::t("notify.clipboard_sent.title"), &i18n::t("notify.clipboard_sent.manual_body"), false, Some(2), "history", NotificationPayload::None);
                      }
                      
                      Ok(())
                  },
-                 Err(e) => Err(format!("Encryption failed: {}", e))
+                 Err(e) => Err(AppError::Crypto(format!("Encryption failed: {}", e)))
              }
         } else {
-            Err("Invalid Cluster Key".to_string())
+            Err(AppError::Crypto("Invalid Cluster Key".to_string()))
         }
     } else {
-        Err("No Cluster Key set".to_string())
+        Err(AppError::Crypto("No Cluster Key set".to_string()))
+    }
+}
+
+fn forget_history_annotation(app_handle: &tauri::AppHandle, state: &AppState, id: &str) {
+    let mut annotations = state.history_annotations.lock().unwrap();
+    if annotations.remove(id).is_some() {
+        crate::storage::save_history_annotations(app_handle, &annotations);
     }
 }
 
@@ -1403,7 +3185,8 @@ async fn delete_history_item(
 ) -> Result<(), String> {
     // 1. Emit Local Event (to update UI immediately)
     tracing::info!("Deleting history item locally: {}", id);
-    let _ = app_handle.emit("history-delete", &id);
+    forget_history_annotation(&app_handle, &state, &id);
+    crate::events::emit(&app_handle, crate::events::HistoryDeleted { id: id.clone() });
 
     // 2. Broadcast to Peers
     let msg = Message::HistoryDelete(id);
@@ -1421,6 +3204,66 @@ async fn delete_history_item(
     Ok(())
 }
 
+// Longest note we accept on a history item
+const MAX_ANNOTATION_LEN: usize = 200;
+
+#[tauri::command]
+fn get_history_annotations(state: tauri::State<'_, AppState>) -> std::collections::HashMap<String, crate::protocol::HistoryAnnotation> {
+    state.history_annotations.lock().unwrap().clone()
+}
+
+#[tauri::command]
+async fn annotate_history_item(
+    app_handle: tauri::AppHandle,
+    id: String,
+    note: Option<String>,
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+) -> Result<(), String> {
+    let note = note
+        .map(|n| n.trim().chars().take(MAX_ANNOTATION_LEN).collect::<String>())
+        .filter(|n| !n.is_empty());
+    let annotation = crate::protocol::HistoryAnnotation {
+        id: id.clone(),
+        note,
+        updated_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+    };
+
+    // 1. Store & Emit Locally
+    tracing::info!("Annotating history item: {}", id);
+    state.apply_history_annotation(annotation.clone());
+    crate::storage::save_history_annotations(&app_handle, &state.history_annotations.lock().unwrap());
+    crate::events::emit(&app_handle, crate::events::HistoryAnnotated(&annotation));
+
+    // 2. Broadcast to Peers (encrypted, notes may be sensitive)
+    let key_arr = {
+        let ck = state.cluster_key.lock().unwrap();
+        let key = ck.as_ref().filter(|k| k.len() == 32).ok_or("No Cluster Key set")?;
+        let mut arr = [0u8; 32];
+        arr.copy_from_slice(key);
+        arr
+    };
+    let json = serde_json::to_vec(&annotation).map_err(|e| e.to_string())?;
+    let cipher = crypto::encrypt(&key_arr, &json).map_err(|e| e.to_string())?;
+    let data = serde_json::to_vec(&Message::HistoryAnnotate(cipher)).map_err(|e| e.to_string())?;
+
+    let peers = state.get_peers();
+    for p in peers.values() {
+         let addr = std::net::SocketAddr::new(p.ip, p.port);
+         let transport_clone = (*transport).clone();
+         let data_vec = data.clone();
+         tauri::async_runtime::spawn(async move {
+             let _ = transport_clone.send_message(addr, &data_vec).await;
+         });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn fetch_link_preview(app_handle: tauri::AppHandle, id: String) -> Result<crate::protocol::LinkPreview, String> {
+    crate::unfurl::fetch_for_item(&app_handle, &id).await
+}
+
 #[tauri::command]
 async fn set_local_clipboard(app: tauri::AppHandle, text: String) -> Result<(), String> {
     clipboard::set_clipboard(&app, text);
@@ -1445,13 +3288,19 @@ async fn retry_connection(
     
     // Re-run the startup probe logic
     tauri::async_runtime::spawn(async move {
-         let known_peers = {
-             state_owned.known_peers.lock().unwrap().clone()
-         };
+         // Peers that are online don't need reconnecting
+         let known_peers: Vec<Peer> = state_owned
+             .known_peers
+             .lock()
+             .unwrap()
+             .clone()
+             .into_values()
+             .filter(|p| crate::presence::of(&state_owned, &p.id) != crate::presence::Presence::Online)
+             .collect();
          
          if !known_peers.is_empty() {
              tracing::info!("Retry Connection: Probing {} known peers...", known_peers.len());
-             for (_id, peer) in known_peers {
+             for peer in known_peers {
                  let s = state_owned.clone();
                  let t = transport_owned.clone();
                  let a = app_handle_clone.clone();
@@ -1463,41 +3312,70 @@ async fn retry_connection(
          } else {
              // If no known peers, maybe we should try scanning? 
              // But for now, we only care about reconnecting to knowns.
-             tracing::warn!("Retry Connection: No known peers to probe.");
+             tracing::warn!("Retry Connection: No unreachable known peers to probe.");
          }
     });
     
     Ok(())
 }
 
+/// Apply a pending incoming item (the most recent one when `id` is omitted).
 #[tauri::command]
 async fn confirm_pending_clipboard(
+    id: Option<String>,
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let pending_opt = {
-        let mut lock = state.pending_clipboard.lock().unwrap();
-        lock.take() // Take it (clearing it)
-    };
+) -> Result<(), AppError> {
+    match clipboard::apply_pending(&app_handle, &state, id.as_deref()) {
+        Some(_) => Ok(()),
+        None => Err(AppError::Invalid("No pending clipboard content".to_string())),
+    }
+}
 
-    if let Some(payload) = pending_opt {
-        tracing::info!("Confirming pending clipboard from {}", payload.sender);
-        clipboard::set_clipboard(&app_handle, payload.text.clone());
-        
-        // Emit change event so history updates
-        let _ = app_handle.emit("clipboard-change", &payload);
-        
+#[tauri::command]
+fn discard_pending_clipboard(id: String, state: tauri::State<'_, AppState>, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    if clipboard::discard_pending(&app_handle, &state, &id) {
+        Ok(())
+    } else {
+        Err(AppError::Invalid("That item is no longer pending".to_string()))
+    }
+}
+
+/// Pending incoming items, oldest first.
+#[tauri::command]
+fn get_pending_clipboards(state: tauri::State<'_, AppState>) -> Vec<crate::protocol::ClipboardPayload> {
+    state.pending_clipboard.lock().unwrap().values().cloned().collect()
+}
+
+#[tauri::command]
+fn confirm_outgoing_clipboard(
+    state: tauri::State<'_, AppState>,
+    transport: tauri::State<'_, Transport>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    if clipboard::confirm_outgoing(&app_handle, &state, &transport) {
+        Ok(())
+    } else {
+        Err(AppError::Invalid("No clipboard content waiting to be sent".to_string()))
+    }
+}
+
+#[tauri::command]
+fn discard_outgoing_clipboard(state: tauri::State<'_, AppState>, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    if clipboard::discard_outgoing(&app_handle, &state) {
         Ok(())
     } else {
-        Err("No pending clipboard content".to_string())
+        Err(AppError::Invalid("No clipboard content waiting to be sent".to_string()))
     }
 }
 
+#[tauri::command]
+fn get_pending_outgoing(state: tauri::State<'_, AppState>) -> Option<crate::protocol::ClipboardPayload> {
+    state.pending_outgoing.lock().unwrap().clone()
+}
+
 #[cfg(target_os = "linux")]
 fn spawn_linux_theme_poller(app: tauri::AppHandle) {
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
-    
     let app_handle = app.clone();
     
     // We already have a shutdown flag in AppState, but accessing it requires state which might be locked?
@@ -1548,7 +3426,7 @@ fn spawn_linux_theme_poller(app: tauri::AppHandle) {
                         let _ = app_handle.emit("tauri://theme-changed", simple_theme);
                     }
                 },
-                Err(e) => {
+                Err(_e) => {
                     // Reduce log spam if gsettings is missing (e.g. non-GNOME)
                     // tracing::debug!("Failed to run gsettings: {}", e);
                 }
@@ -1566,7 +3444,39 @@ pub fn run() {
     
     // Initialize Logging and get Args
     let args = init_logging();
-    let minimized_arg = args.minimized;
+    let tui_arg = args.tui;
+    let minimized_arg = args.minimized || tui_arg;
+    let config_arg = args.config.clone();
+
+    let instance_arg = args.instance.filter(|n| *n > 0);
+    // Each dev instance gets its own storage namespace
+    let profile_arg = match instance_arg {
+        Some(n) => {
+            tracing::warn!("Dev instance mode: instance {} (port offset +{}, single-instance disabled)", n, n);
+            Some(format!("{}-instance-{}", args.profile.as_deref().unwrap_or("default"), n))
+        }
+        None => args.profile.clone(),
+    };
+
+    if let Some(profile) = &profile_arg {
+        if crate::storage::is_valid_profile_name(profile) {
+            tracing::info!("Using profile: {}", profile);
+            crate::storage::set_active_profile(Some(profile.clone()));
+        } else {
+            tracing::error!("Invalid profile name '{}'. Using default profile.", profile);
+        }
+    }
+
+    if let Some(command) = &args.command {
+        std::process::exit(crate::control::run_client(command, crate::storage::active_profile().as_deref()));
+    }
+
+    // The TUI runs on top of the (hidden) Tauri window, so GTK/WebKit still need a display
+    #[cfg(target_os = "linux")]
+    if tui_arg && std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        eprintln!("clustercut: --tui needs a display server (the app window is hidden, not absent). On a headless machine run it under Xvfb: xvfb-run clustercut --tui");
+        std::process::exit(1);
+    }
     
     let mut builder = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -1579,17 +3489,30 @@ pub fn run() {
         builder = builder.plugin(tauri_plugin_deep_link::init());
     }
 
-    builder
-        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+    // Dev instances must be able to run side by side
+    if instance_arg.is_none() {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            // Download action from Toast is handled entirely in the backend
+            if let Some(url) = args.iter().find(|a| a.starts_with("clustercut://action/download")) {
+                handle_download_deep_link(app, url);
+            }
+            // So are Open / Show in Folder on a received file
+            if let Some(url) = args.iter().find(|a| a.starts_with("clustercut://action/open-file") || a.starts_with("clustercut://action/reveal-file")) {
+                handle_file_deep_link(app, url);
+                return;
+            }
+            // Launched again at login (or by a script) while running: stay in the tray
+            if args.iter().any(|a| a == "--minimized" || a == "--hidden") {
+                return;
+            }
             // Handle deep link activation from Toast
-            let _ = app.emit("deep-link", args);
+            crate::events::emit(app, crate::events::DeepLink { args });
             // Always bring to front on activation
-             if let Some(win) = app.get_webview_window("main") {
-                 let _ = win.unminimize();
-                 let _ = win.show();
-                 let _ = win.set_focus();
-             }
-        }))
+            show_main_window(app);
+        }));
+    }
+
+    builder
         // Pass --minimized to autostart args
         .plugin(tauri_plugin_autostart::init(tauri_plugin_autostart::MacosLauncher::LaunchAgent, Some(vec!["--minimized"])))
         .plugin(tauri_plugin_notification::init())
@@ -1612,7 +3535,13 @@ pub fn run() {
                 let _ = window.show();
                 let _ = window.set_focus();
 
-                if minimized_arg {
+                // Opened from a notification: the window is what the user asked for
+                let launched_by_link = std::env::args().any(|a| a.starts_with("clustercut://"));
+                // The setting waits for a first pairing; until then the window is the only way in
+                let start_hidden = minimized_arg || {
+                    load_settings(app.handle()).start_minimized && !crate::storage::load_known_peers(app.handle()).is_empty()
+                };
+                if start_hidden && !launched_by_link {
                     tracing::info!("Starting in minimized mode. Hiding window immediately.");
                     let _ = window.hide();
                 } else {
@@ -1620,15 +3549,48 @@ pub fn run() {
                 }
             }
 
-            // Clear Cache on Startup
-            clear_cache(app.handle());
+            // Apply received-file retention on startup (keeps resumable partial transfers)
+            crate::cache::clean(app.handle(), &load_settings(app.handle()));
+            {
+                let incomplete = crate::storage::load_partial_transfers(app.handle());
+                if !incomplete.is_empty() {
+                    tracing::info!("Found {} incomplete transfers from a previous session", incomplete.len());
+                    let state = app.state::<AppState>();
+                    let mut partials = state.partial_transfers.lock().unwrap();
+                    for t in incomplete {
+                        partials.insert(t.key(), t);
+                    }
+                }
+            }
+
+            // Config File (clustercut.toml / --config)
+            let config_path = crate::config::resolve_path(app.handle(), config_arg.as_deref());
+            let file_config = match config_path.as_deref().map(crate::config::load) {
+                Some(Ok(c)) => c,
+                Some(Err(e)) => {
+                    tracing::error!("{}. Ignoring config file.", e);
+                    crate::config::FileConfig::default()
+                }
+                None => crate::config::FileConfig::default(),
+            };
+            // Dev instances listen on base port + instance number; refuse to wrap past 65535
+            let base_port = file_config.port.unwrap_or(DEFAULT_PORT);
+            let listen_port = match base_port.checked_add(instance_arg.unwrap_or(0)) {
+                Some(p) => p,
+                None => {
+                    return Err(format!(
+                        "Instance {} is out of range: base port {} + offset exceeds 65535",
+                        instance_arg.unwrap_or(0), base_port
+                    ).into());
+                }
+            };
 
-            // Initialize QUIC Transport (Fixed Port 4654 for Discovery, or random fallback)
+            // Initialize QUIC Transport (Fixed Port for Discovery, or random fallback)
             let transport = tauri::async_runtime::block_on(async {  
-                match Transport::new(4654) {
+                match Transport::new(listen_port) {
                     Ok(t) => Ok(t),
                     Err(e) => {
-                        tracing::warn!("Failed to bind port 4654 ({}). Falling back to random port.", e);
+                        tracing::warn!("Failed to bind port {} ({}). Falling back to random port.", listen_port, e);
                         Transport::new(0)
                     }
                 }
@@ -1636,6 +3598,7 @@ pub fn run() {
 
             let port = transport.local_addr().expect("Failed to get port").port();
             tracing::info!("QUIC Transport listening on port {}", port);
+            app.state::<AppState>().set_bound_port(port);
 
             #[cfg(target_os = "macos")]
             app.set_activation_policy(tauri::ActivationPolicy::Accessory);
@@ -1664,9 +3627,21 @@ pub fn run() {
             
             #[cfg(desktop)]
             {
-                let _ = crate::tray::create_tray(&app_handle);
+                let _ = crate::tray::create_tray(app_handle);
             }
 
+            crate::history::track(app_handle);
+            crate::peerstats::spawn_flusher(app_handle.clone());
+            crate::offers::spawn_sweeper(app_handle.clone());
+            crate::peersync::spawn(app_handle.clone());
+
+            let control_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = crate::control::serve(control_handle).await {
+                    tracing::error!("Failed to start control socket: {}", e);
+                }
+            });
+
             #[cfg(target_os = "linux")]
             {
                 let dbus_handle = app_handle.clone();
@@ -1696,21 +3671,32 @@ pub fn run() {
             {
                 let state = app.state::<AppState>();
 
+                // 0. Migrate Storage (before anything is deserialized)
+                let migration = crate::migration::run(app_handle);
+
                 // 1. Load Cluster Key
                 let mut ck_lock = state.cluster_key.lock().unwrap();
-                if let Some(key) = load_cluster_key(app_handle) {
+                let key_generated = if let Some(key) = load_cluster_key(app_handle) {
                     *ck_lock = Some(key);
+                    false
                 } else {
                     tracing::info!("No Cluster Key found. Generating new one...");
                     let mut new_key = [0u8; 32];
                     rand::thread_rng().fill(&mut new_key);
                     save_cluster_key(app_handle, &new_key);
                     *ck_lock = Some(new_key.to_vec());
-                }
+                    true
+                };
 
                 // 2. Load Known Peers
                 let mut kp_lock = state.known_peers.lock().unwrap();
                 *kp_lock = load_known_peers(app_handle);
+                *state.peer_download_policies.lock().unwrap() = crate::storage::load_peer_download_policies(app_handle);
+                *state.peer_permissions.lock().unwrap() = crate::storage::load_peer_permissions(app_handle);
+                *state.peer_stats.lock().unwrap() = crate::peerstats::StatsStore::new(crate::storage::load_peer_stats(app_handle));
+                *state.blocked_peers.lock().unwrap() = crate::storage::load_blocked_peers(app_handle);
+                crate::peersync::load_removals(app_handle, &state);
+                *state.history_annotations.lock().unwrap() = crate::storage::load_history_annotations(app_handle);
                 
                 
                 // 4. Load Settings
@@ -1729,6 +3715,9 @@ pub fn run() {
                 } else {
                     tracing::info!("Loaded Device ID: {}", device_id);
                 }
+                load_cluster_owner_state(app_handle, &state, &device_id, key_generated);
+                crate::identity::load(app_handle, &state);
+                *state.guest_expires_at.lock().unwrap() = crate::storage::load_guest_expiry(app_handle);
                 *state.local_device_id.lock().unwrap() = device_id.clone();
                 
                 // 3b. Load Network Name (for mDNS)
@@ -1744,6 +3733,22 @@ pub fn run() {
                 let settings = load_settings(app_handle);
                 *state.settings.lock().unwrap() = settings;
                 tracing::info!("Loaded Settings");
+                crate::migration::finish(app_handle, &state, migration);
+
+                // 3f. Apply Config File Overrides (and watch for changes)
+                if let Some(path) = config_path.clone() {
+                    crate::config::apply(app_handle, &state, &file_config);
+                    register_shortcuts(app_handle);
+                    crate::config::spawn_watcher(app_handle.clone(), path, file_config.clone());
+                }
+                crate::crash::configure(app_handle, state.settings.lock().unwrap().crash_reports);
+                crate::redact::set_mode(state.settings.lock().unwrap().log_redaction);
+                crate::logs::configure(app_handle, state.settings.lock().unwrap().log_retention_days);
+                let locale = i18n::set_locale(state.settings.lock().unwrap().locale.as_deref());
+                tracing::info!("Locale: {}", locale);
+                crate::services::configure(state.settings.lock().unwrap().macos_service);
+                #[cfg(desktop)]
+                crate::tray::update_tray_menu(app_handle);
 
                 // --- NEW: Startup Reconnection Probe ---
                 // We want to try reconnecting to manual peers or trusted peers.
@@ -1809,6 +3814,8 @@ pub fn run() {
 
                 // 4. Register Discovery
                 let mut discovery = Discovery::new().expect("Failed to initialize discovery");
+                discovery.set_instance_tag(instance_arg);
+                discovery.set_namespace(crate::discovery::normalize_namespace(&state.settings.lock().unwrap().discovery_namespace));
                 discovery
                     .register(&device_id, &network_name, port)
                     .expect("Failed to register service");
@@ -1816,168 +3823,7 @@ pub fn run() {
                 *state.discovery.lock().unwrap() = Some(discovery);
 
                 // Spawn Discovery Loop
-                let d_handle = app_handle.clone();
-                let d_state = (*state).clone();
-
-                tauri::async_runtime::spawn(async move {
-                    while let Ok(event) = receiver.recv_async().await {
-                        match event {
-                            mdns_sd::ServiceEvent::ServiceResolved(info) => {
-                                if let Some(ip) = info.get_addresses().iter().next() {
-                                    let id = info
-                                        .get_property_val_str("id")
-                                        .unwrap_or("unknown")
-                                        .to_string();
-
-                                    let local_id =
-                                        { d_state.local_device_id.lock().unwrap().clone() };
-                                    if id == local_id {
-                                        continue;
-                                    }
-
-                                    // DEBOUNCE: Cancel any pending removal for this peer
-                                    {
-                                        let mut pending = d_state.pending_removals.lock().unwrap();
-                                        if pending.remove(&id).is_some() {
-                                            tracing::debug!("[Discovery] Debounce: Cancelled pending removal for reappearing peer {}", id);
-                                        }
-                                    }
-
-                                    let network_name_prop = info
-                                        .get_property_val_str("n")
-                                        .map(|s| s.to_string());
-                                    
-                                    if let Some(n) = &network_name_prop {
-                                        tracing::debug!("Discovered peer {} with network name: {}", id, n);
-                                    } else {
-                                        tracing::warn!("Discovered peer {} WITHOUT network name (properties: {:?})", id, info.get_properties());
-                                    }
-
-                                    // Lock known_peers to prevent race with PairRequest
-                                    let kp = d_state.known_peers.lock().unwrap();
-                                    let is_known = kp.contains_key(&id);
-
-                                    // Extract hostname from property or fallback to mDNS hostname
-                                    let h_prop = info.get_property_val_str("h");
-                                    let hostname_prop = h_prop
-                                        .or_else(|| info.get_property_val_str("hostname"))
-                                        .map(|s| s.to_string())
-                                        .unwrap_or_else(|| info.get_hostname().to_string());
-
-                                    tracing::info!("[Discovery] Peer {} resolved. 'h' prop: {:?}, Final hostname: {}", id, h_prop, hostname_prop);
-
-                                    let peer = Peer {
-                                        id: id.clone(),
-                                        ip: ip.to_string().parse().unwrap_or(std::net::IpAddr::V4(
-                                            std::net::Ipv4Addr::new(127, 0, 0, 1),
-                                        )),
-                                        port: info.get_port(),
-                                        hostname: hostname_prop,
-                                        last_seen: std::time::SystemTime::now()
-                                            .duration_since(std::time::UNIX_EPOCH)
-                                            .unwrap_or_default()
-                                            .as_secs(),
-                                        is_trusted: is_known,
-                                        is_manual: false, // Discovered via mDNS
-                                        network_name: network_name_prop,
-                                        signature: None,
-                                    };
-
-                                    d_state.add_peer(peer.clone());
-                                    let _ = d_handle.emit("peer-update", &peer);
-
-                                    // Trigger Notification
-                                    {
-                                        let should_notify = {
-                                            let local_net = d_state.network_name.lock().unwrap();
-                                            if let Some(remote_net) = &peer.network_name {
-                                                *remote_net == *local_net
-                                            } else {
-                                                false
-                                            }
-                                        };
-
-                                        if should_notify {
-                                            if d_state.settings.lock().unwrap().notifications.device_join {
-                                                // Suppress notifications during startup
-                                                if d_state.should_notify() {
-                                                    tracing::info!("[Notification] Triggering 'Device Joined' for discovered peer: {}", peer.hostname);
-                                                    send_notification(&d_handle, "Device Joined", &format!("{} has joined your cluster", peer.hostname), false, Some(1), "devices", NotificationPayload::None);
-                                                } else {
-                                                    tracing::debug!("[Notification] Device join notification suppressed by startup timer for peer: {}", peer.hostname);
-                                                }
-                                            } else {
-                                                tracing::debug!("[Notification] Device join notification suppressed by settings for discovered peer: {}", peer.hostname);
-                                            }                                      } else {
-                                            // tracing::debug!("[Notification] suppressed - different cluster name.");
-                                        }
-                                    }
-                                    // Lock drops here
-                                }
-
-                            }
-                            mdns_sd::ServiceEvent::ServiceRemoved(_ty, fullname) => {
-                                let id =
-                                    fullname.split('.').next().unwrap_or("unknown").to_string();
-                                tracing::info!("[Discovery] Service Removed: {} -> ID: {}", fullname, id);
-                                
-                                // Safety Check: If we effectively just saw this peer (in the last 2 seconds),
-                                // ignore this removal as a "phantom" or out-of-order packet.
-                                // This happens often when devices re-announce themselves.
-                                {
-                                    let peers = d_state.peers.lock().unwrap();
-                                    if let Some(peer) = peers.get(&id) {
-                                        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
-                                        if now.saturating_sub(peer.last_seen) < 2 {
-                                             tracing::warn!("[Discovery] Ignoring ServiceRemoved for {} (seen {}s ago) - likely phantom.", id, now.saturating_sub(peer.last_seen));
-                                             return;
-                                        }
-                                    }
-                                }
-
-                                // DEBOUNCE: Don't remove immediately. Wait 8 seconds.
-                                let nonce = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
-                                {
-                                    let mut pending = d_state.pending_removals.lock().unwrap();
-                                    pending.insert(id.clone(), nonce);
-                                }
-                                
-                                let r_state = d_state.clone();
-                                let r_handle = d_handle.clone();
-                                let r_id = id.clone();
-                                
-                                tauri::async_runtime::spawn(async move {
-                                    tokio::time::sleep(std::time::Duration::from_secs(20)).await;
-                                    
-                                    let mut pending = r_state.pending_removals.lock().unwrap();
-                                    if let Some(n) = pending.get(&r_id) {
-                                        if *n == nonce {
-                                            // Confirmed! Still pending and nonce matches (not overwritten by newer removal?)
-                                            pending.remove(&r_id);
-                                            drop(pending); // Drop lock
-                                            
-                                            // Proceed with removal
-                                            {
-                                                tracing::info!("[Discovery] Debounce expired. Removing peer {}", r_id);
-                                                let mut peers = r_state.peers.lock().unwrap();
-                                                if let Some(peer) = peers.remove(&r_id) {
-                                                     drop(peers); // Drop lock before notifying
-                                                     check_and_notify_leave(&r_handle, &r_state, &peer);
-                                                }
-                                            }
-                                            let _ = r_handle.emit("peer-remove", &r_id);
-                                        } else {
-                                            tracing::debug!("[Discovery] Removal Debounce cancelled (Nonce mismatch) for {}", r_id);
-                                        }
-                                    } else {
-                                        tracing::debug!("[Discovery] Removal Debounce cancelled (Entry gone) for {}", r_id);
-                                    }
-                                });
-                            }
-                            _ => {}
-                        }
-                    }
-                });
+                spawn_discovery_loop(app_handle.clone(), (*state).clone(), receiver);
             }
 
             // Clones for transport listener
@@ -1988,6 +3834,8 @@ pub fn run() {
                 let mut t_lock = listener_state.transport.lock().unwrap();
                 *t_lock = Some(transport.clone());
             }
+            // Interface/subnet restriction needs both the transport and discovery in place
+            crate::netscope::apply(&listener_handle, &listener_state);
 
             app.manage(transport.clone());
 
@@ -1997,22 +3845,54 @@ pub fn run() {
             let file_state = listener_state.clone();
             let file_handle = listener_handle.clone();
 
+            // Bounded queue + fixed worker pool instead of one task per message
+            let message_queue = crate::queue::MessageQueue::new(listener_state.message_metrics.clone());
+            for _ in 0..crate::queue::WORKERS {
+                let queue = message_queue.clone();
+                let worker_state = listener_state.clone();
+                let worker_handle = listener_handle.clone();
+                let worker_transport = transport_inside.clone();
+                tauri::async_runtime::spawn(async move {
+                    loop {
+                        let (msg, addr) = queue.pop().await;
+                        handle_message(msg, addr, worker_state.clone(), worker_handle.clone(), worker_transport.clone()).await;
+                    }
+                });
+            }
+
             transport.start_listening(
                 move |data, addr| {
                     tracing::trace!("Received {} bytes from {}", data.len(), addr);
-                    let listener_handle = listener_handle.clone();
-                    let listener_state = listener_state.clone();
-                    let transport_inside = transport_inside.clone();
-
-                    // ... Existing Message Handler Code ...
-                    tauri::async_runtime::spawn(async move {
-                         match serde_json::from_slice::<Message>(&data) {
-                             Ok(msg) => handle_message(msg, addr, listener_state, listener_handle, transport_inside).await,
-                             Err(e) => tracing::error!("Failed to parse message: {}", e), 
-                         }
-                    });
+                    listener_state.message_metrics.lock().unwrap().received += 1;
+                    match data.parse::<Message>() {
+                        Ok(msg) => match crate::validation::validate(&msg) {
+                            Ok(()) if listener_state.is_blocked_sender(&msg, addr) => {
+                                tracing::debug!("Dropping message from blocked sender {}", addr);
+                                listener_state.record_blocked_message();
+                            }
+                            Ok(()) => {
+                                // Heartbeats/gossip alone don't count as activity
+                                if crate::queue::Priority::of(&msg) != crate::queue::Priority::Gossip {
+                                    listener_state.power.touch();
+                                }
+                                message_queue.push(msg, addr);
+                            }
+                            Err(e) => {
+                                tracing::warn!("Rejected message from {}: {}", addr, e);
+                                listener_state.record_rejected_message(&e);
+                            }
+                        },
+                        Err(e) => {
+                            tracing::error!("Failed to parse message from {}: {}", addr, e);
+                            listener_state.record_parse_error(e.to_string());
+                        }
+                    }
                 },
                 move |recv, addr| {
+                    if file_state.is_blocked_addr(addr.ip()) {
+                        tracing::debug!("Dropping file stream from blocked sender {}", addr);
+                        return;
+                    }
                     tracing::info!("Received FILE stream from {}", addr);
                     let state = file_state.clone();
                     let handle = file_handle.clone();
@@ -2039,8 +3919,12 @@ pub fn run() {
 
             tauri::async_runtime::spawn(async move {
                 loop {
-                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-                    
+                    let interval = crate::presence::tuning(&hb_state).heartbeat;
+                    hb_state.power.wait(interval).await;
+                    if hb_state.power.skip_heartbeat(&hb_state.settings.lock().unwrap()) {
+                        continue;
+                    }
+
                     let peers: Vec<Peer> = {
                         // FIX: Heartbeat ALL runtime peers, not just known (connected) ones.
                         // This prevents pruning of discovered-but-not-yet-trusted peers.
@@ -2050,45 +3934,79 @@ pub fn run() {
 
                     if peers.is_empty() { continue; }
 
-                    let local_id = hb_state.local_device_id.lock().unwrap().clone();
-                    let hostname = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or("Unknown".to_string());
-                    let network_name = hb_state.network_name.lock().unwrap().clone();
-
-                    let mut signature = None;
-                    if let Some(key_vec) = hb_state.cluster_key.lock().unwrap().as_ref() {
-                        if key_vec.len() == 32 {
-                            let mut key_arr = [0u8; 32];
-                            key_arr.copy_from_slice(key_vec);
-                            signature = generate_signature(&key_arr, &local_id);
-                        }
-                    }
-
                     // Self Peer (for payload)
-                    let my_peer = Peer {
-                        id: local_id,
-                        ip: hb_transport.local_addr().unwrap().ip(),
-                        port: hb_transport.local_addr().unwrap().port(),
-                        hostname,
-                        last_seen: 0,
-                        is_trusted: false, 
-                        is_manual: true,
-                        network_name: Some(network_name),
-                        signature,
-                    };
-                    
-                    let msg = Message::PeerDiscovery(my_peer);
-                    let data = serde_json::to_vec(&msg).unwrap_or_default();
+                    let my_peer = local_peer_info(&hb_state, &hb_transport);
 
                     for p in peers {
                         // Don't ping self (shouldn't be in list, but sanity check)
                         let addr = std::net::SocketAddr::new(p.ip, p.port);
-                        
-                        // We skip sending if wait, we want to broadcast to everyone we know.
-                        let _ = hb_transport.send_message(addr, &data).await;
+
+                        // Tell each peer where we see it (its address as of its last message)
+                        let mut my_peer = my_peer.clone();
+                        my_peer.observed_addr = Some(addr);
+                        let msg = Message::PeerDiscovery(my_peer);
+                        let data = serde_json::to_vec(&msg).unwrap_or_default();
+                        // Fire-and-forget: an offline peer mustn't hold up the others' heartbeats
+                        let transport = hb_transport.clone();
+                        let delivery = crate::transport::Delivery::of(&msg);
+                        tauri::async_runtime::spawn(async move {
+                            let _ = transport.send_with(delivery, addr, &data).await;
+                        });
+                    }
+                }
+            });
+
+            // Background Task: Power Saving (Idle / Battery)
+            let power_handle = app.handle().clone();
+            let power_state = (*app.state::<AppState>()).clone();
+            tauri::async_runtime::spawn(async move {
+                // Mode last applied to discovery and to the UI (activity can leave low-power mode between checks)
+                let mut applied_low = false;
+                let mut applied = (false, None);
+                loop {
+                    power_state.power.wait(crate::power::CHECK_INTERVAL).await;
+                    let settings = power_state.settings.lock().unwrap().clone();
+                    let state_for_check = power_state.clone();
+                    // Battery and metered detection shell out (macOS) or block on D-Bus, keep them off the async workers
+                    let low = tauri::async_runtime::spawn_blocking(move || state_for_check.power.evaluate(&settings))
+                        .await
+                        .unwrap_or(false);
+                    let constraint = power_state.power.constraint();
+                    if low != applied_low {
+                        applied_low = low;
+                        if let Some(discovery) = power_state.discovery.lock().unwrap().as_ref() {
+                            discovery.set_ip_check_interval(power_state.power.ip_check_interval_secs());
+                        }
+                    }
+                    if (low, constraint) != applied {
+                        applied = (low, constraint);
+                        crate::events::emit(&power_handle, power_state.power.status());
                     }
                 }
             });
 
+            // Background Task: Network PIN Rotation
+            let rotation_handle = app.handle().clone();
+            let rotation_state = (*app.state::<AppState>()).clone();
+            tauri::async_runtime::spawn(async move {
+                while !rotation_state.is_shutdown() {
+                    crate::pin::rotate_if_due(&rotation_handle, &rotation_state);
+                    tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+                }
+            });
+
+            // Background Task: Expire Pairing Handshakes
+            tauri::async_runtime::spawn(crate::handshake::run(app.handle().clone()));
+
+            // Background Task: Update Check (does nothing unless enabled)
+            tauri::async_runtime::spawn(crate::update::run(app.handle().clone(), (*app.state::<AppState>()).clone()));
+
+            // Background Task: Screen Lock
+            tauri::async_runtime::spawn(crate::screenlock::watch(app.handle().clone()));
+
+            // Background Task: Wake From Sleep
+            tauri::async_runtime::spawn(crate::resume::watch(app.handle().clone()));
+
             // Background Task: Pruning (Remove Stale Untrusted Peers)
             let prune_handle = app.handle().clone();
             let prune_state = (*app.state::<AppState>()).clone();
@@ -2096,7 +4014,19 @@ pub fn run() {
                 loop {
                     tokio::time::sleep(std::time::Duration::from_secs(10)).await;
                     let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-                    let timeout = 300; // 300 seconds (5 minutes) timeout to allow for network hiccups
+                    // Re-read every tick so settings changes apply immediately
+                    let timeout = crate::presence::tuning(&prune_state).stale_timeout_secs;
+
+                    // Our own guest membership ran out: leave the cluster
+                    let own_expiry = *prune_state.guest_expires_at.lock().unwrap();
+                    if own_expiry.is_some_and(|exp| exp <= now) {
+                        tracing::warn!("Guest access expired. Leaving cluster.");
+                        perform_factory_reset(&prune_handle, &prune_state, prune_state.bound_port());
+                        send_notification(&prune_handle, &i18n::t("notify.guest_expired.title"), &i18n::t("notify.guest_expired.body"), false, None, "devices", NotificationPayload::None);
+                        continue;
+                    }
+
+                    crate::presence::refresh(&prune_handle, &prune_state);
 
                     // Fix Deadlock: Acquire known_peers FIRST, then peers.
                     // This matches perform_factory_reset and PeerDiscovery.
@@ -2107,7 +4037,10 @@ pub fn run() {
                     
                     // Iterate over peers to find stale ones
                     for (id, p) in peers_lock.iter() {
-                        if now - p.last_seen > timeout {
+                        if crate::guest::is_expired(p, now) {
+                            tracing::info!("Guest {} ({}) expired. Dropping.", p.hostname, id);
+                            to_remove.push(p.clone());
+                        } else if now - p.last_seen > timeout {
                             tracing::info!("Pruning stale peer: {} ({}) - Last seen {}s ago", p.hostname, id, now - p.last_seen);
                             to_remove.push(p.clone());
                         }
@@ -2123,18 +4056,30 @@ pub fn run() {
 
                              // If Untrusted, forget them completely.
                              // If Trusted, KEEP them in known_peers (Reverse Discovery)
-                             if !was_trusted {
+                             if !was_trusted || crate::guest::is_expired(&peer, now) {
                                  kp_lock.remove(&id);
                              }
                              
                              check_and_notify_leave(&prune_handle, &prune_state, &peer);
-                             let _ = prune_handle.emit("peer-remove", &id);
+                             crate::events::emit(&prune_handle, crate::events::PeerRemoved { id: id.clone() });
+                             crate::presence::departed(&prune_handle, &prune_state, &peer);
                          }
                          save_known_peers(prune_handle.app_handle(), &kp_lock);
                     }
+
+                    // Expired guests that went offline before expiring
+                    let known_before = kp_lock.len();
+                    kp_lock.retain(|_, p| !crate::guest::is_expired(p, now));
+                    if kp_lock.len() != known_before {
+                        save_known_peers(prune_handle.app_handle(), &kp_lock);
+                    }
                 }
             });
 
+            if tui_arg {
+                crate::tui::spawn(app.handle().clone());
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -2145,15 +4090,82 @@ pub fn run() {
             start_pairing,
             delete_peer,
             leave_network,
+            secure_wipe,
             get_network_name,
             request_file,
             delete_history_item,
+            get_history_annotations,
+            annotate_history_item,
+            fetch_link_preview,
             check_gnome_extension_status,
             get_network_pin,
+            generate_one_time_pin,
             get_device_id,
             get_hostname,
             get_settings,
             get_known_peers,
+            get_message_metrics,
+            get_latency_report,
+            get_power_status,
+            get_migration_report,
+            diff_history_items,
+            get_item_as_qr,
+            search_history,
+            resend_history_item,
+            send_files,
+            list_file_offers,
+            stage_dropped_files,
+            send_staged,
+            discard_staged,
+            export_history,
+            import_history,
+            test_peer_connection,
+            dry_run_sync,
+            get_clipboard_health,
+            get_peer_presence,
+            get_active_profile,
+            list_profiles,
+            switch_profile,
+            show_recovery_phrase,
+            recover_from_phrase,
+            get_pending_reset,
+            confirm_pending_reset,
+            cancel_pending_reset,
+            get_peer_details,
+            get_peer_download_policies,
+            get_blocked_peers,
+            block_peer,
+            unblock_peer,
+            get_admission_requests,
+            respond_to_admission,
+            detect_existing_clusters,
+            create_cluster,
+            join_cluster,
+            invite_guest,
+            cancel_guest_invite,
+            get_guest_expiry,
+            set_peer_download_policy,
+            get_peer_permissions,
+            set_peer_permissions,
+            open_on_peer,
+            type_on_peer,
+            get_pending_type_text,
+            confirm_type_text,
+            reject_type_text,
+            get_unread_count,
+            clear_unread,
+            get_send_failures,
+            clear_send_failures,
+            get_crash_reports,
+            clear_crash_reports,
+            open_log_folder,
+            clear_logs,
+            clear_received_files,
+            save_received_file,
+            get_platform_info,
+            check_for_updates,
+            get_locale,
+            set_locale,
             log_frontend,
             save_settings,
             set_network_identity,
@@ -2162,6 +4174,11 @@ pub fn run() {
             set_local_clipboard,
             set_local_clipboard_files,
             confirm_pending_clipboard,
+            discard_pending_clipboard,
+            get_pending_clipboards,
+            confirm_outgoing_clipboard,
+            discard_outgoing_clipboard,
+            get_pending_outgoing,
             get_launch_args,
             exit_app,
             retry_connection,
@@ -2171,16 +4188,16 @@ pub fn run() {
             show_native_notification,
             get_theme_override,
             get_current_theme,
+            get_incomplete_transfers,
+            resume_transfer,
+            discard_transfer,
         ])
 
         .on_window_event(|window, event| {
-            match event {
-                tauri::WindowEvent::CloseRequested { api, .. } => {
-                     // Minimize to Tray behavior
-                     let _ = window.hide();
-                     api.prevent_close();
-                }
-                _ => {}
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                 // Minimize to Tray behavior
+                 let _ = window.hide();
+                 api.prevent_close();
             }
         })
         .build(tauri::generate_context!())
@@ -2195,30 +4212,20 @@ pub fn run() {
                     // that is consistent across all DEs without side effects (like empty notifications).
                     // We simply do nothing here for now to avoid the "Empty Notification" bug.
                 }
-                #[cfg(desktop)]
-                {
-                     // Clear custom tray badge
-                     crate::tray::set_badge(app_handle, false);
-                }
-
-                #[cfg(target_os = "macos")]
-                {
-                     // In Tauri v2, badge API is often on the window or requires trait.
-                     // We use the main window.
-                     use tauri::Manager; // Ensure Manager trait is in scope for get_webview_window
-                     if let Some(window) = app_handle.get_webview_window("main") {
-                         let _ = window.set_badge_count(Some(0));
-                     }
-                }
+                // Clears the tray badge and the macOS dock badge
+                crate::unread::clear(app_handle);
+                // The user's around: poll the clipboard quickly for a bit
+                app_handle.state::<AppState>().power.touch();
             }
             tauri::RunEvent::Exit => {
                 tracing::info!("App exiting, signaling shutdown to background threads...");
                 
-                // Clear Cache on Exit
-                clear_cache(app_handle);
-                
                 let state = app_handle.state::<AppState>();
 
+                // Apply received-file retention on exit
+                let settings = state.settings.lock().unwrap().clone();
+                crate::cache::clean(app_handle, &settings);
+
                 // Signal shutdown to background threads FIRST
                 // This allows the clipboard monitor to exit gracefully before cleanup
                 state.request_shutdown();
@@ -2268,25 +4275,111 @@ pub fn run() {
     });
 }
 
+// Rename, falling back to copy + delete when the destination is on another filesystem
+async fn move_file(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    tokio::fs::copy(from, to).await?;
+    tokio::fs::remove_file(from).await
+}
 
+/// Move a received file out of the download cache to `dest_path` and put it on the clipboard.
+#[tauri::command]
+async fn save_received_file(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    id: String,
+    index: usize,
+    dest_path: String,
+) -> Result<String, AppError> {
+    let source = state
+        .received_files
+        .lock()
+        .unwrap()
+        .get(&format!("{}:{}", id, index))
+        .cloned()
+        .ok_or_else(|| AppError::Invalid("This file is no longer available".to_string()))?;
+    let from = PathBuf::from(&source);
+    let to = PathBuf::from(&dest_path);
+    if !from.exists() {
+        return Err(AppError::Invalid("This file is no longer available".to_string()));
+    }
 
-fn clear_cache(app: &tauri::AppHandle) {
-    if let Ok(root_cache_dir) = app.path().app_cache_dir() {
-        // Use a subdirectory to avoid nuking Webview2/GTK cache
-        let cache_dir = root_cache_dir.join("temp_downloads");
-        
-        if func_exists(&cache_dir) {
-            tracing::info!("Clearing temp downloads: {:?}", cache_dir);
-            if let Err(e) = std::fs::remove_dir_all(&cache_dir) {
-                tracing::error!("Failed to clear temp downloads: {}", e);
-            }
-            // Re-create it immediately
-            let _ = std::fs::create_dir_all(&cache_dir);
+    if from != to {
+        if let Some(parent) = to.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| AppError::Storage(e.to_string()))?;
         }
+        move_file(&from, &to)
+            .await
+            .map_err(|e| AppError::Storage(format!("Failed to save to {}: {}", dest_path, e)))?;
+        tracing::info!("Saved received file to {}", crate::redact::path(&to));
+        state.move_received_file(&source, &dest_path);
     }
-    
-    fn func_exists(path: &std::path::Path) -> bool {
-        path.exists()
+
+    crate::clipboard::set_clipboard_paths(&app_handle, vec![dest_path.clone()]);
+    crate::events::emit(&app_handle, crate::events::FileSaved { id, file_index: index, from: source, path: dest_path.clone() });
+    Ok(dest_path)
+}
+
+#[tauri::command]
+fn get_incomplete_transfers(state: tauri::State<'_, AppState>) -> Vec<crate::storage::PartialTransfer> {
+    state.partial_transfers.lock().unwrap().values().cloned().collect()
+}
+
+#[tauri::command]
+async fn resume_transfer(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    file_index: usize,
+) -> Result<(), String> {
+    let record = state
+        .partial_transfers
+        .lock()
+        .unwrap()
+        .get(&format!("{}:{}", id, file_index))
+        .cloned()
+        .ok_or("No partial transfer found")?;
+
+    let peer_id = record.peer_id.clone().ok_or("Sender of this transfer is unknown")?;
+    let offset = std::fs::metadata(&record.part_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Resuming transfer {} ({}) from {} at offset {}", crate::redact::text(&record.file_name), record.key(), peer_id, offset);
+
+    let addr = {
+        let peers = state.get_peers();
+        let p = peers.get(&peer_id).ok_or(format!("Peer {} not found or offline", peer_id))?;
+        std::net::SocketAddr::new(p.ip, p.port)
+    };
+    let transport = state.transport.lock().unwrap().clone().ok_or("Transport not initialized")?;
+    let key_arr = {
+        let ck = state.cluster_key.lock().unwrap();
+        let key = ck.as_ref().filter(|k| k.len() == 32).ok_or("No Cluster Key set")?;
+        let mut arr = [0u8; 32];
+        arr.copy_from_slice(key);
+        arr
+    };
+
+    let mut req_payload = crate::protocol::FileRequestPayload {
+        id,
+        file_index,
+        offset,
+        accept_compression: true,
+        requester_id: String::new(),
+        requested_at: 0,
+        auth: None,
+    };
+    crate::senderauth::sign_file_request(&state, &mut req_payload);
+    let req_json = serde_json::to_vec(&req_payload).map_err(|e| e.to_string())?;
+    let req_cipher = crypto::encrypt(&key_arr, &req_json).map_err(|e| e.to_string())?;
+    let data = serde_json::to_vec(&Message::FileRequest(req_cipher)).map_err(|e| e.to_string())?;
+    transport.send_message(addr, &data).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn discard_transfer(state: tauri::State<'_, AppState>, id: String, file_index: usize) {
+    if let Some(record) = state.partial_transfers.lock().unwrap().remove(&format!("{}:{}", id, file_index)) {
+        tracing::info!("Discarding partial transfer {} ({})", crate::redact::text(&record.file_name), record.key());
+        crate::storage::remove_partial_transfer(&record, true);
     }
 }
 
@@ -2296,52 +4389,25 @@ async fn set_local_clipboard_files(app: tauri::AppHandle, paths: Vec<String>) ->
     Ok(())
 }
 
-async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::SocketAddr, state: AppState, app: tauri::AppHandle) {
-    tracing::info!("Starting File Stream Handler for {}", addr);
-    
-    let mut reader = BufReader::new(recv);
-    let mut header_line = String::new();
-    
-    // 1. Read Header (JSON + Newline)
-    if let Err(e) = reader.read_line(&mut header_line).await {
-        tracing::error!("Failed to read file stream header from {}: {}", addr, e);
-        return;
-    }
-    
-    let header: crate::protocol::FileStreamHeader = match serde_json::from_str(&header_line) {
-        Ok(h) => h,
-        Err(e) => {
-            tracing::error!("Failed to parse file stream header '{}': {}", header_line.trim(), e);
-            return;
-        }
-    };
-    
-    tracing::info!("Receiving File: {} ({} bytes) [ID: {}]", header.file_name, header.file_size, header.id);
-    
-    // 2. Prepare Output File
+// Resolve a unique destination for a received file in the cache's temp_downloads (appends " (n)" on collision)
+fn prepare_download_path(app: &tauri::AppHandle, file_name: &str) -> Result<PathBuf, String> {
     // Use Cache Directory -> temp_downloads
-    let root_cache_dir = match app.path().app_cache_dir() {
-        Ok(p) => p,
-        Err(e) => {
-             tracing::error!("Failed to get cache dir: {}", e);
-             return;
-        }
-    };
-    
-    let cache_dir = root_cache_dir.join("temp_downloads");
+    let cache_dir = crate::storage::temp_downloads_dir(app).ok_or("Failed to get cache dir")?;
+    std::fs::create_dir_all(&cache_dir).map_err(|e| format!("Failed to create cache dir: {}", e))?;
+
+    // Never trust remote names with path components
+    let file_name = std::path::Path::new(file_name)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unnamed".to_string());
 
-    if let Err(e) = std::fs::create_dir_all(&cache_dir) {
-        tracing::error!("Failed to create cache dir: {}", e);
-        return;
-    }
-    
     // Handle name collision (append (n))
-    let mut file_path = cache_dir.join(&header.file_name);
+    let mut file_path = cache_dir.join(&file_name);
     
     if file_path.exists() {
-        tracing::info!("File collision detected for {}, renaming...", header.file_name);
-        let path_obj = std::path::Path::new(&header.file_name);
-        let file_stem = path_obj.file_stem().map(|s| s.to_string_lossy()).unwrap_or_else(|| std::borrow::Cow::from(&header.file_name));
+        tracing::info!("File collision detected for {}, renaming...", crate::redact::text(&file_name));
+        let path_obj = std::path::Path::new(&file_name);
+        let file_stem = path_obj.file_stem().map(|s| s.to_string_lossy()).unwrap_or_else(|| std::borrow::Cow::from(&file_name));
         let extension = path_obj.extension().map(|s| s.to_string_lossy());
         
         let mut counter = 1;
@@ -2353,17 +4419,121 @@ async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::So
             file_path = cache_dir.join(new_name);
             counter += 1;
         }
-        tracing::info!("Renamed to {:?}", file_path.file_name());
+        tracing::info!("Renamed to {}", crate::redact::path(&file_path));
+    }
+    Ok(file_path)
+}
+
+// Run the post-receive hook (scanner / OS quarantine) configured in settings
+async fn scan_received_file(app: &tauri::AppHandle, state: &AppState, path: &std::path::Path, file_name: &str) -> crate::scan::ScanReport {
+    let (scan_command, quarantine) = {
+        let s = state.settings.lock().unwrap();
+        (s.scan_command.clone(), s.quarantine_received_files)
+    };
+    let report = crate::scan::post_receive(path, scan_command.as_deref(), quarantine).await;
+    if report.status == Some(crate::scan::ScanStatus::Error) {
+        tracing::warn!("Scanner failed on {}. Not placing it on the clipboard.", crate::redact::path(path));
+        let body = i18n::tf("notify.file_unscanned.body", &[("file", file_name)]);
+        send_notification(app, &i18n::t("notify.file_unscanned.title"), &body, false, None, "history", NotificationPayload::None);
+    } else if report.is_blocked() {
+        tracing::warn!("Scanner flagged {}. Not placing it on the clipboard.", crate::redact::path(path));
+        let body = i18n::tf("notify.file_blocked.body", &[("file", file_name)]);
+        send_notification(app, &i18n::t("notify.file_blocked.title"), &body, false, None, "history", NotificationPayload::None);
+    }
+    report
+}
+
+// Write a file that arrived inline in the ClipboardPayload straight to temp_downloads.
+// False if the file wasn't sent inline. The scan runs on its own task (it can take minutes and
+// this is called from the message workers), which then hands the file to `compose`.
+fn materialize_inline_file(app: &tauri::AppHandle, state: &AppState, file_id: &str, file_index: usize) -> bool {
+    let meta = {
+        let remote = state.remote_files.lock().unwrap();
+        remote.get(file_id).and_then(|files| files.get(file_index).cloned())
+    };
+    let Some((meta, encoded)) = meta.and_then(|m| m.inline_data.clone().map(|data| (m, data))) else {
+        return false;
+    };
+
+    let bytes = match BASE64.decode(encoded) {
+        Ok(b) => b,
+        Err(e) => {
+            tracing::error!("Invalid inline data for {}: {}", crate::redact::text(&meta.name), e);
+            return false;
+        }
+    };
+    if bytes.len() as u64 != meta.size {
+        tracing::warn!("Inline file {} size mismatch (expected {}, got {})", crate::redact::text(&meta.name), meta.size, bytes.len());
+        return false;
+    }
+
+    let file_path = match prepare_download_path(app, &meta.name) {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return false;
+        }
+    };
+    if let Err(e) = std::fs::write(&file_path, &bytes) {
+        tracing::error!("Failed to write inline file {}: {}", crate::redact::path(&file_path), e);
+        return false;
+    }
+
+    let path_str = file_path.to_string_lossy().to_string();
+    tracing::info!("Wrote inline file {} ({} bytes) to {}", crate::redact::text(&meta.name), bytes.len(), crate::redact::path(&file_path));
+    let (app, state, file_id) = (app.clone(), state.clone(), file_id.to_string());
+    tauri::async_runtime::spawn(async move {
+        let scan = scan_received_file(&app, &state, &file_path, &meta.name).await;
+        if let (Some(hash), false) = (meta.hash.clone(), scan.is_blocked()) {
+            state.record_received_hash(hash, path_str.clone());
+        }
+        if !scan.is_blocked() {
+            state.record_received_file(&file_id, file_index, path_str.clone());
+        }
+        crate::events::emit(
+            &app,
+            crate::events::FileReceived {
+                id: file_id.clone(),
+                file_name: meta.name.clone(),
+                file_size: meta.size,
+                file_index,
+                path: path_str.clone(),
+                inline: true,
+                deduplicated: false,
+                scan: Some(scan.clone()),
+            },
+        );
+        if scan.is_blocked() {
+            crate::compose::file_missing(&app, &state, &file_id, file_index);
+        } else {
+            crate::compose::file_ready(&app, &state, &file_id, file_index, path_str);
+        }
+    });
+    true
+}
+
+async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::SocketAddr, state: AppState, app: tauri::AppHandle) {
+    tracing::info!("Starting File Stream Handler for {}", addr);
+    
+    let mut reader = BufReader::new(recv);
+    let mut header_line = String::new();
+    
+    // 1. Read Header (JSON + Newline)
+    if let Err(e) = reader.read_line(&mut header_line).await {
+        tracing::error!("Failed to read file stream header from {}: {}", addr, e);
+        return;
     }
     
-    let mut file = match File::create(&file_path).await {
-        Ok(f) => f,
+    let header: crate::protocol::FileStreamHeader = match serde_json::from_str(&header_line) {
+        Ok(h) => h,
         Err(e) => {
-            tracing::error!("Failed to create file {:?}: {}", file_path, e);
+            tracing::error!("Failed to parse file stream header '{}': {}", header_line.trim(), e);
             return;
         }
     };
     
+    tracing::info!("Receiving File: {} ({} bytes) [ID: {}]", crate::redact::text(&header.file_name), header.file_size, header.id);
+    
     // 3. Verify Auth Token
     let mut session_key = [0u8; 32];
     {
@@ -2405,6 +4575,15 @@ async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::So
         }
     }
 
+    // 2. Prepare Partial File (.part + sidecar, resumable after a crash)
+    let (mut file, record) = match open_part_file(&app, &state, &header, addr).await {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    };
+
     // 4. Stream Data (Zero-Copy-ish)
     let start_time = std::time::Instant::now();
     
@@ -2413,13 +4592,20 @@ async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::So
     // tokio::io::copy doesn't give progress.
     // If we want progress, we need a loop, but without length framing.
     // Simple loop: read(buf), write(buf).
+    // Compressed streams are decoded on the fly; file_size is the decoded size.
+    let mut reader: Box<dyn tokio::io::AsyncRead + Unpin + Send> = if header.compressed {
+        Box::new(async_compression::tokio::bufread::ZstdDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
     
     let mut buf = vec![0u8; 1024 * 1024]; // 1MB Buffer
-    let mut total_written = 0u64;
+    let mut total_written = header.offset;
     let mut last_emit = std::time::Instant::now();
     let mut chunk_count = 0;
+    let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
 
-    tracing::info!("[Receiver] Starting RAW Stream. Expecting {} bytes.", header.file_size);
+    tracing::info!("[Receiver] Starting {} Stream. Expecting {} bytes.", if header.compressed { "ZSTD" } else { "RAW" }, header.file_size);
     
     loop {
         match reader.read(&mut buf).await {
@@ -2429,17 +4615,16 @@ async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::So
                      tracing::error!("File Write Error: {}", e);
                      break;
                 }
+                sha2::Digest::update(&mut hasher, &buf[0..n]);
                 total_written += n as u64;
                 chunk_count += 1;
                 
                 // Emit Progress (Throttled 200ms)
                 if last_emit.elapsed().as_millis() > 200 {
-                     let _ = app.emit("file-progress", serde_json::json!({
-                         "id": header.id,
-                         "fileName": header.file_name,
-                         "total": header.file_size,
-                         "transferred": total_written
-                     }));
+                     crate::events::emit(
+                         &app,
+                         crate::events::FileProgress { id: header.id.clone(), file_name: header.file_name.clone(), total: header.file_size, transferred: total_written },
+                     );
                      last_emit = std::time::Instant::now();
                 }
             }
@@ -2451,49 +4636,288 @@ async fn handle_incoming_file_stream(recv: quinn::RecvStream, addr: std::net::So
     }
     
     let total_time = start_time.elapsed();
-    let mb = total_written as f64 / 1_000_000.0;
+    let mb = (total_written - header.offset) as f64 / 1_000_000.0;
     let speed = mb / total_time.as_secs_f64();
     tracing::info!("File Stream Completed. Written {} chunks ({} bytes) in {:?}. Speed: {:.2} MB/s", chunk_count, total_written, total_time, speed);
     
     // Final Progress
-    let _ = app.emit("file-progress", serde_json::json!({
-         "id": header.id,
-         "fileName": header.file_name,
-         "total": header.file_size,
-         "transferred": total_written
-     }));
-    
+    crate::events::emit(
+        &app,
+        crate::events::FileProgress { id: header.id.clone(), file_name: header.file_name.clone(), total: header.file_size, transferred: total_written },
+    );
+
+    if let Err(e) = file.flush().await {
+        tracing::error!("Failed to flush {}: {}", record.part_path, e);
+    }
+    drop(file);
+
+    // 5. Verify Size
+    if total_written != header.file_size {
+        // Keep the .part and sidecar so the transfer can be resumed
+        tracing::warn!("File Transfer Incomplete! Expected {}, got {}. Keeping partial file for resume.", header.file_size, total_written);
+        let mut record = record;
+        record.transferred = total_written;
+        crate::events::emit(&app, &record);
+        state.partial_transfers.lock().unwrap().insert(record.key(), record);
+        return;
+    }
+
+    // 6. Verify Checksum (against the hash the sender announced with the offer)
+    // Resumed transfers only streamed the tail, so hash the whole file
+    let hash = if header.offset == 0 {
+        Some(hex::encode(sha2::Digest::finalize(hasher)))
+    } else {
+        crypto::hash_file(std::path::Path::new(&record.part_path)).ok()
+    };
+    let expected = state
+        .remote_files
+        .lock()
+        .unwrap()
+        .get(&header.id)
+        .and_then(|files| files.get(header.file_index))
+        .and_then(|meta| meta.hash.clone());
+    match (&expected, &hash) {
+        (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(actual) => {
+            handle_verify_failure(&app, &state, &header, record).await;
+            return;
+        }
+        (Some(_), None) => tracing::warn!("Couldn't hash {} to verify it", record.part_path),
+        _ => {}
+    }
+    state.verify_failures.lock().unwrap().remove(&record.key());
+
+    tracing::info!("File Transfer Verified OK");
+    if let Some(peer_id) = crate::peerstats::peer_at(&state, addr.ip()) {
+        crate::peerstats::record(&state, &peer_id, crate::peerstats::Direction::Received, crate::peerstats::Kind::File, header.file_size.saturating_sub(header.offset));
+    }
+
+    // 7. Promote .part to its final name
+    let file_path = match prepare_download_path(&app, &header.file_name) {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    };
+    if let Err(e) = tokio::fs::rename(&record.part_path, &file_path).await {
+        tracing::error!("Failed to move {} to {}: {}", record.part_path, crate::redact::path(&file_path), e);
+        return;
+    }
+    crate::storage::remove_partial_transfer(&record, false);
+    state.partial_transfers.lock().unwrap().remove(&record.key());
+
+    // 8. Post-receive hook (scanner / quarantine)
+    let scan = scan_received_file(&app, &state, &file_path, &header.file_name).await;
+
      // Emit received event
-     let _ = app.emit("file-received", serde_json::json!({
-         "id": header.id,
-         "file_name": header.file_name,
-         "file_size": header.file_size,
-         "file_index": header.file_index,
-         "auth_token": header.auth_token, // (optional, maybe redact?)
-         "path": file_path.to_string_lossy()
-     }));
+     crate::events::emit(
+         &app,
+         crate::events::FileReceived {
+             id: header.id.clone(),
+             file_name: header.file_name.clone(),
+             file_size: header.file_size,
+             file_index: header.file_index,
+             path: file_path.to_string_lossy().into_owned(),
+             inline: false,
+             deduplicated: false,
+             scan: Some(scan.clone()),
+         },
+     );
      
      // Notification
-     let settings = state.settings.lock().unwrap();
-     if settings.notify_large_files && header.file_size > settings.max_auto_download_size {
-         let body = format!("Download complete: {}", header.file_name);
-         send_notification(&app, "Download Complete", &body, false, None, "history", NotificationPayload::None);
+     {
+         let settings = state.settings.lock().unwrap();
+         if settings.notify_large_files && header.file_size > settings.max_auto_download_size {
+             let body = i18n::tf("notify.download_complete.body", &[("file", &header.file_name)]);
+             // Blocked files stay out of reach of Open / Show in Folder
+             let payload = match file_path.to_str() {
+                 Some(path) if !scan.is_blocked() => NotificationPayload::FileReceived { path: path.to_string() },
+                 _ => NotificationPayload::None,
+             };
+             send_notification(&app, &i18n::t("notify.download_complete.title"), &body, false, None, "history", payload);
+         }
      }
 
-    // 5. Verify Size
-    if total_written == header.file_size {
-        tracing::info!("File Transfer Verified OK");
-        if let Some(path_str) = file_path.to_str() {
-             crate::clipboard::set_clipboard_paths(&app, vec![path_str.to_string()]);
+    if scan.is_blocked() {
+        crate::compose::file_missing(&app, &state, &header.id, header.file_index);
+        return;
+    }
+
+    if let Some(path_str) = file_path.to_str() {
+         if let Some(hash) = hash {
+             state.record_received_hash(hash, path_str.to_string());
+         }
+         state.record_received_file(&header.id, header.file_index, path_str.to_string());
+
+         let save_prompt_size = state.settings.lock().unwrap().save_prompt_size;
+         if save_prompt_size > 0 && header.file_size > save_prompt_size {
+             // Large files go where the user wants them rather than onto the clipboard from the cache
+             tracing::info!("{} is over the save prompt size; asking where to save it", crate::redact::text(&header.file_name));
+             crate::events::emit(
+                 &app,
+                 crate::events::FileSaveRequested {
+                     id: header.id.clone(),
+                     file_index: header.file_index,
+                     file_name: header.file_name.clone(),
+                     file_size: header.file_size,
+                     path: path_str.to_string(),
+                 },
+             );
+             crate::compose::file_missing(&app, &state, &header.id, header.file_index);
+         } else {
+             crate::compose::file_ready(&app, &state, &header.id, header.file_index, path_str.to_string());
+         }
+    }
+}
+
+// Automatic re-requests after a download fails its checksum
+const MAX_VERIFY_RETRIES: u32 = 2;
+
+// Checksum mismatch: drop what we received and ask the sender for the file again (a few times)
+async fn handle_verify_failure(app: &tauri::AppHandle, state: &AppState, header: &crate::protocol::FileStreamHeader, record: crate::storage::PartialTransfer) {
+    let key = record.key();
+    crate::storage::remove_partial_transfer(&record, true);
+    state.partial_transfers.lock().unwrap().remove(&key);
+
+    let attempt = {
+        let mut failures = state.verify_failures.lock().unwrap();
+        let count = failures.entry(key.clone()).or_insert(0);
+        *count += 1;
+        *count
+    };
+    let retry_from = record.peer_id.filter(|_| attempt <= MAX_VERIFY_RETRIES);
+    tracing::warn!(
+        "{} failed checksum verification (attempt {}). {}",
+        crate::redact::text(&header.file_name),
+        attempt,
+        if retry_from.is_some() { "Requesting it again." } else { "Giving up." }
+    );
+    crate::events::emit(
+        app,
+        crate::events::FileVerifyFailed {
+            id: header.id.clone(),
+            file_index: header.file_index,
+            file_name: header.file_name.clone(),
+            attempt,
+            will_retry: retry_from.is_some(),
+        },
+    );
+
+    let Some(peer_id) = retry_from else {
+        state.verify_failures.lock().unwrap().remove(&key);
+        crate::compose::file_missing(app, state, &header.id, header.file_index);
+        return;
+    };
+    if let Err(e) = request_file_internal(app, state, header.id.clone(), header.file_index, peer_id).await {
+        crate::error::report(app, "file-download", &e);
+        state.verify_failures.lock().unwrap().remove(&key);
+        crate::compose::file_missing(app, state, &header.id, header.file_index);
+    }
+}
+
+// Open (or reopen, when resuming) the .part file for an incoming stream and persist its sidecar
+async fn open_part_file(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    header: &crate::protocol::FileStreamHeader,
+    addr: std::net::SocketAddr,
+) -> Result<(File, crate::storage::PartialTransfer), String> {
+    let dir = crate::storage::temp_downloads_dir(app).ok_or("Failed to get cache dir")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache dir: {}", e))?;
+
+    let key = format!("{}:{}", header.id, header.file_index);
+    let existing = state.partial_transfers.lock().unwrap().get(&key).cloned();
+
+    if header.offset > 0 {
+        if let Some(mut record) = existing {
+            let mut file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(&record.part_path)
+                .await
+                .map_err(|e| format!("Failed to reopen {}: {}", record.part_path, e))?;
+            let on_disk = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+            if on_disk < header.offset {
+                return Err(format!("Partial file {} is shorter ({}) than resume offset {}", record.part_path, on_disk, header.offset));
+            }
+            file.set_len(header.offset).await.map_err(|e| e.to_string())?;
+            file.seek(std::io::SeekFrom::Start(header.offset)).await.map_err(|e| e.to_string())?;
+            record.transferred = header.offset;
+            tracing::info!("Resuming {} at offset {}", crate::redact::text(&header.file_name), header.offset);
+            return Ok((file, record));
+        }
+        return Err(format!("Resume requested for unknown transfer {}", key));
+    }
+
+    // Fresh transfer (drop any stale partial for the same file)
+    if let Some(old) = existing {
+        crate::storage::remove_partial_transfer(&old, true);
+    }
+
+    let safe_id: String = header.id.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+    let part_path = dir.join(format!("{}-{}.part", safe_id, header.file_index));
+    let file = File::create(&part_path)
+        .await
+        .map_err(|e| format!("Failed to create file {:?}: {}", part_path, e))?;
+
+    // Prefer an exact ip:port match (several dev instances can share an IP)
+    let peers = state.get_peers();
+    let peer_id = peers
+        .values()
+        .find(|p| p.ip == addr.ip() && p.port == addr.port())
+        .or_else(|| peers.values().find(|p| p.ip == addr.ip()))
+        .map(|p| p.id.clone());
+    let record = crate::storage::PartialTransfer {
+        id: header.id.clone(),
+        file_index: header.file_index,
+        file_name: header.file_name.clone(),
+        file_size: header.file_size,
+        peer_id,
+        part_path: part_path.to_string_lossy().to_string(),
+        transferred: 0,
+    };
+    crate::storage::save_partial_transfer(&record);
+    state.partial_transfers.lock().unwrap().insert(key, record.clone());
+    Ok((file, record))
+}
+
+// Pass a received clipboard item on to every other peer (`from` already has it).
+// `fallback` is the payload as it arrived, used if re-serializing fails.
+async fn relay_clipboard(
+    state: &AppState,
+    transport: &Transport,
+    from: std::net::SocketAddr,
+    key: &[u8; 32],
+    mut payload: crate::protocol::ClipboardPayload,
+    fallback: Vec<u8>,
+) {
+    let auto_send = { state.settings.lock().unwrap().auto_send };
+    if !auto_send || crate::screenlock::sync_paused(state) {
+        return;
+    }
+
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    crate::latency::add_hop(&mut payload.trace, &local_id);
+    let payload_bytes = serde_json::to_vec(&payload).unwrap_or(fallback);
+
+    if let Ok(relay_ciphertext) = crypto::encrypt(key, &payload_bytes).map_err(|e| e.to_string()) {
+        let relay_data = serde_json::to_vec(&Message::Clipboard(relay_ciphertext)).unwrap_or_default();
+        let peers = state.get_peers();
+        for p in peers.values() {
+            let p_addr = std::net::SocketAddr::new(p.ip, p.port);
+            if p_addr == from { continue; }
+            let Some(data) = clipboard::data_for_peer(state, key, p, &payload_bytes, &relay_data) else { continue; };
+            let _ = transport.send_message(p_addr, &data).await;
         }
-    } else {
-        tracing::warn!("File Transfer Incomplete! Expected {}, got {}", header.file_size, total_written);
     }
 }
 
 async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state: AppState, listener_handle: tauri::AppHandle, transport_inside: Transport) {
     match msg {
         Message::Clipboard(ciphertext) => {
+            if listener_state.is_reset_pending() {
+                tracing::debug!("Reset pending. Ignoring clipboard from {}", addr);
+                return;
+            }
             // Decrypt
             tracing::debug!("Received Encrypted Clipboard from {}", addr);
             let key_opt = {
@@ -2504,7 +4928,14 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                 let mut key_arr = [0u8; 32];
                 if key.len() == 32 {
                     key_arr.copy_from_slice(&key);
-                    match crypto::decrypt(&key_arr, &ciphertext).map_err(|e| e.to_string()) {
+                    // Guests seal with their derived key
+                    let decrypted = crypto::decrypt(&key_arr, &ciphertext).or_else(|e| {
+                        match crate::guest::key_for_addr(&listener_state, &key_arr, addr.ip()) {
+                            Some(guest_key) => crypto::decrypt(&guest_key, &ciphertext),
+                            None => Err(e),
+                        }
+                    });
+                    match decrypted.map_err(|e| e.to_string()) {
                         Ok(plaintext) => {
                             // Try to parse as ClipboardPayload
                             let (text, id, ts, sender, payload) = if let Ok(payload) = serde_json::from_slice::<crate::protocol::ClipboardPayload>(&plaintext) {
@@ -2523,6 +4954,12 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                             sender: "Unknown (Legacy)".to_string(),
                                             sender_id: "unknown".to_string(),
                                             files: None,
+                                            source: None,
+                                            trace: None,
+                                            channel: crate::protocol::DEFAULT_CHANNEL.to_string(),
+                                            link: None,
+                                            content: None,
+                                            auth: None,
                                         }
                                     )
                             } else {
@@ -2530,34 +4967,53 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                     return;
                             };
 
+                            // Relayed/forwarded content still carries the original sender
+                            if listener_state.is_blocked(&payload.sender_id) {
+                                tracing::debug!("Dropping clipboard from blocked device {}", payload.sender_id);
+                                return;
+                            }
+                            if let Err(reason) = crate::senderauth::check_clipboard(&listener_state, addr.ip(), &payload) {
+                                tracing::warn!("Dropping clipboard {} from {} (claims {}): {}", payload.id, addr, payload.sender_id, reason);
+                                return;
+                            }
+
                             // Verify Timestamp Freshness (120s threshold)
                             let now = std::time::SystemTime::now()
                                 .duration_since(std::time::UNIX_EPOCH)
                                 .unwrap_or_default()
                                 .as_secs();
 
-                            let diff = if now > ts {
-                                now - ts
-                            } else {
-                                ts - now // Future timestamp (clock skew)
-                            };
+                            // Either way: future timestamps are clock skew
+                            let diff = now.abs_diff(ts);
 
                             if diff > 120 {
                                 tracing::warn!("Ignored stale clipboard message from {} (Timestamp: {}, Now: {}, Diff: {}s)", sender, ts, now, diff);
                                 return;
                             }
 
-                            // Self-sender check
+                            // Self-sender check by device ID (identically named machines, e.g. cloned
+                            // VMs, must not drop each other's content). Hostname only for legacy payloads.
                             {
-                                let my_hostname = get_hostname_internal();
-                                if sender == my_hostname {
-                                    tracing::debug!("Ignoring clipboard message from self (sender={})", sender);
+                                let local_id = listener_state.local_device_id.lock().unwrap().clone();
+                                let from_self = if payload.sender_id.is_empty() || payload.sender_id == "unknown" {
+                                    sender == get_hostname_internal()
+                                } else {
+                                    payload.sender_id == local_id
+                                };
+                                if from_self {
+                                    tracing::debug!("Ignoring clipboard message from self (sender={}, id={})", sender, payload.sender_id);
                                     return;
                                 }
                             }
 
-                            // Loop/Dedupe Check
-                            let content_signature = if let Some(files) = &payload.files {
+                            if !crate::protocol::is_valid_channel(&payload.channel) {
+                                tracing::debug!("Ignoring clipboard on invalid channel '{}' from {}", payload.channel, sender);
+                                return;
+                            }
+
+                            // Loop/Dedupe Check: keyed by origin device too, so relayed copies of the same
+                            // item are dropped but identical content from another device is not
+                            let content = if let Some(files) = &payload.files {
                                 if !files.is_empty() {
                                     let mut sig = String::from("FILES:");
                                     for f in files {
@@ -2571,6 +5027,7 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                             } else {
                                 text.clone()
                             };
+                            let content_signature = crate::state::LastClipboard::received(&payload.sender_id, content);
 
                             {
                                 let mut last = listener_state.last_clipboard_content.lock().unwrap();
@@ -2582,28 +5039,9 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                             }
 
                             // Check Auto-Receive Setting
-                            tracing::debug!("Decrypted Clipboard from {}: {}...", sender, if text.len() > 20 { &text[0..20] } else { &text }); 
+                            tracing::debug!("Decrypted Clipboard from {}: {}", sender, crate::redact::text(&text));
+                            crate::peerstats::record(&listener_state, &payload.sender_id, crate::peerstats::Direction::Received, crate::peerstats::Kind::Item, ciphertext.len() as u64);
 
-                            if let Some(files) = &payload.files {
-                                if !files.is_empty() {
-                                    #[cfg(desktop)]
-                                    {
-                                        let should_badge = if let Some(window) = listener_handle.get_webview_window("main") {
-                                            match window.is_focused() {
-                                                Ok(focused) => !focused,
-                                                Err(_) => true,
-                                            }
-                                        } else {
-                                            true
-                                        };
-                                        
-                                        if should_badge {
-                                            crate::tray::set_badge(&listener_handle, true);
-                                        }
-                                    }
-                                }
-                            }
-                            
                             // Create Payload Object (already created above as 'payload' or fallback)
                             // Use the one we constructed or parsed
                             let payload_obj = crate::protocol::ClipboardPayload {
@@ -2613,38 +5051,126 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                 timestamp: ts,
                                 sender: sender.clone(),
                                 sender_id: payload.sender_id.clone(),
+                                source: payload.source.clone(),
+                                trace: payload.trace.clone(),
+                                channel: payload.channel.clone(),
+                                link: crate::unfurl::sanitize(payload.link.clone()),
+                                content: None,
+                                // Relays pass the origin's signature on unchanged
+                                auth: payload.auth.clone(),
                             };
 
+                            // Channel filter: unsubscribed channels aren't applied here, but are still
+                            // relayed for devices that only hear them through us
+                            let subscribed = listener_state.settings.lock().unwrap().subscribed_channels.contains(&payload.channel);
+                            if !subscribed {
+                                tracing::debug!("Not applying clipboard on unsubscribed channel '{}' from {}", payload.channel, sender);
+                                relay_clipboard(&listener_state, &transport_inside, addr, &key_arr, payload_obj, plaintext).await;
+                                return;
+                            }
+
+                            crate::unread::received(&listener_handle);
+
                             // FILE HANDLING
+                            // Set when auto-downloaded files will carry the text onto the clipboard with them
+                            let mut composing = false;
                             if let Some(files) = &payload.files {
                                 if !files.is_empty() {
                                     tracing::info!("Received File Metadata from {}: {} files", sender, files.len());
-                                    let _ = listener_handle.emit("clipboard-change", &payload_obj);
+                                    crate::history::commit(&listener_handle, &payload_obj);
+
+                                    // Remember what was offered (for manual requests and dedupe)
+                                    listener_state.remote_files.lock().unwrap().insert(id.clone(), files.clone());
                                     
                                     // Auto-Download Logic
-                                    let (auto_recv, enable_ft, size_limit, notify_large) = {
+                                    let (mut auto_recv, enable_ft, mut size_limit, notify_large) = {
                                         let s = listener_state.settings.lock().unwrap();
                                         (s.auto_receive, s.enable_file_transfer, s.max_auto_download_size, s.notify_large_files)
                                     };
 
+                                    // Per-peer override
+                                    let policy = listener_state.peer_download_policies.lock().unwrap().get(&payload.sender_id).copied();
+                                    match policy {
+                                        Some(crate::storage::AutoDownloadPolicy::Always) => {
+                                            auto_recv = true;
+                                            size_limit = u64::MAX;
+                                        }
+                                        Some(crate::storage::AutoDownloadPolicy::Never) => auto_recv = false,
+                                        Some(crate::storage::AutoDownloadPolicy::UpTo(limit)) => {
+                                            auto_recv = true;
+                                            size_limit = limit;
+                                        }
+                                        None => {}
+                                    }
+                                    if crate::screenlock::sync_paused(&listener_state) {
+                                        tracing::info!("Screen locked. Not auto-downloading files from {}", sender);
+                                        auto_recv = false;
+                                    }
+
                                     if !enable_ft {
                                         tracing::info!("File transfer disabled in settings. Ignoring auto-download.");
                                     } else {
                                         let mut total_size = 0u64;
                                         for f in files { total_size += f.size; }
                                         
-                                        tracing::info!("File Transfer Logic: AutoRecv={}, TotalSize={}, Limit={}, NotifyLarge={}", auto_recv, total_size, size_limit, notify_large);
+                                        tracing::info!("File Transfer Logic: AutoRecv={}, TotalSize={}, Limit={}, NotifyLarge={}, PeerPolicy={:?}", auto_recv, total_size, size_limit, notify_large, policy);
+
+                                        // Metered connection or battery saver: hold large downloads until asked for
+                                        let deferred = if auto_recv && total_size <= size_limit {
+                                            listener_state.power.defer_download(&listener_state.settings.lock().unwrap(), total_size)
+                                        } else {
+                                            None
+                                        };
 
-                                        if auto_recv && total_size <= size_limit {
+                                        if let Some(reason) = deferred {
+                                            tracing::info!("Deferring auto-download of {} files ({} bytes): {:?}", files.len(), total_size, reason);
+                                            crate::events::emit(&listener_handle, crate::events::DownloadDeferred {
+                                                id: id.clone(),
+                                                peer_id: payload.sender_id.clone(),
+                                                file_count: files.len(),
+                                                total_size,
+                                                reason,
+                                            });
+                                            let body_key = match reason {
+                                                crate::power::Constraint::Metered => "notify.download_deferred.metered",
+                                                crate::power::Constraint::BatterySaver => "notify.download_deferred.battery_saver",
+                                            };
+                                            let body = i18n::tf(body_key, &[("count", &files.len().to_string()), ("sender", &sender)]);
+                                            let payload = NotificationPayload::DownloadAvailable {
+                                                msg_id: id.clone(),
+                                                file_count: files.len(),
+                                                peer_id: payload.sender_id.clone(),
+                                            };
+                                            send_notification(&listener_handle, &i18n::t("notify.download_deferred.title"), &body, true, None, "history", payload);
+                                        } else if auto_recv && total_size <= size_limit {
                                             tracing::info!("Auto-downloading {} files ({} bytes)", files.len(), total_size);
+                                            // Apply text and files to the clipboard together once every file is in
+                                            let apply_text = {
+                                                let auto_receive = listener_state.settings.lock().unwrap().auto_receive;
+                                                auto_receive && !crate::screenlock::sync_paused(&listener_state) && !text.is_empty()
+                                            };
+                                            crate::compose::begin(&listener_handle, &listener_state, &id, apply_text.then(|| text.clone()), files.len());
+                                            composing = true;
                                             // Request Each File
                                             for (idx, _file_meta) in files.iter().enumerate() {
+                                                if materialize_inline_file(&listener_handle, &listener_state, &id, idx) {
+                                                    continue;
+                                                }
+                                                if reuse_received_file(&listener_handle, &listener_state, &id, idx) {
+                                                    send_have_already(&transport_inside, &key_arr, addr, id.clone(), idx).await;
+                                                    continue;
+                                                }
                                                 tracing::info!("Requesting file {}/{}", idx, files.len());
-                                                let req_payload = crate::protocol::FileRequestPayload {
+                                                let mut req_payload = crate::protocol::FileRequestPayload {
                                                     id: id.clone(),
                                                     file_index: idx,
                                                     offset: 0,
+                                                    accept_compression: true,
+                                                    requester_id: String::new(),
+                                                    requested_at: 0,
+                                                    auth: None,
                                                 };
+                                                crate::senderauth::sign_file_request(&listener_state, &mut req_payload);
                                                 // Encrypt Request
                                                 if let Ok(req_json) = serde_json::to_vec(&req_payload) {
                                                     if let Ok(req_cipher) = crypto::encrypt(&key_arr, &req_json) {
@@ -2663,15 +5189,14 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                             // Too large or auto-recv off
                                             if notify_large {
                                                 tracing::info!("Large file or manual mode. Sending notification."); 
-                                                let body = format!("Received {} files from {}. Click to download.", files.len(), sender);
-                                                let _body = format!("Received {} files from {}. Click to download.", files.len(), sender);
+                                                let body = i18n::tf("notify.files_available.body", &[("count", &files.len().to_string()), ("sender", &sender)]);
                                                 // Create Payload for Download Button
                                                 let payload = NotificationPayload::DownloadAvailable {
                                                     msg_id: id.clone(),
                                                     file_count: files.len(),
                                                     peer_id: payload.sender_id.clone(),
                                                 };
-                                                send_notification(&listener_handle, "Files Available", &body, true, None, "history", payload);
+                                                send_notification(&listener_handle, &i18n::t("notify.files_available.title"), &body, true, None, "history", payload);
                                             } else {
                                                 tracing::warn!("Large file received but 'notify_large_files' is FALSE. No notification sent.");
                                             }
@@ -2683,47 +5208,27 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                             // TEXT HANDLING
                             if !text.is_empty() {
                                 let auto_receiver = { listener_state.settings.lock().unwrap().auto_receive };
-                                if auto_receiver {
-                                    clipboard::set_clipboard(&listener_handle, text.clone());
-                                    let _ = listener_handle.emit("clipboard-change", &payload_obj);
+                                let locked = crate::screenlock::sync_paused(&listener_state);
+                                if auto_receiver && !locked {
+                                    if !composing {
+                                        clipboard::set_clipboard(&listener_handle, text.clone());
+                                    }
+                                    crate::history::commit(&listener_handle, &payload_obj);
                                 } else {
                                     // Manual Mode
-                                    tracing::info!("[Clipboard] Auto-receive OFF. Storing pending clipboard from {}", sender);
-                                    {
-                                        let mut pending = listener_state.pending_clipboard.lock().unwrap();
-                                        *pending = Some(payload_obj.clone());
-                                    }
-                                    let _ = listener_handle.emit("clipboard-pending", &payload_obj);
+                                    tracing::info!("[Clipboard] Auto-receive OFF or screen locked. Storing pending clipboard from {}", sender);
+                                    clipboard::hold_incoming(&listener_handle, &listener_state, payload_obj.clone());
                                 }
                                 
                                 let notifications = listener_state.settings.lock().unwrap().notifications.clone();
                                 if notifications.data_received {
-                                    send_notification(&listener_handle, "Clipboard Received", "Content copied to clipboard", false, Some(2), "history", NotificationPayload::None);
+                                    send_notification(&listener_handle, &i18n::t("notify.clipboard_received.title"), &i18n::t("notify.clipboard_received.body"), false, Some(2), "history", NotificationPayload::None);
                                 }
                             }
 
-                            // Relay Logic
-                            let auto_send = { listener_state.settings.lock().unwrap().auto_send };
-                            if !auto_send {
-                                    return; 
-                            }
-                            
-                            let state_relay = listener_state.clone();
-                            let transport_relay = transport_inside.clone(); 
-                            let sender_addr = addr;
-                            let relay_key_arr = key_arr; 
-                            
-                            let payload_bytes = serde_json::to_vec(&payload_obj).unwrap_or(plaintext);
-                            
-                            if let Ok(relay_ciphertext) = crypto::encrypt(&relay_key_arr, &payload_bytes).map_err(|e| e.to_string()) {
-                                let relay_data = serde_json::to_vec(&Message::Clipboard(relay_ciphertext)).unwrap_or_default();
-                                let peers = state_relay.get_peers();
-                                for p in peers.values() {
-                                    let p_addr = std::net::SocketAddr::new(p.ip, p.port);
-                                    if p_addr == sender_addr { continue; }
-                                    let _ = transport_relay.send_message(p_addr, &relay_data).await;
-                                }
-                            }
+                            crate::latency::record(&listener_state, &payload_obj);
+
+                            relay_clipboard(&listener_state, &transport_inside, addr, &key_arr, payload_obj, plaintext).await;
                         }
                         Err(e) => tracing::error!("Decryption failed: {}", e),
                     }
@@ -2734,108 +5239,245 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
         }
         Message::HistoryDelete(id) => {
             tracing::info!("Received HistoryDelete for ID: {}", id);
-            let _ = listener_handle.emit("history-delete", &id);
+            forget_history_annotation(&listener_handle, &listener_state, &id);
+            crate::events::emit(&listener_handle, crate::events::HistoryDeleted { id: id.clone() });
         }
-        Message::PairRequest { msg, device_id } => {
-            tracing::info!("Received PairRequest from {} ({}). Authenticating...", addr, device_id);
-            let local_id = listener_state.local_device_id.lock().unwrap().clone();
-            let pin = listener_state.network_pin.lock().unwrap().clone();
-            
-            match crypto::start_spake2(&pin, &local_id, &device_id).map_err(|e| e.to_string()) {
-                Ok((spake_state, response_msg)) => {
-                    let resp_struct = Message::PairResponse {
-                        msg: response_msg,
-                        device_id: local_id.clone(),
-                    };
-                    if let Ok(resp_data) = serde_json::to_vec(&resp_struct) {
-                        if transport_inside.send_message(addr, &resp_data).await.map_err(|e| e.to_string()).is_ok() {
-                            match crypto::finish_spake2(spake_state, &msg).map_err(|e| e.to_string()) {
-                                Ok(session_key) => {
-                                    tracing::info!("Authentication Success for {}!", device_id);
-                                    let cluster_key_opt = {
-                                        listener_state.cluster_key.lock().unwrap().clone()
-                                    };
-                                    if let Some(cluster_key) = cluster_key_opt {
-                                        let mut session_key_arr = [0u8; 32];
-                                        if session_key.len() == 32 {
-                                            session_key_arr.copy_from_slice(&session_key);
-                                            if let Ok(encrypted_ck) = crypto::encrypt(&session_key_arr, &cluster_key).map_err(|e| e.to_string()) {
-                                                let known_peers = listener_state.known_peers.lock().unwrap().values().cloned().collect();
-                                                let network_name = listener_state.network_name.lock().unwrap().clone();
-                                                let network_pin = listener_state.network_pin.lock().unwrap().clone();
-                                                let welcome = Message::Welcome {
-                                                    encrypted_cluster_key: encrypted_ck,
-                                                    known_peers,
-                                                    network_name: network_name.clone(),
-                                                    network_pin
-                                                };
-                                                if let Ok(welcome_data) = serde_json::to_vec(&welcome) {
-                                                    let _ = transport_inside.send_message(addr, &welcome_data).await;
-                                                    
-                                                    let mut kp_lock = listener_state.known_peers.lock().unwrap();
-                                                    let p = crate::peer::Peer {
-                                                        id: device_id.clone(),
-                                                        ip: addr.ip(),
-                                                        port: addr.port(),
-                                                        hostname: format!("Peer ({})", addr.ip()), 
-                                                        last_seen: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
-                                                        is_trusted: true,
-                                                        is_manual: false,
-                                                        network_name: Some(network_name),
-                                                        signature: None,
-                                                    };
-                                                    kp_lock.insert(device_id.clone(), p.clone());
-                                                    save_known_peers(listener_handle.app_handle(), &kp_lock);
-                                                    listener_state.add_peer(p.clone());
-                                                    let _ = listener_handle.emit("peer-update", &p);
-                                                    gossip_peer(&p, &listener_state, &transport_inside, Some(addr));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => tracing::error!("Auth Failed: {}", e),
-                            }
+        Message::HistoryAnnotate(ciphertext) => {
+            let key_opt = { listener_state.cluster_key.lock().unwrap().clone() };
+            let Some(key) = key_opt.filter(|k| k.len() == 32) else {
+                tracing::warn!("Received HistoryAnnotate but no Cluster Key set!");
+                return;
+            };
+            let mut key_arr = [0u8; 32];
+            key_arr.copy_from_slice(&key);
+            match crypto::decrypt(&key_arr, &ciphertext).map_err(|e| e.to_string()) {
+                Ok(plaintext) => match serde_json::from_slice::<crate::protocol::HistoryAnnotation>(&plaintext) {
+                    Ok(mut annotation) => {
+                        tracing::info!("Received HistoryAnnotate for ID: {}", annotation.id);
+                        annotation.note = annotation.note.map(|n| n.chars().take(MAX_ANNOTATION_LEN).collect());
+                        if listener_state.apply_history_annotation(annotation.clone()) {
+                            crate::storage::save_history_annotations(listener_handle.app_handle(), &listener_state.history_annotations.lock().unwrap());
+                            crate::events::emit(&listener_handle, crate::events::HistoryAnnotated(&annotation));
                         }
                     }
+                    Err(e) => tracing::error!("Invalid HistoryAnnotate payload: {}", e),
+                },
+                Err(e) => tracing::error!("Decryption failed: {}", e),
+            }
+        }
+        Message::LinkPreview(ciphertext) => {
+            match decrypt_cluster_message::<crate::protocol::LinkPreviewUpdate>(&listener_state, &ciphertext) {
+                Some(update) => crate::unfurl::apply_update(&listener_handle, update),
+                None => tracing::warn!("Dropping undecryptable LinkPreview from {}", addr),
+            }
+        }
+        Message::PairRequest { msg, device_id, one_time, kdf } => {
+            answer_pair_request(&listener_state, &transport_inside, addr, msg, device_id, one_time, kdf).await;
+        }
+        Message::PairConfirm { device_id, confirmation, device_key } => {
+            let Some(joiner) = confirm_pairing(&listener_state, &transport_inside, addr, device_id, confirmation, device_key).await else {
+                return;
+            };
+            let require_approval = listener_state.settings.lock().unwrap().require_owner_approval;
+            if require_approval {
+                request_admission(&listener_state, &listener_handle, &transport_inside, joiner).await;
+            } else {
+                send_welcome(&listener_state, &listener_handle, &transport_inside, joiner).await;
+            }
+        }
+        Message::AdmissionRequest(ciphertext) => {
+            let Some(request) = decrypt_cluster_message::<crate::protocol::AdmissionRequest>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable AdmissionRequest from {}", addr);
+                return;
+            };
+            let local_id = listener_state.local_device_id.lock().unwrap().clone();
+            if listener_state.cluster_owner.lock().unwrap().as_deref() != Some(local_id.as_str()) {
+                tracing::warn!("Received AdmissionRequest from {} but this device is not the cluster owner", addr);
+                return;
+            }
+            tracing::info!("Admission requested for {} via {}", request.device_id, request.via);
+            notify_admission_request(&listener_handle, &request);
+            listener_state.admission_requests.lock().unwrap().insert(request.request_id.clone(), (request, Some(addr)));
+        }
+        Message::PairRejected { device_id, reason } => {
+            // Only a device we're pairing with can call it off
+            let key = addr.to_string();
+            let pairing = listener_state.pending_handshakes.lock().unwrap().contains_key(&key)
+                || listener_state.handshake_sessions.lock().unwrap().contains_key(&key);
+            if !pairing {
+                tracing::warn!("Ignoring PairRejected from {} ({}): no handshake in progress", addr, device_id);
+                return;
+            }
+            tracing::warn!("{} ({}) rejected pairing: {:?}", device_id, addr, reason);
+            crate::handshake::forget(&listener_state, &key);
+            crate::handshake::settle(&listener_handle, &device_id);
+            crate::events::emit(&listener_handle, crate::events::PairingFailed::new(reason));
+        }
+        Message::AdmissionPending { device_id } => {
+            // Only extends a session we already hold for that address; it can't start one
+            if crate::handshake::extend(&listener_state.handshake_sessions, &addr.to_string(), crate::handshake::ADMISSION_TTL) {
+                tracing::info!("{} ({}) is waiting for the cluster owner to approve us", device_id, addr);
+                crate::events::emit(&listener_handle, crate::events::PairingAwaitingApproval { device_id });
+            } else {
+                tracing::warn!("Ignoring AdmissionPending from {} ({}): no handshake in progress", addr, device_id);
+            }
+        }
+        Message::AdmissionResponse(ciphertext) => {
+            let Some(decision) = decrypt_cluster_message::<crate::protocol::AdmissionDecision>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable AdmissionResponse from {}", addr);
+                return;
+            };
+            // Every member holds the cluster key, so only trust decisions coming from the owner's address
+            let owner_ip = {
+                let owner = listener_state.cluster_owner.lock().unwrap().clone();
+                owner.and_then(|id| listener_state.peers.lock().unwrap().get(&id).map(|p| p.ip))
+            };
+            if owner_ip != Some(addr.ip()) {
+                tracing::warn!("Ignoring AdmissionResponse from {} (not the cluster owner)", addr);
+                return;
+            }
+            complete_admission(&listener_state, &listener_handle, &transport_inside, &decision.request_id, decision.approved).await;
+        }
+        Message::Probe(ciphertext) => {
+            let Some(probe) = decrypt_cluster_message::<crate::protocol::Probe>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable Probe from {}", addr);
+                return;
+            };
+            crate::conntest::answer(&listener_state, &transport_inside, addr, probe).await;
+        }
+        Message::OpenUrl(ciphertext) => {
+            let Some(request) = decrypt_cluster_message::<crate::protocol::OpenUrlRequest>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable OpenUrl from {}", addr);
+                return;
+            };
+            crate::remote::handle_open_url(&listener_handle, &listener_state, addr, request);
+        }
+        Message::TypeText(ciphertext) => {
+            let Some(request) = decrypt_cluster_message::<crate::protocol::TypeTextRequest>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable TypeText from {}", addr);
+                return;
+            };
+            crate::remote::handle_type_text(&listener_handle, &listener_state, addr, request);
+        }
+        Message::ProbeReply(ciphertext) => {
+            if let Some(reply) = decrypt_cluster_message::<crate::protocol::ProbeReply>(&listener_state, &ciphertext) {
+                crate::conntest::deliver(&listener_state, reply);
+            }
+        }
+        Message::DryRun(ciphertext) => {
+            let Some(payload) = decrypt_cluster_message::<crate::protocol::ClipboardPayload>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable DryRun from {}", addr);
+                return;
+            };
+            crate::dryrun::answer(&listener_state, &transport_inside, addr, payload).await;
+        }
+        Message::DryRunReceipt(ciphertext) => {
+            if let Some(receipt) = decrypt_cluster_message::<crate::protocol::DryRunReceipt>(&listener_state, &ciphertext) {
+                crate::dryrun::deliver(&listener_state, receipt);
+            }
+        }
+        Message::MembershipAnnounce(ciphertext) => {
+            let Some(announcement) = decrypt_cluster_message::<crate::protocol::MembershipAnnouncement>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable MembershipAnnounce from {}", addr);
+                return;
+            };
+            // The voucher must be a trusted member, and the message must come from its address
+            let voucher_ip = listener_state
+                .peers
+                .lock()
+                .unwrap()
+                .get(&announcement.admitted_by)
+                .filter(|p| p.is_trusted && p.guest_expires_at.is_none())
+                .map(|p| p.ip);
+            if voucher_ip != Some(addr.ip()) {
+                tracing::warn!("Ignoring MembershipAnnounce from {} (not sent by a trusted member)", addr);
+                return;
+            }
+            if !crate::senderauth::membership_signed(&listener_state, &announcement) {
+                tracing::warn!("Ignoring MembershipAnnounce for {} from {}: missing or invalid signature", announcement.member.id, addr);
+                return;
+            }
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+            if now.abs_diff(announcement.admitted_at) > MEMBERSHIP_ANNOUNCE_MAX_AGE_SECS {
+                tracing::warn!("Ignoring stale MembershipAnnounce for {} from {}", announcement.member.id, announcement.admitted_by);
+                return;
+            }
+            let mut member = announcement.member;
+            let local_id = listener_state.local_device_id.lock().unwrap().clone();
+            if member.id == local_id || listener_state.is_blocked(&member.id) || crate::guest::is_expired(&member, now) {
+                return;
+            }
+            if !crate::netscope::allows_peer(&listener_state, member.ip) {
+                tracing::info!("Ignoring announced member {} outside the allowed networks", member.id);
+                return;
+            }
+
+            tracing::info!("{} vouched for new member {}", announcement.admitted_by, member.id);
+            member.reset_address_history();
+            member.is_trusted = true;
+            member.is_manual = false;
+            member.last_seen = now;
+            {
+                let mut kp_lock = listener_state.known_peers.lock().unwrap();
+                // A key we already pinned stays; the signed announcement only pins a new device's key
+                let existing = kp_lock.get(&member.id).cloned();
+                let pinned_key = existing.as_ref().and_then(|p| p.public_key.clone())
+                    .or_else(|| listener_state.peers.lock().unwrap().get(&member.id).and_then(|p| p.public_key.clone()));
+                member.public_key = crate::identity::pinned(pinned_key.as_deref(), member.public_key.take());
+                if let Some(existing) = existing {
+                    member.is_manual = existing.is_manual;
                 }
-                Err(e) => tracing::error!("SPAKE2 Error: {}", e),
+                kp_lock.insert(member.id.clone(), member.clone());
+                save_known_peers(listener_handle.app_handle(), &kp_lock);
             }
+            listener_state.add_peer(member.clone());
+            crate::events::emit(&listener_handle, crate::events::PeerUpdate(&member));
         }
-        Message::PairResponse { msg, device_id } => {
-            tracing::info!("Received PairResponse from {} ({})", addr, device_id);
-            let spake_state = {
-                let mut pending = listener_state.pending_handshakes.lock().unwrap();
-                pending.remove(&addr.to_string())
+        Message::PeerListDigest(ciphertext) => {
+            let Some(digest) = decrypt_cluster_message::<crate::protocol::PeerListDigest>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable PeerListDigest from {}", addr);
+                return;
             };
-            if let Some(state) = spake_state {
-                match crypto::finish_spake2(state, &msg).map_err(|e| e.to_string()) {
-                    Ok(session_key) => {
-                        tracing::info!("Auth Success (Initiator)! Waiting for Welcome...");
-                        let mut sessions = listener_state.handshake_sessions.lock().unwrap();
-                        sessions.insert(addr.to_string(), session_key);
-                    }
-                    Err(e) => {
-                        tracing::error!("Auth Failed: {}", e);
-                        let _ = listener_handle.emit("pairing-failed", "Authentication failed. Check the PIN and try again.");
-                    }
+            crate::peersync::on_digest(&listener_state, &transport_inside, addr, digest).await;
+        }
+        Message::PeerListRequest(ciphertext) => {
+            let Some(list) = decrypt_cluster_message::<crate::protocol::PeerList>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable PeerListRequest from {}", addr);
+                return;
+            };
+            crate::peersync::on_list(&listener_handle, &listener_state, &transport_inside, addr, list, true).await;
+        }
+        Message::PeerList(ciphertext) => {
+            let Some(list) = decrypt_cluster_message::<crate::protocol::PeerList>(&listener_state, &ciphertext) else {
+                tracing::warn!("Dropping undecryptable PeerList from {}", addr);
+                return;
+            };
+            crate::peersync::on_list(&listener_handle, &listener_state, &transport_inside, addr, list, false).await;
+        }
+        Message::PairResponse { msg, device_id, confirmation } => {
+            tracing::info!("Received PairResponse from {} ({})", addr, device_id);
+            if let Some(result) = answer_pair_response(&listener_state, &transport_inside, addr, &msg, &device_id, &confirmation).await {
+                if let Err(reason) = result {
+                    crate::events::emit(&listener_handle, crate::events::PairingFailed::new(reason));
                 }
+            } else if let Some(intent) = crate::handshake::resume(&listener_handle, &device_id) {
+                // We restarted mid-handshake; the response is for SPAKE2 state we no longer have.
+                // The PIN isn't kept, so the user pairs again (start_pairing uses the peer's known address).
+                tracing::info!("PairResponse from {} is for a pairing started before a restart. Asking for the PIN again.", device_id);
+                crate::events::emit(&listener_handle, crate::events::PairingResumeNeeded { peer_id: intent.peer_id, network_name: intent.network_name });
             } else {
                 tracing::warn!("Received PairResponse but no pending handshake found for {}", addr);
-                let _ = listener_handle.emit("pairing-failed", "Pairing session expired. Please try again.");
+                crate::events::emit(&listener_handle, crate::events::PairingFailed::new(crate::protocol::PairingError::Timeout));
             }
         }
-        Message::Welcome { encrypted_cluster_key, known_peers, network_name, network_pin } => {
+        Message::Welcome { encrypted_cluster_key, known_peers, network_name, network_pin, owner_id, guest_expires_at, key_confirmation, device_key } => {
              tracing::info!("Received WELCOME from {}", addr);
-             let session_key = {
-                 let sessions = listener_state.handshake_sessions.lock().unwrap();
-                 sessions.get(&addr.to_string()).cloned()
-             };
-             if let Some(sk) = session_key {
-                 let mut sk_arr = [0u8; 32];
-                 if sk.len() == 32 {
-                     sk_arr.copy_from_slice(&sk);
-                     match crypto::decrypt(&sk_arr, &encrypted_cluster_key).map_err(|e| e.to_string()) {
+             // One Welcome per handshake: the session is used up whether or not it checks out
+             let session_keys = crate::handshake::take(&listener_state.handshake_sessions, &addr.to_string());
+             if let Some(keys) = session_keys {
+                 let local_id = listener_state.local_device_id.lock().unwrap().clone();
+                 if crypto::verify_confirmation(&keys, crypto::Confirm::Welcome(&local_id), &key_confirmation) {
+                     match crypto::decrypt(&keys.encryption, &encrypted_cluster_key).map_err(|e| e.to_string()) {
                          Ok(cluster_key) => {
                              tracing::info!("Joined Network: {} (PIN: {})", network_name, network_pin);
                              {
@@ -2850,26 +5492,43 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                  let mut np = listener_state.network_pin.lock().unwrap();
                                  *np = network_pin.clone();
                                  save_network_pin(listener_handle.app_handle(), &network_pin);
+
+                                 crate::storage::save_cluster_owner(listener_handle.app_handle(), owner_id.as_deref());
+                                 *listener_state.cluster_owner.lock().unwrap() = owner_id;
+
+                                 if let Some(exp) = guest_expires_at {
+                                     tracing::info!("Joined as a guest until {}", exp);
+                                 }
+                                 crate::storage::save_guest_expiry(listener_handle.app_handle(), guest_expires_at);
+                                 *listener_state.guest_expires_at.lock().unwrap() = guest_expires_at;
                              }
                              let device_id = listener_state.local_device_id.lock().unwrap().clone();
-                             let port = transport_inside.local_addr().map(|a| a.port()).unwrap_or(0);
+                             let port = listener_state.bound_port();
                              if let Some(discovery) = listener_state.discovery.lock().unwrap().as_mut() {
                                   let _ = discovery.register(&device_id, &network_name, port);
                              }
+                             let blocked: std::collections::HashSet<String> = listener_state.blocked_peers.lock().unwrap().keys().cloned().collect();
                              let mut kp_lock = listener_state.known_peers.lock().unwrap();
                              let mut runtime_peers = listener_state.peers.lock().unwrap();
-                             for peer in known_peers {
+                             for mut peer in known_peers.into_iter().filter(|p| !blocked.contains(&p.id)) {
+                                 peer.reset_address_history();
                                  kp_lock.insert(peer.id.clone(), peer.clone());
                                  runtime_peers.insert(peer.id.clone(), peer.clone());
-                                 let _ = listener_handle.emit("peer-update", &peer);
+                                 crate::events::emit(&listener_handle, crate::events::PeerUpdate(&peer));
                              }
                              save_known_peers(listener_handle.app_handle(), &kp_lock);
                              
+                             // Pairing again replaces whatever key we had pinned for the welcomer
+                             let welcomer_key = crate::identity::open(&keys, &device_key);
                              for (id, peer) in runtime_peers.iter_mut() {
                                  if peer.ip == addr.ip() {
+                                     crate::handshake::settle(&listener_handle, id);
                                      peer.is_trusted = true;
                                      peer.network_name = Some(network_name.clone());
-                                     let _ = listener_handle.emit("peer-update", &*peer);
+                                     if welcomer_key.is_some() {
+                                         peer.public_key = welcomer_key.clone();
+                                     }
+                                     crate::events::emit(&listener_handle, crate::events::PeerUpdate(peer));
                                      kp_lock.insert(id.clone(), peer.clone());
                                      break;
                                  }
@@ -2878,16 +5537,16 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                          }
                          Err(e) => {
                              tracing::error!("Decryption Error: {}", e);
-                             let _ = listener_handle.emit("pairing-failed", "Failed to join network. The PIN may be incorrect.");
+                             crate::events::emit(&listener_handle, crate::events::PairingFailed::new(crate::protocol::PairingError::KeyMismatch));
                          }
                      }
                  } else {
-                     tracing::error!("Decryption Error: No Cluster Key loaded.");
-                     let _ = listener_handle.emit("pairing-failed", "Session key error. Please try again.");
+                     tracing::error!("Welcome from {} failed key confirmation", addr);
+                     crate::events::emit(&listener_handle, crate::events::PairingFailed::new(crate::protocol::PairingError::KeyMismatch));
                  }
              } else {
                  tracing::warn!("Received Welcome but no session key found for {}", addr);
-                 let _ = listener_handle.emit("pairing-failed", "Pairing session expired. Please try again.");
+                 crate::events::emit(&listener_handle, crate::events::PairingFailed::new(crate::protocol::PairingError::Timeout));
              }
         }
         Message::PeerDiscovery(mut peer) => {
@@ -2902,8 +5561,8 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                 if !is_local_ip(sender_ip) {
                      tracing::warn!("Device ID Collision Detected! Remote peer at {} has the same ID as me ({}).", sender_ip, local_id);
                      send_notification(&listener_handle, 
-                         "Configuration Error", 
-                         &format!("Device ID Collision! Another device at {} shares your ID. Please reset one device.", sender_ip), 
+                         &i18n::t("notify.id_collision.title"), 
+                         &i18n::tf("notify.id_collision.body", &[("ip", &sender_ip.to_string())]), 
                          true, 
                          None, 
                          "settings", 
@@ -2920,17 +5579,32 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                 }
             }
 
+            if let Some(observed) = peer.observed_addr.take() {
+                note_observed_address(&listener_state, observed);
+            }
+            crate::platform::check_skew(&peer);
+
             peer.ip = addr.ip();
             peer.port = addr.port();
             peer.last_seen = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+            // `addresses` lists the sender's interfaces; the history on them is ours to keep
+            peer.reset_address_history();
+            peer.note_seen(addr, peer.last_seen);
             
             {
                 let kp = listener_state.known_peers.lock().unwrap();
                 if let Some(existing) = kp.get(&peer.id) {
                      peer.is_manual = existing.is_manual;
+                     peer.merge_addresses(existing);
                 } else {
                      peer.is_manual = false; 
                 }
+                // A device's own announcement can't replace the signing key we pinned for it
+                let pinned = match kp.get(&peer.id) {
+                    Some(existing) => existing.public_key.clone(),
+                    None => listener_state.peers.lock().unwrap().get(&peer.id).and_then(|p| p.public_key.clone()),
+                };
+                peer.public_key = crate::identity::pinned(pinned.as_deref(), peer.public_key.take());
             }
             
             let mut should_reply = false;
@@ -2941,7 +5615,8 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                      tracing::info!("Replacing manual placeholder {} with real peer {}", manual_id, peer.id);
                      kp_lock.remove(&manual_id);
                      listener_state.peers.lock().unwrap().remove(&manual_id);
-                     let _ = listener_handle.emit("peer-remove", &manual_id);
+                     crate::presence::forget(&listener_state, &manual_id);
+                     crate::events::emit(&listener_handle, crate::events::PeerRemoved { id: manual_id.clone() });
                      should_reply = true; 
                      peer.is_manual = true;
                  }
@@ -2957,14 +5632,26 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                          if key_vec.len() == 32 {
                              let mut key_arr = [0u8; 32];
                              key_arr.copy_from_slice(key_vec);
+                             // Guests sign with their derived key (which also vouches for the expiry)
+                             if let Some(exp) = peer.guest_expires_at {
+                                 key_arr = crate::guest::derive_key(&key_arr, &peer.id, exp);
+                             }
                              if verify_signature(&key_arr, &peer.id, sig) {
                                  is_signature_valid = true;
                              }
                          }
                      }
                  }
+                 let now_secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+                 // As a guest we can't verify members' signatures; keep the trust granted at Welcome
+                 let keep_guest_trust = listener_state.guest_expires_at.lock().unwrap().is_some()
+                     && peer.guest_expires_at.is_none()
+                     && kp_lock.get(&peer.id).is_some_and(|p| p.is_trusted);
                  
-                 if is_signature_valid {
+                 if crate::guest::is_expired(&peer, now_secs) {
+                     tracing::info!("Ignoring expired guest {}", peer.id);
+                     peer.is_trusted = false;
+                 } else if is_signature_valid || keep_guest_trust {
                      tracing::debug!("Verified Signature for {}! Trust maintained/granted.", peer.id);
                      peer.is_trusted = true;
                  } else {
@@ -2976,8 +5663,9 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                      peer.is_trusted = false;
                  }
 
-                 listener_state.add_peer(peer.clone());
-                 let _ = listener_handle.emit("peer-update", &peer);
+                 let peer = listener_state.add_peer(peer);
+                 crate::events::emit(&listener_handle, crate::events::PeerUpdate(&peer));
+                 crate::presence::set(&listener_handle, &listener_state, &peer, crate::presence::Presence::Online);
 
                  if peer.is_trusted || peer.is_manual {
                      kp_lock.insert(peer.id.clone(), peer.clone());
@@ -2993,35 +5681,15 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
             
             if should_reply {
                 tracing::debug!("Sending Discovery Reply to {}", addr);
-                let local_id = listener_state.local_device_id.lock().unwrap().clone();
-                let hostname = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or("Unknown".to_string());
-                let network_name = listener_state.network_name.lock().unwrap().clone();
-                
-                let mut signature = None;
-                if let Some(key_vec) = listener_state.cluster_key.lock().unwrap().as_ref() {
-                    if key_vec.len() == 32 {
-                        let mut key_arr = [0u8; 32];
-                        key_arr.copy_from_slice(key_vec);
-                        signature = generate_signature(&key_arr, &local_id);
-                    }
-                }
-                
-                let my_peer = crate::peer::Peer {
-                    id: local_id,
-                    ip: transport_inside.local_addr().unwrap().ip(),
-                    port: transport_inside.local_addr().unwrap().port(),
-                    hostname,
-                    last_seen: 0,
-                    is_trusted: false, 
-                    is_manual: true,
-                    network_name: Some(network_name),
-                    signature,
-                };
+                let mut my_peer = local_peer_info(&listener_state, &transport_inside);
+                // ObservedAddress: the source address their message came from
+                my_peer.observed_addr = Some(addr);
                 
                 let msg = Message::PeerDiscovery(my_peer);
                 let data = serde_json::to_vec(&msg).unwrap_or_default();
+                let delivery = crate::transport::Delivery::of(&msg);
                 tauri::async_runtime::spawn(async move {
-                    let _ = transport_inside.send_message(addr, &data).await;
+                    let _ = transport_inside.send_with(delivery, addr, &data).await;
                 });
             }
         }
@@ -3030,12 +5698,8 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
             let local_id = listener_state.local_device_id.lock().unwrap().clone();
             
             if target_id == local_id {
-                tracing::warn!("I have been removed from the network! resetting state...");
-                perform_factory_reset(
-                    &listener_handle,
-                    &listener_state,
-                    transport_inside.local_addr().map(|a| a.port()).unwrap_or(0)
-                );
+                tracing::warn!("I have been removed from the network! Staging reset...");
+                stage_pending_reset(&listener_handle, &listener_state);
             } else {
                 {
                     let mut kp = listener_state.known_peers.lock().unwrap();
@@ -3043,6 +5707,7 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                         save_known_peers(listener_handle.app_handle(), &kp);
                     }
                 }
+                crate::peersync::record_removal(listener_handle.app_handle(), &listener_state, &target_id);
                 {
                     let mut peers = listener_state.peers.lock().unwrap();
                     if let Some(peer) = peers.remove(&target_id) {
@@ -3050,7 +5715,8 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                         check_and_notify_leave(&listener_handle, &listener_state, &peer);
                     }
                 }
-                let _ = listener_handle.emit("peer-remove", &target_id);
+                crate::presence::forget(&listener_state, &target_id);
+                crate::events::emit(&listener_handle, crate::events::PeerRemoved { id: target_id.clone() });
             }
         }
         
@@ -3066,31 +5732,48 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                      match crypto::decrypt(&key_arr, &req_cipher).map_err(|e| e.to_string()) {
                          Ok(plaintext) => {
                              if let Ok(req) = serde_json::from_slice::<crate::protocol::FileRequestPayload>(&plaintext) {
+                                 if let Err(reason) = crate::senderauth::check_file_request(&listener_state, addr.ip(), &req) {
+                                     tracing::warn!("Refusing file request from {}: {}", addr, reason);
+                                     return;
+                                 }
                                  tracing::info!("Processing File Request: ID={}, Index={}", req.id, req.file_index);
                                  
                                  // 2. Find File Path
-                                 let path = {
-                                     let map = listener_state.local_files.lock().unwrap();
-                                     if let Some(paths) = map.get(&req.id) {
-                                         if req.file_index < paths.len() {
-                                             Some(paths[req.file_index].clone())
-                                         } else { None }
-                                     } else { None }
-                                 };
+                                 let path = listener_state.local_files.lock().unwrap().lookup(&req.id, req.file_index).map(str::to_string);
                                  
-                                 if let Some(p_str) = path {
+                                 if let Ok(p_str) = path {
                                       let file_path = PathBuf::from(p_str.clone());
                                       // 3. Open Stream & Send
                                       tauri::async_runtime::spawn(async move {
                                            // Open File
                                            let mut file = match File::open(&file_path).await {
                                                Ok(f) => f,
-                                               Err(e) => { tracing::error!("Failed to open requested file: {}", e); return; }
+                                               Err(e) => {
+                                                   tracing::error!("Failed to open requested file: {}", e);
+                                                   let reason = if e.kind() == std::io::ErrorKind::NotFound {
+                                                       crate::protocol::FileErrorReason::NotFound
+                                                   } else {
+                                                       crate::protocol::FileErrorReason::Unreadable
+                                                   };
+                                                   send_file_error(&transport_inside, &listener_state, addr, req.id, req.file_index, reason, Some(e.to_string())).await;
+                                                   return;
+                                               }
                                            };
                                            let file_size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
                                            let file_name = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+                                           // Resume from requested offset
+                                           let offset = req.offset.min(file_size);
+                                           if offset > 0 {
+                                               if let Err(e) = file.seek(std::io::SeekFrom::Start(offset)).await {
+                                                   tracing::error!("Failed to seek to offset {}: {}", offset, e);
+                                                   send_file_error(&transport_inside, &listener_state, addr, req.id, req.file_index, crate::protocol::FileErrorReason::Unreadable, Some(e.to_string())).await;
+                                                   return;
+                                               }
+                                               tracing::info!("Resuming '{}' from offset {}", crate::redact::text(&file_name), offset);
+                                           }
                                            
-                                           tracing::info!("Opening QUIC Stream to {} for file '{}' ({} bytes)", addr, file_name, file_size);
+                                           tracing::info!("Opening QUIC Stream to {} for file '{}' ({} bytes)", addr, crate::redact::text(&file_name), file_size);
                                            // Open QUIC Stream
                                            match transport_inside.send_file_stream(addr).await {
                                                Ok((_connection, mut stream)) => {
@@ -3109,13 +5792,21 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                                        }
                                                    };
                                                    
-                                                   // 4b. Send Header
+                                                   // 4b. Decide Compression (requester must support it, and the file must be worth it)
+                                                   let compress_enabled = listener_state.settings.lock().unwrap().compress_file_transfers;
+                                                   let compressed = compress_enabled
+                                                       && req.accept_compression
+                                                       && crate::compression::should_compress(&file_path, file_size);
+
+                                                   // 4c. Send Header
                                                    let header = crate::protocol::FileStreamHeader {
                                                        id: req.id,
                                                        file_index: req.file_index,
                                                        file_name,
                                                        file_size,
                                                        auth_token,
+                                                       compressed,
+                                                       offset,
                                                    };
                                                    
                                                    if let Ok(h_json) = serde_json::to_string(&header) {
@@ -3123,41 +5814,55 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                                                        if let Err(e) = stream.write_all(b"\n").await { tracing::error!("Header Newline Error: {}", e); return; }
                                                    }
                                                    
-                                                   // 5. Send Raw File
+                                                   // 5. Send File (Raw or zstd)
+                                                   let mut writer: Box<dyn tokio::io::AsyncWrite + Unpin + Send> = if compressed {
+                                                       Box::new(async_compression::tokio::write::ZstdEncoder::with_quality(
+                                                           stream,
+                                                           async_compression::Level::Precise(crate::compression::ZSTD_LEVEL),
+                                                       ))
+                                                   } else {
+                                                       Box::new(stream)
+                                                   };
+
                                                    let mut buf = vec![0u8; 1024 * 1024]; // 1MB chunks
                                                    let mut chunks_sent = 0;
+                                                   let mut bytes_sent = 0u64;
                                                    let start_time = std::time::Instant::now();
 
-                                                   tracing::info!("[Sender] Starting RAW loop. File size: {}", file_size);
+                                                   tracing::info!("[Sender] Starting {} loop. File size: {}", if compressed { "ZSTD" } else { "RAW" }, file_size);
 
                                                    loop {
                                                        match file.read(&mut buf).await {
                                                            Ok(0) => break, // EOF
                                                            Ok(n) => {
-                                                               // Write Raw Data
-                                                               if let Err(e) = stream.write_all(&buf[0..n]).await { tracing::error!("Stream Write Error: {}", e); break; }
+                                                               if let Err(e) = writer.write_all(&buf[0..n]).await { tracing::error!("Stream Write Error: {}", e); break; }
                                                                chunks_sent += 1;
+                                                               bytes_sent += n as u64;
                                                            }
                                                            Err(e) => { tracing::error!("File Read Error: {}", e); break; }
                                                        }
                                                    }
                                                    let total_time = start_time.elapsed();
                                                    tracing::info!("[Sender] Loop finished in {:?}. Chunks: {}", total_time, chunks_sent);
-                                                   // Finish Stream
-                                                   let _ = stream.finish();
+                                                   // Finish Stream (flushes the zstd frame, then finishes the QUIC stream)
+                                                   let _ = writer.shutdown().await;
                                                    
                                                    // Ensure connection stays alive until data is flushed/acknowledged
                                                    tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
                                                    _connection.close(0u32.into(), b"done");
                                                    
                                                    tracing::info!("File Sent Successfully: {}", p_str);
+                                                   if let Some(peer_id) = crate::peerstats::peer_at(&listener_state, addr.ip()) {
+                                                       crate::peerstats::record(&listener_state, &peer_id, crate::peerstats::Direction::Sent, crate::peerstats::Kind::File, bytes_sent);
+                                                   }
                                                }
 
                                                Err(e) => tracing::error!("Failed to open file stream: {}", e),
                                            }
                                       });
-                                 } else {
-                                     tracing::warn!("Requested file not found (ID: {}, Index: {})", req.id, req.file_index);
+                                 } else if let Err(reason) = path {
+                                     tracing::warn!("Requested file not available (ID: {}, Index: {}): {:?}", req.id, req.file_index, reason);
+                                     send_file_error(&transport_inside, &listener_state, addr, req.id, req.file_index, reason, None).await;
                                  }
                              }
                          }
@@ -3166,6 +5871,54 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
                  }
              }
         }
+        Message::HaveAlready(req_cipher) => {
+             let key_opt = { listener_state.cluster_key.lock().unwrap().clone() };
+             if let Some(key) = key_opt {
+                 let mut key_arr = [0u8; 32];
+                 if key.len() == 32 {
+                     key_arr.copy_from_slice(&key);
+                     match crypto::decrypt(&key_arr, &req_cipher).map_err(|e| e.to_string()) {
+                         Ok(plaintext) => {
+                             if let Ok(req) = serde_json::from_slice::<crate::protocol::FileRequestPayload>(&plaintext) {
+                                 tracing::info!("{} already has file (ID: {}, Index: {}). Transfer skipped.", addr, req.id, req.file_index);
+                                 crate::events::emit(
+                                     &listener_handle,
+                                     crate::events::FileTransferSkipped { id: req.id.clone(), file_index: req.file_index, reason: "deduplicated".to_string() },
+                                 );
+                             }
+                         }
+                         Err(e) => tracing::error!("Failed to decrypt HaveAlready: {}", e),
+                     }
+                 }
+             }
+        }
+        Message::FileRequestError(ciphertext) => {
+            if let Some(reply) = decrypt_cluster_message::<crate::protocol::FileRequestError>(&listener_state, &ciphertext) {
+                use crate::protocol::FileErrorReason;
+                tracing::warn!("{} can't serve file (ID: {}, Index: {}): {:?} {:?}", addr, reply.id, reply.file_index, reply.reason, reply.detail);
+                let summary = match reply.reason {
+                    FileErrorReason::Expired => i18n::t("notify.file_error.expired"),
+                    FileErrorReason::NotFound => i18n::t("notify.file_error.not_found"),
+                    FileErrorReason::Unreadable => i18n::t("notify.file_error.unreadable"),
+                };
+                let message = match &reply.detail {
+                    Some(detail) => format!("{}: {}", summary, detail),
+                    None => summary,
+                };
+                crate::events::emit(
+                    &listener_handle,
+                    crate::events::FileError {
+                        id: reply.id.clone(),
+                        file_index: reply.file_index,
+                        peer_id: crate::peerstats::peer_at(&listener_state, addr.ip()),
+                        reason: reply.reason,
+                        message,
+                        retryable: reply.reason == FileErrorReason::Unreadable,
+                    },
+                );
+                crate::compose::file_missing(&listener_handle, &listener_state, &reply.id, reply.file_index);
+            }
+        }
     }
 }
 
@@ -3176,60 +5929,150 @@ async fn handle_message(msg: Message, addr: std::net::SocketAddr, listener_state
 pub enum NotificationPayload {
     None,
     DownloadAvailable { msg_id: String, file_count: usize, peer_id: String },
+    // A received file, for the Open / Show in Folder actions
+    FileReceived { path: String },
 }
 
 #[tauri::command]
 async fn request_file(
-    _app_handle: tauri::AppHandle,
+    app_handle: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     file_id: String,
     file_index: usize,
     peer_id: String,
-) -> Result<(), String> {
-    request_file_internal(&state, file_id, file_index, peer_id).await
+) -> Result<(), AppError> {
+    request_file_internal(&app_handle, &state, file_id, file_index, peer_id).await
+}
+
+// If we already received a file with the same content hash, reuse it instead of streaming it again.
+// Returns true if the request was satisfied locally.
+fn reuse_received_file(app: &tauri::AppHandle, state: &AppState, file_id: &str, file_index: usize) -> bool {
+    let meta = {
+        let remote = state.remote_files.lock().unwrap();
+        remote.get(file_id).and_then(|files| files.get(file_index).cloned())
+    };
+    let Some(meta) = meta else { return false; };
+    let Some(hash) = meta.hash.as_ref() else { return false; };
+    let Some(path) = state.lookup_received_hash(hash) else { return false; };
+
+    tracing::info!("File {} (ID: {}, Index: {}) already received at {}. Skipping transfer.", crate::redact::text(&meta.name), file_id, file_index, crate::redact::text(&path));
+    crate::events::emit(
+        app,
+        crate::events::FileReceived {
+            id: file_id.to_string(),
+            file_name: meta.name.clone(),
+            file_size: meta.size,
+            file_index,
+            path: path.clone(),
+            inline: false,
+            deduplicated: true,
+            scan: None,
+        },
+    );
+    state.record_received_file(file_id, file_index, path.clone());
+    crate::compose::file_ready(app, state, file_id, file_index, path);
+    true
+}
+
+// Tell the sender we already have the file so it can mark the transfer complete
+async fn send_have_already<T: crate::transport::MessageTransport>(transport: &T, key: &[u8; 32], addr: std::net::SocketAddr, file_id: String, file_index: usize) {
+    let payload = crate::protocol::FileRequestPayload {
+        id: file_id,
+        file_index,
+        offset: 0,
+        accept_compression: false,
+        requester_id: String::new(),
+        requested_at: 0,
+        auth: None,
+    };
+    if let Ok(json) = serde_json::to_vec(&payload) {
+        if let Ok(cipher) = crypto::encrypt(key, &json) {
+            if let Ok(data) = serde_json::to_vec(&Message::HaveAlready(cipher)) {
+                let _ = transport.send_message(addr, &data).await;
+            }
+        }
+    }
+}
+
+// Tell the requester why a file it asked for won't be streamed
+async fn send_file_error<T: crate::transport::MessageTransport>(
+    transport: &T,
+    state: &AppState,
+    addr: std::net::SocketAddr,
+    file_id: String,
+    file_index: usize,
+    reason: crate::protocol::FileErrorReason,
+    detail: Option<String>,
+) {
+    let reply = crate::protocol::FileRequestError { id: file_id, file_index, reason, detail };
+    if let Some(cipher) = encrypt_cluster_message(state, &reply) {
+        if let Ok(data) = serde_json::to_vec(&Message::FileRequestError(cipher)) {
+            let _ = transport.send_message(addr, &data).await;
+        }
+    }
 }
 
 pub async fn request_file_internal(
+    app: &tauri::AppHandle,
     state: &AppState,
     file_id: String,
     file_index: usize,
     peer_id: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     tracing::info!("File Request Internal: ID={}, Index={}, Peer={}", file_id, file_index, peer_id);
     
+    // Inline files need no network round-trip at all
+    if materialize_inline_file(app, state, &file_id, file_index) {
+        return Ok(());
+    }
+
+    let deduplicated = reuse_received_file(app, state, &file_id, file_index);
+    
     // 1. Find Peer Address
     let addr = {
         let peers = state.get_peers();
         if let Some(p) = peers.get(&peer_id) {
             std::net::SocketAddr::new(p.ip, p.port)
+        } else if deduplicated {
+             // Already satisfied locally; the sender just won't hear about it
+             return Ok(());
         } else {
-             return Err(format!("Peer {} not found or offline", peer_id));
+             return Err(AppError::Network(format!("Peer {} not found or offline", peer_id)));
         }
     };
     
     // 2. Get Transport
     let transport = {
         let t_lock = state.transport.lock().unwrap();
-        t_lock.clone().ok_or("Transport not initialized".to_string())?
+        t_lock.clone().ok_or_else(|| AppError::Network("Transport not initialized".to_string()))?
     };
     
     // 3. Encrypt & Send Request
-    let req_payload = crate::protocol::FileRequestPayload {
+    let mut req_payload = crate::protocol::FileRequestPayload {
         id: file_id,
         file_index,
         offset: 0,
+        accept_compression: true,
+        requester_id: String::new(),
+        requested_at: 0,
+        auth: None,
     };
+    crate::senderauth::sign_file_request(state, &mut req_payload);
     
     let key_opt = state.cluster_key.lock().unwrap().clone();
     if let Some(key) = key_opt {
         if key.len() == 32 {
             let mut key_arr = [0u8; 32];
             key_arr.copy_from_slice(&key);
+             if deduplicated {
+                 send_have_already(&transport, &key_arr, addr, req_payload.id, file_index).await;
+                 return Ok(());
+             }
              if let Ok(req_json) = serde_json::to_vec(&req_payload) {
                 if let Ok(req_cipher) = crypto::encrypt(&key_arr, &req_json).map_err(|e| e.to_string()) {
                     let msg = Message::FileRequest(req_cipher);
                     if let Ok(data) = serde_json::to_vec(&msg) {
-                        transport.send_message(addr, &data).await.map_err(|e| e.to_string())?;
+                        transport.send_message(addr, &data).await.map_err(|e| AppError::Network(e.to_string()))?;
                         tracing::info!("File Request sent to {}", addr);
                         return Ok(());
                     }
@@ -3238,7 +6081,7 @@ pub async fn request_file_internal(
         }
     }
     
-    Err("Failed to encrypt/send request".to_string())
+    Err(AppError::Crypto("Failed to encrypt/send request".to_string()))
 }
 
 fn register_shortcuts(app_handle: &tauri::AppHandle) {
@@ -3250,8 +6093,8 @@ fn register_shortcuts(app_handle: &tauri::AppHandle) {
         tracing::warn!("Failed to unregister shortcuts: {}", e);
     }
     
-    // Register Send Shortcut
-    if !settings.auto_send {
+    // Register Send Shortcut (also confirms content held for review)
+    if !settings.auto_send || settings.review_before_send {
         if let Some(s) = &settings.shortcut_send {
             match Shortcut::from_str(s) {
                 Ok(shortcut) => {
@@ -3281,6 +6124,125 @@ fn register_shortcuts(app_handle: &tauri::AppHandle) {
             }
         }
     }
+
+    // Register Channel Send Shortcuts (explicit sends, so independent of auto-send)
+    for (channel, s) in &settings.channel_shortcuts {
+        match Shortcut::from_str(s) {
+            Ok(shortcut) => {
+                if let Err(e) = app_handle.global_shortcut().register(shortcut) {
+                    tracing::error!("Failed to register shortcut '{}' for channel '{}': {}", s, channel, e);
+                } else {
+                    tracing::debug!("Registered shortcut {} for channel '{}'", s, channel);
+                }
+            }
+            Err(e) => tracing::error!("Invalid shortcut '{}' for channel '{}': {}", s, channel, e),
+        }
+    }
+}
+
+// Broadcast the local clipboard to `channel` (global Send and per-channel shortcuts)
+fn send_clipboard_shortcut(app_handle: &tauri::AppHandle, settings: &crate::storage::AppSettings, channel: String) {
+    let state = app_handle.state::<AppState>();
+    if state.is_reset_pending() {
+        tracing::warn!("Reset pending. Ignoring send shortcut.");
+        return;
+    }
+    if !crate::protocol::is_valid_channel(&channel) {
+        tracing::error!("Invalid clipboard channel '{}'. Ignoring send shortcut.", channel);
+        return;
+    }
+    // Trigger Send Logic
+    // Get local content
+    match app_handle.state::<Clipboard>().read_text() {
+        Ok(text) => {
+            let hostname = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or("Unknown".to_string());
+            let msg_id = uuid::Uuid::new_v4().to_string();
+            let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+
+            let local_id = state.local_device_id.lock().unwrap().clone();
+            let mut payload_obj = crate::protocol::ClipboardPayload {
+                id: msg_id.clone(),
+                text: text.clone(),
+                timestamp: ts,
+                sender: hostname,
+                sender_id: local_id,
+                files: None,
+                // Shortcut fires while the source app still has focus
+                source: if settings.share_source_app { crate::source::active_window() } else { None },
+                trace: crate::latency::start_trace(&state),
+                channel,
+                // Runs on the event loop: no link preview fetch here
+                link: None,
+                content: None,
+                auth: None,
+            };
+            crate::senderauth::sign(&state, &mut payload_obj);
+
+            // Emit local event
+            crate::history::commit(app_handle, &payload_obj);
+
+            // Encrypt & Send
+            let ck_lock = state.cluster_key.lock().unwrap();
+            if let Some(key) = ck_lock.as_ref() {
+                if key.len() == 32 {
+                    let mut key_arr = [0u8; 32];
+                    key_arr.copy_from_slice(key);
+                    if let Ok(json_payload) = serde_json::to_vec(&payload_obj) {
+                        if let Ok(cipher) = crypto::encrypt(&key_arr, &json_payload) {
+                            let msg = Message::Clipboard(cipher);
+                            if let Ok(data) = serde_json::to_vec(&msg) {
+                                let transport = app_handle.state::<Transport>();
+                                let peers = state.get_peers();
+                                for p in peers.values() {
+                                    let addr = std::net::SocketAddr::new(p.ip, p.port);
+                                    let transport_clone = (*transport).clone();
+                                    let Some(data_vec) = clipboard::data_for_peer(&state, &key_arr, p, &json_payload, &data) else { continue; };
+                                    let stats_state = (*state).clone();
+                                    let peer_id = p.id.clone();
+                                    tauri::async_runtime::spawn(async move {
+                                        if transport_clone.send_message(addr, &data_vec).await.is_ok() {
+                                            crate::peerstats::record(&stats_state, &peer_id, crate::peerstats::Direction::Sent, crate::peerstats::Kind::Item, data_vec.len() as u64);
+                                        }
+                                    });
+                                }
+
+                                // Notification
+                                let notif_settings = settings.notifications.clone();
+                                if notif_settings.data_sent {
+                                    send_notification(app_handle, &i18n::t("notify.clipboard_sent.title"), &i18n::t("notify.clipboard_sent.manual_body"), false, Some(2), "history", NotificationPayload::None);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        Err(e) => tracing::error!("Failed to read clipboard for global send: {}", e),
+    }
+}
+
+/// Bring the main window back, whether minimized or hidden in the tray (including at
+/// startup): tray, notifications, deep links, D-Bus.
+pub(crate) fn show_main_window(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        crate::unread::clear(app_handle);
+    }
+}
+
+// Send the clipboard now (send shortcut, D-Bus SendClipboard), or what's held for review
+fn send_clipboard_now(app_handle: &tauri::AppHandle, settings: &crate::storage::AppSettings) {
+    if settings.auto_send && settings.review_before_send {
+        let state = app_handle.state::<AppState>();
+        let transport = app_handle.state::<Transport>();
+        if !clipboard::confirm_outgoing(app_handle, &state, &transport) {
+            tracing::info!("No clipboard content waiting for review.");
+        }
+        return;
+    }
+    send_clipboard_shortcut(app_handle, settings, settings.send_channel.clone());
 }
 
 fn handle_shortcut(app_handle: &tauri::AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
@@ -3288,6 +6250,7 @@ fn handle_shortcut(app_handle: &tauri::AppHandle, shortcut: &Shortcut, event: Sh
         return;
     }
     let state = app_handle.state::<AppState>();
+    state.power.touch();
     let settings = state.settings.lock().unwrap().clone();
     
     // Check Send
@@ -3295,85 +6258,33 @@ fn handle_shortcut(app_handle: &tauri::AppHandle, shortcut: &Shortcut, event: Sh
         if let Ok(parsed) = Shortcut::from_str(s) {
            if parsed == *shortcut {
                tracing::info!("Global Send Shortcut Triggered!");
-               // Trigger Send Logic
-               // Get local content
-               match app_handle.state::<Clipboard>().read_text() {
-                   Ok(text) => {
-                        let hostname = hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or("Unknown".to_string());
-                        let msg_id = uuid::Uuid::new_v4().to_string();
-                        let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
-
-                            let local_id = state.local_device_id.lock().unwrap().clone();
-                            let payload_obj = crate::protocol::ClipboardPayload {
-                                id: msg_id.clone(),
-                                text: text.clone(),
-                                timestamp: ts,
-                                sender: hostname,
-                                sender_id: local_id,
-                                files: None,
-                            };
-                        
-                        // Emit local event
-                        let _ = app_handle.emit("clipboard-change", &payload_obj);
-
-                        // Encrypt & Send
-                        let ck_lock = state.cluster_key.lock().unwrap();
-                        if let Some(key) = ck_lock.as_ref() {
-                            if key.len() == 32 {
-                                let mut key_arr = [0u8; 32];
-                                key_arr.copy_from_slice(key);
-                                if let Ok(json_payload) = serde_json::to_vec(&payload_obj) {
-                                    if let Ok(cipher) = crypto::encrypt(&key_arr, &json_payload) {
-                                        let msg = Message::Clipboard(cipher);
-                                        if let Ok(data) = serde_json::to_vec(&msg) {
-                                            let transport = app_handle.state::<Transport>();
-                                            let peers = state.get_peers();
-                                            for p in peers.values() {
-                                                let addr = std::net::SocketAddr::new(p.ip, p.port);
-                                                let transport_clone = (*transport).clone();
-                                                let data_vec = data.clone();
-                                                tauri::async_runtime::spawn(async move {
-                                                    let _ = transport_clone.send_message(addr, &data_vec).await;
-                                                });
-                                            }
-                                            
-                                            // Notification
-                                            let notif_settings = settings.notifications.clone();
-                                            if notif_settings.data_sent {
-                                                send_notification(app_handle, "Clipboard Sent", "Manual broadcast successful.", false, Some(2), "history", NotificationPayload::None);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                   },
-                   Err(e) => tracing::error!("Failed to read clipboard for global send: {}", e),
-               }
+               send_clipboard_now(app_handle, &settings);
                return;
            }
         }
     }
+
+    // Check Channel Sends
+    for (channel, s) in &settings.channel_shortcuts {
+        if Shortcut::from_str(s).is_ok_and(|parsed| parsed == *shortcut) {
+            tracing::info!("Global Send Shortcut Triggered for channel '{}'", channel);
+            send_clipboard_shortcut(app_handle, &settings, channel.clone());
+            return;
+        }
+    }
     
     // Check Receive
     if let Some(s) = &settings.shortcut_receive {
         if let Ok(parsed) = Shortcut::from_str(s) {
            if parsed == *shortcut {
                 tracing::info!("Global Receive Shortcut Triggered!");
-                // Manual Receive Logic
-                let mut guard = state.pending_clipboard.lock().unwrap();
-                if let Some(payload) = guard.take() { // take() removes it from pending
-                    // Apply to System Clipboard
-                    // Using clipboard plugin
-                    if let Err(e) = app_handle.state::<Clipboard>().write_text(payload.text) {
-                        tracing::error!("Failed to write pending clipboard to system: {}", e);
-                    } else {
-                        tracing::info!("Confirmed pending clipboard content via shortcut.");
-                        send_notification(app_handle, "Clipboard Received", "Pending content applied.", false, Some(2), "history", NotificationPayload::None);
-                    }
+                // Manual Receive Logic: the most recent pending item
+                if clipboard::apply_pending(app_handle, &state, None).is_some() {
+                    tracing::info!("Confirmed pending clipboard content via shortcut.");
+                    send_notification(app_handle, &i18n::t("notify.clipboard_received.title"), &i18n::t("notify.clipboard_received.pending_body"), false, Some(2), "history", NotificationPayload::None);
                 } else {
                     tracing::info!("No pending clipboard content to receive.");
-                     send_notification(app_handle, "Manual Receive", "No pending content.", false, Some(3), "history", NotificationPayload::None);
+                     send_notification(app_handle, &i18n::t("notify.manual_receive.title"), &i18n::t("notify.manual_receive.body"), false, Some(3), "history", NotificationPayload::None);
                 }
            }
         }
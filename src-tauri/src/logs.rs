@@ -0,0 +1,143 @@
+//! Log files: where they go, how many are kept, and clearing them.
+//!
+//! Logging starts before there is an AppHandle, so the file layer writes through `Writer`,
+//! which forwards to whichever appender is current. Until setup resolves the app's log
+//! directory that is a bootstrap appender in the temp dir; `configure` then moves logging to
+//! `app_log_dir` with daily files, keeping the newest `log_retention_days`. Earlier versions
+//! only ever logged to the temp dir, so that directory is pruned the same way.
+
+use once_cell::sync::Lazy;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use tauri::{AppHandle, Manager};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+const FILE_PREFIX: &str = "clustercut.log";
+
+static APPENDER: Lazy<Mutex<Option<RollingFileAppender>>> = Lazy::new(|| Mutex::new(None));
+static DIR: Lazy<RwLock<PathBuf>> = Lazy::new(|| RwLock::new(bootstrap_dir()));
+
+// Used until setup (and by CLI commands that exit before it)
+fn bootstrap_dir() -> PathBuf {
+    std::env::temp_dir().join("ClusterCutLogs")
+}
+
+fn appender(dir: &Path, max_files: usize) -> Option<RollingFileAppender> {
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .max_log_files(max_files.max(1))
+        .build(dir)
+        .ok()
+}
+
+/// `MakeWriter` for the file layer; writes go to the current appender.
+pub struct Writer;
+
+pub struct LogFile;
+
+impl io::Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match APPENDER.lock().unwrap().as_mut() {
+            Some(appender) => appender.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match APPENDER.lock().unwrap().as_mut() {
+            Some(appender) => appender.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for Writer {
+    type Writer = LogFile;
+
+    fn make_writer(&'a self) -> LogFile {
+        LogFile
+    }
+}
+
+/// Start logging to the bootstrap directory.
+pub fn init() -> Writer {
+    *APPENDER.lock().unwrap() = appender(&bootstrap_dir(), usize::MAX);
+    Writer
+}
+
+/// The directory log files currently go to.
+pub fn dir() -> PathBuf {
+    DIR.read().unwrap().clone()
+}
+
+/// Log to the app's log directory, keeping `retention_days` files.
+pub fn configure(app: &AppHandle, retention_days: u32) {
+    let Ok(log_dir) = app.path().app_log_dir() else {
+        tracing::warn!("[Logs] No app log directory; staying in {:?}", dir());
+        return;
+    };
+    let Some(next) = appender(&log_dir, retention_days as usize) else {
+        tracing::warn!("[Logs] Can't log to {:?}; staying in {:?}", log_dir, dir());
+        return;
+    };
+    let moved = dir() != log_dir;
+    *APPENDER.lock().unwrap() = Some(next);
+    *DIR.write().unwrap() = log_dir.clone();
+    if moved {
+        tracing::info!("[Logs] Logging to {:?}, keeping {} days", log_dir, retention_days);
+    }
+    prune(&bootstrap_dir(), retention_days as usize);
+}
+
+// Keep the newest `keep` log files in `dir`
+fn prune(dir: &Path, keep: usize) {
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(FILE_PREFIX))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    files.sort_by_key(|f| std::cmp::Reverse(f.0));
+    for (_, path) in files.into_iter().skip(keep.max(1)) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// Remove every log file in `dir`
+fn remove_all(dir: &Path) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(FILE_PREFIX) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Delete every log file, including ones older versions left in the temp dir, then start a
+/// fresh one.
+pub fn clear(retention_days: u32) -> Result<(), String> {
+    let dir = dir();
+    let result = {
+        // Hold the lock so nothing is written while files are removed
+        let mut current = APPENDER.lock().unwrap();
+        *current = None;
+        let result = remove_all(&dir);
+        *current = appender(&dir, retention_days as usize);
+        result
+    };
+    result.map_err(|e| format!("Failed to clear logs in {:?}: {}", dir, e))?;
+
+    let legacy = bootstrap_dir();
+    if legacy != dir {
+        match remove_all(&legacy) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to clear logs in {:?}: {}", legacy, e)),
+        }
+    }
+    tracing::info!("[Logs] Cleared log files in {:?}", dir);
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Versioned storage schema.
+//!
+//! Each profile records the schema version its files were written with (`schema_version`).
+//! On load, pending migrations run in order (vN -> vN+1) after the original files are copied
+//! to `migration_backup/v<N>/`. Anything that still doesn't deserialize is recovered field by
+//! field in storage.rs rather than replaced with defaults, and ends up in the report.
+
+use crate::state::AppState;
+use tauri::AppHandle;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Files covered by migrations (and copied to the pre-migration backup)
+const VERSIONED_FILES: [&str; 5] = [
+    "settings.json",
+    "known_peers.json",
+    "peer_policies.json",
+    "blocked_peers.json",
+    "history_annotations.json",
+];
+
+struct Migration {
+    // Version this step produces (runs on files at `to - 1`)
+    to: u32,
+    description: &'static str,
+    apply: fn(&AppHandle) -> Result<(), String>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 1,
+    description: "Adopt versioned storage (no format changes)",
+    apply: |_| Ok(()),
+}];
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    // Descriptions of the migrations that ran
+    pub applied: Vec<String>,
+    // Fields/entries that couldn't be read and were left out (originals kept as <file>.rejected)
+    pub recovered: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl MigrationReport {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.recovered.is_empty() && self.error.is_none()
+    }
+}
+
+/// Bring the active profile's files up to SCHEMA_VERSION. Call before loading anything.
+pub fn run(app: &AppHandle) -> MigrationReport {
+    let stored = crate::storage::load_schema_version(app);
+    let mut report = MigrationReport { from_version: stored.unwrap_or(0), to_version: SCHEMA_VERSION, ..Default::default() };
+
+    let Some(from) = stored else {
+        // No version file: a fresh profile (nothing to migrate) or one written before versioning (v0)
+        if !VERSIONED_FILES.iter().any(|f| crate::storage::config_file_exists(app, f)) {
+            crate::storage::save_schema_version(app, SCHEMA_VERSION);
+            report.from_version = SCHEMA_VERSION;
+            return report;
+        }
+        return migrate(app, 0, report);
+    };
+
+    if from > SCHEMA_VERSION {
+        // Written by a newer build: leave the files alone, unknown fields are ignored on load
+        tracing::warn!("Storage schema v{} is newer than this build (v{}). Not migrating.", from, SCHEMA_VERSION);
+        report.to_version = from;
+        report.error = Some(format!("Storage was written by a newer version (schema v{})", from));
+        return report;
+    }
+    migrate(app, from, report)
+}
+
+fn migrate(app: &AppHandle, from: u32, mut report: MigrationReport) -> MigrationReport {
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.to > from).collect();
+    if pending.is_empty() {
+        return report;
+    }
+
+    if let Err(e) = crate::storage::backup_config_files(app, &format!("migration_backup/v{}", from), &VERSIONED_FILES) {
+        // Don't touch anything we couldn't back up
+        tracing::error!("Failed to back up storage before migration: {}", e);
+        report.error = Some(format!("Backup before migration failed: {}", e));
+        return report;
+    }
+
+    let mut version = from;
+    for m in pending {
+        tracing::info!("Migrating storage v{} -> v{}: {}", version, m.to, m.description);
+        if let Err(e) = (m.apply)(app) {
+            tracing::error!("Storage migration to v{} failed: {}", m.to, e);
+            report.error = Some(format!("Migration to v{} failed: {}", m.to, e));
+            break;
+        }
+        version = m.to;
+        crate::storage::save_schema_version(app, version);
+        report.applied.push(format!("v{} -> v{}: {}", m.to - 1, m.to, m.description));
+    }
+    report.to_version = version;
+    report
+}
+
+/// Attach anything the loaders had to recover, then publish the report. Call after loading.
+pub fn finish(app: &AppHandle, state: &AppState, mut report: MigrationReport) {
+    report.recovered = crate::storage::take_load_warnings();
+    if !report.recovered.is_empty() {
+        crate::send_notification(
+            app,
+            &crate::i18n::t("notify.settings_unreadable.title"),
+            &crate::i18n::t("notify.settings_unreadable.body"),
+            false,
+            None,
+            "settings",
+            crate::NotificationPayload::None,
+        );
+    }
+    if !report.is_empty() {
+        crate::events::emit(app, &report);
+    }
+    *state.migration_report.lock().unwrap() = Some(report);
+}
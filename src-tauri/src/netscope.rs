@@ -0,0 +1,132 @@
+//! Restricting ClusterCut to chosen network interfaces/subnets (e.g. keep it off a VPN tunnel).
+//!
+//! With `allowed_interfaces` / `allowed_subnets` empty nothing is restricted. Otherwise the
+//! QUIC endpoint is bound to an allowed local address, mDNS only runs on allowed interfaces,
+//! and peers (discovered or manual) outside the allowed networks are ignored.
+
+use crate::state::AppState;
+use crate::storage::AppSettings;
+use ipnetwork::IpNetwork;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tauri::AppHandle;
+
+pub struct Scope {
+    // Networks peers may be in: the configured subnets plus those of allowed interfaces
+    networks: Vec<IpNetwork>,
+    // Local (interface name, address) pairs we may use
+    local: Vec<(String, IpAddr)>,
+}
+
+impl Scope {
+    /// None when no restriction is configured.
+    pub fn from_settings(settings: &AppSettings) -> Option<Self> {
+        if settings.allowed_interfaces.is_empty() && settings.allowed_subnets.is_empty() {
+            return None;
+        }
+        let mut networks: Vec<IpNetwork> = settings
+            .allowed_subnets
+            .iter()
+            .filter_map(|s| match s.trim().parse() {
+                Ok(net) => Some(net),
+                Err(e) => {
+                    tracing::warn!("[NetScope] Ignoring invalid subnet '{}': {}", s, e);
+                    None
+                }
+            })
+            .collect();
+
+        let mut local = Vec::new();
+        for iface in if_addrs::get_if_addrs().unwrap_or_default() {
+            if iface.is_loopback() {
+                continue;
+            }
+            let ip = iface.ip();
+            let by_name = settings.allowed_interfaces.iter().any(|n| n.trim() == iface.name);
+            if by_name {
+                let netmask = match &iface.addr {
+                    if_addrs::IfAddr::V4(a) => IpAddr::V4(a.netmask),
+                    if_addrs::IfAddr::V6(a) => IpAddr::V6(a.netmask),
+                };
+                if let Ok(net) = IpNetwork::with_netmask(ip, netmask) {
+                    networks.push(net);
+                }
+            }
+            if by_name || networks.iter().any(|n| n.contains(ip)) {
+                local.push((iface.name.clone(), ip));
+            }
+        }
+        Some(Self { networks, local })
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        ip.is_loopback() || self.networks.iter().any(|n| n.contains(ip))
+    }
+
+    /// Address to bind the endpoint to. Loopback if no allowed interface is up, so nothing leaks.
+    pub fn bind_ip(&self) -> IpAddr {
+        self.local
+            .iter()
+            .map(|(_, ip)| *ip)
+            .find(IpAddr::is_ipv4)
+            .or_else(|| self.local.first().map(|(_, ip)| *ip))
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    pub fn interfaces(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.local.iter().map(|(n, _)| n.clone()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Whether a peer at `ip` is within the allowed networks (always true when unrestricted).
+pub fn allows_peer(state: &AppState, ip: IpAddr) -> bool {
+    let settings = state.settings.lock().unwrap().clone();
+    Scope::from_settings(&settings).is_none_or(|s| s.allows(ip))
+}
+
+/// Apply the current settings: rebind the endpoint, restrict mDNS and drop out-of-scope peers.
+pub fn apply(app: &AppHandle, state: &AppState) {
+    let settings = state.settings.lock().unwrap().clone();
+    let scope = Scope::from_settings(&settings);
+
+    let Some(transport) = state.transport.lock().unwrap().clone() else {
+        return;
+    };
+    let Ok(current) = transport.local_addr() else {
+        return;
+    };
+    let bind_ip = scope.as_ref().map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), Scope::bind_ip);
+    if current.ip() != bind_ip {
+        match transport.rebind_addr(SocketAddr::new(bind_ip, current.port())) {
+            Ok(()) => tracing::info!("[NetScope] Transport bound to {}", bind_ip),
+            Err(e) => tracing::error!("[NetScope] Failed to bind transport to {}: {}", bind_ip, e),
+        }
+    }
+
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    let network_name = state.network_name.lock().unwrap().clone();
+    if let Some(discovery) = state.discovery.lock().unwrap().as_mut().filter(|d| scope.is_some() || d.is_restricted()) {
+        let interfaces = scope.as_ref().map(Scope::interfaces);
+        let advertise = scope.as_ref().map(Scope::bind_ip);
+        discovery.restrict_interfaces(interfaces, advertise);
+        // Re-announce so the advertised address matches the bound one
+        let _ = discovery.register(&device_id, &network_name, current.port());
+    }
+
+    let Some(scope) = scope else {
+        return;
+    };
+    let removed: Vec<String> = {
+        let mut peers = state.peers.lock().unwrap();
+        let removed = peers.values().filter(|p| !scope.allows(p.ip)).map(|p| p.id.clone()).collect::<Vec<_>>();
+        peers.retain(|_, p| scope.allows(p.ip));
+        removed
+    };
+    for id in removed {
+        tracing::info!("[NetScope] Dropping peer {} outside the allowed networks", id);
+        crate::presence::forget(state, &id);
+        crate::events::emit(app, crate::events::PeerRemoved { id: id.clone() });
+    }
+}
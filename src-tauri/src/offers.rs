@@ -0,0 +1,134 @@
+//! Files this device has offered to the cluster, by clipboard item id (`AppState::local_files`).
+//!
+//! An offer is served for `file_offer_ttl_hours` (0 keeps offers for as long as the app runs),
+//! and at most `max_file_offers` are kept, oldest dropped first. Requests for an offer that's
+//! gone get a `FileRequestError` reply instead of silence, and `file-offers-expired` lets the
+//! UI grey the items out. Expired offers keep their paths (up to `MAX_EXPIRED`) so history can
+//! still re-send them as a new offer.
+
+use crate::protocol::FileErrorReason;
+use crate::state::AppState;
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+// Expired offers remembered for re-sending and for telling requesters why
+const MAX_EXPIRED: usize = 1000;
+
+struct Offer {
+    paths: Vec<String>,
+    offered_at: u64,
+}
+
+#[derive(Default)]
+pub struct LocalFiles {
+    offers: HashMap<String, Offer>,
+    // Oldest first
+    expired: IndexMap<String, Vec<String>>,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+impl LocalFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paths of a served offer.
+    pub fn get(&self, id: &str) -> Option<&Vec<String>> {
+        self.offers.get(id).map(|o| &o.paths)
+    }
+
+    /// Paths of an offer, served or expired (for re-sending from history).
+    pub fn paths(&self, id: &str) -> Option<&Vec<String>> {
+        self.get(id).or_else(|| self.expired.get(id))
+    }
+
+    /// Path of one file of a served offer, or why it can't be served.
+    pub fn lookup(&self, id: &str, index: usize) -> Result<&str, FileErrorReason> {
+        match self.offers.get(id) {
+            Some(offer) => offer.paths.get(index).map(String::as_str).ok_or(FileErrorReason::NotFound),
+            None if self.expired.contains_key(id) => Err(FileErrorReason::Expired),
+            None => Err(FileErrorReason::NotFound),
+        }
+    }
+
+    /// Ids of the offers being served.
+    pub fn active(&self) -> Vec<String> {
+        self.offers.keys().cloned().collect()
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Vec<String>> {
+        let expired = self.expired.shift_remove(id);
+        self.offers.remove(id).map(|o| o.paths).or(expired)
+    }
+
+    pub fn clear(&mut self) {
+        self.offers.clear();
+        self.expired.clear();
+    }
+
+    fn insert(&mut self, id: String, paths: Vec<String>) {
+        self.expired.shift_remove(&id);
+        self.offers.insert(id, Offer { paths, offered_at: now() });
+    }
+
+    // Stop serving offers older than `ttl_secs` (0: no limit) and the oldest beyond `max`.
+    // Returns the ids that expired.
+    fn expire(&mut self, now: u64, ttl_secs: u64, max: usize) -> Vec<String> {
+        let mut by_age: Vec<(String, u64)> = self.offers.iter().map(|(id, o)| (id.clone(), o.offered_at)).collect();
+        by_age.sort_by_key(|(_, at)| std::cmp::Reverse(*at));
+
+        let mut ids = Vec::new();
+        for (kept, (id, offered_at)) in by_age.into_iter().enumerate() {
+            let too_old = ttl_secs > 0 && now.saturating_sub(offered_at) >= ttl_secs;
+            if too_old || kept >= max {
+                if let Some(offer) = self.offers.remove(&id) {
+                    self.expired.insert(id.clone(), offer.paths);
+                }
+                ids.push(id);
+            }
+        }
+        while self.expired.len() > MAX_EXPIRED {
+            self.expired.shift_remove_index(0);
+        }
+        ids
+    }
+}
+
+/// Start serving `paths` as the files of clipboard item `id`.
+pub fn register(app: &AppHandle, state: &AppState, id: String, paths: Vec<String>) {
+    state.local_files.lock().unwrap().insert(id, paths);
+    // Only the count limit can be hit here
+    sweep(app, state);
+}
+
+/// Expire offers past the TTL or the count limit and tell the UI.
+pub fn sweep(app: &AppHandle, state: &AppState) {
+    let (ttl_hours, max) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.file_offer_ttl_hours, settings.max_file_offers)
+    };
+    let ids = state.local_files.lock().unwrap().expire(now(), ttl_hours.saturating_mul(60 * 60), max.max(1));
+    if !ids.is_empty() {
+        tracing::info!("[Offers] {} file offer(s) expired", ids.len());
+        crate::events::emit(app, crate::events::FileOffersExpired { ids });
+    }
+}
+
+pub fn spawn_sweeper(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+            let state = app.state::<AppState>();
+            if state.is_shutdown() {
+                break;
+            }
+            sweep(&app, &state);
+        }
+    });
+}
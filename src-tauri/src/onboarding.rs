@@ -0,0 +1,81 @@
+//! Helpers for the first-run wizard (detect/create/join commands in lib.rs).
+
+use crate::peer::Peer;
+use crate::state::AppState;
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+/// How long join_cluster waits for each device to answer before trying the next one. Once a
+/// device reports that the owner has to approve us, it waits `handshake::ADMISSION_TTL`.
+pub const JOIN_ATTEMPT_TIMEOUT_SECS: u64 = 20;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DiscoveredDevice {
+    pub id: String,
+    pub hostname: String,
+    pub ip: std::net::IpAddr,
+}
+
+/// A network seen on the LAN, with the devices currently advertising it.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DiscoveredCluster {
+    pub network_name: String,
+    pub devices: Vec<DiscoveredDevice>,
+    // We're already a member
+    pub is_current: bool,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStage {
+    Scanning,
+    Creating,
+    Pairing,
+    Waiting,
+    Done,
+    Failed,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OnboardingProgress {
+    pub stage: OnboardingStage,
+    pub message: String,
+}
+
+pub fn progress(app: &AppHandle, stage: OnboardingStage, message: impl Into<String>) {
+    let message = message.into();
+    tracing::info!("[Onboarding] {:?}: {}", stage, message);
+    crate::events::emit(app, OnboardingProgress { stage, message });
+}
+
+/// Group discovered peers by the network they advertise.
+pub fn clusters(state: &AppState) -> Vec<DiscoveredCluster> {
+    let current = state.network_name.lock().unwrap().clone();
+    let mut by_network: HashMap<String, Vec<DiscoveredDevice>> = HashMap::new();
+    for p in state.get_peers().into_values() {
+        let Some(name) = p.network_name.clone().filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        by_network.entry(name).or_default().push(DiscoveredDevice { id: p.id, hostname: p.hostname, ip: p.ip });
+    }
+    let mut out: Vec<DiscoveredCluster> = by_network
+        .into_iter()
+        .map(|(network_name, mut devices)| {
+            devices.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+            DiscoveredCluster { is_current: network_name == current, network_name, devices }
+        })
+        .collect();
+    out.sort_by(|a, b| a.network_name.cmp(&b.network_name));
+    out
+}
+
+/// Devices advertising `network`, most recently seen first.
+pub fn join_candidates(state: &AppState, network: &str) -> Vec<Peer> {
+    let mut peers: Vec<Peer> = state
+        .get_peers()
+        .into_values()
+        .filter(|p| p.network_name.as_deref() == Some(network))
+        .collect();
+    peers.sort_by_key(|p| std::cmp::Reverse(p.last_seen));
+    peers
+}
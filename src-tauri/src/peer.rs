@@ -15,4 +15,163 @@ pub struct Peer {
     // Cluster Authentication Signature (Base64)
     #[serde(default)]
     pub signature: Option<String>,
+    // Ed25519 public key (Base64) the device signs with, see identity.rs (None: older version)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    // Guest membership: unix time after which every peer drops this device
+    #[serde(default)]
+    pub guest_expires_at: Option<u64>,
+    // What this device accepts (None = unknown/older version: everything)
+    #[serde(default)]
+    pub capabilities: Option<Capabilities>,
+    // OS, architecture and app/protocol version (None = older version that doesn't report it)
+    #[serde(default)]
+    pub platform: Option<crate::platform::PlatformInfo>,
+    // Other addresses this device can be reached at (Wi-Fi + Ethernet, old DHCP leases), see
+    // `candidates`. In PeerDiscovery: the sender's own interface addresses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<KnownAddress>,
+    // PeerDiscovery only: the address the sender last saw the *recipient* at (not persisted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_addr: Option<std::net::SocketAddr>,
+}
+
+/// An address a peer was seen or advertised at, and how well sending to it has gone.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct KnownAddress {
+    pub addr: std::net::SocketAddr,
+    // Unix time we last heard from the peer at this address (0 = only advertised)
+    #[serde(default)]
+    pub last_seen: u64,
+    // Unix time a send to this address last succeeded
+    #[serde(default)]
+    pub last_success: u64,
+    // Failed sends since the last success
+    #[serde(default)]
+    pub failures: u32,
+}
+
+impl KnownAddress {
+    pub fn new(addr: std::net::SocketAddr) -> Self {
+        Self { addr, last_seen: 0, last_success: 0, failures: 0 }
+    }
+
+    // Recency of contact, minus five minutes per consecutive failure
+    fn score(&self) -> i64 {
+        self.last_success.max(self.last_seen) as i64 - i64::from(self.failures) * 300
+    }
+}
+
+// Address book size per peer; the lowest-scored entries are dropped beyond this
+pub const MAX_ADDRESSES: usize = 8;
+
+impl Peer {
+    pub fn primary_addr(&self) -> std::net::SocketAddr {
+        std::net::SocketAddr::new(self.ip, self.port)
+    }
+
+    fn address_mut(&mut self, addr: std::net::SocketAddr) -> &mut KnownAddress {
+        let index = match self.addresses.iter().position(|a| a.addr == addr) {
+            Some(i) => i,
+            None => {
+                self.addresses.push(KnownAddress::new(addr));
+                self.addresses.len() - 1
+            }
+        };
+        &mut self.addresses[index]
+    }
+
+    fn trim_addresses(&mut self) {
+        let primary = self.primary_addr();
+        self.addresses.sort_by_key(|a| (a.addr != primary, std::cmp::Reverse(a.score())));
+        self.addresses.truncate(MAX_ADDRESSES);
+    }
+
+    /// We heard from the peer at `addr` (`now` = unix secs).
+    pub fn note_seen(&mut self, addr: std::net::SocketAddr, now: u64) {
+        self.address_mut(addr).last_seen = now;
+        self.trim_addresses();
+    }
+
+    /// Fold in another record of the same peer (what we knew before), keeping the better
+    /// history for addresses both have.
+    pub fn merge_addresses(&mut self, previous: &Peer) {
+        let mut all = previous.addresses.clone();
+        all.push(KnownAddress::new(previous.primary_addr()));
+        all.push(KnownAddress::new(self.primary_addr()));
+        for a in all.into_iter().filter(|a| !a.addr.ip().is_unspecified() && a.addr.port() != 0) {
+            let entry = self.address_mut(a.addr);
+            entry.last_seen = entry.last_seen.max(a.last_seen);
+            if a.last_success > entry.last_success {
+                entry.last_success = a.last_success;
+                entry.failures = a.failures;
+            }
+        }
+        self.trim_addresses();
+    }
+
+    /// Drop reachability history that came over the wire (it's the sender's, not ours).
+    pub fn reset_address_history(&mut self) {
+        for a in &mut self.addresses {
+            *a = KnownAddress::new(a.addr);
+        }
+        self.addresses.retain(|a| !a.addr.ip().is_unspecified() && a.addr.port() != 0);
+        self.addresses.truncate(MAX_ADDRESSES);
+    }
+
+    /// Where to send, best score first (the primary address wins ties and is tried first
+    /// when we have no history for it yet).
+    pub fn candidates(&self) -> Vec<std::net::SocketAddr> {
+        let primary = self.primary_addr();
+        let mut ranked: Vec<(i64, bool, std::net::SocketAddr)> =
+            self.addresses.iter().map(|a| (a.score(), a.addr != primary, a.addr)).collect();
+        if !ranked.iter().any(|(_, _, addr)| *addr == primary) {
+            ranked.push((i64::MAX, false, primary));
+        }
+        ranked.sort_by_key(|(score, not_primary, _)| (std::cmp::Reverse(*score), *not_primary));
+        ranked.into_iter().map(|(_, _, addr)| addr).collect()
+    }
+
+    /// Record a send to `addr`. A success there makes it the primary address.
+    pub fn record_send(&mut self, addr: std::net::SocketAddr, ok: bool, now: u64) {
+        let entry = self.address_mut(addr);
+        if ok {
+            entry.last_success = now;
+            entry.failures = 0;
+            self.ip = addr.ip();
+            self.port = addr.port();
+        } else {
+            entry.failures = entry.failures.saturating_add(1);
+        }
+        self.trim_addresses();
+    }
+}
+
+/// Content a device is willing to receive, advertised in PeerDiscovery.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Capabilities {
+    pub files: bool,
+    pub images: bool,
+    pub rich_text: bool,
+    // Largest clipboard text (bytes) it wants; None = no limit
+    pub max_payload: Option<u64>,
+    // Wants clipboard payloads sealed with AES-256-GCM (older versions: ChaCha20-Poly1305 only)
+    #[serde(default)]
+    pub aes_gcm: bool,
+    // Takes copied bitmaps and native file lists (CF_DIB/PNG, CF_HDROP); Windows only
+    #[serde(default)]
+    pub windows_formats: bool,
+}
+
+impl Capabilities {
+    pub fn from_settings(settings: &crate::storage::AppSettings) -> Self {
+        Self {
+            files: settings.enable_file_transfer,
+            images: settings.enable_file_transfer && settings.accept_images,
+            rich_text: false, // Plain text only for now
+            max_payload: (settings.max_incoming_payload_kb > 0).then(|| settings.max_incoming_payload_kb * 1024),
+            aes_gcm: crate::crypto::prefers_aes(settings.cipher_suite),
+            windows_formats: cfg!(target_os = "windows"),
+        }
+    }
 } // timestamp for pruning old peers
@@ -0,0 +1,207 @@
+//! Per-device details for the devices page (`get_peer_details`): lifetime transfer totals,
+//! the last item from the device, and what we know about its trust.
+//!
+//! Totals count what actually went over the wire: clipboard items by their encrypted size
+//! (counted once delivered, or once accepted on receipt) and file streams by the bytes
+//! streamed. They're kept in peer_stats.json, written at most every `FLUSH_INTERVAL`.
+
+use crate::protocol::ClipboardPayload;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+// Characters of text shown for the last item
+const PREVIEW_CHARS: usize = 80;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct PeerStats {
+    pub items_sent: u64,
+    pub items_received: u64,
+    pub files_sent: u64,
+    pub files_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    // Unix time of the last item delivered to / accepted from this device
+    pub last_sent_at: Option<u64>,
+    pub last_received_at: Option<u64>,
+}
+
+/// All devices' totals, and whether they changed since the last save.
+#[derive(Default)]
+pub struct StatsStore {
+    pub peers: HashMap<String, PeerStats>,
+    dirty: bool,
+}
+
+impl StatsStore {
+    pub fn new(peers: HashMap<String, PeerStats>) -> Self {
+        Self { peers, dirty: false }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Item,
+    File,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Count an item or file exchanged with `peer_id`.
+pub fn record(state: &AppState, peer_id: &str, direction: Direction, kind: Kind, bytes: u64) {
+    let mut store = state.peer_stats.lock().unwrap();
+    let stats = store.peers.entry(peer_id.to_string()).or_default();
+    let (count, total, last) = match direction {
+        Direction::Sent => (
+            if kind == Kind::Item { &mut stats.items_sent } else { &mut stats.files_sent },
+            &mut stats.bytes_sent,
+            &mut stats.last_sent_at,
+        ),
+        Direction::Received => (
+            if kind == Kind::Item { &mut stats.items_received } else { &mut stats.files_received },
+            &mut stats.bytes_received,
+            &mut stats.last_received_at,
+        ),
+    };
+    *count += 1;
+    *total = total.saturating_add(bytes);
+    if kind == Kind::Item {
+        *last = Some(now());
+    }
+    store.dirty = true;
+}
+
+/// The device we know at `ip`, for transfers that only come with an address.
+pub fn peer_at(state: &AppState, ip: std::net::IpAddr) -> Option<String> {
+    state
+        .peers
+        .lock()
+        .unwrap()
+        .values()
+        .find(|p| p.ip == ip || p.addresses.iter().any(|a| a.addr.ip() == ip))
+        .map(|p| p.id.clone())
+}
+
+/// Save the totals every `FLUSH_INTERVAL` when they've changed.
+pub fn spawn_flusher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            let state = app.state::<AppState>();
+            let snapshot = {
+                let mut store = state.peer_stats.lock().unwrap();
+                if !store.dirty {
+                    continue;
+                }
+                store.dirty = false;
+                store.peers.clone()
+            };
+            crate::storage::save_peer_stats(&app, &snapshot);
+        }
+    });
+}
+
+/// Short code derived from the cluster key and a device id. Two devices that share the key show
+/// the same code for the same device, so users can compare them to check who they're paired with.
+pub fn fingerprint(cluster_key: &[u8], device_id: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"clustercut-fingerprint-v1");
+    hasher.update(cluster_key);
+    hasher.update(device_id.as_bytes());
+    let digest = hex::encode_upper(&hasher.finalize()[..8]);
+    digest.as_bytes().chunks(4).map(|c| String::from_utf8_lossy(c).into_owned()).collect::<Vec<_>>().join("-")
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LastItem {
+    pub id: String,
+    pub timestamp: u64,
+    // Start of the text, or the file names
+    pub preview: String,
+    pub file_count: usize,
+}
+
+impl LastItem {
+    pub fn from_payload(item: &ClipboardPayload) -> Self {
+        let files = item.files.as_deref().unwrap_or_default();
+        let preview = if files.is_empty() {
+            item.text.chars().take(PREVIEW_CHARS).collect()
+        } else {
+            files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>().join(", ")
+        };
+        Self { id: item.id.clone(), timestamp: item.timestamp, preview, file_count: files.len() }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TrustInfo {
+    pub is_trusted: bool,
+    pub is_manual: bool,
+    pub is_owner: bool,
+    pub guest_expires_at: Option<u64>,
+    pub blocked: bool,
+    // Its last announcement carried a valid cluster signature
+    pub signature_valid: bool,
+    pub fingerprint: Option<String>,
+    // Ours, to compare against what the other device shows for itself
+    pub local_fingerprint: Option<String>,
+    pub permissions: crate::storage::PeerPermissions,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PeerDetails {
+    pub peer: crate::peer::Peer,
+    pub online: bool,
+    pub stats: PeerStats,
+    // Newest item from this device still in history
+    pub last_item: Option<LastItem>,
+    pub trust: TrustInfo,
+}
+
+/// Everything the devices page shows for one device (online or only known).
+pub fn details(state: &AppState, peer_id: &str) -> Result<PeerDetails, String> {
+    // Separate statements: known_peers is locked before peers elsewhere
+    let online = state.peers.lock().unwrap().get(peer_id).cloned();
+    let known = state.known_peers.lock().unwrap().get(peer_id).cloned();
+    let is_online = online.is_some();
+    let peer = online.or(known).ok_or_else(|| format!("Unknown device {}", peer_id))?;
+
+    let cluster_key = state.cluster_key.lock().unwrap().clone().filter(|k| k.len() == 32);
+    let signature_valid = match (&cluster_key, peer.signature.as_deref()) {
+        (Some(key), Some(signature)) => {
+            let mut key_arr = [0u8; 32];
+            key_arr.copy_from_slice(key);
+            crate::verify_signature(&key_arr, &peer.id, signature)
+        }
+        _ => false,
+    };
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let trust = TrustInfo {
+        is_trusted: peer.is_trusted,
+        is_manual: peer.is_manual,
+        is_owner: state.cluster_owner.lock().unwrap().as_deref() == Some(peer.id.as_str()),
+        guest_expires_at: peer.guest_expires_at,
+        blocked: state.is_blocked(&peer.id),
+        signature_valid,
+        fingerprint: cluster_key.as_ref().map(|k| fingerprint(k, &peer.id)),
+        local_fingerprint: cluster_key.as_ref().map(|k| fingerprint(k, &local_id)),
+        permissions: state.peer_permissions.lock().unwrap().get(&peer.id).copied().unwrap_or_default(),
+    };
+    let last_item = state.recent_items.lock().unwrap().iter().find(|p| p.sender_id == peer.id).map(LastItem::from_payload);
+    let stats = state.peer_stats.lock().unwrap().peers.get(&peer.id).cloned().unwrap_or_default();
+
+    Ok(PeerDetails { peer, online: is_online, stats, last_item, trust })
+}
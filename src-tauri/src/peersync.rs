@@ -0,0 +1,199 @@
+//! Anti-entropy for cluster membership.
+//!
+//! A joining device gets the member list once, in Welcome; members added later reach it through
+//! MembershipAnnounce and PeerDiscovery gossip, which a lossy link or a device that was away can
+//! miss. So every `DIGEST_INTERVAL` each member sends one random online member a
+//! `PeerListDigest` (a hash of the member ids it knows, itself included). A member whose digest
+//! differs answers with `PeerListRequest` carrying its own list; the first side adds what it
+//! was missing and replies with its list in `PeerList`, so both converge in one round trip.
+//!
+//! The exchange only ever adds members. Removals travel as PeerRemoval, and recently removed
+//! ids (`AppState::removed_members`, saved to `removed_members.json` so they survive a
+//! restart) are kept out for `REMOVED_MEMORY_SECS`, so a member that missed a kick can't bring
+//! the device back. Guests take no part: they hold a derived key and aren't members.
+
+use crate::peer::Peer;
+use crate::protocol::{Message, PeerList, PeerListDigest};
+use crate::state::AppState;
+use crate::transport::Transport;
+use rand::seq::IteratorRandom;
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const DIGEST_INTERVAL: Duration = Duration::from_secs(60);
+// How long a removed member stays out of exchanged lists
+const REMOVED_MEMORY_SECS: u64 = 7 * 24 * 60 * 60;
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn is_member(peer: &Peer) -> bool {
+    peer.is_trusted && peer.guest_expires_at.is_none()
+}
+
+// Members we know of, ourselves excluded
+fn members(state: &AppState) -> Vec<Peer> {
+    state.known_peers.lock().unwrap().values().filter(|p| is_member(p)).cloned().collect()
+}
+
+fn digest(state: &AppState) -> String {
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let mut ids: Vec<String> = members(state).into_iter().map(|p| p.id).collect();
+    ids.push(local_id);
+    ids.sort();
+    ids.dedup();
+    let mut hasher = Sha256::new();
+    for id in &ids {
+        hasher.update(id.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Keep `device_id` out of member lists from other devices for a while (it was just removed).
+pub fn record_removal(app: &AppHandle, state: &AppState, device_id: &str) {
+    let now = now();
+    let mut removed = state.removed_members.lock().unwrap();
+    removed.retain(|_, at| now.saturating_sub(*at) < REMOVED_MEMORY_SECS);
+    removed.insert(device_id.to_string(), now);
+    crate::storage::save_removed_members(app, &removed);
+}
+
+/// Load this profile's recent removals, dropping the ones old enough to forget.
+pub fn load_removals(app: &AppHandle, state: &AppState) {
+    let now = now();
+    let mut removed = crate::storage::load_removed_members(app);
+    let before = removed.len();
+    removed.retain(|_, at| now.saturating_sub(*at) < REMOVED_MEMORY_SECS);
+    if removed.len() != before {
+        crate::storage::save_removed_members(app, &removed);
+    }
+    *state.removed_members.lock().unwrap() = removed;
+}
+
+fn recently_removed(state: &AppState, device_id: &str) -> bool {
+    state.removed_members.lock().unwrap().get(device_id).is_some_and(|at| now().saturating_sub(*at) < REMOVED_MEMORY_SECS)
+}
+
+// Whether `sender_id` is a member and the message really came from its address
+fn from_member(state: &AppState, sender_id: &str, addr: SocketAddr) -> bool {
+    state.peers.lock().unwrap().get(sender_id).is_some_and(|p| is_member(p) && p.ip == addr.ip())
+}
+
+async fn send(state: &AppState, transport: &Transport, addr: SocketAddr, wrap: fn(Vec<u8>) -> Message, value: &impl serde::Serialize) {
+    let Some(cipher) = crate::encrypt_cluster_message(state, value) else {
+        return;
+    };
+    if let Ok(data) = serde_json::to_vec(&wrap(cipher)) {
+        if let Err(e) = transport.send_message(addr, &data).await {
+            tracing::debug!("[PeerSync] Failed to reach {}: {}", addr, e);
+        }
+    }
+}
+
+fn own_list(state: &AppState) -> PeerList {
+    PeerList { sender_id: state.local_device_id.lock().unwrap().clone(), members: members(state) }
+}
+
+/// Send our digest to one random online member.
+pub async fn exchange(state: &AppState, transport: &Transport) {
+    // Guests and devices outside a cluster have no member list to reconcile
+    if state.guest_expires_at.lock().unwrap().is_some() || state.cluster_key.lock().unwrap().is_none() {
+        return;
+    }
+    let target = {
+        let peers = state.peers.lock().unwrap();
+        peers.values().filter(|p| is_member(p)).map(|p| SocketAddr::new(p.ip, p.port)).choose(&mut rand::thread_rng())
+    };
+    let Some(addr) = target else {
+        return;
+    };
+    let digest = PeerListDigest { sender_id: state.local_device_id.lock().unwrap().clone(), digest: digest(state) };
+    send(state, transport, addr, Message::PeerListDigest, &digest).await;
+}
+
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(DIGEST_INTERVAL).await;
+            let state = app.state::<AppState>();
+            if state.is_shutdown() {
+                break;
+            }
+            let Some(transport) = app.try_state::<Transport>() else {
+                continue;
+            };
+            exchange(&state, &transport).await;
+        }
+    });
+}
+
+/// A member's digest: if it differs from ours, send our list and ask for theirs.
+pub async fn on_digest(state: &AppState, transport: &Transport, addr: SocketAddr, theirs: PeerListDigest) {
+    if !from_member(state, &theirs.sender_id, addr) {
+        tracing::warn!("[PeerSync] Ignoring digest from {} (not a member)", addr);
+        return;
+    }
+    if theirs.digest == digest(state) {
+        return;
+    }
+    tracing::info!("[PeerSync] Member list differs from {}'s. Exchanging lists.", theirs.sender_id);
+    send(state, transport, addr, Message::PeerListRequest, &own_list(state)).await;
+}
+
+/// A member's list: add whoever we were missing, and answer with our own list if it asked.
+pub async fn on_list(app: &AppHandle, state: &AppState, transport: &Transport, addr: SocketAddr, list: PeerList, reply: bool) {
+    if !from_member(state, &list.sender_id, addr) {
+        tracing::warn!("[PeerSync] Ignoring member list from {} (not a member)", addr);
+        return;
+    }
+    if let Err(e) = crate::validation::peer_list(&list.members) {
+        tracing::warn!("[PeerSync] Rejected member list from {}: {}", addr, e);
+        state.record_rejected_message(&e);
+        return;
+    }
+    merge(app, state, &list.sender_id, list.members);
+    if reply {
+        send(state, transport, addr, Message::PeerList, &own_list(state)).await;
+    }
+}
+
+fn merge(app: &AppHandle, state: &AppState, sender_id: &str, members: Vec<Peer>) {
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let now = now();
+    let candidates: Vec<Peer> = members
+        .into_iter()
+        .filter(|m| {
+            m.id != local_id
+                && is_member(m)
+                && !state.is_blocked(&m.id)
+                && !recently_removed(state, &m.id)
+                && crate::netscope::allows_peer(state, m.ip)
+        })
+        .collect();
+    let mut added = Vec::new();
+    {
+        let mut known = state.known_peers.lock().unwrap();
+        for mut member in candidates {
+            if known.contains_key(&member.id) {
+                continue;
+            }
+            member.reset_address_history();
+            member.is_manual = false;
+            member.last_seen = now;
+            known.insert(member.id.clone(), member.clone());
+            added.push(member);
+        }
+        if !added.is_empty() {
+            crate::storage::save_known_peers(app, &known);
+        }
+    }
+    for member in added {
+        tracing::info!("[PeerSync] Learned about member {} from {}", member.id, sender_id);
+        let member = state.add_peer(member);
+        crate::events::emit(app, crate::events::PeerUpdate(&member));
+    }
+}
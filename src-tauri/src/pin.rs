@@ -0,0 +1,83 @@
+//! Pairing PINs: the network PIN (optionally rotated on a schedule) and one-time PINs.
+//!
+//! The PIN only authenticates a pairing (it's the SPAKE2 password); membership rests on the
+//! cluster key, so rotating it never affects devices that already joined. A one-time PIN
+//! is valid for a single pairing attempt, successful or not, which also rules out guessing.
+
+use crate::protocol::PairingError;
+use crate::state::AppState;
+use rand::Rng;
+use tauri::AppHandle;
+
+pub const DEFAULT_ONE_TIME_TTL_SECS: u64 = 300;
+const MAX_ONE_TIME_TTL_SECS: u64 = 3600;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OneTimePin {
+    pub pin: String,
+    pub expires_at: u64,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// One-time PINs look like `1234-5678`, which is how the joining side tells them apart.
+pub fn is_one_time_format(pin: &str) -> bool {
+    let bytes = pin.as_bytes();
+    bytes.len() == 9 && bytes[4] == b'-' && bytes.iter().enumerate().all(|(i, b)| i == 4 || b.is_ascii_digit())
+}
+
+/// Replace any outstanding one-time PIN with a new one valid for `ttl_secs`.
+pub fn generate_one_time(state: &AppState, ttl_secs: u64) -> OneTimePin {
+    let mut rng = rand::thread_rng();
+    let pin = format!("{:04}-{:04}", rng.gen_range(0..10_000), rng.gen_range(0..10_000));
+    let otp = OneTimePin { pin, expires_at: now() + ttl_secs.clamp(30, MAX_ONE_TIME_TTL_SECS) };
+    *state.one_time_pin.lock().unwrap() = Some(otp.clone());
+    otp
+}
+
+/// SPAKE2 password to answer a PairRequest with. One-time PINs are consumed here.
+///
+/// Guests can't admit anyone, and an empty PIN would let anyone on the LAN pair, so both are
+/// refused outright. The error carries the reason to send back with the rejection.
+pub fn responder_password(state: &AppState, one_time: bool) -> Result<String, (PairingError, String)> {
+    if state.guest_expires_at.lock().unwrap().is_some() {
+        return Err((PairingError::Rejected, "Guest devices can't admit new members".to_string()));
+    }
+    let pin = if one_time {
+        let otp = state.one_time_pin.lock().unwrap().take();
+        // A one-time PIN that's used up or expired is as good as a wrong one
+        match otp {
+            Some(otp) if otp.expires_at >= now() => otp.pin,
+            Some(_) => return Err((PairingError::WrongPin, "One-time PIN expired".to_string())),
+            None => return Err((PairingError::WrongPin, "No one-time PIN outstanding".to_string())),
+        }
+    } else {
+        if !state.settings.lock().unwrap().allow_network_pin_pairing {
+            return Err((PairingError::Rejected, "Pairing with the network PIN is disabled (one-time PINs only)".to_string()));
+        }
+        state.network_pin.lock().unwrap().clone()
+    };
+    if pin.is_empty() {
+        return Err((PairingError::Rejected, "No PIN is set on this device".to_string()));
+    }
+    Ok(pin)
+}
+
+/// Rotate the network PIN if the configured interval has passed since it last changed.
+pub fn rotate_if_due(app: &AppHandle, state: &AppState) {
+    let hours = state.settings.lock().unwrap().pin_rotation_hours;
+    if hours == 0 || state.guest_expires_at.lock().unwrap().is_some() {
+        return;
+    }
+    let due = crate::storage::network_pin_age(app).is_some_and(|age| age.as_secs() >= hours * 3600);
+    if !due {
+        return;
+    }
+    let pin = crate::storage::generate_network_pin();
+    crate::storage::save_network_pin(app, &pin);
+    *state.network_pin.lock().unwrap() = pin;
+    tracing::info!("Network PIN rotated (every {}h)", hours);
+    crate::events::emit(app, crate::events::NetworkPinChanged {});
+}
@@ -0,0 +1,90 @@
+//! What each device runs: OS, architecture, app and protocol version.
+//!
+//! Advertised in the mDNS TXT record (`os`, `osv`, `arch`, `version`, `proto`) and in the
+//! `platform` field of PeerDiscovery, so the devices view can show "macOS 15 · v0.4.2".
+//! Devices on a different `PROTOCOL_VERSION` may not understand each other; `check_skew` logs
+//! that once per peer and version. Older versions report nothing (`platform` is None).
+
+use crate::peer::Peer;
+use crate::protocol::PROTOCOL_VERSION;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlatformInfo {
+    // std::env::consts::OS: "macos", "windows", "linux", ...
+    pub os: String,
+    // e.g. "15.1" ("" when unknown)
+    #[serde(default)]
+    pub os_version: String,
+    pub arch: String,
+    pub app_version: String,
+    pub protocol: u32,
+}
+
+static LOCAL: Lazy<PlatformInfo> = Lazy::new(|| {
+    let info = os_info::get();
+    let os_version = match info.version() {
+        os_info::Version::Unknown => String::new(),
+        version => version.to_string(),
+    };
+    PlatformInfo {
+        os: std::env::consts::OS.to_string(),
+        os_version,
+        arch: std::env::consts::ARCH.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol: PROTOCOL_VERSION,
+    }
+});
+
+// Peer id + protocol version we've already warned about
+static WARNED: Lazy<Mutex<HashSet<(String, u32)>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+pub fn local() -> PlatformInfo {
+    LOCAL.clone()
+}
+
+/// TXT properties for our mDNS registration.
+pub fn txt_properties() -> Vec<(&'static str, String)> {
+    vec![
+        ("os", LOCAL.os.clone()),
+        ("osv", LOCAL.os_version.clone()),
+        ("arch", LOCAL.arch.clone()),
+        ("version", LOCAL.app_version.clone()),
+        ("proto", LOCAL.protocol.to_string()),
+    ]
+}
+
+/// Read a peer's platform from its TXT properties (None for older versions).
+pub fn from_txt<'a>(property: impl Fn(&str) -> Option<&'a str>) -> Option<PlatformInfo> {
+    Some(PlatformInfo {
+        os: property("os")?.to_string(),
+        os_version: property("osv").unwrap_or_default().to_string(),
+        arch: property("arch").unwrap_or_default().to_string(),
+        app_version: property("version").unwrap_or_default().to_string(),
+        protocol: property("proto")?.parse().ok()?,
+    })
+}
+
+/// Log once if `peer` speaks a different protocol version than we do.
+pub fn check_skew(peer: &Peer) {
+    let Some(platform) = &peer.platform else {
+        return;
+    };
+    if platform.protocol == PROTOCOL_VERSION || !WARNED.lock().unwrap().insert((peer.id.clone(), platform.protocol)) {
+        return;
+    }
+    let newer = if platform.protocol > PROTOCOL_VERSION { "newer" } else { "older" };
+    tracing::warn!(
+        "[Platform] {} ({}) runs v{} with {} protocol {} (ours: {}); update the {} device if syncing misbehaves",
+        peer.hostname,
+        peer.id,
+        platform.app_version,
+        newer,
+        platform.protocol,
+        PROTOCOL_VERSION,
+        if platform.protocol > PROTOCOL_VERSION { "this" } else { "other" }
+    );
+}
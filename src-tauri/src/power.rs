@@ -0,0 +1,312 @@
+//! Idle/battery power saving.
+//!
+//! When the device has been idle long enough (sooner on battery), heartbeats drop to a slow
+//! keepalive, the clipboard monitor polls less often and mDNS interface checks back off.
+//! Any clipboard change or incoming peer message wakes everything back up immediately.
+//!
+//! Outside low-power mode the clipboard monitor polls adaptively: every
+//! `ACTIVE_MONITOR_INTERVAL` for `ACTIVE_WINDOW` after activity (a clipboard change, a peer
+//! message, focusing the window or a shortcut), then at `monitor_interval_ms`.
+//!
+//! Independently of idle time, a metered connection or the OS battery saver counts as a
+//! constraint: auto-downloads above `constrained_download_limit` are deferred until the user
+//! asks for them, and (with power saving on) heartbeats go out at the keepalive interval.
+
+use crate::storage::AppSettings;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// How often the power source / idle state is re-evaluated.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// mdns-sd interface check interval (daemon default) and its low-power replacement
+const NORMAL_IP_CHECK_SECS: u32 = 5;
+const LOW_POWER_IP_CHECK_SECS: u32 = 120;
+// Peers prune us after 300s of silence by default; never let the keepalive get close to that
+const MAX_KEEPALIVE_SECS: u64 = 240;
+// Adaptive polling: how often, and for how long after activity
+const ACTIVE_MONITOR_INTERVAL: Duration = Duration::from_millis(150);
+const ACTIVE_WINDOW: Duration = Duration::from_secs(5);
+const MONITOR_INTERVAL_BOUNDS: (u64, u64) = (100, 10_000);
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct PowerStatus {
+    pub low_power: bool,
+    pub on_battery: bool,
+    pub metered: bool,
+    pub battery_saver: bool,
+    pub idle_secs: u64,
+}
+
+/// Why large downloads are being held back.
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Constraint {
+    Metered,
+    BatterySaver,
+}
+
+pub struct PowerMonitor {
+    last_activity: Mutex<Instant>,
+    last_heartbeat: Mutex<Option<Instant>>,
+    low_power: AtomicBool,
+    on_battery: AtomicBool,
+    metered: AtomicBool,
+    battery_saver: AtomicBool,
+    wake: Notify,
+}
+
+impl Default for PowerMonitor {
+    fn default() -> Self {
+        Self {
+            last_activity: Mutex::new(Instant::now()),
+            last_heartbeat: Mutex::new(None),
+            low_power: AtomicBool::new(false),
+            on_battery: AtomicBool::new(false),
+            metered: AtomicBool::new(false),
+            battery_saver: AtomicBool::new(false),
+            wake: Notify::new(),
+        }
+    }
+}
+
+impl PowerMonitor {
+    pub fn is_low_power(&self) -> bool {
+        self.low_power.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> PowerStatus {
+        PowerStatus {
+            low_power: self.is_low_power(),
+            on_battery: self.on_battery.load(Ordering::Relaxed),
+            metered: self.metered.load(Ordering::Relaxed),
+            battery_saver: self.battery_saver.load(Ordering::Relaxed),
+            idle_secs: self.last_activity.lock().unwrap().elapsed().as_secs(),
+        }
+    }
+
+    /// The current constraint, metered connection first.
+    pub fn constraint(&self) -> Option<Constraint> {
+        if self.metered.load(Ordering::Relaxed) {
+            Some(Constraint::Metered)
+        } else if self.battery_saver.load(Ordering::Relaxed) {
+            Some(Constraint::BatterySaver)
+        } else {
+            None
+        }
+    }
+
+    /// Whether an auto-download of `total_size` bytes should wait for the user, and why.
+    pub fn defer_download(&self, settings: &AppSettings, total_size: u64) -> Option<Constraint> {
+        if !settings.defer_downloads_when_constrained || total_size <= settings.constrained_download_limit {
+            return None;
+        }
+        self.constraint()
+    }
+
+    /// Record clipboard/peer activity. Leaves low-power mode immediately.
+    pub fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+        if self.low_power.swap(false, Ordering::Relaxed) {
+            tracing::info!("[Power] Activity detected. Resuming normal networking.");
+            self.wake.notify_waiters();
+        }
+    }
+
+    /// The machine just woke from sleep: count it as activity and cut any pending wait short,
+    /// so the heartbeat goes out now instead of after its interval.
+    pub fn resumed(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+        self.low_power.store(false, Ordering::Relaxed);
+        *self.last_heartbeat.lock().unwrap() = None;
+        self.wake.notify_waiters();
+    }
+
+    /// Re-check the power source and idle time, updating the mode. Returns whether we're now in low-power mode.
+    pub fn evaluate(&self, settings: &AppSettings) -> bool {
+        let on_battery = on_battery();
+        self.on_battery.store(on_battery, Ordering::Relaxed);
+        let metered = is_metered();
+        if self.metered.swap(metered, Ordering::Relaxed) != metered {
+            tracing::info!("[Power] Metered connection: {}", metered);
+        }
+        let saver = battery_saver();
+        if self.battery_saver.swap(saver, Ordering::Relaxed) != saver {
+            tracing::info!("[Power] Battery saver: {}", saver);
+        }
+
+        let idle = self.last_activity.lock().unwrap().elapsed().as_secs();
+        let threshold = if on_battery { settings.battery_idle_suspend_secs } else { settings.idle_suspend_secs };
+        // A threshold of 0 disables idle suspend for that power source
+        let low = settings.power_saving && threshold > 0 && idle >= threshold;
+
+        let was_low = self.low_power.swap(low, Ordering::Relaxed);
+        if low && !was_low {
+            tracing::info!("[Power] Idle for {}s (on battery: {}). Entering low-power mode.", idle, on_battery);
+        } else if !low && was_low {
+            self.wake.notify_waiters();
+        }
+        low
+    }
+
+    /// Sleep for `dur`, returning early if activity wakes us from low-power mode.
+    pub async fn wait(&self, dur: Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(dur) => {}
+            _ = self.wake.notified() => {}
+        }
+    }
+
+    /// Whether this heartbeat tick should be skipped. In low-power mode, or with power saving on
+    /// while constrained, only a slow keepalive goes out.
+    pub fn skip_heartbeat(&self, settings: &AppSettings) -> bool {
+        let mut last = self.last_heartbeat.lock().unwrap();
+        if self.is_low_power() || (settings.power_saving && self.constraint().is_some()) {
+            let keepalive = Duration::from_secs(settings.low_power_heartbeat_secs.clamp(5, MAX_KEEPALIVE_SECS));
+            if last.is_some_and(|t| t.elapsed() < keepalive) {
+                return true;
+            }
+        }
+        *last = Some(Instant::now());
+        false
+    }
+
+    /// Clipboard monitor poll interval for the current mode.
+    pub fn monitor_interval(&self, settings: &AppSettings) -> Duration {
+        if self.is_low_power() {
+            return Duration::from_millis(settings.low_power_monitor_interval_ms.max(500));
+        }
+        if settings.adaptive_polling && self.last_activity.lock().unwrap().elapsed() < ACTIVE_WINDOW {
+            return ACTIVE_MONITOR_INTERVAL;
+        }
+        Duration::from_millis(settings.monitor_interval_ms.clamp(MONITOR_INTERVAL_BOUNDS.0, MONITOR_INTERVAL_BOUNDS.1))
+    }
+
+    pub fn ip_check_interval_secs(&self) -> u32 {
+        if self.is_low_power() {
+            LOW_POWER_IP_CHECK_SECS
+        } else {
+            NORMAL_IP_CHECK_SECS
+        }
+    }
+}
+
+/// Whether the machine is currently running on battery. Desktops (no battery) report false.
+#[cfg(target_os = "linux")]
+pub fn on_battery() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    let mut has_battery = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let kind = std::fs::read_to_string(path.join("type")).unwrap_or_default();
+        match kind.trim() {
+            "Mains" | "USB" if std::fs::read_to_string(path.join("online")).is_ok_and(|s| s.trim() == "1") => {
+                return false;
+            }
+            // Skip peripheral batteries (mice, headsets), they report scope "Device"
+            "Battery" if std::fs::read_to_string(path.join("scope")).map_or(true, |s| s.trim() != "Device") => {
+                has_battery = true;
+            }
+            _ => {}
+        }
+    }
+    has_battery
+}
+
+#[cfg(target_os = "macos")]
+pub fn on_battery() -> bool {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains("'Battery Power'"))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn on_battery() -> bool {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // ACLineStatus: 0 = offline (battery), 1 = online, 255 = unknown
+    unsafe { GetSystemPowerStatus(&mut status) }.is_ok() && status.ACLineStatus == 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn on_battery() -> bool {
+    false
+}
+
+/// Whether the active connection is metered, as NetworkManager sees it.
+#[cfg(target_os = "linux")]
+pub fn is_metered() -> bool {
+    let metered = || -> zbus::Result<u32> {
+        let conn = zbus::blocking::Connection::system()?;
+        let proxy = zbus::blocking::Proxy::new(
+            &conn,
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+        )?;
+        proxy.get_property("Metered")
+    };
+    // NMMetered: 1 = yes, 3 = guessed yes (e.g. a phone hotspot)
+    matches!(metered(), Ok(1 | 3))
+}
+
+/// Whether the active connection is metered (Windows connection cost).
+#[cfg(target_os = "windows")]
+pub fn is_metered() -> bool {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+    let cost = || -> windows::core::Result<NetworkCostType> {
+        NetworkInformation::GetInternetConnectionProfile()?.GetConnectionCost()?.NetworkCostType()
+    };
+    matches!(cost(), Ok(NetworkCostType::Fixed | NetworkCostType::Variable))
+}
+
+// macOS has no system-wide metered flag
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn is_metered() -> bool {
+    false
+}
+
+/// Whether the OS battery saver is on (power-profiles-daemon's "power-saver" profile).
+#[cfg(target_os = "linux")]
+pub fn battery_saver() -> bool {
+    let profile = |dest: &str, path: &str| -> zbus::Result<String> {
+        let conn = zbus::blocking::Connection::system()?;
+        let proxy = zbus::blocking::Proxy::new(&conn, dest, path, dest)?;
+        proxy.get_property("ActiveProfile")
+    };
+    // The service was renamed in power-profiles-daemon 0.20; older releases only have the original
+    profile("org.freedesktop.UPower.PowerProfiles", "/org/freedesktop/UPower/PowerProfiles")
+        .or_else(|_| profile("net.hadess.PowerProfiles", "/net/hadess/PowerProfiles"))
+        .is_ok_and(|p| p == "power-saver")
+}
+
+#[cfg(target_os = "macos")]
+pub fn battery_saver() -> bool {
+    std::process::Command::new("pmset")
+        .arg("-g")
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .any(|l| l.split_whitespace().collect::<Vec<_>>() == ["lowpowermode", "1"])
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn battery_saver() -> bool {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // SystemStatusFlag: 1 = battery saver on
+    unsafe { GetSystemPowerStatus(&mut status) }.is_ok() && status.SystemStatusFlag == 1
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn battery_saver() -> bool {
+    false
+}
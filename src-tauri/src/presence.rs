@@ -0,0 +1,161 @@
+//! Peer presence and its tuning.
+//!
+//! A peer is online while its heartbeats arrive, away once it has missed a few, and offline
+//! after it's pruned (or its mDNS record goes away) while still being a known member. Changes
+//! are emitted as "peer-presence" so the UI can grey peers out instead of dropping them.
+//! Untrusted peers are simply forgotten when they go.
+//!
+//! Tuning (heartbeat interval, prune timeout, mDNS removal debounce) comes from settings and
+//! is read on every use, so changes apply without a restart. Out-of-range values are clamped.
+
+use crate::peer::Peer;
+use crate::state::AppState;
+use crate::storage::AppSettings;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::AppHandle;
+
+pub const DEFAULT_HEARTBEAT_SECS: u64 = 5;
+pub const DEFAULT_STALE_TIMEOUT_SECS: u64 = 300;
+pub const DEFAULT_REMOVAL_DEBOUNCE_SECS: u64 = 20;
+
+const HEARTBEAT_BOUNDS: (u64, u64) = (2, 60);
+const STALE_TIMEOUT_BOUNDS: (u64, u64) = (30, 3600);
+const REMOVAL_DEBOUNCE_BOUNDS: (u64, u64) = (2, 120);
+// A peer must be able to miss this many heartbeats before it counts as stale
+const MIN_MISSED_HEARTBEATS: u64 = 3;
+// ...and is shown as away once it has missed this many
+const AWAY_MISSED_HEARTBEATS: u64 = 3;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Presence {
+    Online,
+    Away,
+    Offline,
+}
+
+/// Payload of "peer-presence" and entries of `get_peer_presence`.
+#[derive(Serialize, Clone, Debug)]
+pub struct PeerPresence {
+    pub peer: Peer,
+    pub presence: Presence,
+    // When the peer entered this state (0 = unknown, e.g. known but not seen since startup)
+    pub since: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tuning {
+    pub heartbeat: Duration,
+    pub stale_timeout_secs: u64,
+    pub removal_debounce: Duration,
+}
+
+impl Tuning {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        let heartbeat = settings.heartbeat_interval_secs.clamp(HEARTBEAT_BOUNDS.0, HEARTBEAT_BOUNDS.1);
+        let min_stale = STALE_TIMEOUT_BOUNDS.0.max(heartbeat * MIN_MISSED_HEARTBEATS);
+        Self {
+            heartbeat: Duration::from_secs(heartbeat),
+            stale_timeout_secs: settings.stale_peer_timeout_secs.clamp(min_stale, STALE_TIMEOUT_BOUNDS.1),
+            removal_debounce: Duration::from_secs(settings.mdns_removal_debounce_secs.clamp(REMOVAL_DEBOUNCE_BOUNDS.0, REMOVAL_DEBOUNCE_BOUNDS.1)),
+        }
+    }
+}
+
+pub fn tuning(state: &AppState) -> Tuning {
+    Tuning::from_settings(&state.settings.lock().unwrap())
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Presence implied by how long ago we last heard from a peer.
+pub fn classify(last_seen: u64, now: u64, tuning: &Tuning) -> Presence {
+    let silent = now.saturating_sub(last_seen);
+    if silent > tuning.stale_timeout_secs {
+        Presence::Offline
+    } else if silent > tuning.heartbeat.as_secs() * AWAY_MISSED_HEARTBEATS {
+        Presence::Away
+    } else {
+        Presence::Online
+    }
+}
+
+/// Current presence of a peer: what we last recorded, else online if it's a runtime peer.
+pub fn of(state: &AppState, peer_id: &str) -> Presence {
+    if let Some((presence, _)) = state.presence.lock().unwrap().get(peer_id) {
+        return *presence;
+    }
+    if state.peers.lock().unwrap().contains_key(peer_id) {
+        Presence::Online
+    } else {
+        Presence::Offline
+    }
+}
+
+/// Record a peer's presence, emitting "peer-presence" if it changed.
+pub fn set(app: &AppHandle, state: &AppState, peer: &Peer, presence: Presence) {
+    let since = now();
+    let previous = {
+        let mut recorded = state.presence.lock().unwrap();
+        if recorded.get(&peer.id).is_some_and(|(p, _)| *p == presence) {
+            return;
+        }
+        recorded.insert(peer.id.clone(), (presence, since))
+    };
+    tracing::debug!("[Presence] {} ({}): {:?} -> {:?}", peer.hostname, peer.id, previous.map(|(p, _)| p), presence);
+    crate::events::emit(app, PeerPresence { peer: peer.clone(), presence, since });
+}
+
+/// A peer left the runtime list: known members go offline, anyone else is forgotten.
+pub fn departed(app: &AppHandle, state: &AppState, peer: &Peer) {
+    // Trusted runtime peers are the ones kept in known_peers (expired guests are dropped)
+    if peer.is_trusted && !crate::guest::is_expired(peer, now()) {
+        set(app, state, peer, Presence::Offline);
+    } else {
+        forget(state, &peer.id);
+    }
+}
+
+/// Stop tracking a peer (deleted, removed from the cluster, or never trusted).
+pub fn forget(state: &AppState, peer_id: &str) {
+    state.presence.lock().unwrap().remove(peer_id);
+}
+
+/// Re-classify runtime peers from their last heartbeat (called from the pruning task, before
+/// stale peers are removed).
+pub fn refresh(app: &AppHandle, state: &AppState) {
+    let tuning = tuning(state);
+    let now = now();
+    for peer in state.get_peers().values() {
+        // Offline is applied when the peer is actually pruned
+        let presence = match classify(peer.last_seen, now, &tuning) {
+            Presence::Offline => Presence::Away,
+            p => p,
+        };
+        set(app, state, peer, presence);
+    }
+}
+
+/// Presence of every runtime peer and every known member.
+pub fn snapshot(state: &AppState) -> Vec<PeerPresence> {
+    let runtime = state.get_peers();
+    let known = state.known_peers.lock().unwrap().clone();
+    let recorded = state.presence.lock().unwrap().clone();
+    let runtime_ids: std::collections::HashSet<String> = runtime.keys().cloned().collect();
+    let offline = known.into_values().filter(|p| p.is_trusted && !runtime_ids.contains(&p.id));
+    runtime
+        .into_values()
+        .chain(offline)
+        .map(|peer| {
+            let (presence, since) = match recorded.get(&peer.id) {
+                Some(entry) => *entry,
+                None if runtime_ids.contains(&peer.id) => (Presence::Online, 0),
+                None => (Presence::Offline, 0),
+            };
+            PeerPresence { peer, presence, since }
+        })
+        .collect()
+}
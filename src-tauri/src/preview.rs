@@ -0,0 +1,89 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use std::path::Path;
+
+/// Longest edge of generated thumbnails, in pixels.
+const THUMBNAIL_EDGE: u32 = 128;
+/// Encoded thumbnails larger than this are dropped rather than bloating the payload.
+const MAX_THUMBNAIL_BYTES: usize = 48 * 1024;
+/// Don't decode huge images on the clipboard monitor thread.
+const MAX_SOURCE_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Best-effort MIME type from the file extension.
+pub fn guess_mime(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "ico" => "image/x-icon",
+        "svg" => "image/svg+xml",
+        "heic" => "image/heic",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "7z" => "application/x-7z-compressed",
+        "tar" => "application/x-tar",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+/// Generate a small PNG thumbnail (base64) for image files.
+/// Returns None for non-images, oversized sources, or if the result exceeds the size cap.
+pub fn generate_thumbnail(path: &Path, size: u64) -> Option<String> {
+    if size > MAX_SOURCE_BYTES {
+        return None;
+    }
+    if !guess_mime(path).is_some_and(|m| m.starts_with("image/")) {
+        return None;
+    }
+
+    let img = match image::open(path) {
+        Ok(img) => img,
+        Err(e) => {
+            tracing::debug!("Thumbnail decode failed for {}: {}", crate::redact::path(path), e);
+            return None;
+        }
+    };
+
+    let thumb = img.thumbnail(THUMBNAIL_EDGE, THUMBNAIL_EDGE);
+    let mut buf = Vec::new();
+    if thumb
+        .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .is_err()
+    {
+        return None;
+    }
+
+    if buf.len() > MAX_THUMBNAIL_BYTES {
+        tracing::debug!("Thumbnail for {} too large ({} bytes), skipping", crate::redact::path(path), buf.len());
+        return None;
+    }
+
+    Some(BASE64.encode(buf))
+}
@@ -1,9 +1,31 @@
 use serde::{Deserialize, Serialize};
 
+// Wire protocol revision, advertised in PeerDiscovery and mDNS (see platform.rs). Bump it when
+// a change means older devices can no longer interoperate.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FileMetadata {
     pub name: String,
     pub size: u64,
+    #[serde(default)]
+    pub mime: Option<String>,
+    // Small PNG preview (Base64), only for images and only if enabled on the sender
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    // SHA-256 of the contents (hex), lets receivers skip files they already have
+    #[serde(default)]
+    pub hash: Option<String>,
+    // Whole file contents (Base64) for tiny files, skipping the request/stream round-trip
+    #[serde(default)]
+    pub inline_data: Option<String>,
+    // Start of the text, when large copied text was sent as a file instead
+    #[serde(default)]
+    pub text_preview: Option<String>,
+    // A copied bitmap rather than a copied file: receivers supporting Windows clipboard formats
+    // put it back on the clipboard as an image (see winclip.rs)
+    #[serde(default)]
+    pub clipboard_image: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -15,6 +37,65 @@ pub struct ClipboardPayload {
     pub timestamp: u64,
     pub sender: String,
     pub sender_id: String,
+    // Where it was copied from (only if the sender opted in)
+    #[serde(default)]
+    pub source: Option<SourceContext>,
+    // Per-hop timestamps (origin first), only when the sender has latency tracing on
+    #[serde(default)]
+    pub trace: Option<Vec<TraceHop>>,
+    // Named channel; receivers only apply channels they subscribe to (older senders: "default")
+    #[serde(default = "default_channel")]
+    pub channel: String,
+    // Title/favicon of a single-URL text. Never set by current senders (they follow up with a
+    // LinkPreview message); kept for older ones
+    #[serde(default)]
+    pub link: Option<LinkPreview>,
+    // Detected content kinds; set locally when the item enters history, never sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<crate::classify::ContentTags>,
+    // Sender's Ed25519 signature (Base64, see senderauth.rs); None from older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
+}
+
+pub const DEFAULT_CHANNEL: &str = "default";
+
+fn default_channel() -> String {
+    DEFAULT_CHANNEL.to_string()
+}
+
+/// Channel names are short identifiers (they end up in settings and shortcuts).
+pub fn is_valid_channel(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraceHop {
+    pub device_id: String,
+    pub at_ms: u64, // Sender's wall clock (unix ms)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourceContext {
+    pub app: Option<String>,          // e.g. "firefox"
+    pub window_title: Option<String>, // e.g. "GitHub"
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub favicon: Option<String>, // data: URL
+}
+
+// Sender -> members: preview for one of its items, found after the item went out (see unfurl.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkPreviewUpdate {
+    pub id: String,        // ClipboardPayload.id
+    pub sender_id: String, // Must match the item's sender_id
+    pub link: LinkPreview,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -22,6 +103,154 @@ pub struct FileRequestPayload {
     pub id: String,        // Matches ClipboardPayload.id (which identifies the batch)
     pub file_index: usize, // Which file in the list?
     pub offset: u64,
+    // Requester can decode zstd-compressed streams
+    #[serde(default)]
+    pub accept_compression: bool,
+    // Requesting device, when it was asked and its Ed25519 signature over the request (Base64,
+    // see senderauth.rs); empty from older versions
+    #[serde(default)]
+    pub requester_id: String,
+    #[serde(default)]
+    pub requested_at: u64,
+    #[serde(default)]
+    pub auth: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileErrorReason {
+    Expired,    // The sender stopped serving this offer (see offers.rs)
+    NotFound,   // Unknown offer/index, or the file is gone from disk
+    Unreadable, // The file exists but couldn't be opened or read
+}
+
+// Sender -> requester: a requested file won't be streamed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileRequestError {
+    pub id: String,
+    pub file_index: usize,
+    pub reason: FileErrorReason,
+    // OS error text, if any
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryAnnotation {
+    pub id: String,           // History item (ClipboardPayload.id)
+    pub note: Option<String>, // None clears the annotation
+    pub updated_at: u64,      // Last write wins
+}
+
+// Admitting member -> rest of the cluster: vouches for a device it just welcomed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MembershipAnnouncement {
+    pub member: crate::peer::Peer,
+    pub admitted_by: String, // Device ID of the member that ran the pairing
+    pub admitted_at: u64,
+    // admitted_by's Ed25519 signature over the member and admitted_at (see senderauth.rs)
+    #[serde(default)]
+    pub auth: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+    Ping,
+    Echo, // Reply carries `data` back
+    Sink, // Throughput sample: reply only reports the size
+}
+
+// Member -> member: hash of the member ids the sender knows (see peersync.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PeerListDigest {
+    pub sender_id: String,
+    pub digest: String,
+}
+
+// Member -> member: the sender's member list (PeerListRequest asks for one back)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PeerList {
+    pub sender_id: String,
+    pub members: Vec<crate::peer::Peer>,
+}
+
+// Connection test (see conntest.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Probe {
+    pub id: String,
+    pub kind: ProbeKind,
+    #[serde(default)]
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProbeReply {
+    pub id: String,
+    #[serde(default)]
+    pub data: String,
+    pub received_bytes: u64,
+}
+
+// Dry-run receiver -> sender: what a real item would have gone through (see dryrun.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DryRunReceipt {
+    pub id: String, // ClipboardPayload.id of the dry run
+    pub device_id: String,
+    pub hostname: String,
+    pub steps: Vec<String>,
+    pub would_apply: bool,
+}
+
+// Member -> member: open a link in the default browser (see remote.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenUrlRequest {
+    pub url: String,
+    pub sender_id: String,
+    pub sent_at: u64,
+}
+
+// Member -> member: type text as keystrokes, once the receiving user confirms (see remote.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TypeTextRequest {
+    pub id: String,
+    pub text: String,
+    pub sender_id: String,
+    pub sent_at: u64,
+}
+
+// Why a pairing attempt failed. Sent by the responder in PairRejected, and reported to the
+// UI in pairing-failed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PairingError {
+    WrongPin,        // Key confirmation failed, or the one-time PIN is no longer valid
+    Timeout,         // No answer before the handshake expired
+    Unreachable,     // The other device couldn't be reached
+    Rejected,        // The other device or the cluster owner turned the request down
+    VersionMismatch, // The two sides derive pairing keys differently
+    KeyMismatch,     // The PIN checked out, but the SPAKE2 message or the Welcome didn't
+    OwnerOffline,    // Approval is required and the cluster owner can't be reached
+}
+
+// Member -> Owner: a device passed the PIN check and is waiting to be let in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdmissionRequest {
+    pub request_id: String,
+    pub device_id: String, // Joining device
+    pub addr: String,      // Joining device's address as seen by the member
+    pub via: String,       // Member (device ID) holding the pairing session
+    pub requested_at: u64,
+    // Admitting as a guest for this long
+    #[serde(default)]
+    pub guest_duration_secs: Option<u64>,
+}
+
+// Owner -> Member
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdmissionDecision {
+    pub request_id: String,
+    pub approved: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -31,6 +260,12 @@ pub struct FileStreamHeader {
     pub file_name: String,
     pub file_size: u64,
     pub auth_token: String, // Encrypted token proving Cluster Key possession
+    // Body is zstd-compressed (file_size is still the uncompressed size)
+    #[serde(default)]
+    pub compressed: bool,
+    // Byte offset the body starts at (non-zero when resuming a partial transfer)
+    #[serde(default)]
+    pub offset: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -39,10 +274,37 @@ pub enum Message {
     PairRequest {
         msg: Vec<u8>,
         device_id: String,
+        // Initiator used a one-time PIN rather than the network PIN
+        #[serde(default)]
+        one_time: bool,
+        // crypto::PAIRING_KDF_VERSION the initiator derived its keys with (0 = raw PIN)
+        #[serde(default)]
+        kdf: u8,
     },
     PairResponse {
         msg: Vec<u8>,
         device_id: String,
+        // crypto::Confirm::Responder tag; lets the initiator spot a wrong PIN right away
+        #[serde(default)]
+        confirmation: Vec<u8>,
+    },
+    // Initiator's key confirmation, sent once the responder's checked out. The responder
+    // only proceeds to admission/Welcome after verifying it.
+    PairConfirm {
+        device_id: String,
+        confirmation: Vec<u8>,
+        // Initiator's Ed25519 public key, encrypted with the session key (empty from older versions)
+        #[serde(default)]
+        device_key: Vec<u8>,
+    },
+    // Responder -> Initiator: the handshake won't go any further (in the clear; no key yet)
+    PairRejected {
+        device_id: String,
+        reason: PairingError,
+    },
+    // Responder -> Initiator: the PIN checked out and the Welcome waits on the owner's approval
+    AdmissionPending {
+        device_id: String,
     },
     // Sent by Responder to Initiator after successful handshake
     Welcome {
@@ -50,6 +312,18 @@ pub enum Message {
         known_peers: Vec<crate::peer::Peer>,
         network_name: String,
         network_pin: String,
+        // Device that approves new members (None for clusters created before owner approval)
+        #[serde(default)]
+        owner_id: Option<String>,
+        // Set for guests: the key above is a derived guest key valid until this time
+        #[serde(default)]
+        guest_expires_at: Option<u64>,
+        // crypto::Confirm::Welcome tag over the joiner's id, with the session's confirmation key
+        #[serde(default)]
+        key_confirmation: Vec<u8>,
+        // Responder's Ed25519 public key, encrypted with the session key (empty from older versions)
+        #[serde(default)]
+        device_key: Vec<u8>,
     },
     // Gossip: Broadcast new peer to known peers
     PeerDiscovery(crate::peer::Peer),
@@ -57,6 +331,34 @@ pub enum Message {
     PeerRemoval(String), // Payload is device_id
     // Broadcast deletion of history item
     HistoryDelete(String), // Payload is item ID
+    // Encrypted HistoryAnnotation (note/label on a history item)
+    HistoryAnnotate(Vec<u8>),
+    // Encrypted LinkPreviewUpdate
+    LinkPreview(Vec<u8>),
     // Encrypted File Request (FileRequestPayload)
     FileRequest(Vec<u8>),
+    // Encrypted FileRequestPayload: requester already has this file (matched by hash), no stream needed
+    HaveAlready(Vec<u8>),
+    // Encrypted FileRequestError (reply to a FileRequest that can't be served)
+    FileRequestError(Vec<u8>),
+    // Encrypted AdmissionRequest (sent to the cluster owner)
+    AdmissionRequest(Vec<u8>),
+    // Encrypted AdmissionDecision (owner's reply)
+    AdmissionResponse(Vec<u8>),
+    // Encrypted MembershipAnnouncement (sent by the admitting member)
+    MembershipAnnounce(Vec<u8>),
+    // Membership anti-entropy: encrypted PeerListDigest, then PeerList both ways
+    PeerListDigest(Vec<u8>),
+    PeerListRequest(Vec<u8>),
+    PeerList(Vec<u8>),
+    // Encrypted Probe / ProbeReply (connection test)
+    Probe(Vec<u8>),
+    ProbeReply(Vec<u8>),
+    // Encrypted OpenUrlRequest
+    OpenUrl(Vec<u8>),
+    // Encrypted TypeTextRequest
+    TypeText(Vec<u8>),
+    // Encrypted ClipboardPayload that receivers check but never apply / encrypted DryRunReceipt
+    DryRun(Vec<u8>),
+    DryRunReceipt(Vec<u8>),
 }
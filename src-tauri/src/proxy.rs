@@ -0,0 +1,83 @@
+//! Outbound SOCKS5 proxy for connections that leave the local network.
+//!
+//! Peer traffic is QUIC on the LAN and always goes direct; there is no TCP or relay transport
+//! to route. What does leave the segment is plain HTTP from the app itself (link previews,
+//! see unfurl.rs), and that goes through `client` so it honours the `proxy` setting. Hosts
+//! matching a bypass rule connect directly. A rule is a host name (`example.com`), a domain
+//! suffix (`.corp.example` or `*.corp.example`), an IP address or a CIDR range. When the proxy
+//! is enabled but its URL is invalid, requests fail rather than silently going direct.
+
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    // socks5://[user:pass@]host:port, or socks5h:// to resolve names through the proxy
+    #[serde(default)]
+    pub url: String,
+    #[serde(default = "default_bypass")]
+    pub bypass: Vec<String>,
+}
+
+fn default_bypass() -> Vec<String> {
+    vec!["localhost".to_string(), "127.0.0.0/8".to_string(), "::1".to_string()]
+}
+
+impl Default for ProxySettings {
+    fn default() -> Self {
+        Self { enabled: false, url: String::new(), bypass: default_bypass() }
+    }
+}
+
+/// Check a proxy URL from the settings.
+pub fn parse(url: &str) -> Result<url::Url, String> {
+    let parsed = url::Url::parse(url.trim()).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+    if !matches!(parsed.scheme(), "socks5" | "socks5h") {
+        return Err("Only socks5:// and socks5h:// proxies are supported".to_string());
+    }
+    if parsed.host_str().is_none() || parsed.port().is_none() {
+        return Err("The proxy URL needs a host and a port".to_string());
+    }
+    Ok(parsed)
+}
+
+fn matches_rule(rule: &str, host: &str) -> bool {
+    let rule = rule.trim().to_ascii_lowercase();
+    if rule.is_empty() {
+        return false;
+    }
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return match rule.parse::<IpNetwork>() {
+            Ok(net) => net.contains(ip),
+            Err(_) => rule.parse::<IpAddr>().is_ok_and(|r| r == ip),
+        };
+    }
+    match rule.strip_prefix("*.").or_else(|| rule.strip_prefix('.')) {
+        Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+        None => host == rule,
+    }
+}
+
+/// Whether `host` connects directly instead of through the proxy.
+pub fn bypassed(settings: &ProxySettings, host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    settings.bypass.iter().any(|rule| matches_rule(rule, &host))
+}
+
+/// An HTTP client builder that follows the proxy settings.
+pub fn client(settings: &ProxySettings) -> Result<reqwest::ClientBuilder, String> {
+    let builder = reqwest::Client::builder();
+    if !settings.enabled {
+        return Ok(builder);
+    }
+    let proxy_url = parse(&settings.url)?;
+    let rules = settings.clone();
+    let proxy = reqwest::Proxy::custom(move |url| match url.host_str() {
+        Some(host) if bypassed(&rules, host) => None,
+        _ => Some(proxy_url.clone()),
+    });
+    Ok(builder.proxy(proxy))
+}
@@ -0,0 +1,160 @@
+//! Bounded, prioritized queue between the transport listener and `handle_message`.
+//!
+//! A fixed pool of workers drains it, so a flood of LAN traffic costs at most the queue
+//! capacity in memory instead of one spawned task per message.
+
+use crate::protocol::Message;
+use crate::state::MessageMetrics;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Number of concurrent handle_message workers.
+pub const WORKERS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    // Handshakes time out if they wait behind bulk traffic
+    Pairing = 0,
+    Clipboard = 1,
+    Gossip = 2,
+}
+
+impl Priority {
+    pub fn of(msg: &Message) -> Self {
+        match msg {
+            Message::PairRequest { .. }
+            | Message::PairResponse { .. }
+            | Message::PairConfirm { .. }
+            | Message::PairRejected { .. }
+            | Message::AdmissionPending { .. }
+            | Message::Welcome { .. }
+            | Message::AdmissionRequest(_)
+            | Message::AdmissionResponse(_) => Priority::Pairing,
+            Message::Clipboard(_)
+            | Message::FileRequest(_)
+            | Message::HaveAlready(_)
+            | Message::FileRequestError(_)
+            | Message::HistoryDelete(_)
+            | Message::HistoryAnnotate(_)
+            | Message::LinkPreview(_)
+            | Message::Probe(_)
+            | Message::ProbeReply(_)
+            | Message::DryRun(_)
+            | Message::DryRunReceipt(_)
+            | Message::OpenUrl(_)
+            | Message::TypeText(_) => Priority::Clipboard,
+            Message::PeerDiscovery(_)
+            | Message::PeerRemoval(_)
+            | Message::MembershipAnnounce(_)
+            | Message::PeerListDigest(_)
+            | Message::PeerListRequest(_)
+            | Message::PeerList(_) => Priority::Gossip,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Pairing => "pairing",
+            Priority::Clipboard => "clipboard",
+            Priority::Gossip => "gossip",
+        }
+    }
+
+    fn capacity(self) -> usize {
+        match self {
+            Priority::Pairing => 32,
+            Priority::Clipboard => 128,
+            Priority::Gossip => 256,
+        }
+    }
+
+    // Clipboard: only the newest content matters, so evict the oldest.
+    // Pairing/Gossip: keep what's queued and drop the newcomer (peers retry/re-announce).
+    fn drop_oldest_when_full(self) -> bool {
+        matches!(self, Priority::Clipboard)
+    }
+}
+
+const CLASSES: [Priority; 3] = [Priority::Pairing, Priority::Clipboard, Priority::Gossip];
+
+type Queued = (Message, SocketAddr);
+
+#[derive(Clone)]
+pub struct MessageQueue {
+    queues: Arc<Mutex<[VecDeque<Queued>; 3]>>,
+    notify: Arc<Notify>,
+    metrics: Arc<Mutex<MessageMetrics>>,
+}
+
+impl MessageQueue {
+    pub fn new(metrics: Arc<Mutex<MessageMetrics>>) -> Self {
+        Self {
+            queues: Arc::new(Mutex::new(Default::default())),
+            notify: Arc::new(Notify::new()),
+            metrics,
+        }
+    }
+
+    /// Enqueue without blocking. Returns false if the message was dropped.
+    pub fn push(&self, msg: Message, addr: SocketAddr) -> bool {
+        let class = Priority::of(&msg);
+        let accepted = {
+            let mut queues = self.queues.lock().unwrap();
+            let q = &mut queues[class as usize];
+            if q.len() < class.capacity() {
+                q.push_back((msg, addr));
+                true
+            } else if class.drop_oldest_when_full() {
+                q.pop_front();
+                q.push_back((msg, addr));
+                self.record_drop(class);
+                true
+            } else {
+                self.record_drop(class);
+                false
+            }
+        };
+        if !accepted {
+            tracing::warn!("Message queue '{}' full. Dropping message from {}", class.label(), addr);
+        }
+        self.update_depths();
+        self.notify.notify_one();
+        accepted
+    }
+
+    /// Wait for the highest-priority queued message.
+    pub async fn pop(&self) -> Queued {
+        loop {
+            let notified = self.notify.notified();
+            let item = {
+                let mut queues = self.queues.lock().unwrap();
+                CLASSES.iter().find_map(|c| queues[*c as usize].pop_front())
+            };
+            if let Some(item) = item {
+                self.update_depths();
+                return item;
+            }
+            notified.await;
+        }
+    }
+
+    fn record_drop(&self, class: Priority) {
+        let mut m = self.metrics.lock().unwrap();
+        *m.queue_dropped.entry(class.label().to_string()).or_insert(0) += 1;
+    }
+
+    fn update_depths(&self) {
+        let depths: Vec<(Priority, usize)> = {
+            let queues = self.queues.lock().unwrap();
+            CLASSES.iter().map(|c| (*c, queues[*c as usize].len())).collect()
+        };
+        let mut m = self.metrics.lock().unwrap();
+        for (class, depth) in depths {
+            m.queue_depth.insert(class.label().to_string(), depth);
+            let hw = m.queue_high_water.entry(class.label().to_string()).or_insert(0);
+            *hw = (*hw).max(depth);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use bip39::Mnemonic;
+
+/// Encode the 32-byte cluster key as a 24-word BIP39 phrase.
+/// The phrase is derived on demand, never stored separately from the key.
+pub fn phrase_from_key(key: &[u8]) -> Result<String, String> {
+    if key.len() != 32 {
+        return Err(format!("Invalid cluster key length: {}", key.len()));
+    }
+    Mnemonic::from_entropy(key)
+        .map(|m| m.to_string())
+        .map_err(|e| format!("Failed to encode recovery phrase: {}", e))
+}
+
+/// Decode a recovery phrase back into the cluster key (checksum is verified).
+pub fn key_from_phrase(phrase: &str) -> Result<Vec<u8>, String> {
+    let normalized = phrase
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mnemonic = Mnemonic::parse(&normalized).map_err(|e| format!("Invalid recovery phrase: {}", e))?;
+    let key = mnemonic.to_entropy();
+    if key.len() != 32 {
+        return Err("Recovery phrase must be 24 words".to_string());
+    }
+    Ok(key)
+}
+
+/// Ask the OS to confirm the user's identity before revealing secrets.
+/// Fails closed: Err where no prompt is available, so the secret stays hidden.
+#[cfg(target_os = "windows")]
+pub async fn verify_user(reason: &str) -> Result<bool, String> {
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    let reason = HSTRING::from(reason);
+    tokio::task::spawn_blocking(move || {
+        let availability = UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|op| op.get())
+            .map_err(|e| e.to_string())?;
+        if availability != UserConsentVerifierAvailability::Available {
+            tracing::warn!("Windows Hello not available ({:?}). Can't verify the user.", availability);
+            return Err("Set up Windows Hello to view the recovery phrase".to_string());
+        }
+        let result = UserConsentVerifier::RequestVerificationAsync(&reason)
+            .and_then(|op| op.get())
+            .map_err(|e| e.to_string())?;
+        Ok(result == UserConsentVerificationResult::Verified)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// macOS: LocalAuthentication's device-owner check (Touch ID, or the user's own login password),
+/// run through JavaScript for Automation since it has no command-line front end.
+#[cfg(target_os = "macos")]
+pub async fn verify_user(reason: &str) -> Result<bool, String> {
+    // LAPolicyDeviceOwnerAuthentication = 2. The reply arrives on another thread, so spin the run loop.
+    const SCRIPT: &str = r#"
+ObjC.import('LocalAuthentication');
+function run(argv) {
+    const context = $.LAContext.alloc.init;
+    let done = false, ok = false;
+    context.evaluatePolicyLocalizedReasonReply(2, argv[0], (success, error) => { ok = success; done = true; });
+    while (!done) {
+        $.NSRunLoop.currentRunLoop.runUntilDate($.NSDate.dateWithTimeIntervalSinceNow(0.1));
+    }
+    return ok ? 'verified' : 'denied';
+}
+"#;
+    let output = tokio::process::Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", SCRIPT, reason])
+        .output()
+        .await
+        .map_err(|e| format!("Can't show the authentication prompt: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Can't verify the user: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    // Cancelled or failed: "denied"
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == "verified")
+}
+
+// Installed with the deb/rpm packages (assets/polkit), asks for the user's own password
+#[cfg(all(unix, not(target_os = "macos")))]
+const POLKIT_ACTION: &str = "com.keithvassallo.clustercut.reveal-secret";
+
+/// Linux: polkit's authentication agent, for an action that only needs the user's own password
+/// (`auth_self`). Needs a running agent (every desktop has one) and the action installed;
+/// without either, or inside a sandbox without pkcheck, verification fails.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub async fn verify_user(_reason: &str) -> Result<bool, String> {
+    let pid = std::process::id().to_string();
+    let status = tokio::process::Command::new("pkcheck")
+        .args(["--action-id", POLKIT_ACTION, "--process", &pid, "--allow-user-interaction"])
+        .status()
+        .await
+        .map_err(|e| format!("Can't verify the user (polkit unavailable: {})", e))?;
+    // 1 = not authorized / dismissed; anything else means polkit itself failed (e.g. action not installed)
+    match status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(format!("Can't verify the user (pkcheck exited with {})", status)),
+    }
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub async fn verify_user(_reason: &str) -> Result<bool, String> {
+    Err("No way to verify the user on this platform".to_string())
+}
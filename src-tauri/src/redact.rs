@@ -0,0 +1,85 @@
+//! Keeping clipboard contents out of the logs (`log_redaction` setting).
+//!
+//! Every tracing call that would print user content (clipboard text, file names and paths,
+//! copied URLs) wraps the value in `text`, `path` or `url`, which format it according to the
+//! current mode: replaced by its length (`full`), by a short keyed hash so repeated values can
+//! still be matched up within one run (`hashed`), or as-is (`off`). The hash key is random per
+//! process, so short values can't be recovered from a precomputed table.
+
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRedaction {
+    Full,
+    #[default]
+    Hashed,
+    Off,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(LogRedaction::Hashed as u8);
+static KEY: Lazy<[u8; 16]> = Lazy::new(rand::random);
+
+pub fn set_mode(mode: LogRedaction) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+fn mode() -> LogRedaction {
+    match MODE.load(Ordering::Relaxed) {
+        m if m == LogRedaction::Off as u8 => LogRedaction::Off,
+        m if m == LogRedaction::Full as u8 => LogRedaction::Full,
+        _ => LogRedaction::Hashed,
+    }
+}
+
+/// A user-supplied value, formatted per the redaction mode.
+pub struct Redacted<'a>(std::borrow::Cow<'a, str>);
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match mode() {
+            LogRedaction::Off => f.write_str(&self.0),
+            LogRedaction::Full => write!(f, "<redacted, {} chars>", self.0.chars().count()),
+            LogRedaction::Hashed => {
+                let mut hasher = Sha256::new();
+                hasher.update(*KEY);
+                hasher.update(self.0.as_bytes());
+                write!(f, "<{}>", &hex::encode(hasher.finalize())[..12])
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Clipboard text, file names, notes.
+pub fn text(value: &str) -> Redacted<'_> {
+    Redacted(value.into())
+}
+
+/// A path to a user's file (not our own config/cache paths).
+pub fn path(value: &Path) -> Redacted<'_> {
+    Redacted(value.to_string_lossy())
+}
+
+/// A copied URL. Only the scheme and host stay readable unless redaction is off.
+pub fn url(value: &url::Url) -> impl fmt::Display + '_ {
+    struct Url<'a>(&'a url::Url);
+    impl fmt::Display for Url<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if mode() == LogRedaction::Off {
+                return write!(f, "{}", self.0);
+            }
+            write!(f, "{}://{}/{}", self.0.scheme(), self.0.host_str().unwrap_or(""), text(&self.0[url::Position::BeforePath..]))
+        }
+    }
+    Url(value)
+}
@@ -0,0 +1,213 @@
+//! Actions a member asks another member's device to perform: opening a link in its default
+//! browser, or typing text as keystrokes (for kiosk/TV devices where pasting isn't an option).
+//! Each one is off until the receiving user grants it per peer (PeerPermissions), rides the
+//! cluster key, and must come from the address of the trusted member it names. Typed text
+//! additionally waits for the receiving user to confirm it.
+
+use crate::protocol::{Message, OpenUrlRequest, TypeTextRequest};
+use crate::state::AppState;
+use crate::transport::Transport;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+// Requests older than this (or this far in the future) are treated as replays
+const MAX_REQUEST_AGE_SECS: u64 = 60;
+// Keeps the encrypted request well under the small-message cap, whatever the characters
+pub const MAX_TYPE_TEXT_CHARS: usize = 2000;
+// An unconfirmed TypeText is dropped after this long
+const CONFIRM_WINDOW_SECS: u64 = 120;
+// After confirming, give focus time to return to the window the text is meant for
+const TYPE_DELAY: Duration = Duration::from_millis(800);
+
+/// A TypeText request waiting for the local user ("type-text-request" event).
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct PendingTypeText {
+    pub id: String,
+    pub sender: String,
+    pub sender_id: String,
+    pub text: String,
+    pub received_at: u64,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn target(state: &AppState, peer_id: &str) -> Result<std::net::SocketAddr, String> {
+    let peer = state.get_peers().get(peer_id).cloned().ok_or_else(|| format!("Peer {} not found or offline", peer_id))?;
+    if !peer.is_trusted || peer.guest_expires_at.is_some() {
+        return Err("Remote actions are only available between full members".to_string());
+    }
+    Ok(std::net::SocketAddr::new(peer.ip, peer.port))
+}
+
+/// Ask `peer_id` to open `url` (it only does so if it allows us to).
+pub async fn open_on_peer(state: &AppState, transport: &Transport, peer_id: &str, url: &str) -> Result<(), String> {
+    let url = crate::unfurl::single_url(url).ok_or("Only http(s) links can be opened on another device")?;
+    let addr = target(state, peer_id)?;
+    let request = OpenUrlRequest {
+        url: url.to_string(),
+        sender_id: state.local_device_id.lock().unwrap().clone(),
+        sent_at: now(),
+    };
+    let cipher = crate::encrypt_cluster_message(state, &request).ok_or("No Cluster Key set")?;
+    let data = serde_json::to_vec(&Message::OpenUrl(cipher)).map_err(|e| e.to_string())?;
+    transport.send_message(addr, &data).await.map_err(|e| e.to_string())?;
+    tracing::info!("[Remote] Asked {} to open {}", peer_id, crate::redact::url(&url));
+    Ok(())
+}
+
+/// Ask `peer_id` to type `text` (it only does so if it allows us to and its user confirms).
+pub async fn type_on_peer(state: &AppState, transport: &Transport, peer_id: &str, text: &str) -> Result<(), String> {
+    if text.is_empty() {
+        return Err("Nothing to type".to_string());
+    }
+    if text.chars().count() > MAX_TYPE_TEXT_CHARS {
+        return Err(format!("Text is too long to type remotely (max {} characters)", MAX_TYPE_TEXT_CHARS));
+    }
+    let addr = target(state, peer_id)?;
+    let request = TypeTextRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        text: text.to_string(),
+        sender_id: state.local_device_id.lock().unwrap().clone(),
+        sent_at: now(),
+    };
+    let cipher = crate::encrypt_cluster_message(state, &request).ok_or("No Cluster Key set")?;
+    let data = serde_json::to_vec(&Message::TypeText(cipher)).map_err(|e| e.to_string())?;
+    transport.send_message(addr, &data).await.map_err(|e| e.to_string())?;
+    tracing::info!("[Remote] Asked {} to type {} characters", peer_id, request.text.chars().count());
+    Ok(())
+}
+
+/// Rule: links copied here are also opened on the peers listed in `open_links_on`.
+pub fn apply_link_rules(state: &AppState, transport: &Transport, text: &str) {
+    let peers = state.settings.lock().unwrap().open_links_on.clone();
+    if peers.is_empty() || state.is_reset_pending() || crate::screenlock::sync_paused(state) || crate::unfurl::single_url(text).is_none() {
+        return;
+    }
+    for peer_id in peers {
+        let (state, transport, url) = (state.clone(), transport.clone(), text.trim().to_string());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = open_on_peer(&state, &transport, &peer_id, &url).await {
+                tracing::debug!("[Remote] Not opening link on {}: {}", peer_id, e);
+            }
+        });
+    }
+}
+
+/// Whether a request naming `sender_id` really comes from that trusted member, is fresh, and
+/// `allowed` says the local user granted it the permission.
+fn accept(state: &AppState, addr: std::net::SocketAddr, sender_id: &str, sent_at: u64, allowed: impl Fn(&crate::storage::PeerPermissions) -> bool) -> Result<String, &'static str> {
+    let sender = state
+        .peers
+        .lock()
+        .unwrap()
+        .get(sender_id)
+        .filter(|p| p.is_trusted && p.guest_expires_at.is_none() && p.ip == addr.ip())
+        .map(|p| p.hostname.clone())
+        .ok_or("not sent by a trusted member")?;
+    if now().abs_diff(sent_at) > MAX_REQUEST_AGE_SECS {
+        return Err("stale request");
+    }
+    let permissions = state.peer_permissions.lock().unwrap().get(sender_id).copied().unwrap_or_default();
+    if !allowed(&permissions) {
+        return Err("not permitted for this peer");
+    }
+    Ok(sender)
+}
+
+pub fn handle_open_url(app: &AppHandle, state: &AppState, addr: std::net::SocketAddr, request: OpenUrlRequest) {
+    let sender = match accept(state, addr, &request.sender_id, request.sent_at, |p| p.open_urls) {
+        Ok(sender) => sender,
+        Err(reason) => {
+            tracing::warn!("[Remote] Ignoring OpenUrl from {}: {}", addr, reason);
+            return;
+        }
+    };
+    let Some(url) = crate::unfurl::single_url(&request.url) else {
+        tracing::warn!("[Remote] Ignoring OpenUrl from {}: not an http(s) link", sender);
+        return;
+    };
+    tracing::info!("[Remote] Opening {} for {}", crate::redact::url(&url), sender);
+    if let Err(e) = app.opener().open_url(url.as_str(), None::<&str>) {
+        tracing::error!("[Remote] Failed to open {}: {}", crate::redact::url(&url), e);
+        return;
+    }
+    crate::events::emit(app, crate::events::UrlOpened { url: url.to_string(), sender: sender.clone() });
+    let host = url.host_str().map(str::to_string).unwrap_or_else(|| crate::i18n::t("notify.link_opened.unknown_host"));
+    let body = crate::i18n::tf("notify.link_opened.body", &[("sender", &sender), ("host", &host)]);
+    crate::send_notification(app, &crate::i18n::t("notify.link_opened.title"), &body, false, Some(2), "history", crate::NotificationPayload::None);
+}
+
+/// Hold a TypeText request until the local user confirms it (a newer one replaces it).
+pub fn handle_type_text(app: &AppHandle, state: &AppState, addr: std::net::SocketAddr, request: TypeTextRequest) {
+    let sender = match accept(state, addr, &request.sender_id, request.sent_at, |p| p.type_text) {
+        Ok(sender) => sender,
+        Err(reason) => {
+            tracing::warn!("[Remote] Ignoring TypeText from {}: {}", addr, reason);
+            return;
+        }
+    };
+    if request.text.is_empty() || request.text.chars().count() > MAX_TYPE_TEXT_CHARS {
+        tracing::warn!("[Remote] Ignoring TypeText from {}: bad length", sender);
+        return;
+    }
+    let pending = PendingTypeText { id: request.id, sender: sender.clone(), sender_id: request.sender_id, text: request.text, received_at: now() };
+    let replaced = state.pending_type_text.lock().unwrap().replace(pending.clone());
+    if let Some(old) = replaced {
+        crate::events::emit(app, crate::events::TypeTextCleared { id: old.id });
+    }
+    tracing::info!("[Remote] {} wants to type {} characters; waiting for confirmation", sender, pending.text.chars().count());
+    crate::events::emit(app, &pending);
+    let body = crate::i18n::tf("notify.type_text.body", &[("sender", &sender)]);
+    crate::send_notification(app, &crate::i18n::t("notify.type_text.title"), &body, false, Some(2), "history", crate::NotificationPayload::None);
+}
+
+pub fn pending_type_text(state: &AppState) -> Option<PendingTypeText> {
+    state.pending_type_text.lock().unwrap().clone().filter(|p| now().saturating_sub(p.received_at) <= CONFIRM_WINDOW_SECS)
+}
+
+/// Type the pending request `id`. The main window is hidden first so the keystrokes land in
+/// whatever had focus before it.
+pub fn confirm_type_text(app: &AppHandle, state: &AppState, id: &str) -> Result<(), String> {
+    let pending = {
+        let mut slot = state.pending_type_text.lock().unwrap();
+        match slot.as_ref() {
+            Some(p) if p.id == id => slot.take().unwrap(),
+            _ => return Err("That request is no longer pending".to_string()),
+        }
+    };
+    crate::events::emit(app, crate::events::TypeTextCleared { id: pending.id.clone() });
+    if now().saturating_sub(pending.received_at) > CONFIRM_WINDOW_SECS {
+        return Err("That request has expired".to_string());
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+    }
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(TYPE_DELAY);
+        match type_text(&pending.text) {
+            Ok(()) => tracing::info!("[Remote] Typed {} characters for {}", pending.text.chars().count(), pending.sender),
+            Err(e) => crate::error::report(&handle, "type-text", &crate::error::AppError::Invalid(format!("Failed to type text: {}", e))),
+        }
+    });
+    Ok(())
+}
+
+pub fn reject_type_text(app: &AppHandle, state: &AppState, id: &str) -> bool {
+    let mut slot = state.pending_type_text.lock().unwrap();
+    if slot.as_ref().is_none_or(|p| p.id != id) {
+        return false;
+    }
+    *slot = None;
+    crate::events::emit(app, crate::events::TypeTextCleared { id: id.to_string() });
+    true
+}
+
+fn type_text(text: &str) -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Settings};
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    enigo.text(text).map_err(|e| e.to_string())
+}
@@ -0,0 +1,225 @@
+//! Recovering quickly after the machine wakes from sleep.
+//!
+//! Without this, peers only notice we're back when the next heartbeat reaches them or their
+//! mDNS records time out. We subscribe to the platform's resume signal (logind
+//! `PrepareForSleep` on Linux, a suspend/resume callback on Windows, IOKit system power
+//! notifications on macOS) and, where that isn't available, watch for the wall clock jumping
+//! ahead of the monotonic one. On resume the mDNS record is re-registered (the network may
+//! have changed), our identity is pushed to current peers, the heartbeat fires without
+//! waiting out its interval, and every known peer is probed. Connections are opened per
+//! message, so there is no pooled connection state to tear down.
+
+use crate::state::AppState;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc::UnboundedSender;
+
+// Let interfaces come back up (DHCP, Wi-Fi association) before announcing
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+// Several signals for one wake (or a signal plus a clock jump) count once
+const DEBOUNCE: Duration = Duration::from_secs(30);
+// Clock-jump fallback: how often to compare clocks, and the gap that counts as a sleep
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const CLOCK_GAP_SECS: u64 = 30;
+
+/// Wait for resume signals until shutdown, recovering the cluster after each.
+pub async fn watch(app: AppHandle) {
+    let state = app.state::<AppState>();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    if !subscribe(tx.clone()).await {
+        tracing::info!("[Resume] No platform sleep notifications; watching for clock jumps instead");
+        tauri::async_runtime::spawn(watch_clock(tx));
+    }
+
+    let mut last: Option<Instant> = None;
+    while let Some(()) = rx.recv().await {
+        if state.is_shutdown() {
+            break;
+        }
+        if last.is_some_and(|t| t.elapsed() < DEBOUNCE) {
+            continue;
+        }
+        last = Some(Instant::now());
+        tracing::info!("[Resume] System woke from sleep; reconnecting to the cluster");
+        tokio::time::sleep(SETTLE_DELAY).await;
+        recover(&app, &state).await;
+    }
+}
+
+async fn recover(app: &AppHandle, state: &AppState) {
+    state.power.resumed();
+    crate::announce_identity(state);
+
+    let Some(transport) = state.transport.lock().unwrap().clone() else {
+        return;
+    };
+    let known: Vec<crate::peer::Peer> = state.known_peers.lock().unwrap().values().cloned().collect();
+    tracing::info!("[Resume] Probing {} known peers", known.len());
+    for peer in known {
+        let (state, transport, app) = (state.clone(), transport.clone(), app.clone());
+        tauri::async_runtime::spawn(async move {
+            crate::probe_ip(peer.ip, peer.port, state, transport, app).await;
+        });
+    }
+}
+
+/// Fallback: the monotonic clock stops while suspended, the wall clock doesn't.
+async fn watch_clock(tx: UnboundedSender<()>) {
+    let mut wall = SystemTime::now();
+    let mut mono = Instant::now();
+    loop {
+        tokio::time::sleep(CLOCK_CHECK_INTERVAL).await;
+        let wall_elapsed = wall.elapsed().unwrap_or_default().as_secs();
+        let mono_elapsed = mono.elapsed().as_secs();
+        if wall_elapsed > mono_elapsed + CLOCK_GAP_SECS && tx.send(()).is_err() {
+            return;
+        }
+        wall = SystemTime::now();
+        mono = Instant::now();
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn subscribe(tx: UnboundedSender<()>) -> bool {
+    use futures::StreamExt;
+
+    let Ok(connection) = zbus::Connection::system().await else {
+        return false;
+    };
+    let proxy = match zbus::Proxy::new(&connection, "org.freedesktop.login1", "/org/freedesktop/login1", "org.freedesktop.login1.Manager").await {
+        Ok(proxy) => proxy,
+        Err(_) => return false,
+    };
+    let mut signals = match proxy.receive_signal("PrepareForSleep").await {
+        Ok(signals) => signals,
+        Err(e) => {
+            tracing::debug!("[Resume] Can't subscribe to logind PrepareForSleep: {}", e);
+            return false;
+        }
+    };
+    tauri::async_runtime::spawn(async move {
+        // Keep the connection alive alongside the stream
+        let _connection = connection;
+        while let Some(message) = signals.next().await {
+            // true = about to sleep, false = just resumed
+            if let Ok(false) = message.body().deserialize::<bool>() {
+                if tx.send(()).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    true
+}
+
+#[cfg(target_os = "windows")]
+static RESUME_TX: once_cell::sync::OnceCell<UnboundedSender<()>> = once_cell::sync::OnceCell::new();
+
+#[cfg(target_os = "windows")]
+async fn subscribe(tx: UnboundedSender<()>) -> bool {
+    use windows::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+    use windows::Win32::System::Power::{PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS};
+    use windows::Win32::UI::WindowsAndMessaging::{DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC};
+
+    unsafe extern "system" fn on_power_event(_context: *const std::ffi::c_void, kind: u32, _setting: *const std::ffi::c_void) -> u32 {
+        if kind == PBT_APMRESUMEAUTOMATIC {
+            if let Some(tx) = RESUME_TX.get() {
+                let _ = tx.send(());
+            }
+        }
+        0
+    }
+
+    if RESUME_TX.set(tx).is_err() {
+        return true;
+    }
+    // Registered for the life of the process, so both are leaked
+    let params = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS { Callback: Some(on_power_event), Context: std::ptr::null_mut() }));
+    let registration = Box::leak(Box::new(std::ptr::null_mut::<std::ffi::c_void>()));
+    let result = unsafe {
+        PowerRegisterSuspendResumeNotification(DEVICE_NOTIFY_CALLBACK, HANDLE(params as *mut _ as *mut std::ffi::c_void), registration)
+    };
+    if result != ERROR_SUCCESS {
+        tracing::debug!("[Resume] PowerRegisterSuspendResumeNotification failed: {:?}", result);
+        return false;
+    }
+    true
+}
+
+#[cfg(target_os = "macos")]
+mod iokit {
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::sync::mpsc::UnboundedSender;
+
+    // iokit_common_msg values from IOMessage.h
+    const CAN_SYSTEM_SLEEP: u32 = 0xE000_0270;
+    const SYSTEM_WILL_SLEEP: u32 = 0xE000_0280;
+    const SYSTEM_HAS_POWERED_ON: u32 = 0xE000_0300;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            port: *mut *mut c_void,
+            callback: extern "C" fn(*mut c_void, u32, u32, *mut c_void),
+            notifier: *mut u32,
+        ) -> u32;
+        fn IONotificationPortGetRunLoopSource(port: *mut c_void) -> core_foundation::runloop::CFRunLoopSourceRef;
+        fn IOAllowPowerChange(kernel_port: u32, notification_id: isize) -> i32;
+    }
+
+    static ROOT_PORT: AtomicU32 = AtomicU32::new(0);
+    static TX: once_cell::sync::OnceCell<UnboundedSender<()>> = once_cell::sync::OnceCell::new();
+
+    extern "C" fn on_power_event(_refcon: *mut c_void, _service: u32, kind: u32, argument: *mut c_void) {
+        match kind {
+            // We never veto or delay sleep, but IOKit waits for an answer
+            CAN_SYSTEM_SLEEP | SYSTEM_WILL_SLEEP => unsafe {
+                IOAllowPowerChange(ROOT_PORT.load(Ordering::Relaxed), argument as isize);
+            },
+            SYSTEM_HAS_POWERED_ON => {
+                if let Some(tx) = TX.get() {
+                    let _ = tx.send(());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs a CFRunLoop on its own thread to receive the notifications.
+    pub fn subscribe(tx: UnboundedSender<()>) -> bool {
+        use core_foundation::base::TCFType;
+        use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource};
+
+        if TX.set(tx).is_err() {
+            return true;
+        }
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut port: *mut c_void = std::ptr::null_mut();
+            let mut notifier = 0u32;
+            let root = unsafe { IORegisterForSystemPower(std::ptr::null_mut(), &mut port, on_power_event, &mut notifier) };
+            if root == 0 || port.is_null() {
+                let _ = ready_tx.send(false);
+                return;
+            }
+            ROOT_PORT.store(root, Ordering::Relaxed);
+            let source = unsafe { CFRunLoopSource::wrap_under_get_rule(IONotificationPortGetRunLoopSource(port)) };
+            CFRunLoop::get_current().add_source(&source, unsafe { kCFRunLoopDefaultMode });
+            let _ = ready_tx.send(true);
+            CFRunLoop::run_current();
+        });
+        ready_rx.recv().unwrap_or(false)
+    }
+}
+
+#[cfg(target_os = "macos")]
+async fn subscribe(tx: UnboundedSender<()>) -> bool {
+    iokit::subscribe(tx)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+async fn subscribe(_tx: UnboundedSender<()>) -> bool {
+    false
+}
@@ -0,0 +1,109 @@
+//! Retrying clipboard sends that fail, and telling the user when a peer never got an item.
+//!
+//! Each (message, peer) pair is tracked while it's being retried. Attempts back off
+//! exponentially and re-resolve the peer's address each time, so a peer that changed IP in
+//! the meantime is still reached. Within an attempt the peer's known addresses are tried in
+//! score order (see `Peer::candidates`), and the outcome of each feeds back into the score. After `send_retry_attempts` retries the failure stays in
+//! the list (see `failures`) and a "send-failed" event goes to the UI.
+
+use crate::state::AppState;
+use crate::transport::Transport;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const FIRST_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// Oldest entries beyond this are forgotten
+const MAX_TRACKED: usize = 100;
+// Addresses tried per attempt before backing off
+const MAX_CANDIDATES: usize = 3;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SendFailure {
+    pub msg_id: String,
+    pub peer_id: String,
+    pub peer: String,
+    pub reason: String,
+    pub attempts: u32,
+    // False while retries are still pending
+    pub exhausted: bool,
+    pub timestamp: u64,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn record(state: &AppState, failure: SendFailure) {
+    let mut failures = state.send_failures.lock().unwrap();
+    failures.shift_remove(&(failure.msg_id.clone(), failure.peer_id.clone()));
+    failures.insert((failure.msg_id.clone(), failure.peer_id.clone()), failure);
+    while failures.len() > MAX_TRACKED {
+        failures.shift_remove_index(0);
+    }
+}
+
+/// Send `data` (message `msg_id`) to `peer_id`, retrying on failure.
+pub async fn send(app: AppHandle, state: AppState, transport: Transport, msg_id: String, peer_id: String, data: Vec<u8>) {
+    let retries = state.settings.lock().unwrap().send_retry_attempts;
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        // Peer may have moved (or left) since the last attempt
+        let Some(peer) = state.get_peers().get(&peer_id).cloned() else {
+            tracing::info!("[Retry] {} left before {} could be delivered", peer_id, msg_id);
+            state.send_failures.lock().unwrap().shift_remove(&(msg_id, peer_id));
+            return;
+        };
+        let addr = peer.primary_addr();
+        let mut reason = String::new();
+        for candidate in peer.candidates().into_iter().take(MAX_CANDIDATES) {
+            let result = transport.send_message(candidate, &data).await;
+            state.record_peer_send(&peer_id, candidate, result.is_ok());
+            match result {
+                Ok(_) => {
+                    tracing::info!("Sent clipboard to {}", candidate);
+                    crate::peerstats::record(&state, &peer_id, crate::peerstats::Direction::Sent, crate::peerstats::Kind::Item, data.len() as u64);
+                    state.send_failures.lock().unwrap().shift_remove(&(msg_id, peer_id));
+                    return;
+                }
+                Err(e) => {
+                    tracing::debug!("Send of {} to {} failed: {}", msg_id, candidate, e);
+                    reason = e.to_string();
+                }
+            }
+        }
+
+        let exhausted = attempt > retries || state.is_shutdown();
+        let failure = SendFailure {
+            msg_id: msg_id.clone(),
+            peer_id: peer_id.clone(),
+            peer: peer.hostname.clone(),
+            reason,
+            attempts: attempt,
+            exhausted,
+            timestamp: now(),
+        };
+        if exhausted {
+            tracing::error!("Failed to send {} to {} after {} attempts: {}", msg_id, addr, attempt, failure.reason);
+            crate::events::emit(&app, &failure);
+            record(&state, failure);
+            return;
+        }
+        tracing::warn!("Failed to send {} to {} (attempt {}): {}; retrying in {:?}", msg_id, addr, attempt, failure.reason, backoff);
+        record(&state, failure);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Tracked failures, oldest first.
+pub fn failures(state: &AppState) -> Vec<SendFailure> {
+    state.send_failures.lock().unwrap().values().cloned().collect()
+}
+
+pub fn clear(state: &AppState) {
+    state.send_failures.lock().unwrap().clear();
+}
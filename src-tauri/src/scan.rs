@@ -0,0 +1,121 @@
+use std::path::Path;
+use std::time::Duration;
+
+/// Give up on the scanner after this long (clamscan loads its signature DB on every run).
+const SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanStatus {
+    Clean,
+    Infected,
+    Error,
+}
+
+/// Outcome of the post-receive hook, attached to the file-received event.
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct ScanReport {
+    // None if no scanner is configured
+    pub status: Option<ScanStatus>,
+    // Scanner output (trimmed) or error message
+    pub detail: Option<String>,
+    // OS quarantine attribute was applied
+    pub quarantined: bool,
+}
+
+impl ScanReport {
+    /// Infected files, and files the scanner failed on or timed out with, are kept on disk
+    /// but never placed on the clipboard.
+    pub fn is_blocked(&self) -> bool {
+        matches!(self.status, Some(ScanStatus::Infected | ScanStatus::Error))
+    }
+}
+
+/// Run the post-receive hook for a downloaded file.
+/// `scan_command` is split on whitespace (no shell); `{path}` is substituted, otherwise the path is appended.
+pub async fn post_receive(path: &Path, scan_command: Option<&str>, quarantine: bool) -> ScanReport {
+    let mut report = ScanReport::default();
+
+    if quarantine {
+        match mark_quarantined(path) {
+            Ok(applied) => report.quarantined = applied,
+            Err(e) => tracing::warn!("Failed to set quarantine attribute on {}: {}", crate::redact::path(path), e),
+        }
+    }
+
+    if let Some(cmd) = scan_command.map(str::trim).filter(|c| !c.is_empty()) {
+        let (status, detail) = run_scanner(cmd, path).await;
+        tracing::info!("Scan of {}: {:?}", crate::redact::path(path), status);
+        report.status = Some(status);
+        report.detail = detail;
+    }
+
+    report
+}
+
+// Exit code 0 = clean, 1 = threat found (clamscan/ClamAV convention), anything else = error
+async fn run_scanner(cmd: &str, path: &Path) -> (ScanStatus, Option<String>) {
+    let path_str = path.to_string_lossy();
+    let mut parts: Vec<String> = cmd.split_whitespace().map(|s| s.to_string()).collect();
+    if parts.iter().any(|p| p.contains("{path}")) {
+        for p in parts.iter_mut() {
+            *p = p.replace("{path}", &path_str);
+        }
+    } else {
+        parts.push(path_str.to_string());
+    }
+
+    let mut command = tokio::process::Command::new(&parts[0]);
+    command.args(&parts[1..]).kill_on_drop(true);
+
+    let output = match tokio::time::timeout(SCAN_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return (ScanStatus::Error, Some(format!("Failed to run scanner: {}", e))),
+        Err(_) => return (ScanStatus::Error, Some("Scanner timed out".to_string())),
+    };
+
+    let mut detail = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if detail.is_empty() {
+        detail = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    }
+    let detail = if detail.is_empty() { None } else { Some(detail) };
+
+    match output.status.code() {
+        Some(0) => (ScanStatus::Clean, detail),
+        Some(1) => (ScanStatus::Infected, detail),
+        _ => (ScanStatus::Error, detail),
+    }
+}
+
+/// Tag the file as downloaded from another machine, so the OS applies its usual checks on open.
+/// Returns Ok(false) on platforms without a quarantine mechanism.
+#[cfg(target_os = "macos")]
+fn mark_quarantined(path: &Path) -> std::io::Result<bool> {
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let value = format!("0081;{:x};ClusterCut;", ts);
+    let status = std::process::Command::new("xattr")
+        .args(["-w", "com.apple.quarantine", &value])
+        .arg(path)
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("xattr exited with {}", status)));
+    }
+    Ok(true)
+}
+
+#[cfg(target_os = "windows")]
+fn mark_quarantined(path: &Path) -> std::io::Result<bool> {
+    // Mark of the Web: NTFS alternate data stream, ZoneId 3 = Internet
+    let mut ads = path.as_os_str().to_os_string();
+    ads.push(":Zone.Identifier");
+    std::fs::write(ads, "[ZoneTransfer]\r\nZoneId=3\r\n")?;
+    Ok(true)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn mark_quarantined(_path: &Path) -> std::io::Result<bool> {
+    Ok(false)
+}
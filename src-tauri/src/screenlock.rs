@@ -0,0 +1,167 @@
+//! Screen lock detection and the "pause sync while locked" policy.
+//!
+//! Polled rather than subscribed to: logind `LockedHint` (falling back to the freedesktop
+//! ScreenSaver API) on Linux, the WTS session state on Windows, and the CG session
+//! dictionary on macOS. While locked (and `pause_sync_when_locked` is on) incoming text
+//! is held as pending instead of applied, and nothing is broadcast or relayed.
+
+use crate::state::AppState;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Whether sync is currently paused because the screen is locked.
+pub fn sync_paused(state: &AppState) -> bool {
+    state.screen_locked.load(Ordering::Relaxed) && state.settings.lock().unwrap().pause_sync_when_locked
+}
+
+/// Track the lock state until shutdown, emitting "screen-lock-changed" on transitions.
+pub async fn watch(app: AppHandle) {
+    let state = app.state::<AppState>();
+    let detector = Detector::new().await;
+    while !state.is_shutdown() {
+        let locked = detector.is_locked().await;
+        if state.screen_locked.swap(locked, Ordering::Relaxed) != locked {
+            tracing::info!("[ScreenLock] Screen {}", if locked { "locked" } else { "unlocked" });
+            crate::events::emit(&app, crate::events::ScreenLockChanged { locked });
+            let held = !state.pending_clipboard.lock().unwrap().is_empty();
+            if !locked && held && state.settings.lock().unwrap().pause_sync_when_locked {
+                crate::send_notification(
+                    &app,
+                    &crate::i18n::t("notify.clipboard_waiting.title"),
+                    &crate::i18n::t("notify.clipboard_waiting.body"),
+                    false,
+                    None,
+                    "history",
+                    crate::NotificationPayload::None,
+                );
+            }
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct Detector {
+    system: Option<zbus::Connection>,
+    session: Option<zbus::Connection>,
+}
+
+#[cfg(target_os = "linux")]
+impl Detector {
+    async fn new() -> Self {
+        Self { system: zbus::Connection::system().await.ok(), session: zbus::Connection::session().await.ok() }
+    }
+
+    async fn is_locked(&self) -> bool {
+        if let Some(locked) = self.logind_locked().await {
+            return locked;
+        }
+        self.screensaver_active().await.unwrap_or(false)
+    }
+
+    // Set by GNOME/KDE/most lockers; unavailable inside some sandboxes
+    async fn logind_locked(&self) -> Option<bool> {
+        let proxy = zbus::Proxy::new(
+            self.system.as_ref()?,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            "org.freedesktop.login1.Session",
+        )
+        .await
+        .ok()?;
+        proxy.get_property::<bool>("LockedHint").await.ok()
+    }
+
+    async fn screensaver_active(&self) -> Option<bool> {
+        let proxy = zbus::Proxy::new(
+            self.session.as_ref()?,
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver",
+        )
+        .await
+        .ok()?;
+        proxy.call::<_, _, bool>("GetActive", &()).await.ok()
+    }
+}
+
+#[cfg(target_os = "windows")]
+struct Detector;
+
+#[cfg(target_os = "windows")]
+impl Detector {
+    async fn new() -> Self {
+        Self
+    }
+
+    async fn is_locked(&self) -> bool {
+        use windows::core::PWSTR;
+        use windows::Win32::System::RemoteDesktop::{
+            WTSFreeMemory, WTSQuerySessionInformationW, WTSSessionInfoEx, WTSINFOEXW, WTS_CURRENT_SERVER_HANDLE,
+            WTS_CURRENT_SESSION, WTS_SESSIONSTATE_LOCK,
+        };
+        let mut buf = PWSTR::null();
+        let mut len = 0u32;
+        unsafe {
+            if WTSQuerySessionInformationW(WTS_CURRENT_SERVER_HANDLE, WTS_CURRENT_SESSION, WTSSessionInfoEx, &mut buf, &mut len)
+                .is_err()
+                || buf.is_null()
+            {
+                return false;
+            }
+            let info = &*(buf.0 as *const WTSINFOEXW);
+            let locked = info.Level == 1 && info.Data.WTSInfoExLevel1.SessionFlags == WTS_SESSIONSTATE_LOCK as i32;
+            WTSFreeMemory(buf.0 as *mut std::ffi::c_void);
+            locked
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+struct Detector;
+
+#[cfg(target_os = "macos")]
+impl Detector {
+    async fn new() -> Self {
+        Self
+    }
+
+    async fn is_locked(&self) -> bool {
+        use core_foundation::base::{CFType, TCFType};
+        use core_foundation::boolean::CFBoolean;
+        use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+        use core_foundation::string::CFString;
+
+        #[link(name = "CoreGraphics", kind = "framework")]
+        extern "C" {
+            fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+        }
+
+        let raw = unsafe { CGSessionCopyCurrentDictionary() };
+        if raw.is_null() {
+            return false;
+        }
+        let dict: CFDictionary<CFString, CFType> = unsafe { CFDictionary::wrap_under_create_rule(raw) };
+        dict.find(CFString::from_static_string("CGSSessionScreenIsLocked"))
+            .and_then(|v| v.downcast::<CFBoolean>())
+            .map(bool::from)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+struct Detector;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+impl Detector {
+    async fn new() -> Self {
+        Self
+    }
+
+    async fn is_locked(&self) -> bool {
+        false
+    }
+}
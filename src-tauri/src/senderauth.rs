@@ -0,0 +1,176 @@
+//! Which incoming clipboard items and file requests are acted on (`sender_authentication`).
+//!
+//! Decrypting a message only proves the sender holds the cluster key (or a guest key), not that
+//! it's one of the devices in our list, and it says nothing about the address it came from.
+//!
+//! - `off`: act on anything that decrypts (the behaviour before this setting existed).
+//! - `trusted` (default): the message must come from the address of a trusted peer, or carry a
+//!   valid signature (`auth`) from a trusted device. The signature covers relayed items and
+//!   peers that moved since their last heartbeat.
+//! - `strict`: both. A trusted source address and a valid signature, so items and file requests
+//!   from versions that don't sign are dropped.
+//!
+//! Signatures are made with the sender's own Ed25519 key (see identity.rs), so holding the
+//! cluster key isn't enough to forge one. A clipboard item's covers its id, sender, timestamp,
+//! channel, text and file list. Receivers that only get some of the files (see
+//! `clipboard::data_for_peer`) get a copy re-signed by the sender; a relay can't re-sign, so a
+//! filtered relayed item only passes on the relay's address. File requests are signed the same
+//! way, with the requesting device's id and the time of the request.
+//!
+//! Membership announcements are always checked, whatever the setting: the admitting member
+//! signs the new device's id and public key along with the time it was admitted, and the
+//! signature must verify against the admitting member's pinned key.
+
+use crate::protocol::{ClipboardPayload, FileRequestPayload, MembershipAnnouncement};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+// Older signed requests are refused: they may be replays of a captured one
+const FILE_REQUEST_MAX_AGE_SECS: u64 = 5 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderAuth {
+    Off,
+    #[default]
+    Trusted,
+    Strict,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn payload_message(payload: &ClipboardPayload) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"clustercut-sender-v2");
+    hasher.update(payload.id.as_bytes());
+    hasher.update([0]);
+    hasher.update(payload.sender_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(payload.timestamp.to_be_bytes());
+    hasher.update(payload.channel.as_bytes());
+    hasher.update([0]);
+    hasher.update(Sha256::digest(payload.text.as_bytes()));
+    for file in payload.files.iter().flatten() {
+        hasher.update(file.name.as_bytes());
+        hasher.update([0]);
+        hasher.update(file.size.to_be_bytes());
+        hasher.update(file.hash.as_deref().unwrap_or_default().as_bytes());
+        hasher.update([0]);
+    }
+    hasher.finalize().to_vec()
+}
+
+fn file_request_message(req: &FileRequestPayload) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"clustercut-file-request-v1");
+    hasher.update(req.id.as_bytes());
+    hasher.update([0]);
+    hasher.update((req.file_index as u64).to_be_bytes());
+    hasher.update(req.offset.to_be_bytes());
+    hasher.update(req.requester_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(req.requested_at.to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn membership_message(announcement: &MembershipAnnouncement) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"clustercut-membership-v1");
+    hasher.update(announcement.member.id.as_bytes());
+    hasher.update([0]);
+    hasher.update(announcement.member.public_key.as_deref().unwrap_or_default().as_bytes());
+    hasher.update([0]);
+    hasher.update(announcement.admitted_by.as_bytes());
+    hasher.update([0]);
+    hasher.update(announcement.admitted_at.to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Sign an item we're sending (no-op without a key).
+pub fn sign(state: &AppState, payload: &mut ClipboardPayload) {
+    payload.auth = crate::identity::sign(state, &payload_message(payload));
+}
+
+/// Sign a file request as this device.
+pub fn sign_file_request(state: &AppState, req: &mut FileRequestPayload) {
+    req.requester_id = state.local_device_id.lock().unwrap().clone();
+    req.requested_at = now();
+    req.auth = crate::identity::sign(state, &file_request_message(req));
+}
+
+/// Sign a membership announcement as the admitting member.
+pub fn sign_membership(state: &AppState, announcement: &mut MembershipAnnouncement) {
+    announcement.auth = crate::identity::sign(state, &membership_message(announcement));
+}
+
+/// Whether `signature` over `message` is from `device_id`, a trusted, unexpired device whose
+/// public key we know.
+fn signed_by_trusted(state: &AppState, device_id: &str, message: &[u8], signature: Option<&str>) -> bool {
+    let Some(signature) = signature else {
+        return false;
+    };
+    // Separate statements: known_peers is locked before peers elsewhere
+    let known = state.known_peers.lock().unwrap().get(device_id).cloned();
+    let online = state.peers.lock().unwrap().get(device_id).cloned();
+    let Some(device) = online.or(known).filter(|p| p.is_trusted && !crate::guest::is_expired(p, now())) else {
+        return false;
+    };
+    device.public_key.as_deref().is_some_and(|key| crate::identity::verify(key, message, signature))
+}
+
+fn clipboard_signed(state: &AppState, payload: &ClipboardPayload) -> bool {
+    signed_by_trusted(state, &payload.sender_id, &payload_message(payload), payload.auth.as_deref())
+}
+
+/// Whether a membership announcement carries a valid signature from the (trusted) member that
+/// admitted the device. A device can't vouch for itself.
+pub fn membership_signed(state: &AppState, announcement: &MembershipAnnouncement) -> bool {
+    announcement.member.id != announcement.admitted_by
+        && signed_by_trusted(state, &announcement.admitted_by, &membership_message(announcement), announcement.auth.as_deref())
+}
+
+fn file_request_signed(state: &AppState, req: &FileRequestPayload) -> bool {
+    now().saturating_sub(req.requested_at) <= FILE_REQUEST_MAX_AGE_SECS
+        && signed_by_trusted(state, &req.requester_id, &file_request_message(req), req.auth.as_deref())
+}
+
+fn is_address_of(peer: &crate::peer::Peer, ip: IpAddr, now: u64) -> bool {
+    peer.is_trusted && !crate::guest::is_expired(peer, now) && (peer.ip == ip || peer.addresses.iter().any(|a| a.addr.ip() == ip))
+}
+
+/// Whether `ip` is an address of a trusted, unexpired peer (online or not).
+pub fn trusted_addr(state: &AppState, ip: IpAddr) -> bool {
+    let now = now();
+    let known = state.known_peers.lock().unwrap().values().any(|p| is_address_of(p, ip, now));
+    known || state.peers.lock().unwrap().values().any(|p| is_address_of(p, ip, now))
+}
+
+/// Check an incoming clipboard item from `ip`. Err says why it isn't acted on.
+pub fn check_clipboard(state: &AppState, ip: IpAddr, payload: &ClipboardPayload) -> Result<(), &'static str> {
+    let mode = state.settings.lock().unwrap().sender_authentication;
+    match mode {
+        SenderAuth::Off => Ok(()),
+        SenderAuth::Trusted if trusted_addr(state, ip) || clipboard_signed(state, payload) => Ok(()),
+        SenderAuth::Trusted => Err("not from a trusted device's address and not signed by one"),
+        SenderAuth::Strict if !trusted_addr(state, ip) => Err("not from a trusted device's address"),
+        SenderAuth::Strict if !clipboard_signed(state, payload) => Err("missing or invalid sender signature"),
+        SenderAuth::Strict => Ok(()),
+    }
+}
+
+/// Check an incoming file request from `ip`. Err says why the file isn't served.
+pub fn check_file_request(state: &AppState, ip: IpAddr, req: &FileRequestPayload) -> Result<(), &'static str> {
+    let mode = state.settings.lock().unwrap().sender_authentication;
+    match mode {
+        SenderAuth::Off => Ok(()),
+        SenderAuth::Trusted if trusted_addr(state, ip) || file_request_signed(state, req) => Ok(()),
+        SenderAuth::Trusted => Err("not from a trusted device's address and not signed by one"),
+        SenderAuth::Strict if !trusted_addr(state, ip) => Err("not from a trusted device's address"),
+        SenderAuth::Strict if !file_request_signed(state, req) => Err("missing, stale or invalid requester signature"),
+        SenderAuth::Strict => Ok(()),
+    }
+}
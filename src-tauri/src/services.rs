@@ -0,0 +1,98 @@
+//! macOS Services menu: "Send to ClusterCut".
+//!
+//! A Quick Action is installed in ~/Library/Services (built from assets/macos-service with
+//! this executable's path) when the app runs from its bundle. It pipes the selected text to
+//! `clustercut share`, which hands it to the running instance over the control socket (see
+//! control.rs); from there it goes out like any copy. It's only installed once the user turns
+//! on the `macos_service` setting, and turning it off removes it again. Nothing happens on
+//! other platforms.
+
+#[cfg(target_os = "macos")]
+const INFO_PLIST: &str = include_str!("../assets/macos-service/Info.plist");
+#[cfg(target_os = "macos")]
+const DOCUMENT_WFLOW: &str = include_str!("../assets/macos-service/document.wflow");
+
+#[cfg(target_os = "macos")]
+fn workflow_dir() -> Option<std::path::PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    let name = match crate::storage::active_profile() {
+        Some(profile) => format!("Send to ClusterCut ({}).workflow", profile),
+        None => "Send to ClusterCut.workflow".to_string(),
+    };
+    Some(std::path::PathBuf::from(home).join("Library/Services").join(name))
+}
+
+#[cfg(target_os = "macos")]
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Single-quoted for the shell
+#[cfg(target_os = "macos")]
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Install or remove the Services menu entry to match the `macos_service` setting.
+#[cfg(target_os = "macos")]
+pub fn configure(enabled: bool) {
+    let Some(dir) = workflow_dir() else {
+        return;
+    };
+    if !enabled {
+        if dir.exists() {
+            match std::fs::remove_dir_all(&dir) {
+                Ok(()) => {
+                    tracing::info!("[Services] Removed {:?}", dir);
+                    refresh();
+                }
+                Err(e) => tracing::warn!("[Services] Failed to remove {:?}: {}", dir, e),
+            }
+        }
+        return;
+    }
+
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    let exe = exe.to_string_lossy().to_string();
+    // A dev build would leave an entry pointing into target/
+    if !exe.contains(".app/Contents/MacOS/") {
+        tracing::debug!("[Services] Not running from the app bundle; not installing the Services entry");
+        return;
+    }
+    let mut command = shell_quote(&exe);
+    if let Some(profile) = crate::storage::active_profile() {
+        command.push_str(&format!(" --profile {}", shell_quote(&profile)));
+    }
+    command.push_str(" share");
+
+    let info = INFO_PLIST.replace("{{MENU_ITEM}}", &xml_escape(&crate::i18n::t("service.menu_item")));
+    let document = DOCUMENT_WFLOW.replace("{{COMMAND}}", &xml_escape(&command));
+    let contents = dir.join("Contents");
+    // Rewritten only when something changed (the app moved, another language)
+    let current = std::fs::read_to_string(contents.join("document.wflow")).ok();
+    let current_info = std::fs::read_to_string(contents.join("Info.plist")).ok();
+    if current.as_deref() == Some(document.as_str()) && current_info.as_deref() == Some(info.as_str()) {
+        return;
+    }
+    let written = std::fs::create_dir_all(&contents)
+        .and_then(|_| std::fs::write(contents.join("Info.plist"), &info))
+        .and_then(|_| std::fs::write(contents.join("document.wflow"), &document));
+    match written {
+        Ok(()) => {
+            tracing::info!("[Services] Installed {:?}", dir);
+            refresh();
+        }
+        Err(e) => tracing::warn!("[Services] Failed to install {:?}: {}", dir, e),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn configure(_enabled: bool) {}
+
+// Have the pasteboard server pick up the change now rather than at next login
+#[cfg(target_os = "macos")]
+fn refresh() {
+    let _ = std::process::Command::new("/System/Library/CoreServices/pbs").arg("-update").status();
+}
@@ -0,0 +1,342 @@
+//! In-process virtual network for deterministic protocol tests.
+//!
+//! `VirtualNetwork` routes datagrams between `VirtualTransport`s over in-memory channels,
+//! and `Cluster` spins up N nodes (each with its own `AppState`) on top of it. Nothing here
+//! touches real sockets or mDNS. The pairing handshake (PairRequest -> PairResponse ->
+//! PairConfirm) runs through the same functions `handle_message` calls; everything past that
+//! needs a Tauri `AppHandle` and isn't covered here.
+
+use crate::protocol::Message;
+use crate::state::AppState;
+use crate::transport::MessageTransport;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+type Datagram = (SocketAddr, Vec<u8>);
+
+#[derive(Clone, Default)]
+pub struct VirtualNetwork {
+    endpoints: Arc<Mutex<HashMap<SocketAddr, mpsc::UnboundedSender<Datagram>>>>,
+    // Links that currently drop traffic (stored both ways)
+    partitions: Arc<Mutex<HashSet<(SocketAddr, SocketAddr)>>>,
+}
+
+impl VirtualNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a new endpoint at `addr`. Returns its transport and inbox.
+    pub fn join(&self, addr: SocketAddr) -> (VirtualTransport, mpsc::UnboundedReceiver<Datagram>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.endpoints.lock().unwrap().insert(addr, tx);
+        (VirtualTransport { addr, net: self.clone() }, rx)
+    }
+
+    /// Detach an endpoint (simulates a node going offline).
+    pub fn leave(&self, addr: SocketAddr) {
+        self.endpoints.lock().unwrap().remove(&addr);
+    }
+
+    pub fn partition(&self, a: SocketAddr, b: SocketAddr) {
+        let mut p = self.partitions.lock().unwrap();
+        p.insert((a, b));
+        p.insert((b, a));
+    }
+
+    pub fn heal(&self, a: SocketAddr, b: SocketAddr) {
+        let mut p = self.partitions.lock().unwrap();
+        p.remove(&(a, b));
+        p.remove(&(b, a));
+    }
+
+    fn deliver(&self, from: SocketAddr, to: SocketAddr, data: &[u8]) -> Result<(), String> {
+        if self.partitions.lock().unwrap().contains(&(from, to)) {
+            return Err(format!("{} unreachable from {} (partitioned)", to, from));
+        }
+        let endpoints = self.endpoints.lock().unwrap();
+        let tx = endpoints.get(&to).ok_or(format!("No endpoint at {}", to))?;
+        tx.send((from, data.to_vec())).map_err(|_| format!("Endpoint {} closed", to))
+    }
+}
+
+#[derive(Clone)]
+pub struct VirtualTransport {
+    addr: SocketAddr,
+    net: VirtualNetwork,
+}
+
+impl MessageTransport for VirtualTransport {
+    async fn send_message(
+        &self,
+        addr: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.net.deliver(self.addr, addr, data).map_err(|e| e.into())
+    }
+}
+
+pub struct SimNode {
+    pub state: AppState,
+    pub transport: VirtualTransport,
+    inbox: mpsc::UnboundedReceiver<Datagram>,
+}
+
+impl SimNode {
+    pub fn addr(&self) -> SocketAddr {
+        self.transport.addr
+    }
+
+    pub fn key(&self) -> Option<[u8; 32]> {
+        let ck = self.state.cluster_key.lock().unwrap();
+        let key = ck.as_ref().filter(|k| k.len() == 32)?;
+        let mut arr = [0u8; 32];
+        arr.copy_from_slice(key);
+        Some(arr)
+    }
+
+    /// Accept pairings with `pin` on the network called `network_name`.
+    pub fn host(&self, network_name: &str, pin: &str) {
+        *self.state.network_name.lock().unwrap() = network_name.to_string();
+        *self.state.network_pin.lock().unwrap() = pin.to_string();
+    }
+
+    /// Drain everything currently queued for this node, in arrival order.
+    pub fn drain(&mut self) -> Vec<(SocketAddr, Message)> {
+        let mut out = Vec::new();
+        while let Ok((from, data)) = self.inbox.try_recv() {
+            match serde_json::from_slice::<Message>(&data) {
+                Ok(msg) => out.push((from, msg)),
+                Err(e) => tracing::warn!("sim: undecodable datagram from {}: {}", from, e),
+            }
+        }
+        out
+    }
+}
+
+pub struct Cluster {
+    pub net: VirtualNetwork,
+    pub nodes: Vec<SimNode>,
+}
+
+impl Cluster {
+    /// N nodes on 10.0.0.x:4654. With `shared_key`, all nodes start in the same cluster.
+    pub fn new(n: usize, shared_key: bool) -> Self {
+        let net = VirtualNetwork::new();
+        let key: [u8; 32] = rand::random();
+        let nodes = (0..n)
+            .map(|i| {
+                let addr = SocketAddr::from(([10, 0, 0, i as u8 + 1], crate::DEFAULT_PORT));
+                let (transport, inbox) = net.join(addr);
+                let state = AppState::new();
+                *state.local_device_id.lock().unwrap() = format!("sim-{}", i);
+                let node_key = if shared_key { key } else { rand::random() };
+                *state.cluster_key.lock().unwrap() = Some(node_key.to_vec());
+                *state.device_key.lock().unwrap() = Some(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng));
+                SimNode { state, transport, inbox }
+            })
+            .collect();
+        Self { net, nodes }
+    }
+
+    /// Send `msg` from node `from` to every other node (what the app does for broadcasts).
+    pub async fn broadcast(&self, from: usize, msg: &Message) -> usize {
+        let data = serde_json::to_vec(msg).expect("serialize");
+        let mut delivered = 0;
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i == from {
+                continue;
+            }
+            if self.nodes[from].transport.send_message(node.addr(), &data).await.is_ok() {
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto;
+    use crate::protocol::{ClipboardPayload, HistoryAnnotation, PairingError};
+
+    fn clipboard_cipher(node: &SimNode, text: &str) -> Vec<u8> {
+        let payload = ClipboardPayload {
+            id: uuid::Uuid::new_v4().to_string(),
+            text: text.to_string(),
+            files: None,
+            timestamp: 0,
+            sender: "sim".to_string(),
+            sender_id: node.state.local_device_id.lock().unwrap().clone(),
+            source: None,
+            trace: None,
+            channel: crate::protocol::DEFAULT_CHANNEL.to_string(),
+            link: None,
+            content: None,
+            auth: None,
+        };
+        crypto::encrypt(&node.key().unwrap(), &serde_json::to_vec(&payload).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn broadcast_reaches_every_other_node() {
+        let mut cluster = Cluster::new(3, true);
+        let delivered = cluster.broadcast(0, &Message::HistoryDelete("x".into())).await;
+        assert_eq!(delivered, 2);
+        assert!(cluster.nodes[0].drain().is_empty());
+        for node in &mut cluster.nodes[1..] {
+            let msgs = node.drain();
+            assert_eq!(msgs.len(), 1);
+            assert!(matches!(&msgs[0].1, Message::HistoryDelete(id) if id == "x"));
+        }
+    }
+
+    #[tokio::test]
+    async fn partition_drops_traffic_until_healed() {
+        let mut cluster = Cluster::new(2, true);
+        let (a, b) = (cluster.nodes[0].addr(), cluster.nodes[1].addr());
+        cluster.net.partition(a, b);
+        assert_eq!(cluster.broadcast(0, &Message::HistoryDelete("x".into())).await, 0);
+        assert!(cluster.nodes[1].drain().is_empty());
+
+        cluster.net.heal(a, b);
+        assert_eq!(cluster.broadcast(0, &Message::HistoryDelete("y".into())).await, 1);
+        assert_eq!(cluster.nodes[1].drain().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn clipboard_only_decrypts_within_cluster() {
+        let mut cluster = Cluster::new(2, true);
+        let cipher = clipboard_cipher(&cluster.nodes[0], "hello");
+        cluster.broadcast(0, &Message::Clipboard(cipher)).await;
+
+        let key = cluster.nodes[1].key().unwrap();
+        let (_, msg) = cluster.nodes[1].drain().remove(0);
+        let Message::Clipboard(cipher) = msg else { panic!("expected Clipboard") };
+        let payload: ClipboardPayload = serde_json::from_slice(&crypto::decrypt(&key, &cipher).unwrap()).unwrap();
+        assert_eq!(payload.text, "hello");
+
+        let outsider = Cluster::new(1, false);
+        assert!(crypto::decrypt(&outsider.nodes[0].key().unwrap(), &cipher).is_err());
+    }
+
+    #[tokio::test]
+    async fn annotations_converge_last_writer_wins() {
+        let cluster = Cluster::new(2, true);
+        let older = HistoryAnnotation { id: "item".into(), note: Some("old".into()), updated_at: 1 };
+        let newer = HistoryAnnotation { id: "item".into(), note: Some("new".into()), updated_at: 2 };
+
+        // Opposite arrival order on each node
+        cluster.nodes[0].state.apply_history_annotation(older.clone());
+        cluster.nodes[0].state.apply_history_annotation(newer.clone());
+        cluster.nodes[1].state.apply_history_annotation(newer);
+        assert!(!cluster.nodes[1].state.apply_history_annotation(older));
+
+        for node in &cluster.nodes {
+            let notes = node.state.history_annotations.lock().unwrap();
+            assert_eq!(notes["item"].note.as_deref(), Some("new"));
+        }
+    }
+
+    // Deliver whatever `node` has queued to the pairing handlers, the way handle_message does.
+    // Returns the messages it didn't handle and what the initiator would report to the UI.
+    async fn pump_pairing(node: &mut SimNode) -> (Vec<Message>, Vec<Result<(), PairingError>>) {
+        let (mut rest, mut outcomes) = (Vec::new(), Vec::new());
+        for (from, msg) in node.drain() {
+            match msg {
+                Message::PairRequest { msg, device_id, one_time, kdf } => {
+                    crate::answer_pair_request(&node.state, &node.transport, from, msg, device_id, one_time, kdf).await;
+                }
+                Message::PairResponse { msg, device_id, confirmation } => {
+                    let outcome = crate::answer_pair_response(&node.state, &node.transport, from, &msg, &device_id, &confirmation).await;
+                    outcomes.push(outcome.unwrap_or(Err(PairingError::Timeout)));
+                }
+                other => rest.push(other),
+            }
+        }
+        (rest, outcomes)
+    }
+
+    async fn start_pairing(cluster: &Cluster, joiner: usize, host: usize, pin: &str) {
+        let network_name = cluster.nodes[host].state.network_name.lock().unwrap().clone();
+        let node = &cluster.nodes[joiner];
+        crate::send_pair_request(&node.state, &node.transport, cluster.nodes[host].addr(), &network_name, pin).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pairing_admits_joiner_with_matching_pin() {
+        let mut cluster = Cluster::new(2, false);
+        cluster.nodes[0].host("sim-net", "2468");
+        start_pairing(&cluster, 1, 0, "2468").await;
+
+        assert!(pump_pairing(&mut cluster.nodes[0]).await.0.is_empty());
+        let (_, outcomes) = pump_pairing(&mut cluster.nodes[1]).await;
+        assert_eq!(outcomes, vec![Ok(())]);
+
+        let (host, joiner) = cluster.nodes.split_at_mut(1);
+        let (host, joiner) = (&mut host[0], &joiner[0]);
+        let (_, msg) = host.drain().remove(0);
+        let Message::PairConfirm { device_id, confirmation, device_key } = msg else { panic!("expected PairConfirm") };
+        let admitted = crate::confirm_pairing(&host.state, &host.transport, joiner.addr(), device_id, confirmation, device_key)
+            .await
+            .expect("joiner admitted");
+        assert_eq!(admitted.device_id, "sim-1");
+        assert_eq!(admitted.public_key, crate::identity::public_key(&joiner.state));
+
+        // Both sides hold the same session key for the Welcome
+        let joiner_keys = crate::handshake::take(&joiner.state.handshake_sessions, &host.addr().to_string()).unwrap();
+        assert_eq!(joiner_keys.encryption, admitted.session_keys.encryption);
+    }
+
+    #[tokio::test]
+    async fn pairing_with_wrong_pin_fails_on_both_sides() {
+        let mut cluster = Cluster::new(2, false);
+        cluster.nodes[0].host("sim-net", "2468");
+        start_pairing(&cluster, 1, 0, "1357").await;
+
+        pump_pairing(&mut cluster.nodes[0]).await;
+        let (_, outcomes) = pump_pairing(&mut cluster.nodes[1]).await;
+        assert_eq!(outcomes, vec![Err(PairingError::WrongPin)]);
+        // No PairConfirm went out, and the joiner kept no session
+        assert!(cluster.nodes[0].drain().is_empty());
+        assert!(cluster.nodes[1].state.handshake_sessions.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn guest_refuses_to_answer_pair_request() {
+        let mut cluster = Cluster::new(2, false);
+        cluster.nodes[0].host("sim-net", "2468");
+        *cluster.nodes[0].state.guest_expires_at.lock().unwrap() = Some(u64::MAX);
+        start_pairing(&cluster, 1, 0, "2468").await;
+
+        pump_pairing(&mut cluster.nodes[0]).await;
+        let (rest, outcomes) = pump_pairing(&mut cluster.nodes[1]).await;
+        assert!(outcomes.is_empty());
+        assert!(matches!(rest.as_slice(), [Message::PairRejected { reason: PairingError::Rejected, .. }]));
+        assert!(cluster.nodes[0].state.pending_confirmations.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn repeated_pair_requests_are_rate_limited() {
+        let mut cluster = Cluster::new(2, false);
+        cluster.nodes[0].host("sim-net", "2468");
+        start_pairing(&cluster, 1, 0, "1357").await;
+        start_pairing(&cluster, 1, 0, "2468").await;
+
+        pump_pairing(&mut cluster.nodes[0]).await;
+        // Only the first request was answered
+        let (_, outcomes) = pump_pairing(&mut cluster.nodes[1]).await;
+        assert_eq!(outcomes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn offline_node_is_unreachable() {
+        let cluster = Cluster::new(3, true);
+        cluster.net.leave(cluster.nodes[2].addr());
+        assert_eq!(cluster.broadcast(0, &Message::HistoryDelete("x".into())).await, 1);
+    }
+}
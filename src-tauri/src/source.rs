@@ -0,0 +1,159 @@
+use crate::protocol::SourceContext;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest window title we pass along (titles can embed whole URLs/documents).
+const MAX_TITLE_LEN: usize = 120;
+
+/// How long a lookup is reused. On macOS and Linux each lookup runs a helper process, and
+/// apps often write the clipboard several times per copy; nobody switches apps and copies
+/// again this fast.
+const CACHE_TTL: Duration = Duration::from_secs(1);
+
+static LAST_LOOKUP: Lazy<Mutex<Option<(Instant, Option<SourceContext>)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Best-effort lookup of the foreground application and window title at copy time.
+/// Returns None where the platform doesn't expose it (e.g. Wayland) or on any failure.
+pub fn active_window() -> Option<SourceContext> {
+    let mut last = LAST_LOOKUP.lock().unwrap();
+    if let Some((_, source)) = last.as_ref().filter(|(at, _)| at.elapsed() < CACHE_TTL) {
+        return source.clone();
+    }
+    let source = lookup();
+    *last = Some((Instant::now(), source.clone()));
+    source
+}
+
+fn lookup() -> Option<SourceContext> {
+    let (app, title) = platform_active_window()?;
+    let app = app.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+    let window_title = title
+        .map(|t| t.trim().chars().take(MAX_TITLE_LEN).collect::<String>())
+        .filter(|t| !t.is_empty());
+    if app.is_none() && window_title.is_none() {
+        return None;
+    }
+    Some(SourceContext { app, window_title })
+}
+
+/// Allow-list mode: a copy may be broadcast if its app identifier (process name on Windows and
+/// Linux, application name on macOS) or its full window title equals an entry, ignoring case.
+/// Substring matches are not enough: "Code" must not admit a browser tab titled "Code review".
+/// Copies whose source can't be determined stay local.
+pub fn is_allowed(allowed_apps: &[String], source: Option<&SourceContext>) -> bool {
+    let Some(source) = source else {
+        return false;
+    };
+    let app = source.app.as_deref().map(|a| a.trim().to_lowercase());
+    let title = source.window_title.as_deref().map(|t| t.trim().to_lowercase());
+    allowed_apps
+        .iter()
+        .map(|entry| entry.trim().to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| app.as_deref() == Some(entry.as_str()) || title.as_deref() == Some(entry.as_str()))
+}
+
+#[cfg(target_os = "windows")]
+fn platform_active_window() -> Option<(Option<String>, Option<String>)> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId,
+    };
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return None;
+        }
+
+        let mut buf = [0u16; 512];
+        let len = GetWindowTextW(hwnd, &mut buf);
+        let title = String::from_utf16_lossy(&buf[..len.max(0) as usize]);
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        let app = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)
+            .ok()
+            .and_then(|handle| {
+                let mut name = [0u16; 260];
+                let mut size = name.len() as u32;
+                let result = QueryFullProcessImageNameW(
+                    handle,
+                    PROCESS_NAME_WIN32,
+                    PWSTR(name.as_mut_ptr()),
+                    &mut size,
+                );
+                let _ = CloseHandle(handle);
+                result.ok()?;
+                let path = String::from_utf16_lossy(&name[..size as usize]);
+                std::path::Path::new(&path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+            });
+
+        Some((app, Some(title)))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_active_window() -> Option<(Option<String>, Option<String>)> {
+    // Window titles need Accessibility permission; fall back to the app name alone
+    let script = [
+        "tell application \"System Events\"",
+        "set p to first application process whose frontmost is true",
+        "set n to name of p",
+        "set t to \"\"",
+        "try",
+        "set t to name of front window of p",
+        "end try",
+        "return n & linefeed & t",
+        "end tell",
+    ];
+    let mut cmd = std::process::Command::new("osascript");
+    for line in script {
+        cmd.arg("-e").arg(line);
+    }
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut lines = stdout.lines();
+    let app = lines.next().map(|s| s.to_string());
+    let title = lines.next().map(|s| s.to_string());
+    Some((app, title))
+}
+
+#[cfg(target_os = "linux")]
+fn platform_active_window() -> Option<(Option<String>, Option<String>)> {
+    // X11 only (via xdotool); Wayland compositors don't expose the focused window
+    std::env::var_os("DISPLAY")?;
+    let xdotool = |arg: &str| -> Option<String> {
+        let output = std::process::Command::new("xdotool")
+            .args(["getactivewindow", arg])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let title = xdotool("getwindowname");
+    let app = xdotool("getwindowpid")
+        .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok());
+    if title.is_none() && app.is_none() {
+        return None;
+    }
+    Some((app, title))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn platform_active_window() -> Option<(Option<String>, Option<String>)> {
+    None
+}
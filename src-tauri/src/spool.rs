@@ -0,0 +1,155 @@
+//! Memory-bounded reading of incoming message streams.
+//!
+//! A message stream is kept in memory only while it's small (`SPOOL_THRESHOLD`) and the
+//! process-wide budget (`MEMORY_BUDGET`) has room for it. Past either limit it goes to a temp
+//! file as it arrives and is parsed straight from that file, so the JSON of a large clipboard
+//! item (byte arrays are several times their decoded size as JSON) is never held whole. Per
+//! connection, the receive window and concurrent streams are capped in transport.rs.
+
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// Largest message accepted on a stream
+const MAX_MESSAGE_BYTES: usize = 10 * 1024 * 1024;
+// Streams longer than this are spooled to disk
+const SPOOL_THRESHOLD: usize = 256 * 1024;
+// Bytes of stream data held in memory across all connections (one permit per byte)
+const MEMORY_BUDGET: usize = 32 * 1024 * 1024;
+const READ_CHUNK: usize = 64 * 1024;
+// Spool files left behind by a crash are removed once they're this old
+const STALE_SPOOL_SECS: u64 = 60 * 60;
+
+static BUDGET: Lazy<Arc<Semaphore>> = Lazy::new(|| Arc::new(Semaphore::new(MEMORY_BUDGET)));
+
+static SPOOL_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    let dir = std::env::temp_dir().join("clustercut-spool");
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("Failed to create spool directory {}: {}", dir.display(), e);
+    }
+    sweep(&dir);
+    dir
+});
+
+/// A received message: in memory, or in a spool file that's deleted when this is dropped.
+pub enum Inbound {
+    Memory {
+        data: Vec<u8>,
+        // Share of `MEMORY_BUDGET`; None for datagrams (bounded by the datagram buffer)
+        _permit: Option<OwnedSemaphorePermit>,
+    },
+    Spooled {
+        path: PathBuf,
+        len: usize,
+    },
+}
+
+impl Inbound {
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Inbound::Memory { data, _permit: None }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Inbound::Memory { data, .. } => data.len(),
+            Inbound::Spooled { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Deserialize the message, streaming it from disk when it was spooled.
+    pub fn parse<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        match self {
+            Inbound::Memory { data, .. } => serde_json::from_slice(data),
+            Inbound::Spooled { path, .. } => {
+                let file = std::fs::File::open(path).map_err(serde_json::Error::io)?;
+                serde_json::from_reader(std::io::BufReader::new(file))
+            }
+        }
+    }
+}
+
+impl Drop for Inbound {
+    fn drop(&mut self) {
+        if let Inbound::Spooled { path, .. } = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// Grow `permit` by `bytes`, or false if the budget can't cover them right now
+fn reserve(permit: &mut Option<OwnedSemaphorePermit>, bytes: usize) -> bool {
+    let Ok(extra) = BUDGET.clone().try_acquire_many_owned(bytes as u32) else {
+        return false;
+    };
+    match permit {
+        Some(held) => held.merge(extra),
+        None => *permit = Some(extra),
+    }
+    true
+}
+
+/// Read a whole message stream (None if it was empty).
+pub async fn read_stream(recv: &mut quinn::RecvStream) -> Result<Option<Inbound>, Box<dyn Error + Send + Sync>> {
+    let mut data = Vec::new();
+    let mut permit = None;
+    // Once spooled: the Inbound (removes the file on error) and the open file
+    let mut spooled: Option<(Inbound, tokio::fs::File)> = None;
+
+    while let Some(chunk) = recv.read_chunk(READ_CHUNK, true).await? {
+        let bytes = chunk.bytes;
+        let total = spooled.as_ref().map_or(data.len(), |(inbound, _)| inbound.len()) + bytes.len();
+        if total > MAX_MESSAGE_BYTES {
+            return Err(format!("message exceeds {} bytes", MAX_MESSAGE_BYTES).into());
+        }
+        if spooled.is_none() && (total > SPOOL_THRESHOLD || !reserve(&mut permit, bytes.len())) {
+            let path = SPOOL_DIR.join(format!("{}.msg", uuid::Uuid::new_v4()));
+            // Created first so the file is removed if anything below fails
+            let inbound = Inbound::Spooled { path: path.clone(), len: data.len() };
+            let mut file = tokio::fs::File::create(&path).await?;
+            file.write_all(&data).await?;
+            spooled = Some((inbound, file));
+            data = Vec::new();
+            permit = None;
+        }
+        match spooled.as_mut() {
+            Some((Inbound::Spooled { len, .. }, file)) => {
+                file.write_all(&bytes).await?;
+                *len += bytes.len();
+            }
+            _ => data.extend_from_slice(&bytes),
+        }
+    }
+
+    if let Some((inbound, mut file)) = spooled {
+        file.flush().await?;
+        tracing::debug!("Spooled a {} byte message to disk", inbound.len());
+        return Ok(Some(inbound));
+    }
+    Ok((!data.is_empty()).then(|| Inbound::Memory { data, _permit: permit }))
+}
+
+// Remove spool files from earlier runs that never got cleaned up
+fn sweep(dir: &std::path::Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age.as_secs() > STALE_SPOOL_SECS);
+        if stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
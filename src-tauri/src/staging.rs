@@ -0,0 +1,129 @@
+//! Files dropped onto the window, prepared before they're sent.
+//!
+//! `stage` checks the paths and returns a batch id straight away. Metadata (hashes, previews)
+//! is built on a blocking thread that reports `staging-progress`, so dropping a few large files
+//! doesn't freeze the window. Once a batch is ready, `send` broadcasts it like `send_files`.
+//! Batches that are never sent are dropped after `STAGE_TTL_SECS`.
+
+use crate::protocol::FileMetadata;
+use crate::state::AppState;
+use crate::transport::Transport;
+use serde::Serialize;
+use tauri::AppHandle;
+
+// Unsent batches are forgotten after this long
+const STAGE_TTL_SECS: u64 = 30 * 60;
+// Batches that can wait to be sent at once
+const MAX_STAGED: usize = 16;
+
+pub struct StagedBatch {
+    pub paths: Vec<String>,
+    // None while the metadata is still being built
+    pub metas: Option<Vec<FileMetadata>>,
+    pub created_at: u64,
+}
+
+/// Preparation of a staged batch; sent once at the start, after each file, and when ready.
+#[derive(Serialize, Clone, Debug)]
+pub struct StagingProgress {
+    pub batch_id: String,
+    // File that was just processed
+    pub file_name: Option<String>,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub ready: bool,
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Validate `paths` and start preparing them. Returns the batch id.
+pub fn stage(app: &AppHandle, state: &AppState, paths: &[String]) -> Result<String, String> {
+    let paths = crate::clipboard::validate_files(state, paths)?;
+    let now = now();
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    {
+        let mut staged = state.staged_files.lock().unwrap();
+        staged.retain(|_, b| now.saturating_sub(b.created_at) < STAGE_TTL_SECS);
+        if staged.len() >= MAX_STAGED {
+            return Err("Too many dropped batches are waiting to be sent".to_string());
+        }
+        staged.insert(batch_id.clone(), StagedBatch { paths: paths.clone(), metas: None, created_at: now });
+    }
+    tracing::info!("[Staging] Preparing {} dropped file(s) as {}", paths.len(), batch_id);
+
+    let (app, state, id) = (app.clone(), state.clone(), batch_id.clone());
+    tauri::async_runtime::spawn_blocking(move || prepare(&app, &state, &id, &paths));
+    Ok(batch_id)
+}
+
+fn prepare(app: &AppHandle, state: &AppState, batch_id: &str, paths: &[String]) {
+    let previews = state.settings.lock().unwrap().generate_file_previews;
+    let mut progress = StagingProgress {
+        batch_id: batch_id.to_string(),
+        file_name: None,
+        files_done: 0,
+        files_total: paths.len(),
+        bytes_done: 0,
+        bytes_total: paths.iter().map(|p| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0)).sum(),
+        ready: false,
+    };
+    crate::events::emit(app, &progress);
+
+    let mut metas = Vec::with_capacity(paths.len());
+    for path in paths {
+        // Discarded while we were working on it
+        if !state.staged_files.lock().unwrap().contains_key(batch_id) {
+            return;
+        }
+        let meta = crate::clipboard::file_metadata(state, std::path::Path::new(path), previews);
+        progress.files_done += 1;
+        progress.bytes_done += meta.size;
+        progress.file_name = Some(meta.name.clone());
+        metas.push(meta);
+        crate::events::emit(app, &progress);
+    }
+
+    match state.staged_files.lock().unwrap().get_mut(batch_id) {
+        Some(batch) => batch.metas = Some(metas),
+        None => return,
+    }
+    progress.ready = true;
+    crate::events::emit(app, &progress);
+}
+
+/// Send a prepared batch to `peers` (every peer when None). Returns the id of the sent item.
+pub fn send(
+    app: &AppHandle,
+    state: &AppState,
+    transport: &Transport,
+    batch_id: &str,
+    peers: Option<&[String]>,
+) -> Result<String, String> {
+    let batch = {
+        let mut staged = state.staged_files.lock().unwrap();
+        let ready = staged.get(batch_id).ok_or_else(|| format!("Staged batch {} not found", batch_id))?.metas.is_some();
+        if !ready {
+            return Err("The dropped files are still being prepared".to_string());
+        }
+        staged.remove(batch_id).expect("checked above")
+    };
+    if let Some(missing) = batch.paths.iter().find(|p| !std::path::Path::new(p).is_file()) {
+        return Err(format!("{} was moved or deleted after it was dropped", missing));
+    }
+
+    let StagedBatch { paths, metas, created_at } = batch;
+    let metas = metas.unwrap_or_default();
+    crate::clipboard::send_file_batch(app, state, transport, paths.clone(), metas.clone(), peers).inspect_err(|_| {
+        // Keep the batch so it can be sent once the peer is back
+        state.staged_files.lock().unwrap().insert(batch_id.to_string(), StagedBatch { paths, metas: Some(metas), created_at });
+    })
+}
+
+/// Forget a staged batch (stops its preparation if it's still running).
+pub fn discard(state: &AppState, batch_id: &str) -> bool {
+    state.staged_files.lock().unwrap().remove(batch_id).is_some()
+}
@@ -1,28 +1,137 @@
 use crate::peer::Peer;
+use crate::handshake::HandshakeMap;
 use crate::storage::AppSettings;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 // use crate::crypto::SpakeState; // We'll just use explicit path or generic if needed, but explicit path is best.
 // actually, let's use Any or just simple wrapper if circular dep is issue.
 // But valid rust module path is crate::crypto::SpakeState
 
+/// Counters for the incoming message path (exposed via get_message_metrics)
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct MessageMetrics {
+    pub received: u64,
+    pub parse_errors: u64,
+    pub rejected: u64,
+    // "field:kind" -> count
+    pub rejected_by_reason: HashMap<String, u64>,
+    // Dropped because the sender is on the blocklist
+    pub blocked: u64,
+    pub last_error: Option<String>,
+    // Processing queue, keyed by priority class ("pairing"/"clipboard"/"gossip")
+    pub queue_depth: HashMap<String, usize>,
+    pub queue_high_water: HashMap<String, usize>,
+    pub queue_dropped: HashMap<String, u64>,
+}
+
+/// Responder side of a handshake whose keys are derived but not yet confirmed by the initiator
+#[derive(Clone, Debug)]
+pub struct PendingConfirmation {
+    pub device_id: String,
+    pub session_keys: crate::crypto::SessionKeys,
+}
+
+/// An admission request waiting for the owner, with the address of the member relaying it
+/// (None when we ran the handshake ourselves)
+pub type RelayedAdmission = (crate::protocol::AdmissionRequest, Option<std::net::SocketAddr>);
+
+/// A device that passed the PIN check, on its way to a Welcome. Parked here while it waits
+/// for the owner's approval (held by the member that ran the handshake, which sends the
+/// Welcome once approved)
+#[derive(Clone, Debug)]
+pub struct PendingAdmission {
+    pub device_id: String,
+    pub addr: std::net::SocketAddr,
+    pub session_keys: crate::crypto::SessionKeys,
+    pub created_at: u64,
+    pub guest_duration_secs: Option<u64>,
+    // Joiner's public key from its PairConfirm
+    pub public_key: Option<String>,
+}
+
+/// Size and modification time of a file, to tell whether it changed since we last looked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified: Option<std::time::SystemTime>,
+}
+
+impl FileStamp {
+    pub fn of(path: &std::path::Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self { size: meta.len(), modified: meta.modified().ok() })
+    }
+}
+
+/// A received file we can hand out again when the same content is offered
+#[derive(Clone, Debug)]
+pub struct ReceivedHash {
+    pub path: String,
+    pub stamp: FileStamp,
+    pub received_at: u64,
+}
+
+/// Last clipboard content seen, for deduplication and loop prevention. Content is the text or a
+/// "FILES:"/"IMAGE:" signature; `sender_id` is set when it came from a peer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LastClipboard {
+    pub sender_id: Option<String>,
+    pub content: String,
+}
+
+impl LastClipboard {
+    pub fn local(content: String) -> Self {
+        Self { sender_id: None, content }
+    }
+
+    pub fn received(sender_id: &str, content: String) -> Self {
+        Self { sender_id: Some(sender_id.to_string()), content }
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub peers: Arc<Mutex<HashMap<String, Peer>>>,
-    // Store pending handshakes map: PeerID -> SpakeState
-    pub pending_handshakes: Arc<Mutex<HashMap<String, crate::crypto::SpakeState>>>,
+    // Store pending handshakes map: Addr -> SpakeState (entries expire, see handshake.rs)
+    pub pending_handshakes: Arc<HandshakeMap<crate::crypto::SpakeState>>,
     // Store completed session keys waiting for Welcome packet: Addr -> SessionKey
-    pub handshake_sessions: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    pub handshake_sessions: Arc<HandshakeMap<crate::crypto::SessionKeys>>,
+    // Responder: Addr -> keys waiting for the initiator's PairConfirm
+    pub pending_confirmations: Arc<HandshakeMap<PendingConfirmation>>,
+    // Responder: when each address last had a PairRequest answered (rate limit, see handshake.rs)
+    pub pair_request_times: Arc<Mutex<HashMap<std::net::IpAddr, std::time::Instant>>>,
     // Shared Network Key (One key to rule them all)
     pub cluster_key: Arc<Mutex<Option<Vec<u8>>>>,
+    // Device ID of the cluster owner (approves new members). Persisted.
+    pub cluster_owner: Arc<Mutex<Option<String>>>,
+    // Member side: Request ID -> paired device waiting for the owner's decision
+    pub pending_admissions: Arc<Mutex<HashMap<String, PendingAdmission>>>,
+    // Owner side: Request ID -> (Request, Relaying member's address; None if we hold the session)
+    pub admission_requests: Arc<Mutex<HashMap<String, RelayedAdmission>>>,
+    // Armed guest invite: (Invite deadline, Guest duration). The next device to pair joins as a guest.
+    pub guest_invite: Arc<Mutex<Option<(u64, u64)>>>,
+    // Set when this device is itself a guest: our membership expiry. Persisted.
+    pub guest_expires_at: Arc<Mutex<Option<u64>>>,
     // Known Peers (Persisted list of devices we know about)
     pub known_peers: Arc<Mutex<HashMap<String, Peer>>>,
+    // Per-peer auto-download overrides (Persisted alongside known peers)
+    pub peer_download_policies: Arc<Mutex<HashMap<String, crate::storage::AutoDownloadPolicy>>>,
+    // Overrides from clustercut.toml currently applied on top of the above (never persisted)
+    pub config_overrides: Arc<Mutex<crate::config::FileConfig>>,
+    // Remote actions each peer may perform here (see remote.rs)
+    pub peer_permissions: Arc<Mutex<HashMap<String, crate::storage::PeerPermissions>>>,
+    // Lifetime transfer totals per device (see peerstats.rs)
+    pub peer_stats: Arc<Mutex<crate::peerstats::StatsStore>>,
+    // Blocklist: Device ID -> Blocked Peer (Persisted, survives network resets)
+    pub blocked_peers: Arc<Mutex<HashMap<String, crate::storage::BlockedPeer>>>,
     pub local_device_id: Arc<Mutex<String>>,
+    // This device's signing key (see identity.rs); None until loaded
+    pub device_key: Arc<Mutex<Option<ed25519_dalek::SigningKey>>>,
     // Discovery Service
     pub discovery: Arc<Mutex<Option<crate::discovery::Discovery>>>,
     // Last Clipboard Content (for deduplication and loop prevention)
-    pub last_clipboard_content: Arc<Mutex<String>>,
+    pub last_clipboard_content: Arc<Mutex<LastClipboard>>,
     // Human Readable Network Name
     pub network_name: Arc<Mutex<String>>,
     // Network PIN (6-char alphanumeric, for auto-joining)
@@ -33,12 +142,66 @@ pub struct AppState {
     // Pending Removals (Debounce for mDNS)
     pub pending_removals: Arc<Mutex<HashMap<String, u64>>>,
     // Pending Clipboard Content (Received but not yet applied due to Auto-Receive OFF)
-    pub pending_clipboard: Arc<Mutex<Option<crate::protocol::ClipboardPayload>>>,
+    // Incoming text not applied yet (auto-receive off or screen locked), by payload id, oldest first
+    pub pending_clipboard: Arc<Mutex<indexmap::IndexMap<String, crate::protocol::ClipboardPayload>>>,
+    // Local content held for review before it's sent (review_before_send)
+    pub pending_outgoing: Arc<Mutex<Option<crate::protocol::ClipboardPayload>>>,
+    // TypeText request waiting for the local user to confirm it
+    pub pending_type_text: Arc<Mutex<Option<crate::remote::PendingTypeText>>>,
+    // Staged factory reset after PeerRemoval: wipe deadline (unix secs). Syncing is paused while set.
+    pub pending_reset: Arc<Mutex<Option<u64>>>,
     // Shutdown flag for graceful termination of background threads
     pub shutdown: Arc<AtomicBool>,
-    // Mapping of Message ID -> File Paths (for serving file requests)
-    // Mapping of Message ID -> File Paths (for serving file requests)
-    pub local_files: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    // Mapping of Message ID -> File Paths (for serving file requests, see offers.rs)
+    pub local_files: Arc<Mutex<crate::offers::LocalFiles>>,
+    // Dropped files being prepared for sending: Batch ID -> Batch (see staging.rs)
+    pub staged_files: Arc<Mutex<HashMap<String, crate::staging::StagedBatch>>>,
+    // Mapping of Message ID -> File Metadata announced by peers (for requesting/dedupe)
+    pub remote_files: Arc<Mutex<HashMap<String, Vec<crate::protocol::FileMetadata>>>>,
+    // Recently received files: Content Hash -> Where it is (only valid while the file is unchanged)
+    pub received_hashes: Arc<Mutex<HashMap<String, ReceivedHash>>>,
+    // Content hashes of local files we offered: Path -> (Stamp when hashed, Hash)
+    pub file_hashes: Arc<Mutex<HashMap<String, (FileStamp, String)>>>,
+    // Where each received file is on disk: "MsgID:Index" -> Path (for save_received_file)
+    pub received_files: Arc<Mutex<HashMap<String, String>>>,
+    // Interrupted/in-flight downloads: "MsgID:Index" -> Partial Transfer (backed by .part files)
+    pub partial_transfers: Arc<Mutex<HashMap<String, crate::storage::PartialTransfer>>>,
+    // Downloads that failed their checksum: "MsgID:Index" -> Failed attempts so far
+    pub verify_failures: Arc<Mutex<HashMap<String, u32>>>,
+    // Received items whose files are still arriving: Message ID -> Parts so far (see compose.rs)
+    pub compositions: Arc<Mutex<HashMap<String, crate::compose::Composition>>>,
+    // Members removed recently: Device ID -> Removed At (kept out of peer list exchanges, see peersync.rs)
+    pub removed_members: Arc<Mutex<HashMap<String, u64>>>,
+    // Notes/labels on history items (Persisted, synced via HistoryAnnotate)
+    pub history_annotations: Arc<Mutex<HashMap<String, crate::protocol::HistoryAnnotation>>>,
+    // Incoming message counters (parse errors, validation rejects)
+    pub message_metrics: Arc<Mutex<MessageMetrics>>,
+    // Recent end-to-end clipboard latencies per origin device (only for traced payloads)
+    pub latency_samples: Arc<Mutex<HashMap<String, std::collections::VecDeque<crate::latency::LatencySample>>>>,
+    // Outcome of the storage migration/recovery for the active profile
+    pub migration_report: Arc<Mutex<Option<crate::migration::MigrationReport>>>,
+    // Recent clipboard items, newest first (see history.rs)
+    pub recent_items: Arc<Mutex<std::collections::VecDeque<crate::protocol::ClipboardPayload>>>,
+    // Outstanding one-time pairing PIN (see pin.rs)
+    pub one_time_pin: Arc<Mutex<Option<crate::pin::OneTimePin>>>,
+    // Screen lock state (see screenlock.rs)
+    pub screen_locked: Arc<AtomicBool>,
+    // Items received while the window was unfocused (see unread.rs)
+    pub unread: Arc<AtomicU32>,
+    // Clipboard sends being retried or given up on: (message ID, peer ID) -> failure (see retry.rs)
+    pub send_failures: Arc<Mutex<indexmap::IndexMap<(String, String), crate::retry::SendFailure>>>,
+    // Connection tests waiting for a reply: Probe ID -> waiter (see conntest.rs)
+    pub pending_probes: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<crate::protocol::ProbeReply>>>>,
+    // Dry runs waiting for receipts: "PayloadID:DeviceID" -> waiter (see dryrun.rs)
+    pub pending_dry_runs: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<crate::protocol::DryRunReceipt>>>>,
+    // Idle/battery tracking for low-power mode
+    pub power: Arc<crate::power::PowerMonitor>,
+    // Peer id -> (presence, since); see presence.rs
+    pub presence: Arc<Mutex<HashMap<String, (crate::presence::Presence, u64)>>>,
+    // Our own addresses as reported by peers (ObservedAddress): local IP -> times reported
+    pub observed_ips: Arc<Mutex<HashMap<std::net::IpAddr, u32>>>,
+    // Port the transport is actually bound to (may differ from 4654 after a fallback); 0 until bound
+    pub bound_port: Arc<AtomicU16>,
     // Transport instance for sending messages from commands
     pub transport: Arc<Mutex<Option<crate::transport::Transport>>>,
     // Tray Menu Handle
@@ -55,18 +218,58 @@ impl AppState {
             peers: Arc::new(Mutex::new(HashMap::new())),
             pending_handshakes: Arc::new(Mutex::new(HashMap::new())),
             handshake_sessions: Arc::new(Mutex::new(HashMap::new())),
+            pending_confirmations: Arc::new(Mutex::new(HashMap::new())),
+            pair_request_times: Arc::new(Mutex::new(HashMap::new())),
             cluster_key: Arc::new(Mutex::new(None)),
+            cluster_owner: Arc::new(Mutex::new(None)),
+            pending_admissions: Arc::new(Mutex::new(HashMap::new())),
+            admission_requests: Arc::new(Mutex::new(HashMap::new())),
+            guest_invite: Arc::new(Mutex::new(None)),
+            guest_expires_at: Arc::new(Mutex::new(None)),
             known_peers: Arc::new(Mutex::new(HashMap::new())),
+            peer_download_policies: Arc::new(Mutex::new(HashMap::new())),
+            config_overrides: Arc::new(Mutex::new(crate::config::FileConfig::default())),
+            peer_permissions: Arc::new(Mutex::new(HashMap::new())),
+            peer_stats: Arc::new(Mutex::new(crate::peerstats::StatsStore::default())),
+            blocked_peers: Arc::new(Mutex::new(HashMap::new())),
             local_device_id: Arc::new(Mutex::new(String::new())),
+            device_key: Arc::new(Mutex::new(None)),
             discovery: Arc::new(Mutex::new(None)),
-            last_clipboard_content: Arc::new(Mutex::new(String::new())),
+            last_clipboard_content: Arc::new(Mutex::new(LastClipboard::default())),
             network_name: Arc::new(Mutex::new(String::new())),
             network_pin: Arc::new(Mutex::new(String::new())),
             settings: Arc::new(Mutex::new(AppSettings::default())),
             pending_removals: Arc::new(Mutex::new(HashMap::new())),
-            pending_clipboard: Arc::new(Mutex::new(None)),
+            pending_clipboard: Arc::new(Mutex::new(indexmap::IndexMap::new())),
+            pending_outgoing: Arc::new(Mutex::new(None)),
+            pending_type_text: Arc::new(Mutex::new(None)),
+            pending_reset: Arc::new(Mutex::new(None)),
             shutdown: Arc::new(AtomicBool::new(false)),
-            local_files: Arc::new(Mutex::new(HashMap::new())),
+            screen_locked: Arc::new(AtomicBool::new(false)),
+            unread: Arc::new(AtomicU32::new(0)),
+            send_failures: Arc::new(Mutex::new(indexmap::IndexMap::new())),
+            pending_probes: Arc::new(Mutex::new(HashMap::new())),
+            pending_dry_runs: Arc::new(Mutex::new(HashMap::new())),
+            one_time_pin: Arc::new(Mutex::new(None)),
+            local_files: Arc::new(Mutex::new(crate::offers::LocalFiles::new())),
+            staged_files: Arc::new(Mutex::new(HashMap::new())),
+            remote_files: Arc::new(Mutex::new(HashMap::new())),
+            received_hashes: Arc::new(Mutex::new(HashMap::new())),
+            file_hashes: Arc::new(Mutex::new(HashMap::new())),
+            received_files: Arc::new(Mutex::new(HashMap::new())),
+            partial_transfers: Arc::new(Mutex::new(HashMap::new())),
+            verify_failures: Arc::new(Mutex::new(HashMap::new())),
+            compositions: Arc::new(Mutex::new(HashMap::new())),
+            removed_members: Arc::new(Mutex::new(HashMap::new())),
+            history_annotations: Arc::new(Mutex::new(HashMap::new())),
+            message_metrics: Arc::new(Mutex::new(MessageMetrics::default())),
+            latency_samples: Arc::new(Mutex::new(HashMap::new())),
+            recent_items: Arc::new(Mutex::new(std::collections::VecDeque::new())),
+            migration_report: Arc::new(Mutex::new(None)),
+            power: Arc::new(crate::power::PowerMonitor::default()),
+            presence: Arc::new(Mutex::new(HashMap::new())),
+            observed_ips: Arc::new(Mutex::new(HashMap::new())),
+            bound_port: Arc::new(AtomicU16::new(0)),
             transport: Arc::new(Mutex::new(None)),
             tray_menu: Arc::new(Mutex::new(None)),
             current_theme: Arc::new(Mutex::new(None)),
@@ -82,9 +285,111 @@ impl AppState {
         self.shutdown.load(Ordering::SeqCst)
     }
 
-    pub fn add_peer(&self, peer: Peer) {
+    /// Port to advertise (mDNS, re-announcements): the real bound port, once known.
+    pub fn bound_port(&self) -> u16 {
+        match self.bound_port.load(Ordering::Relaxed) {
+            0 => crate::DEFAULT_PORT,
+            port => port,
+        }
+    }
+
+    pub fn set_bound_port(&self, port: u16) {
+        self.bound_port.store(port, Ordering::Relaxed);
+    }
+
+    /// The local address peers most often see us at, if any have told us.
+    pub fn preferred_local_ip(&self) -> Option<std::net::IpAddr> {
+        let observed = self.observed_ips.lock().unwrap();
+        observed.iter().max_by_key(|(ip, count)| (**count, **ip)).map(|(ip, _)| *ip)
+    }
+
+    pub fn is_blocked(&self, device_id: &str) -> bool {
+        self.blocked_peers.lock().unwrap().contains_key(device_id)
+    }
+
+    /// Whether traffic from `ip` should be dropped. A non-blocked peer currently at that
+    /// address wins, so a blocked device's old DHCP lease doesn't lock out its successor.
+    pub fn is_blocked_addr(&self, ip: std::net::IpAddr) -> bool {
+        let blocked = self.blocked_peers.lock().unwrap();
+        if !blocked.values().any(|b| b.ip == Some(ip)) {
+            return false;
+        }
+        !self
+            .peers
+            .lock()
+            .unwrap()
+            .values()
+            .any(|p| p.ip == ip && !blocked.contains_key(&p.id))
+    }
+
+    /// Whether an incoming message comes from a blocked device (by claimed id, else by address).
+    pub fn is_blocked_sender(&self, msg: &crate::protocol::Message, addr: std::net::SocketAddr) -> bool {
+        use crate::protocol::Message;
+        let claimed_id = match msg {
+            Message::PairRequest { device_id, .. }
+            | Message::PairResponse { device_id, .. }
+            | Message::PairConfirm { device_id, .. }
+            | Message::PairRejected { device_id, .. }
+            | Message::AdmissionPending { device_id } => Some(device_id),
+            Message::PeerDiscovery(peer) => Some(&peer.id),
+            _ => None,
+        };
+        if claimed_id.is_some_and(|id| self.is_blocked(id)) {
+            return true;
+        }
+        self.is_blocked_addr(addr.ip())
+    }
+
+    pub fn record_blocked_message(&self) {
+        self.message_metrics.lock().unwrap().blocked += 1;
+    }
+
+    pub fn record_parse_error(&self, error: String) {
+        let mut m = self.message_metrics.lock().unwrap();
+        m.parse_errors += 1;
+        m.last_error = Some(error);
+    }
+
+    pub fn record_rejected_message(&self, error: &crate::validation::ValidationError) {
+        let mut m = self.message_metrics.lock().unwrap();
+        m.rejected += 1;
+        *m.rejected_by_reason.entry(error.metric_key()).or_insert(0) += 1;
+        m.last_error = Some(error.to_string());
+    }
+
+    pub fn is_reset_pending(&self) -> bool {
+        self.pending_reset.lock().unwrap().is_some()
+    }
+
+    /// Insert or replace a peer, keeping the address book we already had for it. Returns the
+    /// stored peer.
+    pub fn add_peer(&self, mut peer: Peer) -> Peer {
+        // Advertised addresses outside the allowed networks are never tried
+        peer.addresses.retain(|a| crate::netscope::allows_peer(self, a.addr.ip()));
+        let mut peers = self.peers.lock().unwrap();
+        match peers.get(&peer.id) {
+            Some(previous) => peer.merge_addresses(previous),
+            None => {
+                let own = peer.clone();
+                peer.merge_addresses(&own);
+            }
+        }
+        peers.insert(peer.id.clone(), peer.clone());
+        peer
+    }
+
+    /// Record how a send to one of the peer's addresses went (see `Peer::candidates`).
+    pub fn record_peer_send(&self, peer_id: &str, addr: std::net::SocketAddr, ok: bool) {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
         let mut peers = self.peers.lock().unwrap();
-        peers.insert(peer.id.clone(), peer);
+        let Some(peer) = peers.get_mut(peer_id) else {
+            return;
+        };
+        let previous = peer.primary_addr();
+        peer.record_send(addr, ok, now);
+        if ok && previous != addr {
+            tracing::info!("[Peers] {} now reached at {} (was {})", peer.id, addr, previous);
+        }
     }
 
     pub fn get_peers(&self) -> HashMap<String, Peer> {
@@ -92,6 +397,85 @@ impl AppState {
         peers.clone()
     }
 
+    /// Store an annotation if it is newer than what we have. Returns true if it was applied.
+    pub fn apply_history_annotation(&self, annotation: crate::protocol::HistoryAnnotation) -> bool {
+        const MAX_ENTRIES: usize = 500;
+        let mut annotations = self.history_annotations.lock().unwrap();
+        if let Some(existing) = annotations.get(&annotation.id) {
+            if existing.updated_at > annotation.updated_at {
+                return false;
+            }
+        }
+        if annotation.note.is_none() {
+            annotations.remove(&annotation.id);
+            return true;
+        }
+        if annotations.len() >= MAX_ENTRIES && !annotations.contains_key(&annotation.id) {
+            if let Some(oldest) = annotations
+                .values()
+                .min_by_key(|a| a.updated_at)
+                .map(|a| a.id.clone())
+            {
+                annotations.remove(&oldest);
+            }
+        }
+        annotations.insert(annotation.id.clone(), annotation);
+        true
+    }
+
+    pub fn record_received_hash(&self, hash: String, path: String) {
+        const MAX_ENTRIES: usize = 256;
+        let Some(stamp) = FileStamp::of(std::path::Path::new(&path)) else {
+            return;
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut index = self.received_hashes.lock().unwrap();
+        index.insert(hash, ReceivedHash { path, stamp, received_at: now });
+        // Evict oldest entries
+        while index.len() > MAX_ENTRIES {
+            let oldest = index
+                .iter()
+                .min_by_key(|(_, entry)| entry.received_at)
+                .map(|(h, _)| h.clone());
+            match oldest {
+                Some(h) => {
+                    index.remove(&h);
+                }
+                None => break,
+            }
+        }
+    }
+
+    // Returns the local path of a previously received file with this hash, if it's still there
+    // and hasn't been modified since (size and mtime as when it was recorded)
+    pub fn lookup_received_hash(&self, hash: &str) -> Option<String> {
+        let mut index = self.received_hashes.lock().unwrap();
+        let entry = index.get(hash)?;
+        if FileStamp::of(std::path::Path::new(&entry.path)) == Some(entry.stamp) {
+            Some(entry.path.clone())
+        } else {
+            index.remove(hash);
+            None
+        }
+    }
+
+    pub fn record_received_file(&self, file_id: &str, file_index: usize, path: String) {
+        self.received_files.lock().unwrap().insert(format!("{}:{}", file_id, file_index), path);
+    }
+
+    // A received file was moved; point everything that referenced it at the new path
+    pub fn move_received_file(&self, from: &str, to: &str) {
+        for path in self.received_files.lock().unwrap().values_mut().filter(|p| p.as_str() == from) {
+            *path = to.to_string();
+        }
+        for entry in self.received_hashes.lock().unwrap().values_mut().filter(|e| e.path == from) {
+            entry.path = to.to_string();
+        }
+    }
+
     pub fn should_notify(&self) -> bool {
         self.startup_time.elapsed() > std::time::Duration::from_secs(60)
     }
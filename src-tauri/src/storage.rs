@@ -1,19 +1,282 @@
 use crate::peer::Peer;
+use crate::protocol::HistoryAnnotation;
 use names::Generator;
 use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
 use tauri::{path::BaseDirectory, AppHandle, Manager};
 
+// Active profile. None = default profile, which keeps the original (un-namespaced) paths.
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+pub fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE.read().unwrap().clone()
+}
+
+pub fn set_active_profile(name: Option<String>) {
+    *ACTIVE_PROFILE.write().unwrap() = name.filter(|n| n != "default");
+}
+
+/// Profile names end up in paths, so keep them boring.
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Resolve a config file for the active profile (profiles/<name>/<file>)
+fn resolve_config(app: &AppHandle, file: &str) -> tauri::Result<std::path::PathBuf> {
+    let rel = match active_profile() {
+        Some(profile) => format!("profiles/{}/{}", profile, file),
+        None => file.to_string(),
+    };
+    app.path().resolve(rel, BaseDirectory::AppConfig)
+}
+
+// --- Crash-Safe Writes ---
+// Config files are written to `<file>.tmp` and renamed into place, next to a SHA-256 sidecar
+// (`<file>.sha256`). The previous good version is kept as `<file>.bak` and restored when the
+// primary fails its checksum (or doesn't parse, for JSON).
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(suffix);
+    PathBuf::from(s)
+}
+
+fn checksum(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+// Files written before checksums were introduced have no sidecar; accept them as-is
+fn read_verified(path: &Path) -> Option<Vec<u8>> {
+    let data = fs::read(path).ok()?;
+    match fs::read_to_string(with_suffix(path, ".sha256")) {
+        Ok(sum) if sum.trim() != checksum(&data) => {
+            tracing::warn!("Checksum mismatch for {:?}", path);
+            None
+        }
+        _ => Some(data),
+    }
+}
+
+fn is_intact(path: &Path, data: &[u8]) -> bool {
+    if path.extension().is_some_and(|e| e == "json") {
+        return serde_json::from_slice::<serde_json::Value>(data).is_ok();
+    }
+    true
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Atomically replace `path`, keeping the current (intact) version as `<file>.bak`.
+fn write_atomic(path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    let data = data.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Some(current) = read_verified(path).filter(|d| is_intact(path, d)) {
+        let bak = with_suffix(path, ".bak");
+        write_synced(&with_suffix(&bak, ".sha256"), checksum(&current).as_bytes())?;
+        write_synced(&bak, &current)?;
+    }
+    // Checksum first: a crash before the data rename leaves a mismatch, and the backup holds the same old data
+    write_synced(&with_suffix(path, ".sha256"), checksum(data).as_bytes())?;
+    write_synced(path, data)
+}
+
+/// Read a config file, falling back to (and restoring) its backup if the primary is corrupt.
+fn read_config_bytes(path: &Path) -> io::Result<Vec<u8>> {
+    if let Some(data) = read_verified(path).filter(|d| is_intact(path, d)) {
+        return Ok(data);
+    }
+    // Only reachable for a primary that exists: deleting a file (resets) removes its backup too
+    if !path.exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "file not found"));
+    }
+    let bak = with_suffix(path, ".bak");
+    match read_verified(&bak).filter(|d| is_intact(path, d)) {
+        Some(data) => {
+            tracing::warn!("{:?} is corrupt. Restoring last good version from backup.", path);
+            if let Err(e) = write_atomic(path, &data) {
+                tracing::error!("Failed to restore {:?} from backup: {}", path, e);
+            }
+            Ok(data)
+        }
+        None => Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt and no usable backup")),
+    }
+}
+
+fn read_config_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read_config_bytes(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Remove a config file along with its checksum and backup.
+fn remove_config_file(path: &Path) {
+    let bak = with_suffix(path, ".bak");
+    for p in [with_suffix(path, ".sha256"), with_suffix(&bak, ".sha256"), bak, path.to_path_buf()] {
+        let _ = fs::remove_file(p);
+    }
+}
+
+// --- Schema Versioning (see migration.rs) ---
+
+pub fn load_schema_version(app: &AppHandle) -> Option<u32> {
+    let path = resolve_config(app, "schema_version").ok()?;
+    read_config_string(&path).ok()?.trim().parse().ok()
+}
+
+pub fn save_schema_version(app: &AppHandle, version: u32) {
+    match resolve_config(app, "schema_version") {
+        Ok(path) => {
+            if let Err(e) = write_atomic(&path, version.to_string()) {
+                tracing::error!("Failed to write schema version: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to resolve schema_version path: {}", e),
+    }
+}
+
+pub fn config_file_exists(app: &AppHandle, file: &str) -> bool {
+    resolve_config(app, file).is_ok_and(|p| p.exists())
+}
+
+/// Copy config files (those that exist) into `dir`, relative to the profile's config directory.
+pub fn backup_config_files(app: &AppHandle, dir: &str, files: &[&str]) -> io::Result<()> {
+    for file in files {
+        let src = resolve_config(app, file).map_err(io::Error::other)?;
+        if !src.exists() {
+            continue;
+        }
+        let dest = resolve_config(app, &format!("{}/{}", dir, file)).map_err(io::Error::other)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&src, &dest)?;
+    }
+    Ok(())
+}
+
+// Problems the loaders recovered from, drained into the migration report
+static LOAD_WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn take_load_warnings() -> Vec<String> {
+    std::mem::take(&mut *LOAD_WARNINGS.lock().unwrap())
+}
+
+fn note_recovered(path: &Path, file: &str, what: String) {
+    tracing::warn!("{}: {}", file, what);
+    // Keep the original: the cleaned-up version replaces it on the next save
+    let _ = fs::copy(path, with_suffix(path, ".rejected"));
+    let mut warnings = LOAD_WARNINGS.lock().unwrap();
+    let entry = format!("{}: {}", file, what);
+    if !warnings.contains(&entry) {
+        warnings.push(entry);
+    }
+}
+
+/// Deserialize a struct, keeping every stored field that still parses instead of resetting it all to defaults.
+fn parse_struct_lenient<T>(path: &Path, file: &str, content: &str) -> T
+where
+    T: Default + serde::Serialize + serde::de::DeserializeOwned,
+{
+    if let Ok(value) = serde_json::from_str::<T>(content) {
+        return value;
+    }
+    let stored = match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => {
+            note_recovered(path, file, "unreadable, using defaults".to_string());
+            return T::default();
+        }
+    };
+    let mut merged = match serde_json::to_value(T::default()) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => return T::default(),
+    };
+
+    let mut dropped = Vec::new();
+    for (key, value) in stored {
+        let previous = merged.insert(key.clone(), value);
+        if serde_json::from_value::<T>(serde_json::Value::Object(merged.clone())).is_err() {
+            match previous {
+                Some(p) => merged.insert(key.clone(), p),
+                None => merged.remove(&key),
+            };
+            dropped.push(key);
+        }
+    }
+    if !dropped.is_empty() {
+        note_recovered(path, file, format!("reset unreadable fields to defaults: {}", dropped.join(", ")));
+    }
+    serde_json::from_value(serde_json::Value::Object(merged)).unwrap_or_default()
+}
+
+/// Deserialize an id-keyed map, dropping only the entries that don't parse.
+fn parse_map_lenient<V>(path: &Path, file: &str, content: &str) -> HashMap<String, V>
+where
+    V: serde::de::DeserializeOwned,
+{
+    if let Ok(map) = serde_json::from_str::<HashMap<String, V>>(content) {
+        return map;
+    }
+    let stored = match serde_json::from_str::<HashMap<String, serde_json::Value>>(content) {
+        Ok(map) => map,
+        Err(e) => {
+            note_recovered(path, file, format!("unreadable ({})", e));
+            return HashMap::new();
+        }
+    };
+
+    let mut out = HashMap::new();
+    let mut dropped = Vec::new();
+    for (key, value) in stored {
+        match serde_json::from_value(value) {
+            Ok(v) => {
+                out.insert(key, v);
+            }
+            Err(e) => dropped.push(format!("{} ({})", key, e)),
+        }
+    }
+    if !dropped.is_empty() {
+        note_recovered(path, file, format!("skipped unreadable entries: {}", dropped.join(", ")));
+    }
+    out
+}
+
+pub fn list_profiles(app: &AppHandle) -> Vec<String> {
+    let mut profiles = vec!["default".to_string()];
+    if let Ok(dir) = app.path().resolve("profiles", BaseDirectory::AppConfig) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && is_valid_profile_name(&name) && name != "default" {
+                    profiles.push(name);
+                }
+            }
+        }
+    }
+    profiles
+}
+
 pub fn load_network_name(app: &AppHandle) -> String {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("network_name", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "network_name") {
         Ok(p) => p,
         Err(_) => return String::from("unknown-network"),
     };
 
     if path.exists() {
-        if let Ok(name) = fs::read_to_string(&path) {
+        if let Ok(name) = read_config_string(&path) {
             if !name.trim().is_empty() {
                 tracing::debug!("Loaded Network Name: {}", name);
                 return name;
@@ -33,108 +296,482 @@ pub fn load_network_name(app: &AppHandle) -> String {
     new_name
 }
 
-pub fn save_network_name(app: &AppHandle, name: &str) {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("network_name", BaseDirectory::AppConfig) {
+pub fn save_network_name(app: &AppHandle, name: &str) {
+    let path = match resolve_config(app, "network_name") {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = write_atomic(&path, name);
+}
+
+pub fn load_cluster_key(app: &AppHandle) -> Option<Vec<u8>> {
+    let path = match resolve_config(app, "cluster_key.bin") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve cluster key path: {}", e);
+            return None;
+        }
+    };
+
+    if !path.exists() {
+        return None;
+    }
+
+    match read_config_bytes(&path) {
+        Ok(key) => {
+            if key.len() != 32 {
+                tracing::error!("Cluster key file has invalid length: {}", key.len());
+                return None;
+            }
+            tracing::debug!("Loaded Cluster Key from disk.");
+            Some(key)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to read cluster key file: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save_cluster_key(app: &AppHandle, key: &[u8]) {
+    let path = match resolve_config(app, "cluster_key.bin") {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to resolve cluster key path for saving: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    if let Err(e) = write_atomic(&path, key) {
+        tracing::error!("Failed to write cluster key file: {}", e);
+    } else {
+        tracing::debug!("Saved Cluster Key to disk.");
+    }
+}
+
+/// This device's Ed25519 signing key seed (see identity.rs).
+pub fn load_device_key(app: &AppHandle) -> Option<[u8; 32]> {
+    let path = resolve_config(app, "device_key.bin").ok()?;
+    if !path.exists() {
+        return None;
+    }
+    match read_config_bytes(&path) {
+        Ok(seed) => match <[u8; 32]>::try_from(seed.as_slice()) {
+            Ok(seed) => Some(seed),
+            Err(_) => {
+                tracing::error!("Device key file has invalid length: {}", seed.len());
+                None
+            }
+        },
+        Err(e) => {
+            tracing::warn!("Failed to read device key file: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save_device_key(app: &AppHandle, seed: &[u8; 32]) {
+    match resolve_config(app, "device_key.bin") {
+        Ok(path) => {
+            if let Err(e) = write_atomic(&path, seed) {
+                tracing::error!("Failed to write device key file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to resolve device key path: {}", e),
+    }
+}
+
+pub fn load_known_peers(app: &AppHandle) -> HashMap<String, Peer> {
+    let path = match resolve_config(app, "known_peers.json") {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to resolve config path: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    match read_config_string(&path) {
+        Ok(content) => {
+            let peers: HashMap<String, Peer> = parse_map_lenient(&path, "known_peers.json", &content);
+            tracing::info!("Loaded {} known peers from disk at {:?}", peers.len(), path);
+            peers
+        }
+        Err(e) => {
+            tracing::warn!("Failed to read known peers file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn save_known_peers(app: &AppHandle, peers: &HashMap<String, Peer>) {
+    let path = match resolve_config(app, "known_peers.json") {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to resolve config path for saving: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    match serde_json::to_string_pretty(peers) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                crate::error::report(app, "save-peers", &crate::error::AppError::Storage(format!("Failed to write known peers file: {}", e)));
+            } else {
+                tracing::debug!("Saved known peers to disk at {:?}", path);
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to serialize known peers: {}", e);
+        }
+    }
+}
+
+/// Per-peer override of the global auto-download settings.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoDownloadPolicy {
+    // Download everything from this peer, regardless of size or auto_receive
+    Always,
+    // Never auto-download from this peer (notification only)
+    Never,
+    // Auto-download up to this many bytes (replaces max_auto_download_size)
+    UpTo(u64),
+}
+
+pub fn load_peer_download_policies(app: &AppHandle) -> HashMap<String, AutoDownloadPolicy> {
+    let path = match resolve_config(app, "peer_policies.json") {
+        Ok(p) => p,
+        Err(_) => return HashMap::new(),
+    };
+
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "peer_policies.json", &content),
+        Err(e) => {
+            tracing::warn!("Failed to read peer policies file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn save_peer_download_policies(app: &AppHandle, policies: &HashMap<String, AutoDownloadPolicy>) {
+    let path = match resolve_config(app, "peer_policies.json") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve peer policies path: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let policies = crate::config::persisted_policies(app, policies);
+    match serde_json::to_string_pretty(&policies) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write peer policies file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize peer policies: {}", e),
+    }
+}
+
+pub fn load_peer_stats(app: &AppHandle) -> HashMap<String, crate::peerstats::PeerStats> {
+    let path = match resolve_config(app, "peer_stats.json") {
+        Ok(p) => p,
+        Err(_) => return HashMap::new(),
+    };
+
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "peer_stats.json", &content),
+        Err(e) => {
+            tracing::warn!("Failed to read peer stats file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn save_peer_stats(app: &AppHandle, stats: &HashMap<String, crate::peerstats::PeerStats>) {
+    let path = match resolve_config(app, "peer_stats.json") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve peer stats path: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    match serde_json::to_string_pretty(stats) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write peer stats file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize peer stats: {}", e),
+    }
+}
+
+/// What a peer may do on this device beyond clipboard sync (everything off by default).
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PeerPermissions {
+    // Open links in the default browser (OpenUrl)
+    #[serde(default)]
+    pub open_urls: bool,
+    // Type text as keystrokes (TypeText; each request is still confirmed here)
+    #[serde(default)]
+    pub type_text: bool,
+}
+
+pub fn load_peer_permissions(app: &AppHandle) -> HashMap<String, PeerPermissions> {
+    let path = match resolve_config(app, "peer_permissions.json") {
+        Ok(p) => p,
+        Err(_) => return HashMap::new(),
+    };
+
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "peer_permissions.json", &content),
+        Err(e) => {
+            tracing::warn!("Failed to read peer permissions file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn save_peer_permissions(app: &AppHandle, permissions: &HashMap<String, PeerPermissions>) {
+    let path = match resolve_config(app, "peer_permissions.json") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve peer permissions path: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    match serde_json::to_string_pretty(permissions) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write peer permissions file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize peer permissions: {}", e),
+    }
+}
+
+/// A device we never talk to again (discovery, pairing and messages are dropped).
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct BlockedPeer {
+    pub id: String,
+    #[serde(default)]
+    pub hostname: String,
+    // Last address we saw it on; used to drop messages that don't carry a device id
+    #[serde(default)]
+    pub ip: Option<std::net::IpAddr>,
+    pub blocked_at: u64,
+}
+
+pub fn load_blocked_peers(app: &AppHandle) -> HashMap<String, BlockedPeer> {
+    let path = match resolve_config(app, "blocked_peers.json") {
+        Ok(p) => p,
+        Err(_) => return HashMap::new(),
+    };
+
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "blocked_peers.json", &content),
+        Err(e) => {
+            tracing::warn!("Failed to read blocked peers file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn save_blocked_peers(app: &AppHandle, blocked: &HashMap<String, BlockedPeer>) {
+    let path = match resolve_config(app, "blocked_peers.json") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve blocked peers path: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    match serde_json::to_string_pretty(blocked) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write blocked peers file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize blocked peers: {}", e),
+    }
+}
+
+/// Recently removed members and when they were removed (see `peersync::record_removal`).
+pub fn load_removed_members(app: &AppHandle) -> HashMap<String, u64> {
+    let path = match resolve_config(app, "removed_members.json") {
+        Ok(p) => p,
+        Err(_) => return HashMap::new(),
+    };
+
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "removed_members.json", &content),
+        Err(e) => {
+            tracing::warn!("Failed to read removed members file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn save_removed_members(app: &AppHandle, removed: &HashMap<String, u64>) {
+    let path = match resolve_config(app, "removed_members.json") {
         Ok(p) => p,
-        Err(_) => return,
+        Err(e) => {
+            tracing::error!("Failed to resolve removed members path: {}", e);
+            return;
+        }
     };
 
+    if removed.is_empty() {
+        remove_config_file(&path);
+        return;
+    }
     if let Some(parent) = path.parent() {
         let _ = fs::create_dir_all(parent);
     }
-    let _ = fs::write(path, name);
+
+    match serde_json::to_string_pretty(removed) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write removed members file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize removed members: {}", e),
+    }
 }
 
-pub fn load_cluster_key(app: &AppHandle) -> Option<Vec<u8>> {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("cluster_key.bin", BaseDirectory::AppConfig) {
+/// A pairing this device started, kept on disk so a PairResponse that arrives after a restart
+/// can ask the user to pair again (see `handshake::resume`). The PIN is never stored.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct PairingIntent {
+    pub peer_id: String,
+    pub network_name: String,
+    pub expires_at: u64,
+}
+
+pub fn load_pairing_intents(app: &AppHandle) -> HashMap<String, PairingIntent> {
+    let path = match resolve_config(app, "pairing_intents.json") {
         Ok(p) => p,
-        Err(e) => {
-            tracing::error!("Failed to resolve cluster key path: {}", e);
-            return None;
-        }
+        Err(_) => return HashMap::new(),
     };
 
     if !path.exists() {
-        return None;
+        return HashMap::new();
     }
 
-    match fs::read(&path) {
-        Ok(key) => {
-            if key.len() != 32 {
-                tracing::error!("Cluster key file has invalid length: {}", key.len());
-                return None;
-            }
-            tracing::debug!("Loaded Cluster Key from disk.");
-            Some(key)
-        }
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "pairing_intents.json", &content),
         Err(e) => {
-            tracing::warn!("Failed to read cluster key file: {}", e);
-            None
+            tracing::warn!("Failed to read pairing intents file: {}", e);
+            HashMap::new()
         }
     }
 }
 
-pub fn save_cluster_key(app: &AppHandle, key: &[u8]) {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("cluster_key.bin", BaseDirectory::AppConfig) {
+pub fn save_pairing_intents(app: &AppHandle, intents: &HashMap<String, PairingIntent>) {
+    let path = match resolve_config(app, "pairing_intents.json") {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("Failed to resolve cluster key path for saving: {}", e);
+            tracing::error!("Failed to resolve pairing intents path: {}", e);
             return;
         }
     };
 
+    if intents.is_empty() {
+        remove_config_file(&path);
+        return;
+    }
     if let Some(parent) = path.parent() {
         let _ = fs::create_dir_all(parent);
     }
 
-    if let Err(e) = fs::write(path, key) {
-        tracing::error!("Failed to write cluster key file: {}", e);
-    } else {
-        tracing::debug!("Saved Cluster Key to disk.");
+    match serde_json::to_string_pretty(intents) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write pairing intents file: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize pairing intents: {}", e),
     }
 }
 
-pub fn load_known_peers(app: &AppHandle) -> HashMap<String, Peer> {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("known_peers.json", BaseDirectory::AppConfig) {
+pub fn load_history_annotations(app: &AppHandle) -> HashMap<String, HistoryAnnotation> {
+    let path = match resolve_config(app, "history_annotations.json") {
         Ok(p) => p,
-        Err(e) => {
-            eprintln!("Failed to resolve config path: {}", e);
-            return HashMap::new();
-        }
+        Err(_) => return HashMap::new(),
     };
 
     if !path.exists() {
         return HashMap::new();
     }
 
-    match fs::read_to_string(&path) {
-        Ok(content) => match serde_json::from_str::<HashMap<String, Peer>>(&content) {
-            Ok(peers) => {
-                tracing::info!("Loaded {} known peers from disk at {:?}", peers.len(), path);
-                peers
-            }
-            Err(e) => {
-                tracing::error!("Failed to parse known peers: {}", e);
-                HashMap::new()
-            }
-        },
+    match read_config_string(&path) {
+        Ok(content) => parse_map_lenient(&path, "history_annotations.json", &content),
         Err(e) => {
-            tracing::warn!("Failed to read known peers file: {}", e);
+            tracing::warn!("Failed to read history annotations file: {}", e);
             HashMap::new()
         }
     }
 }
 
-pub fn save_known_peers(app: &AppHandle, peers: &HashMap<String, Peer>) {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("known_peers.json", BaseDirectory::AppConfig) {
+pub fn save_history_annotations(app: &AppHandle, annotations: &HashMap<String, HistoryAnnotation>) {
+    let path = match resolve_config(app, "history_annotations.json") {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("Failed to resolve config path for saving: {}", e);
+            tracing::error!("Failed to resolve history annotations path: {}", e);
             return;
         }
     };
@@ -143,23 +780,18 @@ pub fn save_known_peers(app: &AppHandle, peers: &HashMap<String, Peer>) {
         let _ = fs::create_dir_all(parent);
     }
 
-    match serde_json::to_string_pretty(peers) {
+    match serde_json::to_string_pretty(annotations) {
         Ok(json) => {
-            if let Err(e) = fs::write(&path, json) {
-                tracing::error!("Failed to write known peers file: {}", e);
-            } else {
-                tracing::debug!("Saved known peers to disk at {:?}", path);
+            if let Err(e) = write_atomic(&path, json) {
+                tracing::error!("Failed to write history annotations file: {}", e);
             }
         }
-        Err(e) => {
-            tracing::error!("Failed to serialize known peers: {}", e);
-        }
+        Err(e) => tracing::error!("Failed to serialize history annotations: {}", e),
     }
 }
 
 pub fn load_device_id(app: &AppHandle) -> String {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("device_id", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "device_id") {
         Ok(p) => p,
         Err(_) => return String::new(),
     };
@@ -168,12 +800,11 @@ pub fn load_device_id(app: &AppHandle) -> String {
         return String::new();
     }
 
-    fs::read_to_string(path).unwrap_or_default()
+    read_config_string(&path).unwrap_or_default()
 }
 
 pub fn save_device_id(app: &AppHandle, id: &str) {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("device_id", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "device_id") {
         Ok(p) => p,
         Err(e) => {
             tracing::error!("Failed to resolve device_id path: {}", e);
@@ -185,18 +816,101 @@ pub fn save_device_id(app: &AppHandle, id: &str) {
         let _ = fs::create_dir_all(parent);
     }
 
-    let _ = fs::write(path, id);
+    let _ = write_atomic(&path, id);
+}
+
+// Device ID of the cluster owner (approves new members). None if unknown.
+pub fn load_cluster_owner(app: &AppHandle) -> Option<String> {
+    let path = resolve_config(app, "cluster_owner").ok()?;
+    let owner = read_config_string(&path).ok()?;
+    let owner = owner.trim();
+    if owner.is_empty() {
+        None
+    } else {
+        Some(owner.to_string())
+    }
+}
+
+pub fn save_cluster_owner(app: &AppHandle, owner: Option<&str>) {
+    let path = match resolve_config(app, "cluster_owner") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve cluster_owner path: {}", e);
+            return;
+        }
+    };
+
+    match owner {
+        Some(id) => {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            let _ = write_atomic(&path, id);
+        }
+        None => remove_config_file(&path),
+    }
+}
+
+// Deadline (unix secs) of a staged reset after a PeerRemoval for this device, if one is pending
+pub fn load_pending_reset(app: &AppHandle) -> Option<u64> {
+    let path = resolve_config(app, "pending_reset").ok()?;
+    read_config_string(&path).ok()?.trim().parse().ok()
+}
+
+pub fn save_pending_reset(app: &AppHandle, deadline: Option<u64>) {
+    let path = match resolve_config(app, "pending_reset") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve pending_reset path: {}", e);
+            return;
+        }
+    };
+
+    match deadline {
+        Some(ts) => {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            let _ = write_atomic(&path, ts.to_string());
+        }
+        None => remove_config_file(&path),
+    }
+}
+
+// Our own guest membership expiry (unix secs), if we joined as a guest
+pub fn load_guest_expiry(app: &AppHandle) -> Option<u64> {
+    let path = resolve_config(app, "guest_expires_at").ok()?;
+    read_config_string(&path).ok()?.trim().parse().ok()
+}
+
+pub fn save_guest_expiry(app: &AppHandle, expires_at: Option<u64>) {
+    let path = match resolve_config(app, "guest_expires_at") {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::error!("Failed to resolve guest_expires_at path: {}", e);
+            return;
+        }
+    };
+
+    match expires_at {
+        Some(ts) => {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            let _ = write_atomic(&path, ts.to_string());
+        }
+        None => remove_config_file(&path),
+    }
 }
 
 pub fn load_network_pin(app: &AppHandle) -> String {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("network_pin", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "network_pin") {
         Ok(p) => p,
         Err(_) => return String::from("000000"),
     };
 
     if path.exists() {
-        if let Ok(pin) = fs::read_to_string(&path) {
+        if let Ok(pin) = read_config_string(&path) {
             if !pin.trim().is_empty() {
                 return pin;
             }
@@ -204,22 +918,31 @@ pub fn load_network_pin(app: &AppHandle) -> String {
     }
 
     // Generate new PIN
+    let pin = generate_network_pin();
+    tracing::info!("Generated New Network PIN: {}", pin);
+    save_network_pin(app, &pin);
+    pin
+}
+
+pub fn generate_network_pin() -> String {
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
-    let pin: String = (0..6)
+    (0..6)
         .map(|_| {
             let idx = rand::thread_rng().gen_range(0..CHARSET.len());
             CHARSET[idx] as char
         })
-        .collect();
+        .collect()
+}
 
-    tracing::info!("Generated New Network PIN: {}", pin);
-    save_network_pin(app, &pin);
-    pin
+/// Time since the network PIN was last written (for scheduled rotation).
+pub fn network_pin_age(app: &AppHandle) -> Option<std::time::Duration> {
+    let path = resolve_config(app, "network_pin").ok()?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    modified.elapsed().ok()
 }
 
 pub fn save_network_pin(app: &AppHandle, pin: &str) {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("network_pin", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "network_pin") {
         Ok(p) => p,
         Err(e) => {
             eprintln!("Failed to resolve network_pin path: {}", e);
@@ -230,43 +953,75 @@ pub fn save_network_pin(app: &AppHandle, pin: &str) {
     if let Some(parent) = path.parent() {
         let _ = fs::create_dir_all(parent);
     }
-    let _ = fs::write(path, pin);
+    let _ = write_atomic(&path, pin);
 }
 // Helper to reset network state (Self-Destruct/Kick)
 pub fn reset_network_state(app: &AppHandle) {
-    let path_resolver = app.path();
     // Include the actual filenames used by load/save
     let config_files = [
         "cluster_key.bin",
+        "cluster_owner",
+        "guest_expires_at",
         "network_name",
         "network_pin",
         "known_peers.json",
+        "peer_policies.json",
+        "pairing_intents.json",
+        "removed_members.json",
+        "pending_reset",
     ];
 
     for filename in config_files {
-        match path_resolver.resolve(filename, BaseDirectory::AppConfig) {
-            Ok(path) => {
-                if path.exists() {
-                    let _ = fs::remove_file(path);
-                }
-            }
+        match resolve_config(app, filename) {
+            Ok(path) => remove_config_file(&path),
             Err(e) => tracing::error!("Failed to resolve path for {}: {}", filename, e),
         }
     }
 }
 
+/// Every on-disk copy (primary, backup, checksum sidecars, leftover temp file) of the config
+/// files holding keys, peers or history, for `wipe::secure_wipe`. Settings, the device ID and
+/// the block list are kept.
+pub fn sensitive_config_paths(app: &AppHandle) -> Vec<PathBuf> {
+    let files = [
+        "cluster_key.bin",
+        "device_key.bin",
+        "cluster_owner",
+        "guest_expires_at",
+        "network_name",
+        "network_pin",
+        "known_peers.json",
+        "peer_policies.json",
+        "peer_permissions.json",
+        "peer_stats.json",
+        "history_annotations.json",
+        "pairing_intents.json",
+        "removed_members.json",
+    ];
+    let mut paths = Vec::new();
+    for file in files {
+        let Ok(path) = resolve_config(app, file) else {
+            continue;
+        };
+        let bak = with_suffix(&path, ".bak");
+        paths.extend([
+            with_suffix(&path, ".sha256"),
+            with_suffix(&path, ".tmp"),
+            with_suffix(&bak, ".sha256"),
+            bak,
+            path,
+        ]);
+    }
+    paths
+}
+
 pub fn regenerate_identity(app: &AppHandle) -> (String, String) {
-    let path_resolver = app.path();
     // 1. Delete existing Name/PIN files
-    if let Ok(path) = path_resolver.resolve("network_name", BaseDirectory::AppConfig) {
-        if path.exists() {
-            let _ = fs::remove_file(path);
-        }
+    if let Ok(path) = resolve_config(app, "network_name") {
+        remove_config_file(&path);
     }
-    if let Ok(path) = path_resolver.resolve("network_pin", BaseDirectory::AppConfig) {
-        if path.exists() {
-            let _ = fs::remove_file(path);
-        }
+    if let Ok(path) = resolve_config(app, "network_pin") {
+        remove_config_file(&path);
     }
 
     // 2. Load (which generates new ones if missing)
@@ -296,6 +1051,59 @@ impl Default for NotificationSettings {
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayBadgeStyle {
+    // Filled square (the original badge)
+    #[default]
+    Square,
+    Dot,
+    // Outline only, readable over any icon color
+    Ring,
+    // Dot with the unread count drawn in it
+    Count,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayBadgePosition {
+    #[default]
+    TopRight,
+    TopLeft,
+    BottomRight,
+    BottomLeft,
+}
+
+/// How the unread badge is drawn onto the tray icon (see badge.rs).
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct TrayBadgeSettings {
+    #[serde(default)]
+    pub style: TrayBadgeStyle,
+    // "#rrggbb", or "mono" for the same color as the (theme-dependent) icon
+    #[serde(default = "default_tray_badge_color")]
+    pub color: String,
+    #[serde(default)]
+    pub position: TrayBadgePosition,
+    // Outline the badge in a contrasting color
+    #[serde(default)]
+    pub high_contrast: bool,
+}
+
+fn default_tray_badge_color() -> String {
+    "#ff0000".to_string()
+}
+
+impl Default for TrayBadgeSettings {
+    fn default() -> Self {
+        Self {
+            style: TrayBadgeStyle::default(),
+            color: default_tray_badge_color(),
+            position: TrayBadgePosition::default(),
+            high_contrast: false,
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct AppSettings {
     pub custom_device_name: Option<String>,
@@ -310,6 +1118,261 @@ pub struct AppSettings {
     pub notify_large_files: bool,
     #[serde(default)]
     pub ignore_extension_missing: bool,
+    #[serde(default = "default_true")]
+    pub compress_file_transfers: bool,
+    #[serde(default = "default_true")]
+    pub generate_file_previews: bool,
+    // Files up to this size (KB) are embedded in the clipboard payload. 0 disables.
+    #[serde(default = "default_inline_file_threshold_kb")]
+    pub inline_file_threshold_kb: u64,
+    // Sent files stop being served after this many hours (0 = until the app quits)
+    #[serde(default = "default_file_offer_ttl_hours")]
+    pub file_offer_ttl_hours: u64,
+    // Most file offers served at once; the oldest expire first
+    #[serde(default = "default_max_file_offers")]
+    pub max_file_offers: usize,
+    // Post-receive scanner, e.g. "clamscan --no-summary {path}" (exit 0 = clean, 1 = infected)
+    #[serde(default)]
+    pub scan_command: Option<String>,
+    // Mark received files with the OS quarantine attribute (macOS/Windows)
+    #[serde(default)]
+    pub quarantine_received_files: bool,
+    // Include the source app/window title in sent clipboard payloads (privacy: off by default)
+    #[serde(default)]
+    pub share_source_app: bool,
+    // Strict mode: only copies made in these apps (name, or part of the window title) are broadcast
+    #[serde(default)]
+    pub app_allowlist_mode: bool,
+    #[serde(default)]
+    pub allowed_apps: Vec<String>,
+    // Devices that pair with us (correct PIN) still need the cluster owner's approval
+    #[serde(default)]
+    pub require_owner_approval: bool,
+    // Advertised capabilities: whether peers may offer us images, and the largest text we take (0 = no limit)
+    #[serde(default = "default_true")]
+    pub accept_images: bool,
+    #[serde(default)]
+    pub max_incoming_payload_kb: u64,
+    // Attach per-hop timestamps to sent clipboard payloads (see get_latency_report)
+    #[serde(default)]
+    pub latency_tracing: bool,
+    // Low-power mode after this long without clipboard/peer activity (0 = never), shorter on battery
+    #[serde(default)]
+    pub power_saving: bool,
+    #[serde(default = "default_idle_suspend_secs")]
+    pub idle_suspend_secs: u64,
+    #[serde(default = "default_battery_idle_suspend_secs")]
+    pub battery_idle_suspend_secs: u64,
+    // Clipboard poll interval, and whether to poll fast for a few seconds after activity
+    // (see power.rs)
+    #[serde(default = "default_monitor_interval_ms")]
+    pub monitor_interval_ms: u64,
+    #[serde(default = "default_true")]
+    pub adaptive_polling: bool,
+    // While in low-power mode: keepalive heartbeat interval and clipboard poll interval
+    #[serde(default = "default_low_power_heartbeat_secs")]
+    pub low_power_heartbeat_secs: u64,
+    #[serde(default = "default_low_power_monitor_interval_ms")]
+    pub low_power_monitor_interval_ms: u64,
+    // On a metered connection or in battery saver: hold auto-downloads above this size until
+    // the user asks for them (and, with power_saving, send heartbeats at the keepalive interval)
+    #[serde(default = "default_true")]
+    pub defer_downloads_when_constrained: bool,
+    #[serde(default = "default_constrained_download_limit")]
+    pub constrained_download_limit: u64,
+    // Named channels: which ones we apply, which one automatic/shortcut sends use,
+    // and extra shortcuts that send the clipboard to a specific channel (channel -> shortcut)
+    #[serde(default = "default_subscribed_channels")]
+    pub subscribed_channels: Vec<String>,
+    #[serde(default = "default_send_channel")]
+    pub send_channel: String,
+    #[serde(default)]
+    pub channel_shortcuts: HashMap<String, String>,
+    // Probe copied URLs after sending and share their favicon (contacts the site from this device)
+    #[serde(default)]
+    pub unfurl_links: bool,
+    #[serde(default = "default_unfurl_timeout_ms")]
+    pub unfurl_timeout_ms: u64,
+    // While the screen is locked: hold incoming text as pending, don't broadcast or relay
+    #[serde(default = "default_true")]
+    pub pause_sync_when_locked: bool,
+    // Restrict transport/mDNS/peers to these interfaces (names) and subnets (CIDR). Empty = any.
+    #[serde(default)]
+    pub allowed_interfaces: Vec<String>,
+    #[serde(default)]
+    pub allowed_subnets: Vec<String>,
+    // Off = only one-time PINs can be used to pair with this device
+    #[serde(default = "default_true")]
+    pub allow_network_pin_pairing: bool,
+    // Replace the network PIN this often (0 = never)
+    #[serde(default)]
+    pub pin_rotation_hours: u64,
+    // Presence tuning (clamped, see presence.rs): heartbeat interval, how long a silent peer
+    // is kept, and how long an mDNS removal waits for the peer to reappear
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u64,
+    #[serde(default = "default_stale_peer_timeout_secs")]
+    pub stale_peer_timeout_secs: u64,
+    #[serde(default = "default_mdns_removal_debounce_secs")]
+    pub mdns_removal_debounce_secs: u64,
+    // With auto-send on: hold each new local copy until it's confirmed (command, send shortcut or tray)
+    #[serde(default)]
+    pub review_before_send: bool,
+    // Copied text larger than this goes out as a .txt file transfer (0 = never, capped at 2 MB)
+    #[serde(default = "default_text_to_file_threshold_kb")]
+    pub text_to_file_threshold_kb: u64,
+    // Links copied here are also opened on these peers (device IDs; they must allow it)
+    #[serde(default)]
+    pub open_links_on: Vec<String>,
+    // Keep local crash reports (see crash.rs); never uploaded
+    #[serde(default)]
+    pub crash_reports: bool,
+    // macOS: "Send to ClusterCut" in the Services menu (see services.rs); opt-in
+    #[serde(default)]
+    pub macos_service: bool,
+    // (auto_send, auto_receive) from before a panel widget paused sync; restored on resume
+    #[serde(default)]
+    pub paused_toggles: Option<(bool, bool)>,
+    // Start in the tray without showing the window (like --minimized / --hidden)
+    #[serde(default)]
+    pub start_minimized: bool,
+    // Language for notifications and the tray (e.g. "de"); None follows the system
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub tray_badge: TrayBadgeSettings,
+    // Extra attempts for a clipboard send that fails, with backoff (0 = give up at once)
+    #[serde(default = "default_send_retry_attempts")]
+    pub send_retry_attempts: u32,
+    // How clipboard text, file names and URLs appear in logs (see redact.rs)
+    #[serde(default)]
+    pub log_redaction: crate::redact::LogRedaction,
+    // Daily log files kept in the app log directory (see logs.rs)
+    #[serde(default = "default_log_retention_days")]
+    pub log_retention_days: u32,
+    // Cipher we ask peers to use for clipboard payloads (advertised in our capabilities)
+    #[serde(default)]
+    pub cipher_suite: crate::crypto::CipherPreference,
+    // What happens to received files in temp_downloads at start and exit (see cache.rs)
+    #[serde(default)]
+    pub received_files_retention: crate::cache::ReceivedRetention,
+    #[serde(default = "default_received_files_retention_days")]
+    pub received_files_retention_days: u32,
+    #[serde(default = "default_received_files_retention_gb")]
+    pub received_files_retention_gb: u32,
+    // Streamed files larger than this (bytes) stay in the cache until the user picks where to save them (0 = never ask)
+    #[serde(default = "default_save_prompt_size")]
+    pub save_prompt_size: u64,
+    // SOCKS5 proxy for connections off the local network (see proxy.rs)
+    #[serde(default)]
+    pub proxy: crate::proxy::ProxySettings,
+    // Look for new releases in the background (see update.rs)
+    #[serde(default)]
+    pub update_check: bool,
+    #[serde(default)]
+    pub update_channel: crate::update::UpdateChannel,
+    // Releases JSON in GitHub's API format
+    #[serde(default = "default_update_endpoint")]
+    pub update_endpoint: String,
+    // Only discover devices advertising the same namespace ("" = the shared default); lets
+    // separate groups on one LAN stay out of each other's device lists (see discovery.rs)
+    #[serde(default)]
+    pub discovery_namespace: String,
+    // Which incoming clipboard items/file requests are acted on (see senderauth.rs)
+    #[serde(default)]
+    pub sender_authentication: crate::senderauth::SenderAuth,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_inline_file_threshold_kb() -> u64 {
+    64
+}
+
+fn default_file_offer_ttl_hours() -> u64 {
+    24
+}
+
+fn default_max_file_offers() -> usize {
+    500
+}
+
+fn default_idle_suspend_secs() -> u64 {
+    15 * 60
+}
+
+fn default_battery_idle_suspend_secs() -> u64 {
+    60
+}
+
+fn default_low_power_heartbeat_secs() -> u64 {
+    120
+}
+
+fn default_low_power_monitor_interval_ms() -> u64 {
+    2000
+}
+
+fn default_monitor_interval_ms() -> u64 {
+    2000
+}
+
+fn default_constrained_download_limit() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_text_to_file_threshold_kb() -> u64 {
+    512
+}
+
+fn default_send_retry_attempts() -> u32 {
+    3
+}
+
+fn default_log_retention_days() -> u32 {
+    7
+}
+
+fn default_received_files_retention_days() -> u32 {
+    7
+}
+
+fn default_received_files_retention_gb() -> u32 {
+    5
+}
+
+fn default_save_prompt_size() -> u64 {
+    200 * 1024 * 1024
+}
+
+fn default_update_endpoint() -> String {
+    crate::update::DEFAULT_ENDPOINT.to_string()
+}
+
+fn default_heartbeat_interval_secs() -> u64 {
+    crate::presence::DEFAULT_HEARTBEAT_SECS
+}
+
+fn default_stale_peer_timeout_secs() -> u64 {
+    crate::presence::DEFAULT_STALE_TIMEOUT_SECS
+}
+
+fn default_mdns_removal_debounce_secs() -> u64 {
+    crate::presence::DEFAULT_REMOVAL_DEBOUNCE_SECS
+}
+
+fn default_subscribed_channels() -> Vec<String> {
+    vec![crate::protocol::DEFAULT_CHANNEL.to_string()]
+}
+
+fn default_unfurl_timeout_ms() -> u64 {
+    2500
+}
+
+fn default_send_channel() -> String {
+    crate::protocol::DEFAULT_CHANNEL.to_string()
 }
 
 impl Default for AppSettings {
@@ -326,13 +1389,71 @@ impl Default for AppSettings {
             max_auto_download_size: 50 * 1024 * 1024, // 50 MB
             notify_large_files: true,
             ignore_extension_missing: false,
+            compress_file_transfers: true,
+            generate_file_previews: true,
+            inline_file_threshold_kb: default_inline_file_threshold_kb(),
+            file_offer_ttl_hours: default_file_offer_ttl_hours(),
+            max_file_offers: default_max_file_offers(),
+            scan_command: None,
+            quarantine_received_files: false,
+            share_source_app: false,
+            app_allowlist_mode: false,
+            allowed_apps: Vec::new(),
+            require_owner_approval: false,
+            accept_images: true,
+            max_incoming_payload_kb: 0,
+            latency_tracing: false,
+            power_saving: false,
+            idle_suspend_secs: default_idle_suspend_secs(),
+            battery_idle_suspend_secs: default_battery_idle_suspend_secs(),
+            low_power_heartbeat_secs: default_low_power_heartbeat_secs(),
+            monitor_interval_ms: default_monitor_interval_ms(),
+            adaptive_polling: true,
+            low_power_monitor_interval_ms: default_low_power_monitor_interval_ms(),
+            defer_downloads_when_constrained: true,
+            constrained_download_limit: default_constrained_download_limit(),
+            subscribed_channels: default_subscribed_channels(),
+            send_channel: default_send_channel(),
+            channel_shortcuts: HashMap::new(),
+            unfurl_links: false,
+            unfurl_timeout_ms: default_unfurl_timeout_ms(),
+            pause_sync_when_locked: true,
+            allowed_interfaces: Vec::new(),
+            allowed_subnets: Vec::new(),
+            allow_network_pin_pairing: true,
+            pin_rotation_hours: 0,
+            heartbeat_interval_secs: default_heartbeat_interval_secs(),
+            stale_peer_timeout_secs: default_stale_peer_timeout_secs(),
+            mdns_removal_debounce_secs: default_mdns_removal_debounce_secs(),
+            review_before_send: false,
+            text_to_file_threshold_kb: default_text_to_file_threshold_kb(),
+            open_links_on: Vec::new(),
+            crash_reports: false,
+            macos_service: false,
+            paused_toggles: None,
+            start_minimized: false,
+            locale: None,
+            tray_badge: TrayBadgeSettings::default(),
+            send_retry_attempts: default_send_retry_attempts(),
+            log_redaction: crate::redact::LogRedaction::default(),
+            log_retention_days: default_log_retention_days(),
+            cipher_suite: crate::crypto::CipherPreference::default(),
+            received_files_retention: crate::cache::ReceivedRetention::default(),
+            received_files_retention_days: default_received_files_retention_days(),
+            received_files_retention_gb: default_received_files_retention_gb(),
+            save_prompt_size: default_save_prompt_size(),
+            proxy: crate::proxy::ProxySettings::default(),
+            update_check: false,
+            update_channel: crate::update::UpdateChannel::default(),
+            update_endpoint: default_update_endpoint(),
+            discovery_namespace: String::new(),
+            sender_authentication: crate::senderauth::SenderAuth::default(),
         }
     }
 }
 
 pub fn load_settings(app: &AppHandle) -> AppSettings {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("settings.json", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "settings.json") {
         Ok(p) => p,
         Err(_) => return AppSettings::default(),
     };
@@ -341,15 +1462,17 @@ pub fn load_settings(app: &AppHandle) -> AppSettings {
         return AppSettings::default();
     }
 
-    match fs::read_to_string(&path) {
-        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
-        Err(_) => AppSettings::default(),
+    match read_config_string(&path) {
+        Ok(content) => parse_struct_lenient(&path, "settings.json", &content),
+        Err(e) => {
+            tracing::warn!("Failed to read settings file: {}", e);
+            AppSettings::default()
+        }
     }
 }
 
 pub fn save_settings(app: &AppHandle, settings: &AppSettings) {
-    let path_resolver = app.path();
-    let path = match path_resolver.resolve("settings.json", BaseDirectory::AppConfig) {
+    let path = match resolve_config(app, "settings.json") {
         Ok(p) => p,
         Err(e) => {
             tracing::error!("Failed to resolve settings path: {}", e);
@@ -361,7 +1484,121 @@ pub fn save_settings(app: &AppHandle, settings: &AppSettings) {
         let _ = fs::create_dir_all(parent);
     }
 
-    if let Ok(json) = serde_json::to_string_pretty(settings) {
-        let _ = fs::write(path, json);
+    // clustercut.toml overrides apply at runtime only
+    let settings = crate::config::persisted_settings(app, settings);
+    if let Ok(json) = serde_json::to_string_pretty(&settings) {
+        if let Err(e) = write_atomic(&path, json) {
+            crate::error::report(app, "save-settings", &crate::error::AppError::Storage(format!("Failed to write settings: {}", e)));
+        }
+    }
+}
+
+// --- Partial Transfers (Crash Recovery) ---
+// Incoming files are written to `<id>-<index>.part` in temp_downloads with a `.part.json` sidecar,
+// so an interrupted download can be resumed after a crash or restart.
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct PartialTransfer {
+    pub id: String,
+    pub file_index: usize,
+    pub file_name: String,
+    pub file_size: u64,
+    pub peer_id: Option<String>,
+    pub part_path: String,
+    // Bytes on disk (refreshed from the .part length when loaded)
+    #[serde(default)]
+    pub transferred: u64,
+}
+
+impl PartialTransfer {
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.id, self.file_index)
+    }
+
+    fn sidecar_path(&self) -> std::path::PathBuf {
+        std::path::PathBuf::from(format!("{}.json", self.part_path))
+    }
+}
+
+/// Large copied text waiting to be fetched by peers (see clipboard::text_to_file).
+pub fn outgoing_text_dir(app: &AppHandle) -> Option<std::path::PathBuf> {
+    let dir = match active_profile() {
+        Some(profile) => format!("outgoing_text-{}", profile),
+        None => "outgoing_text".to_string(),
+    };
+    app.path().app_cache_dir().ok().map(|p| p.join(dir))
+}
+
+/// Copied bitmaps waiting to be fetched by peers (see clipboard::image_to_file).
+pub fn outgoing_images_dir(app: &AppHandle) -> Option<std::path::PathBuf> {
+    let dir = match active_profile() {
+        Some(profile) => format!("outgoing_images-{}", profile),
+        None => "outgoing_images".to_string(),
+    };
+    app.path().app_cache_dir().ok().map(|p| p.join(dir))
+}
+
+pub fn temp_downloads_dir(app: &AppHandle) -> Option<std::path::PathBuf> {
+    let dir = match active_profile() {
+        Some(profile) => format!("temp_downloads-{}", profile),
+        None => "temp_downloads".to_string(),
+    };
+    app.path().app_cache_dir().ok().map(|p| p.join(dir))
+}
+
+pub fn save_partial_transfer(record: &PartialTransfer) {
+    match serde_json::to_string_pretty(record) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(&record.sidecar_path(), json) {
+                tracing::error!("Failed to write transfer sidecar for {}: {}", record.key(), e);
+            }
+        }
+        Err(e) => tracing::error!("Failed to serialize transfer sidecar: {}", e),
+    }
+}
+
+pub fn remove_partial_transfer(record: &PartialTransfer, remove_part: bool) {
+    remove_config_file(&record.sidecar_path());
+    if remove_part {
+        let _ = fs::remove_file(&record.part_path);
+    }
+}
+
+pub fn load_partial_transfers(app: &AppHandle) -> Vec<PartialTransfer> {
+    let dir = match temp_downloads_dir(app) {
+        Some(d) => d,
+        None => return Vec::new(),
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut transfers = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.to_string_lossy().ends_with(".part.json") {
+            continue;
+        }
+        let record = read_config_string(&path)
+            .ok()
+            .and_then(|c| serde_json::from_str::<PartialTransfer>(&c).ok());
+        match record {
+            Some(mut record) => match fs::metadata(&record.part_path) {
+                Ok(meta) => {
+                    record.transferred = meta.len();
+                    transfers.push(record);
+                }
+                Err(_) => {
+                    // Orphaned sidecar
+                    remove_config_file(&path);
+                }
+            },
+            None => {
+                tracing::warn!("Discarding unreadable transfer sidecar {:?}", path);
+                remove_config_file(&path);
+            }
+        }
     }
+    transfers
 }
@@ -1,14 +1,63 @@
-use quinn::{ClientConfig, Endpoint, ServerConfig};
+use quinn::{ClientConfig, Connection, Endpoint, ServerConfig};
 use rcgen::generate_simple_self_signed;
+use std::collections::HashMap;
 use std::error::Error;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Message connections whose receiver also reads QUIC datagrams. Offered ahead of the plain
+// transport ALPN for best-effort sends; older versions only know the plain one and pick it.
+const ALPN_DATAGRAM: &[u8] = b"clustercut-transport-dgram";
+// Open connections kept for best-effort traffic (kept alive by the 5s keep-alive, dropped after 30s idle)
+const MAX_CACHED_CONNECTIONS: usize = 64;
+
+// Per message connection: unread bytes the peer may have in flight, and streams open at once
+// (streams are read one at a time; see spool.rs for the memory bound on reading them)
+const MESSAGE_RECEIVE_WINDOW: u32 = 16 * 1024 * 1024;
+const MAX_MESSAGE_STREAMS: u32 = 8;
+
+// Reliable sends: time for the stream to be flushed/accepted before the connection is dropped
+const RELIABLE_LINGER: Duration = Duration::from_millis(500);
+
+/// How a message is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    // Stream, then a fixed linger so the peer reads it before we hang up (clipboard, pairing, replies)
+    Reliable,
+    // Fire-and-forget over a connection kept open between sends: a QUIC datagram when the peer
+    // negotiated them (and the message fits), else a stream that isn't waited on. For traffic
+    // that's repeated anyway, where losing one costs nothing (heartbeats, re-announcements).
+    BestEffort,
+}
+
+impl Delivery {
+    pub fn of(msg: &crate::protocol::Message) -> Self {
+        match msg {
+            crate::protocol::Message::PeerDiscovery(_) => Delivery::BestEffort,
+            _ => Delivery::Reliable,
+        }
+    }
+}
+
+/// Message-sending surface used by protocol code.
+/// Implemented by the QUIC `Transport` and by the in-memory transport used in tests (see `sim`).
+pub trait MessageTransport: Clone + Send + Sync + 'static {
+    fn send_message(
+        &self,
+        addr: SocketAddr,
+        data: &[u8],
+    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error + Send + Sync>>> + Send;
+}
 
 #[derive(Clone)]
 pub struct Transport {
     pub endpoint: Endpoint,
     transport_config: ClientConfig,
     file_config: ClientConfig,
+    datagram_config: ClientConfig,
+    // Connections reused by best-effort sends, by peer address
+    cached: Arc<Mutex<HashMap<SocketAddr, Connection>>>,
 }
 
 impl Transport {
@@ -18,6 +67,7 @@ impl Transport {
 
         let transport_config = configure_client(vec![b"clustercut-transport".to_vec()])?;
         let file_config = configure_client(vec![b"clustercut-file".to_vec()])?;
+        let datagram_config = configure_client(vec![ALPN_DATAGRAM.to_vec(), b"clustercut-transport".to_vec()])?;
 
         let addr = SocketAddr::from(([0, 0, 0, 0], port));
         let mut endpoint = Endpoint::server(server_config, addr)?;
@@ -27,6 +77,8 @@ impl Transport {
             endpoint,
             transport_config,
             file_config,
+            datagram_config,
+            cached: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -35,6 +87,23 @@ impl Transport {
         addr: SocketAddr,
         data: &[u8],
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.send_with(Delivery::Reliable, addr, data).await
+    }
+
+    pub async fn send_with(
+        &self,
+        delivery: Delivery,
+        addr: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if delivery == Delivery::BestEffort {
+            let result = self.send_best_effort(addr, data).await;
+            if result.is_err() {
+                self.cached.lock().unwrap().remove(&addr);
+            }
+            return result;
+        }
+
         // Use connect_with to enforce specific ALPN config
         let connection = self
             .endpoint
@@ -46,8 +115,54 @@ impl Transport {
         send.finish()?;
 
         // Give the stream a moment to flush/be accepted before dropping the connection
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        tokio::time::sleep(RELIABLE_LINGER).await;
+
+        Ok(())
+    }
+
+    async fn send_best_effort(&self, addr: SocketAddr, data: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let connection = self.cached_connection(addr).await?;
+        // Datagrams need both the peer's max_datagram_frame_size transport parameter and our
+        // ALPN (which says its listener reads them); anything else goes on a stream
+        let datagrams = negotiated_protocol(&connection).as_deref() == Some(ALPN_DATAGRAM);
+        if datagrams && connection.max_datagram_size().is_some_and(|max| data.len() <= max) {
+            connection.send_datagram(bytes::Bytes::copy_from_slice(data))?;
+            return Ok(());
+        }
+        let (mut send, _recv) = connection.open_bi().await?;
+        send.write_all(data).await?;
+        send.finish()?;
+        // The connection stays open, so there's nothing to linger for
+        Ok(())
+    }
+
+    // A live connection to `addr` for best-effort sends, reusing the last one if still open
+    async fn cached_connection(&self, addr: SocketAddr) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+        if let Some(connection) = self.cached.lock().unwrap().get(&addr).filter(|c| c.close_reason().is_none()) {
+            return Ok(connection.clone());
+        }
+        let connection = self
+            .endpoint
+            .connect_with(self.datagram_config.clone(), addr, "clustercut")?
+            .await?;
+        let mut cached = self.cached.lock().unwrap();
+        cached.retain(|_, c| c.close_reason().is_none());
+        if cached.len() >= MAX_CACHED_CONNECTIONS {
+            if let Some(evict) = cached.keys().next().copied() {
+                cached.remove(&evict);
+            }
+        }
+        cached.insert(addr, connection.clone());
+        Ok(connection)
+    }
 
+    /// Establish (and close) a connection, to check the peer is reachable (connection test).
+    pub async fn handshake(&self, addr: SocketAddr) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let connection = self
+            .endpoint
+            .connect_with(self.transport_config.clone(), addr, "clustercut")?
+            .await?;
+        connection.close(0u32.into(), b"handshake test");
         Ok(())
     }
 
@@ -69,7 +184,7 @@ impl Transport {
 
     pub fn start_listening<F, G>(&self, on_receive_message: F, on_receive_file: G)
     where
-        F: Fn(Vec<u8>, SocketAddr) + Send + Sync + 'static + Clone,
+        F: Fn(crate::spool::Inbound, SocketAddr) + Send + Sync + 'static + Clone,
         G: Fn(quinn::RecvStream, SocketAddr) + Send + Sync + 'static + Clone,
     {
         let endpoint = self.endpoint.clone();
@@ -84,12 +199,7 @@ impl Transport {
                         // tracing::info!("Transport established connection with {}", remote_addr);
 
                         // Check Protocol (ALPN)
-                        let protocol = conn
-                            .handshake_data()
-                            .unwrap()
-                            .downcast::<quinn::crypto::rustls::HandshakeData>()
-                            .unwrap()
-                            .protocol
+                        let protocol = negotiated_protocol(&conn)
                             .map(|p| String::from_utf8_lossy(&p).to_string());
 
                         // Default to transport if unknown
@@ -124,33 +234,36 @@ impl Transport {
                                 }
                             });
                         } else {
+                            if proto.as_bytes() == ALPN_DATAGRAM {
+                                // Best-effort messages that fit in a datagram arrive this way
+                                let conn = conn.clone();
+                                let on_receive_message = on_receive_message.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    while let Ok(datagram) = conn.read_datagram().await {
+                                        if !datagram.is_empty() {
+                                            on_receive_message(crate::spool::Inbound::from_bytes(datagram.to_vec()), remote_addr);
+                                        }
+                                    }
+                                });
+                            }
                             // Standard Message Handler (clustercut-transport)
+                            conn.set_receive_window(quinn::VarInt::from_u32(MESSAGE_RECEIVE_WINDOW));
+                            conn.set_max_concurrent_bi_streams(quinn::VarInt::from_u32(MAX_MESSAGE_STREAMS));
                             let on_receive_message = on_receive_message.clone();
                             tauri::async_runtime::spawn(async move {
                                 // tracing::debug!("Handling MESSAGE connection from {}", remote_addr);
-                                loop {
-                                    match conn.accept_bi().await {
-                                        Ok((_, mut recv)) => {
-                                            // tracing::debug!("Accepted message stream from {}", remote_addr);
-                                            // Limit 10MB
-                                            if let Ok(buf) =
-                                                recv.read_to_end(1024 * 1024 * 10).await
-                                            {
-                                                if !buf.is_empty() {
-                                                    on_receive_message(buf, remote_addr);
-                                                }
-                                            } else {
-                                                tracing::error!(
-                                                    "Failed to read from stream from {}",
-                                                    remote_addr
-                                                );
-                                            }
-                                        }
-                                        Err(_e) => {
-                                            // connection closed is normal
-                                            // tracing::debug!("Message connection closed/error from {}: {}", remote_addr, e);
-                                            break;
-                                        }
+                                // Ends when the connection closes (normal)
+                                while let Ok((_, mut recv)) = conn.accept_bi().await {
+                                    // tracing::debug!("Accepted message stream from {}", remote_addr);
+                                    // Small messages stay in memory, large ones are spooled to disk
+                                    match crate::spool::read_stream(&mut recv).await {
+                                        Ok(Some(inbound)) => on_receive_message(inbound, remote_addr),
+                                        Ok(None) => {}
+                                        Err(e) => tracing::error!(
+                                            "Failed to read from stream from {}: {}",
+                                            remote_addr,
+                                            e
+                                        ),
                                     }
                                 }
                             });
@@ -162,11 +275,51 @@ impl Transport {
         });
     }
 
+    /// Move the endpoint to a new UDP port, keeping the bound address (existing connections migrate with it).
+    pub fn rebind(&self, port: u16) -> Result<(), Box<dyn Error>> {
+        let ip = self.endpoint.local_addr()?.ip();
+        self.rebind_addr(SocketAddr::new(ip, port))
+    }
+
+    /// Move the endpoint to a specific local address (see netscope.rs).
+    pub fn rebind_addr(&self, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+        let socket = std::net::UdpSocket::bind(addr)?;
+        self.endpoint.rebind(socket)?;
+        Ok(())
+    }
+
+    /// Close the connections kept open for best-effort sends, so nothing negotiated with the
+    /// previous profile's peers is reused (the endpoint's certificate is per run, not per profile).
+    pub fn reset_connections(&self) {
+        let cached: Vec<Connection> = self.cached.lock().unwrap().drain().map(|(_, c)| c).collect();
+        for connection in cached {
+            connection.close(0u32.into(), b"profile switched");
+        }
+    }
+
     pub fn local_addr(&self) -> Result<SocketAddr, Box<dyn Error>> {
         Ok(self.endpoint.local_addr()?)
     }
 }
 
+impl MessageTransport for Transport {
+    async fn send_message(
+        &self,
+        addr: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Transport::send_message(self, addr, data).await
+    }
+}
+
+fn negotiated_protocol(connection: &Connection) -> Option<Vec<u8>> {
+    connection
+        .handshake_data()?
+        .downcast::<quinn::crypto::rustls::HandshakeData>()
+        .ok()?
+        .protocol
+}
+
 fn generate_self_signed_cert() -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
     // Register BOTH protocols
     let cert = generate_simple_self_signed(vec![
@@ -186,7 +339,9 @@ fn configure_server(cert_der: Vec<u8>, key_der: Vec<u8>) -> Result<ServerConfig,
         .with_no_client_auth()
         .with_single_cert(vec![cert], key)?;
 
+    // Datagram-capable first: rustls picks by our order among those the client offers
     crypto.alpn_protocols = vec![
+        ALPN_DATAGRAM.to_vec(),
         b"clustercut-transport".to_vec(),
         b"clustercut-file".to_vec(),
     ];
@@ -201,6 +356,8 @@ fn configure_server(cert_der: Vec<u8>, key_der: Vec<u8>) -> Result<ServerConfig,
     transport_config.stream_receive_window(quinn::VarInt::from_u32(100 * 1024 * 1024));
     // 250MB Connection Window (allows multiple streams to sum up to this)
     transport_config.receive_window(quinn::VarInt::from_u32(250 * 1024 * 1024));
+    // Advertise max_datagram_frame_size (best-effort messages, see `Delivery`)
+    transport_config.datagram_receive_buffer_size(Some(1024 * 1024));
 
     // Keep Alive
     transport_config.keep_alive_interval(Some(std::time::Duration::from_secs(5)));
@@ -280,6 +437,7 @@ fn configure_client(alpn_protocols: Vec<Vec<u8>>) -> Result<ClientConfig, Box<dy
     let mut transport_config = quinn::TransportConfig::default();
     transport_config.stream_receive_window(quinn::VarInt::from_u32(100 * 1024 * 1024));
     transport_config.receive_window(quinn::VarInt::from_u32(250 * 1024 * 1024));
+    transport_config.datagram_receive_buffer_size(Some(1024 * 1024));
     transport_config.keep_alive_interval(Some(std::time::Duration::from_secs(5)));
     transport_config.max_idle_timeout(Some(
         quinn::IdleTimeout::try_from(std::time::Duration::from_secs(30)).unwrap(),
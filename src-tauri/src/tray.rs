@@ -3,7 +3,7 @@ use tauri::{
     image::Image,
     menu::{Menu, MenuItem, PredefinedMenuItem},
     tray::{TrayIcon, TrayIconBuilder},
-    AppHandle, Emitter, Listener, Manager, Wry,
+    AppHandle, Listener, Manager, Wry,
 };
 
 #[cfg(target_os = "linux")]
@@ -19,7 +19,7 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
         let send = CheckMenuItem::with_id(
             app,
             "toggle_auto_send",
-            "Auto-Send",
+            crate::i18n::t("tray.auto_send"),
             true,
             false,
             None::<&str>,
@@ -27,7 +27,7 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
         let receive = CheckMenuItem::with_id(
             app,
             "toggle_auto_receive",
-            "Auto-Receive",
+            crate::i18n::t("tray.auto_receive"),
             true,
             false,
             None::<&str>,
@@ -41,22 +41,24 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
         let send = MenuItem::with_id(
             app,
             "toggle_auto_send",
-            "Disable Auto-Send",
+            auto_send_label(true),
             true,
             None::<&str>,
         )?;
         let receive = MenuItem::with_id(
             app,
             "toggle_auto_receive",
-            "Disable Auto-Receive",
+            auto_receive_label(true),
             true,
             None::<&str>,
         )?;
         (send, receive)
     };
 
-    let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-    let show_i = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
+    let send_pending_i = MenuItem::with_id(app, "send_pending", crate::i18n::t("tray.send_pending"), false, None::<&str>)?;
+    let send_files_i = MenuItem::with_id(app, "send_files", crate::i18n::t("tray.send_files"), true, None::<&str>)?;
+    let quit_i = MenuItem::with_id(app, "quit", crate::i18n::t("tray.quit"), true, None::<&str>)?;
+    let show_i = MenuItem::with_id(app, "show", crate::i18n::t("tray.show"), true, None::<&str>)?;
 
     // Construct Menu
     // Note: We need to cast our platform specific items to &dyn IsMenuItem or similar if strictly typed,
@@ -70,6 +72,8 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
             &PredefinedMenuItem::separator(app)?,
             &toggle_auto_send,
             &toggle_auto_receive,
+            &send_pending_i,
+            &send_files_i,
             &PredefinedMenuItem::separator(app)?,
             &quit_i,
         ],
@@ -91,16 +95,8 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
 
     #[cfg(target_os = "linux")]
     {
-        let _ = toggle_auto_send.set_text(if settings.auto_send {
-            "Disable Auto-Send"
-        } else {
-            "Enable Auto-Send"
-        });
-        let _ = toggle_auto_receive.set_text(if settings.auto_receive {
-            "Disable Auto-Receive"
-        } else {
-            "Enable Auto-Receive"
-        });
+        let _ = toggle_auto_send.set_text(auto_send_label(settings.auto_send));
+        let _ = toggle_auto_receive.set_text(auto_receive_label(settings.auto_receive));
     }
 
     // Capture handles for the closure
@@ -120,27 +116,45 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
             let id = event.id.as_ref();
             match id {
                 "quit" => app.exit(0),
-                "show" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.show();
-                        let _ = window.set_focus();
-                        set_badge(app, false);
-                    }
+                "show" => crate::show_main_window(app),
+                "send_pending" => {
+                    let state = app.state::<AppState>();
+                    let transport = app.state::<crate::transport::Transport>();
+                    crate::clipboard::confirm_outgoing(app, &state, &transport);
+                }
+                "send_files" => {
+                    use tauri_plugin_dialog::DialogExt;
+                    let handle = app.clone();
+                    app.dialog().file().set_title(crate::i18n::t("tray.send_files")).pick_files(move |picked| {
+                        let paths: Vec<String> = picked
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter_map(|p| p.into_path().ok())
+                            .map(|p| p.to_string_lossy().to_string())
+                            .collect();
+                        if paths.is_empty() {
+                            return;
+                        }
+                        // Hashing can take a while; don't hold up the event loop
+                        std::thread::spawn(move || {
+                            let state = handle.state::<AppState>();
+                            let transport = handle.state::<crate::transport::Transport>();
+                            if let Err(e) = crate::clipboard::send_files(&handle, &state, &transport, &paths, None) {
+                                tracing::warn!("[Tray] Failed to send files: {}", e);
+                            }
+                        });
+                    });
                 }
                 "toggle_auto_send" => {
                     let state = app.state::<AppState>();
                     let mut settings = state.settings.lock().unwrap();
                     settings.auto_send = !settings.auto_send;
                     crate::storage::save_settings(app, &settings);
-                    let _ = app.emit("settings-changed", settings.clone());
+                    crate::events::emit(app, crate::events::SettingsChanged(&settings));
 
                     // Update Menu Item using captured handle
                     #[cfg(target_os = "linux")]
-                    let _ = toggle_send_handle.set_text(if settings.auto_send {
-                        "Disable Auto-Send"
-                    } else {
-                        "Enable Auto-Send"
-                    });
+                    let _ = toggle_send_handle.set_text(auto_send_label(settings.auto_send));
 
                     #[cfg(not(target_os = "linux"))]
                     let _ = toggle_send_handle.set_checked(settings.auto_send);
@@ -150,15 +164,11 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
                     let mut settings = state.settings.lock().unwrap();
                     settings.auto_receive = !settings.auto_receive;
                     crate::storage::save_settings(app, &settings);
-                    let _ = app.emit("settings-changed", settings.clone());
+                    crate::events::emit(app, crate::events::SettingsChanged(&settings));
 
                     // Update Menu Item using captured handle
                     #[cfg(target_os = "linux")]
-                    let _ = toggle_receive_handle.set_text(if settings.auto_receive {
-                        "Disable Auto-Receive"
-                    } else {
-                        "Enable Auto-Receive"
-                    });
+                    let _ = toggle_receive_handle.set_text(auto_receive_label(settings.auto_receive));
 
                     #[cfg(not(target_os = "linux"))]
                     let _ = toggle_receive_handle.set_checked(settings.auto_receive);
@@ -174,12 +184,7 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
                 ..
             } = event
             {
-                let app = tray.app_handle();
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                    set_badge(app, false);
-                }
+                crate::show_main_window(tray.app_handle());
             }
             #[cfg(not(target_os = "linux"))]
             {
@@ -190,8 +195,6 @@ pub fn create_tray(app: &AppHandle) -> tauri::Result<TrayIcon<Wry>> {
         })
         .build(app)?;
 
-    // Setup Theme Listener
-    let listener_handle = app.clone();
     // Setup Theme Listener
     let listener_handle = app.clone();
     app.listen("tauri://theme-changed", move |event| {
@@ -308,6 +311,8 @@ pub fn update_tray_icon(app: &AppHandle) {
             let _ = tray.set_icon_as_template(is_template);
             let _ = tray.set_icon(Some(icon));
         }
+        // The badge is drawn in the old theme's colors
+        refresh_badge(app);
     }
 }
 
@@ -324,16 +329,13 @@ pub fn update_tray_menu(app: &AppHandle) {
             #[cfg(target_os = "linux")]
             {
                 if let Some(menu_item) = item.as_menuitem() {
-                    let _ = menu_item.set_text(if settings.auto_send {
-                        "Disable Auto-Send"
-                    } else {
-                        "Enable Auto-Send"
-                    });
+                    let _ = menu_item.set_text(auto_send_label(settings.auto_send));
                 }
             }
             #[cfg(not(target_os = "linux"))]
             {
                 if let Some(check_item) = item.as_check_menuitem() {
+                    let _ = check_item.set_text(crate::i18n::t("tray.auto_send"));
                     let _ = check_item.set_checked(settings.auto_send);
                 }
             }
@@ -344,26 +346,47 @@ pub fn update_tray_menu(app: &AppHandle) {
             #[cfg(target_os = "linux")]
             {
                 if let Some(menu_item) = item.as_menuitem() {
-                    let _ = menu_item.set_text(if settings.auto_receive {
-                        "Disable Auto-Receive"
-                    } else {
-                        "Enable Auto-Receive"
-                    });
+                    let _ = menu_item.set_text(auto_receive_label(settings.auto_receive));
                 }
             }
             #[cfg(not(target_os = "linux"))]
             {
                 if let Some(check_item) = item.as_check_menuitem() {
+                    let _ = check_item.set_text(crate::i18n::t("tray.auto_receive"));
                     let _ = check_item.set_checked(settings.auto_receive);
                 }
             }
         }
+
+        // Content held for review before sending
+        if let Some(item) = menu.get("send_pending").and_then(|i| i.as_menuitem().cloned()) {
+            let _ = item.set_text(crate::i18n::t("tray.send_pending"));
+            let _ = item.set_enabled(state.pending_outgoing.lock().unwrap().is_some());
+        }
+
+        // Fixed labels (the locale may have changed)
+        for (id, key) in [("show", "tray.show"), ("send_files", "tray.send_files"), ("quit", "tray.quit")] {
+            if let Some(item) = menu.get(id).and_then(|i| i.as_menuitem().cloned()) {
+                let _ = item.set_text(crate::i18n::t(key));
+            }
+        }
     }
 }
 
-pub fn set_badge(app: &AppHandle, show: bool) {
+#[cfg(target_os = "linux")]
+fn auto_send_label(enabled: bool) -> String {
+    crate::i18n::t(if enabled { "tray.disable_auto_send" } else { "tray.enable_auto_send" })
+}
+
+#[cfg(target_os = "linux")]
+fn auto_receive_label(enabled: bool) -> String {
+    crate::i18n::t(if enabled { "tray.disable_auto_receive" } else { "tray.enable_auto_receive" })
+}
+
+/// Show `count` unread items on the tray icon (0 restores the plain icon).
+pub fn set_badge(app: &AppHandle, count: u32) {
     if let Some(tray) = app.tray_by_id("main-tray") {
-        if !show {
+        if count == 0 {
             // Restore default icon
             let (icon, is_template) = get_platform_icon(app);
             let _ = tray.set_icon_as_template(is_template);
@@ -375,6 +398,12 @@ pub fn set_badge(app: &AppHandle, show: bool) {
         // We'll reuse get_platform_icon logic but need the raw bytes or re-load.
         // It's cleaner to just re-load source bytes here.
 
+        // Color of the icon itself, for "mono" badges
+        #[cfg(target_os = "windows")]
+        let mono = image::Rgba([255, 255, 255, 255]);
+        #[cfg(not(target_os = "windows"))]
+        let mut mono = image::Rgba([0, 0, 0, 255]);
+
         let icon_bytes = {
             #[cfg(target_os = "windows")]
             {
@@ -406,6 +435,7 @@ pub fn set_badge(app: &AppHandle, show: bool) {
 
                 match theme {
                     Theme::Dark => {
+                        mono = image::Rgba([255, 255, 255, 255]);
                         include_bytes!("../icons/png/clustercut-tray-white.png").to_vec()
                     }
                     Theme::Light => {
@@ -427,29 +457,8 @@ pub fn set_badge(app: &AppHandle, show: bool) {
             // Force RGBA8 to ensure colors are preserved (fixes macOS "Gray Dot" issue)
             let mut img = dynamic_img.into_rgba8();
 
-            // Draw Red Dot (Top Right)
-            // 20% size, 5% padding
-            let (w, h) = (img.width(), img.height());
-            let dot_size = (w as f32 * 0.25) as u32;
-            let padding = (w as f32 * 0.05) as u32; // 5% padding
-
-            // For RGBA drawing manually
-            use image::Rgba;
-
-            let red = Rgba([255, 0, 0, 255]);
-
-            // Draw circle-ish square for now or circle
-            // Simple square dot
-            let x_start = w - dot_size - padding;
-            let y_start = padding;
-
-            for x in x_start..(x_start + dot_size) {
-                for y in y_start..(y_start + dot_size) {
-                    if x < w && y < h {
-                        img.put_pixel(x, y, red);
-                    }
-                }
-            }
+            let badge = app.state::<AppState>().settings.lock().unwrap().tray_badge.clone();
+            crate::badge::draw(&mut img, count, &badge, mono);
 
             // Convert back to bytes (PNG usually best for transport)
             // But for Tauri Tray, Image::from_rgba is best if we have raw buffer
@@ -469,3 +478,11 @@ pub fn set_badge(app: &AppHandle, show: bool) {
         }
     }
 }
+
+/// Redraw the badge that's showing (badge settings or theme changed).
+pub fn refresh_badge(app: &AppHandle) {
+    let count = crate::unread::count(&app.state::<AppState>());
+    if count > 0 {
+        set_badge(app, count);
+    }
+}
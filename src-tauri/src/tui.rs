@@ -0,0 +1,187 @@
+//! Terminal UI (`--tui`) for headless machines.
+//!
+//! Runs on top of the normal app (window hidden): peers come straight from AppState, history
+//! and transfer progress from the same events the GUI listens to. Typed lines are broadcast
+//! through `send_clipboard`, exactly like a manual send from the GUI.
+//!
+//! Because it's the normal app underneath, the binary still links GTK/WebKitGTK (WebView2 on
+//! Windows) and creates its window at startup. On a Linux server that means installing the
+//! same runtime libraries as the desktop package and providing a display, e.g.
+//! `xvfb-run clustercut --tui`; without one `run` exits with that hint instead of letting
+//! GTK fail.
+
+use crate::protocol::ClipboardPayload;
+use crate::state::AppState;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, List, ListItem, Paragraph};
+use ratatui::Frame;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Listener, Manager};
+
+const HISTORY_LIMIT: usize = 50;
+const TRANSFER_LIMIT: usize = 4;
+// Redraw interval (also bounds how long a keypress can wait)
+const TICK: Duration = Duration::from_millis(250);
+
+struct Transfer {
+    id: String,
+    file_name: String,
+    transferred: u64,
+    total: u64,
+}
+
+#[derive(Default)]
+struct Feed {
+    history: VecDeque<ClipboardPayload>,
+    transfers: VecDeque<Transfer>,
+}
+
+/// Take over the terminal. Exits the app when the TUI is closed.
+pub fn spawn(app: AppHandle) {
+    let feed = Arc::new(Mutex::new(Feed::default()));
+
+    let history_feed = feed.clone();
+    app.listen("clipboard-change", move |event| {
+        if let Ok(payload) = serde_json::from_str::<ClipboardPayload>(event.payload()) {
+            let mut feed = history_feed.lock().unwrap();
+            feed.history.push_front(payload);
+            feed.history.truncate(HISTORY_LIMIT);
+        }
+    });
+
+    let transfer_feed = feed.clone();
+    app.listen("file-progress", move |event| {
+        let Ok(p) = serde_json::from_str::<crate::events::FileProgress>(event.payload()) else {
+            return;
+        };
+        let mut feed = transfer_feed.lock().unwrap();
+        match feed.transfers.iter_mut().find(|t| t.id == p.id && t.file_name == p.file_name) {
+            Some(t) => {
+                t.transferred = p.transferred;
+                t.total = p.total;
+            }
+            None => {
+                feed.transfers.push_front(Transfer { id: p.id, file_name: p.file_name, transferred: p.transferred, total: p.total });
+                feed.transfers.truncate(TRANSFER_LIMIT);
+            }
+        }
+    });
+
+    std::thread::spawn(move || {
+        let mut terminal = ratatui::init();
+        let result = run(&mut terminal, &app, &feed);
+        ratatui::restore();
+        if let Err(e) = result {
+            tracing::error!("TUI failed: {}", e);
+        }
+        app.exit(0);
+    });
+}
+
+fn run(terminal: &mut ratatui::DefaultTerminal, app: &AppHandle, feed: &Mutex<Feed>) -> std::io::Result<()> {
+    let state = app.state::<AppState>();
+    let mut input = String::new();
+    let mut status = String::from("Type text and press Enter to send it to the cluster. Esc quits.");
+
+    while !state.is_shutdown() {
+        terminal.draw(|frame| draw(frame, &state, &feed.lock().unwrap(), &input, &status))?;
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Enter if !input.trim().is_empty() => {
+                let text = std::mem::take(&mut input);
+                let sent = tauri::async_runtime::block_on(crate::send_clipboard(text, None, app.state(), app.state(), app.clone()));
+                status = match sent {
+                    Ok(()) => "Sent to the cluster.".to_string(),
+                    Err(e) => format!("Send failed: {}", e),
+                };
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn preview(payload: &ClipboardPayload) -> String {
+    if let Some(files) = payload.files.as_ref().filter(|f| !f.is_empty()) {
+        return format!("[{} file(s): {}]", files.len(), files[0].name);
+    }
+    let line = payload.text.lines().next().unwrap_or_default();
+    match line.char_indices().nth(80) {
+        Some((i, _)) => format!("{}…", &line[..i]),
+        None => line.to_string(),
+    }
+}
+
+fn draw(frame: &mut Frame, state: &AppState, feed: &Feed, input: &str, status: &str) {
+    let [header, body, transfers, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(5),
+        Constraint::Length(TRANSFER_LIMIT as u16 + 2),
+        Constraint::Length(3),
+    ])
+    .areas(frame.area());
+
+    let network = state.network_name.lock().unwrap().clone();
+    let device_id = state.local_device_id.lock().unwrap().clone();
+    let mode = if state.power.is_low_power() { " · low power" } else { "" };
+    frame.render_widget(Paragraph::new(format!("ClusterCut · {} · {}{}", network, device_id, mode)).bold(), header);
+
+    let [peers_area, history_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body);
+
+    let mut peers: Vec<_> = state.get_peers().into_values().collect();
+    peers.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+    let peer_items: Vec<ListItem> = peers
+        .iter()
+        .map(|p| {
+            let mark = if p.is_trusted { "●" } else { "○" };
+            let guest = if p.guest_expires_at.is_some() { " (guest)" } else { "" };
+            ListItem::new(format!("{} {}{}  {}", mark, p.hostname, guest, p.ip))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(peer_items).block(Block::bordered().title(format!(" Peers ({}) ", peers.len()))),
+        peers_area,
+    );
+
+    let history_items: Vec<ListItem> = feed
+        .history
+        .iter()
+        .map(|h| ListItem::new(format!("{}: {}", h.sender, preview(h))))
+        .collect();
+    frame.render_widget(List::new(history_items).block(Block::bordered().title(" History ")), history_area);
+
+    let transfer_items: Vec<ListItem> = feed
+        .transfers
+        .iter()
+        .map(|t| {
+            let pct = (t.transferred * 100).checked_div(t.total).unwrap_or(100);
+            ListItem::new(format!("{:>3}%  {}  ({:.1} / {:.1} MB)", pct, t.file_name, t.transferred as f64 / 1e6, t.total as f64 / 1e6))
+        })
+        .collect();
+    frame.render_widget(List::new(transfer_items).block(Block::bordered().title(" Transfers ")), transfers);
+
+    frame.render_widget(
+        Paragraph::new(input).block(Block::bordered().title(format!(" {} ", status))),
+        footer,
+    );
+    frame.set_cursor_position((footer.x + 1 + input.chars().count() as u16, footer.y + 1));
+}
@@ -0,0 +1,321 @@
+//! Link previews for copied URLs.
+//!
+//! Nothing here runs before an item is sent. With `unfurl_links` on, the *sender* looks at a
+//! copied URL in the background once the item is out: a HEAD request only (GETting the page
+//! could use up a one-time or sign-in link), plus the site's /favicon.ico. The page itself is
+//! only fetched (for its title) when the user asks, with `fetch_link_preview`. Either way the
+//! result goes to history and, from the sender, to the other members in a `LinkPreview`
+//! message. Receivers never contact the site, they only display what came over the wire
+//! (after `sanitize`).
+
+use crate::protocol::{LinkPreview, LinkPreviewUpdate, Message};
+use crate::state::AppState;
+use crate::transport::Transport;
+use base64::Engine;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+// Only the <head> matters; stop reading long before a full page
+const MAX_HTML_BYTES: usize = 256 * 1024;
+const MAX_FAVICON_BYTES: usize = 32 * 1024;
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_URL_LEN: usize = 2048;
+
+/// The URL if `text` is nothing but a single http(s) link.
+pub fn single_url(text: &str) -> Option<url::Url> {
+    let text = text.trim();
+    if text.is_empty() || text.len() > MAX_URL_LEN || text.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let url = url::Url::parse(text).ok()?;
+    // Never send embedded credentials anywhere
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() || !url.username().is_empty() || url.password().is_some() {
+        return None;
+    }
+    Some(url)
+}
+
+fn fetch_settings(state: &AppState) -> (bool, Duration, crate::proxy::ProxySettings) {
+    let settings = state.settings.lock().unwrap();
+    (settings.unfurl_links, Duration::from_millis(settings.unfurl_timeout_ms.clamp(250, 10_000)), settings.proxy.clone())
+}
+
+/// Once `payload` has been sent: if link previews are on and it's a single URL, probe it in
+/// the background and share what we find. Never blocks the caller.
+pub fn after_send(app: &AppHandle, payload: &crate::protocol::ClipboardPayload) {
+    let state = app.state::<AppState>();
+    let (enabled, timeout, proxy) = fetch_settings(&state);
+    if !enabled || payload.files.as_ref().is_some_and(|f| !f.is_empty()) {
+        return;
+    }
+    let Some(url) = single_url(&payload.text) else {
+        return;
+    };
+    let app = app.clone();
+    let id = payload.id.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(link) = probe(&url, timeout, &proxy).await {
+            publish(&app, &id, link).await;
+        }
+    });
+}
+
+/// User asked for the preview of history item `id`: fetch the page (GET) and share the result.
+pub async fn fetch_for_item(app: &AppHandle, id: &str) -> Result<LinkPreview, String> {
+    let (url, timeout, proxy) = {
+        let state = app.state::<AppState>();
+        let (_, timeout, proxy) = fetch_settings(&state);
+        let items = state.recent_items.lock().unwrap();
+        let item = items.iter().find(|p| p.id == id).ok_or("No such history item")?;
+        (single_url(&item.text).ok_or("This item isn't a link")?, timeout, proxy)
+    };
+    let link = fetch(&url, timeout, &proxy).await.ok_or("Couldn't load a preview for this link")?;
+    publish(app, id, link.clone()).await;
+    Ok(link)
+}
+
+/// Attach `link` to history item `id`, and tell the other members if the item is ours.
+async fn publish(app: &AppHandle, id: &str, link: LinkPreview) {
+    let state = app.state::<AppState>();
+    let local_id = state.local_device_id.lock().unwrap().clone();
+    let own_item = {
+        let mut items = state.recent_items.lock().unwrap();
+        let Some(item) = items.iter_mut().find(|p| p.id == id) else {
+            return;
+        };
+        item.link = Some(link.clone());
+        item.sender_id == local_id
+    };
+    crate::events::emit(app, crate::events::HistoryLinkPreview { id: id.to_string(), link: link.clone() });
+    if !own_item {
+        return;
+    }
+
+    let update = LinkPreviewUpdate { id: id.to_string(), sender_id: local_id, link };
+    let Some(cipher) = crate::encrypt_cluster_message(&state, &update) else {
+        return;
+    };
+    let data = serde_json::to_vec(&Message::LinkPreview(cipher)).unwrap_or_default();
+    let transport = app.state::<Transport>().inner().clone();
+    for p in state.get_peers().values().filter(|p| p.is_trusted) {
+        let _ = transport.send_message(p.primary_addr(), &data).await;
+    }
+}
+
+/// A member shared the preview of one of its items. Applied only to that member's own item,
+/// and only if the preview is for the URL the item actually holds.
+pub fn apply_update(app: &AppHandle, update: LinkPreviewUpdate) {
+    let Some(link) = sanitize(Some(update.link)) else {
+        return;
+    };
+    let state = app.state::<AppState>();
+    {
+        let mut items = state.recent_items.lock().unwrap();
+        let Some(item) = items.iter_mut().find(|p| p.id == update.id && p.sender_id == update.sender_id) else {
+            return;
+        };
+        if single_url(&item.text).map(|u| u.to_string()) != Some(link.url.clone()) {
+            tracing::warn!("Ignoring link preview for {} that doesn't match its URL", update.id);
+            return;
+        }
+        item.link = Some(link.clone());
+    }
+    crate::events::emit(app, crate::events::HistoryLinkPreview { id: update.id, link });
+}
+
+fn client(timeout: Duration, proxy: &crate::proxy::ProxySettings, url: &url::Url) -> Option<reqwest::Client> {
+    let builder = match crate::proxy::client(proxy) {
+        Ok(builder) => builder,
+        Err(e) => {
+            tracing::warn!("[Unfurl] Not fetching {}: {}", crate::redact::url(url), e);
+            return None;
+        }
+    };
+    builder
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::limited(5))
+        .user_agent(concat!("ClusterCut/", env!("CARGO_PKG_VERSION"), " (link preview)"))
+        .build()
+        .ok()
+}
+
+/// HEAD `url` and, if it's a web page, fetch the site's favicon. No title: that needs the
+/// page body, which is only fetched on request (`fetch`).
+pub async fn probe(url: &url::Url, timeout: Duration, proxy: &crate::proxy::ProxySettings) -> Option<LinkPreview> {
+    let client = client(timeout, proxy, url)?;
+    let response = match client.head(url.clone()).send().await {
+        Ok(r) if r.status().is_success() => r,
+        Ok(r) => {
+            tracing::debug!("[Unfurl] HEAD {} returned {}", crate::redact::url(url), r.status());
+            return None;
+        }
+        Err(e) => {
+            tracing::debug!("[Unfurl] HEAD {} failed: {}", crate::redact::url(url), e);
+            return None;
+        }
+    };
+    if !content_type(&response).is_some_and(|t| is_html(&t)) {
+        return None;
+    }
+    let favicon = match response.url().join("/favicon.ico") {
+        Ok(u) => fetch_favicon(&client, u).await,
+        Err(_) => None,
+    };
+    favicon.map(|favicon| LinkPreview { url: url.to_string(), title: None, favicon: Some(favicon) })
+}
+
+/// Fetch title/favicon for `url`. None if the page is unreachable, too slow or not HTML.
+pub async fn fetch(url: &url::Url, timeout: Duration, proxy: &crate::proxy::ProxySettings) -> Option<LinkPreview> {
+    let client = client(timeout, proxy, url)?;
+
+    let response = match client.get(url.clone()).send().await {
+        Ok(r) if r.status().is_success() => r,
+        Ok(r) => {
+            tracing::debug!("[Unfurl] {} returned {}", crate::redact::url(url), r.status());
+            return None;
+        }
+        Err(e) => {
+            tracing::debug!("[Unfurl] Fetching {} failed: {}", crate::redact::url(url), e);
+            return None;
+        }
+    };
+    if !content_type(&response).is_some_and(|t| is_html(&t)) {
+        return None;
+    }
+    let final_url = response.url().clone();
+    let html = String::from_utf8_lossy(&read_capped(response, MAX_HTML_BYTES).await?).into_owned();
+
+    let title = meta_content(&html, "og:title").or_else(|| title_tag(&html)).map(|t| clean_text(&t)).filter(|t| !t.is_empty());
+    let icon_url = icon_href(&html)
+        .and_then(|href| final_url.join(&href).ok())
+        .or_else(|| final_url.join("/favicon.ico").ok());
+    let favicon = match icon_url {
+        Some(u) if matches!(u.scheme(), "http" | "https") => fetch_favicon(&client, u).await,
+        _ => None,
+    };
+
+    if title.is_none() && favicon.is_none() {
+        return None;
+    }
+    Some(LinkPreview { url: url.to_string(), title, favicon })
+}
+
+/// Drop anything a receiver shouldn't render (oversized, or a favicon that isn't inline image data).
+pub fn sanitize(link: Option<LinkPreview>) -> Option<LinkPreview> {
+    let mut link = link?;
+    link.title = link.title.map(|t| clean_text(&t)).filter(|t| !t.is_empty());
+    // Inline data only, so displaying the entry never contacts the site
+    link.favicon = link
+        .favicon
+        .filter(|f| f.len() <= MAX_FAVICON_BYTES * 2 && f.starts_with("data:image/") && f.contains(";base64,"));
+    (link.url.len() <= MAX_URL_LEN).then_some(link)
+}
+
+fn is_html(content_type: &str) -> bool {
+    content_type.starts_with("text/html") || content_type.starts_with("application/xhtml")
+}
+
+fn content_type(response: &reqwest::Response) -> Option<String> {
+    let value = response.headers().get(reqwest::header::CONTENT_TYPE)?.to_str().ok()?;
+    Some(value.trim().to_ascii_lowercase())
+}
+
+async fn read_capped(mut response: reqwest::Response, limit: usize) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.ok()? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= limit {
+            body.truncate(limit);
+            break;
+        }
+    }
+    Some(body)
+}
+
+async fn fetch_favicon(client: &reqwest::Client, url: url::Url) -> Option<String> {
+    let response = client.get(url).send().await.ok().filter(|r| r.status().is_success())?;
+    let mime = content_type(&response).filter(|t| t.starts_with("image/"))?;
+    let mime = mime.split(';').next().unwrap_or_default().trim().to_string();
+    if response.content_length().is_some_and(|len| len as usize > MAX_FAVICON_BYTES) {
+        return None;
+    }
+    let bytes = read_capped(response, MAX_FAVICON_BYTES + 1).await?;
+    if bytes.is_empty() || bytes.len() > MAX_FAVICON_BYTES {
+        return None;
+    }
+    Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes)))
+}
+
+// --- Minimal <head> scanning (no HTML parser needed for three values) ---
+
+// ASCII lowercasing keeps byte offsets, so positions found in `lower` index into `html`
+fn tags<'a>(html: &'a str, lower: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let open = format!("<{}", name);
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let start = pos + lower[pos..].find(&open)?;
+        let end = start + lower[start..].find('>')?;
+        pos = end;
+        Some(&html[start..end])
+    })
+}
+
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let at = from + i;
+        from = at + name.len();
+        // Whole attribute name only (not e.g. "data-href")
+        if !lower[..at].ends_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+        let rest = tag[from..].trim_start().strip_prefix('=')?.trim_start();
+        let quote = rest.chars().next()?;
+        let value = if quote == '"' || quote == '\'' {
+            rest[1..].split(quote).next()?
+        } else {
+            rest.split(|c: char| c.is_whitespace() || c == '/').next()?
+        };
+        return Some(value.to_string());
+    }
+    None
+}
+
+fn meta_content(html: &str, property: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let content = tags(html, &lower, "meta")
+        .find(|t| attr(t, "property").or_else(|| attr(t, "name")).is_some_and(|p| p.eq_ignore_ascii_case(property)))
+        .and_then(|t| attr(t, "content"));
+    content
+}
+
+fn title_tag(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    Some(html[start..end].to_string())
+}
+
+fn icon_href(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let href = tags(html, &lower, "link")
+        .find(|t| attr(t, "rel").is_some_and(|r| r.to_ascii_lowercase().split_whitespace().any(|w| w == "icon")))
+        .and_then(|t| attr(t, "href"))
+        .filter(|h| !h.is_empty());
+    href
+}
+
+fn clean_text(s: &str) -> String {
+    let decoded = s
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(MAX_TITLE_CHARS).collect()
+}
+
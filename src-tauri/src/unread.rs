@@ -0,0 +1,43 @@
+//! Unread counter: items received while the window wasn't focused.
+//!
+//! Shown on the tray icon (see badge.rs) and the macOS dock, and sent to the UI as
+//! "unread-changed". Focusing or showing the window resets it, as does `clear_unread`.
+
+use crate::state::AppState;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Manager};
+
+fn window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main").is_some_and(|w| w.is_focused().unwrap_or(false))
+}
+
+/// Count a received item, unless the user is looking at the window.
+pub fn received(app: &AppHandle) {
+    if window_focused(app) {
+        return;
+    }
+    let count = app.state::<AppState>().unread.fetch_add(1, Ordering::Relaxed).saturating_add(1);
+    show(app, count);
+}
+
+pub fn clear(app: &AppHandle) {
+    if app.state::<AppState>().unread.swap(0, Ordering::Relaxed) != 0 {
+        show(app, 0);
+    }
+}
+
+pub fn count(state: &AppState) -> u32 {
+    state.unread.load(Ordering::Relaxed)
+}
+
+fn show(app: &AppHandle, count: u32) {
+    #[cfg(desktop)]
+    crate::tray::set_badge(app, count);
+
+    #[cfg(target_os = "macos")]
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_badge_count(if count == 0 { None } else { Some(count as i64) });
+    }
+
+    crate::events::emit(app, crate::events::UnreadChanged { count });
+}
@@ -0,0 +1,154 @@
+//! Optional check for new releases (`update_check`, off by default).
+//!
+//! About a minute after start and then once a day, the releases list at `update_endpoint` (the
+//! GitHub releases API format) is fetched through the proxy settings. The highest version on
+//! `update_channel` (`stable` skips pre-releases, `beta` includes them) is compared with this
+//! build, and "update-available" is emitted, with the release notes, when it's newer. Each
+//! version is announced once per run. Nothing is downloaded or installed.
+
+use crate::state::AppState;
+use crate::storage::AppSettings;
+use once_cell::sync::Lazy;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.github.com/repos/keithvassallomt/ucp/releases";
+const INTERVAL: Duration = Duration::from_secs(24 * 3600);
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+// How often the background task looks at the settings (picks up `update_check` being turned on)
+const TICK: Duration = Duration::from_secs(3600);
+const TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_NOTES_CHARS: usize = 8000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+// One entry of the GitHub releases API response (fields we use)
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    published_at: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub name: String,
+    // Markdown, as written on the release page
+    pub notes: String,
+    pub url: String,
+    pub prerelease: bool,
+    pub published_at: Option<String>,
+}
+
+// Highest version announced so far this run
+static ANNOUNCED: Lazy<Mutex<Option<Version>>> = Lazy::new(|| Mutex::new(None));
+
+fn parse_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.trim().trim_start_matches(['v', 'V'])).ok()
+}
+
+fn newest(releases: Vec<Release>, channel: UpdateChannel) -> Option<(Version, Release)> {
+    releases
+        .into_iter()
+        .filter(|r| !r.draft)
+        .filter_map(|r| Some((parse_version(&r.tag_name)?, r)))
+        .filter(|(v, r)| channel == UpdateChannel::Beta || (!r.prerelease && v.pre.is_empty()))
+        .max_by(|a, b| a.0.cmp(&b.0))
+}
+
+/// Fetch the releases list and return the newest release on the channel, if it's newer than us.
+pub async fn check(settings: &AppSettings) -> Result<Option<UpdateInfo>, String> {
+    let endpoint = match settings.update_endpoint.trim() {
+        "" => DEFAULT_ENDPOINT,
+        e => e,
+    };
+    let url = url::Url::parse(endpoint).map_err(|e| format!("Invalid update endpoint: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("The update endpoint must be an http(s) URL".to_string());
+    }
+    let client = crate::proxy::client(&settings.proxy)?
+        .timeout(TIMEOUT)
+        .user_agent(concat!("ClusterCut/", env!("CARGO_PKG_VERSION"), " (update check)"))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Update check failed: {}", response.status()));
+    }
+    let body = response.bytes().await.map_err(|e| format!("Update check failed: {}", e))?;
+    let releases: Vec<Release> = serde_json::from_slice(&body).map_err(|e| format!("Unexpected releases response: {}", e))?;
+
+    let current = Version::parse(env!("CARGO_PKG_VERSION")).map_err(|e| e.to_string())?;
+    let Some((version, release)) = newest(releases, settings.update_channel).filter(|(v, _)| *v > current) else {
+        return Ok(None);
+    };
+    Ok(Some(UpdateInfo {
+        version: version.to_string(),
+        current_version: current.to_string(),
+        name: release.name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| release.tag_name.clone()),
+        notes: release.body.unwrap_or_default().chars().take(MAX_NOTES_CHARS).collect(),
+        url: release.html_url,
+        prerelease: release.prerelease || !version.pre.is_empty(),
+        published_at: release.published_at,
+    }))
+}
+
+// Emit "update-available" unless this version (or a newer one) was already announced
+fn announce(app: &AppHandle, info: &UpdateInfo) {
+    let Some(version) = parse_version(&info.version) else {
+        return;
+    };
+    {
+        let mut announced = ANNOUNCED.lock().unwrap();
+        if announced.as_ref().is_some_and(|a| *a >= version) {
+            return;
+        }
+        *announced = Some(version);
+    }
+    tracing::info!("[Update] Version {} is available (running {})", info.version, info.current_version);
+    crate::events::emit(app, info);
+}
+
+pub async fn run(app: AppHandle, state: AppState) {
+    tokio::time::sleep(STARTUP_DELAY).await;
+    let mut last_check: Option<Instant> = None;
+    while !state.is_shutdown() {
+        let settings = state.settings.lock().unwrap().clone();
+        let due = last_check.is_none_or(|t| t.elapsed() >= INTERVAL);
+        if settings.update_check && due {
+            last_check = Some(Instant::now());
+            match check(&settings).await {
+                Ok(Some(info)) => announce(&app, &info),
+                Ok(None) => tracing::debug!("[Update] Up to date ({:?} channel)", settings.update_channel),
+                Err(e) => tracing::warn!("[Update] {}", e),
+            }
+        }
+        tokio::time::sleep(TICK).await;
+    }
+}
@@ -0,0 +1,201 @@
+//! Sanity checks on decoded messages before they reach `handle_message`.
+//!
+//! serde happily decodes arbitrarily large `Vec<u8>`/`String`/`Vec<Peer>` fields from LAN
+//! traffic; these caps reject anything a well-behaved peer would never send.
+
+use crate::peer::Peer;
+use crate::protocol::Message;
+use std::fmt;
+
+// Per-field caps (bytes unless noted)
+const MAX_ID_LEN: usize = 128;
+const MAX_HOSTNAME_LEN: usize = 255;
+pub const MAX_NETWORK_NAME_LEN: usize = 64;
+pub const MAX_PIN_LEN: usize = 32;
+const MAX_SIGNATURE_LEN: usize = 512;
+const MAX_PLATFORM_FIELD_LEN: usize = 64; // os, versions, arch
+const MAX_PEER_ADDRESSES: usize = 16;
+const MAX_SPAKE_MSG_LEN: usize = 256;
+const MAX_WRAPPED_KEY_LEN: usize = 256;
+const MAX_PUBLIC_KEY_LEN: usize = 64; // Base64 Ed25519 key
+const MAX_KEY_CONFIRMATION_LEN: usize = 32; // HMAC-SHA256
+const MAX_WELCOME_PEERS: usize = 256; // entries
+const MAX_SMALL_CIPHER_LEN: usize = 16 * 1024; // requests, annotations
+const MAX_CLIPBOARD_CIPHER_LEN: usize = 8 * 1024 * 1024;
+const MAX_PROBE_CIPHER_LEN: usize = 1024 * 1024; // connection test throughput sample
+const MAX_PEER_LIST_CIPHER_LEN: usize = 1024 * 1024; // up to MAX_WELCOME_PEERS peer records
+const MAX_LINK_PREVIEW_CIPHER_LEN: usize = 64 * 1024; // URL, title and an inline favicon
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    TooLarge { field: &'static str, len: usize, max: usize },
+    Invalid { field: &'static str, reason: &'static str },
+}
+
+impl ValidationError {
+    /// Stable key for metrics ("field:kind").
+    pub fn metric_key(&self) -> String {
+        match self {
+            ValidationError::TooLarge { field, .. } => format!("{}:too_large", field),
+            ValidationError::Invalid { field, .. } => format!("{}:invalid", field),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::TooLarge { field, len, max } => {
+                write!(f, "{} too large ({} > {})", field, len, max)
+            }
+            ValidationError::Invalid { field, reason } => write!(f, "{} invalid: {}", field, reason),
+        }
+    }
+}
+
+fn cap(field: &'static str, len: usize, max: usize) -> Result<(), ValidationError> {
+    if len > max {
+        return Err(ValidationError::TooLarge { field, len, max });
+    }
+    Ok(())
+}
+
+fn non_empty(field: &'static str, value: &[u8]) -> Result<(), ValidationError> {
+    if value.is_empty() {
+        return Err(ValidationError::Invalid { field, reason: "empty" });
+    }
+    Ok(())
+}
+
+// Device/item IDs are generated by us (uuid / "clustercut-N" / "manual-IP"): printable ASCII only
+fn id(field: &'static str, value: &str) -> Result<(), ValidationError> {
+    cap(field, value.len(), MAX_ID_LEN)?;
+    non_empty(field, value.as_bytes())?;
+    if !value.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(ValidationError::Invalid { field, reason: "non-printable characters" });
+    }
+    Ok(())
+}
+
+fn peer(peer: &Peer) -> Result<(), ValidationError> {
+    id("peer.id", &peer.id)?;
+    cap("peer.hostname", peer.hostname.len(), MAX_HOSTNAME_LEN)?;
+    if peer.port == 0 {
+        return Err(ValidationError::Invalid { field: "peer.port", reason: "zero" });
+    }
+    if let Some(n) = &peer.network_name {
+        cap("peer.network_name", n.len(), MAX_NETWORK_NAME_LEN)?;
+    }
+    if let Some(sig) = &peer.signature {
+        cap("peer.signature", sig.len(), MAX_SIGNATURE_LEN)?;
+    }
+    if let Some(key) = &peer.public_key {
+        cap("peer.public_key", key.len(), MAX_PUBLIC_KEY_LEN)?;
+    }
+    if let Some(p) = &peer.platform {
+        cap("peer.platform.os", p.os.len(), MAX_PLATFORM_FIELD_LEN)?;
+        cap("peer.platform.os_version", p.os_version.len(), MAX_PLATFORM_FIELD_LEN)?;
+        cap("peer.platform.arch", p.arch.len(), MAX_PLATFORM_FIELD_LEN)?;
+        cap("peer.platform.app_version", p.app_version.len(), MAX_PLATFORM_FIELD_LEN)?;
+    }
+    cap("peer.addresses", peer.addresses.len(), MAX_PEER_ADDRESSES)?;
+    Ok(())
+}
+
+/// Check the members of a decrypted PeerList.
+pub fn peer_list(members: &[Peer]) -> Result<(), ValidationError> {
+    cap("peer_list.members", members.len(), MAX_WELCOME_PEERS)?;
+    members.iter().try_for_each(peer)
+}
+
+/// Check a decoded message against per-variant limits.
+pub fn validate(msg: &Message) -> Result<(), ValidationError> {
+    match msg {
+        Message::Clipboard(cipher) => {
+            non_empty("clipboard", cipher)?;
+            cap("clipboard", cipher.len(), MAX_CLIPBOARD_CIPHER_LEN)
+        }
+        Message::PairRequest { msg, device_id, .. } => {
+            non_empty("pair.msg", msg)?;
+            cap("pair.msg", msg.len(), MAX_SPAKE_MSG_LEN)?;
+            id("pair.device_id", device_id)
+        }
+        Message::PairResponse { msg, device_id, confirmation } => {
+            non_empty("pair.msg", msg)?;
+            cap("pair.msg", msg.len(), MAX_SPAKE_MSG_LEN)?;
+            cap("pair.confirmation", confirmation.len(), MAX_KEY_CONFIRMATION_LEN)?;
+            id("pair.device_id", device_id)
+        }
+        Message::PairConfirm { device_id, confirmation, device_key } => {
+            non_empty("pair.confirmation", confirmation)?;
+            cap("pair.confirmation", confirmation.len(), MAX_KEY_CONFIRMATION_LEN)?;
+            cap("pair.device_key", device_key.len(), MAX_WRAPPED_KEY_LEN)?;
+            id("pair.device_id", device_id)
+        }
+        Message::PairRejected { device_id, .. } | Message::AdmissionPending { device_id } => id("pair.device_id", device_id),
+        Message::Welcome { encrypted_cluster_key, known_peers, network_name, network_pin, owner_id, key_confirmation, device_key, .. } => {
+            if let Some(owner) = owner_id {
+                id("welcome.owner_id", owner)?;
+            }
+            non_empty("welcome.key", encrypted_cluster_key)?;
+            cap("welcome.key", encrypted_cluster_key.len(), MAX_WRAPPED_KEY_LEN)?;
+            cap("welcome.known_peers", known_peers.len(), MAX_WELCOME_PEERS)?;
+            cap("welcome.network_name", network_name.len(), MAX_NETWORK_NAME_LEN)?;
+            cap("welcome.network_pin", network_pin.len(), MAX_PIN_LEN)?;
+            cap("welcome.key_confirmation", key_confirmation.len(), MAX_KEY_CONFIRMATION_LEN)?;
+            cap("welcome.device_key", device_key.len(), MAX_WRAPPED_KEY_LEN)?;
+            known_peers.iter().try_for_each(peer)
+        }
+        Message::PeerDiscovery(p) => peer(p),
+        Message::PeerRemoval(device_id) => id("peer_removal.device_id", device_id),
+        Message::HistoryDelete(item_id) => id("history_delete.id", item_id),
+        Message::HistoryAnnotate(cipher) => {
+            non_empty("history_annotate", cipher)?;
+            cap("history_annotate", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::LinkPreview(cipher) => {
+            non_empty("link_preview", cipher)?;
+            cap("link_preview", cipher.len(), MAX_LINK_PREVIEW_CIPHER_LEN)
+        }
+        Message::AdmissionRequest(cipher) | Message::AdmissionResponse(cipher) => {
+            non_empty("admission", cipher)?;
+            cap("admission", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::MembershipAnnounce(cipher) => {
+            non_empty("membership_announce", cipher)?;
+            cap("membership_announce", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::PeerListDigest(cipher) => {
+            non_empty("peer_list_digest", cipher)?;
+            cap("peer_list_digest", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::PeerListRequest(cipher) | Message::PeerList(cipher) => {
+            non_empty("peer_list", cipher)?;
+            cap("peer_list", cipher.len(), MAX_PEER_LIST_CIPHER_LEN)
+        }
+        Message::OpenUrl(cipher) => {
+            non_empty("open_url", cipher)?;
+            cap("open_url", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::TypeText(cipher) => {
+            non_empty("type_text", cipher)?;
+            cap("type_text", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::Probe(cipher) | Message::ProbeReply(cipher) => {
+            non_empty("probe", cipher)?;
+            cap("probe", cipher.len(), MAX_PROBE_CIPHER_LEN)
+        }
+        Message::DryRun(cipher) => {
+            non_empty("dry_run", cipher)?;
+            cap("dry_run", cipher.len(), MAX_CLIPBOARD_CIPHER_LEN)
+        }
+        Message::DryRunReceipt(cipher) => {
+            non_empty("dry_run_receipt", cipher)?;
+            cap("dry_run_receipt", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+        Message::FileRequest(cipher) | Message::HaveAlready(cipher) | Message::FileRequestError(cipher) => {
+            non_empty("file_request", cipher)?;
+            cap("file_request", cipher.len(), MAX_SMALL_CIPHER_LEN)
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Native Windows clipboard formats.
+//!
+//! The clipboard plugin deals in text and file lists only. Between Windows devices (both
+//! advertise `Capabilities::windows_formats`) a copied bitmap goes out too: it's read here as
+//! PNG (the registered "PNG" format browsers and Office put up, else CF_DIB) and travels as a
+//! PNG file flagged `FileMetadata::clipboard_image`. The receiver puts it back as CF_DIB and
+//! PNG, plus a CF_HDROP of the file so it also pastes into Explorer. File lists are written as
+//! a wide-char CF_HDROP with "Preferred DropEffect" set to copy, like Explorer's own.
+//!
+//! On other platforms no bitmap is ever reported, so none of this runs.
+
+#[cfg(target_os = "windows")]
+use std::io::Cursor;
+
+#[cfg(target_os = "windows")]
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[cfg(target_os = "windows")]
+mod raw {
+    use windows::core::w;
+    use windows::Win32::Foundation::{GlobalFree, HANDLE, HGLOBAL, HWND};
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, EmptyClipboard, GetClipboardData, GetClipboardSequenceNumber, IsClipboardFormatAvailable,
+        OpenClipboard, RegisterClipboardFormatW, SetClipboardData,
+    };
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE};
+
+    // Standard formats (winuser.h)
+    pub const CF_DIB: u32 = 8;
+    pub const CF_HDROP: u32 = 15;
+
+    pub fn png_format() -> u32 {
+        unsafe { RegisterClipboardFormatW(w!("PNG")) }
+    }
+
+    pub fn drop_effect_format() -> u32 {
+        unsafe { RegisterClipboardFormatW(w!("Preferred DropEffect")) }
+    }
+
+    pub fn available(format: u32) -> bool {
+        unsafe { IsClipboardFormatAvailable(format) }.is_ok()
+    }
+
+    pub fn sequence() -> u32 {
+        unsafe { GetClipboardSequenceNumber() }
+    }
+
+    /// The clipboard, held open until dropped.
+    pub struct Open;
+
+    impl Open {
+        pub fn new() -> Result<Self, String> {
+            unsafe { OpenClipboard(HWND::default()) }.map_err(|e| format!("OpenClipboard: {}", e))?;
+            Ok(Open)
+        }
+
+        pub fn empty(&self) -> Result<(), String> {
+            unsafe { EmptyClipboard() }.map_err(|e| format!("EmptyClipboard: {}", e))
+        }
+
+        /// A copy of the data in `format`, if there is any.
+        pub fn get(&self, format: u32) -> Option<Vec<u8>> {
+            unsafe {
+                let mem = HGLOBAL(GetClipboardData(format).ok()?.0);
+                let ptr = GlobalLock(mem) as *const u8;
+                if ptr.is_null() {
+                    return None;
+                }
+                let data = std::slice::from_raw_parts(ptr, GlobalSize(mem)).to_vec();
+                let _ = GlobalUnlock(mem);
+                Some(data)
+            }
+        }
+
+        /// Add `data` in `format`. The clipboard owns the memory once this succeeds.
+        pub fn set(&self, format: u32, data: &[u8]) -> Result<(), String> {
+            unsafe {
+                let mem = GlobalAlloc(GMEM_MOVEABLE, data.len()).map_err(|e| format!("GlobalAlloc: {}", e))?;
+                let ptr = GlobalLock(mem) as *mut u8;
+                if ptr.is_null() {
+                    let _ = GlobalFree(mem);
+                    return Err("GlobalLock failed".to_string());
+                }
+                std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+                let _ = GlobalUnlock(mem);
+                if let Err(e) = SetClipboardData(format, HANDLE(mem.0)) {
+                    let _ = GlobalFree(mem);
+                    return Err(format!("SetClipboardData: {}", e));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    impl Drop for Open {
+        fn drop(&mut self) {
+            let _ = unsafe { CloseClipboard() };
+        }
+    }
+}
+
+/// While a bitmap is on the clipboard, its sequence number (changes with every copy).
+#[cfg(target_os = "windows")]
+pub fn image_sequence() -> Option<u32> {
+    (raw::available(raw::png_format()) || raw::available(raw::CF_DIB)).then(raw::sequence)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn image_sequence() -> Option<u32> {
+    None
+}
+
+/// The bitmap on the clipboard, as PNG.
+#[cfg(target_os = "windows")]
+pub fn read_image_png() -> Option<Vec<u8>> {
+    let clipboard = raw::Open::new().ok()?;
+    if let Some(png) = clipboard.get(raw::png_format()).filter(|d| d.starts_with(PNG_MAGIC)) {
+        return Some(png);
+    }
+    let dib = clipboard.get(raw::CF_DIB)?;
+    drop(clipboard);
+    dib_to_png(&dib)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn read_image_png() -> Option<Vec<u8>> {
+    None
+}
+
+// CF_DIB is a BMP file without its 14-byte file header; rebuild that and re-encode
+#[cfg(target_os = "windows")]
+fn dib_to_png(dib: &[u8]) -> Option<Vec<u8>> {
+    let u32_at = |i: usize| dib.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let header_size = u32_at(0)?;
+    let bit_count = dib.get(14..16).map(|b| u16::from_le_bytes([b[0], b[1]]))?;
+    let compression = u32_at(16)?;
+    let colors_used = u32_at(32)?;
+    // BI_BITFIELDS masks follow a plain BITMAPINFOHEADER (later headers include them); then the palette
+    let masks = if header_size == 40 && compression == 3 { 12 } else { 0 };
+    let colors = match colors_used {
+        0 if bit_count <= 8 => 1u32 << bit_count,
+        n => n,
+    };
+    // All of these come from the clipboard, so any of them may be garbage
+    let pixels_at = colors.checked_mul(4).and_then(|p| p.checked_add(header_size)).and_then(|p| p.checked_add(14 + masks))?;
+    let file_size = u32::try_from(dib.len()).ok()?.checked_add(14)?;
+    if pixels_at >= file_size {
+        tracing::warn!("[WinClip] Bitmap on the clipboard has no pixel data after its headers");
+        return None;
+    }
+
+    let mut bmp = Vec::with_capacity(14 + dib.len());
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&file_size.to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&pixels_at.to_le_bytes());
+    bmp.extend_from_slice(dib);
+
+    let image = image::load_from_memory_with_format(&bmp, image::ImageFormat::Bmp)
+        .map_err(|e| tracing::warn!("[WinClip] Unreadable bitmap on the clipboard: {}", e))
+        .ok()?;
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).ok()?;
+    Some(png)
+}
+
+/// Put the image file at `path` on the clipboard as a bitmap (CF_DIB and PNG) and as a file.
+#[cfg(target_os = "windows")]
+pub fn write_image(path: &str) -> Result<(), String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+    // 24-bit, so the header is a plain BITMAPINFOHEADER every reader understands
+    let mut bmp = Vec::new();
+    image.to_rgb8().write_to(&mut Cursor::new(&mut bmp), image::ImageFormat::Bmp).map_err(|e| e.to_string())?;
+    let dib = bmp.get(14..).ok_or("Encoded bitmap is empty")?;
+    let png = if bytes.starts_with(PNG_MAGIC) {
+        bytes
+    } else {
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).map_err(|e| e.to_string())?;
+        png
+    };
+
+    let clipboard = raw::Open::new()?;
+    clipboard.empty()?;
+    clipboard.set(raw::CF_DIB, dib)?;
+    clipboard.set(raw::png_format(), &png)?;
+    set_file_list(&clipboard, &[path.to_string()])
+}
+
+/// Put `paths` on the clipboard as a file list.
+#[cfg(target_os = "windows")]
+pub fn write_files(paths: &[String]) -> Result<(), String> {
+    let clipboard = raw::Open::new()?;
+    clipboard.empty()?;
+    set_file_list(&clipboard, paths)
+}
+
+#[cfg(target_os = "windows")]
+fn set_file_list(clipboard: &raw::Open, paths: &[String]) -> Result<(), String> {
+    const DROPEFFECT_COPY: u32 = 1;
+    clipboard.set(raw::CF_HDROP, &drop_files(paths))?;
+    clipboard.set(raw::drop_effect_format(), &DROPEFFECT_COPY.to_le_bytes())
+}
+
+// DROPFILES (pFiles, pt.x, pt.y, fNC, fWide) followed by NUL-terminated UTF-16 paths and a final NUL
+#[cfg(target_os = "windows")]
+fn drop_files(paths: &[String]) -> Vec<u8> {
+    const HEADER_LEN: u32 = 20;
+    let mut data = Vec::new();
+    for field in [HEADER_LEN, 0, 0, 0, 1] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    for unit in paths.iter().flat_map(|p| p.encode_utf16().chain([0])).chain([0]) {
+        data.extend_from_slice(&unit.to_le_bytes());
+    }
+    data
+}
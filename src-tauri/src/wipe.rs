@@ -0,0 +1,118 @@
+//! Secure wipe: shred everything sensitive kept on this device, then factory reset.
+//!
+//! Covers the history (in memory and its annotations file), keys and peer lists in the config
+//! directory (with their backups and checksum sidecars), downloaded and outgoing temp files,
+//! crash reports and the log directory. Files are overwritten with random bytes and synced
+//! before being deleted. This is best-effort: on SSDs and copy-on-write or journaling
+//! filesystems old blocks may survive an overwrite. Today's log is still held open by the
+//! logger, so log files are overwritten and truncated rather than deleted.
+
+use crate::state::AppState;
+use rand::RngCore;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Seek, Write};
+use std::path::Path;
+use tauri::AppHandle;
+
+const CHUNK: usize = 64 * 1024;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct WipeReport {
+    pub shredded: usize,
+    // Paths that couldn't be overwritten or removed
+    pub failed: Vec<String>,
+}
+
+/// Overwrite the file's contents with random bytes, flush them to disk and truncate it.
+fn overwrite(path: &Path) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(io::SeekFrom::Start(0))?;
+    let mut buf = vec![0u8; CHUNK];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(CHUNK as u64) as usize;
+        rand::thread_rng().fill_bytes(&mut buf[..n]);
+        file.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    file.set_len(0)?;
+    file.sync_all()
+}
+
+fn shred_file(path: &Path, remove: bool, report: &mut WipeReport) {
+    let result = overwrite(path).and_then(|_| if remove { fs::remove_file(path) } else { Ok(()) });
+    match result {
+        Ok(()) => report.shredded += 1,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            tracing::warn!("[Wipe] Could not shred {:?}: {}", path, e);
+            report.failed.push(path.to_string_lossy().into_owned());
+        }
+    }
+}
+
+fn shred_dir(dir: &Path, remove: bool, report: &mut WipeReport) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            shred_dir(&path, remove, report);
+        } else {
+            shred_file(&path, remove, report);
+        }
+    }
+    if remove {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Shred local data. The caller performs the factory reset afterwards.
+pub fn secure_wipe(app: &AppHandle, state: &AppState) -> WipeReport {
+    tracing::info!("[Wipe] Secure wipe requested");
+    state.recent_items.lock().unwrap().clear();
+    state.history_annotations.lock().unwrap().clear();
+    state.pending_clipboard.lock().unwrap().clear();
+    *state.pending_outgoing.lock().unwrap() = None;
+    *state.pending_type_text.lock().unwrap() = None;
+    state.local_files.lock().unwrap().clear();
+    state.staged_files.lock().unwrap().clear();
+    state.remote_files.lock().unwrap().clear();
+    state.received_hashes.lock().unwrap().clear();
+    state.file_hashes.lock().unwrap().clear();
+    state.partial_transfers.lock().unwrap().clear();
+    state.verify_failures.lock().unwrap().clear();
+    state.compositions.lock().unwrap().clear();
+    state.peer_permissions.lock().unwrap().clear();
+    *state.peer_stats.lock().unwrap() = crate::peerstats::StatsStore::default();
+    state.latency_samples.lock().unwrap().clear();
+    *state.last_clipboard_content.lock().unwrap() = Default::default();
+    crate::retry::clear(state);
+
+    let mut report = WipeReport::default();
+    for path in crate::storage::sensitive_config_paths(app) {
+        shred_file(&path, true, &mut report);
+    }
+    for dir in [
+        crate::storage::temp_downloads_dir(app),
+        crate::storage::outgoing_text_dir(app),
+        crate::storage::outgoing_images_dir(app),
+        crate::crash::reports_dir(app),
+    ]
+        .into_iter()
+        .flatten()
+    {
+        shred_dir(&dir, true, &mut report);
+    }
+    shred_dir(&crate::logs::dir(), false, &mut report);
+
+    // The old signing key is gone with the config files; start over with a fresh one
+    crate::identity::load(app, state);
+
+    tracing::info!("[Wipe] Shredded {} files ({} failed)", report.shredded, report.failed.len());
+    report
+}
@@ -3,11 +3,14 @@ import { version } from "../package.json";
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { enable, disable, isEnabled } from "@tauri-apps/plugin-autostart";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { openUrl } from "@tauri-apps/plugin-opener";
 import {
   Monitor, Copy, History, ShieldCheck, PlusCircle, Trash2, LogOut,
   Settings, Wifi, Lock, Unlock, AlertTriangle, Info, CheckCircle2,
-  ChevronDown, ChevronRight, ArrowUp, ArrowDown, Send, Download, Puzzle, Loader2, Unplug
+  ChevronDown, ChevronRight, ArrowUp, ArrowDown, Send, Download, Upload, Puzzle, Loader2, Unplug, StickyNote, Clock, KeyRound, Activity, Link2
 } from "lucide-react";
 import clsx from "clsx";
 import { ShortcutRecorder } from "./components/ShortcutRecorder";
@@ -40,9 +43,49 @@ interface Peer {
   is_trusted: boolean;
   is_manual?: boolean;
   network_name?: string;
-  platform?: string; // Backend doesn't send this yet, will mock or infer
+  guest_expires_at?: number | null;
+  platform?: PlatformInfo | null;
 }
 
+type PlatformInfo = {
+  os: string;
+  os_version: string;
+  arch: string;
+  app_version: string;
+  protocol: number;
+};
+
+const OS_NAMES: Record<string, string> = { macos: "macOS", windows: "Windows", linux: "Linux", freebsd: "FreeBSD", ios: "iOS", android: "Android" };
+
+// "macOS 15.1 · v0.4.2"
+function platformLabel(p: PlatformInfo) {
+  const os = [OS_NAMES[p.os] ?? p.os, p.os_version].filter(Boolean).join(" ");
+  return `${os} · v${p.app_version}`;
+}
+
+type Presence = "online" | "away" | "offline";
+
+type PeerPresence = {
+  peer: Peer;
+  presence: Presence;
+  since: number;
+};
+
+type PendingReceive = {
+  id: string;
+  text: string;
+  sender: string;
+  timestamp: number;
+};
+
+type PendingTypeText = {
+  id: string;
+  sender: string;
+  sender_id: string;
+  text: string;
+  received_at: number;
+};
+
 type View = "devices" | "history" | "settings";
 
 type NearbyNetwork = {
@@ -50,16 +93,138 @@ type NearbyNetwork = {
   devices: { id: string; hostname?: string; status: "online" | "offline" }[];
 };
 
+type HistoryAnnotation = {
+  id: string;
+  note: string | null;
+  updated_at: number;
+};
+
+// Background failure reported by the backend ("app-error" event)
+type AppErrorEvent = {
+  category: "network" | "crypto" | "storage" | "clipboard" | "pairing" | "invalid";
+  message: string;
+  retryable: boolean;
+  context: string;
+  timestamp: number;
+  // For "send-failed": what to resend, and to whom
+  resend?: { id: string, peerId: string };
+  // For "file-error": which file to request again, and from whom
+  download?: { id: string, fileIndex: number, peerId: string };
+  // For "download-deferred": the held-back item, fetched in full on "Download anyway"
+  deferred?: { id: string, fileCount: number, peerId: string };
+};
+
+type DownloadDeferred = {
+  id: string;
+  peer_id: string;
+  file_count: number;
+  total_size: number;
+  reason: "metered" | "battery_saver";
+};
+
+type FileError = {
+  id: string;
+  file_index: number;
+  peer_id: string | null;
+  reason: "expired" | "not_found" | "unreadable";
+  message: string;
+  retryable: boolean;
+};
+
+type PairingFailed = {
+  error: "wrong_pin" | "timeout" | "unreachable" | "rejected" | "version_mismatch" | "key_mismatch" | "owner_offline";
+  reason: string; // Localized message
+};
+
+type SendFailure = {
+  msg_id: string;
+  peer_id: string;
+  peer: string;
+  reason: string;
+  attempts: number;
+  exhausted: boolean;
+  timestamp: number;
+};
+
+// Preparation of files dropped onto the window (see staging.rs)
+type StagingProgress = {
+  batch_id: string;
+  file_name: string | null;
+  files_done: number;
+  files_total: number;
+  bytes_done: number;
+  bytes_total: number;
+  ready: boolean;
+};
+
 type HistoryItem = {
   id: string;
   origin: "local" | "remote";
   device: string; // The sender's hostname
   ts: number; // Unix timestamp in seconds
   text: string;
-  files?: { name: string; size: number; }[];
+  files?: { name: string; size: number; mime?: string | null; thumbnail?: string | null; hash?: string | null; text_preview?: string | null; clipboard_image?: boolean; }[];
   sender_id?: string;
+  note?: string | null;
+  source?: { app?: string | null; window_title?: string | null } | null;
+  channel?: string;
+  link?: { url: string; title?: string | null; favicon?: string | null } | null;
+  content?: ContentTags | null;
+};
+
+type PeerStats = {
+  items_sent: number;
+  items_received: number;
+  files_sent: number;
+  files_received: number;
+  bytes_sent: number;
+  bytes_received: number;
+  last_sent_at: number | null;
+  last_received_at: number | null;
+};
+
+// get_peer_details
+type PeerDetails = {
+  online: boolean;
+  stats: PeerStats;
+  last_item: { id: string; timestamp: number; preview: string; file_count: number } | null;
+  trust: {
+    is_trusted: boolean;
+    is_manual: boolean;
+    is_owner: boolean;
+    guest_expires_at: number | null;
+    blocked: boolean;
+    signature_valid: boolean;
+    fingerprint: string | null;
+    local_fingerprint: string | null;
+  };
+};
+
+type ContentKind = "url" | "email" | "color" | "json" | "code" | "sensitive";
+
+// Detected by the backend when the item entered history
+type ContentTags = {
+  kinds: ContentKind[];
+  language?: string | null;
+};
+
+const CONTENT_KIND_LABELS: Record<ContentKind, string> = {
+  url: "Links",
+  email: "Emails",
+  color: "Colors",
+  json: "JSON",
+  code: "Code",
+  sensitive: "Sensitive"
 };
 
+// Export window for export_history, in seconds (null = everything)
+const EXPORT_RANGES: { label: string; secs: number | null }[] = [
+  { label: "All history", secs: null },
+  { label: "Last 24 hours", secs: 86400 },
+  { label: "Last 7 days", secs: 7 * 86400 },
+  { label: "Last 30 days", secs: 30 * 86400 }
+];
+
 // Simple Time Ago Helper
 function timeAgo(ts: number): string {
   const now = Math.floor(Date.now() / 1000);
@@ -100,8 +265,91 @@ interface AppSettings {
   max_auto_download_size: number;
   notify_large_files: boolean;
   ignore_extension_missing: boolean;
+  compress_file_transfers: boolean;
+  generate_file_previews: boolean;
+  inline_file_threshold_kb: number;
+  file_offer_ttl_hours: number;
+  max_file_offers: number;
+  scan_command: string | null;
+  quarantine_received_files: boolean;
+  share_source_app: boolean;
+  app_allowlist_mode: boolean;
+  allowed_apps: string[];
+  require_owner_approval: boolean;
+  accept_images: boolean;
+  max_incoming_payload_kb: number;
+  latency_tracing: boolean;
+  power_saving: boolean;
+  idle_suspend_secs: number;
+  battery_idle_suspend_secs: number;
+  low_power_heartbeat_secs: number;
+  low_power_monitor_interval_ms: number;
+  monitor_interval_ms: number;
+  adaptive_polling: boolean;
+  defer_downloads_when_constrained: boolean;
+  constrained_download_limit: number;
+  subscribed_channels: string[];
+  send_channel: string;
+  channel_shortcuts: Record<string, string>;
+  unfurl_links: boolean;
+  unfurl_timeout_ms: number;
+  pause_sync_when_locked: boolean;
+  allowed_interfaces: string[];
+  allowed_subnets: string[];
+  allow_network_pin_pairing: boolean;
+  pin_rotation_hours: number;
+  heartbeat_interval_secs: number;
+  stale_peer_timeout_secs: number;
+  mdns_removal_debounce_secs: number;
+  review_before_send: boolean;
+  text_to_file_threshold_kb: number;
+  open_links_on: string[];
+  crash_reports: boolean;
+  macos_service: boolean;
+  paused_toggles?: [boolean, boolean] | null;
+  start_minimized: boolean;
+  locale: string | null;
+  tray_badge: TrayBadgeSettings;
+  send_retry_attempts: number;
+  log_redaction: "full" | "hashed" | "off";
+  log_retention_days: number;
+  cipher_suite: "auto" | "chacha20_poly1305" | "aes_256_gcm";
+  received_files_retention: "session" | "days" | "size" | "never";
+  received_files_retention_days: number;
+  received_files_retention_gb: number;
+  save_prompt_size: number;
+  proxy: ProxySettings;
+  update_check: boolean;
+  update_channel: "stable" | "beta";
+  update_endpoint: string;
+  discovery_namespace: string;
+  sender_authentication: "off" | "trusted" | "strict";
 }
 
+// "update-available" event / check_for_updates result
+type UpdateInfo = {
+  version: string;
+  current_version: string;
+  name: string;
+  notes: string;
+  url: string;
+  prerelease: boolean;
+  published_at: string | null;
+};
+
+type ProxySettings = {
+  enabled: boolean;
+  url: string;
+  bypass: string[];
+};
+
+type TrayBadgeSettings = {
+  style: "square" | "dot" | "ring" | "count";
+  color: string;
+  position: "top_right" | "top_left" | "bottom_right" | "bottom_left";
+  high_contrast: boolean;
+};
+
 /* --- Helper Components (from Design) --- */
 // ... (Badge, SectionHeader, Card omitted as they are fine, just fixing Button props below)
 
@@ -309,6 +557,10 @@ export default function App() {
   /* Logic & State from Old App */
   const [peers, setPeers] = useState<Peer[]>([]);
   const peersRef = useRef<Peer[]>([]);
+  // Presence per peer id, and known members that dropped off (shown greyed out)
+  const [presence, setPresence] = useState<Record<string, Presence>>({});
+  const [offlinePeers, setOfflinePeers] = useState<Record<string, Peer>>({});
+  const annotationsRef = useRef<Record<string, HistoryAnnotation>>({});
 
   const [clipboardHistory, setClipboardHistory] = useState<HistoryItem[]>([]);
   const [activeView, setActiveView] = useState<View>("devices");
@@ -339,22 +591,37 @@ export default function App() {
   const [pairingPeerId, setPairingPeerId] = useState<string | null>(null);
 
   const [leaveOpen, setLeaveOpen] = useState(false);
+  // Leave with secure_wipe: also shred history, cached files and logs
+  const [leaveWipe, setLeaveWipe] = useState(false);
 
   const [addManualOpen, setAddManualOpen] = useState(false);
   const [manualIp, setManualIp] = useState("");
   const [manualBusy, setManualBusy] = useState(false);
 
   const [joinError, setJoinError] = useState("");
+  // Only a wrong PIN marks the PIN field itself as the problem
+  const [joinPinWrong, setJoinPinWrong] = useState(false);
   const [expandedNetworks, setExpandedNetworks] = useState<Set<string>>(new Set());
 
+  /* Background Errors (toasts) */
+  const [appErrors, setAppErrors] = useState<(AppErrorEvent & { key: number })[]>([]);
+
   /* Port Warning State */
   const [showPortWarning, setShowPortWarning] = useState(false);
+  const [availableUpdate, setAvailableUpdate] = useState<UpdateInfo | null>(null);
   const [currentPort, setCurrentPort] = useState(4654);
 
   // Manual Sync State
   // Manual Sync State
   const [manualSyncOpen, setManualSyncOpen] = useState(false);
-  const [pendingReceive, setPendingReceive] = useState<{ text: string, sender: string, timestamp: number } | null>(null);
+  // Incoming items waiting to be applied, oldest first
+  const [pendingReceives, setPendingReceives] = useState<PendingReceive[]>([]);
+  // Local copy held for review before sending (review_before_send)
+  const [pendingOutgoing, setPendingOutgoing] = useState<{ id: string, text: string, files?: { name: string }[] | null } | null>(null);
+  const [pendingTypeText, setPendingTypeText] = useState<PendingTypeText | null>(null);
+  // Dropped files being prepared or waiting to be sent
+  const [staged, setStaged] = useState<StagingProgress | null>(null);
+  const stagedRef = useRef<string | null>(null);
   const [localClipboard, setLocalClipboard] = useState(""); // Current local
   const [lastSentClipboard, setLastSentClipboard] = useState(""); // Last successfully sent
   const [lastReceivedClipboard, setLastReceivedClipboard] = useState(""); // Last received from cluster
@@ -371,8 +638,7 @@ export default function App() {
     && localClipboard.length > 0;
 
   // Rule 3: If pending receive matches local (already have it or I sent it), not a candidate.
-  const hasPendingReceive = !!pendingReceive
-    && pendingReceive.text !== localClipboard;
+  const hasPendingReceive = pendingReceives.some((p) => p.text !== localClipboard);
 
   const toggleNetwork = (name: string) => {
     setExpandedNetworks(prev => {
@@ -427,24 +693,9 @@ export default function App() {
           try {
             const parsed = new URL(urlStr);
 
-            // 1. Download Action
+            // 1. Download Action (files are requested by the backend)
             if (urlStr.includes("action/download")) {
-              const msgId = parsed.searchParams.get("msg_id");
-              const peerId = parsed.searchParams.get("peer_id");
-              const countStr = parsed.searchParams.get("file_count");
-
-              if (msgId && peerId && countStr) {
-                const count = parseInt(countStr);
-                logToBackend(`Auto-download triggered via Notification: ${count} files.`);
-
-                // Trigger downloads
-                for (let i = 0; i < count; i++) {
-                  invoke("request_file", { fileId: msgId, fileIndex: i, peerId: peerId }).catch(e => {
-                    console.error("Failed to auto-download:", e);
-                    logToBackend("Failed to auto-download:", e);
-                  });
-                }
-              }
+              logToBackend("Download triggered via Notification.");
               targetView = "history";
             }
             // 2. Show Action
@@ -475,9 +726,9 @@ export default function App() {
       }
 
       // 2. Listen for Runtime Deep Links (Single Instance)
-      unlistenDeepLink = await listen<string[]>("deep-link", (event) => {
+      unlistenDeepLink = await listen<{ args: string[] }>("deep-link", (event) => {
         console.log("Deep Link Event Received:", event);
-        handleArgs(event.payload);
+        handleArgs(event.payload.args);
       });
     };
 
@@ -613,6 +864,10 @@ export default function App() {
     invoke<Record<string, Peer>>("get_peers").then((peerMap) => {
       setPeers(Object.values(peerMap));
     });
+    invoke<PeerPresence[]>("get_peer_presence").then((entries) => {
+      setPresence(Object.fromEntries(entries.map(e => [e.peer.id, e.presence])));
+      setOfflinePeers(Object.fromEntries(entries.filter(e => e.presence === "offline").map(e => [e.peer.id, e.peer])));
+    });
 
     // 2. Metadata
     invoke<string>("get_network_name").then(name => setMyNetworkName(name));
@@ -622,6 +877,11 @@ export default function App() {
     // 3. Settings
     fetchSettings();
 
+    // 3b. History Annotations
+    invoke<Record<string, HistoryAnnotation>>("get_history_annotations").then(map => {
+      annotationsRef.current = map;
+    });
+
     // 4. Port Check
     invoke<number>("get_listening_port").then(port => {
       if (port !== 4654) {
@@ -636,6 +896,141 @@ export default function App() {
     invoke<string>("get_network_pin").then(pin => setNetworkPin(pin));
   }, [myNetworkName]);
 
+  // Background update check found a newer release
+  useEffect(() => {
+    const unlisten = listen<UpdateInfo>("update-available", (event) => setAvailableUpdate(event.payload));
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  // Background errors: show as toasts, newest last, auto-dismissed
+  useEffect(() => {
+    const unlisten = listen<AppErrorEvent>("app-error", (event) => {
+      const key = Date.now() + Math.random();
+      setAppErrors((prev) => [...prev.slice(-2), { ...event.payload, key }]);
+      setTimeout(() => setAppErrors((prev) => prev.filter((e) => e.key !== key)), 8000);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  // A peer never got an item, even after retries
+  useEffect(() => {
+    const unlisten = listen<SendFailure>("send-failed", (event) => {
+      const f = event.payload;
+      const key = Date.now() + Math.random();
+      const error: AppErrorEvent = {
+        category: "network",
+        message: `${f.peer} didn't receive an item (${f.reason})`,
+        retryable: true,
+        context: "send-failed",
+        timestamp: f.timestamp,
+        resend: { id: f.msg_id, peerId: f.peer_id }
+      };
+      setAppErrors((prev) => [...prev.slice(-2), { ...error, key }]);
+      setTimeout(() => setAppErrors((prev) => prev.filter((e) => e.key !== key)), 15000);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  // Files dropped onto the window are staged in the backend, then sent from the prompt
+  useEffect(() => {
+    const unlistenProgress = listen<StagingProgress>("staging-progress", (event) => {
+      setStaged((prev) => (prev && prev.batch_id !== event.payload.batch_id ? prev : event.payload));
+    });
+    const unlistenDrop = getCurrentWebview().onDragDropEvent(async (event) => {
+      if (event.payload.type !== "drop" || event.payload.paths.length === 0) return;
+      const paths = event.payload.paths;
+      try {
+        const batchId = await invoke<string>("stage_dropped_files", { paths });
+        // A new drop replaces the batch that was waiting
+        if (stagedRef.current) invoke("discard_staged", { batchId: stagedRef.current }).catch(() => {});
+        stagedRef.current = batchId;
+        setStaged((prev) => (prev?.batch_id === batchId ? prev : { batch_id: batchId, file_name: null, files_done: 0, files_total: paths.length, bytes_done: 0, bytes_total: 0, ready: false }));
+      } catch (e) {
+        alert("Can't send dropped files: " + String(e));
+      }
+    });
+    return () => {
+      unlistenProgress.then((f) => f());
+      unlistenDrop.then((f) => f());
+    };
+  }, []);
+
+  // A device couldn't serve a file we asked it for, or sent one that failed verification
+  useEffect(() => {
+    const unlisten = listen<FileError>("file-error", (event) => {
+      const f = event.payload;
+      const key = Date.now() + Math.random();
+      const error: AppErrorEvent = {
+        category: "storage",
+        message: f.message,
+        retryable: f.retryable && f.peer_id !== null,
+        context: "file-error",
+        timestamp: Math.floor(Date.now() / 1000),
+        download: f.peer_id ? { id: f.id, fileIndex: f.file_index, peerId: f.peer_id } : undefined
+      };
+      setAppErrors((prev) => [...prev.slice(-2), { ...error, key }]);
+      setTimeout(() => setAppErrors((prev) => prev.filter((e) => e.key !== key)), 15000);
+    });
+    // A download didn't match the sender's checksum and was thrown away
+    const unlistenVerify = listen<{ id: string, file_index: number, file_name: string, attempt: number, will_retry: boolean }>("file-verify-failed", (event) => {
+      const f = event.payload;
+      const key = Date.now() + Math.random();
+      const error: AppErrorEvent = {
+        category: "storage",
+        message: f.will_retry
+          ? `${f.file_name} arrived corrupted, downloading it again (attempt ${f.attempt + 1})`
+          : `${f.file_name} arrived corrupted ${f.attempt} time(s) and was discarded`,
+        retryable: false,
+        context: "file-verify-failed",
+        timestamp: Math.floor(Date.now() / 1000)
+      };
+      setAppErrors((prev) => [...prev.slice(-2), { ...error, key }]);
+      setTimeout(() => setAppErrors((prev) => prev.filter((e) => e.key !== key)), 8000);
+    });
+    return () => {
+      unlisten.then((f) => f());
+      unlistenVerify.then((f) => f());
+    };
+  }, []);
+
+  // Large auto-downloads held back on a metered connection or in battery saver
+  useEffect(() => {
+    const unlisten = listen<DownloadDeferred>("download-deferred", (event) => {
+      const d = event.payload;
+      const key = Date.now() + Math.random();
+      const why = d.reason === "metered" ? "you're on a metered connection" : "battery saver is on";
+      const error: AppErrorEvent = {
+        category: "network",
+        message: `${d.file_count} file(s) (${formatBytes(d.total_size)}) weren't downloaded because ${why}`,
+        retryable: true,
+        context: "download-deferred",
+        timestamp: Math.floor(Date.now() / 1000),
+        deferred: { id: d.id, fileCount: d.file_count, peerId: d.peer_id }
+      };
+      setAppErrors((prev) => [...prev.slice(-2), { ...error, key }]);
+      setTimeout(() => setAppErrors((prev) => prev.filter((e) => e.key !== key)), 30000);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  // Scheduled PIN rotation
+  useEffect(() => {
+    const unlisten = listen("network-pin-changed", () => {
+      invoke<string>("get_network_pin").then(pin => setNetworkPin(pin));
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
   // Listeners
   useEffect(() => {
     if (!myHostname) return; // Wait for identity to prevent false "remote" detection
@@ -694,7 +1089,12 @@ export default function App() {
         sender_id: p.sender_id,
         ts: p.timestamp,
         text: p.text || "",
-        files: p.files
+        files: p.files,
+        note: annotationsRef.current[p.id]?.note,
+        source: p.source,
+        channel: p.channel,
+        link: p.link,
+        content: p.content
       };
 
       // Update Local Clipboard State
@@ -719,19 +1119,93 @@ export default function App() {
       });
     });
 
-    const unlistenPending = listen<{ id: string, text: string, timestamp: number, sender: string }>("clipboard-pending", (event) => {
-      setPendingReceive(event.payload);
+    invoke<{ id: string, text: string, files?: { name: string }[] | null } | null>("get_pending_outgoing").then(setPendingOutgoing);
+
+    const unlistenOutgoing = listen<{ id: string, text: string, files?: { name: string }[] | null }>("clipboard-outgoing-pending", (event) => {
+      setPendingOutgoing(event.payload);
+    });
+
+    const unlistenOutgoingCleared = listen<{ id: string }>("clipboard-outgoing-cleared", (event) => {
+      setPendingOutgoing((prev) => (prev && prev.id === event.payload.id ? null : prev));
+    });
+
+    invoke<PendingTypeText | null>("get_pending_type_text").then(setPendingTypeText);
+
+    const unlistenTypeText = listen<PendingTypeText>("type-text-request", (event) => {
+      setPendingTypeText(event.payload);
+    });
+
+    const unlistenTypeTextCleared = listen<{ id: string }>("type-text-cleared", (event) => {
+      setPendingTypeText((prev) => (prev && prev.id === event.payload.id ? null : prev));
+    });
+
+    invoke<PendingReceive[]>("get_pending_clipboards").then(setPendingReceives);
+
+    const unlistenPending = listen<PendingReceive>("clipboard-pending", (event) => {
+      setPendingReceives((prev) => [...prev.filter((p) => p.id !== event.payload.id), event.payload]);
       // Maybe open modal automatically? Or just show FAB?
       // User requested FAB.
     });
 
-    const unlistenDelete = listen<string>("history-delete", (event) => {
-      const idToDelete = event.payload;
+    const unlistenPendingRemoved = listen<{ id: string }>("clipboard-pending-removed", (event) => {
+      setPendingReceives((prev) => prev.filter((p) => p.id !== event.payload.id));
+    });
+
+    const unlistenDelete = listen<{ id: string }>("history-delete", (event) => {
+      const idToDelete = event.payload.id;
       setClipboardHistory((prev) => prev.filter(i => i.id !== idToDelete));
     });
 
-    const unlistenRemove = listen<string>("peer-remove", (event) => {
-      setPeers((prev) => prev.filter(p => p.id !== event.payload));
+    const unlistenAnnotate = listen<HistoryAnnotation>("history-annotate", (event) => {
+      const a = event.payload;
+      if (a.note) annotationsRef.current[a.id] = a;
+      else delete annotationsRef.current[a.id];
+      setClipboardHistory((prev) => prev.map(i => (i.id === a.id ? { ...i, note: a.note } : i)));
+    });
+
+    // A link preview arrived after the item was sent (see unfurl.rs)
+    const unlistenLinkPreview = listen<{ id: string; link: HistoryItem["link"] }>("history-link-preview", (event) => {
+      const { id, link } = event.payload;
+      setClipboardHistory((prev) => prev.map(i => (i.id === id ? { ...i, link } : i)));
+    });
+
+    // An archive was merged in by import_history: reload the list and notes from the backend
+    const unlistenImported = listen<{ imported: number }>("history-imported", async () => {
+      annotationsRef.current = await invoke<Record<string, HistoryAnnotation>>("get_history_annotations");
+      const items = await invoke<any[]>("search_history", {});
+      setClipboardHistory(items.map((p): HistoryItem => ({
+        id: p.id,
+        origin: p.sender === "self" || p.sender === myHostname ? "local" : "remote",
+        device: p.sender,
+        sender_id: p.sender_id,
+        ts: p.timestamp,
+        text: p.text || "",
+        files: p.files,
+        note: annotationsRef.current[p.id]?.note,
+        source: p.source,
+        channel: p.channel,
+        link: p.link,
+        content: p.content
+      })));
+    });
+
+    const unlistenRemove = listen<{ id: string }>("peer-remove", (event) => {
+      const { id } = event.payload;
+      setPeers((prev) => prev.filter(p => p.id !== id));
+      setOfflinePeers((prev) => {
+        const { [id]: _removed, ...rest } = prev;
+        return rest;
+      });
+    });
+
+    // Sent after peer-remove when a known member goes offline
+    const unlistenPresence = listen<PeerPresence>("peer-presence", (event) => {
+      const { peer, presence: state } = event.payload;
+      setPresence((prev) => ({ ...prev, [peer.id]: state }));
+      setOfflinePeers((prev) => {
+        const { [peer.id]: _previous, ...rest } = prev;
+        return state === "offline" ? { ...rest, [peer.id]: peer } : rest;
+      });
     });
 
     const unlistenReset = listen("network-reset", () => {
@@ -744,12 +1218,17 @@ export default function App() {
       invoke<string>("get_network_pin").then(pin => setNetworkPin(pin));
     });
 
-    const unlistenPairingFailed = listen<string>("pairing-failed", (event) => {
+    const unlistenPairingFailed = listen<PairingFailed>("pairing-failed", (event) => {
       // Show error in the join modal
-      setJoinError(event.payload);
+      setJoinError(event.payload.reason);
+      setJoinPinWrong(event.payload.error === "wrong_pin");
       setJoinBusy(false);
     });
 
+    // A pairing from before a restart was answered; the PIN isn't kept, so ask for it again
+    const unlistenPairingResume = listen<{ peer_id: string, network_name: string }>("pairing-resume-needed", (event) => {
+      startJoinFlow(event.payload.network_name, event.payload.peer_id);
+    });
 
 
 
@@ -766,19 +1245,40 @@ export default function App() {
       setIsAutoSend(event.payload.auto_send);
     });
 
+    const unlistenAdmission = listen<{ request_id: string, device_id: string, addr: string, via: string }>("admission-request", async (event) => {
+      const r = event.payload;
+      const approve = confirm(`${r.device_id} (${r.addr}) entered the correct PIN on ${r.via}.\n\nAllow it to join the cluster?`);
+      try {
+        await invoke("respond_to_admission", { requestId: r.request_id, approve });
+      } catch (e) {
+        alert("Failed to respond to join request: " + String(e));
+      }
+    });
+
     return () => {
       unlistenPeer.then((f) => f());
       unlistenClipboard.then((f) => f());
       unlistenMonitor.then((f) => f());
 
       unlistenPending.then((f) => f());
+      unlistenPendingRemoved.then((f) => f());
+      unlistenOutgoing.then((f) => f());
+      unlistenOutgoingCleared.then((f) => f());
+      unlistenTypeText.then((f) => f());
+      unlistenTypeTextCleared.then((f) => f());
       unlistenRemove.then((f) => f());
+      unlistenPresence.then((f) => f());
       unlistenReset.then((f) => f());
       unlistenUpdate.then((f) => f());
       unlistenDelete.then((f) => f());
+      unlistenAnnotate.then((f) => f());
+      unlistenLinkPreview.then((f) => f());
+      unlistenImported.then((f) => f());
       unlistenPairingFailed.then((f) => f());
+      unlistenPairingResume.then((f) => f());
       unlistenNotification.then((f) => f());
       unlistenSettingsChanged.then((f) => f());
+      unlistenAdmission.then((f) => f());
     };
   }, [myHostname]); // Re-bind if hostname loads (needed for sender check)
 
@@ -789,6 +1289,7 @@ export default function App() {
     setPairingPeerId(targetPeerId);
     setJoinPin("");
     setJoinError("");
+    setJoinPinWrong(false);
     setJoinBusy(false);
     setJoinOpen(true);
   };
@@ -797,6 +1298,7 @@ export default function App() {
     if (!joinPin || !pairingPeerId) return;
     setJoinBusy(true);
     setJoinError("");
+    setJoinPinWrong(false);
 
     try {
       await invoke("start_pairing", { peerId: pairingPeerId, pin: joinPin });
@@ -838,7 +1340,14 @@ export default function App() {
   const confirmLeaveNetwork = async () => {
     setLeaveOpen(false);
     try {
-      await invoke("leave_network");
+      if (leaveWipe) {
+        const report = await invoke<{ shredded: number, failed: string[] }>("secure_wipe");
+        if (report.failed.length > 0) {
+          alert(`Some files could not be erased:\n${report.failed.join("\n")}`);
+        }
+      } else {
+        await invoke("leave_network");
+      }
     } catch (e) {
       alert("Failed to leave network: " + e);
     }
@@ -871,6 +1380,7 @@ export default function App() {
 
   /* Derived State */
   const myPeers = peers.filter(p => p.is_trusted);
+  const clusterPeers = [...myPeers, ...Object.values(offlinePeers).filter(o => !myPeers.some(p => p.id === o.id))];
   const untrustedPeers = peers.filter(p => !p.is_trusted);
   const isConnected = true; // Always "connected" to local discovery at least. Or use myPeers.length > 0 if that implies connection.
 
@@ -1133,7 +1643,8 @@ export default function App() {
                 myNetworkName={myNetworkName}
                 myHostname={myHostname}
                 networkPin={networkPin}
-                peers={myPeers}
+                peers={clusterPeers}
+                presence={presence}
                 nearby={nearbyNetworks}
                 expandedNetworks={expandedNetworks}
                 toggleNetwork={toggleNetwork}
@@ -1160,11 +1671,64 @@ export default function App() {
           onClick={() => setManualSyncOpen(true)}
         />
 
+        <OutgoingReview
+          pending={pendingOutgoing}
+          onSend={() => invoke("confirm_outgoing_clipboard").catch(e => alert("Failed to send: " + String(e)))}
+          onDiscard={() => invoke("discard_outgoing_clipboard").catch(e => logToBackend("Discard failed:", e))}
+        />
+
+        <StagedFiles
+          staged={staged}
+          peers={peers}
+          onSend={(peerId) => {
+            if (!staged) return;
+            invoke<string>("send_staged", { batchId: staged.batch_id, peers: peerId ? [peerId] : null })
+              .then(() => {
+                stagedRef.current = null;
+                setStaged(null);
+              })
+              .catch(e => alert("Failed to send: " + String(e)));
+          }}
+          onDiscard={() => {
+            if (!staged) return;
+            invoke("discard_staged", { batchId: staged.batch_id }).catch(e => logToBackend("Discard failed:", e));
+            stagedRef.current = null;
+            setStaged(null);
+          }}
+        />
+
+        <TypeTextPrompt
+          pending={pendingTypeText}
+          onType={(id) => invoke("confirm_type_text", { id }).catch(e => alert("Failed to type: " + String(e)))}
+          onReject={(id) => invoke("reject_type_text", { id }).catch(e => logToBackend("Reject failed:", e))}
+        />
+
+        <ErrorToasts
+          errors={appErrors}
+          onDismiss={(key) => setAppErrors((prev) => prev.filter((e) => e.key !== key))}
+          onRetry={(key) => {
+            const error = appErrors.find((e) => e.key === key);
+            setAppErrors((prev) => prev.filter((e) => e.key !== key));
+            if (error?.resend) {
+              invoke("resend_history_item", { id: error.resend.id, peerId: error.resend.peerId }).catch(e => logToBackend("Resend failed:", e));
+            } else if (error?.download) {
+              invoke("request_file", { fileId: error.download.id, fileIndex: error.download.fileIndex, peerId: error.download.peerId }).catch(e => logToBackend("Retry failed:", e));
+            } else if (error?.deferred) {
+              const { id, fileCount, peerId } = error.deferred;
+              for (let i = 0; i < fileCount; i++) {
+                invoke("request_file", { fileId: id, fileIndex: i, peerId }).catch(e => logToBackend("Download failed:", e));
+              }
+            } else {
+              invoke("retry_connection").catch(e => logToBackend("Retry failed:", e));
+            }
+          }}
+        />
+
         <ManualSyncModal
           open={manualSyncOpen}
           onClose={() => setManualSyncOpen(false)}
           localContent={localClipboard}
-          remoteContent={pendingReceive}
+          remoteItems={pendingReceives}
           onSend={async () => {
             try {
               await invoke("send_clipboard", { text: localClipboard });
@@ -1175,15 +1739,15 @@ export default function App() {
               alert("Failed to send: " + e);
             }
           }}
-          onReceive={async () => {
+          onReceive={async (id) => {
             try {
-              await invoke("confirm_pending_clipboard");
-              setPendingReceive(null);
-              setManualSyncOpen(false);
+              await invoke("confirm_pending_clipboard", { id });
+              if (pendingReceives.length <= 1) setManualSyncOpen(false);
             } catch (e) {
               alert("Failed to confirm: " + e);
             }
           }}
+          onDiscard={(id) => invoke("discard_pending_clipboard", { id }).catch(e => logToBackend("Discard failed:", e))}
         />
 
         {/* Modals */}
@@ -1209,7 +1773,7 @@ export default function App() {
               <input
                 className={clsx(
                   "mt-2 h-12 w-full rounded-2xl border bg-white px-4 font-mono text-lg tracking-[0.25em] text-zinc-900 outline-none focus:ring-2 dark:bg-zinc-950 dark:text-zinc-50",
-                  joinError
+                  joinPinWrong
                     ? "border-rose-500 focus:ring-rose-500/40 dark:border-rose-500/50"
                     : "border-zinc-200 focus:ring-emerald-500/40 dark:border-white/10"
                 )}
@@ -1218,6 +1782,7 @@ export default function App() {
                 onChange={(e) => {
                   setJoinPin(e.target.value.trim());
                   setJoinError("");
+                  setJoinPinWrong(false);
                 }}
                 onKeyDown={(e) => e.key === "Enter" && submitJoin()}
                 autoFocus
@@ -1255,6 +1820,18 @@ export default function App() {
             <div className="rounded-2xl border border-rose-500/20 bg-rose-500/10 p-4 text-sm text-rose-800 dark:text-rose-200">
               Action is irreversible. You will need a PIN to rejoin.
             </div>
+            <label className="flex items-start gap-2 text-sm text-zinc-700 dark:text-zinc-300">
+              <input
+                type="checkbox"
+                className="mt-0.5"
+                checked={leaveWipe}
+                onChange={(e) => setLeaveWipe(e.target.checked)}
+              />
+              <span>
+                Also securely erase clipboard history, downloaded and cached files, crash reports and logs.
+                <span className="block text-xs text-zinc-500 dark:text-zinc-400">Best effort: files are overwritten before deletion, which SSDs may not fully honour.</span>
+              </span>
+            </label>
           </div>
         </Modal>
 
@@ -1300,6 +1877,36 @@ export default function App() {
           <div className="text-xl font-medium text-zinc-900 dark:text-zinc-50">Connecting to remote cluster...</div>
         </div>
       )}
+      {/* Update Available Modal */}
+      <Modal
+        open={availableUpdate !== null}
+        title={`ClusterCut ${availableUpdate?.version ?? ""} is available`}
+        subtitle={availableUpdate ? `You have ${availableUpdate.current_version}${availableUpdate.prerelease ? " · pre-release" : ""}` : undefined}
+        onClose={() => setAvailableUpdate(null)}
+        footer={
+          <>
+            <Button variant="ghost" onClick={() => setAvailableUpdate(null)}>
+              Later
+            </Button>
+            <Button
+              onClick={() => {
+                if (availableUpdate?.url) openUrl(availableUpdate.url).catch(e => logToBackend("Open release failed:", e));
+                setAvailableUpdate(null);
+              }}
+            >
+              View Release
+            </Button>
+          </>
+        }
+      >
+        <div className="space-y-2">
+          <div className="text-sm font-semibold text-zinc-900 dark:text-zinc-50">{availableUpdate?.name}</div>
+          <pre className="max-h-64 overflow-y-auto whitespace-pre-wrap rounded-xl bg-zinc-50 p-3 text-xs text-zinc-700 dark:bg-white/5 dark:text-zinc-300">
+            {availableUpdate?.notes || "No release notes."}
+          </pre>
+        </div>
+      </Modal>
+
       {/* Port Warning Modal */}
       <Modal
         open={showPortWarning}
@@ -1341,6 +1948,7 @@ function DevicesView({
   myHostname,
   networkPin,
   peers,
+  presence,
   nearby,
   expandedNetworks,
   toggleNetwork,
@@ -1353,6 +1961,7 @@ function DevicesView({
   myHostname: string;
   networkPin: string;
   peers: Peer[];
+  presence: Record<string, Presence>;
   nearby: NearbyNetwork[];
   expandedNetworks: Set<string>;
   toggleNetwork: (name: string) => void;
@@ -1360,6 +1969,93 @@ function DevicesView({
   onDeletePeer: (id: string) => void;
   onAddManual: () => void;
 }) {
+  const [localPlatform, setLocalPlatform] = useState<PlatformInfo | null>(null);
+  useEffect(() => {
+    invoke<PlatformInfo>("get_platform_info").then(setLocalPlatform).catch(() => {});
+  }, []);
+
+  const testConnection = async (p: Peer) => {
+    try {
+      const report = await invoke<{ ok: boolean, steps: { name: string, ok: boolean, duration_ms: number | null, detail: string }[] }>("test_peer_connection", { peerId: p.id });
+      const lines = report.steps.map(s => `${s.ok ? "✓" : "✗"} ${s.name}${s.duration_ms !== null ? ` (${s.duration_ms} ms)` : ""}: ${s.detail}`);
+      alert(`${report.ok ? "Connection OK" : "Connection problems"} – ${p.hostname || p.id}\n\n${lines.join("\n")}`);
+    } catch (e) {
+      alert("Connection test failed: " + String(e));
+    }
+  };
+
+  const showDetails = async (p: Peer) => {
+    try {
+      const d = await invoke<PeerDetails>("get_peer_details", { peerId: p.id });
+      const s = d.stats;
+      const when = (ts: number | null) => (ts ? new Date(ts * 1000).toLocaleString() : "never");
+      const lines = [
+        `Sent: ${s.items_sent} item(s), ${s.files_sent} file(s), ${formatBytes(s.bytes_sent)} (last ${when(s.last_sent_at)})`,
+        `Received: ${s.items_received} item(s), ${s.files_received} file(s), ${formatBytes(s.bytes_received)} (last ${when(s.last_received_at)})`,
+        d.last_item ? `Last item: ${d.last_item.preview} (${timeAgo(d.last_item.timestamp)})` : "Last item: none in history",
+        "",
+        `Trust: ${d.trust.is_trusted ? "trusted" : "untrusted"}${d.trust.is_owner ? ", cluster owner" : ""}${d.trust.guest_expires_at ? ", guest" : ""}${d.trust.blocked ? ", blocked" : ""}`,
+        `Signature: ${d.trust.signature_valid ? "valid" : "missing or invalid"}`,
+        `Verification code: ${d.trust.fingerprint ?? "n/a"}`,
+        `This device's code: ${d.trust.local_fingerprint ?? "n/a"}`,
+        "Both devices show the same two codes when they share the cluster key."
+      ];
+      alert(`${p.hostname || p.id}\n\n${lines.join("\n")}`);
+    } catch (e) {
+      alert("Failed to load device details: " + String(e));
+    }
+  };
+
+  // Send files straight from a picker, without copying them first (all devices when no peer)
+  const sendFiles = async (p?: Peer) => {
+    const picked = await open({ title: p ? `Send files to ${p.hostname || p.id}` : "Send files", multiple: true });
+    if (!picked) return;
+    const paths = Array.isArray(picked) ? picked : [picked];
+    if (paths.length === 0) return;
+    try {
+      await invoke<string>("send_files", { paths, peerId: p?.id ?? null });
+    } catch (e) {
+      alert("Failed to send files: " + String(e));
+    }
+  };
+
+  // Send a test item through the whole sync path; receivers report instead of applying it
+  const dryRun = async () => {
+    try {
+      const report = await invoke<{ peers: { hostname: string, addr: string, ok: boolean, round_trip_ms: number | null, error: string | null, receipt: { steps: string[], would_apply: boolean } | null }[] }>("dry_run_sync", { text: null });
+      if (report.peers.length === 0) {
+        alert("No devices to test.");
+        return;
+      }
+      const sections = report.peers.map(r => {
+        const head = `${r.ok ? "✓" : "✗"} ${r.hostname} (${r.addr})${r.round_trip_ms !== null ? ` – ${r.round_trip_ms} ms` : ""}`;
+        const body = r.receipt ? r.receipt.steps.map(s => `    ${s}`).join("\n") : `    ${r.error ?? "Failed"}`;
+        return `${head}\n${body}`;
+      });
+      alert(`Dry run – nothing was applied\n\n${sections.join("\n\n")}`);
+    } catch (e) {
+      alert("Dry run failed: " + String(e));
+    }
+  };
+
+  const inviteGuest = async () => {
+    try {
+      await invoke("invite_guest", { durationSecs: 2 * 60 * 60 });
+      alert("Guest invite ready. The next device to join with your PIN in the next 5 minutes gets 2 hours of text-only access.");
+    } catch (e) {
+      alert("Failed to invite guest: " + String(e));
+    }
+  };
+
+  const oneTimePin = async () => {
+    try {
+      const otp = await invoke<{ pin: string; expires_at: number }>("generate_one_time_pin", { ttlSecs: 5 * 60 });
+      await navigator.clipboard.writeText(otp.pin);
+      alert(`One-time PIN: ${otp.pin}\n\nValid for a single pairing in the next 5 minutes. Copied to clipboard.`);
+    } catch (e) {
+      alert("Failed to create one-time PIN: " + String(e));
+    }
+  };
 
   return (
     <div className="flex h-full flex-col gap-3">
@@ -1391,9 +2087,17 @@ function DevicesView({
             value={networkPin}
             mono
             action={
-              <IconButton label="Copy PIN" onClick={() => navigator.clipboard.writeText(networkPin)} variant="default">
-                <Copy className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
-              </IconButton>
+              <div className="flex items-center gap-1">
+                <IconButton label="Copy PIN" onClick={() => navigator.clipboard.writeText(networkPin)} variant="default">
+                  <Copy className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
+                </IconButton>
+                <IconButton label="One-Time PIN (5 minutes)" onClick={oneTimePin} variant="default">
+                  <KeyRound className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
+                </IconButton>
+                <IconButton label="Invite Guest (2 hours)" onClick={inviteGuest} variant="default">
+                  <Clock className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
+                </IconButton>
+              </div>
             }
           />
         </div>
@@ -1410,9 +2114,21 @@ function DevicesView({
               title="My Cluster"
               subtitle="Trusted devices."
               right={
-                <Badge tone="good">
-                  <CheckCircle2 className="h-3.5 w-3.5" /> Safe
-                </Badge>
+                <div className="flex items-center gap-2">
+                  {peers.length > 0 && (
+                    <Button size="sm" variant="ghost" iconLeft={<Upload className="h-4 w-4" />} onClick={() => sendFiles()}>
+                      Send Files…
+                    </Button>
+                  )}
+                  {peers.length > 0 && (
+                    <Button size="sm" variant="ghost" onClick={dryRun}>
+                      Dry Run
+                    </Button>
+                  )}
+                  <Badge tone="good">
+                    <CheckCircle2 className="h-3.5 w-3.5" /> Safe
+                  </Badge>
+                </div>
               }
             />
           </div>
@@ -1424,14 +2140,19 @@ function DevicesView({
               </div>
             ) : (
               <div className="mt-2 space-y-2">
-                {peers.map((p) => (
+                {peers.map((p) => {
+                  const state = presence[p.id] ?? "online";
+                  return (
                   <div
                     key={p.id}
-                    className="relative flex items-center justify-between gap-3 rounded-2xl border border-zinc-900/10 bg-white/60 p-3 pr-4 dark:border-white/10 dark:bg-white/5"
+                    className={clsx(
+                      "relative flex items-center justify-between gap-3 rounded-2xl border border-zinc-900/10 bg-white/60 p-3 pr-4 dark:border-white/10 dark:bg-white/5",
+                      state === "offline" && "opacity-50 grayscale"
+                    )}
                   >
-                    {/* Online Badge - Absolute Top Right with some padding */}
+                    {/* Presence Badge - Absolute Top Right with some padding */}
                     <div className="absolute right-2 top-2">
-                      <Badge tone="good">online</Badge>
+                      <Badge tone={state === "online" ? "good" : state === "away" ? "warn" : "neutral"}>{state}</Badge>
                     </div>
 
                     <div className="flex items-center gap-3">
@@ -1440,17 +2161,44 @@ function DevicesView({
                       </div>
                       <div className="min-w-0">
                         <div className="text-sm font-semibold text-zinc-900 dark:text-zinc-50">{p.hostname || p.id}</div>
-                        <div className="text-xs text-zinc-600 dark:text-zinc-400">{p.ip}</div>
+                        <div className="text-xs text-zinc-600 dark:text-zinc-400">
+                          {p.ip}
+                          {p.guest_expires_at && ` · guest until ${new Date(p.guest_expires_at * 1000).toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" })}`}
+                        </div>
+                        {p.platform && (
+                          <div className="text-[10px] text-zinc-500" title={p.platform.arch}>
+                            {platformLabel(p.platform)}
+                            {localPlatform && p.platform.protocol !== localPlatform.protocol && (
+                              <span className="text-amber-600 dark:text-amber-400">
+                                {p.platform.protocol > localPlatform.protocol ? " · newer protocol, update this device" : " · older protocol, needs update"}
+                              </span>
+                            )}
+                          </div>
+                        )}
                       </div>
                     </div>
 
                     <div className="mt-4 flex items-center">
+                      <IconButton label="Details" onClick={() => showDetails(p)}>
+                        <Info className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
+                      </IconButton>
+                      {state !== "offline" && (
+                        <IconButton label="Send Files…" onClick={() => sendFiles(p)}>
+                          <Upload className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
+                        </IconButton>
+                      )}
+                      {state !== "offline" && (
+                        <IconButton label="Test Connection" onClick={() => testConnection(p)}>
+                          <Activity className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />
+                        </IconButton>
+                      )}
                       <IconButton label="Kick / Ban" onClick={() => onDeletePeer(p.id)}>
                         <Trash2 className="h-5 w-5 text-rose-600" />
                       </IconButton>
                     </div>
                   </div>
-                ))}
+                  );
+                })}
               </div>
             )}
           </div>
@@ -1533,6 +2281,72 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
   const [myHostname, setMyHostname] = useState<string>("");
   const [progress, setProgress] = useState<Record<string, { transferred: number, total: number }>>({});
   const [downloadedFiles, setDownloadedFiles] = useState<Record<string, string[]>>({});
+  // Items whose files this device still serves; sent files outside it can't be downloaded anymore
+  const [activeOffers, setActiveOffers] = useState<Set<string> | null>(null);
+
+  useEffect(() => {
+    const refresh = () => invoke<string[]>("list_file_offers").then((ids) => setActiveOffers(new Set(ids))).catch(() => {});
+    refresh();
+    const unlisten = listen<{ ids: string[] }>("file-offers-expired", refresh);
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [items]);
+  // Kind filter: ids of matching items from search_history (null = show everything)
+  const [kindFilter, setKindFilter] = useState<ContentKind | null>(null);
+  const [matchingIds, setMatchingIds] = useState<Set<string> | null>(null);
+
+  useEffect(() => {
+    if (!kindFilter) {
+      setMatchingIds(null);
+      return;
+    }
+    invoke<{ id: string }[]>("search_history", { kinds: [kindFilter] })
+      .then((found) => setMatchingIds(new Set(found.map((f) => f.id))))
+      .catch((e) => console.error("History search failed:", e));
+  }, [kindFilter, items]);
+
+  const visibleItems = matchingIds ? items.filter((it) => matchingIds.has(it.id)) : items;
+
+  const [exportRange, setExportRange] = useState(0);
+  const [exportFiles, setExportFiles] = useState(true);
+  const [exportSensitive, setExportSensitive] = useState(false);
+
+  const exportHistory = async () => {
+    const path = await save({
+      title: "Export clipboard history",
+      defaultPath: "clustercut-history.json",
+      filters: [{ name: "JSON", extensions: ["json"] }, { name: "CSV", extensions: ["csv"] }]
+    });
+    if (!path) return;
+    const secs = EXPORT_RANGES[exportRange].secs;
+    try {
+      const count = await invoke<number>("export_history", {
+        path,
+        format: path.toLowerCase().endsWith(".csv") ? "csv" : "json",
+        dateRange: secs ? { from: Math.floor(Date.now() / 1000) - secs } : null,
+        options: { include_files: exportFiles, include_sensitive: exportSensitive }
+      });
+      alert(`Exported ${count} item(s).`);
+    } catch (e) {
+      alert("Export failed: " + e);
+    }
+  };
+
+  const importHistory = async () => {
+    const path = await open({
+      title: "Import clipboard history",
+      multiple: false,
+      filters: [{ name: "History archive", extensions: ["json", "csv"] }]
+    });
+    if (!path) return;
+    try {
+      const report = await invoke<{ imported: number; skipped: number }>("import_history", { path });
+      alert(`Imported ${report.imported} item(s)` + (report.skipped ? `, skipped ${report.skipped}.` : "."));
+    } catch (e) {
+      alert("Import failed: " + e);
+    }
+  };
 
   useEffect(() => {
     invoke<string>("get_hostname").then(setMyHostname);
@@ -1564,9 +2378,27 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
       });
     });
 
+    // Large files stay in the cache until we know where they should go
+    const unlistenSaveRequested = listen<{ id: string, file_index: number, file_name: string, file_size: number }>("file-save-requested", async (e) => {
+      const dest = await save({ title: `Save ${e.payload.file_name} (${formatBytes(e.payload.file_size)})`, defaultPath: e.payload.file_name });
+      if (!dest) return;
+      invoke("save_received_file", { id: e.payload.id, index: e.payload.file_index, destPath: dest })
+        .catch(err => alert("Failed to save file: " + String(err)));
+    });
+
+    const unlistenSaved = listen<{ id: string, from: string, path: string }>("file-saved", (e) => {
+      setDownloadedFiles(prev => {
+        const existing = prev[e.payload.id] || [];
+        const moved = existing.map(p => p === e.payload.from ? e.payload.path : p);
+        return { ...prev, [e.payload.id]: moved.includes(e.payload.path) ? moved : [...moved, e.payload.path] };
+      });
+    });
+
     return () => {
       unlistenProgress.then(u => u());
       unlistenReceived.then(u => u());
+      unlistenSaveRequested.then(u => u());
+      unlistenSaved.then(u => u());
     };
   }, []);
 
@@ -1580,6 +2412,15 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
     }
   };
 
+  // Files we sent earlier are offered again if they're still on disk
+  const handleResend = async (id: string) => {
+    try {
+      await invoke("resend_history_item", { id });
+    } catch (e) {
+      alert("Failed to send: " + e);
+    }
+  };
+
   const handleLocalCopy = async (text: string) => {
     try {
       await invoke("set_local_clipboard", { text });
@@ -1606,9 +2447,28 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
     }
   };
 
-  const handleDownloadAll = async (fileId: string, files: { name: string }[], peerId: string) => {
+  const handleAnnotate = async (id: string, current?: string | null) => {
+    const note = window.prompt("Note for this item (leave empty to clear):", current || "");
+    if (note === null) return;
     try {
-      for (let i = 0; i < files.length; i++) {
+      await invoke("annotate_history_item", { id, note });
+    } catch (e) {
+      console.error("Failed to annotate:", e);
+    }
+  };
+
+  // Fetches the page itself, so only ever on request (it could be a one-time link)
+  const handleLoadPreview = async (id: string) => {
+    try {
+      await invoke("fetch_link_preview", { id });
+    } catch (e) {
+      alert("Couldn't load a preview: " + e);
+    }
+  };
+
+  const handleDownloadAll = async (fileId: string, files: { name: string }[], peerId: string) => {
+    try {
+      for (let i = 0; i < files.length; i++) {
         setProgress(p => ({ ...p, [fileId]: { transferred: 0, total: 100 } }));
         await invoke("request_file", { fileId, fileIndex: i, peerId });
       }
@@ -1625,11 +2485,59 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
           icon={<Copy className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />}
           title="Clipboard history"
           subtitle="Recent entries."
+          right={
+            <>
+              <Button variant="ghost" size="sm" iconLeft={<Upload className="h-4 w-4" />} onClick={importHistory}>
+                Import
+              </Button>
+              <Button variant="ghost" size="sm" iconLeft={<Download className="h-4 w-4" />} onClick={exportHistory}>
+                Export
+              </Button>
+            </>
+          }
         />
 
+        <div className="mt-3 flex flex-wrap items-center gap-4 text-xs text-zinc-600 dark:text-zinc-400">
+          <select
+            value={exportRange}
+            onChange={(e) => setExportRange(Number(e.target.value))}
+            className="rounded-lg border border-zinc-200 bg-white px-2 py-1 dark:border-white/10 dark:bg-zinc-900"
+          >
+            {EXPORT_RANGES.map((r, i) => (
+              <option key={r.label} value={i}>{r.label}</option>
+            ))}
+          </select>
+          <label className="flex items-center gap-1.5">
+            <input type="checkbox" checked={exportFiles} onChange={(e) => setExportFiles(e.target.checked)} />
+            Export file entries
+          </label>
+          <label className="flex items-center gap-1.5">
+            <input type="checkbox" checked={exportSensitive} onChange={(e) => setExportSensitive(e.target.checked)} />
+            Export sensitive items
+          </label>
+        </div>
+
+        <div className="mt-4 flex flex-wrap gap-2">
+          {(Object.keys(CONTENT_KIND_LABELS) as ContentKind[]).map((kind) => (
+            <button
+              key={kind}
+              onClick={() => setKindFilter(kindFilter === kind ? null : kind)}
+              className={clsx(
+                "rounded-full border px-3 py-1 text-xs font-medium transition-colors",
+                kindFilter === kind
+                  ? "border-emerald-500 bg-emerald-50 text-emerald-700 dark:bg-emerald-900/30 dark:text-emerald-300"
+                  : "border-zinc-200 text-zinc-600 hover:bg-zinc-50 dark:border-white/10 dark:text-zinc-300 dark:hover:bg-white/5"
+              )}
+            >
+              {CONTENT_KIND_LABELS[kind]}
+            </button>
+          ))}
+        </div>
+
         <div className="mt-4 space-y-2">
-          {items.map((it) => {
+          {visibleItems.map((it) => {
             const isMe = it.device === myHostname || it.device === "localhost" || it.origin === "local";
+            const offerExpired = isMe && !!it.files?.length && activeOffers !== null && !activeOffers.has(it.id);
             // Logic check: "origin" in item type is mostly placeholder now if we trust device name.
             // If device name matches myHostname, it is "Sent" (Arrow Up).
             // Else "Received" (Arrow Down).
@@ -1654,19 +2562,43 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
                         )}
                       </Badge>
                       <span className="text-xs text-zinc-500 dark:text-zinc-400">{timeAgo(it.ts)}</span>
+                      {it.channel && it.channel !== "default" && <Badge>#{it.channel}</Badge>}
+                      {it.content?.kinds.map((kind) => (
+                        <Badge key={kind}>{kind === "code" && it.content?.language ? it.content.language : kind}</Badge>
+                      ))}
+                      {it.source && (it.source.app || it.source.window_title) && (
+                        <span className="truncate text-xs text-zinc-500 dark:text-zinc-400">
+                          copied from {[it.source.app, it.source.window_title].filter(Boolean).join(" — ")}
+                        </span>
+                      )}
+                      {it.note && (
+                        <Badge>
+                          <StickyNote className="h-3.5 w-3.5" /> {it.note}
+                        </Badge>
+                      )}
                     </div>
-                    {it.text && <div className="mt-2 line-clamp-3 whitespace-pre-wrap text-sm text-zinc-900 dark:text-zinc-50">{it.text}</div>}
+                    {it.link && (it.link.title || it.link.favicon) && (
+                      <div className="mt-2 flex items-center gap-2 text-sm font-medium text-zinc-900 dark:text-zinc-50">
+                        {it.link.favicon && <img src={it.link.favicon} alt="" className="h-4 w-4 shrink-0 rounded-sm" />}
+                        {it.link.title && <span className="truncate">{it.link.title}</span>}
+                      </div>
+                    )}
+                    {it.text && <div className={clsx("mt-2 line-clamp-3 whitespace-pre-wrap", it.link?.title ? "text-xs text-zinc-500 dark:text-zinc-400" : "text-sm text-zinc-900 dark:text-zinc-50")}>{it.text}</div>}
 
                     {it.files && it.files.length > 0 && (
-                      <div className="mt-2 space-y-1">
+                      <div className={clsx("mt-2 space-y-1", offerExpired && "opacity-50")} title={offerExpired ? "No longer offered to other devices" : undefined}>
                         {it.files.map((f, idx) => (
                           <div key={idx} className="flex flex-col gap-2 rounded-lg bg-zinc-50 p-2 text-sm dark:bg-zinc-800">
                             <div className="flex items-center justify-between">
                               <div className="flex items-center gap-2 overflow-hidden">
                                 <span className="truncate font-medium text-zinc-700 dark:text-zinc-300">{f.name}</span>
                                 <span className="shrink-0 text-xs text-zinc-500">({formatBytes(f.size)})</span>
+                                {offerExpired && idx === 0 && <span className="shrink-0 text-[10px] text-zinc-500">· offer expired</span>}
                               </div>
                             </div>
+                            {f.text_preview && (
+                              <div className="line-clamp-3 whitespace-pre-wrap text-xs text-zinc-500 dark:text-zinc-400">{f.text_preview}…</div>
+                            )}
                             {progress[it.id] && (
                               <div className="w-full">
                                 <div className="flex justify-between text-[10px] text-zinc-500 mb-1">
@@ -1708,7 +2640,17 @@ function HistoryView({ items }: { items: HistoryItem[] }) {
                       </>
                     )}
 
-                    <IconButton label="Send to Cluster" onClick={() => handleSend(it.text)}>
+                    {!it.link?.title && /^https?:\/\/\S+$/.test(it.text.trim()) && (
+                      <IconButton label="Load Link Preview" onClick={() => handleLoadPreview(it.id)}>
+                        <Link2 className="h-4 w-4 text-zinc-600 dark:text-zinc-300" />
+                      </IconButton>
+                    )}
+
+                    <IconButton label="Add Note" onClick={() => handleAnnotate(it.id, it.note)}>
+                      <StickyNote className="h-4 w-4 text-zinc-600 dark:text-zinc-300" />
+                    </IconButton>
+
+                    <IconButton label="Send to Cluster" onClick={() => (it.files && it.files.length > 0 ? handleResend(it.id) : handleSend(it.text))}>
                       <Send className="h-4 w-4 text-emerald-600 dark:text-emerald-400" />
                     </IconButton>
 
@@ -1757,9 +2699,13 @@ function SettingsView({
 
   const toggleAutostart = async () => {
     try {
-      const handled = await invoke<boolean>("configure_autostart", { enable: !autostart });
-      if (handled) {
-        setAutostart(!autostart);
+      // Flatpak: what the Background portal granted (null elsewhere)
+      const granted = await invoke<boolean | null>("configure_autostart", { enable: !autostart });
+      if (granted !== null) {
+        setAutostart(granted);
+        if (!autostart && !granted) {
+          alert("Starting at login was not allowed. You can allow it for ClusterCut in your system's app settings.");
+        }
         return;
       }
 
@@ -1919,6 +2865,21 @@ function SettingsView({
               <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", autostart ? "translate-x-6" : "translate-x-1")} />
             </button>
           </div>
+          <div className="mt-4 flex items-center justify-between">
+            <div>
+              <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Start Minimized</div>
+              <div className="text-xs text-zinc-500">Stay in the tray when launched, including at login. Open the window from the tray.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                start_minimized: !settings.start_minimized
+              })}
+              className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.start_minimized ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.start_minimized ? "translate-x-6" : "translate-x-1")} />
+            </button>
+          </div>
         </div>
       </Card>
 
@@ -2004,6 +2965,22 @@ function SettingsView({
               Cluster identity is randomly generated. To reset, use "Leave & Reset" in the header.
             </div>
           )}
+
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Require Owner Approval</div>
+              <div className="text-[10px] text-zinc-500">Devices pairing with this one also need the cluster owner to approve them.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                require_owner_approval: !settings.require_owner_approval
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.require_owner_approval ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.require_owner_approval ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
         </div>
       </Card>
 
@@ -2029,7 +3006,22 @@ function SettingsView({
             </button>
           </div>
 
-          {!settings.auto_send && (
+          {settings.auto_send && (
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Review Before Send</div>
+                <div className="text-xs text-zinc-500">Hold each copy until you confirm it (send shortcut, tray or the prompt).</div>
+              </div>
+              <button
+                onClick={() => setSettings({ ...settings, review_before_send: !settings.review_before_send })}
+                className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.review_before_send ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+              >
+                <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.review_before_send ? "translate-x-6" : "translate-x-1")} />
+              </button>
+            </div>
+          )}
+
+          {(!settings.auto_send || settings.review_before_send) && (
             <div className="rounded-xl border border-zinc-200 bg-zinc-50 p-3 dark:border-white/10 dark:bg-white/5">
               <div className="flex flex-col gap-2">
                 <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Global Shortcut (Send)</div>
@@ -2075,6 +3067,157 @@ function SettingsView({
               </div>
             </div>
           )}
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Latency Tracing</div>
+              <div className="text-xs text-zinc-500">Attach timing data to sent clipboard items for diagnosing slow sync.</div>
+            </div>
+            <button
+              onClick={() => setSettings({ ...settings, latency_tracing: !settings.latency_tracing })}
+              className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.latency_tracing ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.latency_tracing ? "translate-x-6" : "translate-x-1")} />
+            </button>
+          </div>
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Power Saving</div>
+              <div className="text-xs text-zinc-500">Slow down networking when idle, sooner on battery. Resumes on any activity.</div>
+            </div>
+            <button
+              onClick={() => setSettings({ ...settings, power_saving: !settings.power_saving })}
+              className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.power_saving ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.power_saving ? "translate-x-6" : "translate-x-1")} />
+            </button>
+          </div>
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Adaptive Clipboard Polling</div>
+              <div className="text-xs text-zinc-500">Check the clipboard rapidly for a few seconds after activity, and at the interval below otherwise.</div>
+            </div>
+            <button
+              onClick={() => setSettings({ ...settings, adaptive_polling: !settings.adaptive_polling })}
+              className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.adaptive_polling ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.adaptive_polling ? "translate-x-6" : "translate-x-1")} />
+            </button>
+          </div>
+
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Clipboard Check Interval</div>
+              <div className="text-xs text-zinc-500">How often the clipboard is checked for copies. Shorter syncs sooner but uses more CPU.</div>
+            </div>
+            <select
+              className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+              value={settings.monitor_interval_ms}
+              onChange={(e) => setSettings({ ...settings, monitor_interval_ms: Number(e.target.value) })}
+            >
+              <option value={250}>0.25 seconds</option>
+              <option value={500}>0.5 seconds</option>
+              <option value={1000}>1 second</option>
+              <option value={2000}>2 seconds</option>
+              <option value={5000}>5 seconds</option>
+            </select>
+          </div>
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="grid gap-2">
+            <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Clipboard Channels</div>
+            <div className="text-xs text-zinc-500">Copies go to the send channel. Only subscribed channels are applied here (comma separated).</div>
+            <div className="grid gap-2 md:grid-cols-2">
+              <input
+                className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                placeholder="Send channel"
+                value={settings.send_channel}
+                onChange={(e) => setSettings({ ...settings, send_channel: e.target.value.trim().toLowerCase() || "default" })}
+              />
+              <input
+                className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                placeholder="default, code, urls"
+                defaultValue={settings.subscribed_channels.join(", ")}
+                onBlur={(e) => setSettings({ ...settings, subscribed_channels: e.target.value.split(",").map((c) => c.trim().toLowerCase()).filter(Boolean) })}
+              />
+            </div>
+          </div>
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="grid gap-2">
+            <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Allowed Networks</div>
+            <div className="text-xs text-zinc-500">Only use these interfaces and subnets (comma separated), e.g. to keep traffic off a VPN. Leave empty for all.</div>
+            <div className="grid gap-2 md:grid-cols-2">
+              <input
+                className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                placeholder="Interfaces, e.g. eth0, wlan0"
+                defaultValue={settings.allowed_interfaces.join(", ")}
+                onBlur={(e) => setSettings({ ...settings, allowed_interfaces: e.target.value.split(",").map((c) => c.trim()).filter(Boolean) })}
+              />
+              <input
+                className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                placeholder="Subnets, e.g. 192.168.1.0/24"
+                defaultValue={settings.allowed_subnets.join(", ")}
+                onBlur={(e) => setSettings({ ...settings, allowed_subnets: e.target.value.split(",").map((c) => c.trim()).filter(Boolean) })}
+              />
+            </div>
+          </div>
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="grid gap-2">
+            <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Discovery Namespace</div>
+            <div className="text-xs text-zinc-500">Only discover devices using the same namespace, so separate groups on one network don't see each other. Leave empty for the default.</div>
+            <input
+              className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+              placeholder="e.g. design-team"
+              defaultValue={settings.discovery_namespace}
+              onBlur={(e) => setSettings({ ...settings, discovery_namespace: e.target.value.trim().toLowerCase().replace(/[^a-z0-9-]/g, "") })}
+            />
+          </div>
+
+          <div className="h-px bg-zinc-900/5 dark:bg-white/5" />
+
+          <div className="grid gap-2">
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">SOCKS5 Proxy</div>
+                <div className="text-xs text-zinc-500">For link previews and other requests off the local network. Devices still connect directly.</div>
+              </div>
+              <button
+                onClick={() => setSettings({ ...settings, proxy: { ...settings.proxy, enabled: !settings.proxy.enabled } })}
+                className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.proxy.enabled ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+              >
+                <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.proxy.enabled ? "translate-x-6" : "translate-x-1")} />
+              </button>
+            </div>
+            {settings.proxy.enabled && (
+              <div className="grid gap-2 md:grid-cols-2">
+                <input
+                  className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                  placeholder="socks5h://proxy.example:1080"
+                  value={settings.proxy.url}
+                  onChange={(e) => setSettings({ ...settings, proxy: { ...settings.proxy, url: e.target.value } })}
+                />
+                <input
+                  className="h-10 rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                  placeholder="Bypass, e.g. localhost, .corp.example, 10.0.0.0/8"
+                  defaultValue={settings.proxy.bypass.join(", ")}
+                  onBlur={(e) => setSettings({ ...settings, proxy: { ...settings.proxy, bypass: e.target.value.split(",").map((c) => c.trim()).filter(Boolean) } })}
+                />
+              </div>
+            )}
+          </div>
         </div>
       </Card>
 
@@ -2126,6 +3269,84 @@ function SettingsView({
               </div>
             </div>
           )}
+
+          {settings.enable_file_transfer && (
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Ask Where to Save</div>
+                <div className="text-xs text-zinc-500">Larger files aren't copied to the clipboard; you choose where they go.</div>
+              </div>
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.save_prompt_size}
+                onChange={(e) => setSettings({ ...settings, save_prompt_size: Number(e.target.value) })}
+              >
+                <option value={0}>Never</option>
+                {[50, 100, 200, 500, 1024].map((mb) => (
+                  <option key={mb} value={mb * 1024 * 1024}>Above {formatBytes(mb * 1024 * 1024, 0)}</option>
+                ))}
+              </select>
+            </div>
+          )}
+
+          {settings.enable_file_transfer && (
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Hold Downloads When Constrained</div>
+                <div className="text-xs text-zinc-500">On a metered connection or in battery saver, larger files wait until you download them.</div>
+              </div>
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.defer_downloads_when_constrained ? settings.constrained_download_limit : -1}
+                onChange={(e) => {
+                  const limit = Number(e.target.value);
+                  setSettings(limit < 0
+                    ? { ...settings, defer_downloads_when_constrained: false }
+                    : { ...settings, defer_downloads_when_constrained: true, constrained_download_limit: limit });
+                }}
+              >
+                <option value={-1}>Never</option>
+                <option value={0}>All files</option>
+                {[1, 10, 50, 100].map((mb) => (
+                  <option key={mb} value={mb * 1024 * 1024}>Above {formatBytes(mb * 1024 * 1024, 0)}</option>
+                ))}
+              </select>
+            </div>
+          )}
+
+          {settings.enable_file_transfer && (
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Offer Sent Files For</div>
+                <div className="text-xs text-zinc-500">After this, other devices can no longer download files you sent.</div>
+              </div>
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.file_offer_ttl_hours}
+                onChange={(e) => setSettings({ ...settings, file_offer_ttl_hours: Number(e.target.value) })}
+              >
+                {[1, 8, 24, 72, 168].map((h) => (
+                  <option key={h} value={h}>{h < 24 ? `${h} hour${h > 1 ? "s" : ""}` : `${h / 24} day${h > 24 ? "s" : ""}`}</option>
+                ))}
+                <option value={0}>Until quit</option>
+              </select>
+            </div>
+          )}
+
+          {settings.enable_file_transfer && (
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm font-medium text-zinc-900 dark:text-zinc-50">Accept Images</div>
+                <div className="text-xs text-zinc-500">Let other devices offer image files to this one.</div>
+              </div>
+              <button
+                onClick={() => setSettings({ ...settings, accept_images: !settings.accept_images })}
+                className={clsx("relative h-6 w-11 rounded-full transition-colors", settings.accept_images ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+              >
+                <span className={clsx("block h-4 w-4 transform rounded-full bg-white shadow-sm transition-transform", settings.accept_images ? "translate-x-6" : "translate-x-1")} />
+              </button>
+            </div>
+          )}
         </div>
       </Card>
 
@@ -2170,6 +3391,354 @@ function SettingsView({
               <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.notify_large_files ? "translate-x-5" : "translate-x-1")} />
             </button>
           </div>
+
+          {/* Tray Badge */}
+          <div className="flex items-center justify-between">
+            <div className="text-sm text-zinc-700 dark:text-zinc-300">Tray Badge</div>
+            <div className="flex items-center gap-2">
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.tray_badge.style}
+                onChange={(e) => setSettings({ ...settings, tray_badge: { ...settings.tray_badge, style: e.target.value as TrayBadgeSettings["style"] } })}
+              >
+                <option value="square">Square</option>
+                <option value="dot">Dot</option>
+                <option value="ring">Ring</option>
+                <option value="count">Count</option>
+              </select>
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.tray_badge.position}
+                onChange={(e) => setSettings({ ...settings, tray_badge: { ...settings.tray_badge, position: e.target.value as TrayBadgeSettings["position"] } })}
+              >
+                <option value="top_right">Top right</option>
+                <option value="top_left">Top left</option>
+                <option value="bottom_right">Bottom right</option>
+                <option value="bottom_left">Bottom left</option>
+              </select>
+            </div>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Badge Color</div>
+              <div className="text-[10px] text-zinc-500">Monochrome uses the tray icon's own color.</div>
+            </div>
+            <div className="flex items-center gap-2">
+              <button
+                onClick={() => setSettings({ ...settings, tray_badge: { ...settings.tray_badge, color: settings.tray_badge.color === "mono" ? "#ff0000" : "mono" } })}
+                className="text-xs font-medium text-zinc-500 hover:underline"
+              >
+                {settings.tray_badge.color === "mono" ? "Use color" : "Monochrome"}
+              </button>
+              {settings.tray_badge.color !== "mono" && (
+                <input
+                  type="color"
+                  className="h-6 w-8 cursor-pointer rounded border border-zinc-900/10 bg-transparent dark:border-white/10"
+                  value={settings.tray_badge.color}
+                  onChange={(e) => setSettings({ ...settings, tray_badge: { ...settings.tray_badge, color: e.target.value } })}
+                />
+              )}
+            </div>
+          </div>
+          <div className="flex items-center justify-between">
+            <div className="text-sm text-zinc-700 dark:text-zinc-300">High-Contrast Badge Outline</div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                tray_badge: { ...settings.tray_badge, high_contrast: !settings.tray_badge.high_contrast }
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.tray_badge.high_contrast ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.tray_badge.high_contrast ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
+        </div>
+      </Card>
+
+      {/* Privacy */}
+      <Card className="p-4">
+        <SectionHeader
+          icon={<ShieldCheck className="h-5 w-5 text-zinc-600 dark:text-zinc-300" />}
+          title="Privacy"
+          subtitle="Control what other devices see."
+        />
+        <div className="mt-4 px-1 space-y-3">
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Share Source App</div>
+              <div className="text-[10px] text-zinc-500">Include the app and window title you copied from.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                share_source_app: !settings.share_source_app
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.share_source_app ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.share_source_app ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Only Send From Allowed Apps</div>
+              <div className="text-[10px] text-zinc-500">Copies from any other app (or where the app can't be detected) stay on this device.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                app_allowlist_mode: !settings.app_allowlist_mode
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.app_allowlist_mode ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.app_allowlist_mode ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
+          {settings.app_allowlist_mode && (
+            <input
+              className="h-9 w-full rounded-xl border border-zinc-900/10 bg-white px-3 text-sm text-zinc-900 outline-none focus:ring-2 focus:ring-emerald-500/40 dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+              placeholder="App names or exact window titles, e.g. firefox, code"
+              defaultValue={settings.allowed_apps.join(", ")}
+              onBlur={(e) => setSettings({ ...settings, allowed_apps: e.target.value.split(",").map((c) => c.trim()).filter(Boolean) })}
+            />
+          )}
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Crash Reports</div>
+              <div className="text-[10px] text-zinc-500">Save a report on this device if ClusterCut crashes. Reports are never uploaded.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                crash_reports: !settings.crash_reports
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.crash_reports ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.crash_reports ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
+          {/Mac/.test(navigator.userAgent) && (
+            <div className="flex items-center justify-between">
+              <div>
+                <div className="text-sm text-zinc-700 dark:text-zinc-300">Services Menu</div>
+                <div className="text-[10px] text-zinc-500">Add "Send to ClusterCut" to the Services menu for selected text in any app.</div>
+              </div>
+              <button
+                onClick={() => setSettings({
+                  ...settings,
+                  macos_service: !settings.macos_service
+                })}
+                className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.macos_service ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+              >
+                <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.macos_service ? "translate-x-5" : "translate-x-1")} />
+              </button>
+            </div>
+          )}
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Check for Updates</div>
+              <div className="text-[10px] text-zinc-500">Look for new releases once a day. Nothing is installed automatically.</div>
+            </div>
+            <div className="flex items-center gap-2">
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.update_channel}
+                onChange={(e) => setSettings({ ...settings, update_channel: e.target.value as AppSettings["update_channel"] })}
+              >
+                <option value="stable">Stable</option>
+                <option value="beta">Beta</option>
+              </select>
+              <Button
+                size="sm"
+                variant="ghost"
+                onClick={() =>
+                  invoke<UpdateInfo | null>("check_for_updates")
+                    .then((update) => {
+                      if (!update) {
+                        alert("ClusterCut is up to date.");
+                      } else if (confirm(`ClusterCut ${update.version} is available (you have ${update.current_version}). Open the release page?`)) {
+                        openUrl(update.url).catch(e => logToBackend("Open release failed:", e));
+                      }
+                    })
+                    .catch(e => alert("Update check failed: " + String(e)))
+                }
+              >
+                Check Now
+              </Button>
+              <button
+                onClick={() => setSettings({
+                  ...settings,
+                  update_check: !settings.update_check
+                })}
+                className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.update_check ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+              >
+                <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.update_check ? "translate-x-5" : "translate-x-1")} />
+              </button>
+            </div>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Clipboard Content in Logs</div>
+              <div className="text-[10px] text-zinc-500">How copied text, file names and links appear in log files.</div>
+            </div>
+            <select
+              className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+              value={settings.log_redaction}
+              onChange={(e) => setSettings({ ...settings, log_redaction: e.target.value as AppSettings["log_redaction"] })}
+            >
+              <option value="full">Hidden</option>
+              <option value="hashed">Hashed</option>
+              <option value="off">Shown</option>
+            </select>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Log Files</div>
+              <div className="text-[10px] text-zinc-500">One file per day; older ones are deleted.</div>
+            </div>
+            <div className="flex items-center gap-2">
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.log_retention_days}
+                onChange={(e) => setSettings({ ...settings, log_retention_days: Number(e.target.value) })}
+              >
+                {[1, 3, 7, 14, 30].map((days) => (
+                  <option key={days} value={days}>Keep {days} {days === 1 ? "day" : "days"}</option>
+                ))}
+              </select>
+              <Button size="sm" variant="ghost" onClick={() => invoke("open_log_folder").catch(e => alert("Failed to open log folder: " + String(e)))}>
+                Open
+              </Button>
+              <Button size="sm" variant="ghost" onClick={() => invoke("clear_logs").catch(e => alert("Failed to clear logs: " + String(e)))}>
+                Clear
+              </Button>
+            </div>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Received Files</div>
+              <div className="text-[10px] text-zinc-500">When downloaded files are cleaned up, checked at start and exit.</div>
+            </div>
+            <div className="flex items-center gap-2">
+              <select
+                className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                value={settings.received_files_retention}
+                onChange={(e) => setSettings({ ...settings, received_files_retention: e.target.value as AppSettings["received_files_retention"] })}
+              >
+                <option value="session">Every restart</option>
+                <option value="days">After some days</option>
+                <option value="size">Above a size</option>
+                <option value="never">Never</option>
+              </select>
+              {settings.received_files_retention === "days" && (
+                <select
+                  className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                  value={settings.received_files_retention_days}
+                  onChange={(e) => setSettings({ ...settings, received_files_retention_days: Number(e.target.value) })}
+                >
+                  {[1, 3, 7, 14, 30].map((days) => (
+                    <option key={days} value={days}>{days} {days === 1 ? "day" : "days"}</option>
+                  ))}
+                </select>
+              )}
+              {settings.received_files_retention === "size" && (
+                <select
+                  className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+                  value={settings.received_files_retention_gb}
+                  onChange={(e) => setSettings({ ...settings, received_files_retention_gb: Number(e.target.value) })}
+                >
+                  {[1, 2, 5, 10, 20, 50].map((gb) => (
+                    <option key={gb} value={gb}>{gb} GB</option>
+                  ))}
+                </select>
+              )}
+              <Button
+                size="sm"
+                variant="ghost"
+                onClick={() =>
+                  invoke<{ removed: number; freed_bytes: number }>("clear_received_files")
+                    .then((r) => alert(`Removed ${r.removed} received ${r.removed === 1 ? "file" : "files"} (${formatBytes(r.freed_bytes)}).`))
+                    .catch(e => alert("Failed to clear received files: " + String(e)))
+                }
+              >
+                Clear
+              </Button>
+            </div>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Encryption</div>
+              <div className="text-[10px] text-zinc-500">Cipher other devices use for what they send here. Older versions always use ChaCha20.</div>
+            </div>
+            <select
+              className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+              value={settings.cipher_suite}
+              onChange={(e) => setSettings({ ...settings, cipher_suite: e.target.value as AppSettings["cipher_suite"] })}
+            >
+              <option value="auto">Automatic</option>
+              <option value="chacha20_poly1305">ChaCha20-Poly1305</option>
+              <option value="aes_256_gcm">AES-256-GCM</option>
+            </select>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Sender Verification</div>
+              <div className="text-[10px] text-zinc-500">Which devices' clipboard items and file requests are accepted. Strict drops items from older versions.</div>
+            </div>
+            <select
+              className="h-8 rounded-lg border border-zinc-900/10 bg-white px-2 text-xs text-zinc-900 outline-none dark:border-white/10 dark:bg-white/5 dark:text-zinc-50"
+              value={settings.sender_authentication}
+              onChange={(e) => setSettings({ ...settings, sender_authentication: e.target.value as AppSettings["sender_authentication"] })}
+            >
+              <option value="off">Anyone With the Key</option>
+              <option value="trusted">Trusted Devices</option>
+              <option value="strict">Trusted and Signed</option>
+            </select>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Link Previews</div>
+              <div className="text-[10px] text-zinc-500">Look up the icon of copied links from this device after sending, and share it. Titles load on request.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                unfurl_links: !settings.unfurl_links
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.unfurl_links ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.unfurl_links ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Pause When Locked</div>
+              <div className="text-[10px] text-zinc-500">Hold incoming content as pending and stop sending while the screen is locked.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                pause_sync_when_locked: !settings.pause_sync_when_locked
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.pause_sync_when_locked ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.pause_sync_when_locked ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <div className="text-sm text-zinc-700 dark:text-zinc-300">Pair With Network PIN</div>
+              <div className="text-[10px] text-zinc-500">When off, new devices can only join using a one-time PIN.</div>
+            </div>
+            <button
+              onClick={() => setSettings({
+                ...settings,
+                allow_network_pin_pairing: !settings.allow_network_pin_pairing
+              })}
+              className={clsx("relative h-5 w-9 rounded-full transition-colors", settings.allow_network_pin_pairing ? "bg-emerald-500" : "bg-zinc-200 dark:bg-zinc-700")}
+            >
+              <span className={clsx("block h-3 w-3 transform rounded-full bg-white shadow-sm transition-transform", settings.allow_network_pin_pairing ? "translate-x-5" : "translate-x-1")} />
+            </button>
+          </div>
         </div>
       </Card>
 
@@ -2249,6 +3818,146 @@ function CopyMini({ text }: { text: string }) {
 
 /* --- Manual Sync Components --- */
 
+function ErrorToasts({
+  errors,
+  onDismiss,
+  onRetry
+}: {
+  errors: (AppErrorEvent & { key: number })[],
+  onDismiss: (key: number) => void,
+  onRetry: (key: number) => void
+}) {
+  if (errors.length === 0) return null;
+
+  return (
+    <div className="fixed bottom-6 left-6 z-50 flex w-80 flex-col gap-2">
+      {errors.map((e) => (
+        <div
+          key={e.key}
+          className="flex items-start gap-3 rounded-2xl border border-rose-200 bg-white p-3 shadow-lg dark:border-rose-900/40 dark:bg-zinc-900"
+        >
+          <AlertTriangle className="mt-0.5 h-4 w-4 shrink-0 text-rose-500" />
+          <div className="min-w-0 flex-1">
+            <div className="text-xs font-semibold uppercase tracking-wide text-rose-600 dark:text-rose-400">{e.category}</div>
+            <div className="mt-0.5 break-words text-sm text-zinc-700 dark:text-zinc-200">{e.message}</div>
+            <div className="mt-2 flex gap-3 text-xs font-medium">
+              {e.retryable && (
+                <button onClick={() => onRetry(e.key)} className="text-emerald-600 hover:underline dark:text-emerald-400">
+                  {e.resend ? "Resend" : e.deferred ? "Download anyway" : "Retry"}
+                </button>
+              )}
+              <button onClick={() => onDismiss(e.key)} className="text-zinc-500 hover:underline">
+                Dismiss
+              </button>
+            </div>
+          </div>
+        </div>
+      ))}
+    </div>
+  );
+}
+
+function OutgoingReview({
+  pending,
+  onSend,
+  onDiscard
+}: {
+  pending: { id: string, text: string, files?: { name: string }[] | null } | null,
+  onSend: () => void,
+  onDiscard: () => void
+}) {
+  if (!pending) return null;
+  const preview = pending.files && pending.files.length > 0
+    ? pending.files.map(f => f.name).join(", ")
+    : pending.text;
+
+  return (
+    <div className="fixed bottom-6 left-1/2 z-50 flex w-96 -translate-x-1/2 items-center gap-3 rounded-2xl border border-emerald-200 bg-white p-3 shadow-lg dark:border-emerald-900/40 dark:bg-zinc-900">
+      <Send className="h-4 w-4 shrink-0 text-emerald-500" />
+      <div className="min-w-0 flex-1">
+        <div className="text-xs font-semibold uppercase tracking-wide text-emerald-600 dark:text-emerald-400">Waiting to send</div>
+        <div className="truncate text-sm text-zinc-700 dark:text-zinc-200">{preview}</div>
+      </div>
+      <Button size="sm" variant="primary" onClick={onSend}>Send</Button>
+      <button onClick={onDiscard} className="text-xs font-medium text-zinc-500 hover:underline">
+        Discard
+      </button>
+    </div>
+  );
+}
+
+function StagedFiles({
+  staged,
+  peers,
+  onSend,
+  onDiscard
+}: {
+  staged: StagingProgress | null,
+  peers: Peer[],
+  onSend: (peerId: string | null) => void,
+  onDiscard: () => void
+}) {
+  const [target, setTarget] = useState("");
+  if (!staged) return null;
+  const percent = staged.bytes_total > 0 ? Math.round((staged.bytes_done / staged.bytes_total) * 100) : 0;
+
+  return (
+    <div className="fixed bottom-6 left-1/2 z-50 flex w-[28rem] -translate-x-1/2 items-center gap-3 rounded-2xl border border-emerald-200 bg-white p-3 shadow-lg dark:border-emerald-900/40 dark:bg-zinc-900">
+      {staged.ready ? <Upload className="h-4 w-4 shrink-0 text-emerald-500" /> : <Loader2 className="h-4 w-4 shrink-0 animate-spin text-emerald-500" />}
+      <div className="min-w-0 flex-1">
+        <div className="text-xs font-semibold uppercase tracking-wide text-emerald-600 dark:text-emerald-400">
+          {staged.ready ? `${staged.files_total} file(s) ready` : `Preparing ${staged.files_done}/${staged.files_total}`}
+        </div>
+        <div className="truncate text-sm text-zinc-700 dark:text-zinc-200">
+          {staged.ready ? formatBytes(staged.bytes_total) : `${staged.file_name ?? "…"} · ${percent}%`}
+        </div>
+      </div>
+      {staged.ready && (
+        <select
+          value={target}
+          onChange={(e) => setTarget(e.target.value)}
+          className="rounded-lg border border-zinc-200 bg-white px-2 py-1 text-xs dark:border-zinc-700 dark:bg-zinc-900"
+        >
+          <option value="">All devices</option>
+          {peers.map((p) => (
+            <option key={p.id} value={p.id}>{p.hostname || p.id}</option>
+          ))}
+        </select>
+      )}
+      <Button size="sm" variant="primary" disabled={!staged.ready} onClick={() => onSend(target || null)}>Send</Button>
+      <button onClick={onDiscard} className="text-xs font-medium text-zinc-500 hover:underline">
+        Discard
+      </button>
+    </div>
+  );
+}
+
+function TypeTextPrompt({
+  pending,
+  onType,
+  onReject
+}: {
+  pending: PendingTypeText | null,
+  onType: (id: string) => void,
+  onReject: (id: string) => void
+}) {
+  if (!pending) return null;
+
+  return (
+    <div className="fixed top-6 left-1/2 z-50 w-96 -translate-x-1/2 rounded-2xl border border-amber-200 bg-white p-4 shadow-lg dark:border-amber-900/40 dark:bg-zinc-900">
+      <div className="text-xs font-semibold uppercase tracking-wide text-amber-600 dark:text-amber-400">{pending.sender} wants to type</div>
+      <div className="mt-2 max-h-32 overflow-y-auto whitespace-pre-wrap break-words rounded-lg bg-zinc-50 p-2 font-mono text-xs text-zinc-700 dark:bg-zinc-800 dark:text-zinc-200">{pending.text}</div>
+      <div className="mt-1 text-xs text-zinc-500">This window hides, then the text is typed into the app that had focus.</div>
+      <div className="mt-3 flex items-center justify-end gap-3">
+        <button onClick={() => onReject(pending.id)} className="text-xs font-medium text-zinc-500 hover:underline">
+          Reject
+        </button>
+        <Button size="sm" variant="primary" onClick={() => onType(pending.id)}>Type it</Button>
+      </div>
+    </div>
+  );
+}
+
 function ManualSyncFAB({
   hasPendingSend,
   hasPendingReceive,
@@ -2281,18 +3990,23 @@ function ManualSyncModal({
   open,
   onClose,
   localContent,
-  remoteContent, // ClipboardPayload or string
+  remoteItems,
   onSend,
-  onReceive
+  onReceive,
+  onDiscard
 }: {
   open: boolean;
   onClose: () => void;
   localContent: string;
-  remoteContent: { text: string, sender: string, timestamp: number } | null;
+  remoteItems: PendingReceive[];
   onSend: () => void;
-  onReceive: () => void;
+  onReceive: (id: string) => void;
+  onDiscard: (id: string) => void;
 }) {
   if (!open) return null;
+  // Most recent first; the first one is what the main button applies
+  const items = [...remoteItems].reverse();
+  const latest = items[0];
 
   return (
     <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50 p-4 backdrop-blur-sm">
@@ -2326,12 +4040,12 @@ function ManualSyncModal({
               <span className="font-medium">Receive Remote</span>
             </div>
             <div className="flex-1 rounded-xl bg-white/5 p-4 text-sm font-mono text-zinc-300 h-32 overflow-y-auto whitespace-pre-wrap border border-white/5 relative">
-              {remoteContent ? (
+              {latest ? (
                 <>
-                  {remoteContent.text}
+                  {latest.text}
                   <div className="absolute bottom-2 right-2 flex gap-2">
                     <span className="text-[10px] bg-white/10 px-2 py-0.5 rounded text-zinc-400">
-                      From: {remoteContent.sender}
+                      From: {latest.sender}
                     </span>
                   </div>
                 </>
@@ -2339,9 +4053,22 @@ function ManualSyncModal({
                 <span className="text-zinc-600 italic">No pending data</span>
               )}
             </div>
-            <Button variant="primary" onClick={onReceive} disabled={!remoteContent} iconLeft={<Copy className="h-4 w-4" />}>
+            <Button variant="primary" onClick={() => latest && onReceive(latest.id)} disabled={!latest} iconLeft={<Copy className="h-4 w-4" />}>
               Apply to Clipboard
             </Button>
+            {items.length > 1 && (
+              <div className="space-y-1">
+                <div className="text-[10px] uppercase tracking-wide text-zinc-500">Also waiting</div>
+                {items.slice(1).map((item) => (
+                  <div key={item.id} className="flex items-center gap-2 rounded-lg bg-white/5 px-3 py-1.5 text-xs">
+                    <span className="shrink-0 text-zinc-500">{item.sender}</span>
+                    <span className="min-w-0 flex-1 truncate font-mono text-zinc-300">{item.text}</span>
+                    <button onClick={() => onReceive(item.id)} className="font-medium text-blue-400 hover:underline">Apply</button>
+                    <button onClick={() => onDiscard(item.id)} className="font-medium text-zinc-500 hover:underline">Discard</button>
+                  </div>
+                ))}
+              </div>
+            )}
           </div>
         </div>
       </div>